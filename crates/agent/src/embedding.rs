@@ -0,0 +1,4 @@
+//! Timeout/retry for embedding HTTP calls now lives on the live providers in
+//! `src/embeddings.rs` (`VoyageAiEmbeddingProvider`, `OpenAiCompatibleEmbeddingProvider`), which
+//! carry their own `post_with_retry`/`EmbeddingError`. This crate isn't wired into the binary
+//! (see `state.rs`'s module doc), so it doesn't need its own copy of that logic.
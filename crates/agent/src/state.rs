@@ -1,40 +1,30 @@
+//! Not wired into the `huly-ai-agent` binary: `src/main.rs` declares its own unrelated
+//! `mod agent;` (`src/agent.rs`), and nothing under `src/` references `crates::agent`. The live
+//! memory/embedding/task pipeline this crate duplicates lives in `src/state.rs`, `src/embeddings.rs`,
+//! and `src/storage`; check there before porting anything new here.
+
 use std::path::PathBuf;
 
 use anyhow::{Context, Result};
-use secrecy::{ExposeSecret, SecretString};
-use serde::Deserialize;
 use sqlx::{Row, SqlitePool, migrate::Migrator, sqlite::SqliteConnectOptions};
 use zerocopy::IntoBytes;
 
 use crate::{
     config::Config,
+    embedding::EmbeddingProvider,
     task::{Task, TaskKind},
     tools::memory::Entity,
     types::{AssistantContent, Message, Text, ToolCall, ToolResult, UserContent},
 };
 
 static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
-const VOYAGEAI_URL: &str = "https://api.voyageai.com/v1/embeddings";
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct AgentState {
     pool: SqlitePool,
     has_new_tasks: bool,
     balance: u32,
-    voyageai_api_key: Option<SecretString>,
-    voyageai_model: Option<String>,
-    voyageai_dimensions: Option<u16>,
-    voyageai_http_client: Option<reqwest::Client>,
-}
-
-#[derive(Debug, Deserialize)]
-struct VoyageAIEmbeddingResponse {
-    pub data: Vec<VoyageAIEmbedding>,
-}
-
-#[derive(Debug, Deserialize)]
-struct VoyageAIEmbedding {
-    pub embedding: Vec<f32>,
+    embedding_provider: std::sync::Arc<dyn EmbeddingProvider>,
 }
 
 fn trace_message(message: &Message) {
@@ -86,46 +76,36 @@ impl AgentState {
         // let res = res.columns();
         // println!("{:?}", res);
         MIGRATOR.run(&pool).await?;
+
+        // Crash recovery for tasks stuck mid-execution is handled by the live pipeline's
+        // heartbeat-based sweep (`TaskState::Running`, see `src/storage/{sqlite,postgres}.rs`),
+        // not here.
+
         let balance = sqlx::query!("SELECT balance FROM agent_state")
             .fetch_one(&pool)
             .await?;
+
         Ok(Self {
             pool,
             balance: balance.balance.try_into().unwrap_or_default(),
             has_new_tasks: true,
-            voyageai_api_key: config.voyageai_api_key.clone(),
-            voyageai_model: config.voyageai_model.clone(),
-            voyageai_http_client: None,
+            embedding_provider: std::sync::Arc::from(config.embedding_provider.build()?),
         })
     }
 
     async fn create_embedding(&mut self, text: &str) -> Result<Vec<f32>> {
-        let client = self
-            .voyageai_http_client
-            .get_or_insert_with(|| reqwest::Client::new());
-        let res = client
-            .post(VOYAGEAI_URL)
-            .header("Content-Type", "application/json")
-            .header(
-                "Authorization",
-                format!(
-                    "Bearer {}",
-                    self.voyageai_api_key.as_ref().unwrap().expose_secret()
-                ),
-            )
-            .json(&serde_json::json!({
-                "model": self.voyageai_model.as_ref().unwrap(),
-                "input": text,
-            }))
-            .send()
-            .await?;
-        let mut res = res.json::<VoyageAIEmbeddingResponse>().await?;
-
-        let Some(embedding) = res.data.drain(..).next() else {
+        let mut embeddings = self.create_embeddings(&[text.to_string()]).await?;
+        if embeddings.is_empty() {
             anyhow::bail!("No embedding generated");
-        };
+        }
+        Ok(embeddings.remove(0))
+    }
 
-        Ok(embedding.embedding)
+    async fn create_embeddings(&mut self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.embedding_provider
+            .embed(texts)
+            .await
+            .with_context(|| "Failed to create embeddings")
     }
 
     pub async fn tasks(&self) -> Result<Vec<Task>> {
@@ -314,10 +294,12 @@ impl AgentState {
                 .await
                 .with_context(|| "Failed to create embedding")?;
             let embedding = embedding.as_bytes();
+            let observations = serde_json::to_string(&entity.observations)?;
             let row_id = sqlx::query!(
-                "INSERT INTO mem_entity (name, type, embedding) VALUES (?, ?, ?)",
+                "INSERT INTO mem_entity (name, type, observations, embedding) VALUES (?, ?, ?, ?)",
                 entity.name,
                 entity.entity_type,
+                observations,
                 embedding
             )
             .execute(&self.pool)
@@ -332,5 +314,11 @@ impl AgentState {
         }
         Ok(entities_to_add)
     }
+
+    // Semantic recall over entity embeddings now lives on the live pipeline:
+    // `storage::MemoryStore::mem_relevant_entities_scored` (vector KNN over `vec_mem_entity1`,
+    // same "higher is better" scoring convention as `KnowledgeGraphStore::kg_search_semantic`),
+    // reachable via `database::DbClient::mem_relevant_entities_scored`. This crate isn't wired
+    // into the binary (see module doc), so it doesn't need its own copy of that query.
     // #endregion
 }
@@ -1,48 +1,63 @@
 // Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
 
-use std::{collections::HashMap, time::Duration};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use anyhow::Result;
 use serde_json::json;
 use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 use tracing::Level;
 use wildcard::Wildcard;
 
 use crate::{
     config::{self, Config, McpTransportConfig},
     context::AgentContext,
-    providers::create_provider_client,
+    error::AgentError,
+    providers::ProviderRouter,
     state::AgentState,
-    task::{Task, TaskFinishReason, TaskKind, TaskState},
+    task::{JobOutcome, Task, TaskFinishReason, TaskKind, TaskMetrics, TaskState},
     tools::{
-        ToolImpl, ToolSet, browser::BrowserToolSet, command::CommandsToolSet, files::FilesToolSet,
-        huly::create_huly_tool_set, notes::NotesToolSet, task::TaskToolSet, web::WebToolSet,
+        ToolImpl, ToolMap, ToolSet, browser::BrowserToolSet, command::CommandsToolSet,
+        files::FilesToolSet, huly::create_huly_tool_set, notes::NotesToolSet, task::TaskToolSet,
+        web::WebToolSet,
     },
 };
 
+use self::pool::TaskPool;
+
 const MAX_MEMORY_ENTITIES: u16 = 10;
 
 mod assistant_chat_task;
 mod channel_task;
+mod pool;
 mod sleep_task;
 mod utils;
 
 pub struct Agent {
     pub config: Config,
+    shutdown: CancellationToken,
 }
 
 impl Agent {
     pub fn new(config: Config) -> Result<Self> {
-        let this = Self { config };
+        let this = Self { config, shutdown: CancellationToken::new() };
         Ok(this)
     }
 
+    /// Requests a graceful shutdown: `run`'s main loop stops accepting new tasks, waits up to
+    /// `Config::shutdown_grace_period_secs` for whatever's already in flight in its `TaskPool` to
+    /// finish, then persists anything left (queued or still running) as `TaskState::Postponed`
+    /// before returning, rather than dropping it silently.
+    pub fn shutdown(&self) {
+        self.shutdown.cancel();
+    }
+
     async fn init_tools(
         config: &Config,
         context: &AgentContext,
         state: &AgentState,
-    ) -> Result<(HashMap<String, Box<dyn ToolImpl>>, String, String)> {
-        let mut tools: HashMap<String, Box<dyn ToolImpl>> = HashMap::default();
+    ) -> Result<(ToolMap, String, String)> {
+        let mut tools: ToolMap = HashMap::default();
         let mut system_prompts = String::new();
         let mut tool_context = String::new();
 
@@ -65,7 +80,7 @@ impl Agent {
                 tools.extend(
                     new_tools
                         .into_iter()
-                        .map(|t| (t.name().to_string(), t))
+                        .map(|t| (t.name().to_string(), tokio::sync::Mutex::new(t)))
                         .collect::<HashMap<_, _>>(),
                 );
                 system_prompts.push_str(&tool_set.get_system_prompt(&config));
@@ -87,63 +102,99 @@ impl Agent {
         // mcp tools
         #[cfg(feature = "mcp")]
         if let Some(mcp) = &config.mcp {
-            use crate::tools::mcp::McpTool;
-            use mcp_core::transport::ClientSseTransportBuilder;
-            use mcp_core::types::ProtocolVersion;
-            use serde_json::json;
+            use mcp_core::transport::{
+                ClientSseTransportBuilder, ClientStdioTransportBuilder,
+                ClientStreamableHttpTransportBuilder,
+            };
 
             for (name, config) in mcp {
-                use std::sync::Arc;
-
-                use mcp_core::client::ClientBuilder;
-
                 tracing::info!("Adding mcp tool {}", name);
-                let McpTransportConfig::Sse { url, version } = &config.transport;
-                let transport = ClientSseTransportBuilder::new(url.clone()).build();
-                let client = ClientBuilder::new(transport)
-                    .set_protocol_version(if version == ProtocolVersion::V2025_03_26.as_str() {
-                        ProtocolVersion::V2025_03_26
-                    } else {
-                        ProtocolVersion::V2024_11_05
-                    })
-                    .build();
-
-                client.open().await?;
-                client.initialize().await?;
-                let mcp_tools = client.list_tools(None, None).await?;
-                let client_ref = Arc::new(client);
-                mcp_tools.tools.into_iter().for_each(|tool| {
-                    tools.insert(
-                        tool.name.clone(),
-                        Box::new(McpTool::new(
-                            client_ref.clone(),
-                            json!({
-                                "function": {
-                                    "description": tool.description,
-                                    "name": tool.name,
-                                    "parameters": tool.input_schema
-                                },
-                                "type": "function"
-                            }),
-                        )),
-                    );
-                });
+                match &config.transport {
+                    McpTransportConfig::Sse { url, version } => {
+                        let transport = ClientSseTransportBuilder::new(url.clone()).build();
+                        Self::register_mcp_transport(transport, Some(version), &mut tools).await?;
+                    }
+                    McpTransportConfig::Stdio { command, args, env } => {
+                        let mut builder =
+                            ClientStdioTransportBuilder::new(command.clone(), args.clone());
+                        for (key, value) in env {
+                            builder = builder.with_env(key.clone(), value.clone());
+                        }
+                        let transport = builder.build();
+                        Self::register_mcp_transport(transport, None, &mut tools).await?;
+                    }
+                    McpTransportConfig::StreamableHttp { url, headers } => {
+                        let mut builder =
+                            ClientStreamableHttpTransportBuilder::new(url.clone());
+                        for (key, value) in headers {
+                            builder = builder.with_header(key.clone(), value.clone());
+                        }
+                        let transport = builder.build();
+                        Self::register_mcp_transport(transport, None, &mut tools).await?;
+                    }
+                }
             }
         }
         Ok((tools, system_prompts, tool_context))
     }
 
+    /// Shared by every `McpTransportConfig` variant in `init_tools`: builds the `mcp_core::Client`
+    /// over `transport`, picks the protocol version (`version`, if the transport carries one, else
+    /// the latest), opens and initializes the session, and wraps each tool the server reports as a
+    /// `McpTool` in `tools`. Generic over `T: Transport` since each transport variant builds a
+    /// differently-typed `Client<T>`, but they all get registered the same way from here on.
+    #[cfg(feature = "mcp")]
+    async fn register_mcp_transport<T: mcp_core::transport::Transport>(
+        transport: T,
+        version: Option<&str>,
+        tools: &mut ToolMap,
+    ) -> Result<()> {
+        use crate::tools::mcp::McpTool;
+        use mcp_core::{client::ClientBuilder, types::ProtocolVersion};
+
+        let client = ClientBuilder::new(transport)
+            .set_protocol_version(match version {
+                Some(version) if version == ProtocolVersion::V2025_03_26.as_str() => {
+                    ProtocolVersion::V2025_03_26
+                }
+                Some(_) => ProtocolVersion::V2024_11_05,
+                None => ProtocolVersion::V2025_03_26,
+            })
+            .build();
+
+        client.open().await?;
+        client.initialize().await?;
+        let mcp_tools = client.list_tools(None, None).await?;
+        let client_ref = Arc::new(client);
+        mcp_tools.tools.into_iter().for_each(|tool| {
+            let tool: Box<dyn ToolImpl> = Box::new(McpTool::new(
+                client_ref.clone(),
+                json!({
+                    "function": {
+                        "description": tool.description,
+                        "name": tool.name,
+                        "parameters": tool.input_schema
+                    },
+                    "type": "function"
+                }),
+            ));
+            tools.insert(tool.name().to_string(), tokio::sync::Mutex::new(tool));
+        });
+        Ok(())
+    }
+
     pub async fn run(
         &self,
         task_receiver: mpsc::UnboundedReceiver<Task>,
         memory_task_sender: mpsc::UnboundedSender<Task>,
+        job_outcome_sender: mpsc::UnboundedSender<JobOutcome>,
         mut context: AgentContext,
     ) -> Result<()> {
         tracing::info!("Start");
 
         let mut state = AgentState::new(context.db_client.clone()).await?;
 
-        let (mut tools, tools_system_prompt, tools_context) =
+        let (tools, tools_system_prompt, tools_context) =
             Self::init_tools(&self.config, &context, &state).await?;
         context.tools_context = Some(tools_context);
         context.tools_system_prompt = Some(tools_system_prompt);
@@ -160,8 +211,10 @@ impl Agent {
                         .flat_map(|tool_pattern| {
                             let tool_pattern = Wildcard::new(tool_pattern.as_bytes()).unwrap();
                             tools.iter().filter_map(move |(key, tool)| {
-                                if tool_pattern.is_match(key.as_bytes())
-                                    && !tool.desciption().is_null()
+                                // Uncontended at startup, before any task has had a chance to lock
+                                // a tool, so this never actually blocks.
+                                let tool = tool.try_lock().unwrap();
+                                if tool_pattern.is_match(key.as_bytes()) && !tool.desciption().is_null()
                                 {
                                     Some(tool.desciption().clone())
                                 } else {
@@ -174,106 +227,265 @@ impl Agent {
             })
             .collect();
 
-        let provider_client = create_provider_client(&self.config)?;
+        let provider_routers: HashMap<config::TaskKind, ProviderRouter> = tools_descriptions
+            .iter()
+            .map(|(kind, tools)| {
+                Ok((
+                    kind.clone(),
+                    ProviderRouter::new(&self.config, Some(kind), tools.clone())?,
+                ))
+            })
+            .collect::<Result<_>>()?;
+
+        // Shared across every task the pool spawns below: `Arc` rather than `Clone`, since
+        // `AgentContext` holds a non-`Clone` `tx_client` and `state` must stay one shared instance
+        // (tasks still serialize on it, see `pool` module docs) rather than be duplicated.
+        let config = Arc::new(self.config.clone());
+        let context = Arc::new(context);
+        let tools = Arc::new(tools);
+        let tools_descriptions = Arc::new(tools_descriptions);
+        let provider_routers = Arc::new(provider_routers);
+        let state = Arc::new(tokio::sync::Mutex::new(state));
+        let pool = TaskPool::new(config.max_concurrent_tasks);
 
         let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<Task>();
-        let incoming_tasks_processor = utils::incoming_tasks_processor(
+        let task_router = utils::TaskRouterWorker::new(
             task_receiver,
             memory_task_sender.clone(),
             context.db_client.clone(),
             tx,
-        );
+        )
+        .await;
+        let task_router_paused = context
+            .db_client
+            .paused_worker_ids()
+            .await
+            .unwrap_or_default()
+            .iter()
+            .any(|id| id == "task_router");
+        context
+            .worker_manager
+            .spawn("task_router", Box::new(task_router), task_router_paused)
+            .await;
 
         // main agent loop
         loop {
-            if let Some(mut task) = rx.recv().await {
+            let next_task = tokio::select! {
+                biased;
+                _ = self.shutdown.cancelled() => {
+                    tracing::info!("Shutdown requested, no longer accepting new tasks");
+                    break;
+                }
+                task = rx.recv() => task,
+            };
+            if let Some(mut task) = next_task {
                 let span = tracing::span!(
                     Level::DEBUG,
                     "agent_task",
                     task_id = task.id,
                     task_kind = %task.kind
                 );
-                span.in_scope(async || -> Result<()> {
-                    if let Some(channel_log_writer) = &context.channel_log_writer {
-                        channel_log_writer
-                            .trace_log(&format!("start task: {}, {}", task.id, task.kind));
-                    }
-                    tracing::info!("start task: {}, {}", task.id, task.kind);
-
-                    let finish_reason = match task.kind {
-                        TaskKind::Sleep => {
-                            sleep_task::process_sleep_task(
-                                &self.config,
-                                provider_client.as_ref(),
-                                &task,
-                                &mut state,
-                                &context,
-                            )
-                            .await
-                        }
-                        TaskKind::AssistantChat { .. } => {
-                            assistant_chat_task::process_assistant_chat_task(
-                                &self.config,
-                                provider_client.as_ref(),
-                                &mut tools,
-                                &mut task,
-                                &mut state,
-                                &context,
-                                &tools_descriptions[&config::TaskKind::AssistantChat],
-                            )
-                            .await
-                        }
-                        _ => {
-                            channel_task::process_channel_task(
-                                &self.config,
-                                provider_client.as_ref(),
-                                &mut tools,
-                                &mut task,
-                                &mut state,
-                                &context,
-                                &tools_descriptions[&config::TaskKind::FollowChat],
-                            )
-                            .await
+                context.task_manager.register(&task).await;
+                let job_id = task.job_id.clone();
+                let cancel_token = task.cancel_token.clone();
+
+                let config = config.clone();
+                let context = context.clone();
+                let tools = tools.clone();
+                let tools_descriptions = tools_descriptions.clone();
+                let provider_routers = provider_routers.clone();
+                let state = state.clone();
+                let memory_task_sender = memory_task_sender.clone();
+                let job_outcome_sender = job_outcome_sender.clone();
+
+                pool.spawn(task.id, task.kind.to_string(), cancel_token, move || {
+                    let task_id = task.id;
+                    span.in_scope(async move || -> Result<(), String> {
+                        if let Some(channel_log_writer) = &context.channel_log_writer {
+                            channel_log_writer
+                                .trace_log(&format!("start task: {}, {}", task.id, task.kind));
                         }
-                    };
-                    if let Some(channel_log_writer) = &context.channel_log_writer {
-                        channel_log_writer
-                            .trace_log(&format!("task finished: {}, {:?}", task.id, finish_reason));
-                    }
+                        tracing::info!("start task: {}, {}", task.id, task.kind);
 
-                    match finish_reason {
-                        Ok(finish_reason) => match finish_reason {
-                            TaskFinishReason::Completed => {
-                                tracing::info!("Task complete: {}", task.id);
-                                state.set_task_state(task.id, TaskState::Completed).await?;
-                                let _ = memory_task_sender.send(task);
+                        let outcome = match task.kind {
+                            TaskKind::Sleep => {
+                                let mut state = state.lock().await;
+                                sleep_task::process_sleep_task(
+                                    &config,
+                                    &provider_routers[&config::TaskKind::Sleep],
+                                    &task,
+                                    &mut state,
+                                    &context,
+                                )
+                                .await
                             }
-                            TaskFinishReason::Skipped => {
-                                tracing::info!("Task skipped: {}", task.id);
-                                state.set_task_state(task.id, TaskState::Postponed).await?;
-                                let _ = memory_task_sender.send(task);
+                            TaskKind::AssistantChat { .. } => {
+                                let mut state = state.lock().await;
+                                assistant_chat_task::process_assistant_chat_task(
+                                    &config,
+                                    &provider_routers[&config::TaskKind::AssistantChat],
+                                    &tools,
+                                    &mut task,
+                                    &mut state,
+                                    &context,
+                                    &tools_descriptions[&config::TaskKind::AssistantChat],
+                                )
+                                .await
                             }
-                            TaskFinishReason::Cancelled => {
-                                tracing::info!("Task cancelled: {}", task.id);
-                                state.set_task_state(task.id, TaskState::Cancelled).await?;
+                            _ => {
+                                let mut state = state.lock().await;
+                                channel_task::process_channel_task(
+                                    &config,
+                                    &provider_routers[&config::TaskKind::FollowChat],
+                                    &tools,
+                                    &mut task,
+                                    &mut state,
+                                    &context,
+                                    &tools_descriptions[&config::TaskKind::FollowChat],
+                                )
+                                .await
                             }
-                        },
-                        Err(e) => {
-                            tracing::error!(?e, "Error processing task");
-                            state.set_task_state(task.id, TaskState::Cancelled).await?;
+                        };
+                        if let Some(channel_log_writer) = &context.channel_log_writer {
+                            channel_log_writer.trace_log(&format!(
+                                "task finished: {}, {:?}",
+                                task.id, outcome
+                            ));
                         }
-                    }
-                    Ok(())
+
+                        match outcome {
+                            Ok((finish_reason, metrics)) => match finish_reason {
+                                TaskFinishReason::Completed => {
+                                    tracing::info!("Task complete: {}", task.id);
+                                    let mut state = state.lock().await;
+                                    state
+                                        .set_task_state(task.id, TaskState::Completed)
+                                        .await
+                                        .map_err(|e| e.to_string())?;
+                                    context.task_manager.mark_completed(task.id).await;
+                                    if let Some(job_id) = job_id.clone() {
+                                        let _ = job_outcome_sender.send(JobOutcome {
+                                            job_id,
+                                            error: None,
+                                            retry_after: None,
+                                            metrics,
+                                        });
+                                    }
+                                    let _ = memory_task_sender.send(task);
+                                }
+                                TaskFinishReason::Skipped => {
+                                    tracing::info!("Task skipped: {}", task.id);
+                                    let mut state = state.lock().await;
+                                    state
+                                        .set_task_state(task.id, TaskState::Postponed)
+                                        .await
+                                        .map_err(|e| e.to_string())?;
+                                    context.task_manager.mark_idle(task.id).await;
+                                    if let Some(job_id) = job_id.clone() {
+                                        let _ = job_outcome_sender.send(JobOutcome {
+                                            job_id,
+                                            error: None,
+                                            retry_after: None,
+                                            metrics,
+                                        });
+                                    }
+                                    let _ = memory_task_sender.send(task);
+                                }
+                                TaskFinishReason::Cancelled => {
+                                    tracing::info!("Task cancelled: {}", task.id);
+                                    let mut state = state.lock().await;
+                                    state
+                                        .set_task_state(task.id, TaskState::Cancelled)
+                                        .await
+                                        .map_err(|e| e.to_string())?;
+                                    context.task_manager.mark_cancelled(task.id).await;
+                                    if let Some(job_id) = job_id.clone() {
+                                        let _ = job_outcome_sender.send(JobOutcome {
+                                            job_id,
+                                            error: None,
+                                            retry_after: None,
+                                            metrics,
+                                        });
+                                    }
+                                }
+                            },
+                            Err(e) => {
+                                tracing::error!(?e, "Error processing task");
+                                context.task_manager.mark_failed(task.id, &e.to_string()).await;
+                                context.task_manager.mark_idle(task.id).await;
+                                if let Some(job_id) = job_id.clone() {
+                                    // Job-originated tasks don't go through the row-based
+                                    // `reschedule_task_with_backoff` — `SchedulerWorker` retries
+                                    // them instead, per the job's `RetryPolicy`.
+                                    let retry_after = e
+                                        .downcast_ref::<AgentError>()
+                                        .and_then(|err| match err {
+                                            AgentError::RateLimited { retry_after } => *retry_after,
+                                            _ => None,
+                                        });
+                                    let _ = job_outcome_sender.send(JobOutcome {
+                                        job_id,
+                                        error: Some(e.to_string()),
+                                        retry_after,
+                                        metrics: TaskMetrics::default(),
+                                    });
+                                } else {
+                                    // Errors that would fail identically on a retry (a bad
+                                    // provider profile, a malformed response) skip straight to
+                                    // `TaskState::DeadLettered` instead of burning the attempt
+                                    // budget on retries that can't possibly succeed; only
+                                    // transient classes (timeouts, 429s, 5xx) are retried. An
+                                    // error that never downcasts to `AgentError` (e.g. a plain
+                                    // `anyhow!(...)` from deeper in the task) is treated as
+                                    // retryable, since it isn't necessarily deterministic.
+                                    let retryable = e
+                                        .downcast_ref::<AgentError>()
+                                        .is_none_or(AgentError::is_retryable);
+                                    let mut state = state.lock().await;
+                                    state
+                                        .reschedule_task_with_backoff(
+                                            task_id,
+                                            &e.to_string(),
+                                            retryable,
+                                        )
+                                        .await
+                                        .map_err(|e| e.to_string())?;
+                                }
+                            }
+                        }
+                        Ok(())
+                    })
                 })
-                .await?;
+                .await;
             } else {
                 tokio::time::sleep(Duration::from_millis(100)).await;
             }
-            if incoming_tasks_processor.is_finished() {
+            if context.worker_manager.is_dead("task_router").await {
                 break;
             }
         }
 
+        // Anything still sitting in the channel never got a chance to run — postpone it rather
+        // than dropping it when `rx` goes out of scope below.
+        while let Ok(task) = rx.try_recv() {
+            tracing::info!(task_id = task.id, "Postponing queued task due to shutdown");
+            if let Err(err) = state.lock().await.set_task_state(task.id, TaskState::Postponed).await {
+                tracing::error!(?err, task_id = task.id, "Failed to persist postponed task state");
+            }
+            context.task_manager.mark_idle(task.id).await;
+        }
+
+        let grace_period = Duration::from_secs(config.shutdown_grace_period_secs);
+        let stragglers = pool.wait_drained(grace_period).await;
+        for task_id in stragglers {
+            tracing::warn!(task_id, "Task still running past the shutdown grace period, postponing it");
+            pool.send(task_id, pool::PoolCommand::Cancel).await;
+            if let Err(err) = state.lock().await.set_task_state(task_id, TaskState::Postponed).await {
+                tracing::error!(?err, task_id, "Failed to persist postponed task state");
+            }
+            context.task_manager.mark_idle(task_id).await;
+        }
+
         Ok(())
     }
 }
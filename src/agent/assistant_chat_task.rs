@@ -1,35 +1,33 @@
 // Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
 
-use std::collections::HashMap;
-
 use anyhow::Result;
 use futures::StreamExt;
-use regex::Regex;
-use tokio::select;
-use tracing::Level;
+use tokio::{select, sync::mpsc};
+use tracing::Instrument;
 
 use crate::{
     agent::utils,
+    communication::types::OutboundEvent,
     config::Config,
     context::AgentContext,
     huly,
+    huly::typing::THINKING_KEY,
     providers::ProviderClient,
     state::AgentState,
-    task::{Task, TaskFinishReason, TaskKind},
-    templates::TOOL_CALL_ERROR,
-    tools::ToolImpl,
-    types::{AssistantContent, Message, ToolResultContent},
+    task::{Task, TaskFinishReason, TaskKind, TaskMetrics},
+    tools::ToolMap,
+    types::{AssistantContent, Message, ToolCall, ToolResultContent},
 };
 
 pub async fn process_assistant_chat_task(
     config: &Config,
     provider_client: &dyn ProviderClient,
-    tools: &mut HashMap<String, Box<dyn ToolImpl>>,
+    tools: &ToolMap,
     task: &mut Task,
     state: &mut AgentState,
     context: &AgentContext,
     tools_descriptions: &[serde_json::Value],
-) -> Result<TaskFinishReason> {
+) -> Result<(TaskFinishReason, TaskMetrics)> {
     let system_prompt = utils::prepare_system_prompt(
         config,
         &context.account_info,
@@ -45,7 +43,7 @@ pub async fn process_assistant_chat_task(
         ..
     } = task.kind
     else {
-        return Ok(TaskFinishReason::Skipped);
+        return Ok((TaskFinishReason::Skipped, TaskMetrics::default()));
     };
 
     let mut finished = false;
@@ -76,17 +74,14 @@ pub async fn process_assistant_chat_task(
     ) {
         if !result_content.is_empty() {
             tracing::trace!(trace_info, result_content);
-            if result_content.contains("<|done|>") {
+            let tokens = utils::extract_control_tokens(result_content);
+            if tokens.done {
                 *finished = true;
-                *result_content = result_content.replace("<|done|>", "").trim().to_string();
             }
-            let regex = Regex::new(r"<\|([a-zA-Z\s]+)\|>").unwrap();
-            if let Some(caps) = regex.captures(result_content) {
-                let current_mood = caps[1].to_string();
+            if let Some(current_mood) = tokens.mood {
                 tracing::debug!("Mood: {current_mood}");
                 *mood = Some(current_mood);
             }
-            *result_content = regex.replace_all(result_content, "").to_string();
             if !result_content.is_empty() {
                 messages.push(Message::assistant(result_content));
                 huly::send_message(
@@ -97,6 +92,10 @@ pub async fn process_assistant_chat_task(
                 )
                 .await
                 .ok();
+                context.outbound_hub.publish(OutboundEvent::AgentMessage {
+                    card_id: card_id.to_string(),
+                    content: result_content.clone(),
+                });
             }
             context.typing_client.reset_typing(card_id).await.ok();
             result_content.clear();
@@ -104,6 +103,19 @@ pub async fn process_assistant_chat_task(
     }
 
     let mut last_message_count;
+    let mut total_prompt_tokens: u64 = 0;
+    let mut total_completion_tokens: u64 = 0;
+    let mut total_cost: u64 = 0;
+    let mut total_tool_calls: u32 = 0;
+    macro_rules! metrics {
+        () => {
+            TaskMetrics {
+                tool_calls: total_tool_calls,
+                prompt_tokens: total_prompt_tokens,
+                completion_tokens: total_completion_tokens,
+            }
+        };
+    }
     loop {
         last_message_count = messages.len();
 
@@ -132,7 +144,7 @@ pub async fn process_assistant_chat_task(
         let mut resp = select! {
             _ = task.cancel_token.cancelled() => {
                 state.set_assistant_messages(card_id, &messages).await?;
-                return Ok(TaskFinishReason::Cancelled);
+                return Ok((TaskFinishReason::Cancelled, metrics!()));
             },
             result = send_messages => {
                 result?
@@ -140,11 +152,39 @@ pub async fn process_assistant_chat_task(
         };
 
         result_content.clear();
+        let mut pending_tool_calls: Vec<ToolCall> = Vec::new();
+        let mut reasoning = false;
 
+        let stream_span = tracing::info_span!(
+            "provider_stream",
+            job_id = ?task.job_id,
+            task_kind = %task.kind,
+            prompt_tokens = tracing::field::Empty,
+            completion_tokens = tracing::field::Empty,
+        );
+        async {
         while let Some(result) = resp.next().await {
             match result {
                 Ok(content) => match content {
+                    AssistantContent::Reasoning(_) => {
+                        if !reasoning {
+                            reasoning = true;
+                            context
+                                .typing_client
+                                .set_typing(card_id, Some(THINKING_KEY.to_string()), 5)
+                                .await
+                                .ok();
+                        }
+                    }
                     AssistantContent::Text(text) => {
+                        if reasoning {
+                            reasoning = false;
+                            context
+                                .typing_client
+                                .set_typing(card_id, Some("Thinking".to_string()), 5)
+                                .await
+                                .ok();
+                        }
                         result_content.push_str(&text.text);
                     }
                     AssistantContent::ToolCall(tool_call) => {
@@ -160,58 +200,16 @@ pub async fn process_assistant_chat_task(
                         )
                         .await;
                         messages.push(Message::tool_call(tool_call.clone()));
-                        let tool_result = if let Some(tool) =
-                            tools.get_mut(&tool_call.function.name)
-                        {
-                            let span = tracing::span!(
-                                Level::INFO,
-                                "tool_call",
-                                call_id = tool_call.id,
-                                name = tool_call.function.name
-                            );
-                            context
-                                .typing_client
-                                .set_typing(
-                                    card_id,
-                                    Some(format!("Call {} tool", tool_call.function.name)),
-                                    5,
-                                )
-                                .await
-                                .ok();
-                            match span.in_scope(async || -> std::result::Result<Vec<ToolResultContent>, TaskFinishReason> {
-                                    let tool_call = tool.call(context, tool_call.function.arguments);
-                                    Ok(select! {
-                                        _ = task.cancel_token.cancelled() => {
-                                            state.set_assistant_messages(card_id, &messages).await.ok();
-                                            return std::result::Result::Err(TaskFinishReason::Cancelled);
-                                        },
-                                        res = tool_call => {
-                                            match res {
-                                                Ok(tool_result) => tool_result,
-                                                Err(e) => vec![ToolResultContent::text(
-                                                    subst::substitute(
-                                                        TOOL_CALL_ERROR,
-                                                        &HashMap::from([("ERROR", &e.to_string())]),
-                                                    )
-                                                    .unwrap(),
-                                                )],
-                                            }
-                                        }
-                                    })
-                                })
-                                .await {
-                                    Ok(result) => result,
-                                    Err(reason) => {
-                                        return Ok(reason);
-                                    }
-                                }
-                        } else {
-                            vec![ToolResultContent::text(format!(
-                                "Unknown tool [{}]",
-                                tool_call.function.name
-                            ))]
-                        };
-                        messages.push(Message::tool_result(&tool_call.id, tool_result));
+                        context
+                            .typing_client
+                            .set_typing(
+                                card_id,
+                                Some(format!("Call {} tool", tool_call.function.name)),
+                                5,
+                            )
+                            .await
+                            .ok();
+                        pending_tool_calls.push(tool_call);
                     }
                 },
                 Err(e) => {
@@ -219,6 +217,70 @@ pub async fn process_assistant_chat_task(
                 }
             }
         }
+        }
+        .instrument(stream_span.clone())
+        .await;
+        if let Some(usage) = resp.response.as_ref() {
+            stream_span.record("prompt_tokens", usage.prompt_tokens);
+            stream_span.record("completion_tokens", usage.completion_tokens);
+            total_prompt_tokens += usage.prompt_tokens as u64;
+            total_completion_tokens += usage.completion_tokens as u64;
+            if let Some(cost) = utils::token_cost(config, usage) {
+                total_cost += cost as u64;
+            }
+        }
+        if !pending_tool_calls.is_empty() {
+            total_tool_calls += pending_tool_calls.len() as u32;
+            let (progress_tx, mut progress_rx) = mpsc::channel(8);
+            let forward_progress = tokio::spawn({
+                let typing_client = context.typing_client.clone();
+                let card_id = card_id.clone();
+                async move {
+                    while let Some((_call_id, progress)) = progress_rx.recv().await {
+                        typing_client
+                            .set_typing(&card_id, Some(progress.text), 5)
+                            .await
+                            .ok();
+                    }
+                }
+            });
+            let tool_results = match utils::dispatch_tool_calls(
+                config,
+                context,
+                tools,
+                &task.cancel_token,
+                config.max_concurrent_tool_calls,
+                &pending_tool_calls,
+                Some(&progress_tx),
+            )
+            .await
+            {
+                Ok(tool_results) => tool_results,
+                Err(()) => {
+                    drop(progress_tx);
+                    forward_progress.await.ok();
+                    state.set_assistant_messages(card_id, &messages).await.ok();
+                    return Ok((TaskFinishReason::Cancelled, metrics!()));
+                }
+            };
+            drop(progress_tx);
+            forward_progress.await.ok();
+            for (tool_call, tool_result) in pending_tool_calls.into_iter().zip(tool_results) {
+                context.outbound_hub.publish(OutboundEvent::ToolResult {
+                    card_id: card_id.to_string(),
+                    tool_name: tool_call.function.name.clone(),
+                    content: tool_result
+                        .iter()
+                        .filter_map(|c| match c {
+                            ToolResultContent::Text(text) => Some(text.text.clone()),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                });
+                messages.push(Message::tool_result(&tool_call.id, tool_result));
+            }
+        }
         check_result_content(
             context,
             card_id,
@@ -237,7 +299,7 @@ pub async fn process_assistant_chat_task(
 
         if last_message_count == messages.len() {
             tracing::warn!("Task produced no messages");
-            return Ok(TaskFinishReason::Cancelled);
+            return Ok((TaskFinishReason::Cancelled, metrics!()));
         }
     }
 
@@ -249,9 +311,27 @@ pub async fn process_assistant_chat_task(
             .ok();
     }
 
-    Ok(if finished {
+    let summarizer = crate::compaction::LlmSummarizer {
+        provider: provider_client,
+    };
+    if let Err(err) = state
+        .compact_assistant_messages(card_id, &summarizer, &config.assistant_compaction)
+        .await
+    {
+        tracing::warn!(?err, "Failed to compact assistant messages");
+    }
+
+    tracing::info!(
+        total_prompt_tokens,
+        total_completion_tokens,
+        total_cost,
+        "Task token usage"
+    );
+
+    let finish_reason = if finished {
         TaskFinishReason::Completed
     } else {
         TaskFinishReason::Skipped
-    })
+    };
+    Ok((finish_reason, metrics!()))
 }
@@ -3,26 +3,119 @@
 use std::collections::HashMap;
 
 use anyhow::Result;
-use futures::StreamExt;
+use futures::{StreamExt, future::join_all};
 use tokio::select;
 use tracing::Level;
 
 use crate::{
     agent::utils,
+    communication::types::OutboundEvent,
     config::Config,
     context::AgentContext,
     providers::ProviderClient,
     state::AgentState,
     task::{Task, TaskFinishReason, TaskKind},
     templates::TOOL_CALL_ERROR,
-    tools::ToolImpl,
-    types::{AssistantContent, Message, ToolResultContent},
+    tools::{ToolKind, ToolMap},
+    types::{AssistantContent, Message, ToolCall, ToolResultContent},
 };
 
+/// Runs a single tool call to completion: the execute-tool approval gate (if any), then the call
+/// itself, both racing `task.cancel_token` exactly as the caller would inline. Extracted so
+/// `process_assistant_task` can run several of these concurrently via `join_all` instead of
+/// awaiting them one at a time.
+async fn execute_tool_call(
+    tools: &ToolMap,
+    context: &AgentContext,
+    task: &Task,
+    tool_call: ToolCall,
+) -> std::result::Result<(String, Vec<ToolResultContent>), TaskFinishReason> {
+    let tool_result = if let Some(tool) = tools.get(&tool_call.function.name) {
+        let mut tool = tool.lock().await;
+        let span = tracing::span!(
+            Level::INFO,
+            "tool_call",
+            call_id = tool_call.id,
+            name = tool_call.function.name
+        );
+        let approved = if tool.kind() == ToolKind::Execute {
+            select! {
+                _ = task.cancel_token.cancelled() => {
+                    return Err(TaskFinishReason::Cancelled);
+                },
+                approved = utils::await_execute_approval(
+                    &context.db_client,
+                    &tool_call.id,
+                    &tool_call.function.name,
+                    &tool_call.function.arguments,
+                ) => approved,
+            }
+        } else {
+            true
+        };
+        let cacheable = tool.kind() == ToolKind::Query && tool.is_cacheable();
+        let cached = if cacheable {
+            context
+                .tool_result_cache
+                .get(&tool_call.function.name, &tool_call.function.arguments)
+                .await
+        } else {
+            None
+        };
+
+        if !approved {
+            vec![ToolResultContent::text(format!(
+                "Action [{}] was not approved and was skipped.",
+                tool_call.function.name
+            ))]
+        } else if let Some(cached) = cached {
+            cached
+        } else {
+            let cache_name = tool_call.function.name.clone();
+            let cache_args = tool_call.function.arguments.clone();
+            let tool_result = span
+                .in_scope(async || -> std::result::Result<Vec<ToolResultContent>, TaskFinishReason> {
+                    let tool_call_future = tool.call(context, tool_call.function.arguments);
+                    Ok(select! {
+                        _ = task.cancel_token.cancelled() => {
+                            return std::result::Result::Err(TaskFinishReason::Cancelled);
+                        },
+                        res = tool_call_future => {
+                            match res {
+                                Ok(tool_result) => tool_result,
+                                Err(e) => vec![ToolResultContent::text(
+                                    subst::substitute(
+                                        TOOL_CALL_ERROR,
+                                        &HashMap::from([("ERROR", &e.to_string())]),
+                                    )
+                                    .unwrap(),
+                                )],
+                            }
+                        }
+                    })
+                })
+                .await?;
+            if cacheable {
+                context
+                    .tool_result_cache
+                    .insert(&cache_name, &cache_args, tool_result.clone())
+                    .await;
+            }
+            tool_result
+        }
+    } else {
+        vec![ToolResultContent::text(format!(
+            "Unknown tool [{}]",
+            tool_call.function.name
+        ))]
+    };
+    Ok((tool_call.id, tool_result))
+}
+
 pub async fn process_assistant_task(
     config: &Config,
     provider_client: &dyn ProviderClient,
-    tools: &mut HashMap<String, Box<dyn ToolImpl>>,
+    tools: &ToolMap,
     task: &mut Task,
     state: &mut AgentState,
     context: &AgentContext,
@@ -36,9 +129,17 @@ pub async fn process_assistant_task(
     )
     .await;
 
-    let TaskKind::AssistantTask { ref content, .. } = task.kind else {
+    let TaskKind::AssistantTask {
+        ref content,
+        sheduled_task_id,
+    } = task.kind
+    else {
         return Ok(TaskFinishReason::Skipped);
     };
+    // No chat card backs an `AssistantTask`, so the scheduled task id doubles as the
+    // `OutboundEvent::PartialMessage` card_id — the closest stable identifier a `/ws` client can
+    // key streamed chunks on.
+    let stream_id = sheduled_task_id.to_string();
 
     let mut finished = false;
     let mut messages = state.task_messages(task.id).await?;
@@ -84,12 +185,17 @@ pub async fn process_assistant_task(
         };
 
         result_content.clear();
+        let mut pending_calls: Vec<ToolCall> = Vec::new();
 
         while let Some(result) = resp.next().await {
             match result {
                 Ok(content) => match content {
                     AssistantContent::Text(text) => {
                         result_content.push_str(&text.text);
+                        context.outbound_hub.publish(OutboundEvent::PartialMessage {
+                            card_id: stream_id.clone(),
+                            chunk: text.text,
+                        });
                     }
                     AssistantContent::ToolCall(tool_call) => {
                         tracing::trace!(?tool_call, "Tool call");
@@ -111,62 +217,39 @@ pub async fn process_assistant_task(
                                 .await?,
                         );
 
-                        let tool_result = if let Some(tool) =
-                            tools.get_mut(&tool_call.function.name)
-                        {
-                            let span = tracing::span!(
-                                Level::INFO,
-                                "tool_call",
-                                call_id = tool_call.id,
-                                name = tool_call.function.name
-                            );
-                            match span.in_scope(async || -> std::result::Result<Vec<ToolResultContent>, TaskFinishReason> {
-                                    let tool_call = tool.call(context, tool_call.function.arguments);
-                                    Ok(select! {
-                                        _ = task.cancel_token.cancelled() => {
-                                            return std::result::Result::Err(TaskFinishReason::Cancelled);
-                                        },
-                                        res = tool_call => {
-                                            match res {
-                                                Ok(tool_result) => tool_result,
-                                                Err(e) => vec![ToolResultContent::text(
-                                                    subst::substitute(
-                                                        TOOL_CALL_ERROR,
-                                                        &HashMap::from([("ERROR", &e.to_string())]),
-                                                    )
-                                                    .unwrap(),
-                                                )],
-                                            }
-                                        }
-                                    })
-                                })
-                                .await {
-                                    Ok(result) => result,
-                                    Err(reason) => {
-                                        return Ok(reason);
-                                    }
-                                }
-                        } else {
-                            vec![ToolResultContent::text(format!(
-                                "Unknown tool [{}]",
-                                tool_call.function.name
-                            ))]
-                        };
-                        messages.push(
-                            state
-                                .add_task_message(
-                                    task,
-                                    Message::tool_result(&tool_call.id, tool_result),
-                                )
-                                .await?,
-                        );
+                        pending_calls.push(tool_call);
                     }
+                    AssistantContent::Reasoning(_) => {}
                 },
                 Err(e) => {
                     tracing::error!(?e, "Error processing message");
                 }
             }
         }
+
+        // All tool calls in this response were decided by the model up front (it hasn't seen any
+        // results yet), so they're independent and can run concurrently. `Message::tool_call`
+        // entries were already appended above in emission order; results are applied in that same
+        // order once every call has finished, regardless of which one completes first.
+        let outcomes = join_all(
+            pending_calls
+                .drain(..)
+                .map(|tool_call| execute_tool_call(tools, context, task, tool_call)),
+        )
+        .await;
+        for outcome in outcomes {
+            match outcome {
+                Ok((id, tool_result)) => {
+                    messages.push(
+                        state
+                            .add_task_message(task, Message::tool_result(&id, tool_result))
+                            .await?,
+                    );
+                }
+                Err(reason) => return Ok(reason),
+            }
+        }
+
         if !result_content.is_empty() {
             messages.push(
                 state
@@ -1,14 +1,11 @@
 // Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
 
-use std::{
-    collections::HashMap,
-    time::{Duration, Instant},
-};
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use futures::StreamExt;
 use tokio::select;
-use tracing::Level;
+use tracing::Instrument;
 
 use crate::{
     agent::utils,
@@ -17,10 +14,9 @@ use crate::{
     huly,
     providers::ProviderClient,
     state::AgentState,
-    task::{Task, TaskFinishReason, TaskKind},
-    templates::TOOL_CALL_ERROR,
-    tools::ToolImpl,
-    types::{AssistantContent, Message, ToolResultContent},
+    task::{Task, TaskFinishReason, TaskKind, TaskMetrics},
+    tools::ToolMap,
+    types::{AssistantContent, Message, ToolCall},
 };
 
 const MESSAGE_COST: u32 = 50;
@@ -31,12 +27,12 @@ const MAX_STEPS_PER_COMPLEXITY: usize = 2;
 pub async fn process_channel_task(
     config: &Config,
     provider_client: &dyn ProviderClient,
-    tools: &mut HashMap<String, Box<dyn ToolImpl>>,
+    tools: &ToolMap,
     task: &mut Task,
     state: &mut AgentState,
     context: &AgentContext,
     tools_descriptions: &[serde_json::Value],
-) -> Result<TaskFinishReason> {
+) -> Result<(TaskFinishReason, TaskMetrics)> {
     let system_prompt = utils::prepare_system_prompt(
         config,
         &task.kind.system_prompt(config),
@@ -48,6 +44,7 @@ pub async fn process_channel_task(
         context: &AgentContext,
         task_kind: &TaskKind,
         reaction: &str,
+        message_id_override: Option<&str>,
     ) -> Result<()> {
         if let TaskKind::FollowChat {
             channel_id,
@@ -55,6 +52,7 @@ pub async fn process_channel_task(
             ..
         } = task_kind
         {
+            let message_id = message_id_override.unwrap_or(message_id);
             huly::add_reaction(
                 &context.tx_client,
                 channel_id,
@@ -67,6 +65,29 @@ pub async fn process_channel_task(
         Ok(())
     }
 
+    /// Extracts `ControlTokens` from `result_content`, applies the wait-reaction (the model's own
+    /// `<|reaction:...|>` choice if it gave one, `"👀"` otherwise, optionally targeting a
+    /// `<|reply_to:...|>` message) once the task has grown complex enough, and reports whether the
+    /// model signalled completion.
+    async fn handle_result_content(
+        context: &AgentContext,
+        task: &mut Task,
+        state: &mut AgentState,
+        result_content: &mut String,
+        wait_reaction_added: &mut bool,
+    ) -> Result<bool> {
+        let tokens = utils::extract_control_tokens(result_content);
+        if let Some(complexity) = state.update_task_complexity(task, result_content).await
+            && complexity > WAIT_REACTION_COMPLEXITY
+            && !*wait_reaction_added
+        {
+            let reaction = tokens.reaction.as_deref().unwrap_or("👀");
+            add_reaction(context, &task.kind, reaction, tokens.reply_to.as_deref()).await?;
+            *wait_reaction_added = true;
+        }
+        Ok(tokens.done)
+    }
+
     let mut finished = false;
     let mut messages = state.task_messages(task.id).await?;
     // remove last assistant message if it is Assistant
@@ -83,6 +104,19 @@ pub async fn process_channel_task(
     }
     let start_time = Instant::now();
     let mut wait_reaction_added = false;
+    let mut total_prompt_tokens: u64 = 0;
+    let mut total_completion_tokens: u64 = 0;
+    let mut total_cost: u64 = 0;
+    let mut total_tool_calls: u32 = 0;
+    macro_rules! metrics {
+        () => {
+            TaskMetrics {
+                tool_calls: total_tool_calls,
+                prompt_tokens: total_prompt_tokens,
+                completion_tokens: total_completion_tokens,
+            }
+        };
+    }
     loop {
         if matches!(messages.last().unwrap(), Message::Assistant { .. }) {
             match task.kind {
@@ -123,7 +157,7 @@ pub async fn process_channel_task(
         );
         let mut resp = select! {
             _ = task.cancel_token.cancelled() => {
-                return Ok(TaskFinishReason::Cancelled);
+                return Ok((TaskFinishReason::Cancelled, metrics!()));
             },
             result = send_messages => {
                 result?
@@ -132,6 +166,15 @@ pub async fn process_channel_task(
 
         let mut result_content = String::new();
         let mut balance = state.balance();
+        let mut pending_tool_calls: Vec<ToolCall> = Vec::new();
+        let stream_span = tracing::info_span!(
+            "provider_stream",
+            job_id = ?task.job_id,
+            task_kind = %task.kind,
+            prompt_tokens = tracing::field::Empty,
+            completion_tokens = tracing::field::Empty,
+        );
+        async {
         while let Some(result) = resp.next().await {
             match result {
                 Ok(content) => match content {
@@ -141,6 +184,14 @@ pub async fn process_channel_task(
                     AssistantContent::ToolCall(tool_call) => {
                         tracing::trace!(?tool_call, "Tool call");
                         if !result_content.is_empty() {
+                            let done = handle_result_content(
+                                context,
+                                task,
+                                state,
+                                &mut result_content,
+                                &mut wait_reaction_added,
+                            )
+                            .await?;
                             messages.push(
                                 state
                                     .add_task_message(
@@ -150,16 +201,7 @@ pub async fn process_channel_task(
                                     )
                                     .await?,
                             );
-                            balance = balance.saturating_sub(MESSAGE_COST);
-                            if let Some(complexity) =
-                                state.update_task_complexity(task, &result_content).await
-                            {
-                                if complexity > WAIT_REACTION_COMPLEXITY && !wait_reaction_added {
-                                    add_reaction(context, &task.kind, "👀").await?;
-                                    wait_reaction_added = true;
-                                }
-                            }
-                            if result_content.contains("<attempt_completion>") {
+                            if done {
                                 finished = true;
                             }
                             result_content.clear();
@@ -173,78 +215,78 @@ pub async fn process_channel_task(
                                 )
                                 .await?,
                         );
-                        let tool_result = if let Some(tool) =
-                            tools.get_mut(&tool_call.function.name)
-                        {
-                            let span = tracing::span!(
-                                Level::INFO,
-                                "tool_call",
-                                call_id = tool_call.id,
-                                name = tool_call.function.name
-                            );
-                            match span.in_scope(async || -> std::result::Result<Vec<ToolResultContent>, TaskFinishReason> {
-                                    let tool_call = tool.call(context, tool_call.function.arguments);
-                                    Ok(select! {
-                                        _ = task.cancel_token.cancelled() => {
-                                            return std::result::Result::Err(TaskFinishReason::Cancelled);
-                                        },
-                                        res = tool_call => {
-                                            match res {
-                                                Ok(tool_result) => tool_result,
-                                                Err(e) => vec![ToolResultContent::text(
-                                                    subst::substitute(
-                                                        TOOL_CALL_ERROR,
-                                                        &HashMap::from([("ERROR", &e.to_string())]),
-                                                    )
-                                                    .unwrap(),
-                                                )],
-                                            }
-                                        }
-                                    })
-                                })
-                                .await {
-                                    Ok(result) => result,
-                                    Err(reason) => {
-                                        return Ok(reason);
-                                    }
-                                }
-                        } else {
-                            vec![ToolResultContent::text(format!(
-                                "Unknown tool [{}]",
-                                tool_call.function.name
-                            ))]
-                        };
-                        messages.push(
-                            state
-                                .add_task_message(
-                                    context,
-                                    task,
-                                    Message::tool_result(&tool_call.id, tool_result),
-                                )
-                                .await?,
-                        );
-                        balance = balance.saturating_sub(MESSAGE_COST);
+                        pending_tool_calls.push(tool_call);
                     }
+                    AssistantContent::Reasoning(_) => {}
                 },
                 Err(e) => {
                     tracing::error!(?e, "Error processing message");
                 }
             }
         }
+        Ok::<(), anyhow::Error>(())
+        }
+        .instrument(stream_span.clone())
+        .await?;
+
+        if let Some(usage) = resp.response.as_ref() {
+            stream_span.record("prompt_tokens", usage.prompt_tokens);
+            stream_span.record("completion_tokens", usage.completion_tokens);
+        }
+        let round_trip_cost = match resp.response.as_ref().and_then(|usage| {
+            total_prompt_tokens += usage.prompt_tokens as u64;
+            total_completion_tokens += usage.completion_tokens as u64;
+            utils::token_cost(config, usage)
+        }) {
+            Some(cost) => cost,
+            None => MESSAGE_COST,
+        };
+        total_cost += round_trip_cost as u64;
+        balance = balance.saturating_sub(round_trip_cost);
+
+        if !pending_tool_calls.is_empty() {
+            total_tool_calls += pending_tool_calls.len() as u32;
+            let tool_results = match utils::dispatch_tool_calls(
+                config,
+                context,
+                tools,
+                &task.cancel_token,
+                config.max_concurrent_tool_calls,
+                &pending_tool_calls,
+                None,
+            )
+            .await
+            {
+                Ok(tool_results) => tool_results,
+                Err(()) => return Ok((TaskFinishReason::Cancelled, metrics!())),
+            };
+            for (tool_call, tool_result) in pending_tool_calls.into_iter().zip(tool_results) {
+                messages.push(
+                    state
+                        .add_task_message(
+                            context,
+                            task,
+                            Message::tool_result(&tool_call.id, tool_result),
+                        )
+                        .await?,
+                );
+            }
+        }
         if !result_content.is_empty() {
+            let done = handle_result_content(
+                context,
+                task,
+                state,
+                &mut result_content,
+                &mut wait_reaction_added,
+            )
+            .await?;
             messages.push(
                 state
                     .add_task_message(context, task, Message::assistant(&result_content))
                     .await?,
             );
-            balance = balance.saturating_sub(MESSAGE_COST);
-            if let Some(complexity) = state.update_task_complexity(task, &result_content).await {
-                if complexity > WAIT_REACTION_COMPLEXITY && !wait_reaction_added {
-                    add_reaction(context, &task.kind, "👀").await?;
-                    wait_reaction_added = true;
-                }
-            }
-            if result_content.contains("<attempt_completion>") {
+            if done {
                 finished = true;
             }
             result_content.clear();
@@ -259,20 +301,28 @@ pub async fn process_channel_task(
         }
         if messages.len() > MAX_STEPS_PER_COMPLEXITY * task.complexity as usize {
             tracing::info!("Task steps limit reached");
-            add_reaction(context, &task.kind, "❌").await?;
-            return Ok(TaskFinishReason::Cancelled);
+            add_reaction(context, &task.kind, "❌", None).await?;
+            return Ok((TaskFinishReason::Cancelled, metrics!()));
         }
         if !wait_reaction_added
             && Instant::now().saturating_duration_since(start_time) > WAIT_REACTION_DURATION
         {
-            add_reaction(context, &task.kind, "👀").await?;
+            add_reaction(context, &task.kind, "👀", None).await?;
             wait_reaction_added = true
         }
     }
 
-    Ok(if finished {
+    tracing::info!(
+        total_prompt_tokens,
+        total_completion_tokens,
+        total_cost,
+        "Task token usage"
+    );
+
+    let finish_reason = if finished {
         TaskFinishReason::Completed
     } else {
         TaskFinishReason::Skipped
-    })
+    };
+    Ok((finish_reason, metrics!()))
 }
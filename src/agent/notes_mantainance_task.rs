@@ -1,26 +1,24 @@
 // Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
 
-use std::collections::HashMap;
-
 use anyhow::Result;
 use futures::StreamExt;
+use tokio_util::sync::CancellationToken;
 
 use crate::{
-    agent::utils,
+    agent::utils::{self, LoopBudget},
     config::Config,
     context::AgentContext,
     providers::ProviderClient,
     state::AgentState,
     task::TaskKind,
-    templates::TOOL_CALL_ERROR,
-    tools::ToolImpl,
-    types::{AssistantContent, Message, ToolResultContent},
+    tools::ToolMap,
+    types::{AssistantContent, Message, ToolCall},
 };
 
 pub async fn notes_mantainance(
     config: &Config,
     provider_client: &dyn ProviderClient,
-    tools: &mut HashMap<String, Box<dyn ToolImpl>>,
+    tools: &ToolMap,
     state: &mut AgentState,
     context: &AgentContext,
     tools_descriptions: &[serde_json::Value],
@@ -37,6 +35,10 @@ pub async fn notes_mantainance(
     let mut result_content = String::new();
     let mut last_message_count;
     let mut finished = false;
+    // Unattended maintenance has no `Task`/`cancel_token` of its own to honor; this loop simply
+    // never cancels `Execute`-kind tool calls mid-flight.
+    let cancel_token = CancellationToken::new();
+    let mut loop_budget = LoopBudget::new(config.notes.loop_budget.as_ref());
 
     loop {
         last_message_count = messages.len();
@@ -54,6 +56,7 @@ pub async fn notes_mantainance(
             .await?;
 
         result_content.clear();
+        let mut pending_tool_calls: Vec<ToolCall> = Vec::new();
 
         while let Some(result) = resp.next().await {
             match result {
@@ -72,34 +75,38 @@ pub async fn notes_mantainance(
                             result_content.clear();
                         }
                         messages.push(Message::tool_call(tool_call.clone()));
-
-                        let tool_result =
-                            if let Some(tool) = tools.get_mut(&tool_call.function.name) {
-                                let res = tool.call(context, tool_call.function.arguments).await;
-                                match res {
-                                    Ok(tool_result) => tool_result,
-                                    Err(e) => vec![ToolResultContent::text(
-                                        subst::substitute(
-                                            TOOL_CALL_ERROR,
-                                            &HashMap::from([("ERROR", &e.to_string())]),
-                                        )
-                                        .unwrap(),
-                                    )],
-                                }
-                            } else {
-                                vec![ToolResultContent::text(format!(
-                                    "Unknown tool [{}]",
-                                    tool_call.function.name
-                                ))]
-                            };
-                        messages.push(Message::tool_result(&tool_call.id, tool_result));
+                        pending_tool_calls.push(tool_call);
                     }
+                    AssistantContent::Reasoning(_) => {}
                 },
                 Err(e) => {
                     tracing::error!(?e, "Error processing message");
                 }
             }
         }
+
+        if !pending_tool_calls.is_empty() {
+            // Read-only tool calls run immediately; `Execute`-kind calls are gated behind
+            // `Config::execute_policy` (see `agent::utils::dispatch_one_tool_call`), so an
+            // unattended maintenance run can't make irreversible changes unsupervised.
+            let tool_results = match utils::dispatch_tool_calls(
+                config,
+                context,
+                tools,
+                &cancel_token,
+                config.max_concurrent_tool_calls,
+                &pending_tool_calls,
+                None,
+            )
+            .await
+            {
+                Ok(tool_results) => tool_results,
+                Err(()) => return Ok(()),
+            };
+            for (tool_call, tool_result) in pending_tool_calls.into_iter().zip(tool_results) {
+                messages.push(Message::tool_result(&tool_call.id, tool_result));
+            }
+        }
         if !result_content.is_empty() {
             messages.push(Message::assistant(&result_content));
             if result_content.contains("<|done|>") {
@@ -116,6 +123,11 @@ pub async fn notes_mantainance(
             tracing::warn!("Task produced no messages");
             return Ok(());
         }
+
+        if let Some(exceeded) = loop_budget.check(&messages) {
+            tracing::warn!(%exceeded, "Notes maintenance loop budget exceeded, stopping");
+            break;
+        }
     }
 
     Ok(())
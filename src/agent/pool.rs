@@ -0,0 +1,192 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! Bounded concurrent execution of dispatched `Task`s. `Agent::run` used to fully await each
+//! `process_*_task` call before pulling the next `Task` off its channel, so one long
+//! `AssistantChat` turn stalled every `Sleep`/channel task queued behind it. `TaskPool` instead
+//! spawns each task onto its own `tokio` task, gated by a `Semaphore` sized to
+//! `Config::max_concurrent_tasks`, and tracks one entry per in-flight (or queued) task in a
+//! `HashMap<TaskId, WorkerHandle>` so an operator can see what's running and steer it — mirroring
+//! `worker::WorkerManager`'s introspection/control pattern, but for one-shot task runs rather than
+//! long-lived background loops.
+//!
+//! This only decouples *dispatch* from *processing time*: `Agent::run` still shares one
+//! `AgentState` behind a single `tokio::sync::Mutex` across every spawned task, so two tasks that
+//! both need to touch it still serialize on that lock. What it buys is that a task whose slow part
+//! is provider/tool I/O (not `AgentState` access) no longer blocks unrelated tasks from starting.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use tokio::sync::{Mutex, RwLock, Semaphore, mpsc};
+use tokio_util::sync::CancellationToken;
+
+/// Poll interval used by `TaskPool::wait_drained` while it waits for in-flight tasks to finish.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Used as the `Semaphore`'s permit count when `Config::max_concurrent_tasks` is `None`: large
+/// enough that the pool is unbounded in practice, while still being a concrete number the
+/// semaphore API requires.
+const UNBOUNDED_CONCURRENCY: usize = 10_000;
+
+/// One in-flight (or queued) task run, as reported by `TaskPool::list`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkerState {
+    /// Holding a pool permit and actively running `task_id`.
+    Active { task_id: i64 },
+    /// Registered but still waiting for a permit to free up.
+    Idle,
+    /// Finished — successfully, with an error, or cancelled. Kept around until the next `spawn`
+    /// call prunes it, so a caller reading `list()` right after completion still sees it.
+    Dead,
+}
+
+/// Control-channel commands accepted by one pool worker.
+#[derive(Debug, Clone, Copy)]
+pub enum PoolCommand {
+    /// No effect on an already-running task: `process_*_task` has no preemption point to suspend
+    /// at. Accepted (not rejected) anyway so a caller pausing several tasks at once doesn't get a
+    /// spurious error for one that's already past the point where pausing would matter.
+    Pause,
+    Resume,
+    /// Fires the task's own `CancellationToken` — the same one `Task::cancel_token` carries and
+    /// `TaskManager::cancel` already uses — so it takes effect at whatever check points the
+    /// running `process_*_task` call itself honors.
+    Cancel,
+}
+
+struct WorkerHandle {
+    kind: String,
+    status: Arc<RwLock<WorkerState>>,
+    last_error: Arc<RwLock<Option<String>>>,
+    commands: mpsc::UnboundedSender<PoolCommand>,
+}
+
+/// A registered task's current pool status, as reported by `TaskPool::list`.
+#[derive(Debug, Clone)]
+pub struct WorkerStatus {
+    pub task_id: i64,
+    pub kind: String,
+    pub state: WorkerState,
+    pub last_error: Option<String>,
+}
+
+/// Bounded pool driving `Agent::run`'s dispatched tasks concurrently. See module docs.
+pub struct TaskPool {
+    semaphore: Arc<Semaphore>,
+    workers: Mutex<HashMap<i64, WorkerHandle>>,
+}
+
+impl TaskPool {
+    /// `max_concurrent_tasks` mirrors `Config::max_concurrent_tasks`.
+    pub fn new(max_concurrent_tasks: Option<usize>) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrent_tasks.unwrap_or(UNBOUNDED_CONCURRENCY))),
+            workers: Mutex::default(),
+        }
+    }
+
+    /// Registers `task_id` as `Idle`, then spawns a `tokio` task that waits for a free permit
+    /// (flipping the entry to `Active { task_id }` once it gets one), runs `run`, and records
+    /// `Dead` plus `run`'s error (if any) once it returns. `cancel_token` is fired when a caller
+    /// sends `PoolCommand::Cancel` while `run` is still in flight.
+    pub async fn spawn<F, Fut>(
+        &self,
+        task_id: i64,
+        kind: impl Into<String>,
+        cancel_token: CancellationToken,
+        run: F,
+    ) where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = Result<(), String>> + Send + 'static,
+    {
+        let kind = kind.into();
+        let status = Arc::new(RwLock::new(WorkerState::Idle));
+        let last_error = Arc::new(RwLock::new(None));
+        let (tx, mut rx) = mpsc::unbounded_channel::<PoolCommand>();
+
+        {
+            let mut workers = self.workers.lock().await;
+            let mut dead = Vec::new();
+            for (id, handle) in workers.iter() {
+                if matches!(*handle.status.read().await, WorkerState::Dead) {
+                    dead.push(*id);
+                }
+            }
+            for id in dead {
+                workers.remove(&id);
+            }
+            workers.insert(
+                task_id,
+                WorkerHandle { kind, status: status.clone(), last_error: last_error.clone(), commands: tx },
+            );
+        }
+
+        let semaphore = self.semaphore.clone();
+        tokio::spawn(async move {
+            let permit = semaphore.acquire_owned().await.expect("semaphore is never closed");
+            *status.write().await = WorkerState::Active { task_id };
+
+            let run_fut = run();
+            tokio::pin!(run_fut);
+            let result = loop {
+                tokio::select! {
+                    result = &mut run_fut => break result,
+                    command = rx.recv() => match command {
+                        Some(PoolCommand::Cancel) => cancel_token.cancel(),
+                        Some(PoolCommand::Pause) | Some(PoolCommand::Resume) | None => {}
+                    },
+                }
+            };
+
+            if let Err(err) = result {
+                *last_error.write().await = Some(err);
+            }
+            *status.write().await = WorkerState::Dead;
+            drop(permit);
+        });
+    }
+
+    /// Sends `command` to `task_id`'s worker. `false` if it's no longer registered (already
+    /// finished, or never existed).
+    pub async fn send(&self, task_id: i64, command: PoolCommand) -> bool {
+        let workers = self.workers.lock().await;
+        match workers.get(&task_id) {
+            Some(handle) => handle.commands.send(command).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Polls `list()` every `DRAIN_POLL_INTERVAL` until every worker is `Dead` or `timeout`
+    /// elapses, whichever comes first. Returns the `task_id`s still not `Dead` when it gave up —
+    /// empty if everything drained in time. Used by `Agent::run`'s shutdown path to bound how long
+    /// it waits for in-flight tasks before giving up on them.
+    pub async fn wait_drained(&self, timeout: Duration) -> Vec<i64> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let still_running: Vec<i64> = self
+                .list()
+                .await
+                .into_iter()
+                .filter(|status| !matches!(status.state, WorkerState::Dead))
+                .map(|status| status.task_id)
+                .collect();
+            if still_running.is_empty() || tokio::time::Instant::now() >= deadline {
+                return still_running;
+            }
+            tokio::time::sleep(DRAIN_POLL_INTERVAL.min(deadline - tokio::time::Instant::now())).await;
+        }
+    }
+
+    pub async fn list(&self) -> Vec<WorkerStatus> {
+        let workers = self.workers.lock().await;
+        let mut statuses = Vec::with_capacity(workers.len());
+        for (task_id, handle) in workers.iter() {
+            statuses.push(WorkerStatus {
+                task_id: *task_id,
+                kind: handle.kind.clone(),
+                state: *handle.status.read().await,
+                last_error: handle.last_error.read().await.clone(),
+            });
+        }
+        statuses
+    }
+}
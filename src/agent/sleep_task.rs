@@ -2,9 +2,10 @@
 
 use std::{collections::HashMap, time::Duration};
 
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow};
 use chrono::Utc;
 use futures::StreamExt;
+use tracing::Instrument;
 
 use crate::{
     agent::utils,
@@ -13,12 +14,14 @@ use crate::{
     memory::{MemoryEntity, MemoryEntityType},
     providers::ProviderClient,
     state::AgentState,
-    task::{Task, TaskFinishReason},
+    task::{Task, TaskFinishReason, TaskMetrics},
     types::{AssistantContent, Message},
 };
 
 const MEMORY_CONSOLIDATION_THRESHOLD: f32 = 0.5;
 const MEMORY_CONSOLIDATION_PAGE_SIZE: u16 = 20;
+/// This task's component in `MemoryEntity::version_vector` (see `memory::merge_entities`).
+const SLEEP_TASK_WRITER: &str = "sleep_task";
 // 7 days
 const TASK_EXPIRE_PERIOD: Duration = Duration::from_secs(60 * 60 * 24 * 7);
 
@@ -28,7 +31,8 @@ pub async fn process_sleep_task(
     task: &Task,
     state: &mut AgentState,
     context: &AgentContext,
-) -> Result<TaskFinishReason> {
+) -> Result<(TaskFinishReason, TaskMetrics)> {
+    let mut metrics = TaskMetrics::default();
     let system_prompt = utils::prepare_system_prompt(
         config,
         &context.account_info,
@@ -37,55 +41,111 @@ pub async fn process_sleep_task(
     )
     .await;
 
+    // Recompute and persist decayed importance scores first, so the threshold check below sees
+    // current values instead of whatever was last persisted.
+    crate::memory::memory_mantainance(&context.db_client, &config.memory.scoring).await?;
+
     let ids = context
         .db_client
         .mem_entities_ids_for_consolidation(MEMORY_CONSOLIDATION_THRESHOLD)
         .await?;
     let total_count = ids.len();
     let mut semantic_count = 0;
+    let mut succeeded_batches = 0;
+    let mut failed_batches = 0;
     for ids in ids.chunks(MEMORY_CONSOLIDATION_PAGE_SIZE.into()) {
-        let count = ids.len();
-        let mut semantic_entities: HashMap<String, MemoryEntity> = HashMap::new();
-        let mut mem_entities = vec![];
-        for id in ids {
-            let mem_entity = context.db_client.mem_entity(*id).await?;
-            let query = format!(
-                "{}\n{}\n{}",
-                mem_entity.name,
-                mem_entity.category,
-                mem_entity.observations.join("\n")
-            );
-            let relevant_sem_entities = context
-                .db_client
-                .mem_relevant_entities(3, &query, MemoryEntityType::Semantic)
-                .await?;
-            mem_entities.push(mem_entity);
-            for entity in relevant_sem_entities.into_iter() {
-                if !semantic_entities.contains_key(&entity.name) {
-                    semantic_entities.insert(entity.name.clone(), entity);
-                }
+        match consolidate_batch(config, provider_client, task, state, context, &system_prompt, ids)
+            .await
+        {
+            Ok((count, batch_metrics)) => {
+                semantic_count += count;
+                succeeded_batches += 1;
+                metrics.prompt_tokens += batch_metrics.prompt_tokens;
+                metrics.completion_tokens += batch_metrics.completion_tokens;
+            }
+            Err(e) => {
+                tracing::error!(?e, "Failed to consolidate memory batch, skipping it");
+                failed_batches += 1;
             }
         }
-        tracing::info!("Processing {count} memory items");
+    }
 
-        let messages = vec![Message::user(&serde_json::to_string_pretty(
-            &mem_entities
-                .iter()
-                .chain(semantic_entities.values())
-                .collect::<Vec<_>>(),
-        )?)];
-        let evn_context = utils::create_context(
-            config,
-            context,
-            state,
-            &messages,
-            &task.kind.context(config, context),
-        )
-        .await;
-        let mut resp = provider_client
-            .send_messages(&system_prompt, &evn_context, &messages, &[])
+    tracing::info!(
+        "Memory process finished: stored {semantic_count} semantic entities, {total_count} \
+         episodic entities considered, {succeeded_batches} batches consolidated, \
+         {failed_batches} batches skipped"
+    );
+    context
+        .db_client
+        .delete_old_tasks(Utc::now() - TASK_EXPIRE_PERIOD)
+        .await?;
+    Ok((TaskFinishReason::Completed, metrics))
+}
+
+/// Consolidates a single page of episodic entity ids into semantic entities, returning how many
+/// semantic entities were written. Source episodic entities are only deleted once every derived
+/// semantic entity from this batch has been written successfully, so a failure partway through
+/// (or a malformed response, see `extract_json_array`) leaves the batch's episodic entities intact
+/// for a later sleep cycle to retry instead of silently losing them.
+async fn consolidate_batch(
+    config: &Config,
+    provider_client: &dyn ProviderClient,
+    task: &Task,
+    state: &mut AgentState,
+    context: &AgentContext,
+    system_prompt: &str,
+    ids: &[i64],
+) -> Result<(usize, TaskMetrics)> {
+    let count = ids.len();
+    let mut semantic_entities: HashMap<String, MemoryEntity> = HashMap::new();
+    let mut mem_entities = vec![];
+    for id in ids {
+        let mem_entity = context.db_client.mem_entity(*id).await?;
+        let query = format!(
+            "{}\n{}\n{}",
+            mem_entity.name,
+            mem_entity.category,
+            mem_entity.observations.join("\n")
+        );
+        let relevant_sem_entities = context
+            .db_client
+            .mem_relevant_entities(3, &query, MemoryEntityType::Semantic)
             .await?;
-        let mut result_content = String::new();
+        mem_entities.push(mem_entity);
+        for entity in relevant_sem_entities.into_iter() {
+            if !semantic_entities.contains_key(&entity.name) {
+                semantic_entities.insert(entity.name.clone(), entity);
+            }
+        }
+    }
+    tracing::info!("Processing {count} memory items");
+
+    let messages = vec![Message::user(&serde_json::to_string_pretty(
+        &mem_entities
+            .iter()
+            .chain(semantic_entities.values())
+            .collect::<Vec<_>>(),
+    )?)];
+    let evn_context = utils::create_context(
+        config,
+        context,
+        state,
+        &messages,
+        &task.kind.context(config, context),
+    )
+    .await;
+    let mut resp = provider_client
+        .send_messages(system_prompt, &evn_context, &messages, &[])
+        .await?;
+    let mut result_content = String::new();
+    let stream_span = tracing::info_span!(
+        "provider_stream",
+        job_id = ?task.job_id,
+        task_kind = %task.kind,
+        prompt_tokens = tracing::field::Empty,
+        completion_tokens = tracing::field::Empty,
+    );
+    async {
         while let Some(result) = resp.next().await {
             match result {
                 Ok(content) => {
@@ -98,60 +158,83 @@ pub async fn process_sleep_task(
                 }
             }
         }
-        if result_content.starts_with("```json") {
-            result_content = result_content
-                .trim_start_matches("```json")
-                .trim_end_matches("```")
-                .to_string();
+    }
+    .instrument(stream_span.clone())
+    .await;
+    let mut metrics = TaskMetrics::default();
+    if let Some(usage) = resp.response.as_ref() {
+        stream_span.record("prompt_tokens", usage.prompt_tokens);
+        stream_span.record("completion_tokens", usage.completion_tokens);
+        metrics.prompt_tokens = usage.prompt_tokens as u64;
+        metrics.completion_tokens = usage.completion_tokens as u64;
+    }
+    let json_array = extract_json_array(&result_content).ok_or_else(|| {
+        anyhow!("No JSON array found in consolidation response: {result_content}")
+    })?;
+    let new_entities: Vec<MemoryEntity> = serde_json::from_str(json_array)
+        .with_context(|| format!("Malformed consolidation response: {json_array}"))?;
+
+    for mut entity in new_entities.iter().cloned() {
+        if let Some(sem_entity) = semantic_entities.get(&entity.name) {
+            // if semantic was in current request we should rewrite it
+            tracing::debug!(
+                "rewrite existing semantic entity {}: {} ",
+                sem_entity.id,
+                sem_entity.name
+            );
+            entity.id = sem_entity.id;
+            entity.importance = sem_entity.importance;
+            entity.version_vector = sem_entity.version_vector.clone();
+        } else if let Some(existing_entity) = context
+            .db_client
+            .mem_entity_by_name(&entity.name, MemoryEntityType::Semantic)
+            .await
+        {
+            // check in db if entity already exists
+            tracing::debug!(
+                "rewrite existing semantic entity {}: {} ",
+                existing_entity.id,
+                existing_entity.name
+            );
+            entity.id = existing_entity.id;
+            entity.importance = existing_entity.importance;
+            entity.version_vector = existing_entity.version_vector.clone();
         }
-        let new_entities: Vec<MemoryEntity> = serde_json::from_str(&result_content)?;
-        semantic_count += new_entities.len();
-        for mut entity in new_entities.into_iter() {
-            if let Some(sem_entity) = semantic_entities.get(&entity.name) {
-                // if semantic was in current request we should rewrite it
-                tracing::debug!(
-                    "rewrite existing semantic entity {}: {} ",
-                    sem_entity.id,
-                    sem_entity.name
-                );
-                entity.id = sem_entity.id;
-                entity.importance = sem_entity.importance;
-            } else if let Some(existing_entity) = context
-                .db_client
-                .mem_entity_by_name(&entity.name, MemoryEntityType::Semantic)
-                .await
-            {
-                // check in db if entity already exists
-                tracing::debug!(
-                    "rewrite existing semantic entity {}: {} ",
-                    existing_entity.id,
-                    existing_entity.name
-                );
-                entity.id = existing_entity.id;
-                entity.importance = existing_entity.importance;
-            }
 
-            // write to db
-            for entity_id in ids {
-                context.db_client.mem_delete_entity(*entity_id).await?;
-            }
-            entity.updated_at = Utc::now();
-            if entity.id == 0 {
-                entity.importance = 1.0;
-                context.db_client.mem_add_entity(&entity).await?;
-            } else {
-                entity.importance = (entity.importance * 1.5).min(1.0);
-                context.db_client.mem_update_entity(&entity).await?;
-            }
+        entity.updated_at = Utc::now();
+        if entity.id == 0 {
+            entity.importance = 1.0;
+            context
+                .db_client
+                .mem_add_entity(&entity, SLEEP_TASK_WRITER)
+                .await?;
+        } else {
+            entity.importance = (entity.importance * 1.5).min(1.0);
+            context
+                .db_client
+                .mem_update_entity(&entity, SLEEP_TASK_WRITER)
+                .await?;
         }
     }
 
-    tracing::info!(
-        "Memory process finished: stored {semantic_count} semantic entities, delete {total_count} episodic entities"
-    );
-    context
-        .db_client
-        .delete_old_tasks(Utc::now() - TASK_EXPIRE_PERIOD)
-        .await?;
-    Ok(TaskFinishReason::Completed)
+    // Every derived semantic entity above was written successfully, so it's now safe to retire
+    // the episodic entities this batch consolidated.
+    for entity_id in ids {
+        context.db_client.mem_delete_entity(*entity_id).await?;
+    }
+
+    Ok((new_entities.len(), metrics))
+}
+
+/// Locates the outermost JSON array in `text`, tolerating surrounding prose, code fences, or
+/// trailing commentary that the model sometimes adds around the requested JSON. Returns the slice
+/// spanning the first `[` through the last `]` in `text` (brackets are not matched, so a `]` in
+/// trailing prose extends the slice), not validated as JSON yet.
+fn extract_json_array(text: &str) -> Option<&str> {
+    let start = text.find('[')?;
+    let end = text.rfind(']')?;
+    if end < start {
+        return None;
+    }
+    Some(&text[start..=end])
 }
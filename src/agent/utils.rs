@@ -1,22 +1,32 @@
 // Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
 
-use std::{collections::HashMap, path::Path};
+use std::{collections::HashMap, path::Path, sync::Arc};
 
 use base64::Engine;
+use futures::stream::{self, StreamExt};
 use hulyrs::services::transactor::document::{DocumentClient, FindOptionsBuilder};
 use itertools::Itertools;
 use serde_json::json;
-use tokio::{fs, sync::mpsc, task::JoinHandle};
+use tokio::{
+    fs,
+    runtime::Handle,
+    select,
+    sync::{Semaphore, mpsc},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::Level;
 
 use crate::{
     agent::{MAX_MEMORY_ENTITIES, utils::utils::normalize_path},
-    config::{AgentMode, Config},
+    config::{AgentMode, Config, ExecutePolicy},
     context::{AgentContext, HulyAccountInfo},
     database::DbClient,
+    huly::resilient::Staleness,
     memory::MemoryEntityType,
     state::AgentState,
     task::{MAX_FOLLOW_MESSAGES, Task, TaskKind},
-    templates::{CONTEXT, SYSTEM_PROMPT},
+    templates::{CONTEXT, SYSTEM_PROMPT, TOOL_CALL_ERROR},
+    tools::{ToolImpl, ToolKind, ToolMap, ToolProgress},
     types::{
         AssistantContent, ContentFormat, Image, ImageMediaType, Message, Text, ToolCall,
         ToolResultContent, UserContent,
@@ -106,26 +116,37 @@ pub async fn create_context(
         let mode_context = match &config.agent_mode {
             AgentMode::Employee(_) => "".to_string(),
             AgentMode::PersonalAssistant(_) => {
-                let user_status = context
-                    .tx_client
-                    .find_one::<_, serde_json::Value>(
+                let (user_status, staleness) = context
+                    .resilient_tx
+                    .find_one_resilient(
                         "core:class:UserStatus",
                         json!({"user": context.account_info.account_uuid }),
                         &FindOptionsBuilder::default().project("online").build(),
                     )
-                    .await
-                    .ok()
-                    .flatten();
-                let user_online_status = if let Some(user_status) = user_status
-                    && user_status["online"].as_bool().unwrap_or(false)
-                {
-                    "Online".to_string()
-                } else {
-                    "Offline".to_string()
+                    .await;
+                let online = user_status.is_some_and(|status| {
+                    status["online"].as_bool().unwrap_or(false)
+                });
+                let user_online_status = match staleness {
+                    Staleness::Live if online => "Online".to_string(),
+                    Staleness::Live => "Offline".to_string(),
+                    Staleness::Cached { age } => format!(
+                        "{} (as of {} ago, link down)",
+                        if online { "Online" } else { "Offline" },
+                        utils::format_duration_short(age),
+                    ),
+                    Staleness::NeverFetched => "Offline (link down, no prior status)".to_string(),
+                };
+                let connection_health = match context.resilient_tx.down_since() {
+                    Some(since) => format!(
+                        "Transactor link down since {} ago",
+                        utils::format_duration_short(chrono::Utc::now() - since)
+                    ),
+                    None => "Transactor link up".to_string(),
                 };
 
                 format!(
-                    "#Boss Current Local Time\n{}\n\n#Boss Online Status\n{user_online_status}\n\n",
+                    "#Boss Current Local Time\n{}\n\n#Boss Online Status\n{user_online_status}\n\n#Connection Health\n{connection_health}\n\n",
                     chrono::Utc::now()
                         .with_timezone(&context.account_info.time_zone)
                         .to_rfc2822(),
@@ -194,9 +215,14 @@ pub async fn create_context(
             })
             .join("\n");
 
+        let deduped_count = context.db_client.deduped_task_count().await.unwrap_or(0);
+
         result_context = result_context.replace(
             "${SCHEDULED_TASKS}",
-            &format!("{}\n{}\n{}", header, system_tasks, scheduled_tasks),
+            &format!(
+                "{}\n{}\n{}\n\n# Task Dedup\n{deduped_count} duplicate task(s) dropped so far.",
+                header, system_tasks, scheduled_tasks
+            ),
         );
     }
 
@@ -207,7 +233,13 @@ pub async fn create_context(
             .await
             .unwrap_or_default()
             .into_iter()
-            .map(|(id, note)| format!("## id: {}\n{}", id, note))
+            .map(|note| {
+                if note.tags.is_empty() {
+                    format!("## id: {}\n{}", note.id, note.content)
+                } else {
+                    format!("## id: {} [{}]\n{}", note.id, note.tags.join(", "), note.content)
+                }
+            })
             .join("\n\n");
         let notes = if notes.is_empty() {
             "No notes found".to_string()
@@ -218,7 +250,10 @@ pub async fn create_context(
     }
 
     if result_context.contains("${MEMORY_ENTRIES}") {
-        let string_context = messages
+        // Only the most recent `retrieval_window` turns feed the embedding query: the whole
+        // conversation dilutes relevance and grows unbounded as the task goes on.
+        let window = config.memory.retrieval_window.min(messages.len());
+        let string_context = messages[messages.len() - window..]
             .iter()
             .map(|m| m.string_context())
             .collect::<Vec<_>>()
@@ -228,15 +263,22 @@ pub async fn create_context(
             .mem_last_entities(MAX_MEMORY_ENTITIES)
             .await
             .unwrap();
+        // Relevant entries are a separate, similarity-ranked channel from the recency-ranked
+        // "Last Active" one above; drop anything already surfaced there instead of repeating it.
         let relevant_entities = context
             .db_client
-            .mem_relevant_entities(
+            .mem_relevant_entities_scored(
                 MAX_MEMORY_ENTITIES,
                 &string_context,
                 MemoryEntityType::Semantic,
+                config.memory.min_similarity,
             )
             .await
-            .unwrap();
+            .unwrap()
+            .into_iter()
+            .map(|(entity, _)| entity)
+            .filter(|entity| !last_used_entities.iter().any(|e| e.id == entity.id))
+            .collect::<Vec<_>>();
 
         result_context = result_context.replace(
             "${MEMORY_ENTRIES}",
@@ -279,6 +321,14 @@ pub async fn create_context(
         );
     }
 
+    if result_context.contains("${WORKERS}") {
+        let workers = context.worker_manager.render_markdown().await;
+        result_context = result_context.replace(
+            "${WORKERS}",
+            &format!("# Background Workers\n{workers}"),
+        );
+    }
+
     if result_context.contains("${FILES}") {
         let mut files: Vec<String> = Vec::default();
         for entry in ignore::WalkBuilder::new(&workspace)
@@ -313,6 +363,445 @@ pub async fn create_context(
     result_context
 }
 
+/// How long a tool dispatch loop waits for an operator to approve or reject an `Execute`-kind
+/// tool call (see `tools::ToolKind`) before treating it as rejected.
+const EXECUTE_APPROVAL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(300);
+const EXECUTE_APPROVAL_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Records `call_id` as a pending action awaiting operator sign-off, then polls for its
+/// disposition (set out-of-band by the HTTP approval endpoint) until it's approved, rejected, or
+/// `EXECUTE_APPROVAL_TIMEOUT` elapses with no answer, in which case it's treated as rejected.
+pub async fn await_execute_approval(
+    db_client: &DbClient,
+    call_id: &str,
+    tool_name: &str,
+    arguments: &serde_json::Value,
+) -> bool {
+    if let Err(e) = db_client
+        .add_pending_action(call_id, tool_name, &arguments.to_string())
+        .await
+    {
+        tracing::error!(?e, call_id, tool_name, "Failed to record pending action");
+        return false;
+    }
+
+    let deadline = tokio::time::Instant::now() + EXECUTE_APPROVAL_TIMEOUT;
+    loop {
+        match db_client.pending_action(call_id).await {
+            Ok(Some(action)) => match action.status {
+                crate::storage::PendingActionStatus::Approved => return true,
+                crate::storage::PendingActionStatus::Rejected => return false,
+                crate::storage::PendingActionStatus::Pending => {}
+            },
+            Ok(None) | Err(_) => return false,
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(EXECUTE_APPROVAL_POLL_INTERVAL).await;
+    }
+}
+
+/// Why `LoopBudget::check` stopped a task loop; carries the configured limit that was exceeded,
+/// for the tracing warning logged alongside the partial result.
+#[derive(Debug)]
+pub enum LoopBudgetExceeded {
+    RoundTrips(u32),
+    MessageChars(usize),
+    Duration(std::time::Duration),
+}
+
+impl std::fmt::Display for LoopBudgetExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RoundTrips(max) => write!(f, "more than {max} provider round-trips"),
+            Self::MessageChars(max) => write!(f, "more than {max} cumulative message characters"),
+            Self::Duration(max) => write!(f, "more than {max:?} wall-clock time"),
+        }
+    }
+}
+
+/// Guards a `<|done|>`-terminated task loop (e.g. `notes_mantainance`) against a model that never
+/// signals completion: caps provider round-trips, cumulative serialized message size, and
+/// wall-clock time, all drawn from `TaskConfig::loop_budget`. Call `check` once per round-trip,
+/// after the round-trip's messages have been pushed.
+pub struct LoopBudget {
+    max_round_trips: Option<u32>,
+    max_message_chars: Option<usize>,
+    max_duration: Option<std::time::Duration>,
+    started_at: tokio::time::Instant,
+    round_trips: u32,
+}
+
+impl LoopBudget {
+    pub fn new(config: Option<&crate::config::LoopBudgetConfig>) -> Self {
+        Self {
+            max_round_trips: config.and_then(|config| config.max_round_trips),
+            max_message_chars: config.and_then(|config| config.max_message_chars),
+            max_duration: config
+                .and_then(|config| config.max_duration_secs)
+                .map(std::time::Duration::from_secs),
+            started_at: tokio::time::Instant::now(),
+            round_trips: 0,
+        }
+    }
+
+    pub fn check(&mut self, messages: &[Message]) -> Option<LoopBudgetExceeded> {
+        self.round_trips += 1;
+        if let Some(max) = self.max_round_trips
+            && self.round_trips > max
+        {
+            return Some(LoopBudgetExceeded::RoundTrips(max));
+        }
+
+        if let Some(max) = self.max_message_chars {
+            let chars: usize = messages
+                .iter()
+                .map(|message| serde_json::to_string(message).map(|s| s.len()).unwrap_or(0))
+                .sum();
+            if chars > max {
+                return Some(LoopBudgetExceeded::MessageChars(max));
+            }
+        }
+
+        if let Some(max) = self.max_duration
+            && self.started_at.elapsed() > max
+        {
+            return Some(LoopBudgetExceeded::Duration(max));
+        }
+
+        None
+    }
+}
+
+/// Result of attempting a single tool call within a batch; `Err` means the task was cancelled
+/// while this call was in flight, which aborts the whole batch (see `dispatch_tool_calls`).
+type ToolCallOutcome = std::result::Result<Vec<ToolResultContent>, ()>;
+
+/// Where `dispatch_tool_calls` forwards `ToolProgress` updates, tagged with the originating tool
+/// call's id. `None` (the default, used by `channel_task`) drops progress on the floor; the
+/// assistant-chat loop passes `Some` to relay it through the typing indicator.
+pub type ToolProgressSink = mpsc::Sender<(String, ToolProgress)>;
+
+/// Base delay for the exponential backoff between tool call retries; attempt `n` (0-indexed)
+/// waits `TOOL_RETRY_BASE_DELAY * 2^n`.
+const TOOL_RETRY_BASE_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+fn tool_call_timeout(config: &Config, tool_name: &str) -> std::time::Duration {
+    std::time::Duration::from_secs(
+        config
+            .tool_timeouts_secs
+            .get(tool_name)
+            .copied()
+            .unwrap_or(config.tool_timeout_secs),
+    )
+}
+
+async fn dispatch_one_tool_call(
+    config: &Config,
+    context: &AgentContext,
+    tools: &ToolMap,
+    cancel_token: &CancellationToken,
+    progress_sink: Option<&ToolProgressSink>,
+    tool_call: &ToolCall,
+    blocking_permits: &Arc<Semaphore>,
+) -> ToolCallOutcome {
+    let Some(tool) = tools.get(&tool_call.function.name) else {
+        return Ok(vec![ToolResultContent::text(format!(
+            "Unknown tool [{}]",
+            tool_call.function.name
+        ))]);
+    };
+    let mut tool = tool.lock().await;
+
+    let cacheable = tool.kind() == ToolKind::Query && tool.is_cacheable();
+    if cacheable {
+        if let Some(cached) = context
+            .tool_result_cache
+            .get(&tool_call.function.name, &tool_call.function.arguments)
+            .await
+        {
+            return Ok(cached);
+        }
+    }
+
+    if tool.kind() == ToolKind::Execute {
+        let approved = match config.execute_policy {
+            ExecutePolicy::AutoApprove => true,
+            ExecutePolicy::Confirm => select! {
+                _ = cancel_token.cancelled() => return Err(()),
+                approved = await_execute_approval(
+                    &context.db_client,
+                    &tool_call.id,
+                    &tool_call.function.name,
+                    &tool_call.function.arguments,
+                ) => approved,
+            },
+            ExecutePolicy::DryRun => {
+                return Ok(vec![ToolResultContent::text(format!(
+                    "[dry run] Would have called [{}] with arguments {}",
+                    tool_call.function.name, tool_call.function.arguments
+                ))]);
+            }
+        };
+        if !approved {
+            return Ok(vec![ToolResultContent::text(format!(
+                "Action [{}] was not approved and was skipped.",
+                tool_call.function.name
+            ))]);
+        }
+    }
+
+    let span = tracing::span!(
+        Level::INFO,
+        "tool_call",
+        call_id = tool_call.id,
+        name = tool_call.function.name
+    );
+    let timeout = tool_call_timeout(config, &tool_call.function.name);
+    let max_retries = config.tool_max_retries;
+    let result = span.in_scope(async || -> ToolCallOutcome {
+        let mut attempt = 0;
+        loop {
+            let (progress_tx, mut progress_rx) = mpsc::channel::<ToolProgress>(8);
+            let forward_progress = progress_sink.cloned().map(|sink| {
+                let call_id = tool_call.id.clone();
+                tokio::spawn(async move {
+                    while let Some(progress) = progress_rx.recv().await {
+                        if sink.send((call_id.clone(), progress)).await.is_err() {
+                            break;
+                        }
+                    }
+                })
+            });
+            let is_blocking = tool.is_blocking();
+            let call_future = tool.call_with_progress(
+                context,
+                tool_call.function.arguments.clone(),
+                progress_tx,
+            );
+            // Blocking tools (`ToolImpl::is_blocking`) run via `block_in_place` under
+            // `blocking_permits` instead of being polled inline, so synchronous CPU-bound or
+            // blocking work (local model calls, heavy file parsing) doesn't stall this worker
+            // thread and delay every other task sharing it. Once `block_in_place` starts, the call
+            // runs to completion on its own thread: `cancel_token`/`timeout` below can still fire
+            // and return early, but they no longer abort the blocking call itself, only stop
+            // waiting on it.
+            let run_call = async {
+                if is_blocking {
+                    let _permit = blocking_permits.acquire().await;
+                    let handle = Handle::current();
+                    tokio::task::block_in_place(move || handle.block_on(call_future))
+                } else {
+                    call_future.await
+                }
+            };
+            let call_result = select! {
+                _ = cancel_token.cancelled() => {
+                    if let Some(handle) = forward_progress {
+                        handle.abort();
+                    }
+                    return Err(());
+                }
+                _ = tokio::time::sleep(timeout) => {
+                    if let Some(handle) = forward_progress {
+                        handle.abort();
+                    }
+                    return Ok(vec![ToolResultContent::text(
+                        subst::substitute(
+                            TOOL_CALL_ERROR,
+                            &HashMap::from([(
+                                "ERROR",
+                                &format!("tool call timed out after {timeout:?}"),
+                            )]),
+                        )
+                        .unwrap(),
+                    )]);
+                }
+                res = run_call => {
+                    if let Some(handle) = forward_progress {
+                        handle.abort();
+                    }
+                    res
+                }
+            };
+            match call_result {
+                Ok(tool_result) => return Ok(tool_result),
+                Err(e) => {
+                    if attempt >= max_retries {
+                        return Ok(vec![ToolResultContent::text(
+                            subst::substitute(
+                                TOOL_CALL_ERROR,
+                                &HashMap::from([("ERROR", &e.to_string())]),
+                            )
+                            .unwrap(),
+                        )]);
+                    }
+                    tracing::warn!(attempt, error = %e, "Tool call failed, retrying");
+                    tokio::time::sleep(TOOL_RETRY_BASE_DELAY * 2u32.pow(attempt)).await;
+                    attempt += 1;
+                }
+            }
+        }
+    })
+    .await;
+
+    if cacheable {
+        if let Ok(tool_result) = &result {
+            context
+                .tool_result_cache
+                .insert(
+                    &tool_call.function.name,
+                    &tool_call.function.arguments,
+                    tool_result.clone(),
+                )
+                .await;
+        }
+    }
+
+    result
+}
+
+/// Runs the `Query`-kind tool calls the model requested in one turn concurrently (bounded by
+/// `max_concurrent`); `Execute`-kind calls (see `tools::ToolKind`) are run serially instead, since
+/// they have side effects the operator may be approving one at a time. Either way, results are
+/// returned in the same order as `tool_calls` so the resulting `Message::tool_call` /
+/// `Message::tool_result` pairs keep conversation integrity (`check_integrity`). Returns `Err(())`
+/// if the task was cancelled while any call was still in flight.
+pub async fn dispatch_tool_calls(
+    config: &Config,
+    context: &AgentContext,
+    tools: &ToolMap,
+    cancel_token: &CancellationToken,
+    max_concurrent: usize,
+    tool_calls: &[ToolCall],
+    progress_sink: Option<&ToolProgressSink>,
+) -> std::result::Result<Vec<Vec<ToolResultContent>>, ()> {
+    let mut kinds = Vec::with_capacity(tool_calls.len());
+    for tool_call in tool_calls {
+        let kind = match tools.get(&tool_call.function.name) {
+            Some(tool) => tool.lock().await.kind(),
+            None => ToolKind::Query,
+        };
+        kinds.push(kind);
+    }
+
+    let mut results: Vec<Option<Vec<ToolResultContent>>> = tool_calls.iter().map(|_| None).collect();
+
+    // Shared across every call in this turn, mirroring `max_concurrent`'s per-turn scope: bounds
+    // how many `ToolImpl::is_blocking` calls may occupy the blocking path at once regardless of
+    // whether they came from the concurrent `Query` batch or the serial `Execute` loop below.
+    let blocking_permits = Arc::new(Semaphore::new(config.max_blocking_tools.max(1)));
+
+    let concurrent: Vec<(usize, &ToolCall)> = tool_calls
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| kinds[*index] == ToolKind::Query)
+        .collect();
+    let concurrent_results = stream::iter(concurrent.iter().map(|(_, tool_call)| *tool_call))
+        .map(|tool_call| {
+            dispatch_one_tool_call(
+                config,
+                context,
+                tools,
+                cancel_token,
+                progress_sink,
+                tool_call,
+                &blocking_permits,
+            )
+        })
+        .buffered(max_concurrent.max(1))
+        .collect::<Vec<_>>()
+        .await;
+    for ((index, _), result) in concurrent.into_iter().zip(concurrent_results) {
+        results[index] = Some(result?);
+    }
+
+    for (index, tool_call) in tool_calls.iter().enumerate() {
+        if kinds[index] == ToolKind::Execute {
+            let result = dispatch_one_tool_call(
+                config,
+                context,
+                tools,
+                cancel_token,
+                progress_sink,
+                tool_call,
+                &blocking_permits,
+            )
+            .await?;
+            results[index] = Some(result);
+        }
+    }
+
+    Ok(results.into_iter().map(|result| result.unwrap()).collect())
+}
+
+/// Converts a provider's reported token usage into an `AgentState::balance` debit using
+/// `Config::model_rates`, or `None` if the current model has no configured rate — callers should
+/// fall back to a flat per-message cost in that case.
+pub fn token_cost(config: &Config, usage: &crate::types::streaming::ResponseUsage) -> Option<u32> {
+    let rate = config.model_rates.get(&config.model)?;
+    let cached_tokens = usage.cached_tokens.min(usage.prompt_tokens);
+    let uncached_prompt_tokens = usage.prompt_tokens - cached_tokens;
+    let cost = uncached_prompt_tokens as f32 * rate.input_cost_per_token
+        + cached_tokens as f32 * rate.cached_input_cost_per_token.unwrap_or(rate.input_cost_per_token)
+        + usage.completion_tokens as f32 * rate.output_cost_per_token;
+    Some(cost.round() as u32)
+}
+
+/// Structured control signals the model embeds in its streamed text, extracted by
+/// `extract_control_tokens`.
+#[derive(Debug, Default, Clone)]
+pub struct ControlTokens {
+    /// The model signalled it's done with the task, via `<attempt_completion>` (left in the text,
+    /// `process_channel_task`'s convention) or `<|done|>` (stripped, `process_assistant_chat_task`'s
+    /// convention).
+    pub done: bool,
+    /// A free-form mood word from a bare `<|<mood>|>` directive, e.g. `<|happy|>`.
+    pub mood: Option<String>,
+    /// An emoji to react with, from `<|reaction:<emoji>|>`, overriding a caller's default reaction.
+    pub reaction: Option<String>,
+    /// A specific message id to target instead of the task's own triggering message, from
+    /// `<|reply_to:<message_id>|>`.
+    pub reply_to: Option<String>,
+}
+
+/// Extracts `ControlTokens` out of `text` and strips the tokens it recognizes (`<|done|>` and
+/// every `<|...|>` directive) so the remaining text is what actually gets persisted/sent.
+/// `<attempt_completion>` is detected but deliberately left in place, since
+/// `memory::process_follow_chat` later parses it back out of the persisted message.
+pub fn extract_control_tokens(text: &mut String) -> ControlTokens {
+    let mut tokens = ControlTokens::default();
+
+    if text.contains("<attempt_completion>") {
+        tokens.done = true;
+    }
+    if text.contains("<|done|>") {
+        tokens.done = true;
+        *text = text.replace("<|done|>", "");
+    }
+
+    let keyed = regex::Regex::new(r"<\|(reaction|reply_to):([^|]+)\|>").unwrap();
+    *text = keyed
+        .replace_all(text, |caps: &regex::Captures| {
+            match &caps[1] {
+                "reaction" => tokens.reaction = Some(caps[2].trim().to_string()),
+                "reply_to" => tokens.reply_to = Some(caps[2].trim().to_string()),
+                _ => unreachable!(),
+            }
+            ""
+        })
+        .to_string();
+
+    let mood = regex::Regex::new(r"<\|([a-zA-Z\s]+)\|>").unwrap();
+    if let Some(caps) = mood.captures(text) {
+        tokens.mood = Some(caps[1].to_string());
+    }
+    *text = mood.replace_all(text, "").trim().to_string();
+
+    tokens
+}
+
 pub fn has_send_message(messages: &[Message]) -> bool {
     messages.iter().any(|m| matches!(m, Message::Assistant{ content }
         if content.iter().any(|c|
@@ -413,35 +902,79 @@ pub async fn migrate_image_content(workspace: &Path, messages: &mut [Message]) -
     migrated
 }
 
-pub fn incoming_tasks_processor(
-    mut task_receiver: mpsc::UnboundedReceiver<Task>,
+/// Routes incoming tasks to the memory worker or the main agent loop, skipping a still-running
+/// task when a new one supersedes it. Registered with `WorkerManager` under id `task_router`,
+/// replacing the old free-standing `incoming_tasks_processor()` task.
+pub struct TaskRouterWorker {
+    task_receiver: mpsc::UnboundedReceiver<Task>,
     memory_task_sender: mpsc::UnboundedSender<Task>,
-    mut db_client: DbClient,
+    db_client: DbClient,
     tx: mpsc::UnboundedSender<Task>,
-) -> JoinHandle<()> {
-    tokio::spawn(async move {
+    prev_task: Task,
+}
+
+impl TaskRouterWorker {
+    /// Loads unfinished tasks from `db_client` and re-queues them onto `tx` before the worker
+    /// starts routing newly incoming tasks.
+    pub async fn new(
+        task_receiver: mpsc::UnboundedReceiver<Task>,
+        memory_task_sender: mpsc::UnboundedSender<Task>,
+        mut db_client: DbClient,
+        tx: mpsc::UnboundedSender<Task>,
+    ) -> Self {
         let mut prev_task = Task::new(TaskKind::MemoryMantainance);
-        // initialy load unfinished tasks from db
         for task in db_client.unfinished_tasks().await {
             let _ = tx.send(task.clone());
             prev_task = task;
         }
-        while let Some(mut task) = task_receiver.recv().await {
-            match task.kind {
-                // for some task kind  we need just route the task
-                TaskKind::MemoryMantainance => {
-                    let _ = memory_task_sender.send(task);
+        Self {
+            task_receiver,
+            memory_task_sender,
+            db_client,
+            tx,
+            prev_task,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl crate::worker::Worker for TaskRouterWorker {
+    fn name(&self) -> &str {
+        "task_router"
+    }
+
+    async fn step(&mut self) -> anyhow::Result<crate::worker::WorkerState> {
+        let Some(mut task) = self.task_receiver.recv().await else {
+            return Ok(crate::worker::WorkerState::Done);
+        };
+        match task.kind {
+            // for some task kind  we need just route the task
+            TaskKind::MemoryMantainance => {
+                let _ = self.memory_task_sender.send(task);
+            }
+            _ => {
+                let fingerprint = task.kind.fingerprint();
+                if self
+                    .db_client
+                    .seen_task_fingerprint(&fingerprint, crate::task::TASK_DEDUP_WINDOW)
+                    .await
+                    .unwrap_or(false)
+                {
+                    tracing::info!(%fingerprint, "Dropping duplicate task");
+                    self.db_client.record_task_fingerprint(&fingerprint).await?;
+                    return Ok(crate::worker::WorkerState::Busy);
                 }
-                _ => {
-                    let id = db_client.add_task(&task).await.unwrap();
-                    task.id = id;
-                    if prev_task.kind.can_skip(&task.kind) {
-                        prev_task.cancel_token.cancel();
-                    }
-                    let _ = tx.send(task.clone());
-                    prev_task = task;
+                self.db_client.record_task_fingerprint(&fingerprint).await?;
+
+                let id = self.db_client.add_task(&task).await?;
+                task.id = id;
+                if self.prev_task.kind.can_skip(&task.kind) {
+                    self.prev_task.cancel_token.cancel();
                 }
+                let _ = self.tx.send(task.clone());
+                self.prev_task = task;
             }
         }
-    })
+        Ok(crate::worker::WorkerState::Busy)
+    }
 }
@@ -0,0 +1,217 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+use std::{fs, path::Path, sync::Arc, time::Instant};
+
+use anyhow::{Context as _, Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    agent::Agent,
+    config::{Config, ProviderProfile},
+    context::AgentContext,
+    task::{JobOutcome, Task, TaskKind},
+};
+
+/// One workload task to dispatch, tagged the same way `task::TaskKind`'s dispatchable variants are
+/// named. `expected_tool_calls` is an optional, purely informational list of tool names the task is
+/// expected to call — checked against the `tool_calls` count the run actually reports.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WorkloadTask {
+    Sleep {
+        #[serde(default)]
+        expected_tool_calls: Vec<String>,
+    },
+    FollowChat {
+        card_id: String,
+        card_title: String,
+        message_id: String,
+        content: String,
+        #[serde(default)]
+        expected_tool_calls: Vec<String>,
+    },
+    AssistantChat {
+        card_id: String,
+        message_id: String,
+        content: String,
+        #[serde(default)]
+        expected_tool_calls: Vec<String>,
+    },
+    AssistantTask {
+        sheduled_task_id: i64,
+        content: String,
+        #[serde(default)]
+        expected_tool_calls: Vec<String>,
+    },
+}
+
+impl WorkloadTask {
+    fn expected_tool_calls(&self) -> &[String] {
+        match self {
+            WorkloadTask::Sleep { expected_tool_calls }
+            | WorkloadTask::FollowChat { expected_tool_calls, .. }
+            | WorkloadTask::AssistantChat { expected_tool_calls, .. }
+            | WorkloadTask::AssistantTask { expected_tool_calls, .. } => expected_tool_calls,
+        }
+    }
+
+    fn into_task_kind(self) -> TaskKind {
+        match self {
+            WorkloadTask::Sleep { .. } => TaskKind::Sleep,
+            WorkloadTask::FollowChat { card_id, card_title, message_id, content, .. } => {
+                TaskKind::FollowChat { card_id, card_title, message_id, content }
+            }
+            WorkloadTask::AssistantChat { card_id, message_id, content, .. } => {
+                TaskKind::AssistantChat { card_id, message_id, content }
+            }
+            WorkloadTask::AssistantTask { sheduled_task_id, content, .. } => {
+                TaskKind::AssistantTask { sheduled_task_id, content }
+            }
+        }
+    }
+}
+
+/// A named, ordered list of tasks to run against the agent, deserialized from the `--benchmark`
+/// JSON file. `provider`, if set, overrides `Config::provider`/`model`/`provider_api_key` for this
+/// run only — e.g. to pin `config::ProviderKind::Recorded` for a deterministic CI run.
+#[derive(Debug, Deserialize)]
+pub struct WorkloadFile {
+    pub name: String,
+    #[serde(default)]
+    pub provider: Option<ProviderProfile>,
+    /// Optional webhook the finished `BenchmarkReport` is POSTed to, in addition to being returned.
+    #[serde(default)]
+    pub results_url: Option<String>,
+    pub tasks: Vec<WorkloadTask>,
+}
+
+/// Per-task outcome in a `BenchmarkReport`.
+#[derive(Debug, Serialize)]
+pub struct TaskReport {
+    pub index: usize,
+    pub task_kind: String,
+    pub status: &'static str,
+    pub error: Option<String>,
+    pub wall_clock_ms: u128,
+    pub tool_calls: u32,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// `None` when the task set no `expected_tool_calls`. Otherwise whether `tool_calls` reached
+    /// the expected count — a lower-bound check, since `JobOutcome::metrics` only counts calls
+    /// rather than naming them.
+    pub expected_tool_calls_met: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BenchmarkReport {
+    pub name: String,
+    pub total_wall_clock_ms: u128,
+    pub tasks: Vec<TaskReport>,
+}
+
+/// Runs every task in `workload_path` against a fresh `Agent` built from `config` (overridden by
+/// the workload's own `provider`, if any), one at a time in the order given, and returns a report
+/// of how each fared. Used by `--benchmark` in place of the normal service bootstrap, so CI can
+/// drive the agent through a fixed scenario without a live chat session.
+pub async fn run_workload(
+    config: &Config,
+    context: AgentContext,
+    workload_path: &Path,
+) -> Result<BenchmarkReport> {
+    let data = fs::read_to_string(workload_path)
+        .with_context(|| format!("Failed to read workload file {}", workload_path.display()))?;
+    let workload: WorkloadFile = serde_json::from_str(&data)
+        .with_context(|| format!("Malformed workload file {}", workload_path.display()))?;
+
+    let mut config = config.clone();
+    if let Some(profile) = &workload.provider {
+        config.provider = profile.provider.clone();
+        config.model = profile.model.clone();
+        config.provider_api_key = profile.api_key.clone();
+    }
+
+    let agent = Arc::new(Agent::new(config)?);
+
+    let (task_sender, task_receiver) = tokio::sync::mpsc::unbounded_channel::<Task>();
+    let (memory_task_sender, mut memory_task_receiver) =
+        tokio::sync::mpsc::unbounded_channel::<Task>();
+    let (job_outcome_sender, mut job_outcome_receiver) =
+        tokio::sync::mpsc::unbounded_channel::<JobOutcome>();
+
+    // `Agent::run` hands finished tasks to `memory_task_sender`; the benchmark harness has no
+    // memory-maintenance worker to receive them, so drain it to keep the channel from piling up.
+    tokio::spawn(async move { while memory_task_receiver.recv().await.is_some() {} });
+
+    let run_agent = agent.clone();
+    let run_handle = tokio::spawn(async move {
+        run_agent
+            .run(task_receiver, memory_task_sender, job_outcome_sender, context)
+            .await
+    });
+
+    let total_start = Instant::now();
+    let mut reports = Vec::with_capacity(workload.tasks.len());
+    for (index, workload_task) in workload.tasks.into_iter().enumerate() {
+        let expected_tool_calls = workload_task.expected_tool_calls().len();
+        let task_kind = workload_task.into_task_kind();
+        let task_kind_name = task_kind.to_string();
+        let mut task = Task::new(task_kind);
+        let job_id = format!("bench:{index}");
+        task.job_id = Some(job_id.clone());
+
+        let start = Instant::now();
+        task_sender
+            .send(task)
+            .map_err(|_| anyhow!("Agent stopped accepting tasks"))?;
+
+        let outcome = loop {
+            let outcome = job_outcome_receiver
+                .recv()
+                .await
+                .ok_or_else(|| anyhow!("Agent terminated before reporting task {index}"))?;
+            if outcome.job_id == job_id {
+                break outcome;
+            }
+        };
+        let wall_clock_ms = start.elapsed().as_millis();
+
+        let expected_tool_calls_met = if expected_tool_calls == 0 {
+            None
+        } else {
+            Some(outcome.metrics.tool_calls as usize >= expected_tool_calls)
+        };
+
+        reports.push(TaskReport {
+            index,
+            task_kind: task_kind_name,
+            status: if outcome.error.is_none() { "completed" } else { "failed" },
+            error: outcome.error,
+            wall_clock_ms,
+            tool_calls: outcome.metrics.tool_calls,
+            prompt_tokens: outcome.metrics.prompt_tokens,
+            completion_tokens: outcome.metrics.completion_tokens,
+            expected_tool_calls_met,
+        });
+    }
+
+    drop(task_sender);
+    agent.shutdown();
+    if let Err(err) = run_handle.await.context("Agent task panicked")? {
+        tracing::warn!(?err, "Agent run returned an error after benchmark workload finished");
+    }
+
+    let report = BenchmarkReport {
+        name: workload.name,
+        total_wall_clock_ms: total_start.elapsed().as_millis(),
+        tasks: reports,
+    };
+
+    if let Some(results_url) = &workload.results_url {
+        let client = reqwest::Client::new();
+        if let Err(err) = client.post(results_url).json(&report).send().await {
+            tracing::warn!(?err, results_url, "Failed to post benchmark report");
+        }
+    }
+
+    Ok(report)
+}
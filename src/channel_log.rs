@@ -107,6 +107,7 @@ impl HulyChannelLogWriter {
                                     attachements.push(img.clone())
                                 }
                             }
+                            ToolResultContent::Video(_) => msg.push_str("\n[video]"),
                         });
                     }
                     UserContent::Image(img) => attachements.push(img.clone()),
@@ -123,6 +124,7 @@ impl HulyChannelLogWriter {
                         AssistantContent::ToolCall(ToolCall { function, .. }) => {
                             format!("⚙️ {}", format_tool_function(function))
                         }
+                        AssistantContent::Reasoning(_) => String::new(),
                     })
                     .collect::<Vec<_>>()
                     .join("\n\n");
@@ -160,11 +162,7 @@ pub async fn run_channel_log_worker(
                     .media_type
                     .unwrap_or(crate::types::ImageMediaType::PNG)
                     .to_mime_type();
-                if blob_client
-                    .upload_file(&blob_id, mime_type, content)
-                    .await
-                    .is_ok()
-                {
+                if let Ok(blob_id) = blob_client.upload_file(&blob_id, mime_type, content).await {
                     let attachement_event = BlobPatchEventBuilder::default()
                         .card_id(&card_id)
                         .message_id(&message_id)
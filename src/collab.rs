@@ -0,0 +1,110 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! CRDT op log backing a card's assistant conversation (`storage::StateStore::append_message_ops`
+//! / `message_ops_since`), so two replicas editing the same card concurrently merge instead of
+//! one clobbering the other's writes with a whole-array overwrite.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::Message;
+
+/// Globally unique id for one op: a replica's own monotonic Lamport clock, tie-broken by
+/// `replica_id` so the total order below is deterministic across replicas applying ops in any
+/// delivery order.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MessageOpId {
+    pub replica_id: String,
+    pub clock: u64,
+}
+
+impl PartialOrd for MessageOpId {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MessageOpId {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.clock
+            .cmp(&other.clock)
+            .then_with(|| self.replica_id.cmp(&other.replica_id))
+    }
+}
+
+/// One entry in a card's append-only op log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MessageOp {
+    /// Inserts `message`, recording `after` as its predecessor (`None` for the tail). Each insert
+    /// has its own id, so concurrent inserts after the same predecessor both survive instead of
+    /// one overwriting the other's slot. `materialize` places inserts by `MessageOpId` alone and
+    /// does not consult `after`.
+    Insert {
+        id: MessageOpId,
+        after: Option<MessageOpId>,
+        role: String,
+        message: Message,
+    },
+    /// Tombstones the inserted message with the same id. The tombstone hides that insert even
+    /// when it is merged before the insert itself arrives.
+    Remove { id: MessageOpId },
+}
+
+impl MessageOp {
+    pub fn id(&self) -> &MessageOpId {
+        match self {
+            MessageOp::Insert { id, .. } | MessageOp::Remove { id } => id,
+        }
+    }
+}
+
+/// Highest clock seen per replica for a card. A reconnecting client sends its own vector back
+/// via `ops_since` to receive only the ops it's missing.
+pub type VersionVector = HashMap<String, u64>;
+
+/// Bumps `replica_id`'s own component (the only one a replica is ever allowed to advance) and
+/// returns the new clock value to stamp the op with.
+pub fn bump(vector: &mut VersionVector, replica_id: &str) -> u64 {
+    let clock = vector.entry(replica_id.to_string()).or_insert(0);
+    *clock += 1;
+    *clock
+}
+
+/// Replays a full (or partial, already-merged) op log into the ordered, live message list: every
+/// `Insert` not covered by a `Remove` with the same id, in the deterministic total order of
+/// `MessageOpId` (clock, then replica id); `after` is not consulted. Then applies `max_messages` as a
+/// tombstone-aware truncation of the oldest live prefix, so the cap reads as "the oldest messages
+/// were removed", not "history was rewritten".
+pub fn materialize(ops: &[MessageOp], max_messages: usize) -> Vec<Message> {
+    let mut removed: std::collections::HashSet<&MessageOpId> = std::collections::HashSet::new();
+    let mut inserts: Vec<(&MessageOpId, Option<&MessageOpId>, &Message)> = Vec::new();
+    for op in ops {
+        match op {
+            MessageOp::Remove { id } => {
+                removed.insert(id);
+            }
+            MessageOp::Insert {
+                id,
+                after,
+                message,
+                ..
+            } => inserts.push((id, after.as_ref(), message)),
+        }
+    }
+
+    // Order by id alone. Whenever `after` points at an insert with a lower clock, this is also
+    // a valid topological order of the `after` links.
+    inserts.sort_by_key(|(id, _, _)| (*id).clone());
+
+    inserts
+        .into_iter()
+        .filter(|(id, _, _)| !removed.contains(id))
+        .map(|(_, _, message)| message.clone())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .take(max_messages)
+        .rev()
+        .collect()
+}
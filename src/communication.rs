@@ -2,13 +2,19 @@
 
 use hulyrs::services::transactor::{TransactorClient, backend::http::HttpBackend};
 use serde::Serialize;
+use tokio_util::sync::CancellationToken;
 
 use crate::{config::Config, context::HulyAccountInfo, huly::ServerConfig};
 
+#[cfg(feature = "streaming")]
+pub mod event_sink;
 pub mod http;
 #[cfg(feature = "streaming")]
 mod streaming;
 pub mod types;
+pub mod ws;
+
+pub use ws::OutboundHub;
 
 #[derive(Debug, Serialize)]
 pub struct ScheduledTask {
@@ -28,8 +34,9 @@ pub async fn streaming_worker(
     server_config: &ServerConfig,
     account_info: HulyAccountInfo,
     tx_client: TransactorClient<HttpBackend>,
+    shutdown: CancellationToken,
 ) {
-    streaming::streaming_worker(config, server_config, account_info, tx_client).await
+    streaming::streaming_worker(config, server_config, account_info, tx_client, shutdown).await
 }
 
 #[cfg(not(feature = "streaming"))]
@@ -38,6 +45,7 @@ pub async fn streaming_worker(
     _server_config: &ServerConfig,
     _account_info: HulyAccountInfo,
     _tx_client: TransactorClient<HttpBackend>,
+    _shutdown: CancellationToken,
 ) {
     std::future::pending().await
 }
@@ -0,0 +1,199 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, atomic::AtomicBool},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures::{SinkExt, StreamExt};
+use secrecy::{ExposeSecret, SecretString};
+use serde_json::json;
+use streaming::types::CommunicationEvent;
+use tokio::{net::TcpStream, sync::Mutex};
+use tokio_tungstenite::{
+    MaybeTlsStream, WebSocketStream,
+    tungstenite::{client::IntoClientRequest, http::HeaderValue},
+};
+
+use crate::config::EventSinkConfig;
+
+/// Delivers a single `CommunicationEvent` to `recipient` over whatever transport
+/// `communication::streaming::streaming_worker` was configured with. Selected via
+/// `Config::event_sink`; see `HttpEventSink` / `WebSocketEventSink`. Retry/backoff/dead-lettering
+/// around a failed `deliver` is the caller's responsibility, not the sink's.
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    async fn deliver(&self, recipient: &str, event: &CommunicationEvent) -> Result<()>;
+}
+
+pub fn build_event_sink(config: &EventSinkConfig) -> Arc<dyn EventSink> {
+    match config {
+        EventSinkConfig::Http {
+            url,
+            headers,
+            auth_token,
+        } => Arc::new(HttpEventSink::new(
+            url.clone(),
+            headers.clone(),
+            auth_token.clone(),
+        )),
+        EventSinkConfig::WebSocket { url, auth_token } => {
+            Arc::new(WebSocketEventSink::new(url.clone(), auth_token.clone()))
+        }
+    }
+}
+
+/// Delivers each event with its own HTTP request. `reqwest::Client` pools connections internally,
+/// so building one in `new` and reusing it is already "one connection, many requests" as far as
+/// the OS socket is concerned — only `WebSocketEventSink` needs an explicit long-lived connection.
+pub struct HttpEventSink {
+    client: reqwest::Client,
+    url: String,
+    headers: HashMap<String, String>,
+    auth_token: Option<SecretString>,
+}
+
+impl HttpEventSink {
+    pub fn new(url: String, headers: HashMap<String, String>, auth_token: Option<SecretString>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            headers,
+            auth_token,
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for HttpEventSink {
+    async fn deliver(&self, recipient: &str, event: &CommunicationEvent) -> Result<()> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .json(&json!({"recipient": recipient, "event": event}));
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        if let Some(auth_token) = &self.auth_token {
+            request = request.bearer_auth(auth_token.expose_secret());
+        }
+
+        let response = request.send().await.context("Failed to send event")?;
+        if !response.status().is_success() {
+            anyhow::bail!("Delivery failed with status {}", response.status());
+        }
+        Ok(())
+    }
+}
+
+type WsSink = futures::stream::SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, tokio_tungstenite::tungstenite::Message>;
+
+const RECONNECT_BASE_BACKOFF: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Multiplexes every recipient's events over one long-lived websocket connection instead of
+/// opening a fresh one per message, reconnecting with exponential backoff and jitter when the
+/// upstream drops (mirroring `tools::browser::browser_client::BrowserClient`'s supervised
+/// connection, minus the request/response correlation this one-way sink doesn't need).
+pub struct WebSocketEventSink {
+    url: String,
+    auth_token: Option<SecretString>,
+    sink: Arc<Mutex<Option<WsSink>>>,
+    supervisor_started: AtomicBool,
+}
+
+impl WebSocketEventSink {
+    pub fn new(url: String, auth_token: Option<SecretString>) -> Self {
+        Self {
+            url,
+            auth_token,
+            sink: Arc::new(Mutex::new(None)),
+            supervisor_started: AtomicBool::new(false),
+        }
+    }
+
+    fn lazy_init(&self) {
+        if !self
+            .supervisor_started
+            .swap(true, std::sync::atomic::Ordering::SeqCst)
+        {
+            tokio::spawn(Self::run_connection_loop(
+                self.url.clone(),
+                self.auth_token.clone(),
+                Arc::clone(&self.sink),
+            ));
+        }
+    }
+
+    async fn run_connection_loop(
+        url: String,
+        auth_token: Option<SecretString>,
+        sink: Arc<Mutex<Option<WsSink>>>,
+    ) {
+        let mut backoff = RECONNECT_BASE_BACKOFF;
+        loop {
+            match Self::connect(&url, auth_token.as_ref()).await {
+                Ok(stream) => {
+                    backoff = RECONNECT_BASE_BACKOFF;
+                    let (ws_tx, mut ws_rx) = stream.split();
+                    *sink.lock().await = Some(ws_tx);
+                    tracing::info!(%url, "Connected to event sink websocket");
+
+                    while let Some(result) = ws_rx.next().await {
+                        if let Err(err) = result {
+                            tracing::warn!(%err, "Event sink websocket error");
+                            break;
+                        }
+                    }
+
+                    tracing::warn!("Event sink websocket connection lost, reconnecting");
+                    *sink.lock().await = None;
+                }
+                Err(err) => {
+                    tracing::error!(%err, %url, "Failed to connect to event sink websocket");
+                }
+            }
+
+            let jitter = Duration::from_millis(rand::random::<u64>() % 250);
+            tokio::time::sleep(backoff + jitter).await;
+            backoff = (backoff * 2).min(RECONNECT_MAX_BACKOFF);
+        }
+    }
+
+    /// Builds the upgrade request (adding a bearer `Authorization` header when `auth_token` is
+    /// set) and connects, returning just the stream half since the handshake response carries
+    /// nothing this sink needs.
+    async fn connect(
+        url: &str,
+        auth_token: Option<&SecretString>,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+        let mut request = url.into_client_request()?;
+        if let Some(auth_token) = auth_token {
+            request.headers_mut().insert(
+                "Authorization",
+                HeaderValue::from_str(&format!("Bearer {}", auth_token.expose_secret()))?,
+            );
+        }
+        let (stream, _) = tokio_tungstenite::connect_async(request).await?;
+        Ok(stream)
+    }
+}
+
+#[async_trait]
+impl EventSink for WebSocketEventSink {
+    async fn deliver(&self, recipient: &str, event: &CommunicationEvent) -> Result<()> {
+        self.lazy_init();
+
+        let payload = serde_json::to_string(&json!({"recipient": recipient, "event": event}))?;
+        let mut guard = self.sink.lock().await;
+        let Some(sink) = guard.as_mut() else {
+            anyhow::bail!("Event sink websocket not connected");
+        };
+        sink.send(tokio_tungstenite::tungstenite::Message::Text(payload.into()))
+            .await
+            .context("Failed to send event over websocket")
+    }
+}
@@ -1,14 +1,22 @@
 // Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
 
+use std::sync::{
+    Arc,
+    atomic::{AtomicI64, Ordering},
+};
+
 use actix_cors::Cors;
 use actix_web::{App, HttpResponse, HttpServer, dev::ServerHandle, middleware, web};
 use anyhow::Result;
+use serde::Serialize;
 use tokio::{sync::mpsc, task::JoinHandle};
 
 use crate::{
-    communication::{AgentState, ScheduledTask, types::CommunicationEvent},
+    communication::{AgentState, OutboundHub, ScheduledTask, types::CommunicationEvent, ws},
     config::Config,
     database::DbClient,
+    storage::PendingActionStatus,
+    task_manager::TaskManager,
 };
 
 pub fn server(
@@ -16,6 +24,10 @@ pub fn server(
     sender: mpsc::UnboundedSender<CommunicationEvent>,
     db_client: DbClient,
     activity_sender: mpsc::UnboundedSender<()>,
+    outbound_hub: Arc<OutboundHub>,
+    task_manager: Arc<TaskManager>,
+    remote_worker_registry: Arc<ws::RemoteWorkerRegistry>,
+    scheduler_last_tick: Arc<AtomicI64>,
 ) -> Result<(JoinHandle<Result<(), std::io::Error>>, ServerHandle)> {
     let socket = std::net::SocketAddr::new(
         config.http_api.bind_host.as_str().parse()?,
@@ -36,10 +48,27 @@ pub fn server(
             .app_data(web::Data::new(sender.clone()))
             .app_data(web::Data::new(db_client.clone()))
             .app_data(web::Data::new(activity_sender.clone()))
+            .app_data(web::Data::from(outbound_hub.clone()))
+            .app_data(web::Data::from(task_manager.clone()))
+            .app_data(web::Data::from(remote_worker_registry.clone()))
+            .app_data(web::Data::from(scheduler_last_tick.clone()))
             .wrap(middleware::Logger::default())
             .wrap(cors)
             .route("/event", web::post().to(post_event))
+            .route("/ws", web::get().to(ws::ws_route))
+            .route("/ws/worker", web::get().to(ws::worker_route))
             .route("/state", web::get().to(state))
+            .route("/metrics", web::get().to(metrics))
+            .route(
+                "/pending-actions/{call_id}/approve",
+                web::post().to(approve_pending_action),
+            )
+            .route(
+                "/pending-actions/{call_id}/reject",
+                web::post().to(reject_pending_action),
+            )
+            .route("/tasks", web::get().to(list_tasks))
+            .route("/tasks/{id}/cancel", web::post().to(cancel_task))
             .route(
                 "/status",
                 web::get().to(async || {
@@ -75,6 +104,32 @@ async fn post_event(
     Ok(HttpResponse::Ok().finish())
 }
 
+/// Approves a pending `Execute`-tool call (see `tools::ToolKind`), letting the task loop that
+/// recorded it (polling via `agent::utils::await_execute_approval`) proceed with the dispatch.
+async fn approve_pending_action(
+    db_client: web::Data<DbClient>,
+    call_id: web::Path<String>,
+) -> Result<HttpResponse, actix_web::Error> {
+    db_client
+        .set_pending_action_status(&call_id, PendingActionStatus::Approved)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Rejects a pending `Execute`-tool call; the task loop skips it and reports the rejection back
+/// to the model instead of dispatching it.
+async fn reject_pending_action(
+    db_client: web::Data<DbClient>,
+    call_id: web::Path<String>,
+) -> Result<HttpResponse, actix_web::Error> {
+    db_client
+        .set_pending_action_status(&call_id, PendingActionStatus::Rejected)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().finish())
+}
+
 async fn state(db_client: web::Data<DbClient>) -> Result<HttpResponse, actix_web::Error> {
     let db_client = db_client.into_inner();
     let has_unfinished_tasks = !db_client.unfinished_tasks().await.is_empty();
@@ -91,3 +146,101 @@ async fn state(db_client: web::Data<DbClient>) -> Result<HttpResponse, actix_web
         next_scheduled,
     }))
 }
+
+/// Aggregate runtime health, reusing the same data sources as `/state` and `/tasks` but shaped
+/// for an operator to spot a wedged scheduler loop (`scheduler_last_tick_secs_ago` growing
+/// unbounded) or a provider stream that never completes (`in_flight_tasks` stuck non-zero).
+#[derive(Serialize)]
+struct MetricsResponse {
+    in_flight_tasks: usize,
+    scheduler_last_tick_secs_ago: i64,
+    next_scheduled: Option<ScheduledTask>,
+}
+
+async fn metrics(
+    db_client: web::Data<DbClient>,
+    task_manager: web::Data<TaskManager>,
+    scheduler_last_tick: web::Data<AtomicI64>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let in_flight_tasks = task_manager
+        .list()
+        .await
+        .into_iter()
+        .filter(|status| status.state == crate::task_manager::TaskLiveState::Started)
+        .count();
+
+    let upcoming_jobs = db_client.get_scheduler().await.ok().unwrap_or_default();
+    let next_scheduled = upcoming_jobs
+        .into_iter()
+        .min_by(|x, y| x.1.cmp(&y.1))
+        .map(|item| ScheduledTask {
+            task_kind: item.0,
+            schedule: item.1,
+        });
+
+    let last_tick_millis = scheduler_last_tick.load(Ordering::Relaxed);
+    let scheduler_last_tick_secs_ago =
+        chrono::Utc::now().timestamp_millis().saturating_sub(last_tick_millis) / 1000;
+
+    Ok(HttpResponse::Ok().json(MetricsResponse {
+        in_flight_tasks,
+        scheduler_last_tick_secs_ago,
+        next_scheduled,
+    }))
+}
+
+/// A single `TaskStatus` as returned by `GET /tasks`.
+#[derive(Serialize)]
+struct TaskStatusResponse {
+    id: i64,
+    kind: String,
+    state: &'static str,
+    created_at: chrono::DateTime<chrono::Utc>,
+    elapsed_secs: i64,
+    last_error: Option<String>,
+}
+
+impl From<crate::task_manager::TaskStatus> for TaskStatusResponse {
+    fn from(status: crate::task_manager::TaskStatus) -> Self {
+        let state = match status.state {
+            crate::task_manager::TaskLiveState::Started => "started",
+            crate::task_manager::TaskLiveState::Idle => "idle",
+            crate::task_manager::TaskLiveState::Completed => "completed",
+            crate::task_manager::TaskLiveState::Cancelled => "cancelled",
+            crate::task_manager::TaskLiveState::Dead => "dead",
+        };
+        Self {
+            id: status.id,
+            kind: status.kind,
+            state,
+            created_at: status.created_at,
+            elapsed_secs: (chrono::Utc::now() - status.created_at).num_seconds(),
+            last_error: status.last_error,
+        }
+    }
+}
+
+/// Lists every task currently tracked by `TaskManager`, giving operators visibility into what's
+/// running, idle, or wedged (`Dead`) without having to dig through logs.
+async fn list_tasks(task_manager: web::Data<TaskManager>) -> Result<HttpResponse, actix_web::Error> {
+    let statuses = task_manager
+        .list()
+        .await
+        .into_iter()
+        .map(TaskStatusResponse::from)
+        .collect::<Vec<_>>();
+    Ok(HttpResponse::Ok().json(statuses))
+}
+
+/// Fires the `CancellationToken` of the task `id`, letting an operator unstick a wedged
+/// `FollowChat` or `AssistantTask` without restarting the whole agent.
+async fn cancel_task(
+    task_manager: web::Data<TaskManager>,
+    id: web::Path<i64>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if task_manager.cancel(id.into_inner()).await {
+        Ok(HttpResponse::Ok().finish())
+    } else {
+        Ok(HttpResponse::NotFound().finish())
+    }
+}
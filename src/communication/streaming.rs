@@ -9,47 +9,160 @@ use hulyrs::services::{
     },
 };
 use serde_json::json;
-use std::collections::HashSet;
+use std::{collections::HashSet, sync::Arc, time::Duration};
 use streaming::{AgentInfo, types::CommunicationEvent};
-use tokio::{select, sync::mpsc};
+use tokio::{io::AsyncWriteExt, select, sync::mpsc};
+use tokio_util::sync::CancellationToken;
 
 use crate::{
-    config::Config,
+    communication::event_sink::{EventSink, build_event_sink},
+    config::{Config, EventDeliveryConfig},
     context::HulyAccountInfo,
     huly::{ServerConfig, types::CommunicationDirect},
 };
 
-async fn event_to_http_processor(
-    social_id: String,
+/// Backoff bounds for reconnecting `streaming::worker` after it returns (Kafka disconnect, stream
+/// error, etc.) — same shape as `WebSocketEventSink`'s reconnect backoff.
+const KAFKA_RECONNECT_BASE_BACKOFF: Duration = Duration::from_millis(500);
+const KAFKA_RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+fn kafka_reconnect_backoff(attempt: u32) -> Duration {
+    let exponential = KAFKA_RECONNECT_BASE_BACKOFF.saturating_mul(1 << attempt.min(6));
+    let jitter = Duration::from_millis((rand::random::<f64>() * 250.0) as u64);
+    exponential.min(KAFKA_RECONNECT_MAX_BACKOFF) + jitter
+}
+
+/// Appends an undeliverable `(recipient, event)` pair to `path` as a JSON line, for later replay.
+/// Failures to write the dead-letter file itself are only logged — there's nothing further to fall
+/// back to.
+async fn dead_letter(path: &std::path::Path, recipient: &str, event: &CommunicationEvent) {
+    let line = match serde_json::to_string(&json!({"recipient": recipient, "event": event})) {
+        Ok(line) => line,
+        Err(err) => {
+            tracing::error!(%err, "Failed to serialize event for dead letter queue, dropping");
+            return;
+        }
+    };
+
+    let result = async {
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        file.write_all(b"\n").await
+    }
+    .await;
+
+    if let Err(err) = result {
+        tracing::error!(%err, path = %path.display(), "Failed to write event to dead letter queue, dropping");
+    }
+}
+
+/// Delivers `event` to `recipient` through `sink`, retrying any failure with exponential backoff
+/// and jitter (`EventDeliveryConfig::backoff`) up to `max_attempts`. On final failure, the caller
+/// is expected to dead-letter the event.
+async fn deliver_with_retry(
+    sink: &dyn EventSink,
+    config: &EventDeliveryConfig,
+    recipient: &str,
+    event: &CommunicationEvent,
+) -> anyhow::Result<()> {
+    let mut attempt = 0;
+    loop {
+        let error = match sink.deliver(recipient, event).await {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+
+        attempt += 1;
+        if attempt >= config.max_attempts {
+            return Err(error);
+        }
+
+        let delay = config.backoff(attempt - 1);
+        tracing::warn!(%error, attempt, delay_ms = delay.as_millis() as u64, "Event delivery failed, retrying");
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Delivers `event` once per recipient this agent is responsible for — `own_recipients` is the
+/// union of `social_id` (direct messages) and `AgentInfo.persistent_cards` (group/broadcast
+/// cards) — instead of bailing on anything but a single matching recipient. A failed delivery to
+/// one recipient is dead-lettered independently and does not stop delivery to the others.
+async fn event_delivery_processor(
+    own_recipients: HashSet<String>,
+    sink: Arc<dyn EventSink>,
+    config: EventDeliveryConfig,
     mut messages_receiver: mpsc::UnboundedReceiver<(
         HashSet<std::string::String>,
         CommunicationEvent,
     )>,
 ) {
-    let http_client = reqwest::Client::new();
-
     while let Some((recipients, event)) = messages_receiver.recv().await {
-        let recipients = recipients.into_iter().collect::<Vec<_>>();
         if recipients.is_empty() {
             tracing::warn!("Empty recipients");
             continue;
         }
-        if recipients.len() > 1 {
-            tracing::warn!("Multiple recipients");
-            continue;
-        }
 
-        if !recipients.contains(&social_id) {
-            tracing::warn!("Incorrect recipient");
-            continue;
+        for recipient in recipients.intersection(&own_recipients) {
+            if let Err(err) = deliver_with_retry(sink.as_ref(), &config, recipient, &event).await {
+                tracing::error!(%err, recipient, attempts = config.max_attempts, "Giving up on event delivery, moving to dead letter queue");
+                dead_letter(&config.dead_letter_path, recipient, &event).await;
+            }
         }
+    }
+}
+
+/// Runs `streaming::worker` in a loop, reconnecting with exponential backoff (see
+/// `kafka_reconnect_backoff`) whenever it returns, until `shutdown` is cancelled. Each attempt
+/// gets its own `AgentInfo` channel, since `streaming::worker` consumes its receiver.
+async fn run_kafka_worker(
+    config: &Config,
+    server_config: &ServerConfig,
+    account_info: &HulyAccountInfo,
+    tx_client: &TransactorClient<HttpBackend>,
+    direct_cards: &HashSet<String>,
+    comm_messages_sender: &mpsc::UnboundedSender<(HashSet<String>, CommunicationEvent)>,
+    shutdown: &CancellationToken,
+) {
+    let kafka_config = serde_json::from_value(config.huly.kafka.clone()).unwrap();
+    let mut attempt: u32 = 0;
 
-        http_client
-            .post("http://localhost:8081/event")
-            .json(&event)
-            .send()
-            .await
+    loop {
+        let (agent_info_tx, agent_info_rx) = mpsc::unbounded_channel();
+        agent_info_tx
+            .send(vec![AgentInfo {
+                workspace_uuid: account_info.workspace,
+                account_uuid: account_info.account_uuid,
+                social_id: account_info.social_id.clone(),
+                persistent_cards: direct_cards.clone(),
+                tx_client: tx_client.clone(),
+            }])
             .unwrap();
+
+        streaming::worker(
+            &kafka_config,
+            agent_info_rx,
+            comm_messages_sender.clone(),
+            &server_config.files_url,
+            config.log_level,
+        )
+        .await;
+
+        if shutdown.is_cancelled() {
+            return;
+        }
+
+        let delay = kafka_reconnect_backoff(attempt);
+        attempt = attempt.saturating_add(1);
+        tracing::warn!(attempt, delay_ms = delay.as_millis() as u64, "Kafka streaming worker stopped, reconnecting");
+
+        select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = shutdown.cancelled() => return,
+        }
     }
 }
 
@@ -58,8 +171,8 @@ pub async fn streaming_worker(
     server_config: &ServerConfig,
     account_info: HulyAccountInfo,
     tx_client: TransactorClient<HttpBackend>,
+    shutdown: CancellationToken,
 ) {
-    let (agent_info_tx, agent_info_rx) = mpsc::unbounded_channel();
     let (comm_messages_sender, comm_messages_receiver) = mpsc::unbounded_channel();
 
     let direct_cards = tx_client
@@ -75,27 +188,37 @@ pub async fn streaming_worker(
         .map(|card| card["_id"].as_str().unwrap().to_string())
         .collect::<HashSet<String>>();
 
-    agent_info_tx
-        .send(vec![AgentInfo {
-            workspace_uuid: account_info.workspace,
-            account_uuid: account_info.account_uuid,
-            social_id: account_info.social_id.clone(),
-            persistent_cards: direct_cards,
-            tx_client,
-        }])
-        .unwrap();
+    let mut own_recipients = direct_cards.clone();
+    own_recipients.insert(account_info.social_id.clone());
+
+    let event_delivery = tokio::spawn(event_delivery_processor(
+        own_recipients,
+        build_event_sink(&config.event_sink),
+        config.event_delivery.clone(),
+        comm_messages_receiver,
+    ));
 
-    let kafka_config = serde_json::from_value(config.huly.kafka.clone()).unwrap();
     select! {
-        _ = streaming::worker(
-            &kafka_config,
-            agent_info_rx,
-            comm_messages_sender,
-            &server_config.files_url,
-            config.log_level,
+        _ = run_kafka_worker(
+            config,
+            server_config,
+            &account_info,
+            &tx_client,
+            &direct_cards,
+            &comm_messages_sender,
+            &shutdown,
         ) => {},
-        _ = event_to_http_processor(account_info.social_id.clone(), comm_messages_receiver) => {
-
+        _ = shutdown.cancelled() => {
+            tracing::info!("Streaming worker received shutdown signal, draining pending events");
         }
     }
+
+    // Dropping our sender (the only one left once `run_kafka_worker` has returned) closes the
+    // channel, so `event_delivery_processor` drains whatever is already queued and exits on its
+    // own instead of being torn down mid-delivery.
+    drop(comm_messages_sender);
+
+    if let Err(err) = event_delivery.await {
+        tracing::error!(%err, "Event delivery processor task panicked");
+    }
 }
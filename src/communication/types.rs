@@ -2,7 +2,12 @@
 
 use std::fmt::Display;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+/// Wire version of `OutboundEnvelope`/`CommunicationEvent` frames (see `communication::ws`).
+/// Bumped on breaking, non-additive changes to either protocol so `recv_typed` can reject a frame
+/// it no longer knows how to interpret instead of silently misparsing it.
+pub const PROTOCOL_VERSION: u32 = 1;
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type")]
@@ -12,7 +17,55 @@ pub enum CommunicationEvent {
     Attachment(ReceivedAttachment),
 }
 
-#[derive(Debug, Deserialize)]
+/// An agent-originated event streamed back over a `/ws` connection (see `communication::ws`), as
+/// opposed to `CommunicationEvent` which only flows inward from the chat platform. Derives
+/// `Deserialize` too so `communication::ws::recv_typed` can round-trip a frame for replay/testing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum OutboundEvent {
+    AgentMessage {
+        card_id: String,
+        content: String,
+    },
+    ToolResult {
+        card_id: String,
+        tool_name: String,
+        content: String,
+    },
+    /// A fully-formed message the agent wants posted to `card_id`, optionally threaded as a reply.
+    /// Unlike `AgentMessage`, which records a turn that has already been sent via `huly::send_message`,
+    /// this is the symmetric outbound counterpart to `ReceivedMessage` for transports that don't have
+    /// their own direct send path.
+    SendMessage {
+        card_id: String,
+        content: String,
+        reply_to: Option<String>,
+    },
+    AddReaction {
+        card_id: String,
+        message_id: String,
+        reaction: String,
+    },
+    /// `bytes` is base64-encoded, matching the convention `types::Image`/`tools::files` use
+    /// for binary payloads carried over JSON.
+    UploadAttachment {
+        card_id: String,
+        file_name: String,
+        bytes: String,
+    },
+    Typing {
+        card_id: String,
+    },
+    /// One chunk of an in-progress assistant reply, emitted as `result_content` accumulates so a
+    /// connected `/ws` client can render the response incrementally instead of waiting for the
+    /// turn to finish. See `agent::assistant_task::process_assistant_task`.
+    PartialMessage {
+        card_id: String,
+        chunk: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReceivedMessage {
     pub card_id: String,
     pub card_title: Option<String>,
@@ -39,7 +92,7 @@ pub struct ReceivedReaction {
     pub reaction: String,
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct PersonInfo {
     pub person_id: String,
     pub person_name: String,
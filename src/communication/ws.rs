@@ -0,0 +1,317 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use actix_web::{HttpRequest, HttpResponse, web};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::{
+    communication::types::{CommunicationEvent, OutboundEvent, PROTOCOL_VERSION},
+    protocol::ClientProto,
+};
+
+/// How many past outbound events are kept so a client reconnecting with `last_event_id` can replay
+/// what it missed instead of only seeing events published after it reconnects.
+const BACKLOG_CAPACITY: usize = 256;
+
+/// Outbound events go over a bounded channel per connection, unlike `post_event`'s unbounded
+/// inbound channel, so a client that stops reading applies backpressure instead of growing the
+/// server's memory without bound.
+const SESSION_BUFFER_CAPACITY: usize = 128;
+
+/// An `OutboundEvent` tagged with a monotonic id, so a reconnecting client can ask to resume after
+/// the last id it saw, and a `PROTOCOL_VERSION` so a client built against an older schema can
+/// reject a frame it wasn't built to understand instead of misparsing it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboundEnvelope {
+    pub id: u64,
+    pub version: u32,
+    #[serde(flatten)]
+    pub event: OutboundEvent,
+}
+
+/// Serializes `envelope` to the `/ws` wire format.
+pub fn send_typed(envelope: &OutboundEnvelope) -> serde_json::Result<String> {
+    serde_json::to_string(envelope)
+}
+
+/// Deserializes a `/ws` frame into an `OutboundEnvelope`, dispatching on its `type` tag exactly as
+/// `serde`'s `#[serde(tag = "type")]` on `OutboundEvent` already does, but first rejecting a
+/// `version` newer than this build's `PROTOCOL_VERSION` rather than attempting to interpret a
+/// shape it wasn't built for.
+pub fn recv_typed(text: &str) -> anyhow::Result<OutboundEnvelope> {
+    #[derive(Deserialize)]
+    struct VersionOnly {
+        version: u32,
+    }
+    let VersionOnly { version } = serde_json::from_str(text)?;
+    if version > PROTOCOL_VERSION {
+        anyhow::bail!(
+            "Unsupported outbound protocol version {version} (this build understands up to {PROTOCOL_VERSION})"
+        );
+    }
+    Ok(serde_json::from_str(text)?)
+}
+
+struct HubState {
+    next_id: u64,
+    backlog: VecDeque<OutboundEnvelope>,
+    sessions: HashMap<Uuid, mpsc::Sender<OutboundEnvelope>>,
+}
+
+/// Fans agent-originated `OutboundEvent`s out to every connected `/ws` client, keeping a bounded
+/// backlog so a client that reconnects with a `last_event_id` can replay what it missed.
+pub struct OutboundHub {
+    state: Mutex<HubState>,
+}
+
+impl OutboundHub {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(HubState {
+                next_id: 1,
+                backlog: VecDeque::with_capacity(BACKLOG_CAPACITY),
+                sessions: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Publishes an event to every connected session and records it in the replay backlog. Slow
+    /// clients whose buffer is full are dropped rather than allowed to block the publisher.
+    pub fn publish(&self, event: OutboundEvent) {
+        let mut state = self.state.lock().unwrap();
+        let envelope = OutboundEnvelope {
+            id: state.next_id,
+            version: PROTOCOL_VERSION,
+            event,
+        };
+        state.next_id += 1;
+        if state.backlog.len() >= BACKLOG_CAPACITY {
+            state.backlog.pop_front();
+        }
+        state.backlog.push_back(envelope.clone());
+        state.sessions.retain(|id, sender| {
+            match sender.try_send(envelope.clone()) {
+                Ok(()) => true,
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    tracing::warn!(session = %id, "Outbound ws buffer full, dropping slow client");
+                    false
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => false,
+            }
+        });
+    }
+
+    /// Registers a new connection, returning its id, a receiver for events published from now on,
+    /// and the backlog of events after `last_event_id` for the caller to replay first.
+    fn register(
+        &self,
+        last_event_id: Option<u64>,
+    ) -> (Uuid, mpsc::Receiver<OutboundEnvelope>, Vec<OutboundEnvelope>) {
+        let mut state = self.state.lock().unwrap();
+        let id = Uuid::new_v4();
+        let (sender, receiver) = mpsc::channel(SESSION_BUFFER_CAPACITY);
+        let replay = match last_event_id {
+            Some(last_id) => state
+                .backlog
+                .iter()
+                .filter(|envelope| envelope.id > last_id)
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+        state.sessions.insert(id, sender);
+        (id, receiver, replay)
+    }
+
+    fn unregister(&self, id: Uuid) {
+        self.state.lock().unwrap().sessions.remove(&id);
+    }
+}
+
+impl Default for OutboundHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Deserialize)]
+struct WsQuery {
+    last_event_id: Option<u64>,
+}
+
+/// Long-lived `/ws` connection: incoming text frames are deserialized into `CommunicationEvent`
+/// and forwarded to the same `mpsc` sender `POST /event` uses, while outbound agent messages and
+/// tool results published to `hub` are streamed back over the same socket. A client that dropped
+/// can pass `?last_event_id=<id>` to replay what it missed instead of losing events.
+pub async fn ws_route(
+    req: HttpRequest,
+    body: web::Payload,
+    query: web::Query<WsQuery>,
+    sender: web::Data<mpsc::UnboundedSender<CommunicationEvent>>,
+    activity_sender: web::Data<mpsc::UnboundedSender<()>>,
+    hub: web::Data<OutboundHub>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (response, mut session, mut msg_stream) = actix_ws::handle(&req, body)?;
+
+    let (session_id, mut outbound_receiver, replay) = hub.register(query.last_event_id);
+
+    let mut outbound_session = session.clone();
+    actix_web::rt::spawn(async move {
+        for envelope in replay {
+            if let Ok(json) = send_typed(&envelope) {
+                if outbound_session.text(json).await.is_err() {
+                    return;
+                }
+            }
+        }
+        while let Some(envelope) = outbound_receiver.recv().await {
+            if let Ok(json) = send_typed(&envelope) {
+                if outbound_session.text(json).await.is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    actix_web::rt::spawn(async move {
+        while let Some(Ok(msg)) = msg_stream.next().await {
+            match msg {
+                actix_ws::Message::Text(text) => match serde_json::from_str::<CommunicationEvent>(&text) {
+                    Ok(event) => {
+                        tracing::trace!(event = ?event, "Received ws event");
+                        activity_sender.send(()).ok();
+                        if let Err(e) = sender.send(event) {
+                            tracing::error!(error = ?e, "Failed to forward ws event");
+                        }
+                    }
+                    Err(e) => {
+                        tracing::warn!(error = ?e, "Failed to parse ws frame");
+                    }
+                },
+                actix_ws::Message::Ping(bytes) => {
+                    session.pong(&bytes).await.ok();
+                }
+                actix_ws::Message::Close(_) => break,
+                _ => {}
+            }
+        }
+        hub.unregister(session_id);
+    });
+
+    Ok(response)
+}
+
+/// A connected remote runner's last-known identity, as tracked by `RemoteWorkerRegistry`.
+#[derive(Debug, Clone)]
+pub struct RemoteWorkerStatus {
+    pub host_info: crate::protocol::HostInfo,
+    pub last_seen: std::time::Instant,
+}
+
+/// Tracks remote runners connected over `/ws/worker`, keyed by connection id. Presence-only for
+/// now: see `worker_route`'s doc comment for why `NewTaskPlease` doesn't yet hand out real work.
+#[derive(Default)]
+pub struct RemoteWorkerRegistry {
+    workers: Mutex<HashMap<Uuid, RemoteWorkerStatus>>,
+}
+
+impl RemoteWorkerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn register(&self, id: Uuid, host_info: crate::protocol::HostInfo) {
+        self.workers.lock().unwrap().insert(
+            id,
+            RemoteWorkerStatus {
+                host_info,
+                last_seen: std::time::Instant::now(),
+            },
+        );
+    }
+
+    fn heartbeat(&self, id: Uuid) {
+        if let Some(status) = self.workers.lock().unwrap().get_mut(&id) {
+            status.last_seen = std::time::Instant::now();
+        }
+    }
+
+    fn unregister(&self, id: Uuid) {
+        self.workers.lock().unwrap().remove(&id);
+    }
+
+    pub fn list(&self) -> Vec<RemoteWorkerStatus> {
+        self.workers.lock().unwrap().values().cloned().collect()
+    }
+}
+
+/// Long-lived `/ws/worker` connection for a remote runner process: the first frame must be
+/// `ClientProto::Hello`, after which the connection is tracked in `registry` until it disconnects.
+///
+/// `TaskInfo`/`CommandOutput`/`Heartbeat` are handled fully — logged and, for `Heartbeat`, applied
+/// to the registry entry's `last_seen`. `NewTaskPlease` is not: tasks are pulled exclusively by
+/// `Agent::run` from the scheduler's `task_receiver`, which has exactly one consumer. Fanning work
+/// out to registered remote runners means changing who owns that receiver (or adding a second,
+/// coordinated queue it and `Agent::run` both draw from) — a scheduling-layer decision out of
+/// scope for this connection handler, so `NewTaskPlease` is acknowledged but currently always
+/// answered with "no work available" rather than pretending to dispatch a `Task` it doesn't have.
+pub async fn worker_route(
+    req: HttpRequest,
+    body: web::Payload,
+    registry: web::Data<RemoteWorkerRegistry>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (response, mut session, mut msg_stream) = actix_ws::handle(&req, body)?;
+    let connection_id = Uuid::new_v4();
+    let mut said_hello = false;
+
+    actix_web::rt::spawn(async move {
+        while let Some(Ok(msg)) = msg_stream.next().await {
+            match msg {
+                actix_ws::Message::Text(text) => match serde_json::from_str::<ClientProto>(&text) {
+                    Ok(ClientProto::Hello { host_info }) => {
+                        tracing::info!(?host_info, "Remote worker connected");
+                        registry.register(connection_id, host_info);
+                        said_hello = true;
+                    }
+                    Ok(_) if !said_hello => {
+                        tracing::warn!("Remote worker sent a frame before Hello, dropping connection");
+                        break;
+                    }
+                    Ok(ClientProto::NewTaskPlease) => {
+                        let reply = serde_json::json!({"type": "no_work_available"});
+                        if session.text(reply.to_string()).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(ClientProto::TaskInfo { task_id, kind }) => {
+                        tracing::info!(task_id, kind, "Remote worker task info");
+                    }
+                    Ok(ClientProto::CommandOutput { task_id, chunk }) => {
+                        tracing::trace!(task_id, chunk, "Remote worker command output");
+                    }
+                    Ok(ClientProto::Heartbeat) => {
+                        registry.heartbeat(connection_id);
+                    }
+                    Err(e) => {
+                        tracing::warn!(error = ?e, "Failed to parse /ws/worker frame");
+                    }
+                },
+                actix_ws::Message::Ping(bytes) => {
+                    session.pong(&bytes).await.ok();
+                }
+                actix_ws::Message::Close(_) => break,
+                _ => {}
+            }
+        }
+        registry.unregister(connection_id);
+    });
+
+    Ok(response)
+}
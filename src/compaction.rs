@@ -0,0 +1,83 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! Rolling summarization of an assistant chat's stored history. When more than
+//! `config.assistant_compaction.max_messages` turns are stored for a card,
+//! `compact_if_needed` folds the oldest `collapse_count` of them into the card's
+//! `assistant_summary` row via a pluggable `Summarizer`, so `DbClient::get_assistant_messages`
+//! keeps returning a bounded blob.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::StreamExt;
+
+use crate::{
+    config::AssistantCompactionConfig,
+    database::DbClient,
+    providers::ProviderClient,
+    types::{AssistantContent, Message},
+};
+
+/// Turns a run of collapsed messages into a short summary. The chat model is the only
+/// implementation today, but compaction depends on this trait rather than on `ProviderClient`
+/// directly, so it can be tested (or swapped for a cheaper model) without a live completion.
+#[async_trait]
+pub trait Summarizer: Send + Sync {
+    async fn summarize(&self, messages: &[Message]) -> Result<String>;
+}
+
+const SUMMARY_SYSTEM_PROMPT: &str = "Summarize the following conversation turns into a short \
+paragraph capturing the facts, decisions, and open threads a continuation would need. Do not \
+add commentary or preamble.";
+
+/// Asks the agent's own chat model to condense the collapsed messages.
+pub struct LlmSummarizer<'a> {
+    pub provider: &'a dyn ProviderClient,
+}
+
+#[async_trait]
+impl<'a> Summarizer for LlmSummarizer<'a> {
+    async fn summarize(&self, messages: &[Message]) -> Result<String> {
+        let mut response = self
+            .provider
+            .send_messages(SUMMARY_SYSTEM_PROMPT, "", messages)
+            .await?;
+
+        let mut summary = String::new();
+        while let Some(chunk) = response.next().await {
+            if let AssistantContent::Text(text) = chunk? {
+                summary.push_str(&text.text);
+            }
+        }
+        Ok(summary)
+    }
+}
+
+/// Folds the oldest messages for `card_id` into its summary once it exceeds
+/// `config.max_messages`. Safe to call on every turn: a no-op when under budget.
+pub async fn compact_if_needed(
+    db: &DbClient,
+    card_id: &str,
+    summarizer: &dyn Summarizer,
+    config: &AssistantCompactionConfig,
+) -> Result<()> {
+    let rows = db.get_last_messages(card_id, None, u32::MAX).await?;
+    if rows.len() <= config.max_messages {
+        return Ok(());
+    }
+
+    let collapse_count = config.collapse_count.min(rows.len());
+    let batch = &rows[..collapse_count];
+    let messages = batch
+        .iter()
+        .filter_map(|(_, _, content)| serde_json::from_str::<Message>(content).ok())
+        .collect::<Vec<_>>();
+    if messages.is_empty() {
+        return Ok(());
+    }
+
+    let summary = summarizer.summarize(&messages).await?;
+    let from_seq = batch.first().unwrap().0;
+    let to_seq = batch.last().unwrap().0;
+    db.compact_assistant_messages(card_id, from_seq, to_seq, &summary)
+        .await
+}
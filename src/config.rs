@@ -12,7 +12,7 @@ use std::{
 use anyhow::{Result, anyhow};
 use chrono::{DateTime, Utc};
 use reqwest::Url;
-use secrecy::SecretString;
+use secrecy::{ExposeSecret, SecretString};
 use serde::{
     Deserialize, Deserializer,
     de::{self, Error, Visitor},
@@ -26,6 +26,98 @@ pub enum ProviderKind {
     OpenAI,
     OpenRouter,
     Anthropic,
+    /// Replays a fixture of pre-recorded streaming choices instead of calling a live provider, so
+    /// a `bench::run_workload` run can be deterministic (e.g. in CI). The profile's `model` field
+    /// holds the path to the fixture file; see `providers::recorded::RecordedFixture`.
+    Recorded,
+}
+
+/// One named, independently-configured LLM backend: its `ProviderKind`, `model`, and API key.
+/// `Config::provider_profiles` names a set of these; `TaskConfig::provider_profile` and
+/// `Config::provider_fallback` pick among them. See `providers::ProviderRouter`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ProviderProfile {
+    pub provider: ProviderKind,
+    pub model: String,
+    #[serde(default)]
+    pub api_key: Option<SecretString>,
+}
+
+/// Selects and configures `embeddings::EmbeddingProvider`. VoyageAI is the only backend with a
+/// track record in this deployment, but the storage layer only depends on the trait, so an
+/// OpenAI-compatible endpoint (self-hosted or a third party) or a local model can be swapped in
+/// here without touching any SQL.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EmbeddingProviderConfig {
+    VoyageAi {
+        api_key: SecretString,
+        model: String,
+        dimensions: u16,
+    },
+    OpenAiCompatible {
+        base_url: String,
+        api_key: SecretString,
+        model: String,
+        dimensions: u16,
+    },
+    /// Reserved for a local/offline model; `embeddings::LocalEmbeddingProvider` fails every call
+    /// until one is actually wired up, so picking this today is a deliberate "no embeddings" knob
+    /// rather than a silent fallback to a remote provider.
+    Local {
+        model_path: String,
+        dimensions: u16,
+    },
+}
+
+impl EmbeddingProviderConfig {
+    pub fn dimensions(&self) -> u16 {
+        match self {
+            EmbeddingProviderConfig::VoyageAi { dimensions, .. }
+            | EmbeddingProviderConfig::OpenAiCompatible { dimensions, .. }
+            | EmbeddingProviderConfig::Local { dimensions, .. } => *dimensions,
+        }
+    }
+
+    /// Opaque identity string covering every field that changes which provider `build()` would
+    /// construct. Two configs with equal fingerprints produce behaviorally identical providers, so
+    /// `storage::ConfigOverrideStore::reload_embedding_provider` can skip the rebuild when nothing
+    /// actually changed.
+    pub fn fingerprint(&self) -> String {
+        match self {
+            EmbeddingProviderConfig::VoyageAi {
+                api_key,
+                model,
+                dimensions,
+            } => format!("voyageai:{model}:{dimensions}:{}", api_key.expose_secret()),
+            EmbeddingProviderConfig::OpenAiCompatible {
+                base_url,
+                api_key,
+                model,
+                dimensions,
+            } => format!(
+                "openai_compatible:{base_url}:{model}:{dimensions}:{}",
+                api_key.expose_secret()
+            ),
+            EmbeddingProviderConfig::Local {
+                model_path,
+                dimensions,
+            } => format!("local:{model_path}:{dimensions}"),
+        }
+    }
+}
+
+/// Which storage backend `DbClient` connects to. Defaults to the embedded SQLite database
+/// used by a single-instance deployment; `Postgres` lets the agent share state with a
+/// server-class database instead.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DatabaseConfig {
+    #[default]
+    Sqlite,
+    Postgres {
+        url: String,
+    },
 }
 
 fn deserialize_log_level<'de, D>(deserializer: D) -> Result<tracing::Level, D::Error>
@@ -43,21 +135,131 @@ pub struct Config {
     pub otel: OtelMode,
     pub agent_mode: AgentMode,
     pub http_api: HttpApiConfig,
+    /// Default model, used as the implicit profile when `provider_profiles` is empty, and by
+    /// callers that don't go through `providers::ProviderRouter` (e.g. `task::encoder_for`'s
+    /// tokenizer selection, `MemoryExtractor`'s cost-unrelated model pick).
     pub model: String,
     pub provider: ProviderKind,
     pub provider_api_key: Option<SecretString>,
+    /// Named provider backends `providers::ProviderRouter` can route to, keyed by a name
+    /// referenced from `TaskConfig::provider_profile` / `provider_fallback`. Empty by default, in
+    /// which case the router falls back to a single implicit profile built from
+    /// `provider`/`model`/`provider_api_key`.
+    #[serde(default)]
+    pub provider_profiles: HashMap<String, ProviderProfile>,
+    /// Ordered `provider_profiles` names tried after a `TaskKind`'s preferred profile (or first, if
+    /// it has none) fails with a retryable error. See `providers::ProviderRouter`.
+    #[serde(default)]
+    pub provider_fallback: Vec<String>,
     pub huly: HulyConfig,
     pub user_instructions: String,
     pub workspace: PathBuf,
     pub mcp: Option<HashMap<String, McpConfig>>,
-    pub voyageai_api_key: SecretString,
-    pub voyageai_model: String,
-    pub voyageai_dimensions: u16,
-    pub web_search: WebSearchProvider,
+    pub embedding_provider: EmbeddingProviderConfig,
+    /// Queried concurrently and fused with Reciprocal Rank Fusion when more than one is
+    /// configured; see `tools::web::search`.
+    #[serde(default)]
+    pub web_search: Vec<WebSearchProviderConfig>,
     pub browser: Option<BrowserConfig>,
+    pub code_execution: Option<CodeExecutionConfig>,
     pub memory: MemoryConfig,
     pub jobs: Vec<JobDefinition>,
     pub tasks: HashMap<TaskKind, TaskConfig>,
+    #[serde(default)]
+    pub database: DatabaseConfig,
+    #[serde(default)]
+    pub scheduled_task_catchup: ScheduledTaskCatchup,
+    #[serde(default)]
+    pub assistant_compaction: AssistantCompactionConfig,
+    #[serde(default)]
+    pub notes: NotesConfig,
+    /// Upper bound on how many tool calls from a single assistant turn are dispatched at once when
+    /// the model requests several independent ones in parallel. See `agent::utils::dispatch_tool_calls`.
+    #[serde(default = "default_max_concurrent_tool_calls")]
+    pub max_concurrent_tool_calls: usize,
+    /// Upper bound on how many `ToolImpl::is_blocking` tool calls may be parked on the runtime's
+    /// blocking path at once (see `agent::utils::dispatch_one_tool_call`). Calls beyond this many
+    /// wait their turn rather than running inline and starving the main loop.
+    #[serde(default = "default_max_blocking_tools")]
+    pub max_blocking_tools: usize,
+    /// Per-model token pricing, keyed by the model name as it appears in `Config::model`. A model
+    /// with no entry here falls back to a flat per-message cost. See `agent::utils::token_cost`.
+    #[serde(default)]
+    pub model_rates: HashMap<String, ModelRate>,
+    /// Default timeout for a single `tool.call`, in seconds. See `agent::utils::dispatch_one_tool_call`.
+    #[serde(default = "default_tool_timeout_secs")]
+    pub tool_timeout_secs: u64,
+    /// Per-tool overrides of `tool_timeout_secs`, keyed by tool name.
+    #[serde(default)]
+    pub tool_timeouts_secs: HashMap<String, u64>,
+    /// How many times a failing tool call is retried (with exponential backoff) before its error
+    /// is surfaced to the model. `0` disables retries.
+    #[serde(default = "default_tool_max_retries")]
+    pub tool_max_retries: u32,
+    /// How long a cached result from a `ToolImpl::is_cacheable` tool stays valid before a repeat
+    /// call re-runs it instead. See `tools::cache::ToolResultCache`.
+    #[serde(default = "default_tool_result_cache_ttl_secs")]
+    pub tool_result_cache_ttl_secs: u64,
+    /// Upper bound on how many tasks `task::task_multiplexer` will have `Started` at once (per
+    /// `task_manager::TaskManager`). `None` leaves dispatch unbounded.
+    #[serde(default)]
+    pub max_concurrent_tasks: Option<usize>,
+    /// Retry/backoff/dead-letter policy for outbound event delivery. See
+    /// `communication::streaming::event_delivery_processor`.
+    #[serde(default)]
+    pub event_delivery: EventDeliveryConfig,
+    /// Outbound transport for streamed events. See `communication::event_sink::EventSink`.
+    #[serde(default)]
+    pub event_sink: EventSinkConfig,
+    /// Policy gating `Execute`-kind tool calls (see `tools::ToolKind`). Defaults to `Confirm`,
+    /// preserving today's operator-approval behavior.
+    #[serde(default)]
+    pub execute_policy: ExecutePolicy,
+    /// Installs the `console-subscriber` layer (tokio-console) alongside the regular tracing
+    /// layers set up in `main::init_logger`, for inspecting live task state/poll times/stalls in
+    /// the scheduler loop, actix server, and streaming polls. Off by default since it requires
+    /// building with `tokio_unstable` and opens a local gRPC port.
+    #[serde(default)]
+    pub console: bool,
+    /// How long `Agent::shutdown` waits for in-flight tasks to finish on their own before giving
+    /// up on them and persisting their state as `Postponed` instead. See `agent::pool::TaskPool`.
+    #[serde(default = "default_shutdown_grace_period_secs")]
+    pub shutdown_grace_period_secs: u64,
+}
+
+fn default_shutdown_grace_period_secs() -> u64 {
+    30
+}
+
+fn default_max_concurrent_tool_calls() -> usize {
+    4
+}
+
+fn default_max_blocking_tools() -> usize {
+    4
+}
+
+fn default_tool_timeout_secs() -> u64 {
+    60
+}
+
+fn default_tool_max_retries() -> u32 {
+    2
+}
+
+fn default_tool_result_cache_ttl_secs() -> u64 {
+    300
+}
+
+/// Token pricing for a single model, in the same abstract units as `AgentState::balance`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ModelRate {
+    pub input_cost_per_token: f32,
+    pub output_cost_per_token: f32,
+    /// Cost of a prompt token served from the provider's prompt cache (see
+    /// `streaming::ResponseUsage::cached_tokens`); defaults to `input_cost_per_token` when absent.
+    #[serde(default)]
+    pub cached_input_cost_per_token: Option<f32>,
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq, Hash)]
@@ -86,11 +288,181 @@ pub enum TaskKind {
 pub struct TaskConfig {
     /// available tools by wildcards
     pub tools: Vec<String>,
+    /// Retry policy for tasks of this kind. It is parsed but not read: job retries use the job's
+    /// own `JobDefinition::retry` (see `scheduler::SchedulerWorker`), and a plain chat task
+    /// reschedules through `state::AgentState::reschedule_task_with_backoff`.
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    /// Minimum gap between two tasks of this kind being dispatched, enforced by
+    /// `task::task_multiplexer` before `sender.send(...)`. `None` leaves this kind unthrottled.
+    #[serde(default)]
+    pub tranquility_secs: Option<u64>,
+    /// Token budget for the rendered `task::format_messages` block, measured with the BPE encoder
+    /// matching `Config::provider`/`Config::model`. Messages older than what fits are collapsed
+    /// into a single summary line instead of being dropped. `None` leaves the block unbounded
+    /// (aside from the existing `MAX_FOLLOW_MESSAGES` count cap).
+    #[serde(default)]
+    pub context_budget: Option<usize>,
+    /// Tokens reserved for the system prompt and other fixed context, subtracted from
+    /// `context_budget` before counting message tokens. Ignored when `context_budget` is `None`.
+    #[serde(default)]
+    pub context_reserve: Option<usize>,
+    /// Name of the `Config::provider_profiles` entry this `TaskKind` prefers, tried before
+    /// `Config::provider_fallback`. `None` starts from the fallback chain (or the implicit default
+    /// profile, if no profiles are configured at all).
+    #[serde(default)]
+    pub provider_profile: Option<String>,
+    /// Loop guard for task runners that poll the provider in a `<|done|>`-terminated loop (e.g.
+    /// `notes_mantainance`), stopping a model that never signals completion instead of looping
+    /// forever. See `agent::utils::LoopBudget`. `None` leaves the loop unbounded.
+    #[serde(default)]
+    pub loop_budget: Option<LoopBudgetConfig>,
+}
+
+/// Caps for `agent::utils::LoopBudget`: provider round-trips, cumulative serialized message size,
+/// and wall-clock time. Any field left `None` is not enforced.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct LoopBudgetConfig {
+    #[serde(default)]
+    pub max_round_trips: Option<u32>,
+    #[serde(default)]
+    pub max_message_chars: Option<usize>,
+    #[serde(default)]
+    pub max_duration_secs: Option<u64>,
+}
+
+/// Exponential backoff with full jitter, used by job retries (`scheduler::SchedulerWorker`);
+/// `TaskConfig::retry` holds one too but is not read. On attempt `n` (0-indexed), `backoff(n)` is
+/// `min(max_delay, base_delay * 2^n)` scaled by a uniform factor in `[0.5, 1.0]`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    #[serde(default = "default_retry_base_delay_secs")]
+    pub base_delay_secs: u64,
+    #[serde(default = "default_retry_max_delay_secs")]
+    pub max_delay_secs: u64,
+}
+
+fn default_retry_base_delay_secs() -> u64 {
+    30
+}
+
+fn default_retry_max_delay_secs() -> u64 {
+    3600
+}
+
+impl RetryPolicy {
+    pub fn base_delay(&self) -> Duration {
+        Duration::from_secs(self.base_delay_secs)
+    }
+
+    pub fn max_delay(&self) -> Duration {
+        Duration::from_secs(self.max_delay_secs)
+    }
+
+    /// `min(max_delay, base_delay * 2^attempt)` scaled by a uniform `[0.5, 1.0]` jitter factor.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let capped_exponent = attempt.min(32);
+        let exponential = self.base_delay_secs.saturating_mul(1u64 << capped_exponent);
+        let capped = exponential.min(self.max_delay_secs);
+        let jitter = 0.5 + 0.5 * rand::random::<f64>();
+        Duration::from_secs_f64(capped as f64 * jitter)
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct MemoryConfig {
     pub extract_model: String,
+    /// Most recent N turns folded into the embedding query for `${MEMORY_ENTRIES}`'s
+    /// relevant-entries channel, instead of the whole conversation history.
+    pub retrieval_window: usize,
+    /// Minimum similarity score (see `MemoryStore::mem_relevant_entities_scored`) a candidate
+    /// must clear to be included; entities below this are omitted rather than padded in.
+    pub min_similarity: f32,
+    /// Tuning for `memory::importance::ImportanceCalculator`. Defaults to the weights the
+    /// calculator used before this was configurable.
+    #[serde(default)]
+    pub scoring: MemoryScoringConfig,
+}
+
+/// Tuning knobs for `memory::importance::ImportanceCalculator`, previously hard-coded constants.
+#[derive(Debug, Deserialize, Clone)]
+pub struct MemoryScoringConfig {
+    /// Per-`MemoryEntityType` decay rate (higher decays faster), keyed by its `Display` form
+    /// (e.g. `"episode"`, `"semantic"`). A type with no entry falls back to `default_decay_rate`.
+    #[serde(default)]
+    pub decay_rates: HashMap<String, f32>,
+    #[serde(default = "default_decay_rate")]
+    pub default_decay_rate: f32,
+    #[serde(default = "default_max_access_count")]
+    pub max_access_count: u32,
+    #[serde(default = "default_max_relations_count")]
+    pub max_relations_count: u32,
+    /// Blend weights for `calculate_importance`; should sum to ~1.0 but aren't enforced to.
+    #[serde(default = "default_stored_weight")]
+    pub stored_weight: f32,
+    #[serde(default = "default_time_weight")]
+    pub time_weight: f32,
+    #[serde(default = "default_frequency_weight")]
+    pub frequency_weight: f32,
+    #[serde(default = "default_relations_weight")]
+    pub relations_weight: f32,
+}
+
+impl Default for MemoryScoringConfig {
+    fn default() -> Self {
+        Self {
+            decay_rates: HashMap::new(),
+            default_decay_rate: default_decay_rate(),
+            max_access_count: default_max_access_count(),
+            max_relations_count: default_max_relations_count(),
+            stored_weight: default_stored_weight(),
+            time_weight: default_time_weight(),
+            frequency_weight: default_frequency_weight(),
+            relations_weight: default_relations_weight(),
+        }
+    }
+}
+
+fn default_decay_rate() -> f32 {
+    0.05
+}
+
+fn default_max_access_count() -> u32 {
+    1000
+}
+
+fn default_max_relations_count() -> u32 {
+    20
+}
+
+fn default_stored_weight() -> f32 {
+    0.35
+}
+
+fn default_time_weight() -> f32 {
+    0.25
+}
+
+fn default_frequency_weight() -> f32 {
+    0.25
+}
+
+fn default_relations_weight() -> f32 {
+    0.15
+}
+
+/// Controls the opt-in note-classification step (`note_classifier::NoteClassifier`) run over
+/// notes added via `AddNoteTool`. Disabled by default, in which case notes keep only whatever
+/// tags the caller passed explicitly.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct NotesConfig {
+    #[serde(default)]
+    pub classify: bool,
+    /// Loop guard for `agent::notes_mantainance_task::notes_mantainance`. See
+    /// `agent::utils::LoopBudget`. `None` leaves the loop unbounded.
+    #[serde(default)]
+    pub loop_budget: Option<LoopBudgetConfig>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -101,6 +473,19 @@ pub enum OtelMode {
     Off,
 }
 
+/// How `agent::utils::dispatch_one_tool_call` handles an `Execute`-kind tool call (see
+/// `tools::ToolKind`): `AutoApprove` runs it immediately, `Confirm` polls for operator sign-off
+/// via the `communication::http` approval endpoint (today's only behavior), and `DryRun` returns a
+/// synthesized "would have done X" result without calling the tool at all.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutePolicy {
+    AutoApprove,
+    #[default]
+    Confirm,
+    DryRun,
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct HulyConfig {
     pub kafka: KafkaConfig,
@@ -108,8 +493,37 @@ pub struct HulyConfig {
     #[serde(default)]
     pub person: Option<PersonConfig>,
     pub ignored_channels: HashSet<String>,
+    /// Additional tool backends (e.g. one or more Huly AI presenter services) whose tools are
+    /// merged into the agent's tool set at startup. See `tools::huly::create_huly_tool_set`.
     #[serde(default)]
-    pub presenter_url: Option<Url>,
+    pub tool_backends: Vec<ToolBackendConfig>,
+    /// Largest response body `huly_add_message_attachement` will download from a remote URL
+    /// before giving up and reporting an error to the model.
+    #[serde(default = "default_max_attachment_download_bytes")]
+    pub max_attachment_download_bytes: u64,
+    /// How many prior messages `huly::streaming::worker` backfills from `tx_client` the first
+    /// time it starts following a card (a mention, a personal-space DM, or a
+    /// `ThreadPatchOperation::Attach`), so the agent answers with context instead of seeing only
+    /// the single message that triggered the follow. `None` falls back to `MAX_FOLLOW_MESSAGES`.
+    #[serde(default)]
+    pub backfill_messages: Option<usize>,
+}
+
+fn default_max_attachment_download_bytes() -> u64 {
+    25 * 1024 * 1024
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ToolBackendConfig {
+    /// A Huly AI presenter service, advertising its tools via `GET /params-schema.json` and
+    /// invoked at `POST /<method>`. `prefix` namespaces its tools (`huly_<prefix>_<method>`) so
+    /// several presenters can be registered without their tool names colliding.
+    Presenter {
+        base_url: Url,
+        auth_token: SecretString,
+        prefix: String,
+    },
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -157,7 +571,27 @@ impl Display for RgbRole {
 #[derive(Debug, Deserialize, Clone)]
 #[serde(tag = "transport", rename_all = "lowercase")]
 pub enum McpTransportConfig {
-    Sse { url: String, version: String },
+    Sse {
+        url: String,
+        version: String,
+    },
+    /// Spawns `command` as a child process and speaks MCP over its stdin/stdout, for local servers
+    /// (filesystem, git, sqlite, ...) that only support the stdio transport and would otherwise
+    /// need an HTTP bridge in front of them.
+    Stdio {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        env: HashMap<String, String>,
+    },
+    /// MCP over a single streamable HTTP endpoint (the transport protocol revision 2025-03-26
+    /// introduced), as opposed to `Sse`'s older two-endpoint transport.
+    StreamableHttp {
+        url: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -173,7 +607,7 @@ pub struct WebSearchBraveConfig {
 
 #[derive(Debug, Deserialize, Clone)]
 #[serde(tag = "type", rename_all = "lowercase")]
-pub enum WebSearchProvider {
+pub enum WebSearchProviderConfig {
     Brave(WebSearchBraveConfig),
 }
 
@@ -183,6 +617,19 @@ pub struct BrowserConfig {
     pub profile_name: String,
 }
 
+/// Gates the `fs_run` code-execution tool. Omit to disable the tool entirely (like `browser`).
+/// `fs_run` is **not sandboxed** — it runs the interpreter as a plain child process with the
+/// agent's own filesystem/network/process access, so only enable it for a workspace you'd trust
+/// the model with unrestricted shell access to. `ToolKind::Execute` (see
+/// `tools::files::RunCodeTool::kind`) at least requires operator approval per call.
+#[derive(Debug, Deserialize, Clone)]
+pub struct CodeExecutionConfig {
+    /// Interpreters the agent may invoke, matched case-insensitively against the `language` tool
+    /// argument (e.g. `["python", "javascript"]`). Anything not on this list is rejected before a
+    /// process is spawned.
+    pub allowed_languages: Vec<String>,
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct JobDefinition {
     pub id: String,
@@ -195,6 +642,30 @@ pub struct JobDefinition {
         default
     )]
     pub time_spread: Duration,
+    /// Retry policy applied when a run of this job fails, tracked by `scheduler::SchedulerWorker`
+    /// via the `task::JobOutcome` channel. `None` falls back to simply waiting for the next cron
+    /// tick, as before this was configurable.
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    /// How `scheduler::SchedulerWorker::new` handles a persisted `upcoming` timestamp found in the
+    /// past at startup, i.e. fire times missed while the agent was down.
+    #[serde(default)]
+    pub catchup: JobCatchupPolicy,
+}
+
+/// How a `JobDefinition` catches up on fire times missed while the agent was down, evaluated once
+/// at `scheduler::SchedulerWorker::new` against the persisted `upcoming` timestamp. Distinct from
+/// `ScheduledTaskCatchup`, which governs one-off `AssistantTask`s rather than recurring jobs.
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum JobCatchupPolicy {
+    /// Drop every run that elapsed during downtime and resume at the next future occurrence.
+    #[default]
+    Skip,
+    /// Run exactly one immediate execution if any slot was missed, then resume.
+    FireOnce,
+    /// Enqueue up to `max` of the missed executions, oldest first.
+    Backfill { max: u32 },
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -205,19 +676,213 @@ pub enum JobKind {
 }
 
 #[derive(Debug, Clone)]
-pub struct JobSchedule(cron::Schedule);
+enum JobScheduleKind {
+    Cron(cron::Schedule),
+    /// An iCalendar `RRULE`, anchored at the moment it was parsed (RFC 5545's `DTSTART`), which
+    /// supplies the time-of-day `next_occurrence` candidates keep. `raw` is kept only so
+    /// `source()` can echo back what was configured.
+    RRule {
+        rule: crate::rrule::RRule,
+        dtstart: DateTime<Utc>,
+        raw: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct JobSchedule(JobScheduleKind);
+
+/// An `RRULE` is recognized by its leading `FREQ=` part; anything else is parsed as a cron
+/// expression, matching this crate's existing schedule strings.
+fn is_rrule(schedule: &str) -> bool {
+    schedule
+        .split(';')
+        .next()
+        .is_some_and(|first| first.trim().to_ascii_uppercase().starts_with("FREQ="))
+}
 
 impl JobSchedule {
     pub fn new(schedule: &str) -> Result<Self> {
-        Ok(Self(cron::Schedule::from_str(schedule)?))
+        if is_rrule(schedule) {
+            Ok(Self(JobScheduleKind::RRule {
+                rule: crate::rrule::RRule::parse(schedule)?,
+                dtstart: Utc::now(),
+                raw: schedule.to_string(),
+            }))
+        } else {
+            Ok(Self(JobScheduleKind::Cron(cron::Schedule::from_str(
+                schedule,
+            )?)))
+        }
     }
 
     pub fn source(&self) -> &str {
-        self.0.source()
+        match &self.0 {
+            JobScheduleKind::Cron(schedule) => schedule.source(),
+            JobScheduleKind::RRule { raw, .. } => raw,
+        }
     }
 
     pub fn upcoming(&self) -> DateTime<Utc> {
-        self.0.upcoming(Utc).next().unwrap_or(Utc::now())
+        match &self.0 {
+            JobScheduleKind::Cron(schedule) => schedule.upcoming(Utc).next().unwrap_or(Utc::now()),
+            JobScheduleKind::RRule { rule, dtstart, .. } => rule
+                .next_occurrence(*dtstart, Utc::now())
+                .unwrap_or(*dtstart),
+        }
+    }
+
+    /// The first occurrence strictly after `after`, used to compute `next_run_at` once a
+    /// scheduled task has fired.
+    pub fn next_after(&self, after: DateTime<Utc>) -> DateTime<Utc> {
+        match &self.0 {
+            JobScheduleKind::Cron(schedule) => schedule.after(&after).next().unwrap_or(after),
+            JobScheduleKind::RRule { rule, dtstart, .. } => {
+                rule.next_occurrence(*dtstart, after).unwrap_or(after)
+            }
+        }
+    }
+
+    /// All occurrences in `(after, until]`, used to determine what a scheduled task missed
+    /// while the agent was offline.
+    pub fn occurrences_between(
+        &self,
+        after: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Vec<DateTime<Utc>> {
+        match &self.0 {
+            JobScheduleKind::Cron(schedule) => schedule
+                .after(&after)
+                .take_while(|t| *t <= until)
+                .collect(),
+            JobScheduleKind::RRule { rule, dtstart, .. } => {
+                let mut occurrences = Vec::new();
+                let mut cursor = after;
+                while let Some(next) = rule.next_occurrence(*dtstart, cursor) {
+                    if next > until {
+                        break;
+                    }
+                    occurrences.push(next);
+                    cursor = next;
+                }
+                occurrences
+            }
+        }
+    }
+}
+
+/// How a scheduled task catches up after the agent was offline through one or more of its
+/// occurrences: fire once per missed occurrence, or collapse them into a single run.
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduledTaskCatchup {
+    FireAllMissed,
+    #[default]
+    FireLatestMissed,
+}
+
+/// Rolling compaction of an assistant chat's stored history: once more than `max_messages`
+/// turns are stored for a card, the oldest `collapse_count` are folded into its summary row.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AssistantCompactionConfig {
+    pub max_messages: usize,
+    pub collapse_count: usize,
+}
+
+impl Default for AssistantCompactionConfig {
+    fn default() -> Self {
+        Self {
+            max_messages: 200,
+            collapse_count: 50,
+        }
+    }
+}
+
+/// Retry policy for `communication::streaming::event_delivery_processor`'s outbound delivery.
+/// Unlike `RetryPolicy` (whole-second delays for job/task retries), delays here are sub-second
+/// since a single outbound event is expected to succeed within a few round trips.
+#[derive(Debug, Deserialize, Clone)]
+pub struct EventDeliveryConfig {
+    pub max_attempts: u32,
+    #[serde(default = "default_event_delivery_base_delay_ms")]
+    pub base_delay_ms: u64,
+    #[serde(default = "default_event_delivery_max_delay_ms")]
+    pub max_delay_ms: u64,
+    /// Path to the append-only JSONL file that undeliverable events are written to once
+    /// `max_attempts` is exhausted, one `{"recipient", "event"}` record per recipient, for later
+    /// replay.
+    #[serde(default = "default_event_delivery_dead_letter_path")]
+    pub dead_letter_path: PathBuf,
+}
+
+fn default_event_delivery_base_delay_ms() -> u64 {
+    200
+}
+
+fn default_event_delivery_max_delay_ms() -> u64 {
+    30_000
+}
+
+fn default_event_delivery_dead_letter_path() -> PathBuf {
+    PathBuf::from("event_dead_letter.jsonl")
+}
+
+impl Default for EventDeliveryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay_ms: default_event_delivery_base_delay_ms(),
+            max_delay_ms: default_event_delivery_max_delay_ms(),
+            dead_letter_path: default_event_delivery_dead_letter_path(),
+        }
+    }
+}
+
+/// Outbound transport `communication::event_sink::build_event_sink` constructs for
+/// `communication::streaming::streaming_worker`. Defaults to `Http` against the legacy hardcoded
+/// `http://localhost:8081/event` endpoint, so existing deployments keep working unconfigured.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EventSinkConfig {
+    Http {
+        url: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+        #[serde(default)]
+        auth_token: Option<SecretString>,
+    },
+    WebSocket {
+        url: String,
+        #[serde(default)]
+        auth_token: Option<SecretString>,
+    },
+}
+
+impl Default for EventSinkConfig {
+    fn default() -> Self {
+        Self::Http {
+            url: "http://localhost:8081/event".to_string(),
+            headers: HashMap::new(),
+            auth_token: None,
+        }
+    }
+}
+
+impl EventDeliveryConfig {
+    pub fn base_delay(&self) -> Duration {
+        Duration::from_millis(self.base_delay_ms)
+    }
+
+    pub fn max_delay(&self) -> Duration {
+        Duration::from_millis(self.max_delay_ms)
+    }
+
+    /// `min(max_delay, base_delay * 2^attempt)` scaled by a `[0.8, 1.2]` jitter factor (±20%).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let capped_exponent = attempt.min(32);
+        let exponential = self.base_delay_ms.saturating_mul(1u64 << capped_exponent);
+        let capped = exponential.min(self.max_delay_ms);
+        let jitter = 0.8 + 0.4 * rand::random::<f64>();
+        Duration::from_millis((capped as f64 * jitter) as u64)
     }
 }
 
@@ -231,7 +896,7 @@ impl<'de> Visitor<'de> for JobScheduleVisitor {
     where
         E: de::Error,
     {
-        Ok(JobSchedule(cron::Schedule::from_str(v).map_err(E::custom)?))
+        JobSchedule::new(v).map_err(E::custom)
     }
 }
 
@@ -10,19 +10,33 @@ use secrecy::SecretString;
 use tokio::sync::RwLock;
 
 use crate::{
-    huly::{blob::BlobClient, typing::TypingClient},
-    tools::command::process_registry::ProcessRegistry,
+    communication::OutboundHub,
+    huly::{blob::BlobClient, resilient::ResilientTransactor, typing::TypingClient},
+    task_manager::TaskManager,
+    tools::{cache::ToolResultCache, command::process_registry::ProcessRegistry},
+    worker::WorkerManager,
 };
 
 pub struct AgentContext {
     pub account_info: HulyAccountInfo,
     pub tx_client: TransactorClient<HttpBackend>,
+    /// Retrying, cached wrapper over `tx_client` for queries that should degrade to a stale value
+    /// instead of a hard failure, e.g. `${MODE_CONTEXT}`'s boss `UserStatus` lookup.
+    pub resilient_tx: ResilientTransactor,
     pub blob_client: BlobClient,
     pub typing_client: TypingClient,
     pub process_registry: Arc<RwLock<ProcessRegistry>>,
     pub db_client: crate::database::DbClient,
+    pub worker_manager: Arc<WorkerManager>,
+    pub task_manager: Arc<TaskManager>,
+    /// Fans agent messages and tool results out to connected `/ws` clients (see
+    /// `communication::ws`).
+    pub outbound_hub: Arc<OutboundHub>,
     pub tools_context: Option<String>,
     pub tools_system_prompt: Option<String>,
+    /// Reuses prior results for `ToolImpl::is_cacheable` tools called with identical arguments.
+    /// See `agent::utils::dispatch_one_tool_call` / `agent::assistant_task::execute_tool_call`.
+    pub tool_result_cache: ToolResultCache,
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,391 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+use std::{fmt::Display, time::Duration};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use rand::Rng;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, de::DeserializeOwned};
+
+use crate::config::{Config, EmbeddingProviderConfig};
+
+const VOYAGEAI_URL: &str = "https://api.voyageai.com/v1/embeddings";
+const OPENAI_COMPATIBLE_PATH: &str = "/v1/embeddings";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_ATTEMPTS: u32 = 5;
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Distinguishes the embedding call's failure modes so callers (e.g. `kg_insert_observations_if_new`)
+/// can decide whether to defer the task or fail outright.
+#[derive(Debug)]
+pub enum EmbeddingError {
+    RateLimited { retry_after: Option<Duration> },
+    ServerError(String),
+    Other(anyhow::Error),
+}
+
+impl Display for EmbeddingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EmbeddingError::RateLimited { retry_after } => {
+                write!(f, "embedding provider rate limited the request, retry after {retry_after:?}")
+            }
+            EmbeddingError::ServerError(status) => {
+                write!(f, "embedding provider returned a server error: {status}")
+            }
+            EmbeddingError::Other(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for EmbeddingError {}
+
+impl From<reqwest::Error> for EmbeddingError {
+    fn from(err: reqwest::Error) -> Self {
+        EmbeddingError::Other(err.into())
+    }
+}
+
+impl From<anyhow::Error> for EmbeddingError {
+    fn from(err: anyhow::Error) -> Self {
+        EmbeddingError::Other(err)
+    }
+}
+
+/// POSTs `body` to `url`, retrying on connection errors, HTTP 429, and 5xx with exponential
+/// backoff (base 500ms, doubling, jittered), honoring `Retry-After` when set.
+async fn post_with_retry<T: DeserializeOwned>(
+    client: &reqwest::Client,
+    url: &str,
+    body: &serde_json::Value,
+) -> Result<T, EmbeddingError> {
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let result = client.post(url).json(body).send().await;
+
+        let response = match result {
+            Ok(response) => response,
+            Err(err) if attempt < MAX_ATTEMPTS => {
+                tokio::time::sleep(backoff_delay(attempt, None)).await;
+                tracing::warn!(%err, attempt, "embedding request failed, retrying");
+                continue;
+            }
+            Err(err) => return Err(EmbeddingError::Other(err.into())),
+        };
+
+        let status = response.status();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = retry_after_header(&response);
+            if attempt < MAX_ATTEMPTS {
+                tokio::time::sleep(backoff_delay(attempt, retry_after)).await;
+                tracing::warn!(attempt, "embedding provider rate limited, retrying");
+                continue;
+            }
+            return Err(EmbeddingError::RateLimited { retry_after });
+        }
+        if status.is_server_error() {
+            if attempt < MAX_ATTEMPTS {
+                tokio::time::sleep(backoff_delay(attempt, None)).await;
+                tracing::warn!(%status, attempt, "embedding provider server error, retrying");
+                continue;
+            }
+            return Err(EmbeddingError::ServerError(status.to_string()));
+        }
+
+        return response
+            .error_for_status()
+            .map_err(|err| EmbeddingError::Other(err.into()))?
+            .json::<T>()
+            .await
+            .map_err(|err| EmbeddingError::Other(err.into()));
+    }
+}
+
+fn retry_after_header(response: &reqwest::Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+fn backoff_delay(attempt: u32, retry_after: Option<Duration>) -> Duration {
+    if let Some(retry_after) = retry_after {
+        return retry_after;
+    }
+    let base = BASE_BACKOFF * 2u32.pow(attempt.saturating_sub(1));
+    let jitter_ms = rand::rng().random_range(0..100);
+    base + Duration::from_millis(jitter_ms)
+}
+
+#[derive(Debug, Deserialize)]
+struct VoyageAIEmbeddingResponse {
+    pub data: Vec<VoyageAIEmbedding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VoyageAIEmbedding {
+    pub embedding: Vec<f32>,
+}
+
+/// Turns text into vectors for `vec_mem_entity1`/`mem_entity.embedding` similarity search.
+/// Implemented by `VoyageAiEmbeddingProvider`, `OpenAiCompatibleEmbeddingProvider` and
+/// `LocalEmbeddingProvider`; the storage layer depends on this trait rather than on any one
+/// of them, so a backend can be swapped in without touching any SQL.
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync + std::fmt::Debug {
+    /// The dimensionality of vectors this provider returns; storage uses it to size columns.
+    fn dimensions(&self) -> u16;
+
+    /// Embeds a single piece of text.
+    async fn embed(&self, text: &str) -> Result<Vec<f32>>;
+
+    /// Embeds several texts in one round-trip. Returned vectors are ordered to match `texts`.
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>>;
+}
+
+#[derive(Debug, Clone)]
+pub struct VoyageAiEmbeddingProvider {
+    model: String,
+    dimensions: u16,
+    http_client: reqwest::Client,
+}
+
+impl VoyageAiEmbeddingProvider {
+    pub fn new(api_key: &secrecy::SecretString, model: String, dimensions: u16) -> Result<Self> {
+        Ok(Self {
+            model,
+            dimensions,
+            http_client: reqwest::ClientBuilder::new()
+                .timeout(REQUEST_TIMEOUT)
+                .default_headers({
+                    let mut headers = reqwest::header::HeaderMap::new();
+                    headers.insert(
+                        "Content-Type",
+                        reqwest::header::HeaderValue::from_static("application/json"),
+                    );
+                    headers.insert(
+                        "Authorization",
+                        format!("Bearer {}", api_key.expose_secret()).parse()?,
+                    );
+                    headers
+                })
+                .build()?,
+        })
+    }
+
+    async fn request(&self, input: serde_json::Value) -> Result<Vec<Vec<f32>>> {
+        let res: VoyageAIEmbeddingResponse = post_with_retry(
+            &self.http_client,
+            VOYAGEAI_URL,
+            &serde_json::json!({
+                "model": self.model,
+                "output_dimension": self.dimensions,
+                "input": input,
+            }),
+        )
+        .await?;
+        Ok(res.data.into_iter().map(|e| e.embedding).collect())
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for VoyageAiEmbeddingProvider {
+    fn dimensions(&self) -> u16 {
+        self.dimensions
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let mut embeddings = self.request(serde_json::json!(text)).await?;
+        if embeddings.is_empty() {
+            anyhow::bail!("No embedding generated");
+        }
+        Ok(embeddings.remove(0))
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(vec![]);
+        }
+        let embeddings = self.request(serde_json::json!(texts)).await?;
+        if embeddings.len() != texts.len() {
+            anyhow::bail!(
+                "VoyageAI returned {} embeddings for {} inputs",
+                embeddings.len(),
+                texts.len()
+            );
+        }
+        Ok(embeddings)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiEmbeddingResponse {
+    data: Vec<OpenAiEmbedding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiEmbedding {
+    embedding: Vec<f32>,
+}
+
+/// An embedding backend speaking the OpenAI `POST /v1/embeddings` shape, for self-hosted or
+/// third-party endpoints that implement it (e.g. a local vLLM/Ollama deployment).
+#[derive(Debug, Clone)]
+pub struct OpenAiCompatibleEmbeddingProvider {
+    url: String,
+    model: String,
+    dimensions: u16,
+    http_client: reqwest::Client,
+}
+
+impl OpenAiCompatibleEmbeddingProvider {
+    pub fn new(
+        base_url: &str,
+        api_key: &secrecy::SecretString,
+        model: String,
+        dimensions: u16,
+    ) -> Result<Self> {
+        Ok(Self {
+            url: format!("{}{OPENAI_COMPATIBLE_PATH}", base_url.trim_end_matches('/')),
+            model,
+            dimensions,
+            http_client: reqwest::ClientBuilder::new()
+                .timeout(REQUEST_TIMEOUT)
+                .default_headers({
+                    let mut headers = reqwest::header::HeaderMap::new();
+                    headers.insert(
+                        "Content-Type",
+                        reqwest::header::HeaderValue::from_static("application/json"),
+                    );
+                    headers.insert(
+                        "Authorization",
+                        format!("Bearer {}", api_key.expose_secret()).parse()?,
+                    );
+                    headers
+                })
+                .build()?,
+        })
+    }
+
+    async fn request(&self, input: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let res: OpenAiEmbeddingResponse = post_with_retry(
+            &self.http_client,
+            &self.url,
+            &serde_json::json!({
+                "model": self.model,
+                "input": input,
+            }),
+        )
+        .await?;
+        Ok(res.data.into_iter().map(|e| e.embedding).collect())
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAiCompatibleEmbeddingProvider {
+    fn dimensions(&self) -> u16 {
+        self.dimensions
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let mut embeddings = self.request(&[text]).await?;
+        if embeddings.is_empty() {
+            anyhow::bail!("No embedding generated");
+        }
+        Ok(embeddings.remove(0))
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(vec![]);
+        }
+        let embeddings = self.request(texts).await?;
+        if embeddings.len() != texts.len() {
+            anyhow::bail!(
+                "Embedding endpoint returned {} embeddings for {} inputs",
+                embeddings.len(),
+                texts.len()
+            );
+        }
+        Ok(embeddings)
+    }
+}
+
+/// Reserved for a local/offline model (see `config::EmbeddingProviderConfig::Local`). No
+/// ONNX/candle runtime is wired up yet, so every call fails with a clear error rather than
+/// silently falling back to a remote provider.
+#[derive(Debug, Clone)]
+pub struct LocalEmbeddingProvider {
+    model_path: String,
+    dimensions: u16,
+}
+
+impl LocalEmbeddingProvider {
+    pub fn new(model_path: String, dimensions: u16) -> Self {
+        Self {
+            model_path,
+            dimensions,
+        }
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for LocalEmbeddingProvider {
+    fn dimensions(&self) -> u16 {
+        self.dimensions
+    }
+
+    async fn embed(&self, _text: &str) -> Result<Vec<f32>> {
+        anyhow::bail!(
+            "local embedding model at {} not loaded: no local embedding runtime is wired up yet",
+            self.model_path
+        )
+    }
+
+    async fn embed_batch(&self, _texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        anyhow::bail!(
+            "local embedding model at {} not loaded: no local embedding runtime is wired up yet",
+            self.model_path
+        )
+    }
+}
+
+/// Builds the embedding provider selected by `config.embedding_provider`.
+pub fn create_embedding_provider(config: &Config) -> Result<std::sync::Arc<dyn EmbeddingProvider>> {
+    build_embedding_provider(&config.embedding_provider)
+}
+
+pub fn build_embedding_provider(
+    config: &EmbeddingProviderConfig,
+) -> Result<std::sync::Arc<dyn EmbeddingProvider>> {
+    Ok(match config {
+        EmbeddingProviderConfig::VoyageAi {
+            api_key,
+            model,
+            dimensions,
+        } => std::sync::Arc::new(VoyageAiEmbeddingProvider::new(
+            api_key,
+            model.clone(),
+            *dimensions,
+        )?),
+        EmbeddingProviderConfig::OpenAiCompatible {
+            base_url,
+            api_key,
+            model,
+            dimensions,
+        } => std::sync::Arc::new(OpenAiCompatibleEmbeddingProvider::new(
+            base_url,
+            api_key,
+            model.clone(),
+            *dimensions,
+        )?),
+        EmbeddingProviderConfig::Local {
+            model_path,
+            dimensions,
+        } => std::sync::Arc::new(LocalEmbeddingProvider::new(model_path.clone(), *dimensions)),
+    })
+}
@@ -0,0 +1,91 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! Structured error type for the provider/streaming/HTTP boundary. `anyhow::Error` (the
+//! convention everywhere else in this codebase) is built for logging a report chain, not for a
+//! caller to branch on or for a value to cross a process boundary — `AgentError` trades that
+//! flexibility for a small, closed, serializable set of variants so the scheduler can apply
+//! class-specific retry/backoff (e.g. honor `RateLimited`'s `retry_after`) and an HTTP handler can
+//! map a failure to a proper status code instead of everything collapsing to 500.
+//!
+//! `AgentError` implements `std::error::Error` (via `thiserror`), so it converts into
+//! `anyhow::Error` for free through anyhow's blanket `From` impl — callers further up the stack
+//! that still return `anyhow::Result` keep using `?` unchanged.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Error)]
+pub enum AgentError {
+    /// The provider responded with a non-success HTTP status that isn't rate limiting.
+    #[error("provider returned {status}: {message}")]
+    Provider { status: u16, message: String },
+
+    /// The provider responded with HTTP 429, optionally telling us how long to back off.
+    #[error("rate limited{}", .retry_after.map(|s| format!(", retry after {s}s")).unwrap_or_default())]
+    RateLimited { retry_after: Option<u64> },
+
+    /// The request never reached the provider, or its response never came back (DNS, TLS,
+    /// connection reset, timeout, ...).
+    #[error("transport error: {0}")]
+    Transport(String),
+
+    /// A response body (or part of a streamed one) didn't match the shape we expected.
+    #[error("parse error: {0}")]
+    Parse(String),
+
+    /// A provider profile or task is misconfigured (missing api key, unresolvable chain, ...).
+    #[error("configuration error: {0}")]
+    Config(String),
+
+    /// A database operation needed to serve the request failed.
+    #[error("database error: {0}")]
+    Db(String),
+}
+
+impl AgentError {
+    /// `true` for failures worth retrying: rate limiting, a 5xx from the provider, or a
+    /// transport-level failure — as opposed to e.g. `Parse`/`Config`, which would fail identically
+    /// on a retry. Used both by `providers::ProviderRouter` (to decide whether to fall back to the
+    /// next provider profile) and by `agent::Agent::run`'s dispatch loop (to decide whether a
+    /// failed task is worth rescheduling via `state::AgentState::reschedule_task_with_backoff` at
+    /// all, or should be dead-lettered immediately).
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            AgentError::RateLimited { .. } | AgentError::Transport(_) => true,
+            AgentError::Provider { status, .. } => *status == 429 || (500..600).contains(status),
+            AgentError::Parse(_) | AgentError::Config(_) | AgentError::Db(_) => false,
+        }
+    }
+}
+
+/// Maps each variant to a status code an HTTP client can act on (in particular `RateLimited`'s
+/// `429` with a `Retry-After` header) instead of every failure collapsing to a generic 500, the way
+/// `ErrorInternalServerError(...)`-wrapped `anyhow::Error`s do elsewhere in `communication::http`.
+/// `actix_web::Error` has a blanket `From<T: ResponseError>`, so a handler returning
+/// `Result<_, AgentError>` gets this for free via `?` — no handler in this tree calls anything that
+/// can fail with `AgentError` today (the provider/streaming path runs in background task loops, not
+/// request handlers), but any that grows to should.
+impl actix_web::ResponseError for AgentError {
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        use actix_web::http::StatusCode;
+        match self {
+            AgentError::Provider { status, .. } => {
+                StatusCode::from_u16(*status).unwrap_or(StatusCode::BAD_GATEWAY)
+            }
+            AgentError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            AgentError::Transport(_) | AgentError::Parse(_) => StatusCode::BAD_GATEWAY,
+            AgentError::Config(_) | AgentError::Db(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> actix_web::HttpResponse {
+        let mut builder = actix_web::HttpResponse::build(self.status_code());
+        if let AgentError::RateLimited {
+            retry_after: Some(secs),
+        } = self
+        {
+            builder.insert_header(("Retry-After", secs.to_string()));
+        }
+        builder.json(serde_json::json!({ "error": self.to_string() }))
+    }
+}
@@ -1,6 +1,11 @@
 // Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
 
-use anyhow::Result;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use base64::Engine;
+use crypto_box::{PublicKey, SecretKey};
 use hulyrs::services::core::WorkspaceUuid;
 use reqwest::{
     Client, Url,
@@ -8,111 +13,875 @@ use reqwest::{
     multipart::{Form, Part},
 };
 use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt};
 
 use super::ServerConfig;
+use super::blob_encryption;
 
-#[derive(Clone)]
-enum LakeProvider {
-    Hulylake(Url),
-    Datalake(Url),
+/// Blobs at or above this size are uploaded via the multipart lifecycle instead of in one shot.
+/// Also the size of every part except the last one.
+const MULTIPART_PART_SIZE: usize = 8 * 1024 * 1024;
+/// How many parts may be uploading at once.
+const MULTIPART_CONCURRENCY: usize = 4;
+/// How many attempts a single part gets (the first try included) before the whole upload is
+/// aborted.
+const MULTIPART_PART_RETRIES: u32 = 3;
+
+#[derive(Deserialize)]
+struct InitiateMultipartResponse {
+    upload_id: String,
 }
-#[derive(Clone)]
-pub struct BlobClient {
-    upload_url: LakeProvider,
+
+#[derive(Serialize)]
+struct CompleteMultipartPart {
+    part_number: u32,
+    etag: String,
+}
+
+#[derive(Serialize)]
+struct CompleteMultipartRequest {
+    parts: Vec<CompleteMultipartPart>,
+}
+
+struct UploadedPart {
+    part_number: u32,
+    etag: String,
+}
+
+/// Backend-agnostic blob storage operations. `BlobClient` holds an `Arc<dyn BlobBackend>` and
+/// layers encryption, content-addressed dedup, and the chunked multipart upload path uniformly
+/// on top of whichever backend is selected (see `BlobClient::new`), so a new store only means
+/// implementing these seven methods — `S3Backend` below is the template for one that speaks the
+/// S3 object API (Garage, MinIO, AWS S3).
+#[async_trait]
+trait BlobBackend: Send + Sync {
+    /// Uploads `content` as the complete body for `blob_id` in one request.
+    async fn upload(&self, blob_id: &str, mime_type: &str, content: Vec<u8>) -> Result<()>;
+    /// Fetches `url` and returns its raw bytes.
+    async fn download(&self, url: Url) -> Result<Vec<u8>>;
+    /// Best-effort existence check for content-addressed dedup; `false` if the backend can't
+    /// answer (no per-blob resource, or the request itself failed).
+    async fn exists(&self, blob_id: &str) -> bool;
+    async fn initiate_multipart(&self, blob_id: &str, mime_type: &str) -> Result<String>;
+    async fn upload_part(
+        &self,
+        blob_id: &str,
+        upload_id: &str,
+        part_number: u32,
+        data: Vec<u8>,
+    ) -> Result<String>;
+    async fn complete_multipart(
+        &self,
+        blob_id: &str,
+        upload_id: &str,
+        parts: Vec<UploadedPart>,
+    ) -> Result<()>;
+    async fn abort_multipart(&self, blob_id: &str, upload_id: &str) -> Result<()>;
+}
+
+/// Checks `response`'s status, turning a non-2xx into an `Err` carrying `action` and `blob_id`
+/// for context. Shared by every backend's multipart calls.
+async fn checked(
+    request: reqwest::RequestBuilder,
+    blob_id: &str,
+    action: &str,
+) -> Result<reqwest::Response> {
+    let response = request
+        .send()
+        .await
+        .with_context(|| format!("Error while {action} for {blob_id}"))?;
+    if !response.status().is_success() {
+        anyhow::bail!(
+            "Error status={}, while {action} for {blob_id}",
+            response.status()
+        );
+    }
+    Ok(response)
+}
+
+fn decode_x25519_key(base64_key: &str, what: &str) -> Result<[u8; 32]> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(base64_key)
+        .with_context(|| format!("Failed to base64-decode {what}"))?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("{what} must be 32 bytes"))
+}
+
+// ---------------------------------------------------------------------------
+// Hulylake
+// ---------------------------------------------------------------------------
+
+struct HulylakeBackend {
+    base: Url,
     token: SecretString,
     http: Client,
 }
 
+impl HulylakeBackend {
+    fn object_url(&self, blob_id: &str) -> Result<Url> {
+        Ok(self.base.join(blob_id)?)
+    }
+
+    fn multipart_initiate_url(&self, blob_id: &str) -> Result<Url> {
+        let mut url = self.object_url(blob_id)?;
+        url.query_pairs_mut().append_key_only("uploads");
+        Ok(url)
+    }
+
+    fn multipart_part_url(&self, blob_id: &str, upload_id: &str, part_number: u32) -> Result<Url> {
+        let mut url = self.object_url(blob_id)?;
+        url.query_pairs_mut()
+            .append_pair("partNumber", &part_number.to_string())
+            .append_pair("uploadId", upload_id);
+        Ok(url)
+    }
+
+    fn multipart_complete_or_abort_url(&self, blob_id: &str, upload_id: &str) -> Result<Url> {
+        let mut url = self.object_url(blob_id)?;
+        url.query_pairs_mut().append_pair("uploadId", upload_id);
+        Ok(url)
+    }
+}
+
+#[async_trait]
+impl BlobBackend for HulylakeBackend {
+    async fn upload(&self, blob_id: &str, mime_type: &str, content: Vec<u8>) -> Result<()> {
+        let size = content.len();
+        checked(
+            self.http
+                .put(self.object_url(blob_id)?)
+                .headers(HeaderMap::from_iter(vec![
+                    (header::CONTENT_TYPE, HeaderValue::from_str(mime_type)?),
+                    (
+                        header::CONTENT_LENGTH,
+                        HeaderValue::from_str(&size.to_string())?,
+                    ),
+                ]))
+                .body(content)
+                .bearer_auth(self.token.expose_secret()),
+            blob_id,
+            "uploading file",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn download(&self, url: Url) -> Result<Vec<u8>> {
+        let response = checked(
+            self.http.get(url.clone()).bearer_auth(self.token.expose_secret()),
+            url.as_str(),
+            "downloading file",
+        )
+        .await?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    async fn exists(&self, blob_id: &str) -> bool {
+        let Ok(url) = self.object_url(blob_id) else {
+            return false;
+        };
+        self.http
+            .head(url)
+            .bearer_auth(self.token.expose_secret())
+            .send()
+            .await
+            .is_ok_and(|response| response.status().is_success())
+    }
+
+    async fn initiate_multipart(&self, blob_id: &str, mime_type: &str) -> Result<String> {
+        let url = self.multipart_initiate_url(blob_id)?;
+        let response = checked(
+            self.http
+                .post(url)
+                .bearer_auth(self.token.expose_secret())
+                .header(header::CONTENT_TYPE, mime_type),
+            blob_id,
+            "initiating multipart upload",
+        )
+        .await?;
+        Ok(response
+            .json::<InitiateMultipartResponse>()
+            .await
+            .with_context(|| format!("Invalid initiate-multipart response for {blob_id}"))?
+            .upload_id)
+    }
+
+    async fn upload_part(
+        &self,
+        blob_id: &str,
+        upload_id: &str,
+        part_number: u32,
+        data: Vec<u8>,
+    ) -> Result<String> {
+        let url = self.multipart_part_url(blob_id, upload_id, part_number)?;
+        let response = checked(
+            self.http
+                .put(url)
+                .bearer_auth(self.token.expose_secret())
+                .body(data),
+            blob_id,
+            &format!("uploading part {part_number}"),
+        )
+        .await?;
+        response
+            .headers()
+            .get(header::ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(|etag| etag.trim_matches('"').to_string())
+            .ok_or_else(|| {
+                anyhow::anyhow!("Missing ETag header for part {part_number} of {blob_id}")
+            })
+    }
+
+    async fn complete_multipart(
+        &self,
+        blob_id: &str,
+        upload_id: &str,
+        parts: Vec<UploadedPart>,
+    ) -> Result<()> {
+        let url = self.multipart_complete_or_abort_url(blob_id, upload_id)?;
+        let body = CompleteMultipartRequest {
+            parts: parts
+                .into_iter()
+                .map(|part| CompleteMultipartPart {
+                    part_number: part.part_number,
+                    etag: part.etag,
+                })
+                .collect(),
+        };
+        checked(
+            self.http
+                .post(url)
+                .bearer_auth(self.token.expose_secret())
+                .json(&body),
+            blob_id,
+            "completing multipart upload",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn abort_multipart(&self, blob_id: &str, upload_id: &str) -> Result<()> {
+        let url = self.multipart_complete_or_abort_url(blob_id, upload_id)?;
+        checked(
+            self.http.delete(url).bearer_auth(self.token.expose_secret()),
+            blob_id,
+            "aborting multipart upload",
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Datalake
+// ---------------------------------------------------------------------------
+
+struct DatalakeBackend {
+    base: Url,
+    token: SecretString,
+    http: Client,
+}
+
+impl DatalakeBackend {
+    /// Garage's S3-compatible multipart API lives beside the single-shot upload, at
+    /// `/upload/multipart/` in place of `/upload/form-data/`, with `uploads`/`uploadId` query
+    /// parameters layered on top (S3 convention).
+    fn multipart_base_url(&self, blob_id: &str) -> Result<Url> {
+        let multipart_url = self
+            .base
+            .as_str()
+            .replacen("/upload/form-data/", "/upload/multipart/", 1);
+        Ok(Url::parse(&multipart_url)?.join(blob_id)?)
+    }
+
+    fn multipart_initiate_url(&self, blob_id: &str) -> Result<Url> {
+        let mut url = self.multipart_base_url(blob_id)?;
+        url.query_pairs_mut().append_key_only("uploads");
+        Ok(url)
+    }
+
+    fn multipart_part_url(&self, blob_id: &str, upload_id: &str, part_number: u32) -> Result<Url> {
+        let mut url = self.multipart_base_url(blob_id)?;
+        url.query_pairs_mut()
+            .append_pair("partNumber", &part_number.to_string())
+            .append_pair("uploadId", upload_id);
+        Ok(url)
+    }
+
+    fn multipart_complete_or_abort_url(&self, blob_id: &str, upload_id: &str) -> Result<Url> {
+        let mut url = self.multipart_base_url(blob_id)?;
+        url.query_pairs_mut().append_pair("uploadId", upload_id);
+        Ok(url)
+    }
+}
+
+#[async_trait]
+impl BlobBackend for DatalakeBackend {
+    async fn upload(&self, blob_id: &str, mime_type: &str, content: Vec<u8>) -> Result<()> {
+        let size = content.len();
+        let file = Part::bytes(content)
+            .file_name(blob_id.to_string())
+            .mime_str(mime_type)?;
+        let form = Form::new()
+            .text("filename", blob_id.to_string())
+            .text("contentType", mime_type.to_owned())
+            .text("knownLength", size.to_string())
+            .part("file", file);
+        checked(
+            self.http
+                .post(self.base.clone())
+                .bearer_auth(self.token.expose_secret())
+                .multipart(form),
+            blob_id,
+            "uploading file",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn download(&self, url: Url) -> Result<Vec<u8>> {
+        let response = checked(
+            self.http.get(url.clone()).bearer_auth(self.token.expose_secret()),
+            url.as_str(),
+            "downloading file",
+        )
+        .await?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    /// Datalake's upload endpoint isn't addressed by blob id, so there's no per-blob resource to
+    /// HEAD — content-addressed dedup always falls through to a fresh upload on this backend.
+    async fn exists(&self, _blob_id: &str) -> bool {
+        false
+    }
+
+    async fn initiate_multipart(&self, blob_id: &str, mime_type: &str) -> Result<String> {
+        let url = self.multipart_initiate_url(blob_id)?;
+        let response = checked(
+            self.http
+                .post(url)
+                .bearer_auth(self.token.expose_secret())
+                .header(header::CONTENT_TYPE, mime_type),
+            blob_id,
+            "initiating multipart upload",
+        )
+        .await?;
+        Ok(response
+            .json::<InitiateMultipartResponse>()
+            .await
+            .with_context(|| format!("Invalid initiate-multipart response for {blob_id}"))?
+            .upload_id)
+    }
+
+    async fn upload_part(
+        &self,
+        blob_id: &str,
+        upload_id: &str,
+        part_number: u32,
+        data: Vec<u8>,
+    ) -> Result<String> {
+        let url = self.multipart_part_url(blob_id, upload_id, part_number)?;
+        let response = checked(
+            self.http
+                .put(url)
+                .bearer_auth(self.token.expose_secret())
+                .body(data),
+            blob_id,
+            &format!("uploading part {part_number}"),
+        )
+        .await?;
+        response
+            .headers()
+            .get(header::ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(|etag| etag.trim_matches('"').to_string())
+            .ok_or_else(|| {
+                anyhow::anyhow!("Missing ETag header for part {part_number} of {blob_id}")
+            })
+    }
+
+    async fn complete_multipart(
+        &self,
+        blob_id: &str,
+        upload_id: &str,
+        parts: Vec<UploadedPart>,
+    ) -> Result<()> {
+        let url = self.multipart_complete_or_abort_url(blob_id, upload_id)?;
+        let body = CompleteMultipartRequest {
+            parts: parts
+                .into_iter()
+                .map(|part| CompleteMultipartPart {
+                    part_number: part.part_number,
+                    etag: part.etag,
+                })
+                .collect(),
+        };
+        checked(
+            self.http
+                .post(url)
+                .bearer_auth(self.token.expose_secret())
+                .json(&body),
+            blob_id,
+            "completing multipart upload",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn abort_multipart(&self, blob_id: &str, upload_id: &str) -> Result<()> {
+        let url = self.multipart_complete_or_abort_url(blob_id, upload_id)?;
+        checked(
+            self.http.delete(url).bearer_auth(self.token.expose_secret()),
+            blob_id,
+            "aborting multipart upload",
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// S3-compatible (Garage, MinIO, AWS S3, ...)
+// ---------------------------------------------------------------------------
+
+/// Talks to any store that exposes the S3 object API — Garage, MinIO, AWS S3 itself. Objects are
+/// addressed as `{bucket}/{blob_id}` under `endpoint`, and the multipart lifecycle uses the same
+/// `uploads`/`uploadId`/`partNumber` query-parameter convention as `HulylakeBackend` (S3's own
+/// convention, already mirrored by Hulylake's API).
+///
+/// Auth here is a bearer token, same as the other two backends — this assumes `endpoint` sits
+/// behind a gateway that accepts it (e.g. a reverse proxy in front of Garage), not raw SigV4.
+/// Swapping in a SigV4 signer for stores that enforce it directly would only touch the handful of
+/// `bearer_auth` calls below.
+struct S3Backend {
+    endpoint: Url,
+    bucket: String,
+    token: SecretString,
+    http: Client,
+}
+
+impl S3Backend {
+    fn object_url(&self, blob_id: &str) -> Result<Url> {
+        Ok(self.endpoint.join(&format!("{}/{blob_id}", self.bucket))?)
+    }
+
+    fn multipart_initiate_url(&self, blob_id: &str) -> Result<Url> {
+        let mut url = self.object_url(blob_id)?;
+        url.query_pairs_mut().append_key_only("uploads");
+        Ok(url)
+    }
+
+    fn multipart_part_url(&self, blob_id: &str, upload_id: &str, part_number: u32) -> Result<Url> {
+        let mut url = self.object_url(blob_id)?;
+        url.query_pairs_mut()
+            .append_pair("partNumber", &part_number.to_string())
+            .append_pair("uploadId", upload_id);
+        Ok(url)
+    }
+
+    fn multipart_complete_or_abort_url(&self, blob_id: &str, upload_id: &str) -> Result<Url> {
+        let mut url = self.object_url(blob_id)?;
+        url.query_pairs_mut().append_pair("uploadId", upload_id);
+        Ok(url)
+    }
+}
+
+#[async_trait]
+impl BlobBackend for S3Backend {
+    async fn upload(&self, blob_id: &str, mime_type: &str, content: Vec<u8>) -> Result<()> {
+        let size = content.len();
+        checked(
+            self.http
+                .put(self.object_url(blob_id)?)
+                .headers(HeaderMap::from_iter(vec![
+                    (header::CONTENT_TYPE, HeaderValue::from_str(mime_type)?),
+                    (
+                        header::CONTENT_LENGTH,
+                        HeaderValue::from_str(&size.to_string())?,
+                    ),
+                ]))
+                .body(content)
+                .bearer_auth(self.token.expose_secret()),
+            blob_id,
+            "uploading file",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn download(&self, url: Url) -> Result<Vec<u8>> {
+        let response = checked(
+            self.http.get(url.clone()).bearer_auth(self.token.expose_secret()),
+            url.as_str(),
+            "downloading file",
+        )
+        .await?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    async fn exists(&self, blob_id: &str) -> bool {
+        let Ok(url) = self.object_url(blob_id) else {
+            return false;
+        };
+        self.http
+            .head(url)
+            .bearer_auth(self.token.expose_secret())
+            .send()
+            .await
+            .is_ok_and(|response| response.status().is_success())
+    }
+
+    async fn initiate_multipart(&self, blob_id: &str, mime_type: &str) -> Result<String> {
+        let url = self.multipart_initiate_url(blob_id)?;
+        let response = checked(
+            self.http
+                .post(url)
+                .bearer_auth(self.token.expose_secret())
+                .header(header::CONTENT_TYPE, mime_type),
+            blob_id,
+            "initiating multipart upload",
+        )
+        .await?;
+        Ok(response
+            .json::<InitiateMultipartResponse>()
+            .await
+            .with_context(|| format!("Invalid initiate-multipart response for {blob_id}"))?
+            .upload_id)
+    }
+
+    async fn upload_part(
+        &self,
+        blob_id: &str,
+        upload_id: &str,
+        part_number: u32,
+        data: Vec<u8>,
+    ) -> Result<String> {
+        let url = self.multipart_part_url(blob_id, upload_id, part_number)?;
+        let response = checked(
+            self.http
+                .put(url)
+                .bearer_auth(self.token.expose_secret())
+                .body(data),
+            blob_id,
+            &format!("uploading part {part_number}"),
+        )
+        .await?;
+        response
+            .headers()
+            .get(header::ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(|etag| etag.trim_matches('"').to_string())
+            .ok_or_else(|| {
+                anyhow::anyhow!("Missing ETag header for part {part_number} of {blob_id}")
+            })
+    }
+
+    async fn complete_multipart(
+        &self,
+        blob_id: &str,
+        upload_id: &str,
+        parts: Vec<UploadedPart>,
+    ) -> Result<()> {
+        let url = self.multipart_complete_or_abort_url(blob_id, upload_id)?;
+        let body = CompleteMultipartRequest {
+            parts: parts
+                .into_iter()
+                .map(|part| CompleteMultipartPart {
+                    part_number: part.part_number,
+                    etag: part.etag,
+                })
+                .collect(),
+        };
+        checked(
+            self.http
+                .post(url)
+                .bearer_auth(self.token.expose_secret())
+                .json(&body),
+            blob_id,
+            "completing multipart upload",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn abort_multipart(&self, blob_id: &str, upload_id: &str) -> Result<()> {
+        let url = self.multipart_complete_or_abort_url(blob_id, upload_id)?;
+        checked(
+            self.http.delete(url).bearer_auth(self.token.expose_secret()),
+            blob_id,
+            "aborting multipart upload",
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// BlobClient
+// ---------------------------------------------------------------------------
+
+#[derive(Clone)]
+pub struct BlobClient {
+    backend: Arc<dyn BlobBackend>,
+    encryption_public_key: Option<PublicKey>,
+    encryption_secret_key: Option<SecretKey>,
+    content_addressed: bool,
+}
+
 impl BlobClient {
     pub fn new(
         config: &ServerConfig,
         workspace: WorkspaceUuid,
         token: impl Into<SecretString>,
     ) -> Result<Self> {
-        let upload_url = if let Some(url) = &config.datalake_url {
-            LakeProvider::Datalake(Url::parse(&format!("{url}/upload/form-data/{workspace}",))?)
-        } else if let Some(url) = &config.hulylake_url {
-            LakeProvider::Hulylake(Url::parse(&format!("{url}/api/${workspace}",))?)
-        } else {
-            anyhow::bail!("Hulylake URL is not configured")
-        };
-
+        let token = token.into();
         let http = Client::new();
+
+        let backend: Arc<dyn BlobBackend> =
+            if let (Some(endpoint), Some(bucket)) = (&config.s3_endpoint, &config.s3_bucket) {
+                Arc::new(S3Backend {
+                    endpoint: Url::parse(endpoint)?,
+                    bucket: bucket.clone(),
+                    token,
+                    http,
+                })
+            } else if let Some(url) = &config.datalake_url {
+                Arc::new(DatalakeBackend {
+                    base: Url::parse(&format!("{url}/upload/form-data/{workspace}",))?,
+                    token,
+                    http,
+                })
+            } else if let Some(url) = &config.hulylake_url {
+                Arc::new(HulylakeBackend {
+                    base: Url::parse(&format!("{url}/api/${workspace}",))?,
+                    token,
+                    http,
+                })
+            } else {
+                anyhow::bail!("Hulylake URL is not configured")
+            };
+
+        let encryption_public_key = config
+            .blob_encryption_public_key
+            .as_deref()
+            .map(|key| decode_x25519_key(key, "blob_encryption_public_key"))
+            .transpose()?
+            .map(PublicKey::from);
+        let encryption_secret_key = config
+            .blob_encryption_secret_key
+            .as_deref()
+            .map(|key| decode_x25519_key(key, "blob_encryption_secret_key"))
+            .transpose()?
+            .map(SecretKey::from);
+
         Ok(Self {
-            upload_url,
-            token: token.into(),
-            http,
+            backend,
+            encryption_public_key,
+            encryption_secret_key,
+            content_addressed: config.content_addressed_uploads,
         })
     }
 
+    /// Uploads `content` and returns the blob id it was stored under. Normally that's just
+    /// `blob_id` echoed back; in content-addressed mode (see `ServerConfig::content_addressed_uploads`)
+    /// it's a BLAKE3 digest of `content` instead, and the upload is skipped entirely if a blob
+    /// with that digest already exists.
     pub async fn upload_file(
         &self,
         blob_id: &str,
         mime_type: &str,
         content: Vec<u8>,
-    ) -> Result<()> {
+    ) -> Result<String> {
         tracing::debug!(
             %blob_id,
             %mime_type,
             "Uploading file"
         );
-        let size = content.len();
-        let request = match &self.upload_url {
-            LakeProvider::Datalake(url) => {
-                let file = Part::bytes(content)
-                    .file_name(blob_id.to_string())
-                    .mime_str(mime_type)?;
-                let form = Form::new()
-                    .text("filename", blob_id.to_string())
-                    .text("contentType", mime_type.to_owned())
-                    .text("knownLength", size.to_string())
-                    .part("file", file);
-
-                self.http
-                    .post(url.clone())
-                    .bearer_auth(self.token.expose_secret())
-                    .multipart(form)
-            }
-            LakeProvider::Hulylake(url) => self
-                .http
-                .put(url.clone().join(blob_id)?)
-                .headers(HeaderMap::from_iter(vec![
-                    (header::CONTENT_TYPE, HeaderValue::from_str(mime_type)?),
-                    (
-                        header::CONTENT_LENGTH,
-                        HeaderValue::from_str(&size.to_string())?,
-                    ),
-                ]))
-                .body(content)
-                .bearer_auth(self.token.expose_secret()),
+
+        let blob_id = if self.content_addressed {
+            blake3::hash(&content).to_hex().to_string()
+        } else {
+            blob_id.to_string()
         };
 
-        match request.send().await {
-            Ok(response) => {
-                if response.status().is_success() {
-                    let _ = response.bytes().await?;
-                    tracing::debug!("Uploading file successfully");
-                    Ok(())
-                } else {
-                    tracing::error!(%blob_id,
-                        status = %response.status(),
-                        "Error status, while uploading file"
-                    );
-                    Err(anyhow::anyhow!(
-                        "Error status={}, while uploading file",
-                        response.status()
-                    ))
+        if self.content_addressed && self.backend.exists(&blob_id).await {
+            tracing::debug!(%blob_id, "Blob already exists, skipping upload");
+            return Ok(blob_id);
+        }
+
+        let content = match &self.encryption_public_key {
+            Some(public_key) => blob_encryption::encrypt(&content, public_key)
+                .with_context(|| format!("Failed to encrypt blob {blob_id}"))?,
+            None => content,
+        };
+        self.backend.upload(&blob_id, mime_type, content).await?;
+        Ok(blob_id)
+    }
+
+    /// Uploads `reader`. Blobs under `MULTIPART_PART_SIZE` take the same single-request path as
+    /// `upload_file`; larger ones are split into parts and uploaded via the multipart lifecycle
+    /// (initiate, upload parts with bounded concurrency, complete), retrying only the parts that
+    /// fail. All parts are read into memory before the first one is sent, so the whole blob is
+    /// held in memory during a multipart upload.
+    ///
+    /// Encryption (see `blob_encryption`) uses one AES-GCM nonce for the whole blob, so it can't
+    /// be applied per part: when `blob_encryption_public_key` is configured, the plaintext is
+    /// buffered fully and encrypted once before being re-chunked into parts for upload.
+    pub async fn upload_stream(
+        &self,
+        blob_id: &str,
+        mime_type: &str,
+        mut reader: impl AsyncRead + Unpin,
+    ) -> Result<()> {
+        match &self.encryption_public_key {
+            Some(public_key) => {
+                let mut content = Vec::new();
+                reader.read_to_end(&mut content).await?;
+                let ciphertext = blob_encryption::encrypt(&content, public_key)
+                    .with_context(|| format!("Failed to encrypt blob {blob_id}"))?;
+                self.upload_stream_plain(blob_id, mime_type, std::io::Cursor::new(ciphertext))
+                    .await
+            }
+            None => self.upload_stream_plain(blob_id, mime_type, reader).await,
+        }
+    }
+
+    /// Splits `reader` into `MULTIPART_PART_SIZE` chunks, reading one part ahead of the
+    /// size-threshold decision so small blobs still take the single-shot path.
+    async fn upload_stream_plain(
+        &self,
+        blob_id: &str,
+        mime_type: &str,
+        mut reader: impl AsyncRead + Unpin,
+    ) -> Result<()> {
+        let first_part = read_part(&mut reader).await?;
+        if first_part.len() < MULTIPART_PART_SIZE {
+            return self.backend.upload(blob_id, mime_type, first_part).await;
+        }
+
+        let mut parts = vec![first_part];
+        loop {
+            let part = read_part(&mut reader).await?;
+            let is_last = part.len() < MULTIPART_PART_SIZE;
+            parts.push(part);
+            if is_last {
+                break;
+            }
+        }
+
+        self.upload_multipart(blob_id, mime_type, parts).await
+    }
+
+    async fn upload_multipart(
+        &self,
+        blob_id: &str,
+        mime_type: &str,
+        parts: Vec<Vec<u8>>,
+    ) -> Result<()> {
+        let upload_id = self.backend.initiate_multipart(blob_id, mime_type).await?;
+        tracing::debug!(%blob_id, %upload_id, parts = parts.len(), "Initiated multipart upload");
+
+        let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new(MULTIPART_CONCURRENCY));
+        let mut join_set = tokio::task::JoinSet::new();
+        for (index, part) in parts.into_iter().enumerate() {
+            let part_number = index as u32 + 1;
+            let semaphore = semaphore.clone();
+            let this = self.clone();
+            let blob_id = blob_id.to_string();
+            let upload_id = upload_id.clone();
+            join_set.spawn(async move {
+                let _permit = semaphore
+                    .acquire_owned()
+                    .await
+                    .expect("upload semaphore never closes early");
+                this.upload_part_with_retry(&blob_id, &upload_id, part_number, part)
+                    .await
+            });
+        }
+
+        let mut uploaded = Vec::new();
+        let mut failed = false;
+        while let Some(result) = join_set.join_next().await {
+            match result {
+                Ok(Ok(part)) => uploaded.push(part),
+                Ok(Err(error)) => {
+                    tracing::error!(%blob_id, %upload_id, %error, "Part upload failed permanently");
+                    failed = true;
+                }
+                Err(join_error) => {
+                    tracing::error!(%blob_id, %upload_id, %join_error, "Part upload task panicked");
+                    failed = true;
                 }
             }
+        }
 
-            Err(error) => {
-                tracing::error!(%blob_id, %error, "Error while uploading file");
-                Err(anyhow::anyhow!(
-                    "Error while uploading file={}, error: {}",
-                    blob_id,
-                    error
-                ))
+        if failed {
+            if let Err(error) = self.backend.abort_multipart(blob_id, &upload_id).await {
+                tracing::error!(%blob_id, %upload_id, %error, "Failed to abort multipart upload");
             }
+            anyhow::bail!("Multipart upload of {blob_id} failed, aborted upload {upload_id}");
+        }
+
+        uploaded.sort_by_key(|part| part.part_number);
+        self.backend
+            .complete_multipart(blob_id, &upload_id, uploaded)
+            .await
+    }
+
+    async fn upload_part_with_retry(
+        &self,
+        blob_id: &str,
+        upload_id: &str,
+        part_number: u32,
+        data: Vec<u8>,
+    ) -> Result<UploadedPart> {
+        let mut last_error = None;
+        for attempt in 1..=MULTIPART_PART_RETRIES {
+            match self
+                .backend
+                .upload_part(blob_id, upload_id, part_number, data.clone())
+                .await
+            {
+                Ok(etag) => return Ok(UploadedPart { part_number, etag }),
+                Err(error) => {
+                    tracing::warn!(%blob_id, %upload_id, part_number, attempt, %error, "Part upload failed, retrying");
+                    last_error = Some(error);
+                }
+            }
+        }
+        Err(last_error
+            .unwrap_or_else(|| anyhow::anyhow!("Part {part_number} failed with no error recorded")))
+    }
+
+    /// Fetches `url` and, if `blob_encryption_secret_key` is configured, unwraps the header and
+    /// AES-GCM-decrypts it back to the original plaintext. When encryption isn't configured, the
+    /// fetched bytes are returned as-is.
+    pub async fn download_file(&self, url: Url) -> Result<Vec<u8>> {
+        tracing::debug!(%url, "Downloading file");
+        let content = self.backend.download(url.clone()).await?;
+        match &self.encryption_secret_key {
+            Some(secret_key) => blob_encryption::decrypt(&content, secret_key)
+                .with_context(|| format!("Failed to decrypt blob from {url}")),
+            None => Ok(content),
+        }
+    }
+}
+
+/// Reads up to `MULTIPART_PART_SIZE` bytes from `reader`, stopping early at EOF. The returned
+/// buffer's length being less than `MULTIPART_PART_SIZE` means the stream is exhausted.
+async fn read_part(reader: &mut (impl AsyncRead + Unpin)) -> Result<Vec<u8>> {
+    let mut buf = vec![0u8; MULTIPART_PART_SIZE];
+    let mut filled = 0;
+    while filled < buf.len() {
+        let n = reader.read(&mut buf[filled..]).await?;
+        if n == 0 {
+            break;
         }
+        filled += n;
     }
+    buf.truncate(filled);
+    Ok(buf)
 }
@@ -0,0 +1,98 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! Client-side envelope encryption for blobs, so Hulylake/Datalake only ever stores ciphertext.
+//! Each blob gets a fresh random 256-bit data key, used once with AES-256-GCM; the data key is
+//! then sealed (X25519 anonymous sealed box) to the workspace's public key, so only the holder of
+//! the matching private key can unwrap it. The sealed key and nonce are carried in a small CBOR
+//! header prepended to the ciphertext, length-prefixed so it can be read without buffering the
+//! rest of the blob.
+
+use aes_gcm::{
+    Aes256Gcm, Nonce,
+    aead::{Aead, AeadCore, KeyInit, OsRng as AesOsRng},
+};
+use anyhow::{Context, Result, bail};
+use crypto_box::{PublicKey, SecretKey, SealedBox, aead::OsRng as BoxOsRng};
+use serde::{Deserialize, Serialize};
+
+/// Bumped whenever the header shape or crypto choices change; `decrypt` rejects any other value.
+const HEADER_VERSION: u8 = 1;
+/// The only algorithm combination implemented so far: X25519 sealed-box key wrap + AES-256-GCM.
+const ALG_X25519_SEALEDBOX_AES256GCM: u8 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct BlobHeader {
+    version: u8,
+    alg: u8,
+    wrapped_key: Vec<u8>,
+    nonce: Vec<u8>,
+}
+
+/// Encrypts `content` for `public_key`, returning `[header_len: u32 LE][CBOR header][ciphertext]`.
+/// Generates a fresh data key and nonce on every call, so encrypting the same bytes twice never
+/// produces the same output.
+pub fn encrypt(content: &[u8], public_key: &PublicKey) -> Result<Vec<u8>> {
+    let data_key = Aes256Gcm::generate_key(&mut AesOsRng);
+    let nonce = Aes256Gcm::generate_nonce(&mut AesOsRng);
+    let cipher = Aes256Gcm::new(&data_key);
+    let ciphertext = cipher
+        .encrypt(&nonce, content)
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt blob"))?;
+
+    let wrapped_key = SealedBox::new(public_key)
+        .encrypt(&mut BoxOsRng, data_key.as_slice())
+        .map_err(|_| anyhow::anyhow!("Failed to wrap data key"))?;
+
+    let header = BlobHeader {
+        version: HEADER_VERSION,
+        alg: ALG_X25519_SEALEDBOX_AES256GCM,
+        wrapped_key,
+        nonce: nonce.to_vec(),
+    };
+    let mut header_bytes = Vec::new();
+    ciborium::ser::into_writer(&header, &mut header_bytes)
+        .context("Failed to serialize blob header")?;
+
+    let mut out = Vec::with_capacity(4 + header_bytes.len() + ciphertext.len());
+    out.extend_from_slice(&(header_bytes.len() as u32).to_le_bytes());
+    out.extend_from_slice(&header_bytes);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+/// Reverses `encrypt`: parses the header without touching the ciphertext, rejects an unknown
+/// `version`/`alg`, unwraps the data key with `secret_key`, then AES-GCM-decrypts the remainder,
+/// which fails if the GCM tag doesn't verify.
+pub fn decrypt(data: &[u8], secret_key: &SecretKey) -> Result<Vec<u8>> {
+    if data.len() < 4 {
+        bail!("Encrypted blob is too short to contain a header length");
+    }
+    let header_len = u32::from_le_bytes(data[..4].try_into().unwrap()) as usize;
+    let rest = &data[4..];
+    if rest.len() < header_len {
+        bail!("Encrypted blob is too short to contain its header");
+    }
+    let (header_bytes, ciphertext) = rest.split_at(header_len);
+
+    let header: BlobHeader =
+        ciborium::de::from_reader(header_bytes).context("Failed to parse blob header")?;
+    if header.version != HEADER_VERSION {
+        bail!("Unsupported blob header version: {}", header.version);
+    }
+    if header.alg != ALG_X25519_SEALEDBOX_AES256GCM {
+        bail!("Unsupported blob encryption algorithm: {}", header.alg);
+    }
+
+    let data_key = SealedBox::new(&secret_key.public_key())
+        .decrypt(secret_key, header.wrapped_key.as_slice())
+        .map_err(|_| anyhow::anyhow!("Failed to unwrap data key"))?;
+    let cipher = Aes256Gcm::new_from_slice(&data_key).context("Invalid unwrapped data key length")?;
+    if header.nonce.len() != 12 {
+        bail!("Invalid blob header nonce length: {}", header.nonce.len());
+    }
+    let nonce = Nonce::from_slice(&header.nonce);
+
+    cipher
+        .decrypt(nonce, ciphertext)
+        .map_err(|_| anyhow::anyhow!("Failed to decrypt blob, GCM tag mismatch"))
+}
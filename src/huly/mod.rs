@@ -14,6 +14,9 @@ use serde::Deserialize;
 use serde_json::Value;
 
 pub mod blob;
+pub mod blob_encryption;
+pub mod resilient;
+pub mod streaming;
 pub mod types;
 pub mod typing;
 
@@ -27,6 +30,24 @@ pub struct ServerConfig {
     pub files_url: String,
     pub pulse_url: Url,
     pub collaborator_url: Url,
+    /// Base64-encoded X25519 public key for the workspace. When present, `BlobClient` encrypts
+    /// every blob client-side before upload (see `blob_encryption`); when absent, blobs are
+    /// uploaded as plaintext as before.
+    pub blob_encryption_public_key: Option<String>,
+    /// Base64-encoded X25519 secret key matching `blob_encryption_public_key`. Only needed to
+    /// decrypt previously-uploaded blobs via `BlobClient::download_file`; encryption-only
+    /// deployments can omit it.
+    pub blob_encryption_secret_key: Option<String>,
+    /// When true, `BlobClient::upload_file` derives `blob_id` from a BLAKE3 digest of the content
+    /// instead of using the caller-supplied id, and skips re-uploading blobs that already exist.
+    #[serde(default)]
+    pub content_addressed_uploads: bool,
+    /// Base URL of an S3-compatible store (Garage, MinIO, AWS S3). When set together with
+    /// `s3_bucket`, `BlobClient` uses it instead of `hulylake_url`/`datalake_url`, addressing
+    /// blobs as `{s3_bucket}/{blob_id}` under this endpoint.
+    pub s3_endpoint: Option<String>,
+    /// Bucket blobs are stored in on the S3-compatible store. Required when `s3_endpoint` is set.
+    pub s3_bucket: Option<String>,
 }
 
 pub async fn fetch_server_config(base_url: Url) -> Result<ServerConfig> {
@@ -0,0 +1,128 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! Reconnection-aware wrapper around `TransactorClient::find_one`, so a momentary transactor
+//! disconnect degrades `create_context`'s `${MODE_CONTEXT}` build to a stale-but-labeled cached
+//! value instead of it silently reporting a false "Offline".
+
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use chrono::{DateTime, Utc};
+use hulyrs::services::transactor::{
+    TransactorClient,
+    backend::http::HttpBackend,
+    document::{DocumentClient, FindOptions},
+};
+use serde_json::Value;
+
+/// Retry budget for one query before it falls back to the cache: `MAX_RETRIES` extra attempts
+/// beyond the first, each after `BASE_RETRY_DELAY * 2^attempt`.
+const MAX_RETRIES: u32 = 3;
+const BASE_RETRY_DELAY: Duration = Duration::from_millis(200);
+/// Bound on distinct `(class, query)` cache entries, so a long-running agent issuing many
+/// differently-shaped queries can't grow this unboundedly. The oldest entry by `fetched_at` is
+/// evicted to make room.
+const MAX_CACHE_ENTRIES: usize = 256;
+
+#[derive(Clone)]
+struct CacheEntry {
+    value: Option<Value>,
+    fetched_at: DateTime<Utc>,
+}
+
+/// Whether a `find_one_resilient` result came from a live query or the cache, so callers can
+/// report staleness instead of presenting a cached value as current.
+pub enum Staleness {
+    Live,
+    /// No successful query for this `(class, query)` has ever completed, so there's nothing to
+    /// serve — not even a stale value.
+    NeverFetched,
+    Cached { age: chrono::Duration },
+}
+
+/// Wraps a `TransactorClient` with bounded-retry reconnection and a last-known-value cache keyed
+/// by `(class, query)`. Cloning the inner client is cheap (it's the same pattern every other
+/// `huly` client uses), so this type owns its own clone rather than borrowing one.
+pub struct ResilientTransactor {
+    client: TransactorClient<HttpBackend>,
+    cache: Mutex<HashMap<(String, String), CacheEntry>>,
+    /// When the current outage started, if the last attempt failed. Cleared on the next success.
+    down_since: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl ResilientTransactor {
+    pub fn new(client: TransactorClient<HttpBackend>) -> Self {
+        Self {
+            client,
+            cache: Mutex::new(HashMap::new()),
+            down_since: Mutex::new(None),
+        }
+    }
+
+    /// Since when the transactor connection has been down, if it currently is. `None` means the
+    /// most recent query succeeded.
+    pub fn down_since(&self) -> Option<DateTime<Utc>> {
+        *self.down_since.lock().unwrap()
+    }
+
+    /// Retries `find_one(class, query, options)` with bounded exponential backoff. Once retries
+    /// are exhausted, falls back to the cached value for `(class, query)` tagged with its age, so
+    /// callers can report "stale, link down" instead of a false negative.
+    pub async fn find_one_resilient(
+        &self,
+        class: &str,
+        query: Value,
+        options: &FindOptions,
+    ) -> (Option<Value>, Staleness) {
+        let key = (class.to_string(), query.to_string());
+        let mut delay = BASE_RETRY_DELAY;
+
+        for attempt in 0..=MAX_RETRIES {
+            match self
+                .client
+                .find_one::<_, Value>(class, query.clone(), options)
+                .await
+            {
+                Ok(value) => {
+                    *self.down_since.lock().unwrap() = None;
+                    self.remember(key, value.clone());
+                    return (value, Staleness::Live);
+                }
+                Err(err) if attempt < MAX_RETRIES => {
+                    tracing::warn!(?err, attempt, class, "Transactor query failed, retrying");
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        ?err,
+                        class,
+                        "Transactor query exhausted retries, serving cached value"
+                    );
+                    self.down_since.lock().unwrap().get_or_insert(Utc::now());
+                }
+            }
+        }
+
+        match self.cache.lock().unwrap().get(&key) {
+            Some(entry) => (
+                entry.value.clone(),
+                Staleness::Cached { age: Utc::now() - entry.fetched_at },
+            ),
+            None => (None, Staleness::NeverFetched),
+        }
+    }
+
+    fn remember(&self, key: (String, String), value: Option<Value>) {
+        let mut cache = self.cache.lock().unwrap();
+        if !cache.contains_key(&key) && cache.len() >= MAX_CACHE_ENTRIES {
+            if let Some(oldest) = cache
+                .iter()
+                .min_by_key(|(_, entry)| entry.fetched_at)
+                .map(|(k, _)| k.clone())
+            {
+                cache.remove(&oldest);
+            }
+        }
+        cache.insert(key, CacheEntry { value, fetched_at: Utc::now() });
+    }
+}
@@ -6,27 +6,30 @@ use anyhow::{Context, Result, bail};
 use hulyrs::services::{
     card,
     core::{Space, storage::WithoutStructure},
-    transactor::{
-        self,
-        document::{DocumentClient, FindOptionsBuilder},
-    },
+    transactor::document::{DocumentClient, FindOptionsBuilder},
 };
-use percent_encoding::NON_ALPHANUMERIC;
-use rdkafka::consumer::{Consumer, StreamConsumer};
+use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 use types::{MessageType, ReceivedMessage, ThreadPatchOperation};
 
 use crate::{
     context::{self, CardInfo, SpaceInfo},
+    database::DbClient,
     huly::streaming::types::{
         CommunicationDomainEventKind, CommunicationEvent, CreateMessage, DomainEventKind,
-        PersonInfo, ReceivedAttachment, ReceivedReaction, StreamingMessage, StreamingMessageKind,
+        PatchMessageOperation, PersonInfo, ReceivedMessageEdit, ReceivedMessageRemoval,
+        ReceivedReaction, StreamingMessage, StreamingMessageKind,
     },
     task::MAX_FOLLOW_MESSAGES,
 };
 
 use super::types::{Person, SocialIdentity};
 
+pub mod event_source;
+pub mod handler;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod router;
 pub mod types;
 
 fn try_extract_communication_event_from_payload(
@@ -128,6 +131,10 @@ async fn get_space_info(
     })
 }
 
+/// `Some((is_mention, newly_followed))`: `newly_followed` is set the moment `msg.card_id` is
+/// first inserted into `follow_card_ids`, so the caller knows to run `backfill_card_history`
+/// before dispatching this message — otherwise the agent would join the conversation with no
+/// context beyond the single message that triggered the follow.
 async fn should_process_message(
     context: &mut context::MessagesContext,
     msg: &CreateMessage,
@@ -135,7 +142,7 @@ async fn should_process_message(
     ignore_card_ids: &HashSet<String>,
     follow_card_ids: &mut HashMap<String, u8>,
     persistent_cards: &mut HashSet<String>,
-) -> Option<bool> {
+) -> Option<(bool, bool)> {
     let card_info = get_card_info(context, &msg.card_id).await.ok()?;
     let space_info = get_space_info(context, &card_info.space).await.ok()?;
     if !space_info.can_read || ignore_card_ids.contains(&msg.card_id) {
@@ -146,26 +153,28 @@ async fn should_process_message(
             .parent
             .is_some_and(|parent_id| persistent_cards.contains(&parent_id))
     {
-        return Some(false);
+        return Some((false, false));
     }
 
     if msg.message_type == MessageType::Message && msg.content.contains(match_pattern) {
-        follow_card_ids.insert(msg.card_id.clone(), MAX_FOLLOW_MESSAGES);
-        return Some(true);
+        let newly_followed = follow_card_ids
+            .insert(msg.card_id.clone(), MAX_FOLLOW_MESSAGES)
+            .is_none();
+        return Some((true, newly_followed));
     } else if space_info.can_read && space_info.is_personal {
         // Nobody else can read messages from personal space, meaning it is direct-like message
         if follow_card_ids.contains_key(&msg.card_id) {
-            return Some(false);
+            return Some((false, false));
         } else {
             follow_card_ids.insert(msg.card_id.clone(), MAX_FOLLOW_MESSAGES);
-            return Some(true);
+            return Some((true, true));
         }
     } else if let Some(count) = follow_card_ids.get_mut(&msg.card_id) {
         *count = count.saturating_sub(1);
         if *count == 0 {
             follow_card_ids.remove(&msg.card_id);
         }
-        return Some(false);
+        return Some((false, false));
     }
 
     None
@@ -175,23 +184,241 @@ async fn enrich_create_message(
     context: &mut context::MessagesContext,
     msg: CreateMessage,
     is_mention: bool,
+    is_backfill: bool,
 ) -> Result<ReceivedMessage> {
     let mut msg = ReceivedMessage::from(msg);
     let card_info = get_card_info(context, &msg.card_id).await?;
     msg.person_info = get_person_info(context, &msg.social_id).await?;
     msg.parent_id = card_info.parent;
     msg.is_mention = is_mention;
+    msg.is_backfill = is_backfill;
     msg.card_title = Some(card_info.title);
     Ok(msg)
 }
 
-fn to_kafka_log_level(level: tracing::Level) -> rdkafka::config::RDKafkaLogLevel {
-    match level {
-        tracing::Level::ERROR => rdkafka::config::RDKafkaLogLevel::Error,
-        tracing::Level::WARN => rdkafka::config::RDKafkaLogLevel::Warning,
-        tracing::Level::INFO => rdkafka::config::RDKafkaLogLevel::Info,
-        tracing::Level::DEBUG => rdkafka::config::RDKafkaLogLevel::Debug,
-        tracing::Level::TRACE => rdkafka::config::RDKafkaLogLevel::Debug,
+/// Runs once, right after `should_process_message`/the `ThreadPatchOperation::Attach` handler
+/// first inserts `card_id` into `follow_card_ids`: fetches up to `limit` of the card's most
+/// recent messages via `tx_client`, oldest-first, and emits each as a `CommunicationEvent::Message`
+/// tagged `is_backfill` so downstream treats them as history rather than a fresh trigger. Mirrors
+/// IRC CHATHISTORY-style catch-up so the agent answers with full thread context instead of a
+/// single in-isolation message. Still honors `get_space_info` read permissions, and skips anything
+/// already in `tracked_message_ids` (in particular the message that triggered the follow).
+async fn backfill_card_history(
+    context: &mut context::MessagesContext,
+    sender: &mpsc::UnboundedSender<CommunicationEvent>,
+    card_id: &str,
+    limit: usize,
+    tracked_message_ids: &mut HashSet<String>,
+) -> Result<()> {
+    let card_info = get_card_info(context, card_id).await?;
+    let space_info = get_space_info(context, &card_info.space).await?;
+    if !space_info.can_read {
+        return Ok(());
+    }
+
+    let options = FindOptionsBuilder::default()
+        .sort("created", false)
+        .limit(limit as i64)
+        .build();
+    let query = serde_json::json!({ "cardId": card_id });
+    let mut docs = context
+        .tx_client
+        .find::<serde_json::Value, _>("chat:class:ChatMessage", query, &options)
+        .await?;
+    // Fetched newest-first (so `limit` keeps the most recent window); replay oldest-first so the
+    // agent reads the backfilled history the way a human would scroll up through it.
+    docs.reverse();
+
+    for doc in docs {
+        let (Some(message_id), Some(content), Some(social_id), Some(created)) = (
+            doc["id"].as_str(),
+            doc["content"].as_str(),
+            doc["createdBy"].as_str(),
+            doc["created"].as_str(),
+        ) else {
+            continue;
+        };
+        if tracked_message_ids.contains(message_id) {
+            continue;
+        }
+        let Ok(date) = chrono::DateTime::parse_from_rfc3339(created) else {
+            continue;
+        };
+
+        let mut create_message = CreateMessage::new(card_id, content, social_id);
+        create_message.message_id = message_id.to_string();
+        create_message.date = date.with_timezone(&chrono::Utc);
+
+        tracked_message_ids.insert(message_id.to_string());
+        let message = enrich_create_message(context, create_message, false, true).await?;
+        sender.send(CommunicationEvent::Message(message))?;
+    }
+    Ok(())
+}
+
+/// Anchor for `query_message_history`, mirroring IRC CHATHISTORY's BEFORE/AFTER/LATEST verbs:
+/// page backward from (exclusive of) a message, forward from one, or start at the most recent end
+/// of history.
+#[derive(Debug, Clone)]
+pub enum HistoryAnchor {
+    Before(String),
+    After(String),
+    Latest,
+}
+
+/// One entry in a `query_message_history` page, enriched via `get_person_info` the same way a live
+/// `ReceivedMessage` is, rather than exposing the raw `chat:class:ChatMessage` fields.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryMessage {
+    pub message_id: String,
+    pub created: String,
+    pub author: PersonInfo,
+    pub content: String,
+}
+
+/// What `query_message_history` found.
+#[derive(Debug)]
+pub enum MessageHistoryResult {
+    /// `start_marker`/`end_marker` are the oldest/newest `message_id` in `messages`. Page further
+    /// back with `HistoryAnchor::Before(start_marker)`, or forward with
+    /// `HistoryAnchor::After(end_marker)`, without re-fetching anything already returned.
+    Messages {
+        messages: Vec<HistoryMessage>,
+        start_marker: String,
+        end_marker: String,
+    },
+    /// `anchor` is valid (the card is readable) but there's nothing on that side of it.
+    NothingAvailable,
+    /// `card_id`'s space isn't readable by this agent.
+    NotPermitted,
+}
+
+/// On-demand "scroll back" query for `card_id`'s history, e.g. when the agent needs more context
+/// mid-task than the automatic `backfill_card_history` already gave it. Unlike that function, this
+/// doesn't touch `tracked_message_ids` or emit `CommunicationEvent`s — it just answers a single
+/// request/response query, leaving the caller to decide what to do with the page. Reuses
+/// `context`'s card/person info caches, so repeated scrollbacks over the same card/authors stay
+/// cheap.
+pub async fn query_message_history(
+    context: &mut context::MessagesContext,
+    card_id: &str,
+    anchor: HistoryAnchor,
+    limit: usize,
+) -> Result<MessageHistoryResult> {
+    let card_info = get_card_info(context, card_id).await?;
+    let space_info = get_space_info(context, &card_info.space).await?;
+    if !space_info.can_read {
+        return Ok(MessageHistoryResult::NotPermitted);
+    }
+
+    let mut query = serde_json::json!({ "cardId": card_id });
+    match &anchor {
+        HistoryAnchor::Before(message_id) => {
+            query["id"] = serde_json::json!({ "$lt": message_id });
+        }
+        HistoryAnchor::After(message_id) => {
+            query["id"] = serde_json::json!({ "$gt": message_id });
+        }
+        HistoryAnchor::Latest => {}
+    }
+    // `After` pages forward in time, so the rows closest to the anchor are the *smallest*
+    // remaining ids; everything else (`Before`/`Latest`) wants the largest ones.
+    let ascending = matches!(anchor, HistoryAnchor::After(_));
+
+    let options = FindOptionsBuilder::default()
+        .sort("id", ascending)
+        .limit(limit.max(1) as i64)
+        .build();
+    let mut docs = context
+        .tx_client
+        .find::<serde_json::Value, _>("chat:class:ChatMessage", query, &options)
+        .await?;
+    if !ascending {
+        // Fetched newest-first so `limit` keeps the window closest to the anchor; replay
+        // oldest-first, the order a caller reads a transcript in.
+        docs.reverse();
+    }
+
+    let mut messages = Vec::with_capacity(docs.len());
+    for doc in &docs {
+        let (Some(message_id), Some(content), Some(social_id), Some(created)) = (
+            doc["id"].as_str(),
+            doc["content"].as_str(),
+            doc["createdBy"].as_str(),
+            doc["created"].as_str(),
+        ) else {
+            continue;
+        };
+        let author = get_person_info(context, social_id).await?;
+        messages.push(HistoryMessage {
+            message_id: message_id.to_string(),
+            created: created.to_string(),
+            author,
+            content: content.to_string(),
+        });
+    }
+
+    let (Some(start_marker), Some(end_marker)) = (
+        messages.first().map(|m| m.message_id.clone()),
+        messages.last().map(|m| m.message_id.clone()),
+    ) else {
+        return Ok(MessageHistoryResult::NothingAvailable);
+    };
+
+    Ok(MessageHistoryResult::Messages {
+        messages,
+        start_marker,
+        end_marker,
+    })
+}
+
+/// Everything `worker` needs to pick a follow session back up after a restart: which cards are
+/// being followed and how many messages are left in each window, which message ids have already
+/// been dispatched (so a replayed offset doesn't re-backfill or re-trigger them), and which cards
+/// were statically configured as always-on. Serialized as JSON and round-tripped through
+/// `DbClient::follow_state`/`set_follow_state`, keyed by `(workspace_id, group_id)` so multiple
+/// workspaces or consumer groups never clobber each other's state.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FollowState {
+    follow_card_ids: HashMap<String, u8>,
+    tracked_message_ids: HashSet<String>,
+    persistent_cards: HashSet<String>,
+}
+
+impl FollowState {
+    async fn load(db_client: &DbClient, workspace_id: &str, group_id: &str) -> Self {
+        let stored = match db_client.follow_state(workspace_id, group_id).await {
+            Ok(stored) => stored,
+            Err(err) => {
+                tracing::warn!(%err, "Failed to read persisted follow state, starting cold");
+                None
+            }
+        };
+        stored
+            .and_then(|payload| match serde_json::from_str(&payload) {
+                Ok(state) => Some(state),
+                Err(err) => {
+                    tracing::warn!(%err, "Failed to parse persisted follow state, starting cold");
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    async fn save(&self, db_client: &DbClient, workspace_id: &str, group_id: &str) {
+        let payload = match serde_json::to_string(self) {
+            Ok(payload) => payload,
+            Err(err) => {
+                tracing::warn!(%err, "Failed to serialize follow state");
+                return;
+            }
+        };
+        if let Err(err) = db_client
+            .set_follow_state(workspace_id, group_id, &payload)
+            .await
+        {
+            tracing::warn!(%err, "Failed to persist follow state");
+        }
     }
 }
 
@@ -244,137 +471,195 @@ async fn get_person_info(
     Ok(person_info)
 }
 
-pub async fn worker(
+/// Production entry point: wires up a `KafkaEventSource` from `context`'s Huly config and runs
+/// `worker` against it. Tests that want a deterministic, broker-free run should call `worker`
+/// directly with their own `EventSource` instead.
+pub async fn run_with_kafka(
+    context: context::MessagesContext,
+    sender: mpsc::UnboundedSender<CommunicationEvent>,
+    persistent_cards: HashSet<String>,
+    db_client: DbClient,
+) -> Result<()> {
+    let topic = context.config.huly.kafka.topics.transactions.clone();
+    let source = event_source::KafkaEventSource::connect(
+        &context.config.huly.kafka.group_id,
+        &context.config.huly.kafka.bootstrap,
+        &topic,
+        context.config.log_level,
+        db_client.clone(),
+    )
+    .await?;
+    worker(context, sender, persistent_cards, db_client, source).await
+}
+
+/// Drives the follow-matching/enrichment pipeline from `source` until it errors. Generic over
+/// `EventSource` so the Kafka specifics (`event_source::KafkaEventSource`) are just one
+/// implementor — a test can instead drive this with an in-memory source to exercise
+/// `should_process_message`/`enrich_create_message` deterministically, with no broker at all.
+pub async fn worker<S: event_source::EventSource>(
     mut context: context::MessagesContext,
     sender: mpsc::UnboundedSender<CommunicationEvent>,
     persistent_cards: HashSet<String>,
+    db_client: DbClient,
+    mut source: S,
 ) -> Result<()> {
-    let mut kafka_config = rdkafka::ClientConfig::new();
-    kafka_config
-        .set("group.id", &context.config.huly.kafka.group_id)
-        .set("bootstrap.servers", &context.config.huly.kafka.bootstrap)
-        .set_log_level(to_kafka_log_level(context.config.log_level));
-    let consumer: StreamConsumer = kafka_config.create()?;
     let listening_workspace_uuid = context.workspace_uuid;
-
-    tracing::info!(topics = %format!("[{}]", context.config.huly.kafka.topics.transactions), "Starting consumer");
-    consumer.subscribe(&[&context.config.huly.kafka.topics.transactions])?;
     let person_id = context.person_id.to_string();
     let match_pattern = format!("ref://?_class=contact%3Aclass%3APerson&_id={person_id}");
     let ignore_card_ids = context.config.huly.ignored_channels.clone();
-    let mut persistent_cards = persistent_cards.clone();
-    let mut follow_card_ids = HashMap::<String, u8>::new();
-    let mut tracked_message_ids = HashSet::<String>::new();
+    let workspace_id = listening_workspace_uuid.to_string();
+    let group_id = context.config.huly.kafka.group_id.clone();
+    let restored = FollowState::load(&db_client, &workspace_id, &group_id).await;
+    let mut persistent_cards = persistent_cards
+        .union(&restored.persistent_cards)
+        .cloned()
+        .collect::<HashSet<_>>();
+    let mut follow_card_ids = restored.follow_card_ids;
+    let mut tracked_message_ids = restored.tracked_message_ids;
+    let backfill_limit = context
+        .config
+        .huly
+        .backfill_messages
+        .unwrap_or(MAX_FOLLOW_MESSAGES as usize);
 
     loop {
-        let Ok(kafka_message) = consumer.recv().await else {
+        let Some((workspace, transactor_payload)) = source.next().await? else {
             continue;
         };
-        let (workspace, transactor_payload) = match transactor::kafka::parse_message(&kafka_message)
-        {
-            Ok(data) => data,
-            Err(err) => {
-                tracing::trace!(%err, "Unknown message format, skipping");
-                continue;
-            }
-        };
-        if workspace != listening_workspace_uuid {
-            continue;
-        }
 
-        let event = match try_extract_communication_event_from_payload(transactor_payload) {
-            Ok(Some(e)) => e,
-            Ok(None) => {
-                continue;
-            }
-            Err(error) => {
-                tracing::error!(%error, "Error parsing message from queue");
-                continue;
+        'process: {
+            if workspace != listening_workspace_uuid {
+                break 'process;
             }
-        };
 
-        match event {
-            CommunicationDomainEventKind::CreateMessage(message) => {
-                let Some(is_mention) = should_process_message(
-                    &mut context,
-                    &message,
-                    &match_pattern,
-                    &ignore_card_ids,
-                    &mut follow_card_ids,
-                    &mut persistent_cards,
-                )
-                .await
-                else {
-                    continue;
-                };
-                tracked_message_ids.insert(message.message_id.clone());
-                let message = enrich_create_message(&mut context, message, is_mention).await?;
-                sender.send(CommunicationEvent::Message(message))?;
-            }
-            CommunicationDomainEventKind::AttachmentPatch(patch) => {
-                if tracked_message_ids.contains(&patch.message_id) {
-                    for attachement in patch
-                        .operations
-                        .iter()
-                        .filter_map(|op| {
-                            if op.opcode == "add" {
-                                Some(&op.attachments)
-                            } else {
-                                None
-                            }
-                        })
-                        .flatten()
+            let event = match try_extract_communication_event_from_payload(transactor_payload) {
+                Ok(Some(e)) => e,
+                Ok(None) => break 'process,
+                Err(error) => {
+                    tracing::error!(%error, "Error parsing message from queue");
+                    break 'process;
+                }
+            };
+
+            match event {
+                CommunicationDomainEventKind::CreateMessage(message) => {
+                    let Some((is_mention, newly_followed)) = should_process_message(
+                        &mut context,
+                        &message,
+                        &match_pattern,
+                        &ignore_card_ids,
+                        &mut follow_card_ids,
+                        &mut persistent_cards,
+                    )
+                    .await
+                    else {
+                        break 'process;
+                    };
+                    tracked_message_ids.insert(message.message_id.clone());
+                    if newly_followed
+                        && let Err(err) = backfill_card_history(
+                            &mut context,
+                            &sender,
+                            &message.card_id,
+                            backfill_limit,
+                            &mut tracked_message_ids,
+                        )
+                        .await
                     {
-                        let blob_id = attachement.id.clone();
-                        let params = attachement.params.as_object().unwrap();
-                        let file_name = params
-                            .get("fileName")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or(&attachement.id);
-                        sender.send(CommunicationEvent::Attachment(ReceivedAttachment {
-                            card_id: patch.card_id.clone(),
-                            message_id: patch.message_id.clone(),
-                            file_name: file_name.to_string(),
-                            // http://huly.local:4030/blob/:workspace/:blobId/:filename
-                            url: context
-                                .server_config
-                                .files_url
-                                .clone()
-                                .replace(":workspace", &workspace.to_string())
-                                .replace(":blobId", &blob_id)
-                                .replace(
-                                    ":filename",
-                                    &percent_encoding::percent_encode(
-                                        file_name.as_bytes(),
-                                        NON_ALPHANUMERIC,
-                                    )
-                                    .to_string(),
-                                ),
-                        }))?;
+                        tracing::warn!(%err, card_id = message.card_id, "Failed to backfill card history");
                     }
+                    let message =
+                        enrich_create_message(&mut context, message, is_mention, false).await?;
+                    sender.send(CommunicationEvent::Message(message))?;
                 }
-            }
-            CommunicationDomainEventKind::ReactionPatch(patch) => {
-                if tracked_message_ids.contains(&patch.message_id) {
-                    let person_info = get_person_info(&mut context, &patch.social_id).await?;
-                    if patch.operation.opcode == "add" {
-                        sender.send(CommunicationEvent::Reaction(ReceivedReaction {
-                            card_id: patch.card_id.clone(),
-                            message_id: patch.message_id.clone(),
-                            person: person_info.to_string(),
-                            reaction: patch.operation.reaction,
-                        }))?;
+                CommunicationDomainEventKind::AttachmentPatch(patch) => {
+                    if tracked_message_ids.contains(&patch.message_id) {
+                        // http://huly.local:4030/blob/:workspace/:blobId/:filename
+                        let attachments = patch.resolve_attachments(
+                            &context.server_config.files_url,
+                            &workspace.to_string(),
+                        );
+                        for attachment in attachments {
+                            sender.send(CommunicationEvent::Attachment(attachment))?;
+                        }
                     }
                 }
-            }
-            CommunicationDomainEventKind::ThreadPatch(patch) => {
-                if let ThreadPatchOperation::Attach(op) = patch.operation
-                    && tracked_message_ids.contains(&patch.message_id)
-                {
-                    follow_card_ids.insert(op.thread_id.clone(), MAX_FOLLOW_MESSAGES);
+                CommunicationDomainEventKind::ReactionPatch(patch) => {
+                    if tracked_message_ids.contains(&patch.message_id) {
+                        let person_info = get_person_info(&mut context, &patch.social_id).await?;
+                        if patch.operation.opcode == "add" {
+                            sender.send(CommunicationEvent::Reaction(ReceivedReaction {
+                                card_id: patch.card_id.clone(),
+                                message_id: patch.message_id.clone(),
+                                person: person_info.to_string(),
+                                reaction: patch.operation.reaction,
+                            }))?;
+                        }
+                    }
+                }
+                CommunicationDomainEventKind::ThreadPatch(patch) => {
+                    if let ThreadPatchOperation::Attach(op) = patch.operation
+                        && tracked_message_ids.contains(&patch.message_id)
+                    {
+                        let newly_followed = follow_card_ids
+                            .insert(op.thread_id.clone(), MAX_FOLLOW_MESSAGES)
+                            .is_none();
+                        if newly_followed
+                            && let Err(err) = backfill_card_history(
+                                &mut context,
+                                &sender,
+                                &op.thread_id,
+                                backfill_limit,
+                                &mut tracked_message_ids,
+                            )
+                            .await
+                        {
+                            tracing::warn!(%err, card_id = op.thread_id, "Failed to backfill card history");
+                        }
+                    }
                 }
+                CommunicationDomainEventKind::PatchMessage(patch) => {
+                    if tracked_message_ids.contains(&patch.message_id) {
+                        match patch.operation {
+                            PatchMessageOperation::Update(update) => {
+                                sender.send(CommunicationEvent::MessageEdited(
+                                    ReceivedMessageEdit {
+                                        card_id: patch.card_id.clone(),
+                                        message_id: patch.message_id.clone(),
+                                        social_id: patch.social_id.clone(),
+                                        content: update.content,
+                                        date: patch
+                                            .date
+                                            .to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                                    },
+                                ))?;
+                            }
+                            PatchMessageOperation::Remove => {
+                                sender.send(CommunicationEvent::MessageRemoved(
+                                    ReceivedMessageRemoval {
+                                        card_id: patch.card_id.clone(),
+                                        message_id: patch.message_id.clone(),
+                                        social_id: patch.social_id.clone(),
+                                        date: patch
+                                            .date
+                                            .to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                                    },
+                                ))?;
+                            }
+                        }
+                    }
+                }
+                _ => {}
             }
-            _ => continue,
         }
+
+        source.ack().await?;
+        FollowState {
+            follow_card_ids: follow_card_ids.clone(),
+            tracked_message_ids: tracked_message_ids.clone(),
+            persistent_cards: persistent_cards.clone(),
+        }
+        .save(&db_client, &workspace_id, &group_id)
+        .await;
     }
 }
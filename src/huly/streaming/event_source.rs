@@ -0,0 +1,144 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! Abstracts where `worker` pulls raw transactor events from, so the event-matching logic in
+//! `should_process_message`/`enrich_create_message` can be driven by something other than a live
+//! Kafka broker — an in-memory fixture for deterministic tests, or a different transport (e.g.
+//! direct transactor polling) altogether. The same swappable-backend shape `storage::Backend`
+//! uses for sqlite/postgres.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use hulyrs::services::{core::WorkspaceUuid, transactor};
+use rdkafka::{
+    Message, Offset, TopicPartitionList,
+    consumer::{Consumer, StreamConsumer},
+};
+
+use crate::database::DbClient;
+
+/// A source of raw transactor events for `worker` to decode and dispatch. Implementors own
+/// whatever connection/offset state their transport needs; `worker` itself only ever sees
+/// `next`/`ack`.
+#[async_trait]
+pub trait EventSource: Send {
+    /// Pulls the next raw transactor event. `Ok(None)` means this poll produced nothing worth
+    /// dispatching (a message that didn't parse, a transient receive error, ...) — callers should
+    /// just call `next` again, not treat it as end-of-stream.
+    async fn next(&mut self) -> Result<Option<(WorkspaceUuid, serde_json::Value)>>;
+
+    /// Called once `worker` has fully processed and forwarded the event the last `next` call
+    /// returned, so a source backed by a real broker can commit its position only after delivery
+    /// is confirmed. No-op by default for sources with nothing to commit (e.g. a test fixture).
+    async fn ack(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn to_kafka_log_level(level: tracing::Level) -> rdkafka::config::RDKafkaLogLevel {
+    match level {
+        tracing::Level::ERROR => rdkafka::config::RDKafkaLogLevel::Error,
+        tracing::Level::WARN => rdkafka::config::RDKafkaLogLevel::Warning,
+        tracing::Level::INFO => rdkafka::config::RDKafkaLogLevel::Info,
+        tracing::Level::DEBUG => rdkafka::config::RDKafkaLogLevel::Debug,
+        tracing::Level::TRACE => rdkafka::config::RDKafkaLogLevel::Debug,
+    }
+}
+
+/// Resumes `consumer` from the offset persisted in `db_client`, if any, instead of whatever
+/// position the consumer group would otherwise default to. Falls back to a plain `subscribe` when
+/// no offset has been journaled yet (first run) or the store can't be reached, logging a
+/// reconciliation line comparing the resumed position against the topic's current high watermark
+/// so an operator can see how much backlog a restart is about to replay.
+async fn resume_or_subscribe(consumer: &StreamConsumer, topic: &str, db_client: &DbClient) -> Result<()> {
+    let offset = match db_client.multiplexer_offset().await {
+        Ok(offset) => offset,
+        Err(err) => {
+            tracing::warn!(%err, "Failed to read persisted multiplexer offset, starting from group default");
+            None
+        }
+    };
+
+    let Some(offset) = offset else {
+        consumer.subscribe(&[topic])?;
+        return Ok(());
+    };
+
+    let mut tpl = TopicPartitionList::new();
+    tpl.add_partition_offset(topic, 0, Offset::Offset(offset + 1))?;
+    consumer.assign(&tpl)?;
+
+    match consumer.fetch_watermarks(topic, 0, std::time::Duration::from_secs(5)) {
+        Ok((_, high)) => {
+            let pending = (high - offset - 1).max(0);
+            tracing::info!(resumed_offset = offset, high_watermark = high, pending, "Resumed consumer from persisted offset");
+        }
+        Err(err) => {
+            tracing::warn!(%err, "Failed to fetch watermarks for reconciliation log");
+        }
+    }
+    Ok(())
+}
+
+/// The production `EventSource`: an `rdkafka::StreamConsumer` subscribed to the configured
+/// transactions topic, resuming from (and persisting to) `db_client`'s journaled offset.
+pub struct KafkaEventSource {
+    consumer: StreamConsumer,
+    db_client: DbClient,
+    pending_offset: Option<i64>,
+}
+
+impl KafkaEventSource {
+    /// Connects to `bootstrap` under `group_id`, subscribes to `topic` (resuming from
+    /// `db_client`'s persisted offset if any), and logs at `log_level`.
+    pub async fn connect(
+        group_id: &str,
+        bootstrap: &str,
+        topic: &str,
+        log_level: tracing::Level,
+        db_client: DbClient,
+    ) -> Result<Self> {
+        let mut kafka_config = rdkafka::ClientConfig::new();
+        kafka_config
+            .set("group.id", group_id)
+            .set("bootstrap.servers", bootstrap)
+            .set("enable.auto.commit", "false")
+            .set_log_level(to_kafka_log_level(log_level));
+        let consumer: StreamConsumer = kafka_config.create()?;
+
+        tracing::info!(topics = %format!("[{topic}]"), "Starting consumer");
+        resume_or_subscribe(&consumer, topic, &db_client).await?;
+
+        Ok(Self {
+            consumer,
+            db_client,
+            pending_offset: None,
+        })
+    }
+}
+
+#[async_trait]
+impl EventSource for KafkaEventSource {
+    async fn next(&mut self) -> Result<Option<(WorkspaceUuid, serde_json::Value)>> {
+        let Ok(kafka_message) = self.consumer.recv().await else {
+            return Ok(None);
+        };
+        self.pending_offset = Some(kafka_message.offset());
+
+        match transactor::kafka::parse_message(&kafka_message) {
+            Ok((workspace, payload)) => Ok(Some((workspace, payload))),
+            Err(err) => {
+                tracing::trace!(%err, "Unknown message format, skipping");
+                Ok(None)
+            }
+        }
+    }
+
+    async fn ack(&mut self) -> Result<()> {
+        if let Some(offset) = self.pending_offset.take()
+            && let Err(err) = self.db_client.set_multiplexer_offset(offset).await
+        {
+            tracing::warn!(%err, "Failed to persist multiplexer offset");
+        }
+        Ok(())
+    }
+}
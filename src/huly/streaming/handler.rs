@@ -0,0 +1,55 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! A default-empty visitor for `CommunicationDomainEventKind`, modeled on the Matrix SDK's
+//! `EventEmitter`: implement only the handlers you care about instead of re-writing the full
+//! `StreamingMessageKind`/`DomainEventKind`/`CommunicationDomainEventKind` match at every call
+//! site that consumes the Kafka stream.
+
+use async_trait::async_trait;
+
+use super::types::{
+    AttachmentPatch, CommunicationDomainEventKind, CreateMessage, DomainEventKind, PatchMessage,
+    ReactionPatch, StreamingMessage, StreamingMessageKind, ThreadPatch, UpdateNotificationContext,
+};
+
+#[async_trait]
+pub trait CommunicationEventHandler: Send + Sync {
+    async fn on_create_message(&self, _event: &CreateMessage) {}
+    async fn on_reaction_patch(&self, _event: &ReactionPatch) {}
+    async fn on_thread_patch(&self, _event: &ThreadPatch) {}
+    async fn on_attachment_patch(&self, _event: &AttachmentPatch) {}
+    async fn on_patch_message(&self, _event: &PatchMessage) {}
+    async fn on_notification_context(&self, _event: &UpdateNotificationContext) {}
+}
+
+/// Walks `message`'s `StreamingMessageKind` / `DomainEventKind` / `CommunicationDomainEventKind`
+/// layers and invokes the matching `handler` method, so a consumer can register `handler` once
+/// instead of re-implementing this nested match itself. Non-communication and unrecognized events
+/// are silently ignored, since a handler that doesn't care about them has nothing to do.
+pub async fn dispatch_communication_event(
+    handler: &dyn CommunicationEventHandler,
+    message: &StreamingMessage,
+) {
+    let StreamingMessageKind::Domain(DomainEventKind::Communication(event)) = &message.kind else {
+        return;
+    };
+
+    match event {
+        CommunicationDomainEventKind::CreateMessage(event) => {
+            handler.on_create_message(event).await
+        }
+        CommunicationDomainEventKind::ReactionPatch(event) => {
+            handler.on_reaction_patch(event).await
+        }
+        CommunicationDomainEventKind::ThreadPatch(event) => handler.on_thread_patch(event).await,
+        CommunicationDomainEventKind::AttachmentPatch(event) => {
+            handler.on_attachment_patch(event).await
+        }
+        CommunicationDomainEventKind::PatchMessage(event) => {
+            handler.on_patch_message(event).await
+        }
+        CommunicationDomainEventKind::UpdateNotificationContext(event) => {
+            handler.on_notification_context(event).await
+        }
+    }
+}
@@ -0,0 +1,78 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! OpenTelemetry instrumentation around `StreamingMessage::decode`, gated behind the `metrics`
+//! feature so crates that don't care about dashboards don't pay for the instrument registrations.
+//! Counts decoded events by kind, flags `unknown` events with the raw `_class` as a label (so a
+//! new, unmodeled Huly event class shows up in a dashboard instead of silently disappearing into
+//! `StreamingMessageKind::Unknown`), and records decode latency.
+
+use std::{sync::LazyLock, time::Instant};
+
+use opentelemetry::{
+    KeyValue, global,
+    metrics::{Counter, Histogram},
+};
+use serde_json::Value;
+
+use super::types::{DomainEventKind, StreamingMessage, StreamingMessageKind};
+
+static EVENTS_TOTAL: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    global::meter("huly_streaming")
+        .u64_counter("huly_streaming_events_total")
+        .with_description("Streaming events decoded, labeled by kind")
+        .build()
+});
+
+static UNKNOWN_EVENTS_TOTAL: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    global::meter("huly_streaming")
+        .u64_counter("huly_streaming_unknown_events_total")
+        .with_description("Unrecognized streaming events, labeled by raw `_class`")
+        .build()
+});
+
+static DECODE_LATENCY: LazyLock<Histogram<f64>> = LazyLock::new(|| {
+    global::meter("huly_streaming")
+        .f64_histogram("huly_streaming_decode_latency_ms")
+        .with_description("StreamingMessage::decode latency in milliseconds")
+        .build()
+});
+
+/// The label recorded on `huly_streaming_events_total` for `message.kind`.
+fn event_kind_label(message: &StreamingMessage) -> &'static str {
+    use crate::huly::streaming::types::CommunicationDomainEventKind as Comm;
+
+    match &message.kind {
+        StreamingMessageKind::Workspace(_) => "workspace",
+        StreamingMessageKind::Unknown(_) => "unknown",
+        StreamingMessageKind::Malformed { .. } => "malformed",
+        StreamingMessageKind::Domain(DomainEventKind::UnknownEvent(_)) => "unknown",
+        StreamingMessageKind::Domain(DomainEventKind::Communication(event)) => match event {
+            Comm::CreateMessage(_) => "message_created",
+            Comm::PatchMessage(_) => "message_patched",
+            Comm::AttachmentPatch(_) => "attachment_patch",
+            Comm::ReactionPatch(_) => "reaction_patch",
+            Comm::ThreadPatch(_) => "thread_patch",
+            Comm::UpdateNotificationContext(_) => "notification_context",
+        },
+    }
+}
+
+/// Decodes `raw` via `StreamingMessage::decode`, recording the event-kind counter, the decode
+/// latency, and — for `Unknown` frames — the raw `_class` that isn't modeled yet.
+pub fn decode(raw: &str) -> StreamingMessage {
+    let start = Instant::now();
+    let message = StreamingMessage::decode(raw);
+    DECODE_LATENCY.record(start.elapsed().as_secs_f64() * 1000.0, &[]);
+    EVENTS_TOTAL.add(1, &[KeyValue::new("kind", event_kind_label(&message))]);
+
+    if let StreamingMessageKind::Unknown(raw_value) = &message.kind {
+        let class = raw_value
+            .get("_class")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown")
+            .to_string();
+        UNKNOWN_EVENTS_TOTAL.add(1, &[KeyValue::new("class", class)]);
+    }
+
+    message
+}
@@ -0,0 +1,110 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! Routes a decoded `StreamingMessage` to typed handlers by event kind, so call sites stop
+//! hand-writing the `StreamingMessageKind`/`DomainEventKind`/`CommunicationDomainEventKind` match
+//! themselves. Builds on the `CommunicationEventHandler` visitor from `handler.rs`: the router
+//! owns the outer match (workspace / unrecognized / malformed) and delegates communication
+//! events to a registered handler, reporting what happened as a `RouteOutcome`.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::{
+    handler::{CommunicationEventHandler, dispatch_communication_event},
+    types::{DomainEventKind, StreamingMessage, StreamingMessageKind},
+};
+
+/// What happened when a `StreamingMessage` was routed.
+#[derive(Debug)]
+pub enum RouteOutcome {
+    /// A typed handler ran for this message's event kind.
+    Handled,
+    /// No typed handler ran: an unrecognized/malformed frame (after the fallback handler, if one
+    /// is registered, returned `Ok`), or a `Workspace` event (not modeled as a typed handler here).
+    FellThrough,
+    /// A fallback handler returned an error.
+    Errored(anyhow::Error),
+}
+
+/// Fallback handling for frames that never reach a typed `CommunicationEventHandler` method:
+/// well-formed-but-unrecognized classes/domains (`Unknown`, `UnknownEvent`) and frames that
+/// failed to parse at all (`Malformed`). Both default to a no-op so registering only what you
+/// need is enough.
+#[async_trait]
+pub trait UnrecognizedEventHandler: Send + Sync {
+    async fn on_unknown(&self, _raw: &Value) -> Result<()> {
+        Ok(())
+    }
+
+    async fn on_malformed(&self, _raw: &str, _error: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A no-op `UnrecognizedEventHandler`, used when a caller only cares about communication events.
+pub struct IgnoreUnrecognized;
+
+impl UnrecognizedEventHandler for IgnoreUnrecognized {}
+
+/// Dispatches `StreamingMessage`s to a `CommunicationEventHandler` for known communication event
+/// kinds, and to an `UnrecognizedEventHandler` for everything else.
+pub struct StreamingEventRouter<C, U = IgnoreUnrecognized>
+where
+    C: CommunicationEventHandler,
+    U: UnrecognizedEventHandler,
+{
+    communication: C,
+    unrecognized: U,
+}
+
+impl<C> StreamingEventRouter<C, IgnoreUnrecognized>
+where
+    C: CommunicationEventHandler,
+{
+    /// Builds a router that dispatches communication events to `communication` and silently
+    /// ignores `Unknown`/`Malformed` frames.
+    pub fn new(communication: C) -> Self {
+        Self {
+            communication,
+            unrecognized: IgnoreUnrecognized,
+        }
+    }
+}
+
+impl<C, U> StreamingEventRouter<C, U>
+where
+    C: CommunicationEventHandler,
+    U: UnrecognizedEventHandler,
+{
+    /// Builds a router with an explicit fallback handler for `Unknown`/`Malformed` frames.
+    pub fn with_fallback(communication: C, unrecognized: U) -> Self {
+        Self {
+            communication,
+            unrecognized,
+        }
+    }
+
+    /// Routes `message` to the registered handlers and reports what happened. Never panics on an
+    /// unrecognized or malformed frame — those go to the fallback handler instead.
+    pub async fn route(&self, message: &StreamingMessage) -> RouteOutcome {
+        match &message.kind {
+            StreamingMessageKind::Domain(DomainEventKind::Communication(_)) => {
+                dispatch_communication_event(&self.communication, message).await;
+                RouteOutcome::Handled
+            }
+            StreamingMessageKind::Domain(DomainEventKind::UnknownEvent(raw))
+            | StreamingMessageKind::Unknown(raw) => match self.unrecognized.on_unknown(raw).await {
+                Ok(()) => RouteOutcome::FellThrough,
+                Err(err) => RouteOutcome::Errored(err),
+            },
+            StreamingMessageKind::Malformed { raw, error } => {
+                match self.unrecognized.on_malformed(raw, error).await {
+                    Ok(()) => RouteOutcome::FellThrough,
+                    Err(err) => RouteOutcome::Errored(err),
+                }
+            }
+            StreamingMessageKind::Workspace(_) => RouteOutcome::FellThrough,
+        }
+    }
+}
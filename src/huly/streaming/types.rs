@@ -2,11 +2,70 @@
 
 use std::fmt::Display;
 
+use chrono::{DateTime, Utc};
 use hulyrs::services::core::AccountUuid;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+#[cfg(feature = "ts")]
+use ts_rs::TS;
 
-#[derive(Debug, Deserialize, PartialEq)]
+/// `DateTime<Utc>` carried over the wire as an RFC-3339, `Z`-suffixed, millisecond-precision
+/// string (e.g. `"2025-08-19T17:43:33.012Z"`) — the format every `date`/`lastReply`/`lastView`
+/// field in Huly's streaming events uses. Deserialization accepts any valid RFC-3339 string;
+/// serialization always re-emits millis so round-tripping matches what Huly itself sends.
+mod rfc3339_millis {
+    use chrono::{DateTime, SecondsFormat, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(date: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&date.to_rfc3339_opts(SecondsFormat::Millis, true))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = String::deserialize(deserializer)?;
+        DateTime::parse_from_rfc3339(&raw)
+            .map(|date| date.with_timezone(&Utc))
+            .map_err(serde::de::Error::custom)
+    }
+
+    pub mod option {
+        use chrono::{DateTime, Utc};
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S>(date: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match date {
+                Some(date) => super::serialize(date, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            Option::<String>::deserialize(deserializer)?
+                .map(|raw| {
+                    DateTime::parse_from_rfc3339(&raw)
+                        .map(|date| date.with_timezone(&Utc))
+                        .map_err(serde::de::Error::custom)
+                })
+                .transpose()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 pub struct StreamingMessage {
     #[serde(flatten)]
     pub params: CommonParams,
@@ -14,7 +73,62 @@ pub struct StreamingMessage {
     pub kind: StreamingMessageKind,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+impl StreamingMessage {
+    /// Decodes `raw` into a `StreamingMessage`, never failing: a structurally broken frame
+    /// (truncated payload, wrong types inside a nested field, non-numeric `modifiedOn`) still
+    /// yields a `StreamingMessage` whose `kind` is `Malformed`, carrying the original text and the
+    /// serde error, instead of returning `Err` and forcing the caller to drop the whole frame
+    /// with no record of what it was. Well-formed-but-unrecognized classes keep going through
+    /// `Unknown` as before; `Malformed` is strictly for decode errors.
+    pub fn decode(raw: &str) -> StreamingMessage {
+        match serde_json::from_str::<StreamingMessage>(raw) {
+            Ok(message) => message,
+            Err(error) => {
+                let params = serde_json::from_str::<Value>(raw)
+                    .ok()
+                    .map(|value| CommonParams {
+                        id: value
+                            .get("_id")
+                            .and_then(Value::as_str)
+                            .unwrap_or_default()
+                            .to_string(),
+                        space: value
+                            .get("space")
+                            .and_then(Value::as_str)
+                            .unwrap_or_default()
+                            .to_string(),
+                        object_space: value
+                            .get("objectSpace")
+                            .and_then(Value::as_str)
+                            .unwrap_or_default()
+                            .to_string(),
+                        modified_by: value
+                            .get("modifiedBy")
+                            .and_then(Value::as_str)
+                            .unwrap_or_default()
+                            .to_string(),
+                        modified_on: value
+                            .get("modifiedOn")
+                            .and_then(Value::as_i64)
+                            .and_then(DateTime::from_timestamp_millis)
+                            .unwrap_or_default(),
+                    })
+                    .unwrap_or_default();
+                StreamingMessage {
+                    params,
+                    kind: StreamingMessageKind::Malformed {
+                        raw: raw.to_string(),
+                        error: error.to_string(),
+                    },
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 #[serde(rename_all = "camelCase")]
 pub struct CommonParams {
     #[serde(rename = "_id")]
@@ -22,10 +136,14 @@ pub struct CommonParams {
     pub space: String,
     pub object_space: String,
     pub modified_by: String,
-    pub modified_on: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    #[cfg_attr(feature = "ts", ts(type = "string"))]
+    pub modified_on: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 #[serde(rename_all = "camelCase", tag = "_class")]
 pub enum StreamingMessageKind {
     #[serde(rename = "core:class:TxWorkspaceEvent")]
@@ -33,35 +151,52 @@ pub enum StreamingMessageKind {
     #[serde(rename = "core:class:TxDomainEvent")]
     Domain(DomainEventKind),
     #[serde(untagged)]
+    #[cfg_attr(feature = "ts", ts(type = "unknown"))]
     Unknown(Value),
+    /// Never produced by normal deserialization — only by `StreamingMessage::decode` when a frame
+    /// fails to parse at all, so the raw text and the serde error survive for logging instead of
+    /// the frame being dropped with no trace.
+    Malformed { raw: String, error: String },
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 pub struct WorkspaceEvent {}
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 pub struct DomainEvent {}
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 #[serde(tag = "domain", content = "event", rename_all = "lowercase")]
 pub enum DomainEventKind {
     #[serde(rename = "communication")]
     Communication(CommunicationDomainEventKind),
     #[serde(untagged)]
+    #[cfg_attr(feature = "ts", ts(type = "unknown"))]
     UnknownEvent(Value),
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum CommunicationDomainEventKind {
     CreateMessage(CreateMessage),
     AttachmentPatch(AttachmentPatch),
     ReactionPatch(ReactionPatch),
     ThreadPatch(ThreadPatch),
+    PatchMessage(PatchMessage),
     UpdateNotificationContext(UpdateNotificationContext),
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 #[serde(rename_all = "camelCase")]
 pub enum MessageType {
     Message,
@@ -70,7 +205,9 @@ pub enum MessageType {
     Unknown(String),
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 #[serde(rename_all = "camelCase")]
 pub struct CreateMessage {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
@@ -82,10 +219,36 @@ pub struct CreateMessage {
     pub content: String,
     pub social_id: String,
     pub options: Option<CreateMessageOptions>,
-    pub date: String,
+    #[serde(with = "rfc3339_millis")]
+    #[cfg_attr(feature = "ts", ts(type = "string"))]
+    pub date: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+impl CreateMessage {
+    /// Builds an outbound `createMessage` event for a plain chat message, generating a local
+    /// `message_id` and `date` since those aren't known until send time.
+    pub fn new(
+        card_id: impl Into<String>,
+        content: impl Into<String>,
+        social_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: None,
+            message_id: uuid::Uuid::new_v4().to_string(),
+            message_type: MessageType::Message,
+            card_id: card_id.into(),
+            card_type: "chat:masterTag:Channel".to_string(),
+            content: content.into(),
+            social_id: social_id.into(),
+            options: None,
+            date: Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 #[serde(rename_all = "camelCase")]
 pub struct AttachmentPatch {
     pub card_id: String,
@@ -94,23 +257,89 @@ pub struct AttachmentPatch {
     pub social_id: String,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 #[serde(rename_all = "camelCase")]
 pub struct AttachmentPatchOperation {
     pub opcode: String,
     pub attachments: Vec<AttachmentPatchOperationAttachment>,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 #[serde(rename_all = "camelCase")]
 pub struct AttachmentPatchOperationAttachment {
     pub id: String,
     #[serde(rename = "type")]
     pub mime_type: String,
+    #[cfg_attr(feature = "ts", ts(type = "unknown"))]
     pub params: serde_json::Value,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+impl AttachmentPatchOperationAttachment {
+    /// Parses `params` into its typed shape, so callers stop re-reading the same JSON blob
+    /// field-by-field. Fails if `params` is missing one of the fields every fixture has.
+    pub fn parsed_params(&self) -> serde_json::Result<AttachmentParams> {
+        serde_json::from_value(self.params.clone())
+    }
+}
+
+/// The typed shape of `AttachmentPatchOperationAttachment.params`, as observed on every
+/// `attachmentPatch` fixture. `metadata` stays a raw `Value` since its shape varies by attachment
+/// kind (image dimensions, etc.) and nothing downstream needs to interpret it yet.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentParams {
+    pub blob_id: String,
+    pub mime_type: String,
+    pub file_name: String,
+    pub size: u64,
+    #[serde(default)]
+    #[cfg_attr(feature = "ts", ts(type = "unknown"))]
+    pub metadata: serde_json::Value,
+}
+
+impl AttachmentPatch {
+    /// Resolves the `add`-opcode attachments in `self.operations` into downloadable
+    /// `ReceivedAttachment`s, building each `url` by filling in `files_url`'s `:workspace`,
+    /// `:blobId` and `:filename` placeholders. `remove`/`update` opcodes and attachments whose
+    /// `params` don't parse are skipped rather than failing the whole patch.
+    pub fn resolve_attachments(&self, files_url: &str, workspace: &str) -> Vec<ReceivedAttachment> {
+        self.operations
+            .iter()
+            .filter(|operation| operation.opcode == "add")
+            .flat_map(|operation| &operation.attachments)
+            .filter_map(|attachment| {
+                let params = attachment.parsed_params().ok()?;
+                let url = files_url
+                    .replace(":workspace", workspace)
+                    .replace(":blobId", &params.blob_id)
+                    .replace(
+                        ":filename",
+                        &percent_encoding::percent_encode(
+                            params.file_name.as_bytes(),
+                            percent_encoding::NON_ALPHANUMERIC,
+                        )
+                        .to_string(),
+                    );
+                Some(ReceivedAttachment {
+                    card_id: self.card_id.clone(),
+                    message_id: self.message_id.clone(),
+                    file_name: params.file_name,
+                    url,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 #[serde(rename_all = "camelCase")]
 pub struct ReactionPatch {
     pub card_id: String,
@@ -119,14 +348,39 @@ pub struct ReactionPatch {
     pub social_id: String,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+impl ReactionPatch {
+    /// Builds an outbound `reactionPatch` adding `reaction` to `message_id`, mirroring the
+    /// `add`/`remove` opcodes the platform already sends inbound.
+    pub fn new_add(
+        card_id: impl Into<String>,
+        message_id: impl Into<String>,
+        social_id: impl Into<String>,
+        reaction: impl Into<String>,
+    ) -> Self {
+        Self {
+            card_id: card_id.into(),
+            message_id: message_id.into(),
+            operation: ReactionPatchOperation {
+                opcode: "add".to_string(),
+                reaction: reaction.into(),
+            },
+            social_id: social_id.into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 #[serde(rename_all = "camelCase")]
 pub struct ReactionPatchOperation {
     pub opcode: String,
     pub reaction: String,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 #[serde(rename_all = "camelCase")]
 pub struct ThreadPatch {
     pub card_id: String,
@@ -135,7 +389,44 @@ pub struct ThreadPatch {
     pub operation: ThreadPatchOperation,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+/// A message edit (`update`, carrying the replacement `content`) or deletion (`remove`) — the two
+/// opcodes Huly emits for messages besides creation. Modeled separately from `CreateMessage`
+/// rather than as a variant of it, so a handler can react to a user correcting or retracting a
+/// message without re-checking which case it got.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
+#[serde(rename_all = "camelCase")]
+pub struct PatchMessage {
+    pub card_id: String,
+    pub message_id: String,
+    pub social_id: String,
+    pub operation: PatchMessageOperation,
+    #[serde(with = "rfc3339_millis")]
+    #[cfg_attr(feature = "ts", ts(type = "string"))]
+    pub date: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
+#[serde(rename_all = "camelCase", tag = "opcode")]
+pub enum PatchMessageOperation {
+    Update(UpdateMessageOperation),
+    Remove,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateMessageOperation {
+    pub content: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 #[serde(rename_all = "camelCase")]
 #[serde(tag = "opcode")]
 pub enum ThreadPatchOperation {
@@ -143,29 +434,39 @@ pub enum ThreadPatchOperation {
     Update(UpdateThreadOperation),
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 #[serde(rename_all = "camelCase")]
 pub struct AttachThreadOperation {
     pub thread_id: String,
     pub thread_type: String,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateThreadOperation {
     pub thread_id: String,
     pub updates: UpdateThreadOperationUpdates,
 }
 
-#[derive(Debug, Deserialize, PartialEq, Default)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Default)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateThreadOperationUpdates {
     pub thread_type: Option<String>,
     pub replies_count_op: Option<ThreadRepliesCountOp>,
-    pub last_reply: Option<String>,
+    #[serde(with = "rfc3339_millis::option", default)]
+    #[cfg_attr(feature = "ts", ts(type = "string"))]
+    pub last_reply: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 #[serde(rename_all = "camelCase")]
 pub enum ThreadRepliesCountOp {
     Increment,
@@ -177,6 +478,8 @@ pub enum CommunicationEvent {
     Message(ReceivedMessage),
     Reaction(ReceivedReaction),
     Attachment(ReceivedAttachment),
+    MessageEdited(ReceivedMessageEdit),
+    MessageRemoved(ReceivedMessageRemoval),
 }
 
 #[derive(Debug)]
@@ -189,11 +492,15 @@ pub struct ReceivedMessage {
     pub message_id: String,
     pub date: String,
     pub is_mention: bool,
+    /// Set for messages `huly::streaming::worker` fetched via its backfill step rather than
+    /// having seen live on the Kafka topic, so downstream consumers (the task multiplexer, the
+    /// model's context rendering) can treat them as history instead of a fresh trigger.
+    pub is_backfill: bool,
 }
 
 #[derive(Debug)]
 pub struct ReceivedAttachment {
-    pub channel_id: String,
+    pub card_id: String,
     pub message_id: String,
     pub file_name: String,
     pub url: String,
@@ -207,7 +514,24 @@ pub struct ReceivedReaction {
     pub reaction: String,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug)]
+pub struct ReceivedMessageEdit {
+    pub card_id: String,
+    pub message_id: String,
+    pub social_id: String,
+    pub content: String,
+    pub date: String,
+}
+
+#[derive(Debug)]
+pub struct ReceivedMessageRemoval {
+    pub card_id: String,
+    pub message_id: String,
+    pub social_id: String,
+    pub date: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct PersonInfo {
     pub person_id: String,
     pub person_name: String,
@@ -230,37 +554,70 @@ impl From<CreateMessage> for ReceivedMessage {
             message_id: value.message_id,
             date: value.date,
             is_mention: false,
+            is_backfill: false,
         }
     }
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 #[serde(rename_all = "camelCase")]
 pub struct CreateMessageOptions {
     pub skip_link_previews: bool,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateNotificationContext {
     #[serde(rename = "_id")]
     pub id: String,
     pub context_id: String,
+    #[cfg_attr(feature = "ts", ts(type = "string"))]
     pub account: AccountUuid,
     pub updates: Option<UpdateNotificationContextUpdates>,
-    pub date: String,
+    #[serde(with = "rfc3339_millis")]
+    #[cfg_attr(feature = "ts", ts(type = "string"))]
+    pub date: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(TS))]
+#[cfg_attr(feature = "ts", ts(export))]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateNotificationContextUpdates {
-    pub last_view: String,
+    #[serde(with = "rfc3339_millis")]
+    #[cfg_attr(feature = "ts", ts(type = "string"))]
+    pub last_view: DateTime<Utc>,
 }
 
 mod test {
     #[allow(unused_imports)]
     use super::*;
 
+    /// Serializes `event` and deserializes the result back, asserting it matches `event` — proof
+    /// that `Serialize` round-trips cleanly through the same shape `Deserialize` expects, not just
+    /// that it compiles.
+    fn assert_round_trip(event: &StreamingMessage) {
+        let json = serde_json::to_value(event).unwrap();
+        let round_tripped: StreamingMessage = serde_json::from_value(json).unwrap();
+        assert_eq!(&round_tripped, event);
+    }
+
+    /// Parses a fixture's RFC-3339 timestamp the same way `rfc3339_millis` does.
+    fn dt(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    /// Builds the `DateTime<Utc>` a fixture's epoch-millis `modifiedOn` deserializes to.
+    fn millis(ms: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(ms).unwrap()
+    }
+
     #[test]
     fn test_deserialize_workspace_event() {
         let event = serde_json::from_str::<StreamingMessage>(
@@ -286,11 +643,12 @@ mod test {
                     space: "core:space:DerivedTx".to_string(),
                     object_space: "core:space:DerivedTx".to_string(),
                     modified_by: "core:account:System".to_string(),
-                    modified_on: 1750912595092,
+                    modified_on: millis(1750912595092),
                 },
                 kind: StreamingMessageKind::Workspace(WorkspaceEvent {}),
             }
         );
+        assert_round_trip(&event);
     }
 
     #[test]
@@ -325,7 +683,7 @@ mod test {
                     space: "core:space:Tx".to_string(),
                     object_space: "core:space:Domain".to_string(),
                     modified_by: "1083545787011006465".to_string(),
-                    modified_on: 1750912588185,
+                    modified_on: millis(1750912588185),
                 },
                 kind: StreamingMessageKind::Domain(DomainEventKind::Communication(
                     CommunicationDomainEventKind::UpdateNotificationContext(
@@ -334,14 +692,15 @@ mod test {
                             context_id: "1083586688821067777".to_string(),
                             account: "be650ba5-4d82-40b2-a123-7d1e66f9d55c".parse().unwrap(),
                             updates: Some(UpdateNotificationContextUpdates {
-                                last_view: "2025-06-26T04:36:27.693Z".to_string(),
+                                last_view: dt("2025-06-26T04:36:27.693Z"),
                             }),
-                            date: "2025-06-26T04:36:28.176Z".to_string(),
+                            date: dt("2025-06-26T04:36:28.176Z"),
                         }
                     )
                 ))
             }
         );
+        assert_round_trip(&event);
     }
 
     #[test]
@@ -380,7 +739,7 @@ mod test {
                     space: "core:space:Tx".to_string(),
                     object_space: "core:space:Domain".to_string(),
                     modified_by: "1083545787011006465".to_string(),
-                    modified_on: 1750912595073,
+                    modified_on: millis(1750912595073),
                 },
                 kind: StreamingMessageKind::Domain(DomainEventKind::Communication(
                     CommunicationDomainEventKind::CreateMessage(CreateMessage {
@@ -394,11 +753,12 @@ mod test {
                         options: Some(CreateMessageOptions {
                             skip_link_previews: true,
                         }),
-                        date: "2025-06-26T04:36:35.056Z".to_string(),
+                        date: dt("2025-06-26T04:36:35.056Z"),
                     })
                 ))
             }
         );
+        assert_round_trip(&event);
     }
 
     #[test]
@@ -433,7 +793,7 @@ mod test {
                     space: "core:space:Tx".to_string(),
                     object_space: "core:space:Domain".to_string(),
                     modified_by: "1083586469763645441".to_string(),
-                    modified_on: 1751890174741,
+                    modified_on: millis(1751890174741),
                 },
                 kind: StreamingMessageKind::Domain(DomainEventKind::Communication(
                     CommunicationDomainEventKind::CreateMessage(CreateMessage {
@@ -445,11 +805,12 @@ mod test {
                         content: "asdasda".to_string(),
                         options: None,
                         social_id: "1083545787011006465".to_string(),
-                        date: "2025-07-07T12:09:34.729Z".to_string(),
+                        date: dt("2025-07-07T12:09:34.729Z"),
                     })
                 ))
             }
         );
+        assert_round_trip(&event);
     }
 
     #[test]
@@ -504,7 +865,7 @@ mod test {
                     space: "core:space:Tx".to_string(),
                     object_space: "core:space:Domain".to_string(),
                     modified_by: "1083545787011006465".to_string(),
-                    modified_on: 1755173733877,
+                    modified_on: millis(1755173733877),
                 },
                 kind: StreamingMessageKind::Domain(DomainEventKind::Communication(
                     CommunicationDomainEventKind::AttachmentPatch(AttachmentPatch {
@@ -535,6 +896,7 @@ mod test {
                 ))
             }
         );
+        assert_round_trip(&event);
     }
 
     #[test]
@@ -571,7 +933,7 @@ mod test {
                     space: "core:space:Tx".to_string(),
                     object_space: "core:space:Domain".to_string(),
                     modified_by: "1083545787011006465".to_string(),
-                    modified_on: 1755173733877,
+                    modified_on: millis(1755173733877),
                 },
                 kind: StreamingMessageKind::Domain(DomainEventKind::Communication(
                     CommunicationDomainEventKind::ReactionPatch(ReactionPatch {
@@ -586,6 +948,7 @@ mod test {
                 ))
             }
         );
+        assert_round_trip(&event);
     }
     #[test]
     fn test_deserialize_domain_event_thread_patch() {
@@ -622,7 +985,7 @@ mod test {
                     space: "core:space:Tx".to_string(),
                     object_space: "core:space:Domain".to_string(),
                     modified_by: "1064398389519122433".to_string(),
-                    modified_on: 1755624602371
+                    modified_on: millis(1755624602371)
                 },
                 kind: StreamingMessageKind::Domain(DomainEventKind::Communication(
                     CommunicationDomainEventKind::ThreadPatch(ThreadPatch {
@@ -637,6 +1000,7 @@ mod test {
                 ))
             }
         );
+        assert_round_trip(&attach_thread_event);
         let update_thread_event = serde_json::from_str::<StreamingMessage>(
             r#"{
                    "_id": "68a4b7c52eab7e2ce6351689",
@@ -672,7 +1036,7 @@ mod test {
                     space: "core:space:Tx".to_string(),
                     object_space: "core:space:Domain".to_string(),
                     modified_by: "1064398389519122433".to_string(),
-                    modified_on: 1755625413138
+                    modified_on: millis(1755625413138)
                 },
                 kind: StreamingMessageKind::Domain(DomainEventKind::Communication(
                     CommunicationDomainEventKind::ThreadPatch(ThreadPatch {
@@ -683,7 +1047,7 @@ mod test {
                             thread_id: "68a4b49a0b40986b3b3c11fc".to_string(),
                             updates: UpdateThreadOperationUpdates {
                                 replies_count_op: Some(ThreadRepliesCountOp::Increment),
-                                last_reply: Some("2025-08-19T17:43:33.012Z".to_string()),
+                                last_reply: Some(dt("2025-08-19T17:43:33.012Z")),
                                 ..Default::default()
                             }
                         })
@@ -691,6 +1055,7 @@ mod test {
                 ))
             }
         );
+        assert_round_trip(&update_thread_event);
     }
 
     #[test]
@@ -720,7 +1085,7 @@ mod test {
                     space: "core:space:Tx".to_string(),
                     object_space: "card:space:Default".to_string(),
                     modified_by: "1083545787011006465".to_string(),
-                    modified_on: 1750912595092,
+                    modified_on: millis(1750912595092),
                 },
                 kind: StreamingMessageKind::Unknown(serde_json::json!({
                     "_class": "core:class:TxUpdateDoc",
@@ -733,5 +1098,48 @@ mod test {
                 }))
             }
         );
+        assert_round_trip(&event);
+    }
+
+    #[test]
+    fn test_decode_malformed_frame_recovers_common_params() {
+        let raw = r#"{
+            "_id": "685cce537a178870b1f19a30",
+            "_class": "core:class:TxDomainEvent",
+            "space": "core:space:Tx",
+            "objectSpace": "core:space:Domain",
+            "modifiedBy": "1083545787011006465",
+            "modifiedOn": 1750912595092,
+            "domain": "communication",
+            "event": {
+                "type": "createMessage",
+                "operations": "not-an-array"
+            }
+        }"#;
+        let message = StreamingMessage::decode(raw);
+        assert_eq!(
+            message.params,
+            CommonParams {
+                id: "685cce537a178870b1f19a30".to_string(),
+                space: "core:space:Tx".to_string(),
+                object_space: "core:space:Domain".to_string(),
+                modified_by: "1083545787011006465".to_string(),
+                modified_on: millis(1750912595092),
+            }
+        );
+        match message.kind {
+            StreamingMessageKind::Malformed { raw: stored, error } => {
+                assert_eq!(stored, raw);
+                assert!(!error.is_empty());
+            }
+            other => panic!("expected Malformed, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_decode_garbage_falls_back_to_empty_params() {
+        let message = StreamingMessage::decode("not json at all");
+        assert_eq!(message.params, CommonParams::default());
+        assert!(matches!(message.kind, StreamingMessageKind::Malformed { .. }));
     }
 }
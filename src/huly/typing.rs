@@ -7,6 +7,7 @@ use hulyrs::services::{
 };
 use serde::Serialize;
 
+#[derive(Clone)]
 pub struct TypingClient {
     client: PulseClient,
     social_id: Ref,
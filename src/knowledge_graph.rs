@@ -0,0 +1,88 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! Domain types for the knowledge-graph memory toolset (`tools::memory`), matching the shape
+//! the upstream MCP memory server's tool arguments use. Kept separate from `crate::memory`,
+//! which is the unrelated episodic `MemoryEntity` extraction pipeline.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Observation {
+    #[serde(rename = "entityName")]
+    pub entity_name: String,
+    pub observations: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Entity {
+    #[serde(skip)]
+    pub id: i64,
+    pub name: String,
+    #[serde(rename = "entityType")]
+    pub entity_type: String,
+    pub observations: Vec<String>,
+    /// Cosine-similarity score against the query, set only by `search_nodes`'s `semantic`/
+    /// `hybrid` modes. Absent (and omitted from JSON) everywhere else.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Relation {
+    pub from: String,
+    pub to: String,
+    #[serde(rename = "relationType")]
+    pub relation_type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct KnowledgeGraph {
+    pub entities: Vec<Entity>,
+    pub relations: Vec<Relation>,
+}
+
+/// How `search_nodes` matches entities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Case-insensitive substring match over entity name/type and observation text.
+    #[default]
+    Keyword,
+    /// Cosine similarity between the query embedding and each entity's observations.
+    Semantic,
+    /// Keyword and semantic results merged by reciprocal rank fusion.
+    Hybrid,
+}
+
+/// Reciprocal-rank-fusion constant from the original RRF paper; large enough that a single
+/// mode's top hit doesn't dominate the other mode's entire ranking.
+const RRF_K: f32 = 60.0;
+
+/// Merges two rankings (best match first) of the same entities by reciprocal rank fusion,
+/// returning entities sorted by descending fused score with `score` set to that fused score.
+pub fn reciprocal_rank_fusion(keyword: Vec<Entity>, semantic: Vec<Entity>) -> Vec<Entity> {
+    let mut fused: std::collections::HashMap<String, (Entity, f32)> = std::collections::HashMap::new();
+    for (rank, entity) in keyword.into_iter().enumerate() {
+        let score = 1.0 / (RRF_K + rank as f32 + 1.0);
+        fused
+            .entry(entity.name.clone())
+            .and_modify(|(_, s)| *s += score)
+            .or_insert((entity, score));
+    }
+    for (rank, entity) in semantic.into_iter().enumerate() {
+        let score = 1.0 / (RRF_K + rank as f32 + 1.0);
+        fused
+            .entry(entity.name.clone())
+            .and_modify(|(_, s)| *s += score)
+            .or_insert((entity, score));
+    }
+    let mut entities: Vec<Entity> = fused
+        .into_values()
+        .map(|(mut entity, score)| {
+            entity.score = Some(score);
+            entity
+        })
+        .collect();
+    entities.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+    entities
+}
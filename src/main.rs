@@ -5,11 +5,12 @@ use std::panic::set_hook;
 use std::panic::take_hook;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
+use actix_web::dev::ServerHandle;
 use anyhow::Context;
 use anyhow::Result;
 use anyhow::bail;
-use dashmap::DashMap;
 use huly::fetch_server_config;
 use hulyrs::ServiceFactory;
 use hulyrs::services::account::LoginParams;
@@ -42,6 +43,7 @@ use crate::context::HulyAccountInfo;
 use crate::huly::blob::BlobClient;
 use crate::huly::types::Person;
 use crate::huly::typing::TypingClient;
+use crate::task::JobOutcome;
 use crate::task::Task;
 use crate::task::task_multiplexer;
 use crate::tools::command::process_registry::ProcessRegistry;
@@ -49,23 +51,38 @@ use crate::tools::command::process_registry::ProcessRegistry;
 use clap::Parser;
 use tokio::select;
 use tokio::signal::*;
+use tokio_util::sync::CancellationToken;
 
 mod agent;
+mod bench;
+mod collab;
 mod communication;
+mod compaction;
 mod config;
 mod context;
 mod database;
+mod embeddings;
+mod error;
 mod huly;
+mod knowledge_graph;
 mod memory;
+mod note;
+mod note_classifier;
 mod otel;
+mod protocol;
 mod providers;
+mod rrule;
 mod scheduler;
 mod state;
+mod storage;
+mod supervisor;
 mod task;
+mod task_manager;
 mod templates;
 mod tools;
 mod types;
 mod utils;
+mod worker;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -73,6 +90,11 @@ struct Args {
     /// Path to data directory
     #[arg(short, long, default_value = "data")]
     data: String,
+    /// Path to a workload JSON file. When set, runs that workload through the agent via
+    /// `bench::run_workload` and prints the resulting report instead of starting the full service
+    /// (scheduler, http server, streaming worker).
+    #[arg(long)]
+    benchmark: Option<String>,
 }
 
 fn init_logger(config: &Config) -> Result<()> {
@@ -106,11 +128,23 @@ fn init_logger(config: &Config) -> Result<()> {
                 .with_target(&package_name, config.log_level),
         );
 
-    tracing_subscriber::registry()
+    #[cfg(feature = "console")]
+    let console_subscriber_layer = config.console.then(console_subscriber::spawn);
+    #[cfg(not(feature = "console"))]
+    if config.console {
+        tracing::warn!("config.console is set but this binary wasn't built with the \"console\" feature");
+    }
+
+    let registry = tracing_subscriber::registry()
         .with(console_layer)
         .with(tracer_layer)
-        .with(logger_layer)
-        .try_init()?;
+        .with(logger_layer);
+
+    #[cfg(feature = "console")]
+    registry.with(console_subscriber_layer).try_init()?;
+    #[cfg(not(feature = "console"))]
+    registry.try_init()?;
+
     Ok(())
 }
 
@@ -148,6 +182,34 @@ async fn wait_interrupt() -> Result<()> {
     Ok(())
 }
 
+/// Reacts to SIGHUP by re-reading `ConfigOverrideStore`'s overrides and rebuilding the embedding
+/// provider if the effective config changed, so an operator can rotate a VoyageAI key or switch
+/// models without restarting the process. A no-op on platforms without SIGHUP.
+#[cfg(unix)]
+fn spawn_config_reload_watcher(db_client: database::DbClient) {
+    tokio::spawn(async move {
+        let mut hup = match unix::signal(unix::SignalKind::hangup()) {
+            Ok(hup) => hup,
+            Err(err) => {
+                tracing::error!(?err, "Failed to install SIGHUP handler, config reload disabled");
+                return;
+            }
+        };
+        loop {
+            hup.recv().await;
+            tracing::info!("Received SIGHUP, reloading embedding provider config");
+            match db_client.reload_embedding_provider().await {
+                Ok(true) => tracing::info!("Embedding provider reloaded"),
+                Ok(false) => tracing::info!("Embedding provider config unchanged, nothing to reload"),
+                Err(err) => tracing::error!(?err, "Failed to reload embedding provider"),
+            }
+        }
+    });
+}
+
+#[cfg(windows)]
+fn spawn_config_reload_watcher(_db_client: database::DbClient) {}
+
 #[cfg(windows)]
 async fn wait_interrupt() -> Result<()> {
     let mut term = windows::ctrl_close()?;
@@ -381,27 +443,87 @@ async fn main() -> Result<()> {
     let process_registry = Arc::new(RwLock::new(process_registry));
 
     let db_client = database::DbClient::new(&args.data, &config).await?;
+    spawn_config_reload_watcher(db_client.clone());
     let pulse_client =
         service_factory.new_pulse_client(account_info.workspace, account_info.token.clone())?;
     let typing_client = TypingClient::new(pulse_client, &account_info.person_id);
 
+    let worker_manager = Arc::new(worker::WorkerManager::new(db_client.clone()));
+    let paused_workers = db_client.paused_worker_ids().await?;
+    let outbound_hub = Arc::new(communication::OutboundHub::new());
+    let task_manager = Arc::new(task_manager::TaskManager::new());
+    tokio::spawn(task_manager.clone().run_watchdog());
+
     let agent_context = AgentContext {
         account_info: account_info.clone(),
         process_registry: process_registry.clone(),
         tx_client: tx_client.clone(),
+        resilient_tx: huly::resilient::ResilientTransactor::new(tx_client.clone()),
         blob_client,
         typing_client,
         db_client: db_client.clone(),
+        worker_manager: worker_manager.clone(),
+        task_manager: task_manager.clone(),
+        outbound_hub: outbound_hub.clone(),
         tools_context: None,
         tools_system_prompt: None,
+        tool_result_cache: tools::cache::ToolResultCache::new(Duration::from_secs(
+            config.tool_result_cache_ttl_secs,
+        )),
     };
 
     tracing::info!("Logged in as {}", account_info.account_uuid);
 
+    if let Some(benchmark) = &args.benchmark {
+        let report = bench::run_workload(&config, agent_context, Path::new(benchmark)).await?;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
     let (messages_sender, messages_receiver) = mpsc::unbounded_channel();
     let (task_sender, task_receiver) = tokio::sync::mpsc::unbounded_channel::<Task>();
     let (memory_task_sender, memory_task_receiver) = tokio::sync::mpsc::unbounded_channel::<Task>();
     let (activity_sender, activity_receiver) = tokio::sync::mpsc::unbounded_channel();
+    let (job_outcome_sender, job_outcome_receiver) =
+        tokio::sync::mpsc::unbounded_channel::<JobOutcome>();
+
+    let tranquility = config
+        .tasks
+        .iter()
+        .filter_map(|(kind, task_config)| {
+            task_config
+                .tranquility_secs
+                .map(|secs| (kind.clone(), Duration::from_secs(secs)))
+        })
+        .collect();
+
+    let context_budgets = config
+        .tasks
+        .iter()
+        .filter_map(|(kind, task_config)| {
+            task_config.context_budget.map(|budget| {
+                (
+                    kind.clone(),
+                    budget.saturating_sub(task_config.context_reserve.unwrap_or(0)),
+                )
+            })
+        })
+        .collect();
+    let encoder = Arc::new(task::encoder_for(&config.provider, &config.model)?);
+    let memory_extractor = Arc::new(memory::MemoryExtractor::new(&config)?);
+
+    let replay = db_client
+        .journaled_messages()
+        .await?
+        .into_iter()
+        .filter_map(|(card_id, message_id, payload)| {
+            serde_json::from_str(&payload)
+                .inspect_err(|err| {
+                    tracing::warn!(%err, card_id, message_id, "Failed to deserialize journaled message, skipping");
+                })
+                .ok()
+        })
+        .collect();
 
     let task_multiplexer = task_multiplexer(
         messages_receiver,
@@ -409,34 +531,116 @@ async fn main() -> Result<()> {
         config.agent_mode.clone(),
         account_info.clone(),
         tx_client.clone(),
+        task_manager.clone(),
+        config.max_concurrent_tasks,
+        tranquility,
+        db_client.clone(),
+        replay,
+        context_budgets,
+        encoder,
+        memory_extractor,
     );
 
-    let upcoming_jobs = Arc::new(DashMap::new());
     let agent = Agent::new(config.clone())?;
 
-    let agent_handle = agent.run(task_receiver, memory_task_sender, agent_context);
-
-    let memory_worker_handler =
-        memory::memory_worker(&config, memory_task_receiver, db_client.clone())?;
-
-    let scheduler_handler = scheduler::scheduler(
+    // Boxed+pinned, like `streaming_worker`/`http_server_task` below, so it can be awaited again
+    // after the top-level `select!` to let `Agent::shutdown`'s drain finish once requested.
+    let mut agent_handle = Box::pin(agent.run(
+        task_receiver,
+        memory_task_sender,
+        job_outcome_sender,
+        agent_context,
+    ));
+
+    worker_manager
+        .spawn(
+            "memory_maintenance",
+            Box::new(memory::MemoryMaintenanceWorker::new(
+                &config,
+                memory_task_receiver,
+                db_client.clone(),
+            )?),
+            paused_workers.iter().any(|id| id == "memory_maintenance"),
+        )
+        .await;
+
+    let scheduler_worker = scheduler::SchedulerWorker::new(
         &config,
         db_client.clone(),
         task_sender.clone(),
-        upcoming_jobs.clone(),
         activity_receiver,
-    )?;
-
-    let streaming_worker =
-        communication::streaming_worker(&config, &server_config, account_info, tx_client);
-
-    let (http_server, http_server_handle) = communication::http::server(
+        job_outcome_receiver,
+    )
+    .await?;
+    let scheduler_last_tick = scheduler_worker.last_tick_handle();
+
+    worker_manager
+        .spawn(
+            "scheduler",
+            Box::new(scheduler_worker),
+            paused_workers.iter().any(|id| id == "scheduler"),
+        )
+        .await;
+
+    let streaming_shutdown = CancellationToken::new();
+    let mut streaming_worker = Box::pin(communication::streaming_worker(
         &config,
-        messages_sender,
-        db_client,
-        upcoming_jobs,
-        activity_sender,
-    )?;
+        &server_config,
+        account_info,
+        tx_client,
+        streaming_shutdown.clone(),
+    ));
+
+    // The actix server is wrapped in `supervisor::supervise` rather than awaited as a bare
+    // `JoinHandle`: if its task returns an error or panics, nothing previously noticed or
+    // restarted it. `http_server_handle` is re-populated on every (re)bind so the shutdown path
+    // below always has a handle for whichever instance is currently live.
+    let http_shutdown = CancellationToken::new();
+    let http_server_handle: Arc<tokio::sync::Mutex<Option<ServerHandle>>> =
+        Arc::new(tokio::sync::Mutex::new(None));
+    let remote_worker_registry = Arc::new(communication::ws::RemoteWorkerRegistry::new());
+    let mut http_server_task = Box::pin({
+        let config = config.clone();
+        let handle_cell = http_server_handle.clone();
+        supervisor::supervise(
+            "http_server",
+            "http",
+            supervisor::RestartPolicy::default(),
+            http_shutdown.clone(),
+            move || {
+                let config = config.clone();
+                let messages_sender = messages_sender.clone();
+                let db_client = db_client.clone();
+                let activity_sender = activity_sender.clone();
+                let outbound_hub = outbound_hub.clone();
+                let task_manager = task_manager.clone();
+                let remote_worker_registry = remote_worker_registry.clone();
+                let handle_cell = handle_cell.clone();
+                let scheduler_last_tick = scheduler_last_tick.clone();
+                async move {
+                    let (join_handle, server_handle) = communication::http::server(
+                        &config,
+                        messages_sender,
+                        db_client,
+                        activity_sender,
+                        outbound_hub,
+                        task_manager,
+                        remote_worker_registry,
+                        scheduler_last_tick,
+                    )?;
+                    *handle_cell.lock().await = Some(server_handle);
+                    join_handle.await??;
+                    Ok(())
+                }
+            },
+            |group| {
+                tracing::error!(
+                    group,
+                    "Http server supervision exhausted its restart budget, giving up"
+                )
+            },
+        )
+    });
 
     select! {
         _ = wait_interrupt() => {
@@ -448,29 +652,42 @@ async fn main() -> Result<()> {
                 tracing::info!("Task multiplexer terminated");
             }
         }
-        res = agent_handle => {
+        res = &mut agent_handle => {
             if let Err(e) = res {
                 tracing::error!("Agent error: {:?}", e);
             }
             tracing::info!("Agent terminated");
         }
 
-        _ = streaming_worker => {
+        _ = &mut streaming_worker => {
             tracing::info!("Streaming worker terminated");
         }
 
-        res = http_server => {
-            if let Err(e) = res {
-                tracing::error!("Http server error: {:?}", e);
-            }
-            tracing::info!("Http server terminated");
+        _ = &mut http_server_task => {
+            tracing::info!("Http server supervisor terminated");
         }
     }
 
+    // Let the agent stop accepting new tasks and drain whatever it already has in flight (up to
+    // its configured grace period) instead of being dropped mid-task; a no-op if it already
+    // finished above.
+    agent.shutdown();
+    agent_handle.await;
+
+    // Give the streaming worker a chance to stop reconnecting and drain any already-queued
+    // events instead of being dropped mid-delivery; a no-op if it already finished above.
+    streaming_shutdown.cancel();
+    streaming_worker.await;
+
     tracing::debug!("Shutting down");
-    http_server_handle.stop(true).await;
+    http_shutdown.cancel();
+    http_server_task.await;
+    if let Some(handle) = http_server_handle.lock().await.take() {
+        handle.stop(true).await;
+    }
     process_registry.write().await.stop().await;
-    memory_worker_handler.abort();
-    scheduler_handler.abort();
+    for id in ["memory_maintenance", "scheduler", "task_router"] {
+        let _ = worker_manager.send(id, worker::WorkerCommand::Cancel).await;
+    }
     Ok(())
 }
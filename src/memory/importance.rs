@@ -1,30 +1,16 @@
 // Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
 
-use std::collections::HashMap;
-
 use chrono::Utc;
 
-use crate::memory::MemoryEntity;
+use crate::{config::MemoryScoringConfig, memory::MemoryEntity};
 
 pub(super) struct ImportanceCalculator {
-    max_access_count: u32,
-    max_relations_count: u32,
-    decay_rates: HashMap<String, f32>,
+    scoring: MemoryScoringConfig,
 }
 
 impl ImportanceCalculator {
-    pub fn new() -> Self {
-        let mut decay_rates = HashMap::new();
-        decay_rates.insert("topic".to_string(), 0.1);
-        decay_rates.insert("location".to_string(), 0.07);
-        decay_rates.insert("person".to_string(), 0.04);
-        decay_rates.insert("concept".to_string(), 0.03);
-
-        Self {
-            max_access_count: 1000,
-            max_relations_count: 20,
-            decay_rates,
-        }
+    pub fn new(scoring: MemoryScoringConfig) -> Self {
+        Self { scoring }
     }
 
     pub fn calculate_importance(&self, memory: &MemoryEntity) -> f32 {
@@ -33,10 +19,10 @@ impl ImportanceCalculator {
         let frequency_factor = self.calculate_frequency_factor(memory.access_count);
         let relations_factor = self.calculate_relations_factor(memory.relations.len());
 
-        let combined_importance = 0.35 * memory.importance
-            + 0.25 * time_factor
-            + 0.25 * frequency_factor
-            + 0.15 * relations_factor;
+        let combined_importance = self.scoring.stored_weight * memory.importance
+            + self.scoring.time_weight * time_factor
+            + self.scoring.frequency_weight * frequency_factor
+            + self.scoring.relations_weight * relations_factor;
 
         combined_importance.clamp(0.0, 1.0)
     }
@@ -52,20 +38,21 @@ impl ImportanceCalculator {
             return 0.0;
         }
 
-        let normalized_count = (access_count as f32).min(self.max_access_count as f32);
-        (1.0 + normalized_count).ln() / (1.0 + self.max_access_count as f32).ln()
+        let normalized_count = (access_count as f32).min(self.scoring.max_access_count as f32);
+        (1.0 + normalized_count).ln() / (1.0 + self.scoring.max_access_count as f32).ln()
     }
 
     fn calculate_relations_factor(&self, relations_count: usize) -> f32 {
-        (relations_count as f32 / self.max_relations_count as f32).min(1.0)
+        (relations_count as f32 / self.scoring.max_relations_count as f32).min(1.0)
     }
 
     fn calculate_decay_rate(&self, memory: &MemoryEntity) -> f32 {
         let base_decay = self
+            .scoring
             .decay_rates
-            .get(&memory.entity_type)
+            .get(&memory.entity_type.to_string())
             .copied()
-            .unwrap_or(0.05);
+            .unwrap_or(self.scoring.default_decay_rate);
 
         let mut decay_rate = if memory.access_count > 50 {
             base_decay * 0.5
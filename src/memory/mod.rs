@@ -3,12 +3,13 @@
 use std::{collections::HashMap, fmt::Display, vec};
 
 use anyhow::{Context, Result};
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use reqwest::{Client, ClientBuilder};
 use secrecy::ExposeSecret;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tokio::{sync::mpsc::UnboundedReceiver, task::JoinHandle};
+use tokio::sync::mpsc::UnboundedReceiver;
 
 mod importance;
 
@@ -16,14 +17,21 @@ use crate::{
     config::Config,
     memory::importance::ImportanceCalculator,
     task::{Task, TaskKind},
+    worker::{Worker, WorkerState},
 };
 
 const MAX_OBSERVATIONS: usize = 20;
 const MAX_MEMORY_ENTITIES: u16 = 10;
 const DELETE_THRESHOLD: f32 = 0.01;
 
+/// This worker's component in `MemoryEntity::version_vector` (see `merge_entities`).
+const MEMORY_WORKER_WRITER: &str = "memory_worker";
+
 const OPENROUTER_URL: &str = "https://openrouter.ai/api/v1/chat/completions";
-struct MemoryExtractor {
+/// Thin OpenRouter client bound to `MemoryConfig::extract_model`, used for LLM calls that sit
+/// outside the main agent loop's provider (memory extraction here, overflow summarization in
+/// `task::format_messages`).
+pub(crate) struct MemoryExtractor {
     client: Client,
     system_prompt: String,
     model: String,
@@ -55,6 +63,12 @@ pub struct MemoryEntity {
     pub created_at: DateTime<Utc>,
     #[serde(skip_deserializing)]
     pub updated_at: DateTime<Utc>,
+    /// Causal token: a version vector keyed by writer id (e.g. `"memory_worker"`,
+    /// `"sleep_task"`), bumped in the writer's own component on every successful write. Read by
+    /// `mem_entity`/`mem_entity_by_name`, carried back through `mem_update_entity` so the backend
+    /// can tell a clean update from one racing a concurrent writer (see `merge_entities`).
+    #[serde(skip)]
+    pub version_vector: HashMap<String, u64>,
 }
 
 #[derive(Debug, Clone, sqlx::Type, Serialize, Deserialize)]
@@ -138,8 +152,87 @@ impl Display for MemoryEntity {
     }
 }
 
+/// Element-wise max of two version vectors (a G-Counter merge): commutative, associative and
+/// idempotent, so applying it in any order or repeatedly converges to the same result.
+pub(crate) fn merge_version_vectors(
+    a: &HashMap<String, u64>,
+    b: &HashMap<String, u64>,
+) -> HashMap<String, u64> {
+    let mut merged = a.clone();
+    for (writer, count) in b {
+        let entry = merged.entry(writer.clone()).or_insert(0);
+        if *count > *entry {
+            *entry = *count;
+        }
+    }
+    merged
+}
+
+/// Bumps `writer`'s own component, the only component a writer is ever allowed to advance.
+pub(crate) fn bump_version_vector(vector: &mut HashMap<String, u64>, writer: &str) {
+    *vector.entry(writer.to_string()).or_insert(0) += 1;
+}
+
+/// Deterministically merges two causally-concurrent writes to the same entity (same `id`, and by
+/// invariant the same `name`/`entity_type`) into one, so replaying either delivery order converges
+/// to the same stored state: `observations` and `relations` are unioned (deduped against what's
+/// already there), `access_count` takes the max, `updated_at` the latest, `created_at` the
+/// earliest, and `importance` is recomputed over the merged entity. Every tiebreak is symmetric in
+/// `a`/`b` (never "whichever argument came first"), which is what makes this commutative and
+/// idempotent — required so two replicas applying the same two siblings in opposite order still
+/// agree. `version_vector` is the caller's responsibility (see `merge_version_vectors`); `id` is
+/// shared identity, not merged content.
+pub(crate) fn merge_entities(a: MemoryEntity, b: MemoryEntity) -> MemoryEntity {
+    // Put the siblings in a canonical order first, so the unions below list their entries in the
+    // same order whichever way round the arguments were passed.
+    let (a, b) = if (a.updated_at, &a.observations, &a.relations)
+        < (b.updated_at, &b.observations, &b.relations)
+    {
+        (b, a)
+    } else {
+        (a, b)
+    };
+    let mut observations = a.observations.clone();
+    for observation in &b.observations {
+        if !observations.contains(observation) {
+            observations.push(observation.clone());
+        }
+    }
+    let mut relations = a.relations.clone();
+    for relation in &b.relations {
+        if !relations.contains(relation) {
+            relations.push(relation.clone());
+        }
+    }
+    let category = match a.updated_at.cmp(&b.updated_at) {
+        std::cmp::Ordering::Greater => a.category.clone(),
+        std::cmp::Ordering::Less => b.category.clone(),
+        std::cmp::Ordering::Equal => a.category.clone().max(b.category.clone()),
+    };
+
+    let mut merged = MemoryEntity {
+        id: a.id,
+        name: a.name.clone(),
+        category,
+        entity_type: a.entity_type.clone(),
+        importance: a.importance.max(b.importance),
+        access_count: a.access_count.max(b.access_count),
+        observations,
+        relations,
+        created_at: a.created_at.min(b.created_at),
+        updated_at: a.updated_at.max(b.updated_at),
+        version_vector: HashMap::new(),
+    };
+    // `merge_entities` is called from the storage backends on a concurrent-write conflict, which
+    // have no `Config` access, so it always scores with the default weights.
+    merged.importance =
+        ImportanceCalculator::new(crate::config::MemoryScoringConfig::default())
+            .calculate_importance(&merged);
+    merged
+}
+
 impl MemoryExtractor {
-    pub fn new(config: &Config) -> Result<Self> {
+    pub(crate) fn new(config: &Config) -> Result<Self> {
         Ok(Self {
             client: ClientBuilder::new()
                 .default_headers({
@@ -226,12 +319,57 @@ impl MemoryExtractor {
         tracing::warn!(%response, "No json formated content in message");
         Ok(vec![])
     }
+
+    /// Collapses `overflowing_content` (the oldest messages trimmed from a `format_messages` block
+    /// by a `TaskConfig::context_budget`) into a single summary line, so a long-running channel
+    /// degrades to a compact recap instead of silently losing its early history.
+    pub(crate) async fn summarize_overflow(&self, overflowing_content: &str) -> Result<String> {
+        let request = json!({
+            "messages": [
+                {
+                    "role": "system",
+                    "content": "Summarize the following chat messages into a single concise line, preserving names and concrete facts. Reply with the summary line only.",
+                },
+                {
+                    "role": "user",
+                    "content": overflowing_content,
+                }
+            ],
+            "model": self.model,
+            "temperature": 0.0,
+        });
+        let response = self
+            .client
+            .post(OPENROUTER_URL)
+            .json(&request)
+            .send()
+            .await?
+            .text()
+            .await?;
+
+        let response = serde_json::from_str::<serde_json::Value>(&response)
+            .with_context(|| format!("Failed to parse response: {response}"))?;
+        let content = response
+            .get("choices")
+            .and_then(|choices| choices.as_array())
+            .and_then(|choices| choices.first())
+            .and_then(|choice| choice.get("message"))
+            .and_then(|message| message.get("content"))
+            .and_then(|content| content.as_str());
+
+        let Some(content) = content else {
+            tracing::warn!(%response, "No content in overflow summary response");
+            return Ok(String::new());
+        };
+        Ok(content.trim().to_string())
+    }
 }
 
 async fn process_follow_chat(
     memory_extractor: &MemoryExtractor,
     db_client: &crate::database::DbClient,
     user_name: &str,
+    scoring: &crate::config::MemoryScoringConfig,
     task_id: i64,
     content: &str,
 ) -> Result<()> {
@@ -270,6 +408,7 @@ async fn process_follow_chat(
                             ));
                         }
                     }
+                    crate::types::AssistantContent::Reasoning(_) => {}
                 }
             }
         }
@@ -309,7 +448,7 @@ async fn process_follow_chat(
         ]),
     )?;
     let entities = memory_extractor.extract(&context, &text).await?;
-    let importance_calculator = ImportanceCalculator::new();
+    let importance_calculator = ImportanceCalculator::new(scoring.clone());
 
     for mut ex_entity in entities {
         let entity = db_client
@@ -332,7 +471,7 @@ async fn process_follow_chat(
 
             entity.updated_at = Utc::now();
             entity.importance = importance_calculator.calculate_importance(&entity);
-            db_client.mem_update_entity(&entity).await?;
+            db_client.mem_update_entity(&entity, MEMORY_WORKER_WRITER).await?;
         } else {
             let mut entity = MemoryEntity {
                 id: 0,
@@ -345,19 +484,27 @@ async fn process_follow_chat(
                 relations: vec![],
                 created_at: Default::default(),
                 updated_at: Default::default(),
+                version_vector: HashMap::new(),
             };
             entity.importance = importance_calculator.calculate_importance(&entity);
-            db_client.mem_add_entity(&entity).await?;
+            db_client.mem_add_entity(&entity, MEMORY_WORKER_WRITER).await?;
         }
     }
     Ok(())
 }
 
-async fn memory_mantainance(db_client: &crate::database::DbClient) -> Result<()> {
+/// Recomputes and persists `calculate_importance` across every memory entity, deleting those that
+/// decayed below `DELETE_THRESHOLD`. Also called directly ahead of consolidation in
+/// `agent::sleep_task::process_sleep_task`, so `mem_entities_ids_for_consolidation`'s threshold
+/// check sees freshly decayed scores rather than whatever was last persisted.
+pub(crate) async fn memory_mantainance(
+    db_client: &crate::database::DbClient,
+    scoring: &crate::config::MemoryScoringConfig,
+) -> Result<()> {
     let ids = db_client.mem_get_entity_ids().await?;
     let total_count = ids.len();
     tracing::info!("Memory entities count: {total_count}");
-    let importance_calculator = ImportanceCalculator::new();
+    let importance_calculator = ImportanceCalculator::new(scoring.clone());
     let mut to_delete = vec![];
     for id in ids {
         let entity = db_client.mem_entity(id).await?;
@@ -379,39 +526,59 @@ async fn memory_mantainance(db_client: &crate::database::DbClient) -> Result<()>
     Ok(())
 }
 
-pub fn memory_worker(
-    config: &Config,
-    mut rx: UnboundedReceiver<Task>,
+/// Drains `FollowChat`/`MemoryMantainance` tasks one at a time, as a `Worker` registered with
+/// `WorkerManager`. Replaces the old free-standing `memory_worker()` task.
+pub struct MemoryMaintenanceWorker {
+    rx: UnboundedReceiver<Task>,
     db_client: crate::database::DbClient,
-) -> Result<JoinHandle<()>> {
-    let memory_extractor = MemoryExtractor::new(config)?;
-    let user_name = config.huly.person.name.clone();
-    let handler = tokio::spawn(async move {
-        tracing::info!("Memory worker started");
-        while let Some(task) = rx.recv().await {
-            match task.kind {
-                TaskKind::FollowChat { content, .. } => {
-                    if let Err(e) = process_follow_chat(
-                        &memory_extractor,
-                        &db_client,
-                        &user_name,
-                        task.id,
-                        &content,
-                    )
-                    .await
-                    {
-                        tracing::error!(?e, "Error processing task");
-                    }
-                }
-                TaskKind::MemoryMantainance => {
-                    if let Err(e) = memory_mantainance(&db_client).await {
-                        tracing::error!(?e, "Error processing memory maintenance task");
-                    }
-                }
-                _ => {}
+    memory_extractor: MemoryExtractor,
+    user_name: String,
+    scoring: crate::config::MemoryScoringConfig,
+}
+
+impl MemoryMaintenanceWorker {
+    pub fn new(
+        config: &Config,
+        rx: UnboundedReceiver<Task>,
+        db_client: crate::database::DbClient,
+    ) -> Result<Self> {
+        Ok(Self {
+            rx,
+            db_client,
+            memory_extractor: MemoryExtractor::new(config)?,
+            user_name: config.huly.person.name.clone(),
+            scoring: config.memory.scoring.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl Worker for MemoryMaintenanceWorker {
+    fn name(&self) -> &str {
+        "memory_maintenance"
+    }
+
+    async fn step(&mut self) -> Result<WorkerState> {
+        let Some(task) = self.rx.recv().await else {
+            return Ok(WorkerState::Done);
+        };
+        match task.kind {
+            TaskKind::FollowChat { content, .. } => {
+                process_follow_chat(
+                    &self.memory_extractor,
+                    &self.db_client,
+                    &self.user_name,
+                    &self.scoring,
+                    task.id,
+                    &content,
+                )
+                .await?;
+            }
+            TaskKind::MemoryMantainance => {
+                memory_mantainance(&self.db_client, &self.scoring).await?;
             }
+            _ => {}
         }
-        tracing::info!("Memory worker terminated");
-    });
-    Ok(handler)
+        Ok(WorkerState::Busy)
+    }
 }
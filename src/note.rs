@@ -0,0 +1,15 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! Domain type for the notes subsystem (`tools::notes`) — freeform, optionally tagged scratch
+//! entries the model can later search (`notes_search`) or promote into durable
+//! `knowledge_graph::Entity` records (`notes_promote`).
+
+#[derive(Debug, Clone, Default)]
+pub struct Note {
+    pub id: i64,
+    pub content: String,
+    pub tags: Vec<String>,
+    /// Candidate entity names extracted by `NoteClassifier` when the note was added. Consumed
+    /// by `notes_promote` as the default set of entities to create/attach an observation to.
+    pub mentions: Vec<String>,
+}
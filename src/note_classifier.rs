@@ -0,0 +1,30 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! Pluggable classification step run over new notes (`tools::notes::AddNoteTool`) when
+//! `Config::notes.classify` is enabled. Assigns tags (e.g. task/person/decision/fact) and
+//! extracts candidate entity mentions, so notes can be filtered by tag (`notes_search`) and
+//! later promoted into the knowledge graph (`notes_promote`).
+
+use async_trait::async_trait;
+
+#[derive(Debug, Clone, Default)]
+pub struct ClassifiedNote {
+    pub tags: Vec<String>,
+    pub entity_mentions: Vec<String>,
+}
+
+#[async_trait]
+pub trait NoteClassifier: Send + Sync {
+    async fn classify(&self, content: &str) -> ClassifiedNote;
+}
+
+/// The classifier used when `Config::notes.classify` is disabled — assigns no tags and
+/// extracts no entity mentions, so notes behave exactly as before the feature existed.
+pub struct NoOpNoteClassifier;
+
+#[async_trait]
+impl NoteClassifier for NoOpNoteClassifier {
+    async fn classify(&self, _content: &str) -> ClassifiedNote {
+        ClassifiedNote::default()
+    }
+}
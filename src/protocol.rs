@@ -0,0 +1,67 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! Typed frames for the `/ws/worker` remote-runner protocol (see
+//! `communication::ws::worker_route`), distinct from `communication::types::{CommunicationEvent,
+//! OutboundEvent}` which carry chat traffic rather than task-distribution traffic. A remote
+//! worker process connects, announces itself with `Hello`, then asks for work with
+//! `NewTaskPlease` and reports back with `TaskInfo`/`CommandOutput`/`Heartbeat` — the same shape
+//! `agent::utils`'s local dispatch already produces, just carried over a socket instead of an
+//! in-process channel.
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+/// Identity and capacity a remote runner reports in its `Hello`, before it's ever handed work.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostInfo {
+    pub hostname: String,
+    pub cpus: u32,
+    /// How many tasks this runner is willing to execute concurrently.
+    pub capacity: u32,
+}
+
+/// One frame of the `/ws/worker` protocol. Serde-tagged on `type` like `CommunicationEvent`/
+/// `OutboundEvent` so every protocol in this codebase dispatches on the same field name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum ClientProto {
+    /// Must be the first frame a runner sends; anything else first is a protocol violation.
+    Hello { host_info: HostInfo },
+    /// "I have a free capacity slot, give me a task if one is queued."
+    NewTaskPlease,
+    /// Reports that `task_id` (a `Task::id`) has started running on this runner.
+    TaskInfo { task_id: i64, kind: String },
+    /// One chunk of a running task's command output, mirroring
+    /// `communication::types::OutboundEvent::PartialMessage`'s incremental-streaming shape.
+    CommandOutput { task_id: i64, chunk: String },
+    /// Keeps the connection's registry entry from being reaped as stale; carries no payload.
+    Heartbeat,
+}
+
+/// Serializes `value` as a length-prefixed JSON frame: a 4-byte big-endian length followed by the
+/// payload. For a raw-socket transport where message boundaries aren't otherwise delimited.
+/// `/ws/worker` itself doesn't need this — actix-ws already frames each `Message::Text`/`Binary`
+/// — so `communication::ws::worker_route` just serializes a `ClientProto` as a single JSON text
+/// frame directly; this pair exists for a future non-websocket transport over the same protocol.
+pub fn encode_framed<T: Serialize>(value: &T) -> serde_json::Result<Vec<u8>> {
+    let payload = serde_json::to_vec(value)?;
+    let mut framed = Vec::with_capacity(4 + payload.len());
+    framed.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    framed.extend_from_slice(&payload);
+    Ok(framed)
+}
+
+/// Inverse of `encode_framed`: reads the 4-byte length prefix and decodes the JSON payload that
+/// follows, returning the value and the number of bytes the frame occupied so the caller can
+/// advance past it in a longer-lived read buffer. Errors (rather than blocking) if `buf` doesn't
+/// yet contain a complete frame — callers read more and retry.
+pub fn decode_framed<T: DeserializeOwned>(buf: &[u8]) -> anyhow::Result<(T, usize)> {
+    if buf.len() < 4 {
+        anyhow::bail!("Incomplete frame header");
+    }
+    let len = u32::from_be_bytes(buf[..4].try_into().unwrap()) as usize;
+    if buf.len() < 4 + len {
+        anyhow::bail!("Incomplete frame body");
+    }
+    let value = serde_json::from_slice(&buf[4..4 + len])?;
+    Ok((value, 4 + len))
+}
@@ -1,38 +1,253 @@
 // Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use async_trait::async_trait;
+use futures::{StreamExt, stream};
 use secrecy::ExposeSecret;
 
 use crate::{
-    config::{Config, ProviderKind},
-    types::{Message, streaming::StreamingCompletionResponse},
+    config::{self, Config, ProviderKind, ProviderProfile},
+    error::AgentError,
+    types::{
+        AssistantContent, Message, ToolCall, ToolResultContent,
+        streaming::{RawStreamingChoice, ResponseUsage, StreamingCompletionResponse},
+    },
 };
 
+mod anthropic;
 mod openrouter;
+mod recorded;
+mod sse;
+
+/// Runs a single tool call on behalf of `ProviderClient::send_messages_with_tools` and returns
+/// its result content. Implemented by callers against whatever tool registry they have on hand
+/// (e.g. `agent::utils::dispatch_tool_calls` over a `ToolMap`), so this trait stays decoupled
+/// from `tools::ToolMap`.
+#[async_trait]
+pub trait ToolExecutor: Send + Sync {
+    async fn execute(&self, tool_call: &ToolCall) -> Vec<ToolResultContent>;
+}
 
 #[async_trait]
 pub trait ProviderClient: Send + Sync {
-    /// Sends messages to the provider and returns a streaming response.
+    /// Sends messages to the provider and returns a streaming response. Fails with `AgentError`
+    /// rather than `anyhow::Error` so callers (in particular `ProviderRouter` and the scheduler)
+    /// can branch on the failure class instead of pattern-matching error text.
     /// The system prompt and context are used to provide additional information to the provider.
     async fn send_messages(
         &self,
         system_prompt: &str,
         context: &str,
         messages: &[Message],
-    ) -> Result<StreamingCompletionResponse>;
+    ) -> Result<StreamingCompletionResponse, AgentError>;
+
+    /// Agentic multi-step driver built on `send_messages`: streams a completion, runs any tool
+    /// calls it contains through `executor`, appends the assistant tool-call message and the
+    /// `role: "tool"` result messages to `messages`, and resends — until a step returns no tool
+    /// calls or `max_steps` is reached. Mirrors aichat's multi-step function calling.
+    ///
+    /// Every step, the last one included, is read to completion before the next request is built
+    /// or the loop ends, and its content is replayed into the returned stream rather than
+    /// streamed live. The caller sees one continuous `StreamingCompletionResponse` spanning every step, with
+    /// `response` holding usage summed across all of them.
+    async fn send_messages_with_tools(
+        &self,
+        system_prompt: &str,
+        context: &str,
+        messages: &mut Vec<Message>,
+        executor: &dyn ToolExecutor,
+        max_steps: usize,
+    ) -> Result<StreamingCompletionResponse, AgentError> {
+        let mut buffered: Vec<Result<RawStreamingChoice, AgentError>> = Vec::new();
+        let mut final_usage: Option<ResponseUsage> = None;
+
+        for _ in 0..max_steps {
+            let mut resp = self.send_messages(system_prompt, context, messages).await?;
+
+            let mut result_content = String::new();
+            let mut pending_tool_calls: Vec<ToolCall> = Vec::new();
+
+            while let Some(item) = resp.next().await {
+                match item {
+                    Ok(AssistantContent::Text(text)) => {
+                        buffered.push(Ok(RawStreamingChoice::Message(text.text.clone())));
+                        result_content.push_str(&text.text);
+                    }
+                    Ok(AssistantContent::ToolCall(tool_call)) => {
+                        buffered.push(Ok(RawStreamingChoice::ToolCall {
+                            id: tool_call.id.clone(),
+                            name: tool_call.function.name.clone(),
+                            arguments: tool_call.function.arguments.clone(),
+                        }));
+                        pending_tool_calls.push(tool_call);
+                    }
+                    Ok(AssistantContent::Reasoning(reasoning)) => {
+                        buffered.push(Ok(RawStreamingChoice::Reasoning(reasoning.reasoning)));
+                    }
+                    Err(e) => buffered.push(Err(e)),
+                }
+            }
+
+            if let Some(usage) = resp.response.take() {
+                final_usage = Some(match final_usage.take() {
+                    Some(acc) => ResponseUsage {
+                        prompt_tokens: acc.prompt_tokens + usage.prompt_tokens,
+                        completion_tokens: acc.completion_tokens + usage.completion_tokens,
+                        total_tokens: acc.total_tokens + usage.total_tokens,
+                        cached_tokens: acc.cached_tokens + usage.cached_tokens,
+                        cost: acc.cost + usage.cost,
+                    },
+                    None => usage,
+                });
+            }
+
+            if !result_content.is_empty() {
+                messages.push(Message::assistant(&result_content));
+            }
+
+            if pending_tool_calls.is_empty() {
+                break;
+            }
+
+            for tool_call in &pending_tool_calls {
+                messages.push(Message::tool_call(tool_call.clone()));
+            }
+            for tool_call in pending_tool_calls {
+                let result = executor.execute(&tool_call).await;
+                messages.push(Message::tool_result(&tool_call.id, result));
+            }
+        }
+
+        if let Some(usage) = final_usage {
+            buffered.push(Ok(RawStreamingChoice::FinalResponse(usage)));
+        }
+
+        Ok(StreamingCompletionResponse::new(Box::pin(
+            stream::iter(buffered),
+        )))
+    }
 }
 
-pub fn create_provider_client(
-    config: &Config,
+fn create_provider_client(
+    profile: &ProviderProfile,
     tools: Vec<serde_json::Value>,
 ) -> Result<Box<dyn ProviderClient>> {
-    match config.provider {
+    match profile.provider {
         ProviderKind::OpenRouter => Ok(Box::new(openrouter::Client::new(
-            config.provider_api_key.as_ref().unwrap().expose_secret(),
-            &config.model,
+            profile
+                .api_key
+                .as_ref()
+                .ok_or_else(|| anyhow!("Provider profile has no api key"))?
+                .expose_secret(),
+            &profile.model,
             tools,
         )?)),
-        _ => Err(anyhow::anyhow!("Unsupported provider")),
+        ProviderKind::Anthropic => Ok(Box::new(anthropic::Client::new(
+            profile
+                .api_key
+                .as_ref()
+                .ok_or_else(|| anyhow!("Provider profile has no api key"))?
+                .expose_secret(),
+            &profile.model,
+            tools,
+        )?)),
+        ProviderKind::Recorded => Ok(Box::new(recorded::RecordedClient::new(&profile.model)?)),
+        _ => Err(anyhow!("Unsupported provider")),
+    }
+}
+
+/// Resolves the ordered `(name, profile)` chain to try for a task preferring `preferred`: that
+/// profile first (if configured), then `Config::provider_fallback` with it deduplicated out. With
+/// no `provider_profiles` configured at all, falls back to a single implicit profile built from the
+/// top-level `provider`/`model`/`provider_api_key` fields, so a single-provider config keeps working
+/// unchanged.
+fn resolve_chain(config: &Config, preferred: Option<&str>) -> Vec<(String, ProviderProfile)> {
+    if config.provider_profiles.is_empty() {
+        return vec![(
+            "default".to_string(),
+            ProviderProfile {
+                provider: config.provider.clone(),
+                model: config.model.clone(),
+                api_key: config.provider_api_key.clone(),
+            },
+        )];
+    }
+
+    let mut names = Vec::new();
+    if let Some(preferred) = preferred {
+        names.push(preferred.to_string());
+    }
+    for name in &config.provider_fallback {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+
+    names
+        .into_iter()
+        .filter_map(|name| {
+            config
+                .provider_profiles
+                .get(&name)
+                .cloned()
+                .map(|profile| (name, profile))
+        })
+        .collect()
+}
+
+/// A `ProviderClient` backed by an ordered chain of named profiles: tries the preferred one first
+/// and advances to the next on a retryable error (see `is_retryable`), logging which profile
+/// ultimately served the request. `Agent::run` builds one per `TaskKind`, since the preferred
+/// profile — and therefore the chain — can differ per kind.
+pub struct ProviderRouter {
+    chain: Vec<(String, Box<dyn ProviderClient>)>,
+}
+
+impl ProviderRouter {
+    /// Builds the chain for `task_kind` (the config-wide fallback chain, if it names no preferred
+    /// profile or isn't given), sharing `tools` across every profile's client.
+    pub fn new(
+        config: &Config,
+        task_kind: Option<&config::TaskKind>,
+        tools: Vec<serde_json::Value>,
+    ) -> Result<Self> {
+        let preferred = task_kind
+            .and_then(|kind| config.tasks.get(kind))
+            .and_then(|task_config| task_config.provider_profile.as_deref());
+        let profiles = resolve_chain(config, preferred);
+        if profiles.is_empty() {
+            anyhow::bail!("No provider profile resolved for task kind {task_kind:?}");
+        }
+        let chain = profiles
+            .into_iter()
+            .map(|(name, profile)| Ok((name, create_provider_client(&profile, tools.clone())?)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { chain })
+    }
+}
+
+#[async_trait]
+impl ProviderClient for ProviderRouter {
+    async fn send_messages(
+        &self,
+        system_prompt: &str,
+        context: &str,
+        messages: &[Message],
+    ) -> Result<StreamingCompletionResponse, AgentError> {
+        let mut last_error = None;
+        for (name, client) in &self.chain {
+            match client.send_messages(system_prompt, context, messages).await {
+                Ok(response) => {
+                    tracing::debug!(profile = %name, "Request served by provider");
+                    return Ok(response);
+                }
+                Err(err) if err.is_retryable() => {
+                    tracing::warn!(profile = %name, error = %err, "Provider failed, falling back to next profile");
+                    last_error = Some(err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| AgentError::Config("No provider profile available".to_string())))
     }
 }
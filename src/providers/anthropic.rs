@@ -0,0 +1,398 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+use std::{collections::HashMap, pin::Pin};
+
+use anyhow::Result;
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    error::AgentError,
+    providers::{ProviderClient, sse},
+    types::{
+        AssistantContent, ContentFormat, Image, ImageMediaType, Message, Text, ToolResultContent,
+        UserContent,
+        streaming::{RawStreamingChoice, ResponseUsage, StreamingCompletionResponse},
+    },
+};
+
+const ANTHROPIC_API_BASE_URL: &str = "https://api.anthropic.com/v1";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Claude's Messages API requires `max_tokens`; OpenRouter/OpenAI treat it as optional. This is a
+/// generous default for a provider profile that doesn't need to tune it further.
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+#[derive(Clone)]
+pub struct Client {
+    base_url: String,
+    http_client: reqwest::Client,
+    model: String,
+}
+
+impl Client {
+    /// Create a new Anthropic client with the given API key and model (e.g. `claude-3-5-sonnet-20241022`).
+    pub fn new(api_key: &str, model: &str) -> Result<Self> {
+        Ok(Self {
+            base_url: ANTHROPIC_API_BASE_URL.to_string(),
+            model: model.to_string(),
+            http_client: reqwest::Client::builder()
+                .default_headers({
+                    let mut headers = reqwest::header::HeaderMap::new();
+                    headers.insert("x-api-key", api_key.parse()?);
+                    headers.insert("anthropic-version", ANTHROPIC_VERSION.parse().unwrap());
+                    headers
+                })
+                .build()?,
+        })
+    }
+
+    pub(crate) fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}/{}", self.base_url, path).replace("//", "/");
+        self.http_client.post(url)
+    }
+
+    async fn prepare_request(
+        &self,
+        system_prompt: &str,
+        context: &str,
+        messages: &[Message],
+        tools: &[serde_json::Value],
+    ) -> Result<serde_json::Value> {
+        let mut full_history = Vec::with_capacity(messages.len());
+
+        for (idx, message) in messages.iter().enumerate() {
+            match message {
+                Message::User { content } => {
+                    let content = if idx == 0 {
+                        &content
+                            .clone()
+                            .into_iter()
+                            .chain(std::iter::once(UserContent::Text(Text {
+                                text: context.to_string(),
+                            })))
+                            .collect()
+                    } else {
+                        content
+                    };
+                    let blocks = content
+                        .iter()
+                        .map(user_content_to_block)
+                        .collect::<Result<Vec<_>>>()?;
+                    full_history.push(json!({ "role": "user", "content": blocks }));
+                }
+                Message::Assistant { content } => {
+                    let blocks = content
+                        .iter()
+                        .filter_map(|c| match c {
+                            AssistantContent::Text(text) => {
+                                Some(json!({ "type": "text", "text": text.text }))
+                            }
+                            AssistantContent::ToolCall(tool_call) => Some(json!({
+                                "type": "tool_use",
+                                "id": tool_call.id,
+                                "name": tool_call.function.name,
+                                "input": tool_call.function.arguments,
+                            })),
+                            // Reasoning is a live "thinking" signal surfaced from the stream (see
+                            // `types::streaming`), never persisted into message history.
+                            AssistantContent::Reasoning(_) => None,
+                        })
+                        .collect::<Vec<_>>();
+                    full_history.push(json!({ "role": "assistant", "content": blocks }));
+                }
+            }
+        }
+
+        let mut request = json!({
+            "model": self.model,
+            "system": system_prompt,
+            "messages": full_history,
+            "max_tokens": DEFAULT_MAX_TOKENS,
+            "stream": true,
+        });
+
+        if !tools.is_empty() {
+            let tools = tools.iter().map(tool_to_anthropic).collect::<Vec<_>>();
+            request["tools"] = serde_json::Value::Array(tools);
+        }
+
+        Ok(request)
+    }
+
+    async fn send_streaming_request(
+        &self,
+        request_builder: reqwest::RequestBuilder,
+    ) -> Result<StreamingCompletionResponse, AgentError> {
+        let response = request_builder
+            .send()
+            .await
+            .map_err(|e| AgentError::Transport(e.to_string()))?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response
+                .text()
+                .await
+                .unwrap_or_else(|e| format!("<failed to read response body: {e}>"));
+            return Err(if status == 429 {
+                AgentError::RateLimited { retry_after: None }
+            } else {
+                AgentError::Provider { status, message: body }
+            });
+        }
+        let response_stream = response.bytes_stream();
+        let line_stream = sse::sse_lines_stream(response_stream).await;
+        let mut events = sse::sse_events_stream(line_stream).await;
+
+        // Handle Anthropic's content-block SSE event model.
+        let stream = Box::pin(stream! {
+            let mut tool_calls: HashMap<usize, ToolUseBuffer> = HashMap::new();
+            let mut final_usage = ResponseUsage::default();
+
+            while let Some(event_result) = events.next().await {
+                let event = match event_result {
+                    Ok(e) => e,
+                    Err(e) => {
+                        yield Err(AgentError::Transport(e.to_string()));
+                        break;
+                    }
+                };
+
+                // An event with an empty `data` body has nothing to parse; skip it rather than
+                // failing to parse it. (`ping` keepalives carry a body and match `Ping` below.)
+                if event.data.is_empty() {
+                    continue;
+                }
+
+                let data = match serde_json::from_str::<AnthropicStreamEvent>(&event.data) {
+                    Ok(data) => data,
+                    Err(_) => continue,
+                };
+
+                match data {
+                    AnthropicStreamEvent::MessageStart { message } => {
+                        final_usage.prompt_tokens = message.usage.input_tokens;
+                        final_usage.cached_tokens = message.usage.cache_read_input_tokens.unwrap_or_default();
+                    }
+                    AnthropicStreamEvent::ContentBlockStart { index, content_block } => {
+                        if let AnthropicContentBlock::ToolUse { id, name } = content_block {
+                            yield Ok(RawStreamingChoice::ToolCallDelta {
+                                index,
+                                id: Some(id.clone()),
+                                name: Some(name.clone()),
+                                arguments_chunk: String::new(),
+                            });
+                            tool_calls.insert(index, ToolUseBuffer { id, name, arguments: String::new() });
+                        }
+                    }
+                    AnthropicStreamEvent::ContentBlockDelta { index, delta } => match delta {
+                        AnthropicDelta::TextDelta { text } => yield Ok(RawStreamingChoice::Message(text)),
+                        AnthropicDelta::ThinkingDelta { thinking } => yield Ok(RawStreamingChoice::Reasoning(thinking)),
+                        AnthropicDelta::InputJsonDelta { partial_json } => {
+                            if let Some(buf) = tool_calls.get_mut(&index) {
+                                buf.arguments.push_str(&partial_json);
+                            }
+                            yield Ok(RawStreamingChoice::ToolCallDelta {
+                                index,
+                                id: None,
+                                name: None,
+                                arguments_chunk: partial_json,
+                            });
+                        }
+                        AnthropicDelta::SignatureDelta { .. } => {}
+                    },
+                    AnthropicStreamEvent::ContentBlockStop { index } => {
+                        if let Some(buf) = tool_calls.remove(&index) {
+                            let arguments = if buf.arguments.is_empty() {
+                                serde_json::Value::Object(Default::default())
+                            } else {
+                                match serde_json::from_str(&buf.arguments) {
+                                    Ok(parsed) => parsed,
+                                    Err(_) => {
+                                        yield Err(AgentError::Parse(format!(
+                                            "Tool call '{}' is invalid: arguments must be valid JSON",
+                                            buf.name
+                                        )));
+                                        continue;
+                                    }
+                                }
+                            };
+                            yield Ok(RawStreamingChoice::ToolCall {
+                                id: buf.id,
+                                name: buf.name,
+                                arguments,
+                            });
+                        }
+                    }
+                    AnthropicStreamEvent::MessageDelta { usage, .. } => {
+                        if let Some(usage) = usage {
+                            final_usage.completion_tokens = usage.output_tokens;
+                        }
+                    }
+                    AnthropicStreamEvent::MessageStop => break,
+                    AnthropicStreamEvent::Ping => {}
+                    AnthropicStreamEvent::Error { error } => {
+                        yield Err(AgentError::Provider { status: 0, message: error.message });
+                        break;
+                    }
+                }
+            }
+
+            final_usage.total_tokens = final_usage.prompt_tokens + final_usage.completion_tokens;
+            yield Ok(RawStreamingChoice::FinalResponse(final_usage));
+        });
+
+        Ok(StreamingCompletionResponse::new(stream))
+    }
+}
+
+/// Translates a tool description from the OpenAI `{"type":"function","function":{"name",
+/// "description","parameters"}}` shape every `tools::*` module builds into Claude's flatter
+/// `{name, description, input_schema}` shape.
+fn tool_to_anthropic(tool: &serde_json::Value) -> serde_json::Value {
+    let function = &tool["function"];
+    json!({
+        "name": function["name"],
+        "description": function["description"],
+        "input_schema": function["parameters"],
+    })
+}
+
+fn user_content_to_block(content: &UserContent) -> Result<serde_json::Value> {
+    match content {
+        UserContent::Text(text) => Ok(json!({ "type": "text", "text": text.text })),
+        UserContent::Image(image) => Ok(image_to_block(image)),
+        UserContent::ToolResult(tool_result) => {
+            let blocks = tool_result
+                .content
+                .iter()
+                .filter_map(|c| match c {
+                    ToolResultContent::Text(text) => Some(json!({ "type": "text", "text": text.text })),
+                    ToolResultContent::Resource(resource) => {
+                        Some(json!({ "type": "text", "text": resource.description }))
+                    }
+                    ToolResultContent::Image(image) => Some(image_to_block(image)),
+                    // Claude's tool_result content blocks don't support audio/video.
+                    ToolResultContent::Video(_) | ToolResultContent::Audio(_) => None,
+                })
+                .collect::<Vec<_>>();
+            Ok(json!({
+                "type": "tool_result",
+                "tool_use_id": tool_result.id,
+                "content": blocks,
+            }))
+        }
+        UserContent::Audio(_) => anyhow::bail!("Audio is not supported"),
+        UserContent::Document(_) => anyhow::bail!("Document is not supported"),
+        UserContent::Video(_) => anyhow::bail!("Video is not supported"),
+    }
+}
+
+fn image_to_block(image: &Image) -> serde_json::Value {
+    if let Some(ContentFormat::String) = image.format {
+        json!({
+            "type": "image",
+            "source": {
+                "type": "url",
+                "url": image.data,
+            }
+        })
+    } else {
+        json!({
+            "type": "image",
+            "source": {
+                "type": "base64",
+                "media_type": image.media_type.clone().unwrap_or(ImageMediaType::PNG).to_mime_type(),
+                "data": image.data,
+            }
+        })
+    }
+}
+
+/// Accumulates one `tool_use` content block's `input` across however many `input_json_delta`
+/// fragments it arrives in, mirroring `openrouter::ToolCallBuffer`. Unlike OpenAI-shaped streams,
+/// Claude always sends an `id`/`name` up front in `content_block_start`, so there's no need for
+/// `openrouter`'s id-synthesis fallback.
+struct ToolUseBuffer {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicStreamEvent {
+    MessageStart { message: AnthropicMessageStart },
+    ContentBlockStart { index: usize, content_block: AnthropicContentBlock },
+    ContentBlockDelta { index: usize, delta: AnthropicDelta },
+    ContentBlockStop { index: usize },
+    MessageDelta {
+        #[serde(default)]
+        delta: serde_json::Value,
+        usage: Option<AnthropicUsage>,
+    },
+    MessageStop,
+    Ping,
+    Error { error: AnthropicError },
+}
+
+#[derive(Deserialize, Debug)]
+struct AnthropicMessageStart {
+    usage: AnthropicUsage,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicContentBlock {
+    Text {
+        #[serde(default)]
+        text: String,
+    },
+    ToolUse { id: String, name: String },
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicDelta {
+    TextDelta { text: String },
+    InputJsonDelta { partial_json: String },
+    ThinkingDelta { thinking: String },
+    SignatureDelta { signature: String },
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct AnthropicUsage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+    #[serde(default)]
+    cache_read_input_tokens: Option<u32>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AnthropicError {
+    message: String,
+}
+
+#[async_trait]
+impl ProviderClient for Client {
+    async fn send_messages(
+        &self,
+        system_prompt: &str,
+        context: &str,
+        messages: &[Message],
+        tools: &[serde_json::Value],
+    ) -> Result<StreamingCompletionResponse, AgentError> {
+        let request = self
+            .prepare_request(system_prompt, context, messages, tools)
+            .await
+            .map_err(|e| AgentError::Parse(e.to_string()))?;
+        let builder = self.post("/messages").json(&request);
+        self.send_streaming_request(builder).await
+    }
+}
@@ -1,6 +1,10 @@
 // Copyright В© 2025 Huly Labs. Use of this source code is governed by the MIT license.
 
-use std::{collections::HashMap, fmt::Write, pin::Pin};
+use std::{
+    collections::{HashMap, hash_map::DefaultHasher},
+    hash::{Hash, Hasher},
+    pin::Pin,
+};
 
 use anyhow::{Result, anyhow};
 use async_stream::stream;
@@ -11,11 +15,15 @@ use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 
 use crate::{
-    providers::ProviderClient,
+    error::AgentError,
+    providers::{
+        ProviderClient,
+        sse::{self, SseEvent},
+    },
     types::{
-        AssistantContent, ContentFormat, ImageMediaType, Message, Text, ToolCall, ToolFunction,
+        AssistantContent, AudioMediaType, ContentFormat, ImageMediaType, Message, Text, ToolCall,
         ToolResultContent, UserContent,
-        streaming::{RawStreamingChoice, StreamingCompletionResponse},
+        streaming::{RawStreamingChoice, ResponseUsage, StreamingCompletionResponse},
     },
 };
 
@@ -31,7 +39,26 @@ pub struct OpenRouterStreamingCompletionResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub system_fingerprint: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub usage: Option<serde_json::Value>,
+    pub usage: Option<OpenRouterUsage>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct OpenRouterUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+    #[serde(default)]
+    pub prompt_tokens_details: Option<OpenRouterPromptTokensDetails>,
+    /// Dollar cost OpenRouter billed for this request, present when the request set
+    /// `"usage": {"include": true}`.
+    #[serde(default)]
+    pub cost: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct OpenRouterPromptTokensDetails {
+    #[serde(default)]
+    pub cached_tokens: u32,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -88,22 +115,171 @@ pub struct DeltaResponse {
     pub role: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<String>,
+    /// Structured reasoning blocks some models emit alongside/instead of plain `reasoning` text
+    /// (e.g. redacted or provider-specific reasoning formats). Not yet surfaced as its own
+    /// `RawStreamingChoice`; kept here so a future consumer doesn't need another round of
+    /// `DeltaResponse` plumbing to reach it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_details: Option<Value>,
     #[serde(default)]
     pub tool_calls: Vec<OpenRouterToolCall>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub native_finish_reason: Option<String>,
 }
 
+/// Per-call overrides layered onto `prepare_request`'s default single-model, temperature-0 request
+/// body. Every field is merged into the JSON body only when set, so `RequestOptions::default()`
+/// (or omitting it entirely) reproduces the existing fixed-model/temperature behavior exactly.
+#[derive(Clone, Debug, Default)]
+pub struct RequestOptions {
+    /// Additional models to fall back to, tried in order, if the primary model (`Client::model`)
+    /// errors or is overloaded. Maps to OpenRouter's `models` field, a sibling of `model`.
+    pub fallback_models: Vec<String>,
+    /// OpenRouter provider routing preferences (allowed/ignored providers, fallback policy, data
+    /// collection policy, sort order). See OpenRouter's provider-routing docs for field semantics.
+    pub provider: Option<ProviderPreferences>,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub max_tokens: Option<u32>,
+    /// OpenRouter's `reasoning` config, controlling how much (if any) chain-of-thought a reasoning
+    /// model emits as `RawStreamingChoice::Reasoning` chunks.
+    pub reasoning: Option<ReasoningOptions>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ReasoningOptions {
+    pub effort: Option<ReasoningEffort>,
+    /// Explicit reasoning token budget, as an alternative to `effort` (providers generally accept
+    /// one or the other).
+    pub max_tokens: Option<u32>,
+    /// When `true`, the model still reasons internally but `reasoning`/`reasoning_details` chunks
+    /// are omitted from the response — useful for callers that only want the final answer.
+    pub exclude: Option<bool>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum ReasoningEffort {
+    Low,
+    Medium,
+    High,
+}
+
+impl ReasoningEffort {
+    fn as_str(self) -> &'static str {
+        match self {
+            ReasoningEffort::Low => "low",
+            ReasoningEffort::Medium => "medium",
+            ReasoningEffort::High => "high",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ProviderPreferences {
+    /// Preferred provider order (an allow-list in priority order); providers not listed are still
+    /// eligible unless `allow_fallbacks` is `false`.
+    pub order: Option<Vec<String>>,
+    /// Providers to never route to.
+    pub ignore: Option<Vec<String>>,
+    pub allow_fallbacks: Option<bool>,
+    pub data_collection: Option<DataCollectionPolicy>,
+    pub sort: Option<ProviderSort>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum DataCollectionPolicy {
+    Allow,
+    Deny,
+}
+
+impl DataCollectionPolicy {
+    fn as_str(self) -> &'static str {
+        match self {
+            DataCollectionPolicy::Allow => "allow",
+            DataCollectionPolicy::Deny => "deny",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum ProviderSort {
+    Price,
+    Throughput,
+    Latency,
+}
+
+impl ProviderSort {
+    fn as_str(self) -> &'static str {
+        match self {
+            ProviderSort::Price => "price",
+            ProviderSort::Throughput => "throughput",
+            ProviderSort::Latency => "latency",
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Client {
     base_url: String,
     http_client: reqwest::Client,
     model: String,
+    cache_strategy: CacheStrategy,
+    sse_max_reconnects: usize,
 }
 
 /// Anthropic allows only 4 blocks marked for caching
 const MAX_CACHE_BLOCKS: i8 = 4;
 
+/// Default bound on `send_streaming_request`'s automatic SSE reconnects (see
+/// `Client::with_sse_max_reconnects`); a handful of attempts rides out a brief network blip
+/// without masking a persistently failing endpoint forever.
+const DEFAULT_SSE_MAX_RECONNECTS: usize = 3;
+
+/// Initial reconnect backoff used before the first event on a connection has set one via the SSE
+/// `retry:` field.
+const DEFAULT_SSE_RETRY_DELAY_MS: u64 = 1000;
+
+/// Which prompt-caching `cache_control: {type: ephemeral}` breakpoints (if any) `prepare_request`
+/// marks in the outgoing request body. `Client::new` picks `Anthropic` automatically for
+/// `anthropic/`-prefixed models and `None` otherwise, matching the behavior before this was
+/// configurable; `Client::with_cache_strategy` overrides that guess, e.g. for a non-Anthropic model
+/// proxied through OpenRouter that happens to honor the same breakpoint marker.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CacheStrategy {
+    /// No `cache_control` markers are added.
+    #[default]
+    None,
+    /// Anthropic's breakpoint marker, capped at `max_blocks` (Anthropic currently allows 4 per
+    /// request — see `MAX_CACHE_BLOCKS`).
+    Anthropic { max_blocks: i8 },
+    /// The same breakpoint marker and placement logic as `Anthropic`, for a model that isn't
+    /// Anthropic but understands the identical annotation.
+    Breakpoints { max_blocks: i8 },
+}
+
+impl CacheStrategy {
+    /// The Anthropic strategy `Client::new` selects by default for `anthropic/`-prefixed models.
+    fn default_for_model(model: &str) -> Self {
+        if model.starts_with("anthropic/") {
+            CacheStrategy::Anthropic { max_blocks: MAX_CACHE_BLOCKS }
+        } else {
+            CacheStrategy::None
+        }
+    }
+
+    /// `None` if caching is off, otherwise the strategy's block cap.
+    fn max_blocks(self) -> Option<i8> {
+        match self {
+            CacheStrategy::None => None,
+            CacheStrategy::Anthropic { max_blocks } | CacheStrategy::Breakpoints { max_blocks } => {
+                Some(max_blocks)
+            }
+        }
+    }
+}
+
 fn user_text_to_json(content: &UserContent) -> serde_json::Value {
     match content {
         UserContent::Text(text) => json!({
@@ -139,6 +315,7 @@ fn user_content_to_json(content: &UserContent) -> Result<serde_json::Value> {
         }
         UserContent::Audio(_) => anyhow::bail!("Audio is not supported"),
         UserContent::Document(_) => anyhow::bail!("Document is not supported"),
+        UserContent::Video(_) => anyhow::bail!("Video is not supported"),
         UserContent::ToolResult(_) => unreachable!(),
     }
 }
@@ -156,7 +333,8 @@ fn tool_content_to_json(content: Vec<&UserContent>) -> Result<serde_json::Value>
                     .iter()
                     .map(|c| match c {
                         ToolResultContent::Text(text) => text.text.clone(),
-                        // ignore image content
+                        ToolResultContent::Resource(resource) => resource.description.clone(),
+                        // image/video/audio content is forwarded as separate follow-up messages below
                         _ => "".to_string(),
                     })
                     .collect::<Vec<_>>()
@@ -172,11 +350,74 @@ fn tool_content_to_json(content: Vec<&UserContent>) -> Result<serde_json::Value>
     }))
 }
 
+/// Accumulates one streamed tool call's fields across however many `DeltaResponse` fragments it
+/// arrives in. `arguments` is kept as a raw string buffer rather than attempting to parse it on
+/// every fragment, since a partial JSON object can spuriously look complete (or incomplete) mid
+/// stream; it's only parsed once `drain_tool_calls` is called, by which point the full buffer for
+/// each index has arrived.
+#[derive(Default)]
+struct ToolCallBuffer {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+/// Some OpenAI-compatible backends (local servers, some proxies) never send a tool call `id`.
+/// Synthesizes a deterministic one from the call's index and a hash of its name/arguments so it
+/// still has a stable, unique identifier to key the `role: "tool"` result message on.
+fn synthesize_tool_call_id(index: usize, buf: &ToolCallBuffer) -> String {
+    let mut hasher = DefaultHasher::new();
+    buf.name.hash(&mut hasher);
+    buf.arguments.hash(&mut hasher);
+    format!("call_{}_{:x}", index, hasher.finish())
+}
+
+/// Parses each buffered tool call's `arguments` into JSON and hands back `RawStreamingChoice`
+/// items ready to yield, clearing `tool_calls` so a later call (e.g. another `finish_reason:
+/// "tool_calls"` choice, or end of stream) doesn't re-emit them.
+fn drain_tool_calls(
+    tool_calls: &mut HashMap<usize, ToolCallBuffer>,
+) -> Vec<Result<RawStreamingChoice, AgentError>> {
+    tool_calls
+        .drain()
+        .map(|(index, buf)| {
+            let id = if buf.id.is_empty() {
+                synthesize_tool_call_id(index, &buf)
+            } else {
+                buf.id.clone()
+            };
+            let arguments = if buf.arguments.is_empty() {
+                Value::Object(Default::default())
+            } else {
+                match serde_json::from_str(&buf.arguments) {
+                    Ok(parsed) => parsed,
+                    // A malformed-JSON argument buffer can't be silently coerced to `{}` without
+                    // dropping whatever parameters the model did emit, so surface it as a stream
+                    // error instead of producing a tool call with silently missing arguments.
+                    Err(_) => {
+                        return Err(AgentError::Parse(format!(
+                            "Tool call '{}' is invalid: arguments must be valid JSON",
+                            buf.name
+                        )));
+                    }
+                }
+            };
+            Ok(RawStreamingChoice::ToolCall {
+                id,
+                name: buf.name,
+                arguments,
+            })
+        })
+        .collect()
+}
+
 impl Client {
     /// Create a new OpenRouter client with the given API key and base API URL.
     pub fn new(api_key: &str, model: &str) -> Result<Self> {
         Ok(Self {
             base_url: OPENROUTER_API_BASE_URL.to_string(),
+            cache_strategy: CacheStrategy::default_for_model(model),
+            sse_max_reconnects: DEFAULT_SSE_MAX_RECONNECTS,
             model: model.to_string(),
             http_client: reqwest::Client::builder()
                 .default_headers({
@@ -190,6 +431,20 @@ impl Client {
         })
     }
 
+    /// Overrides the prompt-caching strategy `new` guessed from the model name.
+    pub fn with_cache_strategy(mut self, cache_strategy: CacheStrategy) -> Self {
+        self.cache_strategy = cache_strategy;
+        self
+    }
+
+    /// Overrides how many times `send_streaming_request` will transparently reconnect (via
+    /// `Last-Event-ID`) after a mid-stream transport error before giving up and surfacing it.
+    /// Defaults to `DEFAULT_SSE_MAX_RECONNECTS`; `0` disables reconnection entirely.
+    pub fn with_sse_max_reconnects(mut self, sse_max_reconnects: usize) -> Self {
+        self.sse_max_reconnects = sse_max_reconnects;
+        self
+    }
+
     pub(crate) fn post(&self, path: &str) -> reqwest::RequestBuilder {
         let url = format!("{}/{}", self.base_url, path).replace("//", "/");
         self.http_client.post(url)
@@ -201,8 +456,10 @@ impl Client {
         context: &str,
         messages: &[Message],
         tools: &[serde_json::Value],
+        options: Option<&RequestOptions>,
     ) -> Result<serde_json::Value> {
-        let need_cache_control = self.model.starts_with("anthropic/");
+        let max_cache_blocks = self.cache_strategy.max_blocks();
+        let need_cache_control = max_cache_blocks.is_some();
         let mut full_history = vec![if need_cache_control {
             json!({
                 "role": "system",
@@ -275,6 +532,19 @@ impl Client {
                                                 }]
                                             }));
                                             }
+                                        } else if let ToolResultContent::Audio(audio) =
+                                            tool_result_content
+                                        {
+                                            full_history.push(json!({
+                                                "role": "user",
+                                                "content": [{
+                                                    "type": "input_audio",
+                                                    "input_audio": {
+                                                        "data": audio.data,
+                                                        "format": audio.media_type.unwrap_or(AudioMediaType::MP3).to_file_ext(),
+                                                    }
+                                                }]
+                                            }));
                                         }
                                     }
                                 }
@@ -324,19 +594,22 @@ impl Client {
                                     }]
                                 }));
                             }
+                            // Reasoning is a live "thinking" signal surfaced from the stream
+                            // (see `types::streaming`), never persisted into message history.
+                            AssistantContent::Reasoning(_) => {}
                         }
                     }
                 }
             };
         }
 
-        if need_cache_control {
+        if let Some(max_cache_blocks) = max_cache_blocks {
             let len = full_history.len();
             full_history = full_history
                 .iter_mut()
                 .enumerate()
                 .map(|(idx, m)| {
-                    if idx > 0 && idx < len - 1 && cache_blocks < MAX_CACHE_BLOCKS {
+                    if idx > 0 && idx < len - 1 && cache_blocks < max_cache_blocks {
                         let mut message = m.clone();
                         let message = message.as_object_mut().unwrap();
                         if message.contains_key("content") {
@@ -356,7 +629,7 @@ impl Client {
                                     message.get_mut("content").unwrap().as_array_mut().unwrap();
                                 for content in content.iter_mut() {
                                     let content = content.as_object_mut().unwrap();
-                                    if cache_blocks < MAX_CACHE_BLOCKS
+                                    if cache_blocks < max_cache_blocks
                                         && ((content.contains_key("text")
                                             && !content["text"]
                                                 .as_str()
@@ -392,7 +665,7 @@ impl Client {
             }
         });
         if !tools.is_empty() {
-            let tools = if self.model.starts_with("anthropic/") {
+            let tools = if need_cache_control {
                 tools
                     .iter()
                     .enumerate()
@@ -412,170 +685,157 @@ impl Client {
             };
             request["tools"] = serde_json::Value::Array(tools);
         }
-        Ok(request)
-    }
 
-    async fn sse_lines_stream<E: std::error::Error + Send + Sync + 'static>(
-        mut stream: impl Stream<Item = std::result::Result<Bytes, E>> + Unpin + Send + 'static,
-    ) -> Pin<Box<dyn Stream<Item = Result<String>> + Send>> {
-        const CR: u8 = 0x0D;
-        const LF: u8 = 0x0A;
-
-        Box::pin(stream! {
-            let mut chunks: Vec<Bytes> = Vec::new();
-            let mut chunks_length = 0;
-            let mut has_end_carriage = false;
-            while let Some(chunk_result) = stream.next().await {
-                let chunk = match chunk_result {
-                    Ok(c) => c,
-                    Err(e) => {
-                        yield Err(anyhow!(e));
-                        break;
-                    }
-                };
-                if chunk.is_empty() {
-                    continue;
+        if let Some(options) = options {
+            if !options.fallback_models.is_empty() {
+                request["models"] = json!(options.fallback_models);
+            }
+            if let Some(provider) = &options.provider {
+                let mut provider_json = serde_json::Map::new();
+                if let Some(order) = &provider.order {
+                    provider_json.insert("order".to_string(), json!(order));
                 }
-                let mut chunk_start = 0;
-                for (idx, &b) in chunk.iter().enumerate() {
-                    if has_end_carriage {
-                        has_end_carriage = false;
-                        if b == LF {
-                            chunk_start += 1;
-                            continue;
-                        }
-                    }
-                    if b == CR || b == LF {
-                        has_end_carriage = b == CR;
-                        let total_line_length = chunks_length + idx - chunk_start;
-                        let mut buf = Vec::with_capacity(total_line_length);
-                        for c in chunks.drain(..) {
-                            buf.extend_from_slice(&c);
-                        }
-                        buf.extend_from_slice(&chunk[chunk_start..idx]);
-                        chunk_start = idx + 1;
-                        let line = match String::from_utf8(buf) {
-                            Ok(t) => t,
-                            Err(e) => {
-                                yield Err(anyhow!(e));
-                                break;
-                            }
-                        };
-                        yield Ok(line);
-                    }
+                if let Some(ignore) = &provider.ignore {
+                    provider_json.insert("ignore".to_string(), json!(ignore));
                 }
-                let chunk = chunk.slice(chunk_start..);
-                if !chunk.is_empty() {
-                    chunks_length += chunk.len();
-                    chunks.push(chunk);
+                if let Some(allow_fallbacks) = provider.allow_fallbacks {
+                    provider_json.insert("allow_fallbacks".to_string(), json!(allow_fallbacks));
                 }
-            }
-            if chunks_length > 0 {
-                let total_line_length = chunks_length;
-                let mut buf = Vec::with_capacity(total_line_length);
-                for c in chunks.drain(..) {
-                    buf.extend_from_slice(&c);
+                if let Some(data_collection) = provider.data_collection {
+                    provider_json
+                        .insert("data_collection".to_string(), json!(data_collection.as_str()));
+                }
+                if let Some(sort) = provider.sort {
+                    provider_json.insert("sort".to_string(), json!(sort.as_str()));
+                }
+                if !provider_json.is_empty() {
+                    request["provider"] = serde_json::Value::Object(provider_json);
                 }
-                match String::from_utf8(buf) {
-                    Ok(line) => {
-                        yield Ok(line);
-                    },
-                    Err(e) => {
-                        yield Err(anyhow!(e));
-                    }
-                };
             }
-        })
-    }
-
-    async fn sse_events_stream(
-        line_stream: impl Stream<Item = Result<String>> + Unpin + Send + 'static,
-    ) -> Pin<Box<dyn Stream<Item = Result<(String, String, String)>> + Send>> {
-        Box::pin(stream! {
-            let mut stream = line_stream;
-            let mut event_type = String::new();
-            let mut event_data = String::new();
-            let mut event_last_id = String::new();
-
-            while let Some(line_result) = stream.next().await {
-                let line = match line_result {
-                    Ok(c) => c,
-                    Err(e) => {
-                        yield Err(e);
-                        break;
-                    }
-                };
-                if line.is_empty() {
-                    if event_data.is_empty() {
-                        event_type.clear();
-                        continue;
-                    }
-                    let mut new_event_data = std::mem::take(&mut event_data);
-                    let mut new_event_type = std::mem::take(&mut event_type);
-                    if new_event_data.ends_with('\n') {
-                        new_event_data.truncate(new_event_data.len() - 1);
-                    }
-                    if new_event_type.is_empty() {
-                        new_event_type.push_str("message");
-                    }
-                    yield Ok((event_last_id.clone(), new_event_type, new_event_data));
+            if let Some(temperature) = options.temperature {
+                request["temperature"] = json!(temperature);
+            }
+            if let Some(top_p) = options.top_p {
+                request["top_p"] = json!(top_p);
+            }
+            if let Some(max_tokens) = options.max_tokens {
+                request["max_tokens"] = json!(max_tokens);
+            }
+            if let Some(reasoning) = &options.reasoning {
+                let mut reasoning_json = serde_json::Map::new();
+                if let Some(effort) = reasoning.effort {
+                    reasoning_json.insert("effort".to_string(), json!(effort.as_str()));
                 }
-
-                // Skip comments
-                if line.starts_with(":") {
-                    continue;
+                if let Some(max_tokens) = reasoning.max_tokens {
+                    reasoning_json.insert("max_tokens".to_string(), json!(max_tokens));
                 }
-                let (field_name, mut field_value) = line.split_once(":").unwrap_or((line.as_str(), ""));
-                if field_value.starts_with(' ') {
-                    field_value = &field_value[1..];
+                if let Some(exclude) = reasoning.exclude {
+                    reasoning_json.insert("exclude".to_string(), json!(exclude));
                 }
-                match field_name {
-                    "id" => {
-                        if !field_value.contains('\0') {
-                            event_last_id = field_value.to_string();
-                        }
-                    }
-                    "event" => {
-                        event_type = field_value.to_string();
-                    }
-                    "data" => {
-                        event_data.write_fmt(format_args!("{field_value}\n")).expect("write to string does not fail");
-                    }
-                    _ => (),
+                if !reasoning_json.is_empty() {
+                    request["reasoning"] = serde_json::Value::Object(reasoning_json);
                 }
             }
-        })
+        }
+
+        Ok(request)
     }
 
-    async fn send_streaming_request(
-        &self,
+    /// Sends `request_builder` and wraps the response body as a parsed SSE event stream. Used both
+    /// for the initial connection and, on a mid-stream transport error, for each reconnect attempt
+    /// inside `send_streaming_request`'s generator.
+    async fn connect_events_stream(
         request_builder: reqwest::RequestBuilder,
-    ) -> Result<StreamingCompletionResponse> {
-        let response = request_builder.send().await?;
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<SseEvent>> + Send>>, AgentError> {
+        let response = request_builder
+            .send()
+            .await
+            .map_err(|e| AgentError::Transport(e.to_string()))?;
 
         if !response.status().is_success() {
-            return Err(anyhow!(format!(
-                "{}: {}",
-                response.status(),
-                response.text().await?
-            )));
+            let status = response.status().as_u16();
+            let body = response
+                .text()
+                .await
+                .unwrap_or_else(|e| format!("<failed to read response body: {e}>"));
+            return Err(if status == 429 {
+                AgentError::RateLimited { retry_after: None }
+            } else {
+                AgentError::Provider { status, message: body }
+            });
         }
         let response_stream = response.bytes_stream();
-        let line_stream = Self::sse_lines_stream(response_stream).await;
-        let events_stream = Self::sse_events_stream(line_stream).await;
+        let line_stream = sse::sse_lines_stream(response_stream).await;
+        Ok(sse::sse_events_stream(line_stream).await)
+    }
+
+    async fn send_streaming_request(
+        &self,
+        request_builder: reqwest::RequestBuilder,
+    ) -> Result<StreamingCompletionResponse, AgentError> {
+        let first_attempt = request_builder.try_clone().ok_or_else(|| {
+            AgentError::Transport("request body could not be cloned for SSE reconnect support".to_string())
+        })?;
+        let events_stream = Self::connect_events_stream(first_attempt).await?;
+        let max_reconnects = self.sse_max_reconnects;
+
         // Handle OpenAI Compatible SSE chunks
         let stream = Box::pin(stream! {
             let mut stream = events_stream;
             let mut tool_calls = HashMap::new();
             let mut final_usage = None;
-            while let Some(event_result) = stream.next().await {
-                let (_, _, event_data) = match event_result {
-                    Ok(c) => c,
+            let mut last_event_id = String::new();
+            let mut retry_delay_ms = DEFAULT_SSE_RETRY_DELAY_MS;
+            let mut reconnects = 0usize;
+
+            'events: loop {
+                let Some(event_result) = stream.next().await else { break; };
+                let event = match event_result {
+                    Ok(e) => e,
                     Err(e) => {
-                        yield Err(e);
-                        break;
+                        // A transport error mid-stream (the connection dropping, a read timeout,
+                        // etc.) doesn't necessarily mean the completion is done — reconnect using
+                        // `Last-Event-ID` so long tool-calling turns survive brief network blips,
+                        // instead of failing the whole request outright.
+                        if reconnects >= max_reconnects {
+                            yield Err(AgentError::Transport(format!(
+                                "SSE stream disconnected after {reconnects} reconnect attempt(s): {e}"
+                            )));
+                            break;
+                        }
+                        reconnects += 1;
+                        tokio::time::sleep(std::time::Duration::from_millis(retry_delay_ms)).await;
+
+                        let Some(mut retry_builder) = request_builder.try_clone() else {
+                            yield Err(AgentError::Transport(
+                                "request body could not be cloned for SSE reconnect support".to_string(),
+                            ));
+                            break;
+                        };
+                        if !last_event_id.is_empty() {
+                            retry_builder = retry_builder.header("Last-Event-ID", last_event_id.clone());
+                        }
+                        match Self::connect_events_stream(retry_builder).await {
+                            Ok(new_stream) => {
+                                stream = new_stream;
+                                continue 'events;
+                            }
+                            Err(e) => {
+                                yield Err(e);
+                                break;
+                            }
+                        }
                     }
                 };
+
+                if !event.id.is_empty() {
+                    last_event_id = event.id.clone();
+                }
+                if let Some(retry_ms) = event.retry {
+                    retry_delay_ms = retry_ms;
+                }
+                let event_data = event.data;
+
                 // OpenAI (and OpenRouter) uses [DONE] as marker of last message in stream
                 if event_data == "[DONE]" {
                     break;
@@ -590,62 +850,42 @@ impl Client {
 
                 let choice = data.choices.first().expect("Should have at least one choice");
 
-                // TODO this has to handle outputs like this:
-                // [{"index": 0, "id": "call_DdmO9pD3xa9XTPNJ32zg2hcA", "function": {"arguments": "", "name": "get_weather"}, "type": "function"}]
-                // [{"index": 0, "id": null, "function": {"arguments": "{\"", "name": null}, "type": null}]
-                // [{"index": 0, "id": null, "function": {"arguments": "location", "name": null}, "type": null}]
-                // [{"index": 0, "id": null, "function": {"arguments": "\":\"", "name": null}, "type": null}]
-                // [{"index": 0, "id": null, "function": {"arguments": "Paris", "name": null}, "type": null}]
-                // [{"index": 0, "id": null, "function": {"arguments": ",", "name": null}, "type": null}]
-                // [{"index": 0, "id": null, "function": {"arguments": " France", "name": null}, "type": null}]
-                // [{"index": 0, "id": null, "function": {"arguments": "\"}", "name": null}, "type": null}]
+                // Tool call deltas can arrive fragmented across many chunks (`id`/`name` in one,
+                // `arguments` dribbled in piece by piece) and several `index`es can be present in
+                // the same delta for parallel tool calls; `tool_calls` buffers each index's raw
+                // pieces until the call is known complete (see `ToolCallBuffer`/`drain_tool_calls`).
                 if let Some(delta) = &choice.delta {
-                    if !delta.tool_calls.is_empty() {
-                        for tool_call in &delta.tool_calls {
-                            let index = tool_call.index;
-
-                            // Get or create tool call entry
-                            let existing_tool_call = tool_calls.entry(index).or_insert_with(|| ToolCall {
-                                id: String::new(),
-                                function: ToolFunction {
-                                    name: String::new(),
-                                    arguments: serde_json::Value::Null,
-                                },
-                            });
-
-                            // Update fields if present
-                            if let Some(id) = &tool_call.id {
-                                if !id.is_empty() {
-                                    existing_tool_call.id = id.clone();
-                                }
+                    for tool_call in &delta.tool_calls {
+                        let buf = tool_calls.entry(tool_call.index).or_default();
+
+                        if let Some(id) = &tool_call.id {
+                            if !id.is_empty() {
+                                buf.id = id.clone();
                             }
-                            if let Some(name) = &tool_call.function.name {
-                                if !name.is_empty() {
-                                    existing_tool_call.function.name = name.clone();
-                                }
+                        }
+                        if let Some(name) = &tool_call.function.name {
+                            if !name.is_empty() {
+                                buf.name = name.clone();
                             }
-                            if let Some(chunk) = &tool_call.function.arguments {
-                                // Convert current arguments to string if needed
-                                let current_args = match &existing_tool_call.function.arguments {
-                                    serde_json::Value::Null => String::new(),
-                                    serde_json::Value::String(s) => s.clone(),
-                                    v => v.to_string(),
-                                };
-
-                                // Concatenate the new chunk
-                                let combined = format!("{current_args}{chunk}");
-
-                                // Try to parse as JSON if it looks complete
-                                if combined.trim_start().starts_with('{') && combined.trim_end().ends_with('}') {
-                                    match serde_json::from_str(&combined) {
-                                        Ok(parsed) => existing_tool_call.function.arguments = parsed,
-                                        Err(_) => existing_tool_call.function.arguments = serde_json::Value::String(combined),
-                                    }
-                                } else {
-                                    existing_tool_call.function.arguments = serde_json::Value::String(combined);
-                                }
+                        }
+                        if let Some(chunk) = &tool_call.function.arguments {
+                            if !chunk.is_empty() {
+                                buf.arguments.push_str(chunk);
                             }
                         }
+
+                        yield Ok(RawStreamingChoice::ToolCallDelta {
+                            index: tool_call.index,
+                            id: tool_call.id.clone(),
+                            name: tool_call.function.name.clone(),
+                            arguments_chunk: tool_call.function.arguments.clone().unwrap_or_default(),
+                        });
+                    }
+
+                    if let Some(reasoning) = &delta.reasoning {
+                        if !reasoning.is_empty() {
+                            yield Ok(RawStreamingChoice::Reasoning(reasoning.clone()))
+                        }
                     }
 
                     if let Some(content) = &delta.content {
@@ -655,36 +895,44 @@ impl Client {
                     }
 
                     if let Some(usage) = data.usage {
-                        final_usage = Some(usage);
+                        final_usage = Some(ResponseUsage {
+                            prompt_tokens: usage.prompt_tokens,
+                            completion_tokens: usage.completion_tokens,
+                            total_tokens: usage.total_tokens,
+                            cached_tokens: usage
+                                .prompt_tokens_details
+                                .map(|d| d.cached_tokens)
+                                .unwrap_or_default(),
+                            cost: usage.cost.unwrap_or_default(),
+                        });
                     }
                 }
 
                 // Handle message format
                 if let Some(message) = &choice.message {
                     for tool_call in &message.tool_calls {
-                        let name = tool_call.function.name.clone();
-                        let id = tool_call.id.clone();
-                        let arguments = if let Some(args) = &tool_call.function.arguments {
-                            // Try to parse the string as JSON, fallback to string value
+                        let buf = tool_calls.entry(tool_call.index).or_default();
+                        if let Some(id) = &tool_call.id {
+                            if !id.is_empty() {
+                                buf.id = id.clone();
+                            }
+                        }
+                        if let Some(name) = &tool_call.function.name {
+                            if !name.is_empty() {
+                                buf.name = name.clone();
+                            }
+                        }
+                        if let Some(args) = &tool_call.function.arguments {
                             if !args.is_empty() {
-                                match serde_json::from_str(args) {
-                                    Ok(v) => v,
-                                    Err(_) => serde_json::Value::String(args.to_string()),
-                                }
-                            } else {
-                                serde_json::Value::Object(Default::default())
+                                buf.arguments.push_str(args);
                             }
-                        } else {
-                            serde_json::Value::Object(Default::default())
-                        };
-                        let index = tool_call.index;
-
-                        tool_calls.insert(index, ToolCall{
-                            id: id.unwrap_or_default(),
-                            function: ToolFunction {
-                                name: name.unwrap_or_default(),
-                                arguments,
-                            },
+                        }
+
+                        yield Ok(RawStreamingChoice::ToolCallDelta {
+                            index: tool_call.index,
+                            id: tool_call.id.clone(),
+                            name: tool_call.function.name.clone(),
+                            arguments_chunk: tool_call.function.arguments.clone().unwrap_or_default(),
                         });
                     }
 
@@ -692,20 +940,19 @@ impl Client {
                         yield Ok(RawStreamingChoice::Message(message.content.clone()))
                     }
                 }
-            }
 
-            for (_, tool_call) in tool_calls.into_iter() {
-                let arguments = if tool_call.function.arguments.is_object() {
-                    tool_call.function.arguments
-                } else {
-                    Value::Object(Default::default())
-                };
+                // A complete set of tool calls can be signalled either by `finish_reason` on the
+                // choice (the common case) or, if the provider omits it, by the `[DONE]` marker
+                // caught above ending the loop. Only parse/emit once buffers stop growing.
+                if choice.finish_reason.as_deref() == Some("tool_calls") {
+                    for tool_call in drain_tool_calls(&mut tool_calls) {
+                        yield tool_call;
+                    }
+                }
+            }
 
-                yield Ok(RawStreamingChoice::ToolCall{
-                    name: tool_call.function.name,
-                    id: tool_call.id,
-                    arguments
-                });
+            for tool_call in drain_tool_calls(&mut tool_calls) {
+                yield tool_call;
             }
 
             // if let Some(final_usage) = final_usage.clone() {
@@ -716,6 +963,120 @@ impl Client {
 
         Ok(StreamingCompletionResponse::new(stream))
     }
+
+    /// Drives a full multi-step tool-calling turn: sends `messages`, and each time the model
+    /// responds with tool calls instead of a final answer, invokes `execute_tool` on each and
+    /// appends a `Message::tool_call`/`Message::tool_result` pair to `messages` before re-issuing
+    /// `prepare_request` — exactly the history shape `prepare_request` expects to see again next
+    /// turn. Stops once a response comes back with no tool calls (OpenAI-style APIs only ever set
+    /// `finish_reason: "tool_calls"` when at least one is present, so no tool calls is equivalent
+    /// to `finish_reason: "stop"`) or after `max_steps` round trips, whichever comes first. Yields
+    /// `AssistantContent` chunks as they stream in, same item type as consuming a plain
+    /// `send_messages` response, so callers can forward intermediate text to a UI as it arrives.
+    /// `usage` accumulates prompt/completion/cached tokens and dollar cost across every round trip
+    /// of the loop, so the caller can read it once the stream ends to report totals for the whole
+    /// turn rather than just the last request.
+    pub async fn stream_with_tools<'a, F, Fut>(
+        &'a self,
+        system_prompt: &'a str,
+        context: &'a str,
+        messages: &'a mut Vec<Message>,
+        tools: &'a [serde_json::Value],
+        options: Option<&'a RequestOptions>,
+        max_steps: usize,
+        usage: &'a mut ResponseUsage,
+        mut execute_tool: F,
+    ) -> Pin<Box<dyn Stream<Item = Result<AssistantContent, AgentError>> + Send + 'a>>
+    where
+        F: FnMut(ToolCall) -> Fut + Send + 'a,
+        Fut: std::future::Future<Output = Vec<ToolResultContent>> + Send + 'a,
+    {
+        Box::pin(stream! {
+            for _ in 0..max_steps {
+                let request = match self.prepare_request(system_prompt, context, messages, tools, options).await {
+                    Ok(r) => r,
+                    Err(e) => {
+                        yield Err(AgentError::Parse(e.to_string()));
+                        return;
+                    }
+                };
+                let builder = self.post("/chat/completions").json(&request);
+                let mut resp = match self.send_streaming_request(builder).await {
+                    Ok(r) => r,
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                };
+
+                let mut result_content = String::new();
+                let mut pending_tool_calls: Vec<ToolCall> = Vec::new();
+
+                while let Some(item) = resp.next().await {
+                    match item {
+                        Ok(AssistantContent::Text(text)) => {
+                            result_content.push_str(&text.text);
+                            yield Ok(AssistantContent::Text(text));
+                        }
+                        Ok(AssistantContent::ToolCall(tool_call)) => {
+                            if !result_content.is_empty() {
+                                messages.push(Message::assistant(&result_content));
+                                result_content.clear();
+                            }
+                            messages.push(Message::tool_call(tool_call.clone()));
+                            pending_tool_calls.push(tool_call.clone());
+                            yield Ok(AssistantContent::ToolCall(tool_call));
+                        }
+                        Ok(other @ AssistantContent::Reasoning(_)) => yield Ok(other),
+                        Err(e) => {
+                            yield Err(e);
+                            return;
+                        }
+                    }
+                }
+
+                if !result_content.is_empty() {
+                    messages.push(Message::assistant(&result_content));
+                }
+
+                if let Some(step_usage) = resp.response.as_ref() {
+                    usage.prompt_tokens += step_usage.prompt_tokens;
+                    usage.completion_tokens += step_usage.completion_tokens;
+                    usage.total_tokens += step_usage.total_tokens;
+                    usage.cached_tokens += step_usage.cached_tokens;
+                    usage.cost += step_usage.cost;
+                }
+
+                if pending_tool_calls.is_empty() {
+                    return;
+                }
+
+                for tool_call in pending_tool_calls {
+                    let result = execute_tool(tool_call.clone()).await;
+                    messages.push(Message::tool_result(&tool_call.id, result));
+                }
+            }
+        })
+    }
+
+    /// Same as `send_messages`, but with `options` merged into the request body — lets a caller
+    /// pin a cheaper/faster provider, add a model fallback chain, or override sampling for a single
+    /// call without constructing a new `Client`.
+    pub async fn send_messages_with_options(
+        &self,
+        system_prompt: &str,
+        context: &str,
+        messages: &[Message],
+        tools: &[serde_json::Value],
+        options: &RequestOptions,
+    ) -> Result<StreamingCompletionResponse, AgentError> {
+        let request = self
+            .prepare_request(system_prompt, context, messages, tools, Some(options))
+            .await
+            .map_err(|e| AgentError::Parse(e.to_string()))?;
+        let builder = self.post("/chat/completions").json(&request);
+        self.send_streaming_request(builder).await
+    }
 }
 
 #[async_trait]
@@ -726,10 +1087,11 @@ impl ProviderClient for Client {
         context: &str,
         messages: &[Message],
         tools: &[serde_json::Value],
-    ) -> Result<StreamingCompletionResponse> {
+    ) -> Result<StreamingCompletionResponse, AgentError> {
         let request = self
-            .prepare_request(system_prompt, context, messages, tools)
-            .await?;
+            .prepare_request(system_prompt, context, messages, tools, None)
+            .await
+            .map_err(|e| AgentError::Parse(e.to_string()))?;
         if std::env::var_os("HULY_AI_AGENT_TRACE_REQUEST").is_some() {
             std::fs::write(
                 "request.json",
@@ -742,172 +1104,3 @@ impl ProviderClient for Client {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use bytes::Bytes;
-    use futures::StreamExt;
-
-    use super::Client;
-
-    #[tokio::test]
-    async fn test_sse_lines_stream() {
-        let stream = futures::stream::iter([std::io::Result::Ok(Bytes::from_owner(
-            "Hello, world!".to_string(),
-        ))]);
-        let lines: Vec<_> = Client::sse_lines_stream(stream)
-            .await
-            .map(|l| l.unwrap())
-            .collect()
-            .await;
-        assert_eq!(lines, vec!["Hello, world!".to_string()]);
-
-        let stream = futures::stream::iter([std::io::Result::Ok(Bytes::from_owner(
-            "Hello,\nwo\r\rrld!".to_string(),
-        ))]);
-        let lines: Vec<_> = Client::sse_lines_stream(stream)
-            .await
-            .map(|l| l.unwrap())
-            .collect()
-            .await;
-        assert_eq!(
-            lines,
-            vec![
-                "Hello,".to_string(),
-                "wo".to_string(),
-                "".to_string(),
-                "rld!".to_string()
-            ]
-        );
-
-        let stream = futures::stream::iter([std::io::Result::Ok(Bytes::from_owner(
-            "Hello,\rwo\n\nrld!".to_string(),
-        ))]);
-        let lines: Vec<_> = Client::sse_lines_stream(stream)
-            .await
-            .map(|l| l.unwrap())
-            .collect()
-            .await;
-        assert_eq!(
-            lines,
-            vec![
-                "Hello,".to_string(),
-                "wo".to_string(),
-                "".to_string(),
-                "rld!".to_string()
-            ]
-        );
-
-        let stream = futures::stream::iter([std::io::Result::Ok(Bytes::from_owner(
-            "Hello,\r\nworld!".to_string(),
-        ))]);
-        let lines: Vec<_> = Client::sse_lines_stream(stream)
-            .await
-            .map(|l| l.unwrap())
-            .collect()
-            .await;
-        assert_eq!(lines, vec!["Hello,".to_string(), "world!".to_string()]);
-
-        let stream = futures::stream::iter([
-            std::io::Result::Ok(Bytes::from_owner("Hello,\r".to_string())),
-            std::io::Result::Ok(Bytes::from_owner("\nworld!".to_string())),
-        ]);
-        let lines: Vec<_> = Client::sse_lines_stream(stream)
-            .await
-            .map(|l| l.unwrap())
-            .collect()
-            .await;
-        assert_eq!(lines, vec!["Hello,".to_string(), "world!".to_string()]);
-
-        let stream = futures::stream::iter([
-            std::io::Result::Ok(Bytes::from_static(&[0xF0, 0x9F])),
-            std::io::Result::Ok(Bytes::from_static(&[0x8C, 0x8E])),
-            std::io::Result::Ok(Bytes::from_owner("\nHello")),
-        ]);
-        let lines: Vec<_> = Client::sse_lines_stream(stream)
-            .await
-            .map(|l| l.unwrap())
-            .collect()
-            .await;
-        assert_eq!(lines, vec!["🌎".to_string(), "Hello".to_string()]);
-    }
-
-    #[tokio::test]
-    async fn test_sse_events_stream() {
-        let stream = futures::stream::iter(
-            r#": test stream
-
-            data: first event
-            id: 1
-
-            data:second event
-            id
-
-            data:  third event"#
-                .lines()
-                .map(|s| Ok(s.trim().to_string())),
-        );
-        let events: Vec<_> = Client::sse_events_stream(stream)
-            .await
-            .map(|l| l.unwrap())
-            .collect()
-            .await;
-        assert_eq!(
-            events,
-            vec![
-                (
-                    "1".to_string(),
-                    "message".to_string(),
-                    "first event".to_string()
-                ),
-                (
-                    "".to_string(),
-                    "message".to_string(),
-                    "second event".to_string()
-                ),
-            ]
-        );
-        let stream = futures::stream::iter(
-            r#"data: YHOO
-            data: +2
-            data: 10
-            "#
-            .lines()
-            .map(|s| Ok(s.trim().to_string())),
-        );
-        let events: Vec<_> = Client::sse_events_stream(stream)
-            .await
-            .map(|l| l.unwrap())
-            .collect()
-            .await;
-        assert_eq!(
-            events,
-            vec![(
-                "".to_string(),
-                "message".to_string(),
-                "YHOO\n+2\n10".to_string()
-            )]
-        );
-        let stream = futures::stream::iter(
-            r#"data
-
-            data
-            data
-
-            data:"#
-                .lines()
-                .map(|s| Ok(s.trim().to_string())),
-        );
-        let events: Vec<_> = Client::sse_events_stream(stream)
-            .await
-            .map(|l| l.unwrap())
-            .collect()
-            .await;
-        assert_eq!(
-            events,
-            vec![
-                ("".to_string(), "message".to_string(), "".to_string()),
-                ("".to_string(), "message".to_string(), "\n".to_string())
-            ]
-        );
-    }
-}
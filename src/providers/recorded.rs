@@ -0,0 +1,84 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures::stream;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::AgentError,
+    providers::ProviderClient,
+    types::{
+        Message,
+        streaming::{RawStreamingChoice, ResponseUsage, StreamingCompletionResponse},
+    },
+};
+
+/// Serializable mirror of `streaming::RawStreamingChoice`, used only by recorded fixtures — kept
+/// separate since `RawStreamingChoice` itself isn't `Serialize`/`Deserialize`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RecordedChoice {
+    Message { text: String },
+    ToolCall { id: String, name: String, arguments: serde_json::Value },
+    Reasoning { text: String },
+    FinalResponse { usage: ResponseUsage },
+}
+
+impl RecordedChoice {
+    fn into_raw(self) -> RawStreamingChoice {
+        match self {
+            RecordedChoice::Message { text } => RawStreamingChoice::Message(text),
+            RecordedChoice::ToolCall { id, name, arguments } => {
+                RawStreamingChoice::ToolCall { id, name, arguments }
+            }
+            RecordedChoice::Reasoning { text } => RawStreamingChoice::Reasoning(text),
+            RecordedChoice::FinalResponse { usage } => RawStreamingChoice::FinalResponse(usage),
+        }
+    }
+}
+
+/// One `send_messages` response per entry, replayed in order by `RecordedClient`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordedFixture {
+    pub calls: Vec<Vec<RecordedChoice>>,
+}
+
+/// A `ProviderClient` that replays a fixture of pre-recorded responses instead of calling a live
+/// provider. Each `send_messages` call consumes the next entry in `calls`, in order; once
+/// exhausted, further calls fail rather than looping or fabricating a response, so a workload that
+/// outruns its fixture is a visible error instead of silent drift.
+pub struct RecordedClient {
+    calls: Mutex<std::vec::IntoIter<Vec<RecordedChoice>>>,
+}
+
+impl RecordedClient {
+    pub fn new(fixture_path: &str) -> Result<Self> {
+        let data = std::fs::read_to_string(fixture_path)
+            .with_context(|| format!("Failed to read recorded fixture {fixture_path}"))?;
+        let fixture: RecordedFixture = serde_json::from_str(&data)
+            .with_context(|| format!("Malformed recorded fixture {fixture_path}"))?;
+        Ok(Self { calls: Mutex::new(fixture.calls.into_iter()) })
+    }
+}
+
+#[async_trait]
+impl ProviderClient for RecordedClient {
+    async fn send_messages(
+        &self,
+        _system_prompt: &str,
+        _context: &str,
+        _messages: &[Message],
+    ) -> Result<StreamingCompletionResponse, AgentError> {
+        let next = self.calls.lock().unwrap().next();
+        let Some(choices) = next else {
+            return Err(AgentError::Config(
+                "Recorded fixture exhausted: no more responses to replay".to_string(),
+            ));
+        };
+        let items: Vec<_> = choices.into_iter().map(|c| Ok(c.into_raw())).collect();
+        Ok(StreamingCompletionResponse::new(Box::pin(stream::iter(items))))
+    }
+}
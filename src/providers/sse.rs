@@ -0,0 +1,354 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! Generic Server-Sent Events framing, shared by every streaming `ProviderClient` (OpenAI-style
+//! deltas and Anthropic's content-block events alike): splitting a raw byte stream into lines,
+//! then lines into `id`/`event`/`data`/`retry` events per the SSE spec. Provider-specific payload
+//! parsing lives in each provider's own module.
+
+use std::pin::Pin;
+
+use anyhow::{Result, anyhow};
+use async_stream::stream;
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+
+/// A parsed SSE event, per the spec's `id`/`event`/`data`/`retry` fields. `id` carries the last
+/// `id:` seen on the connection so far (not just this event's own, per spec; an empty `id:`
+/// resets it to empty), letting a
+/// reconnect resume via `Last-Event-ID`; `retry`, when present, is the server's requested
+/// reconnect backoff in milliseconds.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SseEvent {
+    pub(crate) id: String,
+    pub(crate) event: String,
+    pub(crate) data: String,
+    pub(crate) retry: Option<u64>,
+}
+
+pub(crate) async fn sse_lines_stream<E: std::error::Error + Send + Sync + 'static>(
+    mut stream: impl Stream<Item = std::result::Result<Bytes, E>> + Unpin + Send + 'static,
+) -> Pin<Box<dyn Stream<Item = Result<String>> + Send>> {
+    const CR: u8 = 0x0D;
+    const LF: u8 = 0x0A;
+
+    Box::pin(stream! {
+        let mut chunks: Vec<Bytes> = Vec::new();
+        let mut chunks_length = 0;
+        let mut has_end_carriage = false;
+        while let Some(chunk_result) = stream.next().await {
+            let chunk = match chunk_result {
+                Ok(c) => c,
+                Err(e) => {
+                    yield Err(anyhow!(e));
+                    break;
+                }
+            };
+            if chunk.is_empty() {
+                continue;
+            }
+            let mut chunk_start = 0;
+            for (idx, &b) in chunk.iter().enumerate() {
+                if has_end_carriage {
+                    has_end_carriage = false;
+                    if b == LF {
+                        chunk_start += 1;
+                        continue;
+                    }
+                }
+                if b == CR || b == LF {
+                    has_end_carriage = b == CR;
+                    let total_line_length = chunks_length + idx - chunk_start;
+                    let mut buf = Vec::with_capacity(total_line_length);
+                    for c in chunks.drain(..) {
+                        buf.extend_from_slice(&c);
+                    }
+                    buf.extend_from_slice(&chunk[chunk_start..idx]);
+                    chunk_start = idx + 1;
+                    let line = match String::from_utf8(buf) {
+                        Ok(t) => t,
+                        Err(e) => {
+                            yield Err(anyhow!(e));
+                            break;
+                        }
+                    };
+                    yield Ok(line);
+                }
+            }
+            let chunk = chunk.slice(chunk_start..);
+            if !chunk.is_empty() {
+                chunks_length += chunk.len();
+                chunks.push(chunk);
+            }
+        }
+        if chunks_length > 0 {
+            let total_line_length = chunks_length;
+            let mut buf = Vec::with_capacity(total_line_length);
+            for c in chunks.drain(..) {
+                buf.extend_from_slice(&c);
+            }
+            match String::from_utf8(buf) {
+                Ok(line) => {
+                    yield Ok(line);
+                },
+                Err(e) => {
+                    yield Err(anyhow!(e));
+                }
+            };
+        }
+    })
+}
+
+pub(crate) async fn sse_events_stream(
+    line_stream: impl Stream<Item = Result<String>> + Unpin + Send + 'static,
+) -> Pin<Box<dyn Stream<Item = Result<SseEvent>> + Send>> {
+    use std::fmt::Write;
+
+    Box::pin(stream! {
+        let mut stream = line_stream;
+        let mut event_type = String::new();
+        let mut event_data = String::new();
+        let mut event_last_id = String::new();
+        let mut event_retry_ms = None;
+
+        while let Some(line_result) = stream.next().await {
+            let line = match line_result {
+                Ok(c) => c,
+                Err(e) => {
+                    yield Err(e);
+                    break;
+                }
+            };
+            if line.is_empty() {
+                if event_data.is_empty() {
+                    event_type.clear();
+                    continue;
+                }
+                let mut new_event_data = std::mem::take(&mut event_data);
+                let mut new_event_type = std::mem::take(&mut event_type);
+                if new_event_data.ends_with('\n') {
+                    new_event_data.truncate(new_event_data.len() - 1);
+                }
+                if new_event_type.is_empty() {
+                    new_event_type.push_str("message");
+                }
+                yield Ok(SseEvent {
+                    id: event_last_id.clone(),
+                    event: new_event_type,
+                    data: new_event_data,
+                    retry: event_retry_ms,
+                });
+            }
+
+            // Skip comments
+            if line.starts_with(":") {
+                continue;
+            }
+            let (field_name, mut field_value) = line.split_once(":").unwrap_or((line.as_str(), ""));
+            if field_value.starts_with(' ') {
+                field_value = &field_value[1..];
+            }
+            match field_name {
+                "id" => {
+                    if !field_value.contains('\0') {
+                        event_last_id = field_value.to_string();
+                    }
+                }
+                "event" => {
+                    event_type = field_value.to_string();
+                }
+                "data" => {
+                    event_data.write_fmt(format_args!("{field_value}\n")).expect("write to string does not fail");
+                }
+                "retry" => {
+                    if let Ok(ms) = field_value.trim().parse::<u64>() {
+                        event_retry_ms = Some(ms);
+                    }
+                }
+                _ => (),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use futures::StreamExt;
+
+    use super::{SseEvent, sse_events_stream, sse_lines_stream};
+
+    fn event(id: &str, event: &str, data: &str) -> SseEvent {
+        SseEvent {
+            id: id.to_string(),
+            event: event.to_string(),
+            data: data.to_string(),
+            retry: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sse_lines_stream() {
+        let stream = futures::stream::iter([std::io::Result::Ok(Bytes::from_owner(
+            "Hello, world!".to_string(),
+        ))]);
+        let lines: Vec<_> = sse_lines_stream(stream)
+            .await
+            .map(|l| l.unwrap())
+            .collect()
+            .await;
+        assert_eq!(lines, vec!["Hello, world!".to_string()]);
+
+        let stream = futures::stream::iter([std::io::Result::Ok(Bytes::from_owner(
+            "Hello,\nwo\r\rrld!".to_string(),
+        ))]);
+        let lines: Vec<_> = sse_lines_stream(stream)
+            .await
+            .map(|l| l.unwrap())
+            .collect()
+            .await;
+        assert_eq!(
+            lines,
+            vec![
+                "Hello,".to_string(),
+                "wo".to_string(),
+                "".to_string(),
+                "rld!".to_string()
+            ]
+        );
+
+        let stream = futures::stream::iter([std::io::Result::Ok(Bytes::from_owner(
+            "Hello,\rwo\n\nrld!".to_string(),
+        ))]);
+        let lines: Vec<_> = sse_lines_stream(stream)
+            .await
+            .map(|l| l.unwrap())
+            .collect()
+            .await;
+        assert_eq!(
+            lines,
+            vec![
+                "Hello,".to_string(),
+                "wo".to_string(),
+                "".to_string(),
+                "rld!".to_string()
+            ]
+        );
+
+        let stream = futures::stream::iter([std::io::Result::Ok(Bytes::from_owner(
+            "Hello,\r\nworld!".to_string(),
+        ))]);
+        let lines: Vec<_> = sse_lines_stream(stream)
+            .await
+            .map(|l| l.unwrap())
+            .collect()
+            .await;
+        assert_eq!(lines, vec!["Hello,".to_string(), "world!".to_string()]);
+
+        let stream = futures::stream::iter([
+            std::io::Result::Ok(Bytes::from_owner("Hello,\r".to_string())),
+            std::io::Result::Ok(Bytes::from_owner("\nworld!".to_string())),
+        ]);
+        let lines: Vec<_> = sse_lines_stream(stream)
+            .await
+            .map(|l| l.unwrap())
+            .collect()
+            .await;
+        assert_eq!(lines, vec!["Hello,".to_string(), "world!".to_string()]);
+
+        let stream = futures::stream::iter([
+            std::io::Result::Ok(Bytes::from_static(&[0xF0, 0x9F])),
+            std::io::Result::Ok(Bytes::from_static(&[0x8C, 0x8E])),
+            std::io::Result::Ok(Bytes::from_owner("\nHello")),
+        ]);
+        let lines: Vec<_> = sse_lines_stream(stream)
+            .await
+            .map(|l| l.unwrap())
+            .collect()
+            .await;
+        assert_eq!(lines, vec!["🌎".to_string(), "Hello".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_sse_events_stream() {
+        let stream = futures::stream::iter(
+            r#": test stream
+
+            data: first event
+            id: 1
+
+            data:second event
+            id
+
+            data:  third event"#
+                .lines()
+                .map(|s| Ok(s.trim().to_string())),
+        );
+        let events: Vec<_> = sse_events_stream(stream)
+            .await
+            .map(|l| l.unwrap())
+            .collect()
+            .await;
+        assert_eq!(
+            events,
+            vec![
+                event("1", "message", "first event"),
+                event("", "message", "second event"),
+            ]
+        );
+        let stream = futures::stream::iter(
+            r#"data: YHOO
+            data: +2
+            data: 10
+            "#
+            .lines()
+            .map(|s| Ok(s.trim().to_string())),
+        );
+        let events: Vec<_> = sse_events_stream(stream)
+            .await
+            .map(|l| l.unwrap())
+            .collect()
+            .await;
+        assert_eq!(events, vec![event("", "message", "YHOO\n+2\n10")]);
+        let stream = futures::stream::iter(
+            r#"data
+
+            data
+            data
+
+            data:"#
+                .lines()
+                .map(|s| Ok(s.trim().to_string())),
+        );
+        let events: Vec<_> = sse_events_stream(stream)
+            .await
+            .map(|l| l.unwrap())
+            .collect()
+            .await;
+        assert_eq!(
+            events,
+            vec![event("", "message", ""), event("", "message", "\n")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sse_events_stream_retry_field() {
+        let stream = futures::stream::iter(
+            r#"retry: 5000
+            data: first event
+
+            data: second event
+
+            "#
+                .lines()
+                .map(|s| Ok(s.trim().to_string())),
+        );
+        let events: Vec<_> = sse_events_stream(stream)
+            .await
+            .map(|e| e.unwrap())
+            .collect()
+            .await;
+        assert_eq!(events[0].retry, Some(5000));
+        // `retry` (like `id`) is sticky: it carries over to later events on the same connection.
+        assert_eq!(events[1].retry, Some(5000));
+    }
+}
@@ -0,0 +1,249 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! A minimal RFC 5545 `RRULE` parser and occurrence calculator, covering the subset scheduled
+//! tasks need: `FREQ`, `INTERVAL`, `BYDAY`, `BYMONTHDAY`, `COUNT`, `UNTIL`. Used by
+//! `config::JobSchedule` alongside its existing cron-expression support.
+
+use anyhow::{Result, anyhow, bail};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc, Weekday};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Freq {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, Clone)]
+pub struct RRule {
+    freq: Freq,
+    interval: u32,
+    by_day: Vec<Weekday>,
+    by_month_day: Vec<i32>,
+    count: Option<u32>,
+    until: Option<DateTime<Utc>>,
+}
+
+impl RRule {
+    /// Parses a `FREQ=...;INTERVAL=...;BYDAY=...;BYMONTHDAY=...;COUNT=...;UNTIL=...` string.
+    /// `COUNT` and `UNTIL` are mutually exclusive, per RFC 5545.
+    pub fn parse(rule: &str) -> Result<Self> {
+        let mut freq = None;
+        let mut interval = 1u32;
+        let mut by_day = Vec::new();
+        let mut by_month_day = Vec::new();
+        let mut count = None;
+        let mut until = None;
+
+        for part in rule.split(';') {
+            let part = part.trim();
+            if part.is_empty() {
+                continue;
+            }
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid RRULE part '{part}'"))?;
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    freq = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Freq::Daily,
+                        "WEEKLY" => Freq::Weekly,
+                        "MONTHLY" => Freq::Monthly,
+                        "YEARLY" => Freq::Yearly,
+                        other => bail!("Unsupported RRULE FREQ '{other}'"),
+                    });
+                }
+                "INTERVAL" => {
+                    interval = value
+                        .parse()
+                        .map_err(|_| anyhow!("Invalid RRULE INTERVAL '{value}'"))?;
+                }
+                "BYDAY" => {
+                    for day in value.split(',') {
+                        by_day.push(parse_weekday(day)?);
+                    }
+                }
+                "BYMONTHDAY" => {
+                    for day in value.split(',') {
+                        by_month_day.push(
+                            day.parse::<i32>()
+                                .map_err(|_| anyhow!("Invalid RRULE BYMONTHDAY '{day}'"))?,
+                        );
+                    }
+                }
+                "COUNT" => {
+                    count = Some(
+                        value
+                            .parse()
+                            .map_err(|_| anyhow!("Invalid RRULE COUNT '{value}'"))?,
+                    );
+                }
+                "UNTIL" => {
+                    until = Some(parse_until(value)?);
+                }
+                other => bail!("Unsupported RRULE part '{other}'"),
+            }
+        }
+
+        if count.is_some() && until.is_some() {
+            bail!("RRULE COUNT and UNTIL are mutually exclusive");
+        }
+        if interval == 0 {
+            bail!("RRULE INTERVAL must be at least 1");
+        }
+
+        Ok(Self {
+            freq: freq.ok_or_else(|| anyhow!("RRULE is missing FREQ"))?,
+            interval,
+            by_day,
+            by_month_day,
+            count,
+            until,
+        })
+    }
+
+    /// The first occurrence strictly after `after`, anchored at `dtstart` (which supplies the
+    /// time-of-day, and the day-of-month/weekday/month for any period not narrowed down further
+    /// by `BYDAY`/`BYMONTHDAY`). Returns `None` once `COUNT` occurrences (counted from `dtstart`
+    /// on; candidates before it are skipped, and `dtstart` is the 1st when it matches the rule)
+    /// have elapsed, or the candidate would fall after `UNTIL`.
+    pub fn next_occurrence(
+        &self,
+        dtstart: DateTime<Utc>,
+        after: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        let mut occurrence_index: u32 = 0;
+        // Bounds the search so a rule that can never advance (shouldn't happen, but `INTERVAL`
+        // is user input) can't loop forever.
+        for period in 0..10_000 {
+            let period_start = self.advance_period(dtstart, period);
+            for candidate in self.candidates_in_period(period_start) {
+                if candidate < dtstart {
+                    continue;
+                }
+                occurrence_index += 1;
+                if let Some(count) = self.count
+                    && occurrence_index > count
+                {
+                    return None;
+                }
+                if let Some(until) = self.until
+                    && candidate > until
+                {
+                    return None;
+                }
+                if candidate > after {
+                    return Some(candidate);
+                }
+            }
+        }
+        None
+    }
+
+    /// Candidate occurrences within the period starting at `period_start`, in chronological
+    /// order. With no `BYDAY`/`BYMONTHDAY`, a period has exactly one candidate: `period_start`
+    /// itself. Invalid calendar dates (e.g. `BYMONTHDAY=30` in February) are silently skipped
+    /// rather than rolled over into a neighboring month.
+    fn candidates_in_period(&self, period_start: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        if !self.by_day.is_empty() {
+            let mut candidates: Vec<DateTime<Utc>> = self
+                .by_day
+                .iter()
+                .filter_map(|weekday| day_in_week_matching(period_start, *weekday))
+                .collect();
+            candidates.sort();
+            return candidates;
+        }
+        if !self.by_month_day.is_empty() {
+            let mut candidates: Vec<DateTime<Utc>> = self
+                .by_month_day
+                .iter()
+                .filter_map(|&day| day_of_month(period_start, day))
+                .collect();
+            candidates.sort();
+            return candidates;
+        }
+        vec![period_start]
+    }
+
+    /// Advances `dtstart` by `periods` `INTERVAL`s worth of `FREQ`, keeping its time-of-day (and,
+    /// for `FREQ=MONTHLY`/`YEARLY`, its day-of-month, clamped to the target month's length).
+    /// Always computed from `dtstart`, so a clamp in a short month doesn't carry into later periods.
+    fn advance_period(&self, dtstart: DateTime<Utc>, periods: u32) -> DateTime<Utc> {
+        let steps = self.interval as i64 * periods as i64;
+        match self.freq {
+            Freq::Daily => dtstart + Duration::days(steps),
+            Freq::Weekly => dtstart + Duration::weeks(steps),
+            Freq::Monthly => add_months(dtstart, steps as i32),
+            Freq::Yearly => add_months(dtstart, steps as i32 * 12),
+        }
+    }
+}
+
+/// The date within `period_start`'s week (Monday-anchored, per iCalendar's default `WKST=MO`)
+/// that falls on `weekday`, keeping `period_start`'s time-of-day.
+fn day_in_week_matching(period_start: DateTime<Utc>, weekday: Weekday) -> Option<DateTime<Utc>> {
+    let monday = period_start.date_naive()
+        - Duration::days(period_start.weekday().num_days_from_monday() as i64);
+    let date = monday + Duration::days(weekday.num_days_from_monday() as i64);
+    date.and_time(period_start.time())
+        .and_local_timezone(Utc)
+        .single()
+}
+
+/// The `day`th of `period_start`'s month, keeping `period_start`'s time-of-day. `None` if the
+/// month doesn't have that many days, per RFC 5545's "skip invalid dates" rule.
+fn day_of_month(period_start: DateTime<Utc>, day: i32) -> Option<DateTime<Utc>> {
+    if day < 1 {
+        return None;
+    }
+    let month_start = NaiveDate::from_ymd_opt(period_start.year(), period_start.month(), 1)?;
+    let date = month_start.checked_add_signed(Duration::days(day as i64 - 1))?;
+    if date.month() != period_start.month() {
+        return None;
+    }
+    date.and_time(period_start.time())
+        .and_local_timezone(Utc)
+        .single()
+}
+
+fn add_months(date_time: DateTime<Utc>, months: i32) -> DateTime<Utc> {
+    let total_months = date_time.year() * 12 + date_time.month() as i32 - 1 + months;
+    let year = total_months.div_euclid(12);
+    let month = total_months.rem_euclid(12) as u32 + 1;
+    let day = date_time.day().min(days_in_month(year, month));
+    NaiveDate::from_ymd_opt(year, month, day)
+        .and_then(|date| date.and_time(date_time.time()).and_local_timezone(Utc).single())
+        .unwrap_or(date_time)
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let next_month = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)
+    }
+    .unwrap();
+    let this_month = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+    (next_month - this_month).num_days() as u32
+}
+
+fn parse_weekday(value: &str) -> Result<Weekday> {
+    match value.trim().to_ascii_uppercase().as_str() {
+        "MO" => Ok(Weekday::Mon),
+        "TU" => Ok(Weekday::Tue),
+        "WE" => Ok(Weekday::Wed),
+        "TH" => Ok(Weekday::Thu),
+        "FR" => Ok(Weekday::Fri),
+        "SA" => Ok(Weekday::Sat),
+        "SU" => Ok(Weekday::Sun),
+        other => bail!("Invalid RRULE BYDAY value '{other}'"),
+    }
+}
+
+fn parse_until(value: &str) -> Result<DateTime<Utc>> {
+    chrono::NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ")
+        .map(|naive| Utc.from_utc_datetime(&naive))
+        .map_err(|_| anyhow!("Invalid RRULE UNTIL '{value}', expected YYYYMMDDThhmmssZ"))
+}
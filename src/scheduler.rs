@@ -2,55 +2,130 @@
 
 use std::{
     collections::{HashMap, HashSet},
+    sync::{
+        Arc,
+        atomic::{AtomicI64, Ordering},
+    },
     time::{Duration, SystemTime},
 };
 
 use anyhow::Result;
+use async_trait::async_trait;
 use chrono::Utc;
 use rand::{Rng, SeedableRng, rngs::StdRng};
-use tokio::{
-    sync::mpsc::{UnboundedReceiver, UnboundedSender},
-    task::JoinHandle,
-};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 
 use crate::{
-    config::{Config, JobDefinition},
+    config::{Config, JobCatchupPolicy, JobDefinition, ScheduledTaskCatchup},
     database::DbClient,
-    task::{Task, TaskKind},
+    task::{JobOutcome, Task, TaskKind},
+    worker::{Worker, WorkerState},
 };
 
-pub async fn scheduler(
-    config: &Config,
+/// Drives job/assistant-task scheduling one tick at a time, as a `Worker` registered with
+/// `WorkerManager`. Replaces the old free-standing `scheduler()` task, which owned the same
+/// state in a bare `tokio::spawn` loop.
+pub struct SchedulerWorker {
+    jobs: HashMap<String, JobDefinition>,
     db_client: DbClient,
     sender: UnboundedSender<Task>,
-    mut activity_listener: UnboundedReceiver<()>,
-) -> Result<JoinHandle<()>> {
-    let jobs = config
-        .jobs
-        .iter()
-        .map(|job| (job.id.clone(), job.clone()))
-        .collect::<HashMap<String, JobDefinition>>();
-
-    let seed = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let mut rng = StdRng::seed_from_u64(seed);
-    let mut job_activity = HashSet::<String>::new();
-    let mut upcoming_jobs = db_client
-        .get_scheduler()
-        .await?
-        .into_iter()
-        .collect::<HashMap<_, _>>();
-
-    let handler = tokio::spawn(async move {
-        tracing::info!("Job scheduler started");
-        let mut interval = tokio::time::interval(Duration::from_secs(1));
+    activity_listener: UnboundedReceiver<()>,
+    rng: StdRng,
+    job_activity: HashSet<String>,
+    upcoming_jobs: HashMap<String, chrono::DateTime<Utc>>,
+    interval: tokio::time::Interval,
+    job_outcome_receiver: UnboundedReceiver<JobOutcome>,
+    /// Consecutive failures of a job since its last success, consulted against
+    /// `JobDefinition::retry` to decide whether `step()` reschedules it sooner (with backoff) or
+    /// gives up and leaves it for the next natural cron tick.
+    job_attempts: HashMap<String, u32>,
+    /// Job ids with a run currently outstanding (sent to `sender`, no `JobOutcome` seen yet).
+    /// `step()` consults this before firing a job id again, so a long stall coalesces into one
+    /// outstanding run per job instead of piling them up. The startup catch-up pass only records
+    /// the ids it fired; a `Backfill` there can send several runs of one job at once.
+    in_flight: HashSet<String>,
+    /// Unix millis of the most recently completed `step()`, shared with `communication::http`'s
+    /// `/metrics` handler via `last_tick_handle` so an operator can tell a wedged scheduler loop
+    /// (a tick that stopped advancing) from one that's simply idle.
+    last_tick: Arc<AtomicI64>,
+    /// How many runs to fire for an `AssistantTask` that missed one or more occurrences during
+    /// downtime, mirroring `JobCatchupPolicy`'s role for `jobs` but for the one-off scheduled
+    /// tasks in `scheduled_tasks` (`Config::scheduled_task_catchup`).
+    scheduled_task_catchup: ScheduledTaskCatchup,
+}
+
+impl SchedulerWorker {
+    pub async fn new(
+        config: &Config,
+        db_client: DbClient,
+        sender: UnboundedSender<Task>,
+        activity_listener: UnboundedReceiver<()>,
+        job_outcome_receiver: UnboundedReceiver<JobOutcome>,
+    ) -> Result<Self> {
+        let jobs = config
+            .jobs
+            .iter()
+            .map(|job| (job.id.clone(), job.clone()))
+            .collect::<HashMap<String, JobDefinition>>();
 
+        let seed = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let job_activity = HashSet::<String>::new();
+        let mut in_flight = HashSet::<String>::new();
+        let mut upcoming_jobs = db_client
+            .get_scheduler()
+            .await?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+
+        tracing::info!("Job scheduler started");
+        let now = Utc::now();
         for (id, job) in &jobs {
             let job = job.clone();
-            if let Some(upcoming) = upcoming_jobs.get(id) {
-                tracing::info!("[{id}] scheduled for {:?}", upcoming);
+            if let Some(upcoming) = upcoming_jobs.get(id).copied() {
+                if upcoming <= now {
+                    // Downtime carried this job's persisted fire time into the past: `upcoming`
+                    // itself is one missed slot, plus however many more occurred before `now`.
+                    let missed = 1 + job.schedule.occurrences_between(upcoming, now).len();
+                    match job.catchup {
+                        JobCatchupPolicy::Skip => {
+                            tracing::info!("[{id}] skipping {missed} run(s) missed during downtime");
+                        }
+                        JobCatchupPolicy::FireOnce => {
+                            tracing::info!(
+                                "[{id}] firing one catch-up run for {missed} run(s) missed during downtime"
+                            );
+                            let _ = sender.send(job_task(&job.kind, id));
+                            in_flight.insert(id.clone());
+                        }
+                        JobCatchupPolicy::Backfill { max } => {
+                            let to_fire = missed.min(max as usize);
+                            tracing::info!(
+                                "[{id}] backfilling {to_fire} of {missed} run(s) missed during downtime"
+                            );
+                            for _ in 0..to_fire {
+                                let _ = sender.send(job_task(&job.kind, id));
+                            }
+                            if to_fire > 0 {
+                                in_flight.insert(id.clone());
+                            }
+                        }
+                    }
+                    if let Some(mut next) = job.schedule.upcoming() {
+                        if job.time_spread.as_secs() > 0 {
+                            next += Duration::from_secs_f64(
+                                rng.random::<f64>() * job.time_spread.as_secs_f64(),
+                            );
+                        }
+                        upcoming_jobs.insert(id.clone(), next);
+                        tracing::info!("[{id}] scheduled for {:?}", next);
+                    }
+                } else {
+                    tracing::info!("[{id}] scheduled for {:?}", upcoming);
+                }
             } else if !job.disable_on_inactivity || job_activity.contains(id) {
                 if let Some(mut upcoming) = job.schedule.upcoming() {
                     if job.time_spread.as_secs() > 0 {
@@ -69,123 +144,211 @@ pub async fn scheduler(
             .update_scheduler(upcoming_jobs.iter().map(|(k, v)| (k.clone(), *v)).collect())
             .await
             .ok();
-        loop {
-            let mut changed = false;
-            let assist_tasks = db_client
-                .scheduled_tasks()
-                .await
-                .into_iter()
-                .map(|task| (task.id.to_string(), task))
-                .collect::<HashMap<_, _>>();
-            for (task_id, task) in &assist_tasks {
-                if !upcoming_jobs.contains_key(task_id) {
-                    if let Some(upcoming) = task.schedule.upcoming() {
-                        upcoming_jobs.insert(task.id.to_string(), upcoming);
+
+        Ok(Self {
+            jobs,
+            db_client,
+            sender,
+            activity_listener,
+            rng,
+            job_activity,
+            upcoming_jobs,
+            interval: tokio::time::interval(Duration::from_secs(1)),
+            job_outcome_receiver,
+            job_attempts: HashMap::new(),
+            in_flight,
+            last_tick: Arc::new(AtomicI64::new(Utc::now().timestamp_millis())),
+            scheduled_task_catchup: config.scheduled_task_catchup,
+        })
+    }
+
+    /// Handle to the most recently completed `step()`'s timestamp (unix millis), for
+    /// `communication::http`'s `/metrics` endpoint to report alongside in-flight task counts.
+    pub fn last_tick_handle(&self) -> Arc<AtomicI64> {
+        self.last_tick.clone()
+    }
+
+    /// Applies one `JobOutcome`: clears `job_attempts` on success, or — if `job.retry` still
+    /// allows it — schedules a sooner retry with `RetryPolicy::backoff` instead of waiting for the
+    /// next natural cron tick.
+    fn handle_job_outcome(&mut self, outcome: JobOutcome) {
+        let JobOutcome {
+            job_id,
+            error,
+            retry_after,
+            metrics: _,
+        } = outcome;
+        self.in_flight.remove(&job_id);
+        let Some(error) = error else {
+            self.job_attempts.remove(&job_id);
+            return;
+        };
+        let Some(job) = self.jobs.get(&job_id) else {
+            return;
+        };
+        let Some(retry) = &job.retry else {
+            tracing::error!(%job_id, %error, "Job failed, no retry policy configured");
+            return;
+        };
+        let attempt = self.job_attempts.get(&job_id).copied().unwrap_or(0);
+        if attempt >= retry.max_retries {
+            tracing::error!(%job_id, %error, attempt, "Job failed, giving up after max_retries");
+            self.job_attempts.remove(&job_id);
+            return;
+        }
+        // A `RateLimited` failure with its own `retry_after` overrides `RetryPolicy::backoff`:
+        // the provider has told us exactly how long to wait, which is more reliable than our own
+        // exponential guess.
+        let backoff = retry_after
+            .map(Duration::from_secs)
+            .unwrap_or_else(|| retry.backoff(attempt));
+        let retry_at = Utc::now() + backoff;
+        tracing::warn!(%job_id, %error, attempt, ?backoff, "Job failed, retrying with backoff");
+        self.job_attempts.insert(job_id.clone(), attempt + 1);
+        self.upcoming_jobs.insert(job_id, retry_at);
+    }
+}
+
+#[async_trait]
+impl Worker for SchedulerWorker {
+    fn name(&self) -> &str {
+        "scheduler"
+    }
+
+    async fn step(&mut self) -> Result<WorkerState> {
+        self.interval.tick().await;
+        let _span = tracing::info_span!("scheduler_tick").entered();
+        self.last_tick.store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+
+        while let Ok(outcome) = self.job_outcome_receiver.try_recv() {
+            self.handle_job_outcome(outcome);
+        }
+
+        let mut changed = false;
+
+        // One-off `AssistantTask`s live entirely in `scheduled_tasks`, queried and updated
+        // directly through `due_scheduled_tasks`/`mark_scheduled_task_ran` rather than threaded
+        // through `upcoming_jobs` like recurring `jobs` are — the DB row already tracks
+        // `next_run_at`/`last_run_at`, so there's no separate in-memory schedule to keep in sync.
+        let now = Utc::now();
+        for task in self.db_client.due_scheduled_tasks(now).await {
+            let _job_span = tracing::info_span!("job_execution", job_id = %task.id).entered();
+            let runs = match self.scheduled_task_catchup {
+                ScheduledTaskCatchup::FireLatestMissed => 1,
+                ScheduledTaskCatchup::FireAllMissed => {
+                    1 + task.schedule.occurrences_between(task.next_run_at, now).len()
+                }
+            };
+            tracing::info!("Executing [assist_task_{}] ({runs} run(s) due)", task.id);
+            for _ in 0..runs {
+                let _ = self.sender.send(Task::new(TaskKind::AssistantTask {
+                    sheduled_task_id: task.id,
+                    content: task.content.clone(),
+                }));
+            }
+            if let Err(err) = self.db_client.mark_scheduled_task_ran(task.id, now).await {
+                tracing::error!(?err, task_id = task.id, "Failed to record scheduled task run");
+            }
+        }
+
+        let jobs = &self.jobs;
+        self.upcoming_jobs.retain(|task_id, _time| jobs.contains_key(task_id));
+
+        let mut was_activity = false;
+        while self.activity_listener.try_recv().is_ok() {
+            was_activity = true;
+        }
+
+        if was_activity {
+            for job in self.jobs.values() {
+                if job.disable_on_inactivity && !self.job_activity.contains(job.id.as_str()) {
+                    let id = job.id.clone();
+                    self.job_activity.insert(id.clone());
+                    if !self.upcoming_jobs.contains_key(&id)
+                        && let Some(mut upcoming) = job.schedule.upcoming()
+                    {
+                        if job.time_spread.as_secs() > 0 {
+                            upcoming += Duration::from_secs_f64(
+                                self.rng.random::<f64>() * job.time_spread.as_secs_f64(),
+                            );
+                        }
+                        self.upcoming_jobs.insert(id.clone(), upcoming);
                         changed = true;
-                        tracing::info!("[assist_task_{}] scheduled for {:?}", task.id, upcoming);
-                    } else {
-                        tracing::info!("[assist_task_{}] delete past task", task.id);
-                        db_client.delete_scheduled_task(task.id).await.ok();
+                        tracing::info!("[{id}] scheduled for {:?}", upcoming);
                     }
                 }
             }
-            upcoming_jobs.retain(|task_id, _time| {
-                assist_tasks.contains_key(task_id) || jobs.contains_key(task_id)
-            });
+        }
 
-            let mut was_activity = false;
-            while activity_listener.try_recv().is_ok() {
-                was_activity = true;
-            }
+        let mut jobs_to_exectute = vec![];
+        let mut jobs_to_remove = vec![];
 
-            if was_activity {
-                for job in jobs.values() {
-                    if job.disable_on_inactivity && !job_activity.contains(job.id.as_str()) {
-                        let id = job.id.clone();
-                        job_activity.insert(id.clone());
-                        if !upcoming_jobs.contains_key(&id)
-                            && let Some(mut upcoming) = job.schedule.upcoming()
-                        {
+        for (key, value) in self.upcoming_jobs.iter_mut() {
+            if *value <= Utc::now() {
+                let id = key.clone();
+                jobs_to_exectute.push(key.clone());
+                if let Some(job) = self.jobs.get(&id) {
+                    if !job.disable_on_inactivity {
+                        if let Some(mut upcoming) = job.schedule.upcoming() {
                             if job.time_spread.as_secs() > 0 {
                                 upcoming += Duration::from_secs_f64(
-                                    rng.random::<f64>() * job.time_spread.as_secs_f64(),
+                                    self.rng.random::<f64>() * job.time_spread.as_secs_f64(),
                                 );
                             }
-                            upcoming_jobs.insert(id.clone(), upcoming);
+                            *value = upcoming;
                             changed = true;
                             tracing::info!("[{id}] scheduled for {:?}", upcoming);
                         }
-                    }
-                }
-            }
-
-            let mut jobs_to_exectute = vec![];
-            let mut jobs_to_remove = vec![];
-
-            for (key, value) in upcoming_jobs.iter_mut() {
-                if *value <= Utc::now() {
-                    let id = key.clone();
-                    jobs_to_exectute.push(key.clone());
-                    if let Some(job) = jobs.get(&id) {
-                        if !job.disable_on_inactivity {
-                            if let Some(mut upcoming) = job.schedule.upcoming() {
-                                if job.time_spread.as_secs() > 0 {
-                                    upcoming += Duration::from_secs_f64(
-                                        rng.random::<f64>() * job.time_spread.as_secs_f64(),
-                                    );
-                                }
-                                *value = upcoming;
-                                changed = true;
-                                tracing::info!("[{id}] scheduled for {:?}", upcoming);
-                            }
-                        } else {
-                            jobs_to_remove.push(id.clone());
-                            changed = true;
-                            tracing::info!("[{id}] not scheduled due inactivity");
-                        }
-                    } else if let Some(task) = assist_tasks.get(&id)
-                        && let Some(upcoming) = task.schedule.upcoming()
-                    {
-                        *value = upcoming;
+                    } else {
+                        jobs_to_remove.push(id.clone());
                         changed = true;
-                        tracing::info!("[assist_task_{id}] scheduled for {:?}", upcoming);
+                        tracing::info!("[{id}] not scheduled due inactivity");
                     }
                 }
             }
+        }
 
-            if !jobs_to_remove.is_empty() {
-                upcoming_jobs.retain(|id, _| !jobs_to_remove.contains(id));
-            }
+        if !jobs_to_remove.is_empty() {
+            let jobs_to_remove = &jobs_to_remove;
+            self.upcoming_jobs
+                .retain(|id, _| !jobs_to_remove.contains(id));
+        }
 
-            if changed {
-                db_client
-                    .update_scheduler(upcoming_jobs.iter().map(|(k, v)| (k.clone(), *v)).collect())
-                    .await
-                    .ok();
-            }
+        if changed {
+            self.db_client
+                .update_scheduler(self.upcoming_jobs.iter().map(|(k, v)| (k.clone(), *v)).collect())
+                .await
+                .ok();
+        }
 
-            for id in jobs_to_exectute.drain(..) {
-                tracing::info!("Executing [{id}]");
-                if let Some(job_definition) = jobs.get(&id) {
-                    job_activity.remove(&id);
-                    match job_definition.kind {
-                        crate::config::JobKind::MemoryMantainance => {
-                            let _ =
-                                sender.send(Task::new(crate::task::TaskKind::MemoryMantainance));
-                        }
-                        crate::config::JobKind::Sleep => {
-                            let _ = sender.send(Task::new(TaskKind::Sleep));
-                        }
-                    }
-                } else if let Some(task) = assist_tasks.get(&id) {
-                    let _ = sender.send(Task::new(TaskKind::AssistantTask {
-                        sheduled_task_id: task.id,
-                        content: task.content.clone(),
-                    }));
+        for id in jobs_to_exectute.drain(..) {
+            if let Some(job_definition) = self.jobs.get(&id) {
+                let _job_span =
+                    tracing::info_span!("job_execution", job_id = %id, job_kind = ?job_definition.kind)
+                        .entered();
+                self.job_activity.remove(&id);
+                if self.in_flight.contains(&id) {
+                    tracing::warn!("[{id}] previous run still outstanding, coalescing misfire");
+                    continue;
                 }
+                tracing::info!("Executing [{id}]");
+                let _ = self.sender.send(job_task(&job_definition.kind, &id));
+                self.in_flight.insert(id.clone());
             }
-            interval.tick().await;
         }
-    });
-    Ok(handler)
+
+        Ok(WorkerState::Busy)
+    }
+}
+
+/// Builds the `Task` fired for a `JobDefinition`, tagged with `job_id` so `Agent::run` reports its
+/// outcome back over `job_outcome_sender`. Shared by `SchedulerWorker::new`'s startup catch-up pass
+/// and `step()`'s normal cron-driven firing.
+fn job_task(kind: &crate::config::JobKind, job_id: &str) -> Task {
+    let mut task = match kind {
+        crate::config::JobKind::MemoryMantainance => Task::new(crate::task::TaskKind::MemoryMantainance),
+        crate::config::JobKind::Sleep => Task::new(TaskKind::Sleep),
+    };
+    task.job_id = Some(job_id.to_string());
+    task
 }
@@ -1,13 +1,58 @@
+use std::sync::LazyLock;
+
 use anyhow::Result;
 use itertools::Itertools;
+use opentelemetry::{
+    KeyValue, global,
+    metrics::{Counter, Gauge},
+};
 
 use crate::{
+    collab::{self, MessageOp, VersionVector},
+    compaction::Summarizer,
+    config::AssistantCompactionConfig,
     database::DbClient,
+    knowledge_graph::{Entity, KnowledgeGraph, Observation, Relation},
     task::{Task, TaskState},
     types::Message,
 };
 
+static TASKS_TOTAL: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    global::meter("huly_agent")
+        .u64_counter("huly_agent_tasks_total")
+        .with_description("Task state transitions, labeled by the state transitioned to")
+        .build()
+});
+
+static BALANCE: LazyLock<Gauge<u64>> = LazyLock::new(|| {
+    global::meter("huly_agent")
+        .u64_gauge("huly_agent_balance")
+        .with_description("Current agent balance")
+        .build()
+});
+
+/// The label recorded on `huly_agent_tasks_total` for a state transition, matching
+/// `task::TaskState`'s variant names.
+fn task_state_label(state: &TaskState) -> &'static str {
+    match state {
+        TaskState::Created => "created",
+        TaskState::Started => "started",
+        TaskState::Completed => "completed",
+        TaskState::Cancelled => "cancelled",
+        TaskState::Postponed => "postponed",
+        TaskState::Running => "running",
+        TaskState::Failed => "failed",
+        TaskState::DeadLettered => "dead_lettered",
+    }
+}
+
 const MAX_ASSISTANT_MESSAGES: usize = 20;
+/// Replica id this process stamps CRDT ops with. A single-process deployment never needs more
+/// than one id per card, but every op still carries it so a future multi-device client can merge
+/// its own ids into the same log without colliding with ours.
+const LOCAL_REPLICA_ID: &str = "local";
+/// Cap on total entities `mem_expand_nodes` will return, regardless of `depth`.
+const MEM_EXPAND_MAX_NODES: usize = 200;
 
 #[derive(Debug, Clone)]
 pub struct AgentState {
@@ -28,6 +73,7 @@ impl AgentState {
     pub async fn set_balance(&mut self, balance: u32) -> Result<()> {
         self.db_client.set_balance(balance).await?;
         self.balance = balance;
+        BALANCE.record(balance as u64, &[]);
         Ok(())
     }
 
@@ -71,19 +117,200 @@ impl AgentState {
     }
 
     pub async fn set_task_state(&mut self, task_id: i64, state: TaskState) -> Result<()> {
-        self.db_client.set_task_state(task_id, state).await
+        self.db_client.set_task_state(task_id, state.clone()).await?;
+        TASKS_TOTAL.add(1, &[KeyValue::new("state", task_state_label(&state))]);
+        Ok(())
+    }
+
+    pub async fn reschedule_task_with_backoff(
+        &mut self,
+        task_id: i64,
+        err: &str,
+        retryable: bool,
+    ) -> Result<()> {
+        self.db_client
+            .reschedule_task_with_backoff(task_id, err, retryable)
+            .await
     }
 
     pub async fn get_assistant_messages(&self, card_id: &str) -> Result<Vec<Message>> {
         Ok(serde_json::from_str(
-            &self.db_client.get_assistant_messages(card_id).await,
+            &self.db_client.get_assistant_messages(card_id).await?,
         )?)
     }
 
+    pub async fn delete_assistant_messages(&self, card_id: &str) -> Result<()> {
+        self.db_client.delete_assistant_messages(card_id).await
+    }
+
+    /// Appends `ops` to `card_id`'s CRDT op log (see `crate::collab`). Safe to call with ops a
+    /// replica has already sent before — `DbClient::append_message_ops` is idempotent per op id.
+    pub async fn apply_ops(&self, card_id: &str, ops: &[MessageOp]) -> Result<()> {
+        self.db_client.append_message_ops(card_id, ops).await
+    }
+
+    /// Every op for `card_id` a reconnecting client (tracking `version_vector`) hasn't seen yet,
+    /// so it can merge just the delta instead of refetching the whole conversation.
+    pub async fn ops_since(&self, card_id: &str, version_vector: &VersionVector) -> Result<Vec<MessageOp>> {
+        self.db_client.message_ops_since(card_id, version_vector).await
+    }
+
+    /// The version vector to persist after applying the ops returned by `ops_since`, so the next
+    /// call only requests what's new since then.
+    pub async fn message_version_vector(&self, card_id: &str) -> Result<VersionVector> {
+        self.db_client.message_version_vector(card_id).await
+    }
+
+    /// Replays `card_id`'s full CRDT op log into its ordered, live message list, capped to
+    /// `MAX_ASSISTANT_MESSAGES` via `collab::materialize`'s tombstone-aware truncation of the
+    /// oldest committed prefix rather than a destructive rewrite of the log itself.
+    pub async fn assistant_messages_from_ops(&self, card_id: &str) -> Result<Vec<Message>> {
+        let ops = self.ops_since(card_id, &VersionVector::new()).await?;
+        Ok(collab::materialize(&ops, MAX_ASSISTANT_MESSAGES))
+    }
+
+    /// Appends a single message to `card_id`'s CRDT op log as a local-replica insert stamped with
+    /// the next local clock. `after` is recorded as its predecessor; `collab::materialize` places
+    /// the message by op id, not by `after`.
+    pub async fn add_message_op(
+        &self,
+        card_id: &str,
+        after: Option<collab::MessageOpId>,
+        role: &str,
+        message: Message,
+    ) -> Result<()> {
+        let mut version_vector = self.message_version_vector(card_id).await?;
+        let clock = collab::bump(&mut version_vector, LOCAL_REPLICA_ID);
+        let op = MessageOp::Insert {
+            id: collab::MessageOpId { replica_id: LOCAL_REPLICA_ID.to_string(), clock },
+            after,
+            role: role.to_string(),
+            message,
+        };
+        self.apply_ops(card_id, &[op]).await
+    }
+
+    pub async fn has_assistant_messages(&self, card_id: &str) -> Result<bool> {
+        self.db_client.has_assistant_messages(card_id).await
+    }
+
+    /// Folds the oldest stored messages for `card_id` into its summary once it exceeds
+    /// `config.max_messages`. A no-op when under budget.
+    pub async fn compact_assistant_messages(
+        &self,
+        card_id: &str,
+        summarizer: &dyn Summarizer,
+        config: &AssistantCompactionConfig,
+    ) -> Result<()> {
+        crate::compaction::compact_if_needed(&self.db_client, card_id, summarizer, config).await
+    }
+
+    //#region knowledge graph
+    pub async fn mem_add_entities(&mut self, entities: &mut Vec<Entity>) -> Result<Vec<Entity>> {
+        self.db_client.kg_add_entities(entities).await
+    }
+
+    pub async fn mem_add_relations(
+        &mut self,
+        relations: &mut Vec<Relation>,
+    ) -> Result<Vec<Relation>> {
+        self.db_client.kg_add_relations(relations).await
+    }
+
+    pub async fn mem_add_observations(
+        &mut self,
+        observations: Vec<Observation>,
+    ) -> Result<Vec<Observation>> {
+        self.db_client.kg_add_observations(&observations).await
+    }
+
+    pub async fn mem_delete_entities(&mut self, names: &[String]) -> Result<()> {
+        self.db_client.kg_delete_entities(names).await
+    }
+
+    pub async fn mem_delete_observations(&mut self, observations: &[Observation]) -> Result<()> {
+        self.db_client.kg_delete_observations(observations).await
+    }
+
+    pub async fn mem_delete_relations(&mut self, relations: &[Relation]) -> Result<()> {
+        self.db_client.kg_delete_relations(relations).await
+    }
+
+    pub async fn mem_search_nodes(&self, query: Option<&str>) -> Result<KnowledgeGraph> {
+        self.db_client.kg_search_nodes(query).await
+    }
+
+    /// Cosine-similarity ranked variant of `mem_search_nodes`, backing `search_nodes`'s
+    /// `semantic`/`hybrid` modes.
+    pub async fn mem_search_nodes_semantic(&self, query: &str, k: usize) -> Result<Vec<Entity>> {
+        self.db_client.kg_search_semantic(query, k).await
+    }
+
+    pub async fn mem_list_entities(&self, names: &[String]) -> Result<Vec<Entity>> {
+        self.db_client.kg_list_entities(names).await
+    }
+
+    /// Breadth-first expansion over `Relation` edges starting from `names`, following matching
+    /// relations (all of them when `relation_filter` is `None`) up to `depth` hops. Stops early
+    /// once `MEM_EXPAND_MAX_NODES` entities have been reached, so a densely-connected graph can't
+    /// turn "expand two hops" into "return the entire graph".
+    pub async fn mem_expand_nodes(
+        &self,
+        names: &[String],
+        depth: u32,
+        relation_filter: Option<&[String]>,
+    ) -> Result<KnowledgeGraph> {
+        let mut visited: std::collections::HashSet<String> = names.iter().cloned().collect();
+        let mut seen_relations: std::collections::HashSet<(String, String, String)> =
+            std::collections::HashSet::new();
+        let mut entities = self.db_client.kg_list_entities(names).await?;
+        let mut relations = Vec::new();
+        let mut frontier: Vec<String> = names.to_vec();
+
+        for _ in 0..depth {
+            if frontier.is_empty() || visited.len() >= MEM_EXPAND_MAX_NODES {
+                break;
+            }
+            let touching = self.db_client.kg_relations_touching(&frontier).await?;
+            let mut next_names = Vec::new();
+            for relation in touching {
+                if relation_filter
+                    .is_some_and(|allowed| !allowed.contains(&relation.relation_type))
+                {
+                    continue;
+                }
+                let key = (
+                    relation.from.clone(),
+                    relation.to.clone(),
+                    relation.relation_type.clone(),
+                );
+                if seen_relations.insert(key) {
+                    relations.push(relation.clone());
+                }
+                for candidate in [&relation.from, &relation.to] {
+                    if visited.len() + next_names.len() < MEM_EXPAND_MAX_NODES
+                        && !visited.contains(candidate)
+                        && !next_names.contains(candidate)
+                    {
+                        next_names.push(candidate.clone());
+                    }
+                }
+            }
+            if next_names.is_empty() {
+                break;
+            }
+            visited.extend(next_names.iter().cloned());
+            entities.extend(self.db_client.kg_list_entities(&next_names).await?);
+            frontier = next_names;
+        }
+
+        Ok(KnowledgeGraph { entities, relations })
+    }
+    //#endregion
+
     pub async fn set_assistant_messages(&self, card_id: &str, messages: &[Message]) -> Result<()> {
         if messages.len() > MAX_ASSISTANT_MESSAGES {
-            let _: () = self
-                .db_client
+            self.db_client
                 .set_assistant_messages(
                     card_id,
                     serde_json::to_string(
@@ -93,14 +320,11 @@ impl AgentState {
                             .collect_vec(),
                     )?,
                 )
-                .await;
-            Ok(())
+                .await
         } else {
-            let _: () = self
-                .db_client
+            self.db_client
                 .set_assistant_messages(card_id, serde_json::to_string(messages)?)
-                .await;
-            Ok(())
+                .await
         }
     }
 }
@@ -0,0 +1,310 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! In-memory `NoteStore`/`KnowledgeGraphStore` implementation backed by `Arc<RwLock<...>>`
+//! maps, so the tool layer can be exercised in tests without a real database. Not registered as
+//! a `Backend` (it doesn't implement `StateStore`/`TaskStore`/`ScheduleStore`/`MemoryStore`) —
+//! it's a test double for the two traits the memory/notes tools actually depend on.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::RwLock;
+
+use crate::knowledge_graph::{Entity, KnowledgeGraph, Observation, Relation};
+use crate::note::Note;
+use crate::storage::{KnowledgeGraphStore, NoteStore};
+
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryBackend {
+    notes: Arc<RwLock<HashMap<i64, Note>>>,
+    next_note_id: Arc<std::sync::atomic::AtomicI64>,
+    entities: Arc<RwLock<HashMap<String, Entity>>>,
+    next_entity_id: Arc<std::sync::atomic::AtomicI64>,
+    relations: Arc<RwLock<Vec<Relation>>>,
+}
+
+impl InMemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl NoteStore for InMemoryBackend {
+    async fn notes(&self) -> Result<Vec<Note>> {
+        Ok(self.notes.read().await.values().cloned().collect())
+    }
+
+    async fn add_note(&self, content: &str, tags: &[String], mentions: &[String]) -> Result<i64> {
+        let id = self
+            .next_note_id
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        self.notes.write().await.insert(
+            id,
+            Note {
+                id,
+                content: content.to_string(),
+                tags: tags.to_vec(),
+                mentions: mentions.to_vec(),
+            },
+        );
+        Ok(id)
+    }
+
+    async fn delete_notes(&self, ids: Vec<i64>) -> Result<()> {
+        let mut notes = self.notes.write().await;
+        for id in ids {
+            notes.remove(&id);
+        }
+        Ok(())
+    }
+
+    async fn notes_search(
+        &self,
+        tag: Option<&str>,
+        query: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Note>> {
+        let mut notes: Vec<Note> = self.notes.read().await.values().cloned().collect();
+        notes.sort_by(|a, b| b.id.cmp(&a.id));
+        if let Some(tag) = tag {
+            notes.retain(|note| note.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)));
+        }
+        if let Some(query) = query {
+            let query = query.to_lowercase();
+            notes.retain(|note| note.content.to_lowercase().contains(&query));
+        }
+        Ok(notes
+            .into_iter()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .collect())
+    }
+}
+
+#[async_trait]
+impl KnowledgeGraphStore for InMemoryBackend {
+    async fn kg_add_entities(&self, entities: &[Entity]) -> Result<Vec<Entity>> {
+        let mut store = self.entities.write().await;
+        let mut created = Vec::new();
+        for entity in entities {
+            if store.contains_key(&entity.name) {
+                continue;
+            }
+            let id = self
+                .next_entity_id
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let entity = Entity {
+                id,
+                ..entity.clone()
+            };
+            store.insert(entity.name.clone(), entity.clone());
+            created.push(entity);
+        }
+        Ok(created)
+    }
+
+    async fn kg_add_relations(&self, relations: &[Relation]) -> Result<Vec<Relation>> {
+        let mut store = self.relations.write().await;
+        let mut created = Vec::new();
+        for relation in relations {
+            let exists = store.iter().any(|existing| {
+                existing.from == relation.from
+                    && existing.to == relation.to
+                    && existing.relation_type == relation.relation_type
+            });
+            if exists {
+                continue;
+            }
+            store.push(relation.clone());
+            created.push(relation.clone());
+        }
+        Ok(created)
+    }
+
+    async fn kg_add_observations(&self, observations: &[Observation]) -> Result<Vec<Observation>> {
+        let mut store = self.entities.write().await;
+        let mut added = Vec::new();
+        for observation in observations {
+            let Some(entity) = store.get_mut(&observation.entity_name) else {
+                continue;
+            };
+            let mut added_texts = Vec::new();
+            for text in &observation.observations {
+                if !entity.observations.contains(text) {
+                    entity.observations.push(text.clone());
+                    added_texts.push(text.clone());
+                }
+            }
+            if !added_texts.is_empty() {
+                added.push(Observation {
+                    entity_name: observation.entity_name.clone(),
+                    observations: added_texts,
+                });
+            }
+        }
+        Ok(added)
+    }
+
+    async fn kg_delete_entities(&self, names: &[String]) -> Result<()> {
+        let mut entities = self.entities.write().await;
+        for name in names {
+            entities.remove(name);
+        }
+        let mut relations = self.relations.write().await;
+        relations.retain(|relation| !names.contains(&relation.from) && !names.contains(&relation.to));
+        Ok(())
+    }
+
+    async fn kg_delete_observations(&self, deletions: &[Observation]) -> Result<()> {
+        let mut store = self.entities.write().await;
+        for deletion in deletions {
+            if let Some(entity) = store.get_mut(&deletion.entity_name) {
+                entity
+                    .observations
+                    .retain(|text| !deletion.observations.contains(text));
+            }
+        }
+        Ok(())
+    }
+
+    async fn kg_delete_relations(&self, relations: &[Relation]) -> Result<()> {
+        let mut store = self.relations.write().await;
+        store.retain(|existing| {
+            !relations.iter().any(|relation| {
+                existing.from == relation.from
+                    && existing.to == relation.to
+                    && existing.relation_type == relation.relation_type
+            })
+        });
+        Ok(())
+    }
+
+    async fn kg_search_nodes(&self, query: Option<&str>) -> Result<KnowledgeGraph> {
+        let entities_store = self.entities.read().await;
+        let entities: Vec<Entity> = match query {
+            Some(query) => {
+                let query = query.to_lowercase();
+                entities_store
+                    .values()
+                    .filter(|entity| {
+                        entity.name.to_lowercase().contains(&query)
+                            || entity.entity_type.to_lowercase().contains(&query)
+                            || entity
+                                .observations
+                                .iter()
+                                .any(|observation| observation.to_lowercase().contains(&query))
+                    })
+                    .cloned()
+                    .collect()
+            }
+            None => entities_store.values().cloned().collect(),
+        };
+        let names: std::collections::HashSet<&str> =
+            entities.iter().map(|entity| entity.name.as_str()).collect();
+        let relations = self
+            .relations
+            .read()
+            .await
+            .iter()
+            .filter(|relation| {
+                names.contains(relation.from.as_str()) && names.contains(relation.to.as_str())
+            })
+            .cloned()
+            .collect();
+        Ok(KnowledgeGraph { entities, relations })
+    }
+
+    async fn kg_list_entities(&self, names: &[String]) -> Result<Vec<Entity>> {
+        let store = self.entities.read().await;
+        Ok(names
+            .iter()
+            .filter_map(|name| store.get(name).cloned())
+            .collect())
+    }
+
+    async fn kg_relations_touching(&self, names: &[String]) -> Result<Vec<Relation>> {
+        Ok(self
+            .relations
+            .read()
+            .await
+            .iter()
+            .filter(|relation| names.contains(&relation.from) || names.contains(&relation.to))
+            .cloned()
+            .collect())
+    }
+
+    /// No embedding provider to call in tests, so this ranks by the same case-insensitive
+    /// substring match as `kg_search_nodes` rather than real cosine similarity.
+    async fn kg_search_semantic(&self, query: &str, k: usize) -> Result<Vec<Entity>> {
+        let mut entities = self.kg_search_nodes(Some(query)).await?.entities;
+        entities.truncate(k);
+        for entity in &mut entities {
+            entity.score = Some(1.0);
+        }
+        Ok(entities)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(name: &str) -> Entity {
+        Entity {
+            id: 0,
+            name: name.to_string(),
+            entity_type: "person".to_string(),
+            observations: vec![],
+            score: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn add_entities_skips_existing_names() {
+        let store = InMemoryBackend::new();
+        let created = store.kg_add_entities(&[entity("alice")]).await.unwrap();
+        assert_eq!(created.len(), 1);
+        let created_again = store.kg_add_entities(&[entity("alice")]).await.unwrap();
+        assert!(created_again.is_empty());
+    }
+
+    #[tokio::test]
+    async fn add_observations_dedups_per_entity() {
+        let store = InMemoryBackend::new();
+        store.kg_add_entities(&[entity("alice")]).await.unwrap();
+        let added = store
+            .kg_add_observations(&[Observation {
+                entity_name: "alice".to_string(),
+                observations: vec!["likes tea".to_string(), "likes tea".to_string()],
+            }])
+            .await
+            .unwrap();
+        assert_eq!(added[0].observations, vec!["likes tea".to_string()]);
+        let added_again = store
+            .kg_add_observations(&[Observation {
+                entity_name: "alice".to_string(),
+                observations: vec!["likes tea".to_string()],
+            }])
+            .await
+            .unwrap();
+        assert!(added_again.is_empty());
+    }
+
+    #[tokio::test]
+    async fn add_relations_dedups_exact_triples() {
+        let store = InMemoryBackend::new();
+        let relation = Relation {
+            from: "alice".to_string(),
+            to: "bob".to_string(),
+            relation_type: "knows".to_string(),
+        };
+        let created = store.kg_add_relations(&[relation.clone()]).await.unwrap();
+        assert_eq!(created.len(), 1);
+        let created_again = store.kg_add_relations(&[relation]).await.unwrap();
+        assert!(created_again.is_empty());
+    }
+}
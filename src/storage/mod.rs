@@ -0,0 +1,490 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! Backend-agnostic persistence traits. `DbClient` (in `crate::database`) holds a
+//! `Box<dyn Backend>` and forwards every call here, so swapping the SQLite backend for
+//! `PostgresBackend` (or any future backend) doesn't touch call sites elsewhere in the crate.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::{
+    collab::{MessageOp, VersionVector},
+    config::{Config, DatabaseConfig},
+    knowledge_graph::{Entity, KnowledgeGraph, Observation, Relation},
+    memory::{MemoryEntity, MemoryEntityType},
+    note::Note,
+    task::{ScheduledAssistantTask, Task, TaskState},
+    types::Message,
+};
+
+pub mod in_memory;
+pub mod postgres;
+pub mod sqlite;
+
+/// Content address for `message_body` rows: the hex BLAKE3 hash of the message content, so
+/// repeated bodies (system-prompt boilerplate, retried tool output, ...) are stored once and
+/// every `assistant_message` row just references it.
+pub fn content_hash(content: &str) -> String {
+    blake3::hash(content.as_bytes()).to_hex().to_string()
+}
+
+/// One embedded schema change, applied at most once by each backend's own migration runner
+/// (`sqlite::run_migrations`, `postgres::run_migrations`). `version` is the primary key of the
+/// `schema_migrations` table, so it must be unique and is conventionally zero-padded and ordered
+/// (`"0001_..."`, `"0002_..."`, ...) so migrations apply in a stable order across restarts.
+pub struct Migration {
+    pub version: &'static str,
+    pub sql: &'static str,
+}
+
+#[async_trait]
+pub trait StateStore: Send + Sync {
+    async fn balance(&self) -> Result<u32>;
+    async fn set_balance(&self, balance: u32) -> Result<()>;
+
+    /// Appends one message to `card_id`'s history in `assistant_message`, returning its
+    /// monotonic per-card `seq` (computed as `MAX(seq) + 1` inside a transaction).
+    async fn add_assistant_message(&self, card_id: &str, role: &str, content: &str)
+    -> Result<i64>;
+    /// The `count` messages immediately before `before_seq` (the most recent `count` when
+    /// `before_seq` is `None`), returned oldest-first as `(seq, role, content)`.
+    async fn get_last_messages(
+        &self,
+        card_id: &str,
+        before_seq: Option<i64>,
+        count: u32,
+    ) -> Result<Vec<(i64, String, String)>>;
+    /// Drops every stored message for `card_id`.
+    async fn delete_assistant_messages(&self, card_id: &str) -> Result<()>;
+    /// Whether `card_id` has any stored messages, without fetching or decoding them.
+    async fn has_assistant_messages(&self, card_id: &str) -> Result<bool>;
+
+    /// The current rolling summary for `card_id` (if compaction has ever run for it), as
+    /// `(from_seq, to_seq, summary)` — the inclusive `seq` range it collapses.
+    async fn assistant_summary(&self, card_id: &str) -> Result<Option<(i64, i64, String)>>;
+    /// Folds the messages in `[from_seq, to_seq]` into `card_id`'s summary, merging with any
+    /// summary already stored there. The upsert and the collapsed-message deletes happen in one
+    /// transaction, so a crash mid-compaction either fully applies or fully doesn't — safe to
+    /// retry with the same range.
+    async fn compact_assistant_messages(
+        &self,
+        card_id: &str,
+        from_seq: i64,
+        to_seq: i64,
+        summary: &str,
+    ) -> Result<()>;
+
+    /// Batched counterpart of `get_assistant_messages`: the message histories for every card in
+    /// `card_ids`, fetched with a single `WHERE card_id IN (...)` query instead of one round trip
+    /// per card. Cards with no stored messages map to `"[]"`, matching the single-key method's
+    /// contract.
+    async fn get_assistant_messages_many(&self, card_ids: &[&str]) -> HashMap<String, String>;
+
+    /// Appends `ops` to `card_id`'s CRDT op log (see `crate::collab`). Idempotent per op id, so
+    /// replaying an op a backend already has is a no-op rather than a duplicate insert.
+    async fn append_message_ops(&self, card_id: &str, ops: &[MessageOp]) -> Result<()>;
+    /// Every op for `card_id` the caller hasn't seen yet, i.e. whose id's clock exceeds the
+    /// component `version_vector` records for that id's `replica_id`. An empty `version_vector`
+    /// returns the full log.
+    async fn message_ops_since(
+        &self,
+        card_id: &str,
+        version_vector: &VersionVector,
+    ) -> Result<Vec<MessageOp>>;
+    /// The highest clock seen per replica for `card_id`, i.e. the version vector a client should
+    /// persist after applying a batch of ops, to request only the next delta on reconnect.
+    async fn message_version_vector(&self, card_id: &str) -> Result<VersionVector>;
+
+    /// Compatibility shim over `get_last_messages`/`assistant_summary`: reconstructs the single
+    /// JSON-array blob that callers used to read directly, with the rolling summary (if any)
+    /// prepended in place of the messages it collapsed. Propagates the underlying store error
+    /// instead of collapsing "no messages" and "DB error" into the same empty result.
+    async fn get_assistant_messages(&self, card_id: &str) -> Result<String> {
+        let rows = self.get_last_messages(card_id, None, u32::MAX).await?;
+        let mut messages: Vec<Message> = rows
+            .into_iter()
+            .filter_map(|(_, _, content)| serde_json::from_str(&content).ok())
+            .collect();
+        if let Some((_, _, summary)) = self.assistant_summary(card_id).await? {
+            messages.insert(
+                0,
+                Message::assistant(&format!("Summary of earlier conversation:\n{summary}")),
+            );
+        }
+        Ok(serde_json::to_string(&messages).unwrap_or_else(|_| "[]".to_string()))
+    }
+
+    /// Compatibility shim over `add_assistant_message`/`delete_assistant_messages`: replaces
+    /// `card_id`'s entire history with the JSON array `messages` used to be stored as. Propagates
+    /// the first write failure instead of silently dropping the rest of the history.
+    async fn set_assistant_messages(&self, card_id: &str, messages: String) -> Result<()> {
+        let messages: Vec<Message> = serde_json::from_str(&messages)?;
+        self.delete_assistant_messages(card_id).await?;
+        for message in &messages {
+            let role = match message {
+                Message::User { .. } => "user",
+                Message::Assistant { .. } => "assistant",
+            };
+            let content = serde_json::to_string(message)?;
+            self.add_assistant_message(card_id, role, &content).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+pub trait TaskStore: Send + Sync {
+    async fn unfinished_tasks(&self) -> Vec<Task>;
+    async fn reschedule_task_with_backoff(
+        &self,
+        task_id: i64,
+        err: &str,
+        retryable: bool,
+    ) -> Result<()>;
+    async fn touch_task_heartbeat(&self, task_id: i64) -> Result<()>;
+    async fn reclaim_stale_tasks(&self, stale_after: chrono::Duration) -> Result<Vec<Task>>;
+    async fn task_messages(&self, task_id: i64) -> Result<Vec<Message>>;
+    async fn add_task(&self, task: &Task) -> Result<i64>;
+    async fn add_task_message(&self, task: &Task, message: Message) -> Result<Message>;
+    async fn update_task_messages(&self, task_id: i64, messages: &[Message]) -> Result<()>;
+    async fn set_task_state(&self, task_id: i64, state: TaskState) -> Result<()>;
+    async fn set_task_complexity(&self, task_id: i64, complexity: u32) -> Result<()>;
+    async fn delete_old_tasks(&self, expire_date: DateTime<Utc>) -> Result<()>;
+
+    /// Records that a task fingerprinted as `fingerprint` (see `TaskKind::fingerprint`) was just
+    /// enqueued, upserting `seen_at`/`hits` so a later duplicate is detected even across a
+    /// restart, mirroring how `unfinished_tasks` survives one.
+    async fn record_task_fingerprint(&self, fingerprint: &str) -> Result<()>;
+    /// Whether `fingerprint` was recorded within `window` of now — an in-flight or
+    /// recently-enqueued duplicate that the dedup cache should drop instead of persisting again.
+    async fn seen_task_fingerprint(&self, fingerprint: &str, window: chrono::Duration)
+    -> Result<bool>;
+    /// Total tasks dropped as duplicates so far, rendered alongside `${SCHEDULED_TASKS}` in
+    /// `create_context`.
+    async fn deduped_task_count(&self) -> Result<i64>;
+}
+
+#[async_trait]
+pub trait ScheduleStore: Send + Sync {
+    async fn scheduled_tasks(&self) -> Vec<ScheduledAssistantTask>;
+    async fn add_scheduled_task(
+        &self,
+        content: &str,
+        schedule: &str,
+    ) -> Result<ScheduledAssistantTask>;
+    async fn delete_scheduled_task(&self, task_id: i64) -> Result<()>;
+    /// Scheduled tasks whose `next_run_at` has passed, i.e. are due (or overdue) to fire.
+    async fn due_scheduled_tasks(&self, now: DateTime<Utc>) -> Vec<ScheduledAssistantTask>;
+    /// Records that a scheduled task fired at `fired_at` and advances `next_run_at` to the
+    /// next occurrence after it.
+    async fn mark_scheduled_task_ran(&self, task_id: i64, fired_at: DateTime<Utc>) -> Result<()>;
+}
+
+#[async_trait]
+pub trait MemoryStore: Send + Sync {
+    async fn mem_entity_by_name(
+        &self,
+        name: &str,
+        entity_type: MemoryEntityType,
+    ) -> Option<MemoryEntity>;
+    async fn mem_entity(&self, id: i64) -> Result<MemoryEntity>;
+    /// Updates `entity`, which must carry the `version_vector` it was originally read with. If
+    /// the stored version vector has since moved on (another writer committed in the meantime),
+    /// the two are treated as causal siblings and merged deterministically (see
+    /// `memory::merge_entities`) instead of one clobbering the other. Either way, `writer`'s own
+    /// component in the stored version vector is bumped by one on success.
+    async fn mem_update_entity(&self, entity: &MemoryEntity, writer: &str) -> Result<()>;
+    async fn mem_update_entity_importance(&self, id: i64, importance: f32) -> Result<()>;
+    /// Inserts `entity` as a new row, seeding its version vector to `{writer: 1}` (its
+    /// `version_vector` field is ignored — there's no prior causal history to extend).
+    async fn mem_add_entity(&self, entity: &MemoryEntity, writer: &str) -> Result<()>;
+    async fn mem_last_entities(&self, limit: u16) -> Result<Vec<MemoryEntity>>;
+    async fn mem_entities_ids_for_consolidation(&self, threshold: f32) -> Result<Vec<i64>>;
+    async fn mem_relevant_entities(
+        &self,
+        limit: u16,
+        query: &str,
+        entity_type: MemoryEntityType,
+    ) -> Result<Vec<MemoryEntity>>;
+    /// Like `mem_relevant_entities`, but pairs every candidate with a "higher is better"
+    /// similarity score (same convention as `KnowledgeGraphStore::kg_search_semantic`) and drops
+    /// any candidate scoring below `min_similarity` instead of padding the result out to `limit`.
+    /// Used by `create_context`'s `${MEMORY_ENTRIES}` relevant-entries channel.
+    async fn mem_relevant_entities_scored(
+        &self,
+        limit: u16,
+        query: &str,
+        entity_type: MemoryEntityType,
+        min_similarity: f32,
+    ) -> Result<Vec<(MemoryEntity, f32)>>;
+    async fn mem_get_entity_ids(&self) -> Result<Vec<i64>>;
+    async fn mem_delete_entity(&self, id: i64) -> Result<()>;
+    /// Re-embeds every entity (e.g. after switching `EmbeddingProvider` or its dimensions) and
+    /// rewrites its stored embedding, in `embed_batch` batches instead of one request per entity.
+    async fn mem_reembed_all(&self) -> Result<()>;
+}
+
+#[async_trait]
+pub trait NoteStore: Send + Sync {
+    async fn notes(&self) -> Result<Vec<Note>>;
+    async fn add_note(&self, content: &str, tags: &[String], mentions: &[String]) -> Result<i64>;
+    async fn delete_notes(&self, ids: Vec<i64>) -> Result<()>;
+    /// Filtered, paginated view over `notes`, so `notes_search` doesn't have to pull the entire
+    /// `${NOTES}` dump. `tag` matches notes carrying that tag (case-insensitive); `query` matches
+    /// a case-insensitive substring of the content. Either filter is skipped when `None`.
+    async fn notes_search(
+        &self,
+        tag: Option<&str>,
+        query: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Note>>;
+}
+
+/// Persists which background workers (`crate::worker::WorkerManager`) are paused, so a pause
+/// set by an operator survives a restart instead of every worker coming back active.
+#[async_trait]
+pub trait WorkerStore: Send + Sync {
+    async fn paused_worker_ids(&self) -> Result<Vec<String>>;
+    async fn set_worker_paused(&self, id: &str, paused: bool) -> Result<()>;
+}
+
+/// Operator-set overrides that take precedence over `Config`'s static values without a restart.
+/// Keyed like `"embedding.api_key"`/`"embedding.model"`/`"embedding.dimensions"`.
+#[async_trait]
+pub trait ConfigOverrideStore: Send + Sync {
+    async fn config_overrides(&self) -> Result<HashMap<String, String>>;
+    async fn set_config_override(&self, key: &str, value: &str) -> Result<()>;
+
+    /// Re-reads `config_overrides` and, if the embedding settings changed since the provider
+    /// was last built, rebuilds it. Returns whether a rebuild happened. Call this on SIGHUP to
+    /// pick up a rotated API key or a model switch without restarting the process.
+    async fn reload_embedding_provider(&self) -> Result<bool>;
+}
+
+/// Shared hot-reload cell behind each backend's `embedding_provider` field: the active provider,
+/// plus the fingerprint of the `EmbeddingProviderConfig` it was built from, so
+/// `ConfigOverrideStore::reload_embedding_provider` can tell an override no-op from one that
+/// actually needs a rebuild.
+#[derive(Debug)]
+pub struct EmbeddingHotState {
+    pub provider: std::sync::Arc<dyn crate::embeddings::EmbeddingProvider>,
+    pub fingerprint: String,
+}
+
+impl EmbeddingHotState {
+    pub fn new(config: &crate::config::EmbeddingProviderConfig) -> Result<Self> {
+        Ok(Self {
+            provider: crate::embeddings::build_embedding_provider(config)?,
+            fingerprint: config.fingerprint(),
+        })
+    }
+}
+
+/// Overlays operator-set `overrides` (keyed `"embedding.api_key"`/`"embedding.model"`/
+/// `"embedding.base_url"`/`"embedding.model_path"`/`"embedding.dimensions"`) onto `base`, returning
+/// the effective config to rebuild the provider from. A key absent from `overrides` keeps `base`'s
+/// value; keys that don't apply to `base`'s variant (e.g. `embedding.base_url` against a
+/// `VoyageAi` config) are ignored — switching provider *kind* via override isn't supported, only
+/// tuning the kind already configured.
+pub fn apply_embedding_overrides(
+    base: crate::config::EmbeddingProviderConfig,
+    overrides: &HashMap<String, String>,
+) -> crate::config::EmbeddingProviderConfig {
+    use crate::config::EmbeddingProviderConfig::{Local, OpenAiCompatible, VoyageAi};
+    let dimensions = overrides
+        .get("embedding.dimensions")
+        .and_then(|v| v.parse::<u16>().ok());
+    match base {
+        VoyageAi {
+            api_key,
+            model,
+            dimensions: base_dimensions,
+        } => VoyageAi {
+            api_key: overrides
+                .get("embedding.api_key")
+                .map(|v| secrecy::SecretString::from(v.clone()))
+                .unwrap_or(api_key),
+            model: overrides.get("embedding.model").cloned().unwrap_or(model),
+            dimensions: dimensions.unwrap_or(base_dimensions),
+        },
+        OpenAiCompatible {
+            base_url,
+            api_key,
+            model,
+            dimensions: base_dimensions,
+        } => OpenAiCompatible {
+            base_url: overrides
+                .get("embedding.base_url")
+                .cloned()
+                .unwrap_or(base_url),
+            api_key: overrides
+                .get("embedding.api_key")
+                .map(|v| secrecy::SecretString::from(v.clone()))
+                .unwrap_or(api_key),
+            model: overrides.get("embedding.model").cloned().unwrap_or(model),
+            dimensions: dimensions.unwrap_or(base_dimensions),
+        },
+        Local {
+            model_path,
+            dimensions: base_dimensions,
+        } => Local {
+            model_path: overrides
+                .get("embedding.model_path")
+                .cloned()
+                .unwrap_or(model_path),
+            dimensions: dimensions.unwrap_or(base_dimensions),
+        },
+    }
+}
+
+/// Disposition of a `PendingAction`, set by the operator via the HTTP approval endpoint
+/// (`communication::http`). Stored as lowercase text so the column reads naturally in ad-hoc
+/// queries against either backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PendingActionStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl PendingActionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PendingActionStatus::Pending => "pending",
+            PendingActionStatus::Approved => "approved",
+            PendingActionStatus::Rejected => "rejected",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "approved" => PendingActionStatus::Approved,
+            "rejected" => PendingActionStatus::Rejected,
+            _ => PendingActionStatus::Pending,
+        }
+    }
+}
+
+/// An `Execute`-kind tool call (see `tools::ToolKind`) awaiting operator sign-off before the task
+/// loop that requested it is allowed to dispatch it.
+#[derive(Debug, Clone)]
+pub struct PendingAction {
+    pub call_id: String,
+    pub tool_name: String,
+    pub arguments: String,
+    pub status: PendingActionStatus,
+}
+
+/// Backing store for the execute-tool confirmation gate: the task loop records a `PendingAction`
+/// before it will call an `Execute`-kind tool, then polls it while the operator approves or
+/// rejects it out-of-band through the HTTP API.
+#[async_trait]
+pub trait PendingActionStore: Send + Sync {
+    async fn add_pending_action(&self, call_id: &str, tool_name: &str, arguments: &str) -> Result<()>;
+    async fn pending_action(&self, call_id: &str) -> Result<Option<PendingAction>>;
+    async fn set_pending_action_status(
+        &self,
+        call_id: &str,
+        status: PendingActionStatus,
+    ) -> Result<()>;
+}
+
+/// Crash-durable state for `task::task_multiplexer`: the raw Kafka offset it has finished
+/// processing, and any `CardMessage`s coalesced into `waiting_messages` but not yet dispatched as
+/// a `Task`. Together these let a restart resume exactly where it left off — replaying
+/// `journaled_messages` to rebuild its in-memory buffers — instead of losing a batch that was
+/// still debouncing or double-processing one the Kafka consumer redelivers.
+#[async_trait]
+pub trait MultiplexerStore: Send + Sync {
+    /// Last Kafka offset the multiplexer has durably recorded. `None` before the first message.
+    async fn multiplexer_offset(&self) -> Result<Option<i64>>;
+    async fn set_multiplexer_offset(&self, offset: i64) -> Result<()>;
+    /// Records `payload` (the serialized `ReceivedMessage`) as part of `card_id`'s still-waiting
+    /// batch. Upserts by `(card_id, message_id)`, so a redelivered message overwrites in place.
+    async fn journal_card_message(&self, card_id: &str, message_id: &str, payload: &str) -> Result<()>;
+    /// Drops every journaled message for `card_id`, once its batch has been dispatched as a `Task`.
+    async fn clear_card_journal(&self, card_id: &str) -> Result<()>;
+    /// All journaled messages not yet cleared, as `(card_id, message_id, payload)`, for replay on
+    /// startup.
+    async fn journaled_messages(&self) -> Result<Vec<(String, String, String)>>;
+    /// Serialized `huly::streaming::FollowState` (`follow_card_ids`, `tracked_message_ids`,
+    /// `persistent_cards`) for `(workspace_id, group_id)`, so `huly::streaming::worker` restores
+    /// open follow windows and seen message ids across a restart instead of starting cold and
+    /// re-backfilling (or re-triggering) conversations it was already following.
+    async fn follow_state(&self, workspace_id: &str, group_id: &str) -> Result<Option<String>>;
+    async fn set_follow_state(&self, workspace_id: &str, group_id: &str, payload: &str) -> Result<()>;
+}
+
+/// Backing store for the knowledge-graph memory toolset (`tools::memory`) — a distinct,
+/// user-curated entity/relation graph, separate from the episodic `MemoryStore` pipeline.
+#[async_trait]
+pub trait KnowledgeGraphStore: Send + Sync {
+    /// Creates the entities in `entities` that don't already exist (matched by name); existing
+    /// ones are left untouched. Returns only the ones actually created.
+    async fn kg_add_entities(&self, entities: &[Entity]) -> Result<Vec<Entity>>;
+    /// Creates relations that don't already exist (matched by the `(from, to, relation_type)`
+    /// triple). Returns only the ones actually created.
+    async fn kg_add_relations(&self, relations: &[Relation]) -> Result<Vec<Relation>>;
+    /// Adds new observations (and their embeddings) to existing entities, skipping any that
+    /// already exist verbatim on that entity. Returns, per entity, only the observations
+    /// actually added. Observations for an entity that doesn't exist are silently skipped.
+    async fn kg_add_observations(&self, observations: &[Observation]) -> Result<Vec<Observation>>;
+    async fn kg_delete_entities(&self, names: &[String]) -> Result<()>;
+    async fn kg_delete_observations(&self, deletions: &[Observation]) -> Result<()>;
+    async fn kg_delete_relations(&self, relations: &[Relation]) -> Result<()>;
+    /// All entities and relations, optionally filtered to those whose name, type, or any
+    /// observation contains `query` (case-insensitive substring match). `None` returns the
+    /// full graph.
+    async fn kg_search_nodes(&self, query: Option<&str>) -> Result<KnowledgeGraph>;
+    async fn kg_list_entities(&self, names: &[String]) -> Result<Vec<Entity>>;
+    /// Every relation with `from` or `to` (or both) in `names` — the one-hop frontier used by
+    /// `AgentState::mem_expand_nodes`'s breadth-first graph expansion.
+    async fn kg_relations_touching(&self, names: &[String]) -> Result<Vec<Relation>>;
+    /// Embeds `query` and ranks every entity by the best cosine similarity across its
+    /// observations (max-pooled), returning the top `k` with `Entity::score` set.
+    async fn kg_search_semantic(&self, query: &str, k: usize) -> Result<Vec<Entity>>;
+}
+
+/// Everything a concrete storage backend (`SqliteBackend`, `PostgresBackend`, ...) must
+/// implement. `DbClient` depends only on this trait object, never on a specific backend.
+pub trait Backend:
+    StateStore
+    + TaskStore
+    + ScheduleStore
+    + MemoryStore
+    + NoteStore
+    + KnowledgeGraphStore
+    + WorkerStore
+    + PendingActionStore
+    + MultiplexerStore
+    + ConfigOverrideStore
+{
+}
+impl<
+    T: StateStore
+        + TaskStore
+        + ScheduleStore
+        + MemoryStore
+        + NoteStore
+        + KnowledgeGraphStore
+        + WorkerStore
+        + PendingActionStore
+        + MultiplexerStore
+        + ConfigOverrideStore,
+> Backend for T
+{
+}
+
+/// Picks and connects the backend selected by `config.database`.
+pub async fn connect(data_dir: &str, config: &Config) -> Result<Box<dyn Backend>> {
+    match &config.database {
+        DatabaseConfig::Sqlite => Ok(Box::new(sqlite::SqliteBackend::new(data_dir, config).await?)),
+        DatabaseConfig::Postgres { url } => {
+            Ok(Box::new(postgres::PostgresBackend::new(url, config).await?))
+        }
+    }
+}
@@ -0,0 +1,2286 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! Postgres + pgvector implementation of the storage traits. Functionally equivalent to
+//! `sqlite::SqliteBackend`, but queries are written against a server-class schema: embeddings live
+//! in a pgvector `embedding` column on `mem_entity`/`kg_observation` instead of `sqlite_vec`'s
+//! `vec_mem_entity1`/`vec_kg_observation1` virtual tables, and nearest-neighbour search uses
+//! pgvector's `<=>` (cosine distance) operator.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use pgvector::Vector;
+use sqlx::{PgPool, Row, postgres::PgPoolOptions};
+use std::collections::HashMap;
+
+use crate::{
+    config::{Config, EmbeddingProviderConfig, JobSchedule},
+    embeddings::EmbeddingProvider,
+    knowledge_graph::{Entity, KnowledgeGraph, Observation, Relation},
+    memory::{MemoryEntity, MemoryEntityType},
+    storage::{EmbeddingHotState, Migration},
+    task::{ScheduledAssistantTask, Task, TaskKind, TaskState},
+    types::Message,
+};
+
+const RETRY_BASE_BACKOFF: chrono::Duration = chrono::Duration::seconds(30);
+const RETRY_MAX_BACKOFF: chrono::Duration = chrono::Duration::hours(1);
+/// How many entities to re-embed per `embed_batch` request in `mem_reembed_all`.
+const REEMBED_BATCH_SIZE: usize = 64;
+
+/// The Postgres counterpart of `sqlite::MIGRATIONS`: same tables and order, written against
+/// Postgres types (`BIGSERIAL`, `TIMESTAMPTZ`) instead of SQLite's.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: "0001_agent_state",
+        sql: "CREATE TABLE IF NOT EXISTS agent_state (balance BIGINT NOT NULL DEFAULT 0)",
+    },
+    Migration {
+        version: "0002_agent_state_seed",
+        sql: "INSERT INTO agent_state (balance) SELECT 0 WHERE NOT EXISTS (SELECT 1 FROM agent_state)",
+    },
+    Migration {
+        version: "0003_tasks",
+        sql: "CREATE TABLE IF NOT EXISTS tasks (
+            id BIGSERIAL PRIMARY KEY,
+            kind TEXT NOT NULL,
+            social_id TEXT,
+            person_id TEXT,
+            person_name TEXT,
+            card_id TEXT,
+            card_title TEXT,
+            content TEXT,
+            message_id TEXT,
+            state INTEGER NOT NULL DEFAULT 0,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            max_attempts INTEGER NOT NULL DEFAULT 5,
+            run_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            last_error TEXT,
+            heartbeat_at TIMESTAMPTZ,
+            complexity INTEGER NOT NULL DEFAULT 0,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        )",
+    },
+    Migration {
+        version: "0004_task_message",
+        sql: "CREATE TABLE IF NOT EXISTS task_message (
+            id BIGSERIAL PRIMARY KEY,
+            task_id BIGINT NOT NULL REFERENCES tasks(id),
+            content TEXT NOT NULL
+        )",
+    },
+    Migration {
+        version: "0005_scheduled_tasks",
+        sql: "CREATE TABLE IF NOT EXISTS scheduled_tasks (
+            id BIGSERIAL PRIMARY KEY,
+            content TEXT NOT NULL,
+            schedule TEXT NOT NULL,
+            last_run_at TIMESTAMPTZ,
+            next_run_at TIMESTAMPTZ
+        )",
+    },
+    Migration {
+        version: "0006_mem_entity",
+        sql: "CREATE TABLE IF NOT EXISTS mem_entity (
+            id BIGSERIAL PRIMARY KEY,
+            name TEXT NOT NULL,
+            entity_type INTEGER NOT NULL,
+            category TEXT,
+            importance REAL NOT NULL DEFAULT 0,
+            access_count INTEGER NOT NULL DEFAULT 0,
+            observations TEXT NOT NULL DEFAULT '[]',
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        )",
+    },
+    Migration {
+        version: "0007_mem_relation",
+        sql: "CREATE TABLE IF NOT EXISTS mem_relation (
+            from_id BIGINT NOT NULL REFERENCES mem_entity(id),
+            to_id BIGINT NOT NULL REFERENCES mem_entity(id)
+        )",
+    },
+    Migration {
+        version: "0008_notes",
+        sql: "CREATE TABLE IF NOT EXISTS notes (
+            id BIGSERIAL PRIMARY KEY,
+            content TEXT NOT NULL
+        )",
+    },
+    Migration {
+        version: "0009_message_body",
+        sql: "CREATE TABLE IF NOT EXISTS message_body (
+            hash TEXT PRIMARY KEY,
+            content TEXT NOT NULL
+        )",
+    },
+    Migration {
+        version: "0010_assistant_message",
+        sql: "CREATE TABLE IF NOT EXISTS assistant_message (
+            id BIGSERIAL PRIMARY KEY,
+            card_id TEXT NOT NULL,
+            seq BIGINT NOT NULL,
+            role TEXT NOT NULL,
+            hash TEXT NOT NULL REFERENCES message_body(hash),
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            UNIQUE(card_id, seq)
+        )",
+    },
+    Migration {
+        version: "0011_assistant_summary",
+        sql: "CREATE TABLE IF NOT EXISTS assistant_summary (
+            card_id TEXT PRIMARY KEY,
+            from_seq BIGINT NOT NULL,
+            to_seq BIGINT NOT NULL,
+            summary TEXT NOT NULL
+        )",
+    },
+    Migration {
+        version: "0012_pgvector_extension",
+        sql: "CREATE EXTENSION IF NOT EXISTS vector",
+    },
+    Migration {
+        version: "0013_mem_entity_version_vector",
+        sql: "ALTER TABLE mem_entity ADD COLUMN version_vector TEXT NOT NULL DEFAULT '{}'",
+    },
+    Migration {
+        version: "0014_kg_entity",
+        sql: "CREATE TABLE IF NOT EXISTS kg_entity (
+            id BIGSERIAL PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            entity_type TEXT NOT NULL
+        )",
+    },
+    Migration {
+        version: "0015_kg_observation",
+        sql: "CREATE TABLE IF NOT EXISTS kg_observation (
+            id BIGSERIAL PRIMARY KEY,
+            entity_id BIGINT NOT NULL REFERENCES kg_entity(id),
+            text TEXT NOT NULL,
+            UNIQUE(entity_id, text)
+        )",
+    },
+    Migration {
+        version: "0016_kg_relation",
+        sql: "CREATE TABLE IF NOT EXISTS kg_relation (
+            from_name TEXT NOT NULL,
+            to_name TEXT NOT NULL,
+            relation_type TEXT NOT NULL,
+            UNIQUE(from_name, to_name, relation_type)
+        )",
+    },
+    Migration {
+        version: "0017_notes_tags",
+        sql: "ALTER TABLE notes ADD COLUMN IF NOT EXISTS tags TEXT NOT NULL DEFAULT '[]'",
+    },
+    Migration {
+        version: "0018_notes_mentions",
+        sql: "ALTER TABLE notes ADD COLUMN IF NOT EXISTS mentions TEXT NOT NULL DEFAULT '[]'",
+    },
+    Migration {
+        version: "0019_worker_pause_state",
+        sql: "CREATE TABLE IF NOT EXISTS worker_pause_state (id TEXT PRIMARY KEY)",
+    },
+    Migration {
+        version: "0020_assistant_message_op",
+        sql: "CREATE TABLE IF NOT EXISTS assistant_message_op (
+            card_id TEXT NOT NULL,
+            replica_id TEXT NOT NULL,
+            clock BIGINT NOT NULL,
+            kind TEXT NOT NULL,
+            after_replica TEXT,
+            after_clock BIGINT,
+            role TEXT,
+            hash TEXT REFERENCES message_body(hash),
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            PRIMARY KEY (card_id, replica_id, clock)
+        )",
+    },
+    Migration {
+        version: "0021_task_fingerprint",
+        sql: "CREATE TABLE IF NOT EXISTS task_fingerprint (
+            fingerprint TEXT PRIMARY KEY,
+            seen_at TIMESTAMPTZ NOT NULL,
+            hits INTEGER NOT NULL DEFAULT 1
+        )",
+    },
+    Migration {
+        version: "0022_pending_action",
+        sql: "CREATE TABLE IF NOT EXISTS pending_action (
+            call_id TEXT PRIMARY KEY,
+            tool_name TEXT NOT NULL,
+            arguments TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        )",
+    },
+    Migration {
+        version: "0023_multiplexer_offset",
+        sql: "CREATE TABLE IF NOT EXISTS multiplexer_offset (
+            id INTEGER PRIMARY KEY CHECK (id = 0),
+            offset_value BIGINT NOT NULL
+        )",
+    },
+    Migration {
+        version: "0024_multiplexer_journal",
+        sql: "CREATE TABLE IF NOT EXISTS multiplexer_journal (
+            card_id TEXT NOT NULL,
+            message_id TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            PRIMARY KEY (card_id, message_id)
+        )",
+    },
+    Migration {
+        version: "0025_multiplexer_follow_state",
+        sql: "CREATE TABLE IF NOT EXISTS multiplexer_follow_state (
+            workspace_id TEXT NOT NULL,
+            group_id TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            PRIMARY KEY (workspace_id, group_id)
+        )",
+    },
+    Migration {
+        version: "0026_config_override",
+        sql: "CREATE TABLE IF NOT EXISTS config_override (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+    },
+];
+
+/// Applies every migration in `migrations` not yet recorded in `schema_migrations`, each in its
+/// own transaction, in order. Bails out on the first failure rather than silently skipping it, so
+/// a broken migration surfaces as a startup error instead of a missing table down the line.
+async fn run_migrations(pool: &PgPool, migrations: &[Migration]) -> Result<()> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version TEXT PRIMARY KEY,
+            applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    for migration in migrations {
+        let already_applied = sqlx::query("SELECT 1 as present FROM schema_migrations WHERE version = $1")
+            .bind(migration.version)
+            .fetch_optional(pool)
+            .await?
+            .is_some();
+        if already_applied {
+            continue;
+        }
+
+        let mut tx = pool.begin().await?;
+        sqlx::query(migration.sql)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("migration {} failed", migration.version))?;
+        sqlx::query("INSERT INTO schema_migrations (version) VALUES ($1)")
+            .bind(migration.version)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        tracing::info!(version = migration.version, "Applied schema migration");
+    }
+
+    Ok(())
+}
+
+/// A single shared connection pool: unlike SQLite, Postgres has no single-writer limitation,
+/// so reads and writes can safely share a pool sized for concurrency.
+#[derive(Debug, Clone)]
+pub struct PostgresBackend {
+    pool: PgPool,
+    /// Base config the provider was last (re)built from, before any `config_override` overlay.
+    /// Stays fixed for the process lifetime; only `embedding_state`'s contents change on reload.
+    embedding_config: EmbeddingProviderConfig,
+    embedding_state: std::sync::Arc<tokio::sync::RwLock<EmbeddingHotState>>,
+}
+
+impl PostgresBackend {
+    pub async fn new(url: &str, config: &Config) -> Result<Self> {
+        let pool = PgPoolOptions::new().max_connections(10).connect(url).await?;
+        run_migrations(&pool, MIGRATIONS).await?;
+
+        let embedding_config = config.embedding_provider.clone();
+        let embedding_state = EmbeddingHotState::new(&embedding_config)?;
+        // Kept outside `MIGRATIONS`: the vector column width depends on the configured embedding
+        // provider's dimensions. `ADD COLUMN IF NOT EXISTS` is natively idempotent in Postgres, so
+        // running this on every startup is safe.
+        sqlx::query(&format!(
+            "ALTER TABLE mem_entity ADD COLUMN IF NOT EXISTS embedding vector({})",
+            embedding_state.provider.dimensions()
+        ))
+        .execute(&pool)
+        .await?;
+        sqlx::query(&format!(
+            "ALTER TABLE kg_observation ADD COLUMN IF NOT EXISTS embedding vector({})",
+            embedding_state.provider.dimensions()
+        ))
+        .execute(&pool)
+        .await?;
+
+        Ok(Self {
+            pool,
+            embedding_config,
+            embedding_state: std::sync::Arc::new(tokio::sync::RwLock::new(embedding_state)),
+        })
+    }
+
+    async fn embedding_provider(&self) -> std::sync::Arc<dyn EmbeddingProvider> {
+        self.embedding_state.read().await.provider.clone()
+    }
+
+    async fn create_embedding(&self, text: &str) -> Result<Vec<f32>> {
+        self.embedding_provider().await.embed(text).await
+    }
+
+    async fn create_embeddings(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        self.embedding_provider().await.embed_batch(texts).await
+    }
+
+    fn text_for_embedding(entity: &MemoryEntity) -> String {
+        format!(
+            r#"Entity name: {}\n
+               Category: {}\n
+               Observations: {}\n"#,
+            entity.name,
+            entity.category,
+            entity.observations.join("\n")
+        )
+    }
+
+    async fn create_entity_embedding(&self, entity: &MemoryEntity) -> Result<Vec<f32>> {
+        self.create_embedding(&Self::text_for_embedding(entity))
+            .await
+            .with_context(|| "Failed to create embedding")
+    }
+
+    async fn relations_by_entity(&self, entity_id: i64, entity_name: &str) -> Vec<String> {
+        if let Ok(relations) = sqlx::query!(
+            r#"
+            SELECT en1.name as name_from, en2.name as name_to
+            FROM mem_relation rel
+            JOIN mem_entity en1 ON rel.from_id = en1.id
+            JOIN mem_entity en2 ON rel.to_id = en2.id
+            WHERE rel.from_id = $1 OR rel.to_id = $1
+            "#,
+            entity_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        {
+            relations
+                .into_iter()
+                .map(|r| {
+                    if r.name_from == entity_name {
+                        r.name_to
+                    } else {
+                        r.name_from
+                    }
+                })
+                .collect()
+        } else {
+            vec![]
+        }
+    }
+
+    async fn mem_update_relations(&self, from_id: i64, relations: &[String]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            "DELETE FROM mem_relation WHERE from_id = $1 OR to_id = $1",
+            from_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        for relation in relations {
+            let Some(to_id) = sqlx::query!(
+                "SELECT id FROM mem_entity WHERE lower(name) = lower($1)",
+                relation
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            .map(|r| r.id) else {
+                continue;
+            };
+            sqlx::query!(
+                "INSERT INTO mem_relation (from_id, to_id) VALUES ($1, $2)",
+                from_id,
+                to_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Substring match over `name`/`category`/`observations`, used by `mem_relevant_entities`
+    /// when the embedding provider is unavailable (e.g. a VoyageAI outage). Quality is worse
+    /// than cosine similarity, but it keeps memory retrieval working through a brief API failure
+    /// instead of the extraction prompt losing its relevant-entries context entirely.
+    async fn mem_relevant_entities_lexical(
+        &self,
+        limit: u16,
+        query: &str,
+        entity_type: MemoryEntityType,
+    ) -> Result<Vec<MemoryEntity>> {
+        let pattern = format!("%{}%", query.replace(['%', '_'], ""));
+        let records = sqlx::query!(
+            r#"SELECT id, name, category, entity_type as "entity_type: MemoryEntityType",
+                      importance, access_count, observations, version_vector, created_at, updated_at
+               FROM mem_entity
+               WHERE entity_type = $1
+                 AND (name ILIKE $2 OR category ILIKE $2 OR observations ILIKE $2)
+               ORDER BY importance DESC, updated_at DESC
+               LIMIT $3"#,
+            entity_type as _,
+            pattern,
+            limit as i64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut entities = Vec::with_capacity(records.len());
+        for record in records {
+            let mut entity = MemoryEntity {
+                id: record.id,
+                name: record.name,
+                category: record.category,
+                entity_type: record.entity_type,
+                importance: record.importance,
+                access_count: record.access_count as u32,
+                relations: vec![],
+                observations: serde_json::from_str(&record.observations).unwrap_or_default(),
+                version_vector: serde_json::from_str(&record.version_vector).unwrap_or_default(),
+                created_at: record.created_at,
+                updated_at: record.updated_at,
+            };
+            entity.relations = self.relations_by_entity(entity.id, &entity.name).await;
+            entities.push(entity);
+        }
+        Ok(entities)
+    }
+}
+
+#[async_trait]
+impl crate::storage::StateStore for PostgresBackend {
+    async fn balance(&self) -> Result<u32> {
+        let balance = sqlx::query!("SELECT balance FROM agent_state")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(balance.balance.try_into().unwrap_or_default())
+    }
+
+    async fn set_balance(&self, balance: u32) -> Result<()> {
+        sqlx::query!("UPDATE agent_state SET balance = $1", balance as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn add_assistant_message(
+        &self,
+        card_id: &str,
+        role: &str,
+        content: &str,
+    ) -> Result<i64> {
+        let hash = crate::storage::content_hash(content);
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            "INSERT INTO message_body (hash, content) VALUES ($1, $2) ON CONFLICT (hash) DO NOTHING",
+            hash,
+            content
+        )
+        .execute(&mut *tx)
+        .await?;
+        let seq = sqlx::query!(
+            "SELECT COALESCE(MAX(seq), 0) + 1 as seq FROM assistant_message WHERE card_id = $1",
+            card_id
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .seq
+        .unwrap_or(1);
+        sqlx::query!(
+            "INSERT INTO assistant_message (card_id, seq, role, hash) VALUES ($1, $2, $3, $4)",
+            card_id,
+            seq,
+            role,
+            hash
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(seq)
+    }
+
+    async fn get_last_messages(
+        &self,
+        card_id: &str,
+        before_seq: Option<i64>,
+        count: u32,
+    ) -> Result<Vec<(i64, String, String)>> {
+        let before_seq = before_seq.unwrap_or(i64::MAX);
+        let mut rows = sqlx::query!(
+            r#"SELECT am.seq, am.role, mb.content
+               FROM assistant_message am
+               JOIN message_body mb ON mb.hash = am.hash
+               WHERE am.card_id = $1 AND am.seq < $2
+               ORDER BY am.seq DESC LIMIT $3"#,
+            card_id,
+            before_seq,
+            count as i64
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|r| (r.seq, r.role, r.content))
+        .collect::<Vec<_>>();
+        rows.reverse();
+        Ok(rows)
+    }
+
+    async fn delete_assistant_messages(&self, card_id: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!("DELETE FROM assistant_message WHERE card_id = $1", card_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!(
+            "DELETE FROM message_body WHERE NOT EXISTS \
+             (SELECT 1 FROM assistant_message WHERE assistant_message.hash = message_body.hash)"
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn has_assistant_messages(&self, card_id: &str) -> Result<bool> {
+        let row = sqlx::query!(
+            "SELECT 1 as present FROM assistant_message WHERE card_id = $1 LIMIT 1",
+            card_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.is_some())
+    }
+
+    async fn assistant_summary(&self, card_id: &str) -> Result<Option<(i64, i64, String)>> {
+        let row = sqlx::query!(
+            "SELECT from_seq, to_seq, summary FROM assistant_summary WHERE card_id = $1",
+            card_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|r| (r.from_seq, r.to_seq, r.summary)))
+    }
+
+    async fn compact_assistant_messages(
+        &self,
+        card_id: &str,
+        from_seq: i64,
+        to_seq: i64,
+        summary: &str,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let existing = sqlx::query!(
+            "SELECT from_seq, to_seq, summary FROM assistant_summary WHERE card_id = $1",
+            card_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let (merged_from, merged_to, merged_summary) = match existing {
+            Some(existing) => (
+                existing.from_seq.min(from_seq),
+                existing.to_seq.max(to_seq),
+                format!("{}\n{}", existing.summary, summary),
+            ),
+            None => (from_seq, to_seq, summary.to_string()),
+        };
+
+        sqlx::query!(
+            "INSERT INTO assistant_summary (card_id, from_seq, to_seq, summary) VALUES ($1, $2, $3, $4)
+             ON CONFLICT (card_id) DO UPDATE SET from_seq = excluded.from_seq, to_seq = excluded.to_seq, summary = excluded.summary",
+            card_id,
+            merged_from,
+            merged_to,
+            merged_summary
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM assistant_message WHERE card_id = $1 AND seq BETWEEN $2 AND $3",
+            card_id,
+            from_seq,
+            to_seq
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_assistant_messages_many(
+        &self,
+        card_ids: &[&str],
+    ) -> std::collections::HashMap<String, String> {
+        let mut out: std::collections::HashMap<String, String> = card_ids
+            .iter()
+            .map(|id| (id.to_string(), "[]".to_string()))
+            .collect();
+        if card_ids.is_empty() {
+            return out;
+        }
+
+        let mut messages_by_card: std::collections::HashMap<String, Vec<Message>> =
+            std::collections::HashMap::new();
+        let mut qb: sqlx::QueryBuilder<sqlx::Postgres> = sqlx::QueryBuilder::new(
+            "SELECT am.card_id, am.seq, am.role, mb.content \
+             FROM assistant_message am \
+             JOIN message_body mb ON mb.hash = am.hash \
+             WHERE am.card_id IN (",
+        );
+        {
+            let mut separated = qb.separated(", ");
+            for card_id in card_ids {
+                separated.push_bind(*card_id);
+            }
+            separated.push_unseparated(")");
+        }
+        qb.push(" ORDER BY am.card_id, am.seq");
+        if let Ok(rows) = qb.build().fetch_all(&self.pool).await {
+            for row in rows {
+                let card_id: String = row.get("card_id");
+                let content: String = row.get("content");
+                if let Ok(message) = serde_json::from_str::<Message>(&content) {
+                    messages_by_card.entry(card_id).or_default().push(message);
+                }
+            }
+        }
+
+        let mut summaries_by_card: std::collections::HashMap<String, String> =
+            std::collections::HashMap::new();
+        let mut qb: sqlx::QueryBuilder<sqlx::Postgres> = sqlx::QueryBuilder::new(
+            "SELECT card_id, summary FROM assistant_summary WHERE card_id IN (",
+        );
+        {
+            let mut separated = qb.separated(", ");
+            for card_id in card_ids {
+                separated.push_bind(*card_id);
+            }
+            separated.push_unseparated(")");
+        }
+        if let Ok(rows) = qb.build().fetch_all(&self.pool).await {
+            for row in rows {
+                summaries_by_card.insert(row.get("card_id"), row.get("summary"));
+            }
+        }
+
+        for (card_id, mut messages) in messages_by_card {
+            if let Some(summary) = summaries_by_card.get(&card_id) {
+                messages.insert(
+                    0,
+                    Message::assistant(&format!("Summary of earlier conversation:\n{summary}")),
+                );
+            }
+            let json = serde_json::to_string(&messages).unwrap_or_else(|_| "[]".to_string());
+            out.insert(card_id, json);
+        }
+
+        out
+    }
+
+    async fn append_message_ops(
+        &self,
+        card_id: &str,
+        ops: &[crate::collab::MessageOp],
+    ) -> Result<()> {
+        use crate::collab::MessageOp;
+
+        let mut tx = self.pool.begin().await?;
+        for op in ops {
+            match op {
+                MessageOp::Insert {
+                    id,
+                    after,
+                    role,
+                    message,
+                } => {
+                    let content = serde_json::to_string(message)?;
+                    let hash = crate::storage::content_hash(&content);
+                    sqlx::query!(
+                        "INSERT INTO message_body (hash, content) VALUES ($1, $2) ON CONFLICT (hash) DO NOTHING",
+                        hash,
+                        content
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                    let after_replica = after.as_ref().map(|a| a.replica_id.clone());
+                    let after_clock = after.as_ref().map(|a| a.clock as i64);
+                    let clock = id.clock as i64;
+                    sqlx::query!(
+                        "INSERT INTO assistant_message_op
+                         (card_id, replica_id, clock, kind, after_replica, after_clock, role, hash)
+                         VALUES ($1, $2, $3, 'insert', $4, $5, $6, $7)
+                         ON CONFLICT (card_id, replica_id, clock) DO NOTHING",
+                        card_id,
+                        id.replica_id,
+                        clock,
+                        after_replica,
+                        after_clock,
+                        role,
+                        hash
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                MessageOp::Remove { id } => {
+                    let clock = id.clock as i64;
+                    sqlx::query!(
+                        "INSERT INTO assistant_message_op (card_id, replica_id, clock, kind)
+                         VALUES ($1, $2, $3, 'remove')
+                         ON CONFLICT (card_id, replica_id, clock) DO NOTHING",
+                        card_id,
+                        id.replica_id,
+                        clock
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            }
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn message_ops_since(
+        &self,
+        card_id: &str,
+        version_vector: &crate::collab::VersionVector,
+    ) -> Result<Vec<crate::collab::MessageOp>> {
+        use crate::collab::{MessageOp, MessageOpId};
+
+        let rows = sqlx::query!(
+            r#"SELECT amo.replica_id, amo.clock, amo.kind, amo.after_replica, amo.after_clock, amo.role, mb.content
+               FROM assistant_message_op amo
+               LEFT JOIN message_body mb ON mb.hash = amo.hash
+               WHERE amo.card_id = $1
+               ORDER BY amo.clock, amo.replica_id"#,
+            card_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut ops = Vec::with_capacity(rows.len());
+        for row in rows {
+            let clock = row.clock as u64;
+            if version_vector.get(&row.replica_id).copied().unwrap_or(0) >= clock {
+                continue;
+            }
+            let id = MessageOpId {
+                replica_id: row.replica_id,
+                clock,
+            };
+            if row.kind == "remove" {
+                ops.push(MessageOp::Remove { id });
+                continue;
+            }
+            let after = row.after_replica.map(|replica_id| MessageOpId {
+                replica_id,
+                clock: row.after_clock.unwrap_or(0) as u64,
+            });
+            let message: Message = serde_json::from_str(&row.content.unwrap_or_default())?;
+            ops.push(MessageOp::Insert {
+                id,
+                after,
+                role: row.role.unwrap_or_default(),
+                message,
+            });
+        }
+        Ok(ops)
+    }
+
+    async fn message_version_vector(&self, card_id: &str) -> Result<crate::collab::VersionVector> {
+        let rows = sqlx::query!(
+            "SELECT replica_id, MAX(clock) as max_clock FROM assistant_message_op WHERE card_id = $1 GROUP BY replica_id",
+            card_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|r| (r.replica_id, r.max_clock.unwrap_or(0) as u64))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl crate::storage::TaskStore for PostgresBackend {
+    async fn unfinished_tasks(&self) -> Vec<Task> {
+        let now = Utc::now();
+        sqlx::query!(
+            r#"SELECT id, kind, social_id, person_id, person_name, card_id, card_title, content,
+                      message_id, state, created_at, updated_at, complexity
+               FROM tasks
+               WHERE (state = $1 OR state = $2 OR state = $3) AND run_at <= $4
+               ORDER BY run_at"#,
+            TaskState::Created as i32,
+            TaskState::Postponed as i32,
+            TaskState::Failed as i32,
+            now
+        )
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|record| Task {
+            id: record.id,
+            kind: match record.kind.as_str() {
+                "follow_chat" => TaskKind::FollowChat {
+                    card_id: record.card_id.unwrap_or_default(),
+                    card_title: record.card_title.unwrap_or_default(),
+                    content: record.content.unwrap_or_default(),
+                    message_id: record.message_id.unwrap_or_default(),
+                },
+                "memory_mantainance" => TaskKind::MemoryMantainance,
+                "sleep" => TaskKind::Sleep,
+                "assistant_chat" => TaskKind::AssistantChat {
+                    card_id: record.card_id.unwrap_or_default(),
+                    message_id: record.message_id.unwrap_or_default(),
+                    content: record.content.unwrap_or_default(),
+                },
+                _ => unreachable!(),
+            },
+            state: TaskState::from_i64(record.state as i64),
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+            complexity: record.complexity as u32,
+            cancel_token: tokio_util::sync::CancellationToken::new(),
+        })
+        .collect()
+    }
+
+    async fn reschedule_task_with_backoff(
+        &self,
+        task_id: i64,
+        err: &str,
+        retryable: bool,
+    ) -> Result<()> {
+        let record = sqlx::query!(
+            "SELECT attempts, max_attempts FROM tasks WHERE id = $1",
+            task_id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        let attempts = record.attempts + 1;
+
+        if retryable && attempts < record.max_attempts {
+            let backoff =
+                (RETRY_BASE_BACKOFF * 2i32.pow((attempts - 1) as u32)).min(RETRY_MAX_BACKOFF);
+            let jitter = chrono::Duration::milliseconds(rand::random::<u64>() as i64 % 1000);
+            let run_at = Utc::now() + backoff + jitter;
+            sqlx::query!(
+                "UPDATE tasks SET attempts = $1, state = $2, run_at = $3, last_error = $4 WHERE id = $5",
+                attempts,
+                TaskState::Failed as i32,
+                run_at,
+                err,
+                task_id
+            )
+            .execute(&self.pool)
+            .await?;
+            tracing::warn!(task_id, attempts, %run_at, err, "Failed task scheduled for retry");
+        } else {
+            sqlx::query!(
+                "UPDATE tasks SET attempts = $1, state = $2, last_error = $3 WHERE id = $4",
+                attempts,
+                TaskState::DeadLettered as i32,
+                err,
+                task_id
+            )
+            .execute(&self.pool)
+            .await?;
+            tracing::error!(task_id, attempts, err, retryable, "Dead-lettered task after exhausting retries");
+        }
+        Ok(())
+    }
+
+    async fn touch_task_heartbeat(&self, task_id: i64) -> Result<()> {
+        sqlx::query!(
+            "UPDATE tasks SET heartbeat_at = now() WHERE id = $1",
+            task_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn reclaim_stale_tasks(&self, stale_after: chrono::Duration) -> Result<Vec<Task>> {
+        let cutoff = Utc::now() - stale_after;
+        let mut tx = self.pool.begin().await?;
+        let stale = sqlx::query!(
+            "SELECT id FROM tasks WHERE state = $1 AND (heartbeat_at IS NULL OR heartbeat_at < $2)",
+            TaskState::Running as i32,
+            cutoff
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        for record in &stale {
+            sqlx::query!(
+                "UPDATE tasks SET state = $1 WHERE id = $2",
+                TaskState::Postponed as i32,
+                record.id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        if !stale.is_empty() {
+            tracing::warn!(count = stale.len(), "Reclaimed stale Running tasks");
+        }
+        let mut reclaimed = Vec::with_capacity(stale.len());
+        for record in stale {
+            reclaimed.push(self.task_by_id(record.id).await?);
+        }
+        Ok(reclaimed)
+    }
+
+    async fn task_messages(&self, task_id: i64) -> Result<Vec<Message>> {
+        let messages = sqlx::query!("SELECT content FROM task_message WHERE task_id = $1", task_id)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|m| serde_json::from_str(&m.content).unwrap())
+            .collect();
+        Ok(messages)
+    }
+
+    async fn add_task(&self, task: &Task) -> Result<i64> {
+        let (task_kind, card_id, card_title, content, message_id) = match &task.kind {
+            TaskKind::FollowChat {
+                card_id,
+                card_title,
+                content,
+                message_id,
+            } => (
+                "follow_chat",
+                Some(card_id.as_str()),
+                Some(card_title.as_str()),
+                Some(content.as_str()),
+                Some(message_id.as_str()),
+            ),
+            TaskKind::MemoryMantainance => ("memory_mantainance", None, None, None, None),
+            TaskKind::Sleep => ("sleep", None, None, None, None),
+            TaskKind::AssistantTask { content, .. } => {
+                ("sleep", None, None, Some(content.as_str()), None)
+            }
+            TaskKind::AssistantChat {
+                card_id,
+                message_id,
+                content,
+            } => (
+                "assistant_chat",
+                Some(card_id.as_str()),
+                None,
+                Some(content.as_str()),
+                Some(message_id.as_str()),
+            ),
+        };
+        let record = sqlx::query!(
+            "INSERT INTO tasks (kind, card_id, card_title, content, message_id) VALUES ($1, $2, $3, $4, $5) RETURNING id",
+            task_kind,
+            card_id,
+            card_title,
+            content,
+            message_id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(record.id)
+    }
+
+    async fn add_task_message(&self, task: &Task, message: Message) -> Result<Message> {
+        let json_message = serde_json::to_string(&message)?;
+        sqlx::query!(
+            "INSERT INTO task_message (task_id, content) VALUES ($1, $2)",
+            task.id,
+            json_message
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(message)
+    }
+
+    async fn update_task_messages(&self, task_id: i64, messages: &[Message]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!("DELETE FROM task_message WHERE task_id = $1", task_id)
+            .execute(&mut *tx)
+            .await?;
+        for message in messages {
+            let json_message = serde_json::to_string(&message)?;
+            sqlx::query!(
+                "INSERT INTO task_message (task_id, content) VALUES ($1, $2)",
+                task_id,
+                json_message
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn set_task_state(&self, task_id: i64, state: TaskState) -> Result<()> {
+        sqlx::query!(
+            "UPDATE tasks SET state = $1 WHERE id = $2",
+            state as i32,
+            task_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn set_task_complexity(&self, task_id: i64, complexity: u32) -> Result<()> {
+        sqlx::query!(
+            "UPDATE tasks SET complexity = $1 WHERE id = $2",
+            complexity as i32,
+            task_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_old_tasks(&self, expire_date: DateTime<Utc>) -> Result<()> {
+        tracing::info!(%expire_date, "Delete old tasks");
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            "DELETE FROM task_message WHERE task_id IN (SELECT id FROM tasks WHERE (state = $1 OR state = $2) AND updated_at < $3)",
+            TaskState::Completed as i32,
+            TaskState::Cancelled as i32,
+            expire_date
+        )
+        .execute(&mut *tx)
+        .await?;
+        let count = sqlx::query!(
+            "DELETE FROM tasks WHERE (state = $1 OR state = $2) AND updated_at < $3",
+            TaskState::Completed as i32,
+            TaskState::Cancelled as i32,
+            expire_date
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        tx.commit().await?;
+        tracing::info!(%count, "Deleted tasks");
+        // Postgres reclaims space via autovacuum; no blocking VACUUM needed here.
+        Ok(())
+    }
+
+    async fn record_task_fingerprint(&self, fingerprint: &str) -> Result<()> {
+        let now = Utc::now();
+        sqlx::query!(
+            "INSERT INTO task_fingerprint (fingerprint, seen_at, hits) VALUES ($1, $2, 1)
+             ON CONFLICT (fingerprint) DO UPDATE SET seen_at = excluded.seen_at, hits = task_fingerprint.hits + 1",
+            fingerprint,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn seen_task_fingerprint(
+        &self,
+        fingerprint: &str,
+        window: chrono::Duration,
+    ) -> Result<bool> {
+        let cutoff = Utc::now() - window;
+        let row = sqlx::query!(
+            "SELECT 1 as present FROM task_fingerprint WHERE fingerprint = $1 AND seen_at >= $2",
+            fingerprint,
+            cutoff
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.is_some())
+    }
+
+    async fn deduped_task_count(&self) -> Result<i64> {
+        let row = sqlx::query!(
+            "SELECT COALESCE(SUM(hits - 1), 0) as count FROM task_fingerprint WHERE hits > 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.count.unwrap_or(0))
+    }
+}
+
+impl PostgresBackend {
+    async fn task_by_id(&self, id: i64) -> Result<Task> {
+        let record = sqlx::query!(
+            r#"SELECT id, kind, card_id, card_title, content, message_id, state, created_at,
+                      updated_at, complexity FROM tasks WHERE id = $1"#,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(Task {
+            id: record.id,
+            kind: match record.kind.as_str() {
+                "follow_chat" => TaskKind::FollowChat {
+                    card_id: record.card_id.unwrap_or_default(),
+                    card_title: record.card_title.unwrap_or_default(),
+                    content: record.content.unwrap_or_default(),
+                    message_id: record.message_id.unwrap_or_default(),
+                },
+                "memory_mantainance" => TaskKind::MemoryMantainance,
+                "sleep" => TaskKind::Sleep,
+                "assistant_chat" => TaskKind::AssistantChat {
+                    card_id: record.card_id.unwrap_or_default(),
+                    message_id: record.message_id.unwrap_or_default(),
+                    content: record.content.unwrap_or_default(),
+                },
+                _ => unreachable!(),
+            },
+            state: TaskState::from_i64(record.state as i64),
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+            complexity: record.complexity as u32,
+            cancel_token: tokio_util::sync::CancellationToken::new(),
+        })
+    }
+}
+
+#[async_trait]
+impl crate::storage::ScheduleStore for PostgresBackend {
+    async fn scheduled_tasks(&self) -> Vec<ScheduledAssistantTask> {
+        sqlx::query!("SELECT id, content, schedule, last_run_at, next_run_at FROM scheduled_tasks")
+            .fetch_all(&self.pool)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|record| {
+                let schedule = JobSchedule::new(&record.schedule).unwrap();
+                ScheduledAssistantTask {
+                    id: record.id,
+                    content: record.content,
+                    last_run_at: record.last_run_at,
+                    next_run_at: record.next_run_at.unwrap_or_else(|| schedule.upcoming()),
+                    schedule,
+                }
+            })
+            .collect()
+    }
+
+    async fn add_scheduled_task(
+        &self,
+        content: &str,
+        schedule: &str,
+    ) -> Result<ScheduledAssistantTask> {
+        let job_schedule = JobSchedule::new(schedule)?;
+        let next_run_at = job_schedule.upcoming();
+        let record = sqlx::query!(
+            "INSERT INTO scheduled_tasks (content, schedule, next_run_at) VALUES ($1, $2, $3) RETURNING id",
+            content,
+            schedule,
+            next_run_at
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(ScheduledAssistantTask {
+            id: record.id,
+            content: content.to_string(),
+            last_run_at: None,
+            next_run_at,
+            schedule: job_schedule,
+        })
+    }
+
+    async fn delete_scheduled_task(&self, task_id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM scheduled_tasks WHERE id = $1", task_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn due_scheduled_tasks(&self, now: DateTime<Utc>) -> Vec<ScheduledAssistantTask> {
+        sqlx::query!(
+            "SELECT id, content, schedule, last_run_at, next_run_at FROM scheduled_tasks WHERE next_run_at <= $1",
+            now
+        )
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|record| {
+            let schedule = JobSchedule::new(&record.schedule).unwrap();
+            ScheduledAssistantTask {
+                id: record.id,
+                content: record.content,
+                last_run_at: record.last_run_at,
+                next_run_at: record.next_run_at.unwrap_or(now),
+                schedule,
+            }
+        })
+        .collect()
+    }
+
+    async fn mark_scheduled_task_ran(&self, task_id: i64, fired_at: DateTime<Utc>) -> Result<()> {
+        let schedule = sqlx::query!("SELECT schedule FROM scheduled_tasks WHERE id = $1", task_id)
+            .fetch_one(&self.pool)
+            .await?
+            .schedule;
+        let next_run_at = JobSchedule::new(&schedule)?.next_after(fired_at);
+        sqlx::query!(
+            "UPDATE scheduled_tasks SET last_run_at = $1, next_run_at = $2 WHERE id = $3",
+            fired_at,
+            next_run_at,
+            task_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl crate::storage::MemoryStore for PostgresBackend {
+    async fn mem_entity_by_name(
+        &self,
+        name: &str,
+        entity_type: MemoryEntityType,
+    ) -> Option<MemoryEntity> {
+        let record = sqlx::query!(
+            r#"SELECT id, name, category, entity_type as "entity_type: MemoryEntityType",
+                      importance, access_count, observations, version_vector, created_at, updated_at
+               FROM mem_entity WHERE lower(name) = lower($1) AND entity_type = $2"#,
+            name,
+            entity_type as _
+        )
+        .fetch_one(&self.pool)
+        .await
+        .ok()?;
+        let mut entity = MemoryEntity {
+            id: record.id,
+            name: record.name,
+            category: record.category,
+            entity_type: record.entity_type,
+            importance: record.importance,
+            access_count: record.access_count as u32,
+            relations: vec![],
+            observations: serde_json::from_str(&record.observations).unwrap_or_default(),
+            version_vector: serde_json::from_str(&record.version_vector).unwrap_or_default(),
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+        };
+        entity.relations = self.relations_by_entity(entity.id, &entity.name).await;
+        Some(entity)
+    }
+
+    async fn mem_entity(&self, id: i64) -> Result<MemoryEntity> {
+        let record = sqlx::query!(
+            r#"SELECT id, name, category, entity_type as "entity_type: MemoryEntityType",
+                      importance, access_count, observations, version_vector, created_at, updated_at
+               FROM mem_entity WHERE id = $1"#,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        let mut entity = MemoryEntity {
+            id: record.id,
+            name: record.name,
+            category: record.category,
+            entity_type: record.entity_type,
+            importance: record.importance,
+            access_count: record.access_count as u32,
+            relations: vec![],
+            observations: serde_json::from_str(&record.observations).unwrap_or_default(),
+            version_vector: serde_json::from_str(&record.version_vector).unwrap_or_default(),
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+        };
+        entity.relations = self.relations_by_entity(id, &entity.name).await;
+        Ok(entity)
+    }
+
+    async fn mem_update_entity(&self, entity: &MemoryEntity, writer: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        // `FOR UPDATE` holds a row lock for the life of `tx`, so a concurrent `mem_update_entity`
+        // on the same entity blocks on this SELECT instead of reading the same pre-merge row and
+        // clobbering this merge with its own UPDATE once both commit.
+        let current = sqlx::query!(
+            r#"SELECT id, name, category, entity_type as "entity_type: MemoryEntityType",
+                      importance, access_count, observations, version_vector, created_at, updated_at
+               FROM mem_entity WHERE id = $1 FOR UPDATE"#,
+            entity.id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        let current_entity = MemoryEntity {
+            id: current.id,
+            name: current.name,
+            category: current.category,
+            entity_type: current.entity_type,
+            importance: current.importance,
+            access_count: current.access_count as u32,
+            relations: vec![],
+            observations: serde_json::from_str(&current.observations).unwrap_or_default(),
+            version_vector: serde_json::from_str(&current.version_vector).unwrap_or_default(),
+            created_at: current.created_at,
+            updated_at: current.updated_at,
+        };
+
+        let mut merged = if current_entity.version_vector == entity.version_vector {
+            entity.clone()
+        } else {
+            tracing::debug!(
+                id = entity.id,
+                "Concurrent update detected for memory entity, merging siblings"
+            );
+            crate::memory::merge_entities(current_entity.clone(), entity.clone())
+        };
+        merged.version_vector =
+            crate::memory::merge_version_vectors(&current_entity.version_vector, &entity.version_vector);
+        crate::memory::bump_version_vector(&mut merged.version_vector, writer);
+
+        let observations = serde_json::to_string(&merged.observations).unwrap();
+        let version_vector = serde_json::to_string(&merged.version_vector).unwrap();
+        let embedding = Vector::from(self.create_entity_embedding(&merged).await?);
+
+        sqlx::query!(
+            r#"UPDATE mem_entity SET name = $1, entity_type = $2, category = $3, importance = $4,
+                      access_count = $5, observations = $6, embedding = $7, version_vector = $8, updated_at = $9
+               WHERE id = $10"#,
+            merged.name,
+            merged.entity_type.clone() as _,
+            merged.category,
+            merged.importance,
+            merged.access_count as i32,
+            observations,
+            embedding as _,
+            version_vector,
+            merged.updated_at,
+            merged.id
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        self.mem_update_relations(merged.id, &merged.relations).await?;
+        Ok(())
+    }
+
+    async fn mem_update_entity_importance(&self, id: i64, importance: f32) -> Result<()> {
+        sqlx::query!(
+            "UPDATE mem_entity SET importance = $1 WHERE id = $2",
+            importance,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn mem_add_entity(&self, entity: &MemoryEntity, writer: &str) -> Result<()> {
+        let observations = serde_json::to_string(&entity.observations).unwrap();
+        let mut version_vector = HashMap::new();
+        crate::memory::bump_version_vector(&mut version_vector, writer);
+        let version_vector = serde_json::to_string(&version_vector).unwrap();
+        let embedding = Vector::from(self.create_entity_embedding(entity).await?);
+
+        let record = sqlx::query!(
+            r#"INSERT INTO mem_entity (name, entity_type, category, importance, access_count, observations, version_vector, embedding)
+               VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id"#,
+            entity.name,
+            entity.entity_type.clone() as _,
+            entity.category,
+            entity.importance,
+            entity.access_count as i32,
+            observations,
+            version_vector,
+            embedding as _,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        self.mem_update_relations(record.id, &entity.relations).await?;
+        Ok(())
+    }
+
+    async fn mem_last_entities(&self, limit: u16) -> Result<Vec<MemoryEntity>> {
+        let records = sqlx::query!(
+            r#"SELECT id, name, category, entity_type as "entity_type: MemoryEntityType",
+                      importance, access_count, observations, version_vector, created_at, updated_at
+               FROM mem_entity ORDER BY importance DESC, updated_at DESC LIMIT $1"#,
+            limit as i64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut entities = Vec::with_capacity(records.len());
+        for record in records {
+            let mut entity = MemoryEntity {
+                id: record.id,
+                name: record.name,
+                category: record.category,
+                entity_type: record.entity_type,
+                importance: record.importance,
+                access_count: record.access_count as u32,
+                relations: vec![],
+                observations: serde_json::from_str(&record.observations).unwrap_or_default(),
+                version_vector: serde_json::from_str(&record.version_vector).unwrap_or_default(),
+                created_at: record.created_at,
+                updated_at: record.updated_at,
+            };
+            entity.relations = self.relations_by_entity(entity.id, &entity.name).await;
+            entities.push(entity);
+        }
+        Ok(entities)
+    }
+
+    async fn mem_entities_ids_for_consolidation(&self, threshold: f32) -> Result<Vec<i64>> {
+        let ids = sqlx::query!(
+            "SELECT id FROM mem_entity WHERE importance >= $1 AND entity_type = 0 ORDER BY updated_at DESC LIMIT 10000",
+            threshold
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|record| record.id)
+        .collect();
+        Ok(ids)
+    }
+
+    async fn mem_relevant_entities(
+        &self,
+        limit: u16,
+        query: &str,
+        entity_type: MemoryEntityType,
+    ) -> Result<Vec<MemoryEntity>> {
+        let query_embedding = match self.create_embedding(query).await {
+            Ok(embedding) => Vector::from(embedding),
+            Err(err) => {
+                tracing::warn!(
+                    ?err,
+                    "Embedding provider unavailable, falling back to lexical memory search"
+                );
+                return self
+                    .mem_relevant_entities_lexical(limit, query, entity_type)
+                    .await;
+            }
+        };
+
+        let records = sqlx::query!(
+            r#"SELECT id, name, category, entity_type as "entity_type: MemoryEntityType",
+                      importance, access_count, observations, version_vector, created_at, updated_at
+               FROM mem_entity
+               WHERE entity_type = $1
+               ORDER BY embedding <=> $2, importance DESC, updated_at DESC
+               LIMIT $3"#,
+            entity_type as _,
+            query_embedding as _,
+            limit as i64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut entities = Vec::with_capacity(records.len());
+        for record in records {
+            let mut entity = MemoryEntity {
+                id: record.id,
+                name: record.name,
+                category: record.category,
+                entity_type: record.entity_type,
+                importance: record.importance,
+                access_count: record.access_count as u32,
+                relations: vec![],
+                observations: serde_json::from_str(&record.observations).unwrap_or_default(),
+                version_vector: serde_json::from_str(&record.version_vector).unwrap_or_default(),
+                created_at: record.created_at,
+                updated_at: record.updated_at,
+            };
+            entity.relations = self.relations_by_entity(entity.id, &entity.name).await;
+            entities.push(entity);
+        }
+        Ok(entities)
+    }
+
+    async fn mem_relevant_entities_scored(
+        &self,
+        limit: u16,
+        query: &str,
+        entity_type: MemoryEntityType,
+        min_similarity: f32,
+    ) -> Result<Vec<(MemoryEntity, f32)>> {
+        let query_embedding = match self.create_embedding(query).await {
+            Ok(embedding) => Vector::from(embedding),
+            Err(err) => {
+                tracing::warn!(
+                    ?err,
+                    "Embedding provider unavailable, falling back to lexical memory search"
+                );
+                return Ok(self
+                    .mem_relevant_entities_lexical(limit, query, entity_type)
+                    .await?
+                    .into_iter()
+                    .map(|entity| (entity, 1.0))
+                    .collect());
+            }
+        };
+
+        let records = sqlx::query!(
+            r#"SELECT id, name, category, entity_type as "entity_type: MemoryEntityType",
+                      importance, access_count, observations, version_vector, created_at, updated_at,
+                      embedding <=> $2 as "distance!"
+               FROM mem_entity
+               WHERE entity_type = $1
+               ORDER BY embedding <=> $2, importance DESC, updated_at DESC
+               LIMIT $3"#,
+            entity_type as _,
+            query_embedding as _,
+            limit as i64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut entries = Vec::with_capacity(records.len());
+        for record in records {
+            // pgvector's `<=>` is cosine *distance* (0 = identical); flip it to a "higher is
+            // better" similarity score, same convention as `kg_search_semantic`.
+            let score = 1.0 - record.distance as f32;
+            if score < min_similarity {
+                continue;
+            }
+            let mut entity = MemoryEntity {
+                id: record.id,
+                name: record.name,
+                category: record.category,
+                entity_type: record.entity_type,
+                importance: record.importance,
+                access_count: record.access_count as u32,
+                relations: vec![],
+                observations: serde_json::from_str(&record.observations).unwrap_or_default(),
+                version_vector: serde_json::from_str(&record.version_vector).unwrap_or_default(),
+                created_at: record.created_at,
+                updated_at: record.updated_at,
+            };
+            entity.relations = self.relations_by_entity(entity.id, &entity.name).await;
+            entries.push((entity, score));
+        }
+        Ok(entries)
+    }
+
+    async fn mem_get_entity_ids(&self) -> Result<Vec<i64>> {
+        let ids = sqlx::query!("SELECT id FROM mem_entity")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|record| record.id)
+            .collect();
+        Ok(ids)
+    }
+
+    async fn mem_delete_entity(&self, id: i64) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            "DELETE FROM mem_relation WHERE from_id = $1 OR to_id = $1",
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!("DELETE FROM mem_entity WHERE id = $1", id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn mem_reembed_all(&self) -> Result<()> {
+        let records = sqlx::query!(
+            r#"SELECT id, name, category, entity_type as "entity_type: MemoryEntityType",
+                      importance, access_count, observations, version_vector, created_at, updated_at
+               FROM mem_entity"#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let entities = records
+            .into_iter()
+            .map(|record| MemoryEntity {
+                id: record.id,
+                name: record.name,
+                category: record.category,
+                entity_type: record.entity_type,
+                importance: record.importance,
+                access_count: record.access_count as u32,
+                relations: vec![],
+                observations: serde_json::from_str(&record.observations).unwrap_or_default(),
+                version_vector: serde_json::from_str(&record.version_vector).unwrap_or_default(),
+                created_at: record.created_at,
+                updated_at: record.updated_at,
+            })
+            .collect::<Vec<_>>();
+
+        for chunk in entities.chunks(REEMBED_BATCH_SIZE) {
+            let texts = chunk
+                .iter()
+                .map(Self::text_for_embedding)
+                .collect::<Vec<_>>();
+            let texts = texts.iter().map(String::as_str).collect::<Vec<_>>();
+            let embeddings = self.embedding_provider().await.embed_batch(&texts).await?;
+
+            let mut tx = self.pool.begin().await?;
+            for (entity, embedding) in chunk.iter().zip(embeddings) {
+                let embedding = Vector::from(embedding);
+                sqlx::query!(
+                    "UPDATE mem_entity SET embedding = $1 WHERE id = $2",
+                    embedding as _,
+                    entity.id
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+            tx.commit().await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl crate::storage::NoteStore for PostgresBackend {
+    async fn notes(&self) -> Result<Vec<crate::note::Note>> {
+        let notes = sqlx::query!("SELECT id, content, tags, mentions FROM notes")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|record| crate::note::Note {
+                id: record.id,
+                content: record.content,
+                tags: serde_json::from_str(&record.tags).unwrap_or_default(),
+                mentions: serde_json::from_str(&record.mentions).unwrap_or_default(),
+            })
+            .collect();
+        Ok(notes)
+    }
+
+    async fn add_note(&self, content: &str, tags: &[String], mentions: &[String]) -> Result<i64> {
+        let tags = serde_json::to_string(tags)?;
+        let mentions = serde_json::to_string(mentions)?;
+        let record = sqlx::query!(
+            "INSERT INTO notes (content, tags, mentions) VALUES ($1, $2, $3) RETURNING id",
+            content,
+            tags,
+            mentions
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(record.id)
+    }
+
+    async fn delete_notes(&self, ids: Vec<i64>) -> Result<()> {
+        sqlx::query!("DELETE FROM notes WHERE id = ANY($1)", &ids)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn notes_search(
+        &self,
+        tag: Option<&str>,
+        query: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<crate::note::Note>> {
+        let tag_pattern = tag.map(|tag| format!("%\"{}\"%", tag.replace(['%', '_', '"'], "")));
+        let query_pattern = query.map(|query| format!("%{}%", query.replace(['%', '_'], "")));
+        let rows = sqlx::query!(
+            "SELECT id, content, tags, mentions FROM notes
+             WHERE ($1::TEXT IS NULL OR tags ILIKE $1)
+               AND ($2::TEXT IS NULL OR content ILIKE $2)
+             ORDER BY id DESC
+             LIMIT $3 OFFSET $4",
+            tag_pattern,
+            query_pattern,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|record| crate::note::Note {
+                id: record.id,
+                content: record.content,
+                tags: serde_json::from_str(&record.tags).unwrap_or_default(),
+                mentions: serde_json::from_str(&record.mentions).unwrap_or_default(),
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl crate::storage::WorkerStore for PostgresBackend {
+    async fn paused_worker_ids(&self) -> Result<Vec<String>> {
+        Ok(sqlx::query!("SELECT id FROM worker_pause_state")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|record| record.id)
+            .collect())
+    }
+
+    async fn set_worker_paused(&self, id: &str, paused: bool) -> Result<()> {
+        if paused {
+            sqlx::query!(
+                "INSERT INTO worker_pause_state (id) VALUES ($1) ON CONFLICT (id) DO NOTHING",
+                id
+            )
+            .execute(&self.pool)
+            .await?;
+        } else {
+            sqlx::query!("DELETE FROM worker_pause_state WHERE id = $1", id)
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl crate::storage::ConfigOverrideStore for PostgresBackend {
+    async fn config_overrides(&self) -> Result<HashMap<String, String>> {
+        Ok(sqlx::query!("SELECT key, value FROM config_override")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|record| (record.key, record.value))
+            .collect())
+    }
+
+    async fn set_config_override(&self, key: &str, value: &str) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO config_override (key, value) VALUES ($1, $2)
+             ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+            key,
+            value
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn reload_embedding_provider(&self) -> Result<bool> {
+        let overrides = self.config_overrides().await?;
+        let effective =
+            crate::storage::apply_embedding_overrides(self.embedding_config.clone(), &overrides);
+        let fingerprint = effective.fingerprint();
+
+        let mut state = self.embedding_state.write().await;
+        if state.fingerprint == fingerprint {
+            return Ok(false);
+        }
+        state.provider = crate::embeddings::build_embedding_provider(&effective)?;
+        state.fingerprint = fingerprint;
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl crate::storage::PendingActionStore for PostgresBackend {
+    async fn add_pending_action(&self, call_id: &str, tool_name: &str, arguments: &str) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO pending_action (call_id, tool_name, arguments) VALUES ($1, $2, $3)",
+            call_id,
+            tool_name,
+            arguments
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn pending_action(&self, call_id: &str) -> Result<Option<crate::storage::PendingAction>> {
+        Ok(sqlx::query!(
+            "SELECT call_id, tool_name, arguments, status FROM pending_action WHERE call_id = $1",
+            call_id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|record| crate::storage::PendingAction {
+            call_id: record.call_id,
+            tool_name: record.tool_name,
+            arguments: record.arguments,
+            status: crate::storage::PendingActionStatus::parse(&record.status),
+        }))
+    }
+
+    async fn set_pending_action_status(
+        &self,
+        call_id: &str,
+        status: crate::storage::PendingActionStatus,
+    ) -> Result<()> {
+        let status = status.as_str();
+        sqlx::query!(
+            "UPDATE pending_action SET status = $1 WHERE call_id = $2",
+            status,
+            call_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+impl PostgresBackend {
+    /// Inserts `texts` as new observations of `entity_id`, skipping any exact text already
+    /// recorded for that entity. The not-yet-recorded texts are embedded in a single
+    /// `embed_batch` call rather than one request per text. Returns the texts actually added.
+    async fn kg_insert_observations_if_new(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        entity_id: i64,
+        texts: &[String],
+    ) -> Result<Vec<String>> {
+        let mut new_texts = Vec::new();
+        for text in texts {
+            let exists = sqlx::query!(
+                "SELECT id FROM kg_observation WHERE entity_id = $1 AND text = $2",
+                entity_id,
+                text
+            )
+            .fetch_optional(&mut **tx)
+            .await?
+            .is_some();
+            if !exists {
+                new_texts.push(text.clone());
+            }
+        }
+        if new_texts.is_empty() {
+            return Ok(new_texts);
+        }
+
+        let embeddings = self
+            .create_embeddings(&new_texts.iter().map(String::as_str).collect::<Vec<_>>())
+            .await?;
+        for (text, embedding) in new_texts.iter().zip(embeddings) {
+            let embedding = Vector::from(embedding);
+            sqlx::query!(
+                "INSERT INTO kg_observation (entity_id, text, embedding) VALUES ($1, $2, $3)",
+                entity_id,
+                text,
+                embedding as _
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+        Ok(new_texts)
+    }
+
+    async fn kg_load_entity(&self, id: i64, name: String, entity_type: String) -> Result<Entity> {
+        let observations = sqlx::query!(
+            "SELECT text FROM kg_observation WHERE entity_id = $1 ORDER BY id",
+            id
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|record| record.text)
+        .collect();
+        Ok(Entity {
+            id,
+            name,
+            entity_type,
+            observations,
+            score: None,
+        })
+    }
+
+    /// Every relation whose `from` and `to` are both in `names`.
+    async fn kg_relations_among(&self, names: &[&str]) -> Result<Vec<Relation>> {
+        let mut qb: sqlx::QueryBuilder<sqlx::Postgres> = sqlx::QueryBuilder::new(
+            "SELECT from_name, to_name, relation_type FROM kg_relation WHERE from_name IN (",
+        );
+        {
+            let mut separated = qb.separated(", ");
+            for name in names {
+                separated.push_bind(*name);
+            }
+            separated.push_unseparated(")");
+        }
+        qb.push(" AND to_name IN (");
+        {
+            let mut separated = qb.separated(", ");
+            for name in names {
+                separated.push_bind(*name);
+            }
+            separated.push_unseparated(")");
+        }
+        let rows = qb.build().fetch_all(&self.pool).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| Relation {
+                from: row.get("from_name"),
+                to: row.get("to_name"),
+                relation_type: row.get("relation_type"),
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl crate::storage::KnowledgeGraphStore for PostgresBackend {
+    async fn kg_add_entities(&self, entities: &[Entity]) -> Result<Vec<Entity>> {
+        let mut created = Vec::new();
+        for entity in entities {
+            let exists = sqlx::query!("SELECT id FROM kg_entity WHERE name = $1", entity.name)
+                .fetch_optional(&self.pool)
+                .await?
+                .is_some();
+            if exists {
+                continue;
+            }
+            let mut tx = self.pool.begin().await?;
+            let entity_id = sqlx::query!(
+                "INSERT INTO kg_entity (name, entity_type) VALUES ($1, $2) RETURNING id",
+                entity.name,
+                entity.entity_type
+            )
+            .fetch_one(&mut *tx)
+            .await?
+            .id;
+            self.kg_insert_observations_if_new(&mut tx, entity_id, &entity.observations)
+                .await?;
+            tx.commit().await?;
+            created.push(Entity {
+                id: entity_id,
+                ..entity.clone()
+            });
+        }
+        Ok(created)
+    }
+
+    async fn kg_add_relations(&self, relations: &[Relation]) -> Result<Vec<Relation>> {
+        let mut created = Vec::new();
+        for relation in relations {
+            let exists = sqlx::query!(
+                "SELECT 1 as present FROM kg_relation WHERE from_name = $1 AND to_name = $2 AND relation_type = $3",
+                relation.from,
+                relation.to,
+                relation.relation_type
+            )
+            .fetch_optional(&self.pool)
+            .await?
+            .is_some();
+            if exists {
+                continue;
+            }
+            sqlx::query!(
+                "INSERT INTO kg_relation (from_name, to_name, relation_type) VALUES ($1, $2, $3)",
+                relation.from,
+                relation.to,
+                relation.relation_type
+            )
+            .execute(&self.pool)
+            .await?;
+            created.push(relation.clone());
+        }
+        Ok(created)
+    }
+
+    async fn kg_add_observations(&self, observations: &[Observation]) -> Result<Vec<Observation>> {
+        let mut added = Vec::new();
+        for observation in observations {
+            let Some(entity_id) = sqlx::query!(
+                "SELECT id FROM kg_entity WHERE name = $1",
+                observation.entity_name
+            )
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|record| record.id) else {
+                continue;
+            };
+            let mut tx = self.pool.begin().await?;
+            let added_texts = self
+                .kg_insert_observations_if_new(&mut tx, entity_id, &observation.observations)
+                .await?;
+            tx.commit().await?;
+            if !added_texts.is_empty() {
+                added.push(Observation {
+                    entity_name: observation.entity_name.clone(),
+                    observations: added_texts,
+                });
+            }
+        }
+        Ok(added)
+    }
+
+    async fn kg_delete_entities(&self, names: &[String]) -> Result<()> {
+        for name in names {
+            let Some(entity_id) = sqlx::query!("SELECT id FROM kg_entity WHERE name = $1", name)
+                .fetch_optional(&self.pool)
+                .await?
+                .map(|record| record.id)
+            else {
+                continue;
+            };
+            sqlx::query!("DELETE FROM kg_observation WHERE entity_id = $1", entity_id)
+                .execute(&self.pool)
+                .await?;
+            sqlx::query!(
+                "DELETE FROM kg_relation WHERE from_name = $1 OR to_name = $1",
+                name
+            )
+            .execute(&self.pool)
+            .await?;
+            sqlx::query!("DELETE FROM kg_entity WHERE id = $1", entity_id)
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn kg_delete_observations(&self, deletions: &[Observation]) -> Result<()> {
+        for deletion in deletions {
+            let Some(entity_id) = sqlx::query!(
+                "SELECT id FROM kg_entity WHERE name = $1",
+                deletion.entity_name
+            )
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|record| record.id) else {
+                continue;
+            };
+            for text in &deletion.observations {
+                sqlx::query!(
+                    "DELETE FROM kg_observation WHERE entity_id = $1 AND text = $2",
+                    entity_id,
+                    text
+                )
+                .execute(&self.pool)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn kg_delete_relations(&self, relations: &[Relation]) -> Result<()> {
+        for relation in relations {
+            sqlx::query!(
+                "DELETE FROM kg_relation WHERE from_name = $1 AND to_name = $2 AND relation_type = $3",
+                relation.from,
+                relation.to,
+                relation.relation_type
+            )
+            .execute(&self.pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn kg_search_nodes(&self, query: Option<&str>) -> Result<KnowledgeGraph> {
+        let entity_rows = match query {
+            Some(query) => {
+                let pattern = format!("%{}%", query.replace(['%', '_'], ""));
+                sqlx::query!(
+                    r#"
+                    SELECT DISTINCT e.id, e.name, e.entity_type
+                    FROM kg_entity e
+                    LEFT JOIN kg_observation o ON o.entity_id = e.id
+                    WHERE e.name ILIKE $1 OR e.entity_type ILIKE $1 OR o.text ILIKE $1
+                    ORDER BY e.name
+                    "#,
+                    pattern
+                )
+                .fetch_all(&self.pool)
+                .await?
+            }
+            None => sqlx::query!("SELECT id, name, entity_type FROM kg_entity ORDER BY name")
+                .fetch_all(&self.pool)
+                .await?,
+        };
+        let mut entities = Vec::with_capacity(entity_rows.len());
+        for row in entity_rows {
+            entities.push(self.kg_load_entity(row.id, row.name, row.entity_type).await?);
+        }
+        let names: Vec<&str> = entities.iter().map(|entity| entity.name.as_str()).collect();
+        let relations = if names.is_empty() {
+            vec![]
+        } else {
+            self.kg_relations_among(&names).await?
+        };
+        Ok(KnowledgeGraph { entities, relations })
+    }
+
+    async fn kg_list_entities(&self, names: &[String]) -> Result<Vec<Entity>> {
+        if names.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut qb: sqlx::QueryBuilder<sqlx::Postgres> =
+            sqlx::QueryBuilder::new("SELECT id, name, entity_type FROM kg_entity WHERE name IN (");
+        {
+            let mut separated = qb.separated(", ");
+            for name in names {
+                separated.push_bind(name);
+            }
+            separated.push_unseparated(")");
+        }
+        let rows = qb.build().fetch_all(&self.pool).await?;
+        let mut entities = Vec::with_capacity(rows.len());
+        for row in rows {
+            let id: i64 = row.get("id");
+            let name: String = row.get("name");
+            let entity_type: String = row.get("entity_type");
+            entities.push(self.kg_load_entity(id, name, entity_type).await?);
+        }
+        Ok(entities)
+    }
+
+    async fn kg_relations_touching(&self, names: &[String]) -> Result<Vec<Relation>> {
+        if names.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut qb: sqlx::QueryBuilder<sqlx::Postgres> = sqlx::QueryBuilder::new(
+            "SELECT DISTINCT from_name, to_name, relation_type FROM kg_relation WHERE from_name IN (",
+        );
+        {
+            let mut separated = qb.separated(", ");
+            for name in names {
+                separated.push_bind(name);
+            }
+            separated.push_unseparated(")");
+        }
+        qb.push(" OR to_name IN (");
+        {
+            let mut separated = qb.separated(", ");
+            for name in names {
+                separated.push_bind(name);
+            }
+            separated.push_unseparated(")");
+        }
+        let rows = qb.build().fetch_all(&self.pool).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| Relation {
+                from: row.get("from_name"),
+                to: row.get("to_name"),
+                relation_type: row.get("relation_type"),
+            })
+            .collect())
+    }
+
+    async fn kg_search_semantic(&self, query: &str, k: usize) -> Result<Vec<Entity>> {
+        let query_embedding = Vector::from(self.create_embedding(query).await?);
+        let records = sqlx::query!(
+            r#"
+            SELECT e.id, e.name, e.entity_type, MIN(o.embedding <=> $1) as "best_distance!"
+            FROM kg_observation o
+            JOIN kg_entity e ON e.id = o.entity_id
+            WHERE o.embedding IS NOT NULL
+            GROUP BY e.id
+            ORDER BY best_distance ASC
+            LIMIT $2
+            "#,
+            query_embedding as _,
+            k as i64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut entities = Vec::with_capacity(records.len());
+        for record in records {
+            let mut entity = self
+                .kg_load_entity(record.id, record.name, record.entity_type)
+                .await?;
+            // pgvector's `<=>` is cosine *distance* (0 = identical); flip it to a "higher is
+            // better" similarity score so callers don't need to know the underlying metric.
+            entity.score = Some(1.0 - record.best_distance as f32);
+            entities.push(entity);
+        }
+        Ok(entities)
+    }
+}
+
+#[async_trait]
+impl crate::storage::MultiplexerStore for PostgresBackend {
+    async fn multiplexer_offset(&self) -> Result<Option<i64>> {
+        Ok(
+            sqlx::query!("SELECT offset_value FROM multiplexer_offset WHERE id = 0")
+                .fetch_optional(&self.pool)
+                .await?
+                .map(|record| record.offset_value),
+        )
+    }
+
+    async fn set_multiplexer_offset(&self, offset: i64) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO multiplexer_offset (id, offset_value) VALUES (0, $1)
+             ON CONFLICT (id) DO UPDATE SET offset_value = excluded.offset_value",
+            offset
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn journal_card_message(&self, card_id: &str, message_id: &str, payload: &str) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO multiplexer_journal (card_id, message_id, payload) VALUES ($1, $2, $3)
+             ON CONFLICT (card_id, message_id) DO UPDATE SET payload = excluded.payload",
+            card_id,
+            message_id,
+            payload
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn clear_card_journal(&self, card_id: &str) -> Result<()> {
+        sqlx::query!("DELETE FROM multiplexer_journal WHERE card_id = $1", card_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn journaled_messages(&self) -> Result<Vec<(String, String, String)>> {
+        Ok(sqlx::query!(
+            "SELECT card_id, message_id, payload FROM multiplexer_journal ORDER BY created_at"
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|record| (record.card_id, record.message_id, record.payload))
+        .collect())
+    }
+
+    async fn follow_state(&self, workspace_id: &str, group_id: &str) -> Result<Option<String>> {
+        Ok(sqlx::query!(
+            "SELECT payload FROM multiplexer_follow_state WHERE workspace_id = $1 AND group_id = $2",
+            workspace_id,
+            group_id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|record| record.payload))
+    }
+
+    async fn set_follow_state(&self, workspace_id: &str, group_id: &str, payload: &str) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO multiplexer_follow_state (workspace_id, group_id, payload) VALUES ($1, $2, $3)
+             ON CONFLICT (workspace_id, group_id) DO UPDATE SET payload = excluded.payload, updated_at = now()",
+            workspace_id,
+            group_id,
+            payload
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,2481 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{Row, SqliteConnection};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use zerocopy::IntoBytes;
+
+use crate::config::{EmbeddingProviderConfig, JobSchedule};
+use crate::embeddings::EmbeddingProvider;
+use crate::knowledge_graph::{Entity, KnowledgeGraph, Observation, Relation};
+use crate::memory::MemoryEntityType;
+use crate::storage::{EmbeddingHotState, Migration};
+use crate::task::{ScheduledAssistantTask, TaskState};
+use crate::{
+    config::Config,
+    memory::MemoryEntity,
+    task::{Task, TaskKind},
+    types::Message,
+};
+use sqlx::{SqlitePool, sqlite::SqliteConnectOptions, sqlite::SqlitePoolOptions};
+const RETRY_BASE_BACKOFF: chrono::Duration = chrono::Duration::seconds(30);
+const RETRY_MAX_BACKOFF: chrono::Duration = chrono::Duration::hours(1);
+/// How many entities to re-embed per `embed_batch` request in `mem_reembed_all`.
+const REEMBED_BATCH_SIZE: usize = 64;
+
+/// The crate's full schema, in order, as a flat list of migrations rather than a `./migrations`
+/// directory: each one runs at most once (tracked in `schema_migrations`), in its own transaction.
+/// The table-creating ones use `CREATE TABLE IF NOT EXISTS`; the `ALTER TABLE ... ADD COLUMN` ones
+/// rely on that tracking alone and fail if re-run. New columns/tables are added by appending a
+/// migration here, never by editing an old one.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: "0001_agent_state",
+        sql: "CREATE TABLE IF NOT EXISTS agent_state (balance INTEGER NOT NULL DEFAULT 0)",
+    },
+    Migration {
+        version: "0002_agent_state_seed",
+        sql: "INSERT INTO agent_state (balance) SELECT 0 WHERE NOT EXISTS (SELECT 1 FROM agent_state)",
+    },
+    Migration {
+        version: "0003_tasks",
+        sql: "CREATE TABLE IF NOT EXISTS tasks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            social_id TEXT,
+            person_id TEXT,
+            person_name TEXT,
+            card_id TEXT,
+            card_title TEXT,
+            content TEXT,
+            message_id TEXT,
+            state INTEGER NOT NULL DEFAULT 0,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            max_attempts INTEGER NOT NULL DEFAULT 5,
+            run_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            last_error TEXT,
+            heartbeat_at TIMESTAMP,
+            complexity INTEGER NOT NULL DEFAULT 0,
+            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+    },
+    Migration {
+        version: "0004_task_message",
+        sql: "CREATE TABLE IF NOT EXISTS task_message (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            task_id INTEGER NOT NULL REFERENCES tasks(id),
+            content TEXT NOT NULL
+        )",
+    },
+    Migration {
+        version: "0005_scheduled_tasks",
+        sql: "CREATE TABLE IF NOT EXISTS scheduled_tasks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            content TEXT NOT NULL,
+            schedule TEXT NOT NULL,
+            last_run_at TIMESTAMP,
+            next_run_at TIMESTAMP
+        )",
+    },
+    Migration {
+        version: "0006_mem_entity",
+        sql: "CREATE TABLE IF NOT EXISTS mem_entity (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            entity_type INTEGER NOT NULL,
+            category TEXT,
+            importance REAL NOT NULL DEFAULT 0,
+            access_count INTEGER NOT NULL DEFAULT 0,
+            observations TEXT NOT NULL DEFAULT '[]',
+            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+    },
+    Migration {
+        version: "0007_mem_relation",
+        sql: "CREATE TABLE IF NOT EXISTS mem_relation (
+            from_id INTEGER NOT NULL REFERENCES mem_entity(id),
+            to_id INTEGER NOT NULL REFERENCES mem_entity(id)
+        )",
+    },
+    Migration {
+        version: "0008_notes",
+        sql: "CREATE TABLE IF NOT EXISTS notes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            content TEXT NOT NULL
+        )",
+    },
+    Migration {
+        version: "0009_message_body",
+        sql: "CREATE TABLE IF NOT EXISTS message_body (
+            hash TEXT PRIMARY KEY,
+            content TEXT NOT NULL
+        )",
+    },
+    Migration {
+        version: "0010_assistant_message",
+        sql: "CREATE TABLE IF NOT EXISTS assistant_message (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            card_id TEXT NOT NULL,
+            seq INTEGER NOT NULL,
+            role TEXT NOT NULL,
+            hash TEXT NOT NULL REFERENCES message_body(hash),
+            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(card_id, seq)
+        )",
+    },
+    Migration {
+        version: "0011_assistant_summary",
+        sql: "CREATE TABLE IF NOT EXISTS assistant_summary (
+            card_id TEXT PRIMARY KEY,
+            from_seq INTEGER NOT NULL,
+            to_seq INTEGER NOT NULL,
+            summary TEXT NOT NULL
+        )",
+    },
+    Migration {
+        version: "0012_mem_entity_version_vector",
+        sql: "ALTER TABLE mem_entity ADD COLUMN version_vector TEXT NOT NULL DEFAULT '{}'",
+    },
+    Migration {
+        version: "0013_kg_entity",
+        sql: "CREATE TABLE IF NOT EXISTS kg_entity (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            entity_type TEXT NOT NULL
+        )",
+    },
+    Migration {
+        version: "0014_kg_observation",
+        sql: "CREATE TABLE IF NOT EXISTS kg_observation (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            entity_id INTEGER NOT NULL REFERENCES kg_entity(id),
+            text TEXT NOT NULL,
+            UNIQUE(entity_id, text)
+        )",
+    },
+    Migration {
+        version: "0015_kg_relation",
+        sql: "CREATE TABLE IF NOT EXISTS kg_relation (
+            from_name TEXT NOT NULL,
+            to_name TEXT NOT NULL,
+            relation_type TEXT NOT NULL,
+            UNIQUE(from_name, to_name, relation_type)
+        )",
+    },
+    Migration {
+        version: "0016_notes_tags",
+        sql: "ALTER TABLE notes ADD COLUMN tags TEXT NOT NULL DEFAULT '[]'",
+    },
+    Migration {
+        version: "0017_notes_mentions",
+        sql: "ALTER TABLE notes ADD COLUMN mentions TEXT NOT NULL DEFAULT '[]'",
+    },
+    Migration {
+        version: "0018_worker_pause_state",
+        sql: "CREATE TABLE IF NOT EXISTS worker_pause_state (id TEXT PRIMARY KEY)",
+    },
+    Migration {
+        version: "0019_assistant_message_op",
+        sql: "CREATE TABLE IF NOT EXISTS assistant_message_op (
+            card_id TEXT NOT NULL,
+            replica_id TEXT NOT NULL,
+            clock INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            after_replica TEXT,
+            after_clock INTEGER,
+            role TEXT,
+            hash TEXT REFERENCES message_body(hash),
+            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (card_id, replica_id, clock)
+        )",
+    },
+    Migration {
+        version: "0020_task_fingerprint",
+        sql: "CREATE TABLE IF NOT EXISTS task_fingerprint (
+            fingerprint TEXT PRIMARY KEY,
+            seen_at TIMESTAMP NOT NULL,
+            hits INTEGER NOT NULL DEFAULT 1
+        )",
+    },
+    Migration {
+        version: "0021_pending_action",
+        sql: "CREATE TABLE IF NOT EXISTS pending_action (
+            call_id TEXT PRIMARY KEY,
+            tool_name TEXT NOT NULL,
+            arguments TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+    },
+    Migration {
+        version: "0022_multiplexer_offset",
+        sql: "CREATE TABLE IF NOT EXISTS multiplexer_offset (
+            id INTEGER PRIMARY KEY CHECK (id = 0),
+            offset_value INTEGER NOT NULL
+        )",
+    },
+    Migration {
+        version: "0023_multiplexer_journal",
+        sql: "CREATE TABLE IF NOT EXISTS multiplexer_journal (
+            card_id TEXT NOT NULL,
+            message_id TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (card_id, message_id)
+        )",
+    },
+    Migration {
+        version: "0024_multiplexer_follow_state",
+        sql: "CREATE TABLE IF NOT EXISTS multiplexer_follow_state (
+            workspace_id TEXT NOT NULL,
+            group_id TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (workspace_id, group_id)
+        )",
+    },
+    Migration {
+        version: "0025_config_override",
+        sql: "CREATE TABLE IF NOT EXISTS config_override (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+    },
+];
+
+/// Applies every migration in `migrations` not yet recorded in `schema_migrations`, each in its
+/// own transaction, in order. Bails out on the first failure rather than silently skipping it, so
+/// a broken migration surfaces as a startup error instead of a missing table down the line.
+async fn run_migrations(pool: &SqlitePool, migrations: &[Migration]) -> Result<()> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version TEXT PRIMARY KEY,
+            applied_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    for migration in migrations {
+        let already_applied = sqlx::query(
+            "SELECT 1 as present FROM schema_migrations WHERE version = ?",
+        )
+        .bind(migration.version)
+        .fetch_optional(pool)
+        .await?
+        .is_some();
+        if already_applied {
+            continue;
+        }
+
+        let mut tx = pool.begin().await?;
+        sqlx::query(migration.sql)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("migration {} failed", migration.version))?;
+        sqlx::query("INSERT INTO schema_migrations (version) VALUES (?)")
+            .bind(migration.version)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        tracing::info!(version = migration.version, "Applied schema migration");
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct SqliteBackend {
+    /// Read pool: many connections, safe for concurrent reads under WAL.
+    pool: SqlitePool,
+    /// Write pool: capped at a single connection so all mutating queries (and `VACUUM`) are
+    /// naturally serialized instead of racing each other for SQLite's one writer lock.
+    write_pool: SqlitePool,
+    /// Base config the provider was last (re)built from, before any `config_override` overlay.
+    /// Stays fixed for the process lifetime; only `embedding_state`'s contents change on reload.
+    embedding_config: EmbeddingProviderConfig,
+    embedding_state: std::sync::Arc<tokio::sync::RwLock<EmbeddingHotState>>,
+}
+
+macro_rules! to_task {
+    ($record:expr) => {
+        Task {
+            id: $record.id.unwrap_or_default(),
+            kind: match $record.kind.as_str() {
+                "follow_chat" => TaskKind::FollowChat {
+                    card_id: $record.card_id.unwrap_or_default(),
+                    card_title: $record.card_title.unwrap_or_default(),
+                    content: $record.content.unwrap_or_default(),
+                    message_id: $record.message_id.unwrap_or_default(),
+                },
+                "memory_mantainance" => TaskKind::MemoryMantainance,
+                "sleep" => TaskKind::Sleep,
+                "assistant_chat" => TaskKind::AssistantChat {
+                    card_id: $record.card_id.unwrap_or_default(),
+                    message_id: $record.message_id.unwrap_or_default(),
+                    content: $record.content.unwrap_or_default(),
+                },
+                _ => unreachable!(),
+            },
+            state: TaskState::from_i64($record.state),
+            created_at: $record.created_at.and_utc(),
+            updated_at: $record.updated_at.and_utc(),
+            complexity: $record.complexity as u32,
+            cancel_token: tokio_util::sync::CancellationToken::new(),
+        }
+    };
+}
+
+macro_rules! to_mem_entity {
+    ($record:expr) => {
+        MemoryEntity {
+            id: $record.id.unwrap_or_default(),
+            name: $record.name,
+            category: $record.category,
+            entity_type: MemoryEntityType::from_i64($record.entity_type),
+            importance: $record.importance as f32,
+            access_count: $record.access_count as u32,
+            relations: vec![],
+            observations: serde_json::from_str(&$record.observations).unwrap_or_default(),
+            created_at: $record.created_at.and_utc(),
+            updated_at: $record.updated_at.and_utc(),
+            version_vector: serde_json::from_str(&$record.version_vector).unwrap_or_default(),
+        }
+    };
+}
+
+
+impl SqliteBackend {
+    pub async fn new(data_dir: &str, config: &Config) -> Result<Self> {
+        unsafe {
+            libsqlite3_sys::sqlite3_auto_extension(Some(std::mem::transmute::<
+                *const (),
+                unsafe extern "C" fn(
+                    *mut libsqlite3_sys::sqlite3,
+                    *mut *mut i8,
+                    *const libsqlite3_sys::sqlite3_api_routines,
+                ) -> i32,
+            >(
+                sqlite_vec::sqlite3_vec_init as *const (),
+            )));
+        }
+        let filename = format!(
+            "file:{}",
+            Path::new(data_dir)
+                .to_path_buf()
+                .join("state.db")
+                .to_str()
+                .unwrap()
+        );
+        let opt = SqliteConnectOptions::new()
+            .create_if_missing(true)
+            .filename(filename.clone())
+            .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
+            .synchronous(sqlx::sqlite::SqliteSynchronous::Normal)
+            .busy_timeout(Duration::from_secs(30))
+            .foreign_keys(true);
+        let pool = SqlitePool::connect_with(opt.clone()).await?;
+        // A single-connection pool so every mutating query goes through one writer and
+        // never contends with another write for SQLite's exclusive lock.
+        let write_pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(opt.filename(filename))
+            .await?;
+        let res = sqlx::query("select vec_version()").fetch_one(&pool).await?;
+        tracing::info!("vec_version={:?}", res.get::<String, _>(0));
+        run_migrations(&write_pool, MIGRATIONS).await?;
+
+        let embedding_config = config.embedding_provider.clone();
+        let embedding_state = EmbeddingHotState::new(&embedding_config)?;
+        // Kept outside `MIGRATIONS`: the vector column width depends on the configured embedding
+        // provider's dimensions, but `CREATE VIRTUAL TABLE IF NOT EXISTS` is itself idempotent, so
+        // running it on every startup is safe.
+        sqlx::query(&format!(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS vec_mem_entity1 USING vec0(entity_type INTEGER, embedding FLOAT[{}])",
+            embedding_state.provider.dimensions()
+        ))
+        .execute(&write_pool)
+        .await?;
+        // `vec_kg_observation1.rowid` always equals the `kg_observation.id` it embeds, the same
+        // rowid-sharing trick `vec_mem_entity1` uses for `mem_entity`.
+        sqlx::query(&format!(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS vec_kg_observation1 USING vec0(embedding FLOAT[{}])",
+            embedding_state.provider.dimensions()
+        ))
+        .execute(&write_pool)
+        .await?;
+
+        Ok(Self {
+            pool,
+            write_pool,
+            embedding_config,
+            embedding_state: std::sync::Arc::new(tokio::sync::RwLock::new(embedding_state)),
+        })
+    }
+
+    async fn embedding_provider(&self) -> std::sync::Arc<dyn EmbeddingProvider> {
+        self.embedding_state.read().await.provider.clone()
+    }
+
+    async fn create_embedding(&self, text: &str) -> Result<Vec<f32>> {
+        self.embedding_provider().await.embed(text).await
+    }
+
+    async fn create_embeddings(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        self.embedding_provider().await.embed_batch(texts).await
+    }
+
+    fn text_for_embedding(entity: &MemoryEntity) -> String {
+        format!(
+            r#"Entity name: {}\n
+               Category: {}\n
+               Observations: {}\n"#,
+            entity.name,
+            entity.category,
+            entity.observations.join("\n")
+        )
+    }
+
+    async fn create_entity_embedding(&self, entity: &MemoryEntity) -> Result<Vec<f32>> {
+        self.create_embedding(&Self::text_for_embedding(entity))
+            .await
+            .with_context(|| "Failed to create embedding")
+    }
+
+    async fn relations_by_entity(&self, entity_id: i64, entity_name: &str) -> Vec<String> {
+        if let Ok(relations) = sqlx::query!(
+            r#"
+            SELECT
+                en1.name as name_from,
+                en2.name as name_to
+            FROM mem_relation as rel,
+                    mem_entity as en1,
+                    mem_entity as en2
+            WHERE (rel.from_id = ? OR rel.to_id = ?)
+                AND rel.from_id = en1.id
+                AND rel.to_id = en2.id
+            "#,
+            entity_id,
+            entity_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        {
+            relations
+                .into_iter()
+                .map(|r| {
+                    if r.name_from == entity_name {
+                        r.name_to
+                    } else {
+                        r.name_from
+                    }
+                })
+                .collect()
+        } else {
+            vec![]
+        }
+    }
+
+    async fn mem_update_relations(
+        &self,
+        tx: &mut SqliteConnection,
+        from_id: i64,
+        relations: &[String],
+    ) -> Result<()> {
+        // clear all relations
+        sqlx::query!(
+            "DELETE FROM mem_relation WHERE from_id = ? OR to_id = ?",
+            from_id,
+            from_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        for relation in relations {
+            let Some(to_id) = sqlx::query!(
+                "SELECT id FROM mem_entity WHERE lower(name) = lower(?)",
+                relation
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            .and_then(|r| r.id) else {
+                continue;
+            };
+            sqlx::query("INSERT INTO mem_relation (from_id, to_id) VALUES (?, ?)")
+                .bind(from_id)
+                .bind(to_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Substring match over `name`/`category`/`observations`, used by `mem_relevant_entities`
+    /// when the embedding provider is unavailable (e.g. a VoyageAI outage). Quality is worse
+    /// than cosine similarity, but it keeps memory retrieval working through a brief API failure
+    /// instead of the extraction prompt losing its relevant-entries context entirely.
+    async fn mem_relevant_entities_lexical(
+        &self,
+        limit: u16,
+        query: &str,
+        entity_type: MemoryEntityType,
+    ) -> Result<Vec<MemoryEntity>> {
+        let pattern = format!("%{}%", query.replace(['%', '_'], ""));
+        let mut entries = sqlx::query(
+            r#"
+                SELECT * FROM mem_entity
+                WHERE entity_type = ?
+                  AND (name LIKE ? OR category LIKE ? OR observations LIKE ?)
+                ORDER BY importance DESC, updated_at DESC
+                LIMIT ?
+            "#,
+        )
+        .bind(entity_type)
+        .bind(&pattern)
+        .bind(&pattern)
+        .bind(&pattern)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|record| MemoryEntity {
+            id: record.get("id"),
+            name: record.get("name"),
+            entity_type: record.get("entity_type"),
+            category: record.get("category"),
+            importance: record.get("importance"),
+            access_count: record.get("access_count"),
+            relations: vec![],
+            observations: serde_json::from_str(&record.get::<String, _>("observations"))
+                .unwrap_or_default(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            version_vector: serde_json::from_str(&record.get::<String, _>("version_vector"))
+                .unwrap_or_default(),
+        })
+        .collect::<Vec<MemoryEntity>>();
+        for entity in entries.iter_mut() {
+            entity.relations = self.relations_by_entity(entity.id, &entity.name).await;
+        }
+        Ok(entries)
+    }
+}
+
+#[async_trait]
+impl crate::storage::StateStore for SqliteBackend {
+    async fn balance(&self) -> Result<u32> {
+        let balance = sqlx::query!("SELECT balance FROM agent_state")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(balance.balance.try_into().unwrap_or_default())
+    }
+
+    async fn set_balance(&self, balance: u32) -> Result<()> {
+        sqlx::query!("UPDATE agent_state SET balance = ?", balance)
+            .execute(&self.write_pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn add_assistant_message(
+        &self,
+        card_id: &str,
+        role: &str,
+        content: &str,
+    ) -> Result<i64> {
+        let hash = crate::storage::content_hash(content);
+        let mut tx = self.write_pool.begin().await?;
+        sqlx::query!(
+            "INSERT OR IGNORE INTO message_body (hash, content) VALUES (?, ?)",
+            hash,
+            content
+        )
+        .execute(&mut *tx)
+        .await?;
+        let seq = sqlx::query!(
+            "SELECT COALESCE(MAX(seq), 0) + 1 as seq FROM assistant_message WHERE card_id = ?",
+            card_id
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .seq;
+        sqlx::query!(
+            "INSERT INTO assistant_message (card_id, seq, role, hash) VALUES (?, ?, ?, ?)",
+            card_id,
+            seq,
+            role,
+            hash
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(seq)
+    }
+
+    async fn get_last_messages(
+        &self,
+        card_id: &str,
+        before_seq: Option<i64>,
+        count: u32,
+    ) -> Result<Vec<(i64, String, String)>> {
+        let before_seq = before_seq.unwrap_or(i64::MAX);
+        let count = count as i64;
+        let mut rows = sqlx::query!(
+            r#"
+            SELECT am.seq, am.role, mb.content
+            FROM assistant_message am
+            JOIN message_body mb ON mb.hash = am.hash
+            WHERE am.card_id = ? AND am.seq < ?
+            ORDER BY am.seq DESC
+            LIMIT ?
+            "#,
+            card_id,
+            before_seq,
+            count
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|r| (r.seq, r.role, r.content))
+        .collect::<Vec<_>>();
+        rows.reverse();
+        Ok(rows)
+    }
+
+    async fn delete_assistant_messages(&self, card_id: &str) -> Result<()> {
+        let mut tx = self.write_pool.begin().await?;
+        sqlx::query!("DELETE FROM assistant_message WHERE card_id = ?", card_id)
+            .execute(&mut *tx)
+            .await?;
+        // Sweep bodies no longer referenced by any card now that this one's rows are gone.
+        sqlx::query!(
+            "DELETE FROM message_body WHERE NOT EXISTS \
+             (SELECT 1 FROM assistant_message WHERE assistant_message.hash = message_body.hash)"
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn has_assistant_messages(&self, card_id: &str) -> Result<bool> {
+        let row = sqlx::query!(
+            "SELECT 1 as present FROM assistant_message WHERE card_id = ? LIMIT 1",
+            card_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.is_some())
+    }
+
+    async fn assistant_summary(&self, card_id: &str) -> Result<Option<(i64, i64, String)>> {
+        let row = sqlx::query!(
+            "SELECT from_seq, to_seq, summary FROM assistant_summary WHERE card_id = ?",
+            card_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|r| (r.from_seq, r.to_seq, r.summary)))
+    }
+
+    async fn compact_assistant_messages(
+        &self,
+        card_id: &str,
+        from_seq: i64,
+        to_seq: i64,
+        summary: &str,
+    ) -> Result<()> {
+        let mut tx = self.write_pool.begin().await?;
+
+        let existing = sqlx::query!(
+            "SELECT from_seq, to_seq, summary FROM assistant_summary WHERE card_id = ?",
+            card_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let (merged_from, merged_to, merged_summary) = match existing {
+            Some(existing) => (
+                existing.from_seq.min(from_seq),
+                existing.to_seq.max(to_seq),
+                format!("{}\n{}", existing.summary, summary),
+            ),
+            None => (from_seq, to_seq, summary.to_string()),
+        };
+
+        sqlx::query!(
+            "INSERT INTO assistant_summary (card_id, from_seq, to_seq, summary) VALUES (?, ?, ?, ?)
+             ON CONFLICT (card_id) DO UPDATE SET from_seq = excluded.from_seq, to_seq = excluded.to_seq, summary = excluded.summary",
+            card_id,
+            merged_from,
+            merged_to,
+            merged_summary
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM assistant_message WHERE card_id = ? AND seq BETWEEN ? AND ?",
+            card_id,
+            from_seq,
+            to_seq
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_assistant_messages_many(
+        &self,
+        card_ids: &[&str],
+    ) -> std::collections::HashMap<String, String> {
+        let mut out: std::collections::HashMap<String, String> = card_ids
+            .iter()
+            .map(|id| (id.to_string(), "[]".to_string()))
+            .collect();
+        if card_ids.is_empty() {
+            return out;
+        }
+
+        let mut messages_by_card: std::collections::HashMap<String, Vec<Message>> =
+            std::collections::HashMap::new();
+        let mut qb: sqlx::QueryBuilder<sqlx::Sqlite> = sqlx::QueryBuilder::new(
+            "SELECT am.card_id, am.seq, am.role, mb.content \
+             FROM assistant_message am \
+             JOIN message_body mb ON mb.hash = am.hash \
+             WHERE am.card_id IN (",
+        );
+        {
+            let mut separated = qb.separated(", ");
+            for card_id in card_ids {
+                separated.push_bind(*card_id);
+            }
+            separated.push_unseparated(")");
+        }
+        qb.push(" ORDER BY am.card_id, am.seq");
+        if let Ok(rows) = qb.build().fetch_all(&self.pool).await {
+            for row in rows {
+                let card_id: String = row.get("card_id");
+                let content: String = row.get("content");
+                if let Ok(message) = serde_json::from_str::<Message>(&content) {
+                    messages_by_card.entry(card_id).or_default().push(message);
+                }
+            }
+        }
+
+        let mut summaries_by_card: std::collections::HashMap<String, String> =
+            std::collections::HashMap::new();
+        let mut qb: sqlx::QueryBuilder<sqlx::Sqlite> = sqlx::QueryBuilder::new(
+            "SELECT card_id, summary FROM assistant_summary WHERE card_id IN (",
+        );
+        {
+            let mut separated = qb.separated(", ");
+            for card_id in card_ids {
+                separated.push_bind(*card_id);
+            }
+            separated.push_unseparated(")");
+        }
+        if let Ok(rows) = qb.build().fetch_all(&self.pool).await {
+            for row in rows {
+                summaries_by_card.insert(row.get("card_id"), row.get("summary"));
+            }
+        }
+
+        for (card_id, mut messages) in messages_by_card {
+            if let Some(summary) = summaries_by_card.get(&card_id) {
+                messages.insert(
+                    0,
+                    Message::assistant(&format!("Summary of earlier conversation:\n{summary}")),
+                );
+            }
+            let json = serde_json::to_string(&messages).unwrap_or_else(|_| "[]".to_string());
+            out.insert(card_id, json);
+        }
+
+        out
+    }
+
+    async fn append_message_ops(
+        &self,
+        card_id: &str,
+        ops: &[crate::collab::MessageOp],
+    ) -> Result<()> {
+        use crate::collab::MessageOp;
+
+        let mut tx = self.write_pool.begin().await?;
+        for op in ops {
+            match op {
+                MessageOp::Insert {
+                    id,
+                    after,
+                    role,
+                    message,
+                } => {
+                    let content = serde_json::to_string(message)?;
+                    let hash = crate::storage::content_hash(&content);
+                    sqlx::query!(
+                        "INSERT OR IGNORE INTO message_body (hash, content) VALUES (?, ?)",
+                        hash,
+                        content
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                    let after_replica = after.as_ref().map(|a| a.replica_id.clone());
+                    let after_clock = after.as_ref().map(|a| a.clock as i64);
+                    let clock = id.clock as i64;
+                    sqlx::query!(
+                        "INSERT OR IGNORE INTO assistant_message_op
+                         (card_id, replica_id, clock, kind, after_replica, after_clock, role, hash)
+                         VALUES (?, ?, ?, 'insert', ?, ?, ?, ?)",
+                        card_id,
+                        id.replica_id,
+                        clock,
+                        after_replica,
+                        after_clock,
+                        role,
+                        hash
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                MessageOp::Remove { id } => {
+                    let clock = id.clock as i64;
+                    sqlx::query!(
+                        "INSERT OR IGNORE INTO assistant_message_op (card_id, replica_id, clock, kind)
+                         VALUES (?, ?, ?, 'remove')",
+                        card_id,
+                        id.replica_id,
+                        clock
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            }
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn message_ops_since(
+        &self,
+        card_id: &str,
+        version_vector: &crate::collab::VersionVector,
+    ) -> Result<Vec<crate::collab::MessageOp>> {
+        use crate::collab::{MessageOp, MessageOpId};
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT amo.replica_id, amo.clock, amo.kind, amo.after_replica, amo.after_clock, amo.role, mb.content
+            FROM assistant_message_op amo
+            LEFT JOIN message_body mb ON mb.hash = amo.hash
+            WHERE amo.card_id = ?
+            ORDER BY amo.clock, amo.replica_id
+            "#,
+            card_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut ops = Vec::with_capacity(rows.len());
+        for row in rows {
+            let clock = row.clock as u64;
+            if version_vector.get(&row.replica_id).copied().unwrap_or(0) >= clock {
+                continue;
+            }
+            let id = MessageOpId {
+                replica_id: row.replica_id,
+                clock,
+            };
+            if row.kind == "remove" {
+                ops.push(MessageOp::Remove { id });
+                continue;
+            }
+            let after = row.after_replica.map(|replica_id| MessageOpId {
+                replica_id,
+                clock: row.after_clock.unwrap_or(0) as u64,
+            });
+            let message: Message = serde_json::from_str(&row.content.unwrap_or_default())?;
+            ops.push(MessageOp::Insert {
+                id,
+                after,
+                role: row.role.unwrap_or_default(),
+                message,
+            });
+        }
+        Ok(ops)
+    }
+
+    async fn message_version_vector(&self, card_id: &str) -> Result<crate::collab::VersionVector> {
+        let rows = sqlx::query!(
+            "SELECT replica_id, MAX(clock) as max_clock FROM assistant_message_op WHERE card_id = ? GROUP BY replica_id",
+            card_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|r| (r.replica_id, r.max_clock.unwrap_or(0) as u64))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl crate::storage::TaskStore for SqliteBackend {
+    async fn unfinished_tasks(&self) -> Vec<Task> {
+        let now = Utc::now();
+        sqlx::query!(
+            "SELECT * FROM tasks WHERE (state = ? OR state = ? OR state = ?) AND run_at <= ? ORDER BY run_at",
+            TaskState::Created as u8,
+            TaskState::Postponed as u8,
+            TaskState::Failed as u8,
+            now
+        )
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|record| to_task!(record))
+        .collect()
+    }
+
+    /// Increments `attempts` for `task_id` and either schedules a retry with exponential
+    /// backoff (`run_at = now + base * 2^(attempts-1)`, capped and jittered) or, once
+    /// `max_attempts` is exhausted, moves the task to `DeadLettered` with `err` recorded as
+    /// `last_error`. Transient failures (a timed-out embedding call, a flaky LLM response)
+    /// become self-healing instead of requiring a human to requeue the task. `retryable` is
+    /// `false` for error classes that would fail identically on a retry (see
+    /// `error::AgentError::is_retryable`) — those skip straight to `DeadLettered` regardless of
+    /// attempts remaining, instead of burning the budget on retries that can't possibly succeed.
+    async fn reschedule_task_with_backoff(
+        &self,
+        task_id: i64,
+        err: &str,
+        retryable: bool,
+    ) -> Result<()> {
+        let record = sqlx::query!(
+            "SELECT attempts, max_attempts FROM tasks WHERE id = ?",
+            task_id
+        )
+        .fetch_one(&self.write_pool)
+        .await?;
+        let attempts = record.attempts + 1;
+
+        if retryable && attempts < record.max_attempts {
+            let backoff = (RETRY_BASE_BACKOFF * 2i32.pow((attempts - 1) as u32))
+                .min(RETRY_MAX_BACKOFF);
+            let jitter = chrono::Duration::milliseconds(rand::random::<u64>() as i64 % 1000);
+            let run_at = Utc::now() + backoff + jitter;
+            let state = TaskState::Failed as u8;
+            sqlx::query!(
+                "UPDATE tasks SET attempts = ?, state = ?, run_at = ?, last_error = ? WHERE id = ?",
+                attempts,
+                state,
+                run_at,
+                err,
+                task_id
+            )
+            .execute(&self.write_pool)
+            .await?;
+            tracing::warn!(task_id, attempts, %run_at, err, "Failed task scheduled for retry");
+        } else {
+            let state = TaskState::DeadLettered as u8;
+            sqlx::query!(
+                "UPDATE tasks SET attempts = ?, state = ?, last_error = ? WHERE id = ?",
+                attempts,
+                state,
+                err,
+                task_id
+            )
+            .execute(&self.write_pool)
+            .await?;
+            tracing::error!(task_id, attempts, err, retryable, "Dead-lettered task after exhausting retries");
+        }
+        Ok(())
+    }
+
+    /// Records that `task_id` is still alive, so `reclaim_stale_tasks` doesn't mistake it for
+    /// one whose process died. Call this periodically while a `Running` task executes.
+    async fn touch_task_heartbeat(&self, task_id: i64) -> Result<()> {
+        sqlx::query!(
+            "UPDATE tasks SET heartbeat_at = CURRENT_TIMESTAMP WHERE id = ?",
+            task_id
+        )
+        .execute(&self.write_pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Resets any task stuck in `Running` whose heartbeat hasn't been touched in
+    /// `stale_after` back to `Postponed` so `unfinished_tasks` requeues it, and returns the
+    /// reclaimed tasks.
+    async fn reclaim_stale_tasks(&self, stale_after: chrono::Duration) -> Result<Vec<Task>> {
+        let cutoff = Utc::now() - stale_after;
+        let mut tx = self.write_pool.begin().await?;
+        let stale = sqlx::query!(
+            "SELECT * FROM tasks WHERE state = ? AND (heartbeat_at IS NULL OR heartbeat_at < ?)",
+            TaskState::Running as u8,
+            cutoff
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let postponed = TaskState::Postponed as u8;
+        for record in &stale {
+            sqlx::query!(
+                "UPDATE tasks SET state = ? WHERE id = ?",
+                postponed,
+                record.id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        let count = stale.len();
+        if count > 0 {
+            tracing::warn!(count, "Reclaimed stale Running tasks");
+        }
+        Ok(stale.into_iter().map(|record| to_task!(record)).collect())
+    }
+
+    async fn task_messages(&self, task_id: i64) -> Result<Vec<Message>> {
+        let messages = sqlx::query!("SELECT * FROM task_message WHERE task_id = ?", task_id);
+        let messages = messages
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|m| serde_json::from_str(&m.content).unwrap())
+            .collect();
+        Ok(messages)
+    }
+
+    async fn add_task(&self, task: &Task) -> Result<i64> {
+        let (task_kind, social_id, person_id, name, card_id, card_title, content, message_id) =
+            match &task.kind {
+                TaskKind::FollowChat {
+                    card_id,
+                    card_title,
+                    content,
+                    message_id,
+                } => (
+                    "follow_chat",
+                    None::<String>,
+                    None::<String>,
+                    None::<String>,
+                    Some(card_id),
+                    Some(card_title),
+                    Some(content),
+                    Some(message_id),
+                ),
+                TaskKind::MemoryMantainance => (
+                    "memory_mantainance",
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                ),
+                TaskKind::Sleep => ("sleep", None, None, None, None, None, None, None),
+                TaskKind::AssistantTask { content, .. } => {
+                    ("sleep", None, None, None, None, None, Some(content), None)
+                }
+                TaskKind::AssistantChat {
+                    card_id,
+                    message_id,
+                    content,
+                } => (
+                    "assistant_chat",
+                    None,
+                    None,
+                    None,
+                    Some(card_id),
+                    None,
+                    Some(content),
+                    Some(message_id),
+                ),
+            };
+        let rowid = sqlx::query!(
+            "INSERT INTO tasks (kind, social_id, person_id, person_name, card_id, card_title, content, message_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            task_kind,
+            social_id,
+            person_id,
+            name,
+            card_id,
+            card_title,
+            content,
+            message_id
+        )
+        .execute(&self.write_pool)
+        .await?.last_insert_rowid();
+        let task_id = sqlx::query!("SELECT id FROM tasks WHERE rowid = ?", rowid)
+            .fetch_one(&self.write_pool)
+            .await?
+            .id
+            .unwrap();
+        Ok(task_id)
+    }
+
+    async fn add_task_message(&self, task: &Task, message: Message) -> Result<Message> {
+        let json_message = serde_json::to_string(&message)?;
+        sqlx::query!(
+            "INSERT INTO task_message (task_id, content) VALUES (?, ?)",
+            task.id,
+            json_message
+        )
+        .execute(&self.write_pool)
+        .await?;
+        Ok(message)
+    }
+
+    async fn update_task_messages(&self, task_id: i64, messages: &[Message]) -> Result<()> {
+        let mut tx = self.write_pool.begin().await?;
+
+        sqlx::query!("DELETE FROM task_message WHERE task_id = ?", task_id)
+            .execute(&mut *tx)
+            .await?;
+        for message in messages {
+            let json_message = serde_json::to_string(&message)?;
+            sqlx::query!(
+                "INSERT INTO task_message (task_id, content) VALUES (?, ?)",
+                task_id,
+                json_message
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn set_task_state(&self, task_id: i64, state: TaskState) -> Result<()> {
+        let state = state as i64;
+        sqlx::query!("UPDATE tasks SET state = ? WHERE id = ?", state, task_id)
+            .execute(&self.write_pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_task_complexity(&self, task_id: i64, complexity: u32) -> Result<()> {
+        sqlx::query!(
+            "UPDATE tasks SET complexity = ? WHERE id = ?",
+            complexity,
+            task_id
+        )
+        .execute(&self.write_pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_old_tasks(&self, expire_date: DateTime<Utc>) -> Result<()> {
+        tracing::info!(%expire_date, "Delete old tasks");
+        let mut tx = self.write_pool.begin().await?;
+        sqlx::query!(
+            "DELETE FROM task_message WHERE task_id IN (SELECT id FROM tasks WHERE (state = ? OR state = ?) AND updated_at < ?)",
+            TaskState::Completed as u8,
+            TaskState::Cancelled as u8,
+            expire_date
+        )
+        .execute(&mut *tx)
+        .await?;
+        let count = sqlx::query!(
+            "DELETE FROM tasks WHERE (state = ? OR state = ?) AND updated_at < ?",
+            TaskState::Completed as u8,
+            TaskState::Cancelled as u8,
+            expire_date
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        tx.commit().await?;
+        tracing::info!(%count, "Deleted tasks");
+        sqlx::query!("VACUUM").execute(&self.write_pool).await?;
+        Ok(())
+    }
+
+    async fn record_task_fingerprint(&self, fingerprint: &str) -> Result<()> {
+        let now = Utc::now();
+        sqlx::query!(
+            "INSERT INTO task_fingerprint (fingerprint, seen_at, hits) VALUES (?, ?, 1)
+             ON CONFLICT (fingerprint) DO UPDATE SET seen_at = excluded.seen_at, hits = hits + 1",
+            fingerprint,
+            now
+        )
+        .execute(&self.write_pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn seen_task_fingerprint(
+        &self,
+        fingerprint: &str,
+        window: chrono::Duration,
+    ) -> Result<bool> {
+        let cutoff = Utc::now() - window;
+        let row = sqlx::query!(
+            "SELECT 1 as present FROM task_fingerprint WHERE fingerprint = ? AND seen_at >= ?",
+            fingerprint,
+            cutoff
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.is_some())
+    }
+
+    async fn deduped_task_count(&self) -> Result<i64> {
+        let row = sqlx::query!(
+            "SELECT COALESCE(SUM(hits - 1), 0) as count FROM task_fingerprint WHERE hits > 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.count)
+    }
+}
+
+#[async_trait]
+impl crate::storage::ScheduleStore for SqliteBackend {
+    async fn scheduled_tasks(&self) -> Vec<ScheduledAssistantTask> {
+        sqlx::query!("SELECT * FROM scheduled_tasks")
+            .fetch_all(&self.pool)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|record| {
+                let schedule = JobSchedule::new(&record.schedule).unwrap();
+                let next_run_at = record
+                    .next_run_at
+                    .map(|t| t.and_utc())
+                    .unwrap_or_else(|| schedule.upcoming());
+                ScheduledAssistantTask {
+                    id: record.id.unwrap_or_default(),
+                    content: record.content,
+                    last_run_at: record.last_run_at.map(|t| t.and_utc()),
+                    next_run_at,
+                    schedule,
+                }
+            })
+            .collect()
+    }
+
+    async fn add_scheduled_task(
+        &self,
+        content: &str,
+        schedule: &str,
+    ) -> Result<ScheduledAssistantTask> {
+        let job_schedule = JobSchedule::new(schedule)?;
+        let next_run_at = job_schedule.upcoming();
+        let rowid = sqlx::query!(
+            "INSERT INTO scheduled_tasks (content, schedule, next_run_at) VALUES (?, ?, ?)",
+            content,
+            schedule,
+            next_run_at
+        )
+        .execute(&self.write_pool)
+        .await?
+        .last_insert_rowid();
+
+        let task_id = sqlx::query!("SELECT id FROM scheduled_tasks WHERE rowid = ?", rowid)
+            .fetch_one(&self.write_pool)
+            .await?
+            .id
+            .unwrap();
+
+        Ok(ScheduledAssistantTask {
+            id: task_id,
+            content: content.to_string(),
+            last_run_at: None,
+            next_run_at,
+            schedule: job_schedule,
+        })
+    }
+
+    async fn delete_scheduled_task(&self, task_id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM scheduled_tasks WHERE id = ?", task_id)
+            .execute(&self.write_pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn due_scheduled_tasks(&self, now: DateTime<Utc>) -> Vec<ScheduledAssistantTask> {
+        sqlx::query!("SELECT * FROM scheduled_tasks WHERE next_run_at <= ?", now)
+            .fetch_all(&self.pool)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|record| {
+                let schedule = JobSchedule::new(&record.schedule).unwrap();
+                ScheduledAssistantTask {
+                    id: record.id.unwrap_or_default(),
+                    content: record.content,
+                    last_run_at: record.last_run_at.map(|t| t.and_utc()),
+                    next_run_at: record
+                        .next_run_at
+                        .map(|t| t.and_utc())
+                        .unwrap_or(now),
+                    schedule,
+                }
+            })
+            .collect()
+    }
+
+    async fn mark_scheduled_task_ran(&self, task_id: i64, fired_at: DateTime<Utc>) -> Result<()> {
+        let schedule = sqlx::query!("SELECT schedule FROM scheduled_tasks WHERE id = ?", task_id)
+            .fetch_one(&self.write_pool)
+            .await?
+            .schedule;
+        let next_run_at = JobSchedule::new(&schedule)?.next_after(fired_at);
+        sqlx::query!(
+            "UPDATE scheduled_tasks SET last_run_at = ?, next_run_at = ? WHERE id = ?",
+            fired_at,
+            next_run_at,
+            task_id
+        )
+        .execute(&self.write_pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl crate::storage::MemoryStore for SqliteBackend {
+    /// Get entity by name and use lower case name representation
+    async fn mem_entity_by_name(
+        &self,
+        name: &str,
+        entity_type: MemoryEntityType,
+    ) -> Option<MemoryEntity> {
+        let mut entity = sqlx::query!(
+            "SELECT * FROM mem_entity WHERE lower(name) = lower(?) and entity_type = ?",
+            name,
+            entity_type
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map(|record| to_mem_entity!(record))
+        .ok()?;
+        entity.relations = self.relations_by_entity(entity.id, &entity.name).await;
+        Some(entity)
+    }
+
+    async fn mem_entity(&self, id: i64) -> Result<MemoryEntity> {
+        let record = sqlx::query!("SELECT * FROM mem_entity WHERE id = ?", id)
+            .fetch_one(&self.pool)
+            .await?;
+        let mut entity = to_mem_entity!(record);
+        entity.relations = self.relations_by_entity(id, &entity.name).await;
+        Ok(entity)
+    }
+
+    async fn mem_update_entity(&self, entity: &MemoryEntity, writer: &str) -> Result<()> {
+        let mut tx = self.write_pool.begin().await?;
+
+        let row_id = sqlx::query!("SELECT rowid FROM mem_entity WHERE id = ?", entity.id)
+            .fetch_one(&mut *tx)
+            .await?
+            .id;
+        let current = sqlx::query!("SELECT * FROM mem_entity WHERE id = ?", entity.id)
+            .fetch_one(&mut *tx)
+            .await?;
+        let current_entity = to_mem_entity!(current);
+
+        let mut merged = if current_entity.version_vector == entity.version_vector {
+            entity.clone()
+        } else {
+            tracing::debug!(
+                id = entity.id,
+                "Concurrent update detected for memory entity, merging siblings"
+            );
+            crate::memory::merge_entities(current_entity.clone(), entity.clone())
+        };
+        merged.version_vector =
+            crate::memory::merge_version_vectors(&current_entity.version_vector, &entity.version_vector);
+        crate::memory::bump_version_vector(&mut merged.version_vector, writer);
+
+        let observations = serde_json::to_string(&merged.observations).unwrap();
+        let version_vector = serde_json::to_string(&merged.version_vector).unwrap();
+        let embedding = self.create_entity_embedding(&merged).await?;
+
+        sqlx::query!(
+            "UPDATE mem_entity SET name = ?, entity_type = ?, category = ?, importance = ?, access_count = ?, observations = ?, version_vector = ?, updated_at = ? WHERE id = ?",
+            merged.name,
+            merged.entity_type,
+            merged.category,
+            merged.importance,
+            merged.access_count,
+            observations,
+            version_vector,
+            merged.updated_at,
+            merged.id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("UPDATE vec_mem_entity1 SET entity_type = ?, embedding = ? WHERE rowid = ?")
+            .bind(merged.entity_type.clone())
+            .bind(embedding.as_bytes())
+            .bind(row_id)
+            .execute(&mut *tx)
+            .await?;
+
+        self.mem_update_relations(&mut tx, merged.id, &merged.relations)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn mem_update_entity_importance(&self, id: i64, importance: f32) -> Result<()> {
+        sqlx::query!(
+            "UPDATE mem_entity SET importance = ? WHERE id = ?",
+            importance,
+            id
+        )
+        .execute(&self.write_pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn mem_add_entity(&self, entity: &MemoryEntity, writer: &str) -> Result<()> {
+        let observations = serde_json::to_string(&entity.observations).unwrap();
+        let mut version_vector = HashMap::new();
+        crate::memory::bump_version_vector(&mut version_vector, writer);
+        let version_vector = serde_json::to_string(&version_vector).unwrap();
+
+        let embedding = self.create_entity_embedding(entity).await?;
+        let mut tx = self.write_pool.begin().await?;
+
+        let row_id =sqlx::query!(
+            "INSERT INTO mem_entity (name, entity_type, category, importance, access_count, observations, version_vector) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            entity.name,
+            entity.entity_type,
+            entity.category,
+            entity.importance,
+            entity.access_count,
+            observations,
+            version_vector,
+        )
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+
+        sqlx::query("INSERT INTO vec_mem_entity1 (rowid, entity_type, embedding) VALUES (?, ?, ?)")
+            .bind(row_id)
+            .bind(entity.entity_type.clone())
+            .bind(embedding.as_bytes())
+            .execute(&mut *tx)
+            .await?;
+
+        let id = sqlx::query!("SELECT id FROM mem_entity WHERE rowid = ?", row_id)
+            .fetch_one(&mut *tx)
+            .await?
+            .id
+            .unwrap();
+        self.mem_update_relations(&mut tx, id, &entity.relations)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn mem_last_entities(&self, limit: u16) -> Result<Vec<MemoryEntity>> {
+        let mut entities = sqlx::query!(
+            "SELECT * FROM mem_entity ORDER BY importance DESC, updated_at DESC LIMIT ?",
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|record| to_mem_entity!(record))
+        .collect::<Vec<MemoryEntity>>();
+        for entity in entities.iter_mut() {
+            entity.relations = self.relations_by_entity(entity.id, &entity.name).await;
+        }
+        Ok(entities)
+    }
+
+    async fn mem_entities_ids_for_consolidation(&self, threshold: f32) -> Result<Vec<i64>> {
+        let ids =sqlx::query!(
+            "SELECT id FROM mem_entity WHERE importance >= ? AND entity_type == 0 ORDER BY updated_at DESC LIMIT 10000",
+            threshold,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .filter_map(|record| record.id)
+        .collect::<Vec<_>>();
+        Ok(ids)
+    }
+
+    async fn mem_relevant_entities(
+        &self,
+        limit: u16,
+        query: &str,
+        entity_type: MemoryEntityType,
+    ) -> Result<Vec<MemoryEntity>> {
+        let query_embedding = match self.create_embedding(query).await {
+            Ok(embedding) => embedding,
+            Err(err) => {
+                tracing::warn!(
+                    ?err,
+                    "Embedding provider unavailable, falling back to lexical memory search"
+                );
+                return self
+                    .mem_relevant_entities_lexical(limit, query, entity_type)
+                    .await;
+            }
+        };
+        let query_embedding = query_embedding.as_bytes();
+        let mut entries = sqlx::query(
+            r#"
+                WITH matches as (
+                    SELECT rowid, distance FROM vec_mem_entity1
+                    WHERE entity_type = ? AND embedding MATCH ?
+                    ORDER BY distance
+                    LIMIT ?
+                )
+                SELECT * FROM mem_entity
+                JOIN matches on mem_entity.rowid = matches.rowid
+                ORDER BY distance ASC, importance DESC, updated_at DESC
+            "#,
+        )
+        .bind(entity_type)
+        .bind(query_embedding)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|record| MemoryEntity {
+            id: record.get("id"),
+            name: record.get("name"),
+            entity_type: record.get("entity_type"),
+            category: record.get("category"),
+            importance: record.get("importance"),
+            access_count: record.get("access_count"),
+            relations: vec![],
+            observations: serde_json::from_str(&record.get::<String, _>("observations"))
+                .unwrap_or_default(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            version_vector: serde_json::from_str(&record.get::<String, _>("version_vector"))
+                .unwrap_or_default(),
+        })
+        .collect::<Vec<MemoryEntity>>();
+        for entity in entries.iter_mut() {
+            entity.relations = self.relations_by_entity(entity.id, &entity.name).await;
+        }
+        Ok(entries)
+    }
+
+    async fn mem_relevant_entities_scored(
+        &self,
+        limit: u16,
+        query: &str,
+        entity_type: MemoryEntityType,
+        min_similarity: f32,
+    ) -> Result<Vec<(MemoryEntity, f32)>> {
+        let query_embedding = match self.create_embedding(query).await {
+            Ok(embedding) => embedding,
+            Err(err) => {
+                tracing::warn!(
+                    ?err,
+                    "Embedding provider unavailable, falling back to lexical memory search"
+                );
+                return Ok(self
+                    .mem_relevant_entities_lexical(limit, query, entity_type)
+                    .await?
+                    .into_iter()
+                    .map(|entity| (entity, 1.0))
+                    .collect());
+            }
+        };
+        let query_embedding = query_embedding.as_bytes();
+        let mut entries = sqlx::query(
+            r#"
+                WITH matches as (
+                    SELECT rowid, distance FROM vec_mem_entity1
+                    WHERE entity_type = ? AND embedding MATCH ?
+                    ORDER BY distance
+                    LIMIT ?
+                )
+                SELECT * FROM mem_entity
+                JOIN matches on mem_entity.rowid = matches.rowid
+                ORDER BY distance ASC, importance DESC, updated_at DESC
+            "#,
+        )
+        .bind(entity_type)
+        .bind(query_embedding)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .filter_map(|record| {
+            let distance: f64 = record.get("distance");
+            // `vec0`'s default metric is L2 distance over plain (non-normalized) embeddings;
+            // collapse it to a bounded "higher is better" score, same as `kg_search_semantic`.
+            let score = 1.0 / (1.0 + distance as f32);
+            (score >= min_similarity).then(|| {
+                (
+                    MemoryEntity {
+                        id: record.get("id"),
+                        name: record.get("name"),
+                        entity_type: record.get("entity_type"),
+                        category: record.get("category"),
+                        importance: record.get("importance"),
+                        access_count: record.get("access_count"),
+                        relations: vec![],
+                        observations: serde_json::from_str(
+                            &record.get::<String, _>("observations"),
+                        )
+                        .unwrap_or_default(),
+                        created_at: Utc::now(),
+                        updated_at: Utc::now(),
+                        version_vector: serde_json::from_str(
+                            &record.get::<String, _>("version_vector"),
+                        )
+                        .unwrap_or_default(),
+                    },
+                    score,
+                )
+            })
+        })
+        .collect::<Vec<(MemoryEntity, f32)>>();
+        for (entity, _) in entries.iter_mut() {
+            entity.relations = self.relations_by_entity(entity.id, &entity.name).await;
+        }
+        Ok(entries)
+    }
+
+    async fn mem_get_entity_ids(&self) -> Result<Vec<i64>> {
+        let idxs = sqlx::query!("SELECT id FROM mem_entity")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .filter_map(|record| record.id)
+            .collect::<Vec<i64>>();
+        Ok(idxs)
+    }
+
+    async fn mem_delete_entity(&self, id: i64) -> Result<()> {
+        let mut tx = self.write_pool.begin().await?;
+        sqlx::query!(
+            "DELETE FROM mem_relation WHERE from_id = ? OR to_id = ?",
+            id,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!("DELETE FROM mem_entity WHERE id = ?", id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn mem_reembed_all(&self) -> Result<()> {
+        let entities = sqlx::query!("SELECT * FROM mem_entity")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|record| to_mem_entity!(record))
+            .collect::<Vec<MemoryEntity>>();
+
+        for chunk in entities.chunks(REEMBED_BATCH_SIZE) {
+            let texts = chunk
+                .iter()
+                .map(Self::text_for_embedding)
+                .collect::<Vec<_>>();
+            let texts = texts.iter().map(String::as_str).collect::<Vec<_>>();
+            let embeddings = self.embedding_provider().await.embed_batch(&texts).await?;
+
+            let mut tx = self.write_pool.begin().await?;
+            for (entity, embedding) in chunk.iter().zip(embeddings) {
+                let row_id = sqlx::query!("SELECT rowid FROM mem_entity WHERE id = ?", entity.id)
+                    .fetch_one(&mut *tx)
+                    .await?
+                    .id;
+                sqlx::query(
+                    "UPDATE vec_mem_entity1 SET entity_type = ?, embedding = ? WHERE rowid = ?",
+                )
+                .bind(entity.entity_type.clone())
+                .bind(embedding.as_bytes())
+                .bind(row_id)
+                .execute(&mut *tx)
+                .await?;
+            }
+            tx.commit().await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl crate::storage::NoteStore for SqliteBackend {
+    async fn notes(&self) -> Result<Vec<crate::note::Note>> {
+        let notes = sqlx::query!("SELECT id, content, tags, mentions FROM notes")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|record| crate::note::Note {
+                id: record.id.unwrap_or_default(),
+                content: record.content,
+                tags: serde_json::from_str(&record.tags).unwrap_or_default(),
+                mentions: serde_json::from_str(&record.mentions).unwrap_or_default(),
+            })
+            .collect();
+        Ok(notes)
+    }
+
+    async fn add_note(&self, content: &str, tags: &[String], mentions: &[String]) -> Result<i64> {
+        let tags = serde_json::to_string(tags)?;
+        let mentions = serde_json::to_string(mentions)?;
+        let id = sqlx::query!(
+            "INSERT INTO notes (content, tags, mentions) VALUES (?, ?, ?)",
+            content,
+            tags,
+            mentions
+        )
+        .execute(&self.write_pool)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    async fn delete_notes(&self, ids: Vec<i64>) -> Result<()> {
+        for id in ids {
+            sqlx::query!("DELETE FROM notes WHERE id = ?", id)
+                .execute(&self.write_pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn notes_search(
+        &self,
+        tag: Option<&str>,
+        query: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<crate::note::Note>> {
+        let tag_pattern = tag.map(|tag| format!("%\"{}\"%", tag.replace(['%', '_', '"'], "")));
+        let query_pattern = query.map(|query| format!("%{}%", query.replace(['%', '_'], "")));
+        let rows = sqlx::query!(
+            "SELECT id, content, tags, mentions FROM notes
+             WHERE (?1 IS NULL OR tags LIKE ?1)
+               AND (?2 IS NULL OR content LIKE ?2)
+             ORDER BY id DESC
+             LIMIT ?3 OFFSET ?4",
+            tag_pattern,
+            query_pattern,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|record| crate::note::Note {
+                id: record.id.unwrap_or_default(),
+                content: record.content,
+                tags: serde_json::from_str(&record.tags).unwrap_or_default(),
+                mentions: serde_json::from_str(&record.mentions).unwrap_or_default(),
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl crate::storage::WorkerStore for SqliteBackend {
+    async fn paused_worker_ids(&self) -> Result<Vec<String>> {
+        Ok(sqlx::query!("SELECT id FROM worker_pause_state")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|record| record.id)
+            .collect())
+    }
+
+    async fn set_worker_paused(&self, id: &str, paused: bool) -> Result<()> {
+        if paused {
+            sqlx::query!(
+                "INSERT INTO worker_pause_state (id) VALUES (?) ON CONFLICT(id) DO NOTHING",
+                id
+            )
+            .execute(&self.write_pool)
+            .await?;
+        } else {
+            sqlx::query!("DELETE FROM worker_pause_state WHERE id = ?", id)
+                .execute(&self.write_pool)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl crate::storage::ConfigOverrideStore for SqliteBackend {
+    async fn config_overrides(&self) -> Result<HashMap<String, String>> {
+        Ok(sqlx::query!("SELECT key, value FROM config_override")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|record| (record.key, record.value))
+            .collect())
+    }
+
+    async fn set_config_override(&self, key: &str, value: &str) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO config_override (key, value) VALUES (?, ?)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            key,
+            value
+        )
+        .execute(&self.write_pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn reload_embedding_provider(&self) -> Result<bool> {
+        let overrides = self.config_overrides().await?;
+        let effective =
+            crate::storage::apply_embedding_overrides(self.embedding_config.clone(), &overrides);
+        let fingerprint = effective.fingerprint();
+
+        let mut state = self.embedding_state.write().await;
+        if state.fingerprint == fingerprint {
+            return Ok(false);
+        }
+        state.provider = crate::embeddings::build_embedding_provider(&effective)?;
+        state.fingerprint = fingerprint;
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl crate::storage::PendingActionStore for SqliteBackend {
+    async fn add_pending_action(&self, call_id: &str, tool_name: &str, arguments: &str) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO pending_action (call_id, tool_name, arguments) VALUES (?, ?, ?)",
+            call_id,
+            tool_name,
+            arguments
+        )
+        .execute(&self.write_pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn pending_action(&self, call_id: &str) -> Result<Option<crate::storage::PendingAction>> {
+        Ok(sqlx::query!(
+            "SELECT call_id, tool_name, arguments, status FROM pending_action WHERE call_id = ?",
+            call_id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|record| crate::storage::PendingAction {
+            call_id: record.call_id,
+            tool_name: record.tool_name,
+            arguments: record.arguments,
+            status: crate::storage::PendingActionStatus::parse(&record.status),
+        }))
+    }
+
+    async fn set_pending_action_status(
+        &self,
+        call_id: &str,
+        status: crate::storage::PendingActionStatus,
+    ) -> Result<()> {
+        let status = status.as_str();
+        sqlx::query!(
+            "UPDATE pending_action SET status = ? WHERE call_id = ?",
+            status,
+            call_id
+        )
+        .execute(&self.write_pool)
+        .await?;
+        Ok(())
+    }
+}
+
+impl SqliteBackend {
+    /// Inserts `texts` as new observations of `entity_id`, skipping any exact text already
+    /// recorded for that entity. The not-yet-recorded texts are embedded in a single
+    /// `embed_batch` call rather than one request per text. Returns the texts actually added.
+    async fn kg_insert_observations_if_new(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        entity_id: i64,
+        texts: &[String],
+    ) -> Result<Vec<String>> {
+        let mut new_texts = Vec::new();
+        for text in texts {
+            let exists = sqlx::query!(
+                "SELECT id FROM kg_observation WHERE entity_id = ? AND text = ?",
+                entity_id,
+                text
+            )
+            .fetch_optional(&mut **tx)
+            .await?
+            .is_some();
+            if !exists {
+                new_texts.push(text.clone());
+            }
+        }
+        if new_texts.is_empty() {
+            return Ok(new_texts);
+        }
+
+        let embeddings = self
+            .create_embeddings(&new_texts.iter().map(String::as_str).collect::<Vec<_>>())
+            .await?;
+        for (text, embedding) in new_texts.iter().zip(embeddings) {
+            let observation_id = sqlx::query!(
+                "INSERT INTO kg_observation (entity_id, text) VALUES (?, ?)",
+                entity_id,
+                text
+            )
+            .execute(&mut **tx)
+            .await?
+            .last_insert_rowid();
+            sqlx::query("INSERT INTO vec_kg_observation1 (rowid, embedding) VALUES (?, ?)")
+                .bind(observation_id)
+                .bind(embedding.as_bytes())
+                .execute(&mut **tx)
+                .await?;
+        }
+        Ok(new_texts)
+    }
+
+    async fn kg_load_entity(&self, id: i64, name: String, entity_type: String) -> Result<Entity> {
+        let observations = sqlx::query!(
+            "SELECT text FROM kg_observation WHERE entity_id = ? ORDER BY id",
+            id
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|record| record.text)
+        .collect();
+        Ok(Entity {
+            id,
+            name,
+            entity_type,
+            observations,
+            score: None,
+        })
+    }
+
+    /// Every relation whose `from` and `to` are both in `names`.
+    async fn kg_relations_among(&self, names: &[&str]) -> Result<Vec<Relation>> {
+        let mut qb: sqlx::QueryBuilder<sqlx::Sqlite> = sqlx::QueryBuilder::new(
+            "SELECT from_name, to_name, relation_type FROM kg_relation WHERE from_name IN (",
+        );
+        {
+            let mut separated = qb.separated(", ");
+            for name in names {
+                separated.push_bind(*name);
+            }
+            separated.push_unseparated(")");
+        }
+        qb.push(" AND to_name IN (");
+        {
+            let mut separated = qb.separated(", ");
+            for name in names {
+                separated.push_bind(*name);
+            }
+            separated.push_unseparated(")");
+        }
+        let rows = qb.build().fetch_all(&self.pool).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| Relation {
+                from: row.get("from_name"),
+                to: row.get("to_name"),
+                relation_type: row.get("relation_type"),
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl crate::storage::KnowledgeGraphStore for SqliteBackend {
+    async fn kg_add_entities(&self, entities: &[Entity]) -> Result<Vec<Entity>> {
+        let mut created = Vec::new();
+        for entity in entities {
+            let exists = sqlx::query!("SELECT id FROM kg_entity WHERE name = ?", entity.name)
+                .fetch_optional(&self.pool)
+                .await?
+                .is_some();
+            if exists {
+                continue;
+            }
+            let mut tx = self.write_pool.begin().await?;
+            let entity_id = sqlx::query!(
+                "INSERT INTO kg_entity (name, entity_type) VALUES (?, ?)",
+                entity.name,
+                entity.entity_type
+            )
+            .execute(&mut *tx)
+            .await?
+            .last_insert_rowid();
+            self.kg_insert_observations_if_new(&mut tx, entity_id, &entity.observations)
+                .await?;
+            tx.commit().await?;
+            created.push(Entity {
+                id: entity_id,
+                ..entity.clone()
+            });
+        }
+        Ok(created)
+    }
+
+    async fn kg_add_relations(&self, relations: &[Relation]) -> Result<Vec<Relation>> {
+        let mut created = Vec::new();
+        for relation in relations {
+            let exists = sqlx::query!(
+                "SELECT 1 as present FROM kg_relation WHERE from_name = ? AND to_name = ? AND relation_type = ?",
+                relation.from,
+                relation.to,
+                relation.relation_type
+            )
+            .fetch_optional(&self.write_pool)
+            .await?
+            .is_some();
+            if exists {
+                continue;
+            }
+            sqlx::query!(
+                "INSERT INTO kg_relation (from_name, to_name, relation_type) VALUES (?, ?, ?)",
+                relation.from,
+                relation.to,
+                relation.relation_type
+            )
+            .execute(&self.write_pool)
+            .await?;
+            created.push(relation.clone());
+        }
+        Ok(created)
+    }
+
+    async fn kg_add_observations(&self, observations: &[Observation]) -> Result<Vec<Observation>> {
+        let mut added = Vec::new();
+        for observation in observations {
+            let Some(entity_id) = sqlx::query!(
+                "SELECT id FROM kg_entity WHERE name = ?",
+                observation.entity_name
+            )
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|record| record.id) else {
+                continue;
+            };
+            let mut tx = self.write_pool.begin().await?;
+            let added_texts = self
+                .kg_insert_observations_if_new(&mut tx, entity_id, &observation.observations)
+                .await?;
+            tx.commit().await?;
+            if !added_texts.is_empty() {
+                added.push(Observation {
+                    entity_name: observation.entity_name.clone(),
+                    observations: added_texts,
+                });
+            }
+        }
+        Ok(added)
+    }
+
+    async fn kg_delete_entities(&self, names: &[String]) -> Result<()> {
+        for name in names {
+            let Some(entity_id) = sqlx::query!("SELECT id FROM kg_entity WHERE name = ?", name)
+                .fetch_optional(&self.pool)
+                .await?
+                .map(|record| record.id)
+            else {
+                continue;
+            };
+            sqlx::query!(
+                "DELETE FROM vec_kg_observation1 WHERE rowid IN (SELECT id FROM kg_observation WHERE entity_id = ?)",
+                entity_id
+            )
+            .execute(&self.write_pool)
+            .await?;
+            sqlx::query!("DELETE FROM kg_observation WHERE entity_id = ?", entity_id)
+                .execute(&self.write_pool)
+                .await?;
+            sqlx::query!(
+                "DELETE FROM kg_relation WHERE from_name = ? OR to_name = ?",
+                name,
+                name
+            )
+            .execute(&self.write_pool)
+            .await?;
+            sqlx::query!("DELETE FROM kg_entity WHERE id = ?", entity_id)
+                .execute(&self.write_pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn kg_delete_observations(&self, deletions: &[Observation]) -> Result<()> {
+        for deletion in deletions {
+            let Some(entity_id) = sqlx::query!(
+                "SELECT id FROM kg_entity WHERE name = ?",
+                deletion.entity_name
+            )
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|record| record.id) else {
+                continue;
+            };
+            for text in &deletion.observations {
+                let Some(observation_id) = sqlx::query!(
+                    "SELECT id FROM kg_observation WHERE entity_id = ? AND text = ?",
+                    entity_id,
+                    text
+                )
+                .fetch_optional(&self.pool)
+                .await?
+                .map(|record| record.id) else {
+                    continue;
+                };
+                sqlx::query!(
+                    "DELETE FROM vec_kg_observation1 WHERE rowid = ?",
+                    observation_id
+                )
+                .execute(&self.write_pool)
+                .await?;
+                sqlx::query!("DELETE FROM kg_observation WHERE id = ?", observation_id)
+                    .execute(&self.write_pool)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn kg_delete_relations(&self, relations: &[Relation]) -> Result<()> {
+        for relation in relations {
+            sqlx::query!(
+                "DELETE FROM kg_relation WHERE from_name = ? AND to_name = ? AND relation_type = ?",
+                relation.from,
+                relation.to,
+                relation.relation_type
+            )
+            .execute(&self.write_pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn kg_search_nodes(&self, query: Option<&str>) -> Result<KnowledgeGraph> {
+        let entity_rows = match query {
+            Some(query) => {
+                let pattern = format!("%{}%", query.replace(['%', '_'], ""));
+                sqlx::query!(
+                    r#"
+                    SELECT DISTINCT e.id, e.name, e.entity_type
+                    FROM kg_entity e
+                    LEFT JOIN kg_observation o ON o.entity_id = e.id
+                    WHERE e.name LIKE ? OR e.entity_type LIKE ? OR o.text LIKE ?
+                    ORDER BY e.name
+                    "#,
+                    pattern,
+                    pattern,
+                    pattern
+                )
+                .fetch_all(&self.pool)
+                .await?
+            }
+            None => sqlx::query!("SELECT id, name, entity_type FROM kg_entity ORDER BY name")
+                .fetch_all(&self.pool)
+                .await?,
+        };
+        let mut entities = Vec::with_capacity(entity_rows.len());
+        for row in entity_rows {
+            entities
+                .push(
+                    self.kg_load_entity(row.id.unwrap_or_default(), row.name, row.entity_type)
+                        .await?,
+                );
+        }
+        let names: Vec<&str> = entities.iter().map(|entity| entity.name.as_str()).collect();
+        let relations = if names.is_empty() {
+            vec![]
+        } else {
+            self.kg_relations_among(&names).await?
+        };
+        Ok(KnowledgeGraph { entities, relations })
+    }
+
+    async fn kg_list_entities(&self, names: &[String]) -> Result<Vec<Entity>> {
+        if names.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut qb: sqlx::QueryBuilder<sqlx::Sqlite> =
+            sqlx::QueryBuilder::new("SELECT id, name, entity_type FROM kg_entity WHERE name IN (");
+        {
+            let mut separated = qb.separated(", ");
+            for name in names {
+                separated.push_bind(name);
+            }
+            separated.push_unseparated(")");
+        }
+        let rows = qb.build().fetch_all(&self.pool).await?;
+        let mut entities = Vec::with_capacity(rows.len());
+        for row in rows {
+            let id: i64 = row.get("id");
+            let name: String = row.get("name");
+            let entity_type: String = row.get("entity_type");
+            entities.push(self.kg_load_entity(id, name, entity_type).await?);
+        }
+        Ok(entities)
+    }
+
+    async fn kg_relations_touching(&self, names: &[String]) -> Result<Vec<Relation>> {
+        if names.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut qb: sqlx::QueryBuilder<sqlx::Sqlite> = sqlx::QueryBuilder::new(
+            "SELECT DISTINCT from_name, to_name, relation_type FROM kg_relation WHERE from_name IN (",
+        );
+        {
+            let mut separated = qb.separated(", ");
+            for name in names {
+                separated.push_bind(name);
+            }
+            separated.push_unseparated(")");
+        }
+        qb.push(" OR to_name IN (");
+        {
+            let mut separated = qb.separated(", ");
+            for name in names {
+                separated.push_bind(name);
+            }
+            separated.push_unseparated(")");
+        }
+        let rows = qb.build().fetch_all(&self.pool).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| Relation {
+                from: row.get("from_name"),
+                to: row.get("to_name"),
+                relation_type: row.get("relation_type"),
+            })
+            .collect())
+    }
+
+    async fn kg_search_semantic(&self, query: &str, k: usize) -> Result<Vec<Entity>> {
+        let query_embedding = self.create_embedding(query).await?;
+        let rows = sqlx::query(
+            r#"
+            WITH matches AS (
+                SELECT rowid, distance FROM vec_kg_observation1
+                WHERE embedding MATCH ?
+                ORDER BY distance
+                LIMIT 1000
+            )
+            SELECT e.id, e.name, e.entity_type, MIN(matches.distance) as best_distance
+            FROM matches
+            JOIN kg_observation o ON o.id = matches.rowid
+            JOIN kg_entity e ON e.id = o.entity_id
+            GROUP BY e.id
+            ORDER BY best_distance ASC
+            LIMIT ?
+            "#,
+        )
+        .bind(query_embedding.as_bytes())
+        .bind(k as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut entities = Vec::with_capacity(rows.len());
+        for row in rows {
+            let id: i64 = row.get("id");
+            let name: String = row.get("name");
+            let entity_type: String = row.get("entity_type");
+            let distance: f64 = row.get("best_distance");
+            let mut entity = self.kg_load_entity(id, name, entity_type).await?;
+            // `vec0`'s default metric is L2 distance over plain (non-normalized) embeddings;
+            // collapse it to a bounded "higher is better" score instead of leaking distance units.
+            entity.score = Some(1.0 / (1.0 + distance as f32));
+            entities.push(entity);
+        }
+        Ok(entities)
+    }
+}
+
+#[async_trait]
+impl crate::storage::MultiplexerStore for SqliteBackend {
+    async fn multiplexer_offset(&self) -> Result<Option<i64>> {
+        Ok(
+            sqlx::query!("SELECT offset_value FROM multiplexer_offset WHERE id = 0")
+                .fetch_optional(&self.pool)
+                .await?
+                .map(|record| record.offset_value),
+        )
+    }
+
+    async fn set_multiplexer_offset(&self, offset: i64) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO multiplexer_offset (id, offset_value) VALUES (0, ?)
+             ON CONFLICT(id) DO UPDATE SET offset_value = excluded.offset_value",
+            offset
+        )
+        .execute(&self.write_pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn journal_card_message(&self, card_id: &str, message_id: &str, payload: &str) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO multiplexer_journal (card_id, message_id, payload) VALUES (?, ?, ?)
+             ON CONFLICT(card_id, message_id) DO UPDATE SET payload = excluded.payload",
+            card_id,
+            message_id,
+            payload
+        )
+        .execute(&self.write_pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn clear_card_journal(&self, card_id: &str) -> Result<()> {
+        sqlx::query!("DELETE FROM multiplexer_journal WHERE card_id = ?", card_id)
+            .execute(&self.write_pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn journaled_messages(&self) -> Result<Vec<(String, String, String)>> {
+        Ok(sqlx::query!(
+            "SELECT card_id, message_id, payload FROM multiplexer_journal ORDER BY created_at"
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|record| (record.card_id, record.message_id, record.payload))
+        .collect())
+    }
+
+    async fn follow_state(&self, workspace_id: &str, group_id: &str) -> Result<Option<String>> {
+        Ok(sqlx::query!(
+            "SELECT payload FROM multiplexer_follow_state WHERE workspace_id = ? AND group_id = ?",
+            workspace_id,
+            group_id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|record| record.payload))
+    }
+
+    async fn set_follow_state(&self, workspace_id: &str, group_id: &str, payload: &str) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO multiplexer_follow_state (workspace_id, group_id, payload) VALUES (?, ?, ?)
+             ON CONFLICT(workspace_id, group_id) DO UPDATE SET payload = excluded.payload, updated_at = CURRENT_TIMESTAMP",
+            workspace_id,
+            group_id,
+            payload
+        )
+        .execute(&self.write_pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Never exercised by these tests — `follow_state`/`set_follow_state` don't touch embeddings —
+    /// but `SqliteBackend` needs one to exist at all.
+    #[derive(Debug)]
+    struct NullEmbeddingProvider;
+
+    #[async_trait]
+    impl EmbeddingProvider for NullEmbeddingProvider {
+        fn dimensions(&self) -> u16 {
+            1
+        }
+
+        async fn embed(&self, _text: &str) -> Result<Vec<f32>> {
+            Ok(vec![0.0])
+        }
+
+        async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+            Ok(texts.iter().map(|_| vec![0.0]).collect())
+        }
+    }
+
+    /// Opens a fresh connection to the sqlite file at `path`, running migrations if they haven't
+    /// been applied yet. Called twice against the same file in the restart test below, standing in
+    /// for the real process exit/relaunch a worker would go through.
+    async fn open_backend(path: &Path) -> SqliteBackend {
+        let filename = format!("file:{}", path.to_str().unwrap());
+        let opt = SqliteConnectOptions::new()
+            .create_if_missing(true)
+            .filename(filename.clone());
+        let pool = SqlitePool::connect_with(opt.clone()).await.unwrap();
+        let write_pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(opt)
+            .await
+            .unwrap();
+        run_migrations(&write_pool, MIGRATIONS).await.unwrap();
+        let embedding_config = EmbeddingProviderConfig::Local {
+            model_path: "null".to_string(),
+            dimensions: 1,
+        };
+        SqliteBackend {
+            pool,
+            write_pool,
+            embedding_config,
+            embedding_state: std::sync::Arc::new(tokio::sync::RwLock::new(EmbeddingHotState {
+                provider: std::sync::Arc::new(NullEmbeddingProvider),
+                fingerprint: "null".to_string(),
+            })),
+        }
+    }
+
+    // Regression test for the lavina reboot-with-two-users bug: a worker that dies mid-follow and
+    // comes back up must resume with the same follow windows and seen-message set instead of
+    // starting cold, or it silently drops (or re-backfills) in-flight conversations.
+    #[tokio::test]
+    async fn follow_state_survives_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("state.db");
+
+        let before_restart = open_backend(&db_path).await;
+        before_restart
+            .set_follow_state("ws-1", "group-a", r#"{"cards":["card-1"],"remaining":3}"#)
+            .await
+            .unwrap();
+        drop(before_restart);
+
+        let after_restart = open_backend(&db_path).await;
+        let restored = after_restart
+            .follow_state("ws-1", "group-a")
+            .await
+            .unwrap();
+        assert_eq!(
+            restored,
+            Some(r#"{"cards":["card-1"],"remaining":3}"#.to_string())
+        );
+
+        // A different workspace/group never sees another group's state.
+        assert_eq!(
+            after_restart.follow_state("ws-1", "group-b").await.unwrap(),
+            None
+        );
+
+        after_restart
+            .set_follow_state("ws-1", "group-a", r#"{"cards":[],"remaining":0}"#)
+            .await
+            .unwrap();
+        assert_eq!(
+            after_restart.follow_state("ws-1", "group-a").await.unwrap(),
+            Some(r#"{"cards":[],"remaining":0}"#.to_string())
+        );
+    }
+}
@@ -0,0 +1,124 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! OneForOne supervision for long-lived background tasks that used to be bare `tokio::spawn`
+//! `JoinHandle`s nobody watched (e.g. `communication::http::server`): if the task returned an
+//! error or panicked, nothing noticed and nothing restarted it.
+//!
+//! `supervise` drives one such child, re-invoking `make_child` to build a fresh future after each
+//! abnormal termination, with exponential backoff between attempts and an escalation callback
+//! once a child fails too many times within a rolling window. It does not attempt to supervise
+//! `ProviderClient` response streams — those are consumed inline inside the owning task's own
+//! loop (see `agent::assistant_task::process_assistant_task`'s `resp.next().await`) rather than as
+//! independent spawned background tasks, so there is no standalone child to restart there; a
+//! stream error already surfaces to, and is handled by, that loop directly.
+
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use tokio_util::sync::CancellationToken;
+
+/// How a supervised child is restarted after it terminates abnormally (an `Err` return, or a
+/// panic).
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    /// Max restarts allowed within `window` before the supervisor gives up and escalates.
+    pub max_restarts: u32,
+    pub window: Duration,
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 5,
+            window: Duration::from_secs(60),
+            base_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RestartPolicy {
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self.base_backoff.saturating_mul(1u32 << attempt.min(6));
+        exponential.min(self.max_backoff)
+    }
+}
+
+/// Drives one supervised child under `group` (a label used only for logging/escalation, so
+/// several related children can be reasoned about together): spawns `make_child()`'s future fresh
+/// on every (re)start, and on abnormal termination waits out `policy`'s backoff and tries again —
+/// up to `policy.max_restarts` within `policy.window`. Beyond that it logs and calls `on_escalate`
+/// once instead of retrying forever. Returns (without restarting) as soon as a child exits
+/// `Ok(())`, or immediately once `shutdown` is cancelled.
+pub async fn supervise<F, Fut>(
+    name: &str,
+    group: &str,
+    policy: RestartPolicy,
+    shutdown: CancellationToken,
+    mut make_child: F,
+    on_escalate: impl Fn(&str),
+) where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+{
+    let mut restarts: Vec<Instant> = Vec::new();
+    let mut attempt = 0u32;
+
+    loop {
+        if shutdown.is_cancelled() {
+            return;
+        }
+
+        let child = tokio::spawn(make_child());
+        let outcome = tokio::select! {
+            res = child => res,
+            _ = shutdown.cancelled() => return,
+        };
+
+        match outcome {
+            Ok(Ok(())) => {
+                tracing::info!(name, group, "Supervised child exited normally");
+                return;
+            }
+            Ok(Err(err)) => {
+                tracing::error!(name, group, %err, "Supervised child returned an error");
+            }
+            Err(join_err) => {
+                tracing::error!(name, group, %join_err, "Supervised child panicked");
+            }
+        }
+
+        let now = Instant::now();
+        restarts.retain(|t| now.duration_since(*t) <= policy.window);
+        restarts.push(now);
+        if restarts.len() as u32 > policy.max_restarts {
+            tracing::error!(
+                name,
+                group,
+                restarts = restarts.len(),
+                window_secs = policy.window.as_secs(),
+                "Supervised child exceeded max restarts within window, escalating"
+            );
+            on_escalate(group);
+            return;
+        }
+
+        let delay = policy.backoff(attempt);
+        attempt = attempt.saturating_add(1);
+        tracing::warn!(
+            name,
+            group,
+            delay_ms = delay.as_millis() as u64,
+            restarts_in_window = restarts.len(),
+            "Restarting supervised child after backoff"
+        );
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = shutdown.cancelled() => return,
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::{
     collections::HashMap,
     fmt::Display,
+    sync::Arc,
     time::{Duration, Instant},
 };
 
@@ -8,14 +9,18 @@ use anyhow::Result;
 use hulyrs::services::transactor::{TransactorClient, backend::http::HttpBackend};
 use indexmap::IndexMap;
 use itertools::Itertools;
+use tiktoken_rs::CoreBPE;
 use tokio::{select, sync::mpsc};
 use tokio_util::sync::CancellationToken;
 
 use crate::{
     HulyAccountInfo,
     communication::types::{CommunicationEvent, ReceivedMessage},
-    config::{AgentMode, Config, JobSchedule, RgbRole},
+    config::{self, AgentMode, Config, JobSchedule, RgbRole},
     context::AgentContext,
+    database::DbClient,
+    memory::MemoryExtractor,
+    task_manager::{TaskLiveState, TaskManager},
     types::Message,
     utils,
 };
@@ -37,6 +42,37 @@ pub struct Task {
     #[allow(dead_code)]
     pub state: TaskState,
     pub cancel_token: CancellationToken,
+    /// Set on tasks `scheduler::SchedulerWorker` fires for a `config::JobDefinition` (`Sleep`,
+    /// `MemoryMantainance`). Lets `Agent::run`'s dispatch loop tell job-originated tasks, which
+    /// are never persisted to the `tasks` table, apart from ordinary chat tasks when deciding how
+    /// to report a failure: via `JobOutcome` to the scheduler instead of
+    /// `AgentState::reschedule_task_with_backoff`.
+    pub job_id: Option<String>,
+}
+
+/// Reported by `Agent::run`'s dispatch loop back to `scheduler::SchedulerWorker` once a
+/// job-originated task (`Task::job_id.is_some()`) finishes, so the job's `config::RetryPolicy` can
+/// kick in instead of the run simply waiting for the next cron tick.
+#[derive(Debug, Clone)]
+pub struct JobOutcome {
+    pub job_id: String,
+    pub error: Option<String>,
+    /// Set when `error` downcasts to `error::AgentError::RateLimited { retry_after: Some(_) }`, so
+    /// `SchedulerWorker::handle_job_outcome` can honor the provider's own backoff hint instead of
+    /// always computing one from `config::RetryPolicy`.
+    pub retry_after: Option<u64>,
+    /// Token/tool-call counters the task accumulated, for `bench::run_workload`'s per-task report.
+    /// Zeroed for task kinds or finish paths that don't track them (e.g. `Sleep`, `Cancelled`).
+    pub metrics: TaskMetrics,
+}
+
+/// Token/tool-call counters a `process_*_task` function accumulated over its run, attached to the
+/// `JobOutcome` it reports so `bench::run_workload` can include them in its per-task report.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TaskMetrics {
+    pub tool_calls: u32,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -48,6 +84,14 @@ pub enum TaskState {
     Completed = 2,
     Cancelled = 3,
     Postponed = 4,
+    /// Actively executing; paired with `heartbeat_at` so a crashed process's tasks can be
+    /// reclaimed on the next startup instead of looking identical to a fresh task.
+    Running = 5,
+    /// Failed but still has retry attempts left; `run_at` holds the backed-off retry time.
+    Failed = 6,
+    /// Failed `max_attempts` times in a row, or once with a non-retryable error; kept around
+    /// (with `last_error`) for inspection instead of being retried forever.
+    DeadLettered = 7,
 }
 
 impl TaskState {
@@ -58,6 +102,9 @@ impl TaskState {
             2 => TaskState::Started,
             3 => TaskState::Completed,
             4 => TaskState::Cancelled,
+            5 => TaskState::Running,
+            6 => TaskState::Failed,
+            7 => TaskState::DeadLettered,
             _ => TaskState::Cancelled,
         }
     }
@@ -68,6 +115,8 @@ pub struct ScheduledAssistantTask {
     pub id: i64,
     pub content: String,
     pub schedule: JobSchedule,
+    pub last_run_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub next_run_at: chrono::DateTime<chrono::Utc>,
 }
 
 impl Task {
@@ -80,6 +129,7 @@ impl Task {
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
             cancel_token: CancellationToken::new(),
+            job_id: None,
         }
     }
 }
@@ -169,16 +219,19 @@ impl TaskKind {
         }
     }
 }
+#[derive(Clone)]
 pub struct Attachment {
     pub file_name: String,
     pub url: String,
 }
 
+#[derive(Clone)]
 pub struct Reaction {
     pub person: String,
     pub reaction: String,
 }
 
+#[derive(Clone)]
 pub struct CardMessage {
     pub message_id: String,
     pub person_info: String,
@@ -251,8 +304,34 @@ impl TaskKind {
             _ => false,
         }
     }
+
+    /// Stable fingerprint of this task's kind, target (card or scheduled task id), and normalized
+    /// content, used by `TaskRouterWorker`'s dedup cache to recognize a retried producer or
+    /// scheduler misfire re-sending work already in flight. `Sleep`/`MemoryMantainance` have no
+    /// natural target/content, so the kind name alone is their fingerprint.
+    pub fn fingerprint(&self) -> String {
+        let target = match self {
+            TaskKind::FollowChat { card_id, .. } | TaskKind::AssistantChat { card_id, .. } => {
+                card_id.clone()
+            }
+            TaskKind::AssistantTask { sheduled_task_id, .. } => sheduled_task_id.to_string(),
+            TaskKind::MemoryMantainance | TaskKind::Sleep => String::new(),
+        };
+        let content = match self {
+            TaskKind::FollowChat { content, .. }
+            | TaskKind::AssistantChat { content, .. }
+            | TaskKind::AssistantTask { content, .. } => content.trim().to_lowercase(),
+            TaskKind::MemoryMantainance | TaskKind::Sleep => String::new(),
+        };
+        crate::storage::content_hash(&format!("{self}|{target}|{content}"))
+    }
 }
 
+/// Window within which a repeated `TaskKind::fingerprint` is treated as a duplicate rather than
+/// new work — long enough to cover a retrying producer or a scheduler misfire, short enough that
+/// genuinely repeated requests (e.g. the same reminder firing again tomorrow) aren't dropped.
+pub const TASK_DEDUP_WINDOW: chrono::Duration = chrono::Duration::minutes(10);
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum TaskFinishReason {
     Completed,
@@ -260,45 +339,123 @@ pub enum TaskFinishReason {
     Cancelled,
 }
 
-fn format_messages<'a>(messages: impl IntoIterator<Item = &'a CardMessage>) -> String {
-    messages
-        .into_iter()
-        .map(|m| {
-            let attachements_block = if m.attachments.is_empty() {
-                "".to_string()
-            } else {
-                format!(
-                    "\n- attachments\n{}",
-                    m.attachments
-                        .iter()
-                        .map(|a| format!("  - [{}]({})", a.file_name, a.url))
-                        .join("\n")
-                )
-            };
-            let reactions_block = if m.reactions.is_empty() {
-                "".to_string()
-            } else {
-                format!(
-                    "\n- reactions\n{}",
-                    m.reactions
-                        .iter()
-                        .map(|r| format!("  - {}|{}", r.person, r.reaction))
-                        .join("\n")
-                )
-            };
-
-            format!(
-                "{}|{} _{}_:\n{}{}{}",
-                m.message_id, m.person_info, m.date, m.content, attachements_block, reactions_block
-            )
-        })
-        .join("\n\n")
+fn render_message(m: &CardMessage) -> String {
+    let attachements_block = if m.attachments.is_empty() {
+        "".to_string()
+    } else {
+        format!(
+            "\n- attachments\n{}",
+            m.attachments
+                .iter()
+                .map(|a| format!("  - [{}]({})", a.file_name, a.url))
+                .join("\n")
+        )
+    };
+    let reactions_block = if m.reactions.is_empty() {
+        "".to_string()
+    } else {
+        format!(
+            "\n- reactions\n{}",
+            m.reactions
+                .iter()
+                .map(|r| format!("  - {}|{}", r.person, r.reaction))
+                .join("\n")
+        )
+    };
+
+    format!(
+        "{}|{} _{}_:\n{}{}{}",
+        m.message_id, m.person_info, m.date, m.content, attachements_block, reactions_block
+    )
+}
+
+/// Picks the BPE encoder matching `model`'s tokenizer family, for `TaskConfig::context_budget`
+/// accounting. `ProviderKind::Anthropic` has no public tokenizer, so `cl100k_base` (GPT-4's
+/// vocabulary) is used as a close-enough approximation for a soft budget — this is not meant to
+/// match exact provider billing.
+pub(crate) fn encoder_for(provider: &config::ProviderKind, model: &str) -> Result<CoreBPE> {
+    match provider {
+        config::ProviderKind::OpenAI | config::ProviderKind::OpenRouter
+            if model.starts_with("gpt-4o") || model.starts_with("o1") || model.starts_with("o3") =>
+        {
+            tiktoken_rs::o200k_base()
+        }
+        _ => tiktoken_rs::cl100k_base(),
+    }
+}
+
+/// Token budget applied when rendering a card's messages for a `FollowChat`/`AssistantChat`
+/// dispatch: `encoder` counts tokens against `tokens`, and anything that doesn't fit is collapsed
+/// into one summary line via `summarizer` (`MemoryConfig::extract_model`) rather than silently
+/// dropped.
+struct MessageBudget<'a> {
+    encoder: &'a CoreBPE,
+    tokens: usize,
+    summarizer: &'a MemoryExtractor,
+}
+
+/// Renders `messages` newest-first against `budget`, keeping as many of the newest whole and
+/// replacing any older overflow with a single summary line. With no `budget`, behaves like a plain
+/// join of every message (the pre-token-budget behavior).
+async fn format_messages(messages: &[CardMessage], budget: Option<&MessageBudget<'_>>) -> String {
+    let Some(budget) = budget else {
+        return messages.iter().map(render_message).join("\n\n");
+    };
+
+    let mut split = messages.len();
+    let mut used_tokens = 0usize;
+    for (i, message) in messages.iter().enumerate().rev() {
+        let tokens = budget.encoder.encode_ordinary(&render_message(message)).len();
+        // Always keep at least the newest message whole, even if it alone exceeds the budget.
+        if i != messages.len() - 1 && used_tokens + tokens > budget.tokens {
+            break;
+        }
+        used_tokens += tokens;
+        split = i;
+    }
+
+    let kept = messages[split..].iter().map(render_message).join("\n\n");
+    if split == 0 {
+        return kept;
+    }
+
+    let overflow_text = messages[..split].iter().map(render_message).join("\n\n");
+    match budget.summarizer.summarize_overflow(&overflow_text).await {
+        Ok(summary) => format!("_(summary of {split} earlier messages)_ {summary}\n\n{kept}"),
+        Err(err) => {
+            tracing::warn!(%err, "Failed to summarize overflow messages, dropping them");
+            kept
+        }
+    }
+}
+
+/// Records `message` in `card_messages`, shared by live ingestion (`process_incoming_event`) and
+/// journal replay on startup so both paths build the same `CardMessage` shape.
+fn record_card_message(
+    card_messages: &mut HashMap<String, IndexMap<String, CardMessage>>,
+    message: &ReceivedMessage,
+) {
+    card_messages
+        .entry(message.card_id.clone())
+        .or_default()
+        .insert(
+            message.message_id.clone(),
+            CardMessage {
+                message_id: message.message_id.clone(),
+                person_info: message.person_info.to_string(),
+                date: message.date.clone(),
+                content: message.content.clone(),
+                attachments: vec![],
+                reactions: vec![],
+            },
+        );
 }
 
 async fn process_incoming_event(
     receiver: &mut mpsc::UnboundedReceiver<CommunicationEvent>,
     card_messages: &mut HashMap<String, IndexMap<String, CardMessage>>,
     social_id: &str,
+    db_client: &DbClient,
 ) -> (bool, Option<ReceivedMessage>) {
     let Some(event) = receiver.recv().await else {
         return (false, None);
@@ -333,20 +490,19 @@ async fn process_incoming_event(
     let CommunicationEvent::Message(new_message) = event else {
         return (true, None);
     };
-    card_messages
-        .entry(new_message.card_id.clone())
-        .or_default()
-        .insert(
-            new_message.message_id.clone(),
-            CardMessage {
-                message_id: new_message.message_id.clone(),
-                person_info: new_message.person_info.to_string(),
-                date: new_message.date.clone(),
-                content: new_message.content.clone(),
-                attachments: vec![],
-                reactions: vec![],
-            },
-        );
+    record_card_message(card_messages, &new_message);
+
+    match serde_json::to_string(&new_message) {
+        Ok(payload) => {
+            if let Err(err) = db_client
+                .journal_card_message(&new_message.card_id, &new_message.message_id, &payload)
+                .await
+            {
+                tracing::warn!(%err, "Failed to journal incoming message");
+            }
+        }
+        Err(err) => tracing::warn!(%err, "Failed to serialize message for journaling"),
+    }
 
     // skip messages from the same social_id for follow mode
     if new_message.social_id == social_id {
@@ -355,20 +511,71 @@ async fn process_incoming_event(
     (true, Some(new_message))
 }
 
+/// How long to postpone a re-check of a throttled message, so a still-saturated multiplexer
+/// doesn't busy-loop `select!` against an already-due `time`.
+const THROTTLE_RECHECK_DELAY: Duration = Duration::from_millis(250);
+
+/// The `config::TaskKind` a waiting message would dispatch as, mirroring the routing logic inside
+/// `task_multiplexer`'s dispatch match — kept in sync with it so the tranquility throttle gates
+/// the same kind it's about to send.
+fn classify(agent_mode: &AgentMode, control_card_id: &Option<String>, card_id: &str) -> config::TaskKind {
+    match agent_mode {
+        AgentMode::Employee(_) => config::TaskKind::FollowChat,
+        AgentMode::PersonalAssistant(_) => match control_card_id {
+            Some(control_card_id)
+                if card_id == control_card_id.as_str()
+                    || card_id.starts_with(&format!("{control_card_id}_")) =>
+            {
+                config::TaskKind::AssistantChat
+            }
+            _ => config::TaskKind::FollowChat,
+        },
+    }
+}
+
+/// A `FollowChat` dispatch whose content rendering (and, on overflow, summarization) is deferred
+/// until after `waiting_messages.retain`'s synchronous closure returns, since `IndexMap::retain`
+/// can't await `MemoryExtractor::summarize_overflow`.
+struct PendingFollowChat {
+    card_id: String,
+    card_title: String,
+    message_id: String,
+    messages: Vec<CardMessage>,
+}
+
 pub async fn task_multiplexer(
     mut receiver: mpsc::UnboundedReceiver<CommunicationEvent>,
     sender: mpsc::UnboundedSender<Task>,
     agent_mode: AgentMode,
     account_info: HulyAccountInfo,
     tx_client: TransactorClient<HttpBackend>,
+    task_manager: Arc<TaskManager>,
+    max_concurrent_tasks: Option<usize>,
+    tranquility: HashMap<config::TaskKind, Duration>,
+    db_client: DbClient,
+    replay: Vec<ReceivedMessage>,
+    context_budgets: HashMap<config::TaskKind, usize>,
+    encoder: Arc<CoreBPE>,
+    memory_extractor: Arc<MemoryExtractor>,
 ) -> Result<()> {
     tracing::debug!("Start task multiplexer");
     let mut last_check_control_card = Instant::now();
     let mut control_card_id = account_info.control_card_id.clone();
+    let mut last_dispatch = HashMap::<config::TaskKind, Instant>::new();
 
     let mut card_messages = HashMap::<String, IndexMap<String, CardMessage>>::new();
     let mut waiting_messages = IndexMap::<String, (ReceivedMessage, Instant)>::new();
 
+    if !replay.is_empty() {
+        tracing::info!(count = replay.len(), "Replaying journaled messages from previous run");
+    }
+    for message in replay {
+        record_card_message(&mut card_messages, &message);
+        waiting_messages
+            .entry(message.card_id.clone())
+            .or_insert_with(|| (message, Instant::now()));
+    }
+
     let mut delay = Duration::from_secs(u64::MAX);
     let recalculate_delay = |waiting_messages: &IndexMap<String, (ReceivedMessage, Instant)>| {
         let now = Instant::now();
@@ -382,7 +589,7 @@ pub async fn task_multiplexer(
     };
     loop {
         select! {
-            (should_continue, new_message) = process_incoming_event(&mut receiver, &mut card_messages, &account_info.social_id) => {
+            (should_continue, new_message) = process_incoming_event(&mut receiver, &mut card_messages, &account_info.social_id, &db_client) => {
                 if !should_continue {
                     break;
                 }
@@ -394,20 +601,36 @@ pub async fn task_multiplexer(
             _ = tokio::time::sleep(delay) => {
                 let now = Instant::now();
                 let mut check_control_card = false;
+                let active_count = task_manager
+                    .list()
+                    .await
+                    .iter()
+                    .filter(|status| status.state == TaskLiveState::Started)
+                    .count();
+                let mut budget = max_concurrent_tasks.map(|max| max.saturating_sub(active_count));
+                let mut dispatched_cards = Vec::<String>::new();
+                let mut pending_follow_chats = Vec::<PendingFollowChat>::new();
                 waiting_messages.retain(|_, (message, time)| if *time > now {
                     true
+                } else if budget == Some(0) {
+                    *time = now + THROTTLE_RECHECK_DELAY;
+                    true
                 } else {
+                    let kind = classify(&agent_mode, &control_card_id, &message.card_id);
+                    let gap = tranquility.get(&kind).copied().unwrap_or_default();
+                    if last_dispatch.get(&kind).is_some_and(|last| now.saturating_duration_since(*last) < gap) {
+                        *time = now + THROTTLE_RECHECK_DELAY;
+                        return true;
+                    }
                     match agent_mode {
                         AgentMode::Employee(_) => {
                             let messages = card_messages.get(&message.card_id).unwrap();
-                            sender.send(Task::new(TaskKind::FollowChat {
+                            pending_follow_chats.push(PendingFollowChat {
                                 card_id: message.card_id.clone(),
                                 card_title: message.card_title.clone().unwrap_or_default(),
                                 message_id: message.message_id.clone(),
-                                content: format_messages(
-                                    messages.values(),
-                                ),
-                            })).unwrap();
+                                messages: messages.values().cloned().collect(),
+                            });
                             if messages.len() > MAX_FOLLOW_MESSAGES as usize {
                                 card_messages.remove(&message.card_id);
                             }
@@ -427,22 +650,44 @@ pub async fn task_multiplexer(
                                 })).unwrap();
                             } else {
                                 let messages = card_messages.get(&message.card_id).unwrap();
-                                sender.send(Task::new(TaskKind::FollowChat {
+                                pending_follow_chats.push(PendingFollowChat {
                                     card_id: message.card_id.clone(),
                                     card_title: message.card_title.clone().unwrap_or_default(),
                                     message_id: message.message_id.clone(),
-                                    content: format_messages(
-                                        messages.values(),
-                                    ),
-                                })).unwrap();
+                                    messages: messages.values().cloned().collect(),
+                                });
                                 if messages.len() > MAX_FOLLOW_MESSAGES as usize {
                                     card_messages.remove(&message.card_id);
                                 }
                             }
                         }
                     }
+                    last_dispatch.insert(kind, now);
+                    if let Some(budget) = &mut budget {
+                        *budget = budget.saturating_sub(1);
+                    }
+                    dispatched_cards.push(message.card_id.clone());
                     false
                 });
+                for card_id in dispatched_cards {
+                    if let Err(err) = db_client.clear_card_journal(&card_id).await {
+                        tracing::warn!(%err, "Failed to clear multiplexer journal");
+                    }
+                }
+                let follow_chat_budget = context_budgets.get(&config::TaskKind::FollowChat).map(|tokens| MessageBudget {
+                    encoder: &encoder,
+                    tokens: *tokens,
+                    summarizer: &memory_extractor,
+                });
+                for pending in pending_follow_chats {
+                    let content = format_messages(&pending.messages, follow_chat_budget.as_ref()).await;
+                    sender.send(Task::new(TaskKind::FollowChat {
+                        card_id: pending.card_id,
+                        card_title: pending.card_title,
+                        message_id: pending.message_id,
+                        content,
+                    })).unwrap();
+                }
                 if check_control_card {
                     control_card_id = utils::get_control_card_id(tx_client.clone()).await;
                 }
@@ -0,0 +1,175 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! Live registry of dispatched `Task`s, mirroring `worker::WorkerManager`'s introspection/control
+//! pattern but for individual one-shot tasks rather than long-running background workers.
+//! `Agent::run`'s dispatch loop registers every `Task` it hands off and reports state transitions
+//! back as the task progresses; a watchdog sweep reclaims tasks that stop reporting in. Exposed
+//! over HTTP by `communication::http::server`'s `/tasks` routes, so an operator can see what's
+//! running and cancel a wedged `FollowChat` or `AssistantTask`.
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
+
+use crate::task::Task;
+
+/// How long a task can go without a state update before the watchdog sweep marks it `Dead`.
+const WATCHDOG_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+/// How often the watchdog sweep runs.
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Runtime state of a registered task, as reported by `TaskManager::list`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskLiveState {
+    /// Handed to the dispatch loop and actively being processed.
+    Started,
+    /// Registered but not yet (or no longer) being actively worked — e.g. waiting its turn.
+    Idle,
+    Completed,
+    Cancelled,
+    /// No state update for longer than `WATCHDOG_TIMEOUT`; presumed wedged.
+    Dead,
+}
+
+/// A registered task's current state, as reported by `TaskManager::list`.
+#[derive(Debug, Clone)]
+pub struct TaskStatus {
+    pub id: i64,
+    pub kind: String,
+    pub state: TaskLiveState,
+    pub created_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+}
+
+struct TaskEntry {
+    status: Arc<RwLock<TaskStatus>>,
+    cancel_token: CancellationToken,
+    last_update: Arc<RwLock<Instant>>,
+}
+
+/// Central registry of dispatched tasks. See module docs.
+#[derive(Default)]
+pub struct TaskManager {
+    tasks: RwLock<HashMap<i64, TaskEntry>>,
+}
+
+impl TaskManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `task` as `Started`, reusing its own `cancel_token` for `cancel()` rather than
+    /// minting a separate one.
+    pub async fn register(&self, task: &Task) {
+        let entry = TaskEntry {
+            status: Arc::new(RwLock::new(TaskStatus {
+                id: task.id,
+                kind: task.kind.to_string(),
+                state: TaskLiveState::Started,
+                created_at: task.created_at,
+                last_error: None,
+            })),
+            cancel_token: task.cancel_token.clone(),
+            last_update: Arc::new(RwLock::new(Instant::now())),
+        };
+        self.tasks.write().await.insert(task.id, entry);
+    }
+
+    async fn set_state(&self, id: i64, state: TaskLiveState) {
+        let tasks = self.tasks.read().await;
+        if let Some(entry) = tasks.get(&id) {
+            entry.status.write().await.state = state;
+            *entry.last_update.write().await = Instant::now();
+        }
+    }
+
+    pub async fn mark_idle(&self, id: i64) {
+        self.set_state(id, TaskLiveState::Idle).await;
+    }
+
+    pub async fn mark_completed(&self, id: i64) {
+        self.set_state(id, TaskLiveState::Completed).await;
+    }
+
+    pub async fn mark_cancelled(&self, id: i64) {
+        self.set_state(id, TaskLiveState::Cancelled).await;
+    }
+
+    /// Records `error` without changing `state` — the caller reports the resulting state (e.g.
+    /// `Idle` after a reschedule-with-backoff) via a separate `mark_*` call.
+    pub async fn mark_failed(&self, id: i64, error: &str) {
+        let tasks = self.tasks.read().await;
+        if let Some(entry) = tasks.get(&id) {
+            let mut status = entry.status.write().await;
+            status.last_error = Some(error.to_string());
+            *entry.last_update.write().await = Instant::now();
+        }
+    }
+
+    pub async fn list(&self) -> Vec<TaskStatus> {
+        let tasks = self.tasks.read().await;
+        let mut statuses = Vec::with_capacity(tasks.len());
+        for entry in tasks.values() {
+            statuses.push(entry.status.read().await.clone());
+        }
+        statuses
+    }
+
+    /// Fires the task's `CancellationToken`, the same mechanism `Task::cancel_token` already
+    /// carries, so the running `process_*_task` call observes it at its next check. `false` if
+    /// `id` isn't currently registered.
+    pub async fn cancel(&self, id: i64) -> bool {
+        let tasks = self.tasks.read().await;
+        match tasks.get(&id) {
+            Some(entry) => {
+                entry.cancel_token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Marks any `Started`/`Idle` task that hasn't had a state update in `WATCHDOG_TIMEOUT` as
+    /// `Dead`, then retires finished entries (`Completed`/`Cancelled`/`Dead`) that have sat
+    /// untouched for just as long, so the registry doesn't grow unbounded. Runs forever; spawn
+    /// alongside the other background loops.
+    pub async fn run_watchdog(self: Arc<Self>) {
+        loop {
+            tokio::time::sleep(WATCHDOG_INTERVAL).await;
+            let now = Instant::now();
+            let mut to_remove = Vec::new();
+            {
+                let tasks = self.tasks.read().await;
+                for (id, entry) in tasks.iter() {
+                    let elapsed = now.saturating_duration_since(*entry.last_update.read().await);
+                    if elapsed <= WATCHDOG_TIMEOUT {
+                        continue;
+                    }
+                    let mut status = entry.status.write().await;
+                    match status.state {
+                        TaskLiveState::Started | TaskLiveState::Idle => {
+                            status.state = TaskLiveState::Dead;
+                        }
+                        TaskLiveState::Completed
+                        | TaskLiveState::Cancelled
+                        | TaskLiveState::Dead => {
+                            to_remove.push(*id);
+                        }
+                    }
+                }
+            }
+            if !to_remove.is_empty() {
+                let mut tasks = self.tasks.write().await;
+                for id in to_remove {
+                    tasks.remove(&id);
+                }
+            }
+        }
+    }
+}
@@ -1,18 +1,46 @@
 // Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
 
+use std::collections::HashMap;
+
 use anyhow::Result;
 use async_trait::async_trait;
+use tokio::sync::{Mutex, mpsc};
 
 use crate::{config::Config, context::AgentContext, state::AgentState, types::ToolResultContent};
 
+/// A human-readable status line a tool reports while it's still running, forwarded to the user
+/// via the typing indicator (see `agent::assistant_chat_task`). Tools with nothing intermediate to
+/// report (the default `ToolImpl::call_with_progress`) never send one.
+#[derive(Debug, Clone)]
+pub struct ToolProgress {
+    pub text: String,
+}
+
+/// The agent's resolved tool set, keyed by tool name. Each tool is behind its own `Mutex` (rather
+/// than the whole map behind one) so independent tool calls within the same turn can run
+/// concurrently instead of serializing on a single `&mut` borrow of the map — see
+/// `agent::channel_task` and `agent::assistant_chat_task`.
+pub type ToolMap = HashMap<String, Mutex<Box<dyn ToolImpl>>>;
+
 pub mod browser;
+pub mod cache;
 pub mod command;
 pub mod files;
 pub mod huly;
 #[cfg(feature = "mcp")]
 pub mod mcp;
+pub mod memory;
 pub mod web;
 
+/// Whether a tool only reads state (`Query`, dispatched as soon as the model calls it) or
+/// performs a side effect visible outside the agent (`Execute`, e.g. sending a message), and so
+/// must clear the execute-confirmation gate in the task loops before it's actually called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolKind {
+    Query,
+    Execute,
+}
+
 #[async_trait]
 pub trait ToolImpl: Send + Sync {
     fn name(&self) -> &str {
@@ -25,7 +53,44 @@ pub trait ToolImpl: Send + Sync {
             .unwrap()
     }
 
+    /// Defaults to `Query`; tools with an externally-visible side effect (e.g. `SendMessageTool`)
+    /// override this to `Execute` so the task loops gate them behind operator approval.
+    fn kind(&self) -> ToolKind {
+        ToolKind::Query
+    }
+
+    /// Whether identical arguments to this tool may be served from `cache::ToolResultCache`
+    /// instead of calling it again. Defaults to `false`; side-effecting or stateful tools (network
+    /// fetches with freshness requirements, `cmd_*`, anything with `kind() == Execute`) must not
+    /// opt in. Overridden by e.g. `WebFetchTool`/`WebSearchTool`, whose results are safe to reuse
+    /// for the configured TTL.
+    fn is_cacheable(&self) -> bool {
+        false
+    }
+
+    /// Whether `call`/`call_with_progress` does synchronous CPU-bound or blocking work (local
+    /// model inference, heavy file parsing, shelling out) rather than just awaiting I/O. Defaults
+    /// to `false`; tools that override it to `true` are run by
+    /// `agent::utils::dispatch_one_tool_call` via `tokio::task::block_in_place` under a bounded
+    /// permit (`Config::max_blocking_tools`) instead of being polled inline, so they can't stall
+    /// the worker thread that every other task shares.
+    fn is_blocking(&self) -> bool {
+        false
+    }
+
     async fn call(&mut self, arguments: serde_json::Value) -> Result<Vec<ToolResultContent>>;
+
+    /// Like `call`, but lets the tool report progress while it runs by sending `ToolProgress`
+    /// updates on `progress`. Defaults to `call` and never sending progress; `McpTool` overrides
+    /// this to forward the MCP server's progress notifications for the in-flight request.
+    async fn call_with_progress(
+        &mut self,
+        arguments: serde_json::Value,
+        _progress: mpsc::Sender<ToolProgress>,
+    ) -> Result<Vec<ToolResultContent>> {
+        self.call(arguments).await
+    }
+
     fn desciption(&self) -> &serde_json::Value;
 }
 
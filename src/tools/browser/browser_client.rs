@@ -13,11 +13,21 @@ use serde::{Deserialize, Serialize};
 use tokio::{
     net::TcpStream,
     select,
-    sync::{RwLock, oneshot},
+    sync::{RwLock, broadcast, oneshot, watch},
 };
+use tokio_stream::wrappers::{BroadcastStream, WatchStream};
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, tungstenite::Message};
 
 const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Bound on how many unconsumed `BrowserEvent`s a subscriber can lag behind before the broadcast
+/// channel starts dropping its oldest events for that subscriber.
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+/// How often `wait_for_selector` re-checks `document.querySelector` while polling.
+const WAIT_FOR_SELECTOR_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Initial delay before the first reconnect attempt, doubled after each failed attempt up to
+/// `RECONNECT_MAX_BACKOFF`.
+const RECONNECT_BASE_BACKOFF: Duration = Duration::from_millis(250);
+const RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(30);
 
 #[derive(Debug, Serialize, Deserialize)]
 struct WsRequest {
@@ -75,11 +85,33 @@ pub enum WsRequestParams {
         tab: i32,
         unicode: u16,
     },
+    Evaluate {
+        tab: i32,
+        expression: String,
+    },
+    SetCookies {
+        tab: i32,
+        cookies: Vec<Cookie>,
+    },
+    GetCookies {
+        tab: i32,
+    },
+    SetExtraHeaders {
+        tab: i32,
+        headers: HashMap<String, String>,
+    },
+    SetViewport {
+        tab: i32,
+        width: u32,
+        height: u32,
+        device_scale_factor: f64,
+        mobile: bool,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct WsResponse {
-    pub id: String,
+    pub id: Option<String>,
     pub result: Option<serde_json::Value>,
     pub error: Option<serde_json::Value>,
 }
@@ -134,6 +166,24 @@ pub struct EmptySuccessResult {
     pub success: bool,
 }
 
+/// A browser cookie, serialized to match the browser bridge's CDP-style `Network.setCookie`/
+/// `Network.getCookies` shape.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    pub domain: String,
+    pub path: String,
+    pub secure: bool,
+    pub http_only: bool,
+    pub expires: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CookiesResult {
+    pub cookies: Vec<Cookie>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
 pub enum BrowserResponseMessageType {
@@ -146,64 +196,413 @@ pub enum BrowserResponseMessageType {
     Dom(String),
     ClickableElements(Vec<ClickableElement>),
 }
-type RequestsMap = Arc<RwLock<HashMap<String, oneshot::Sender<WsResponse>>>>;
-type MessagesSender = RwLock<SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>>;
+
+/// Unsolicited, CDP-style events the browser bridge pushes outside of a request/response cycle
+/// (a tab finished loading, navigation started, the DOM mutated, …), delivered through
+/// `BrowserClient::subscribe` instead of `send_request`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", content = "data", rename_all = "camelCase")]
+pub enum BrowserEvent {
+    TabLoaded {
+        tab: i32,
+    },
+    NavigationStarted {
+        tab: i32,
+        url: String,
+    },
+    NavigationCompleted {
+        tab: i32,
+        url: String,
+    },
+    DomMutated {
+        tab: i32,
+    },
+    ConsoleLog {
+        tab: i32,
+        level: String,
+        message: String,
+    },
+    TabClosed {
+        tab: i32,
+    },
+    /// Mirrors CDP's `Network.requestWillBeSent`. Used by `wait_for_network_idle` to track
+    /// in-flight requests; not otherwise surfaced to tool callers.
+    NetworkRequestStarted {
+        tab: i32,
+        request_id: String,
+    },
+    /// Mirrors CDP's `Network.loadingFinished`/`Network.loadingFailed` (the bridge doesn't
+    /// distinguish the two for idle-tracking purposes).
+    NetworkRequestFinished {
+        tab: i32,
+        request_id: String,
+    },
+    /// Mirrors CDP's `Network.responseReceived`, with the body (if any) already resolved via a
+    /// bridge-side `Network.getResponseBody` call. Consumed by `capture_responses`.
+    NetworkResponseReceived {
+        tab: i32,
+        request_id: String,
+        url: String,
+        status: u16,
+        content_type: String,
+        body: Option<String>,
+    },
+}
+
+impl BrowserEvent {
+    pub fn tab(&self) -> i32 {
+        match self {
+            BrowserEvent::TabLoaded { tab }
+            | BrowserEvent::NavigationStarted { tab, .. }
+            | BrowserEvent::NavigationCompleted { tab, .. }
+            | BrowserEvent::DomMutated { tab }
+            | BrowserEvent::ConsoleLog { tab, .. }
+            | BrowserEvent::TabClosed { tab }
+            | BrowserEvent::NetworkRequestStarted { tab, .. }
+            | BrowserEvent::NetworkRequestFinished { tab, .. }
+            | BrowserEvent::NetworkResponseReceived { tab, .. } => *tab,
+        }
+    }
+}
+
+/// A single captured `Network.responseReceived`, returned by `BrowserClient::capture_responses`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturedResponse {
+    pub url: String,
+    pub status: u16,
+    pub content_type: String,
+    pub body: Option<String>,
+}
+
+/// State of the supervised websocket connection, pushed to `subscribe_connection_state` every
+/// time it changes. `Reconnecting` covers both "lost the connection and backing off" and
+/// "currently retrying" — subscribers that care about attempt counts should watch the
+/// `tracing` logs instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    Reconnecting,
+}
+
+/// A request that hasn't been answered yet. Kept alongside the serialized message so a dropped
+/// connection can replay it on the next connection instead of failing it outright.
+struct PendingRequest {
+    text: String,
+    tx: oneshot::Sender<WsResponse>,
+}
+
+type RequestsMap = Arc<RwLock<HashMap<String, PendingRequest>>>;
+type WsSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+type SharedSink = Arc<RwLock<Option<WsSink>>>;
 
 pub struct BrowserClient {
     ws_url: String,
-    sender: Option<MessagesSender>,
+    sender: SharedSink,
     requests: RequestsMap,
+    events: broadcast::Sender<BrowserEvent>,
+    connection_state: watch::Sender<ConnectionState>,
     id_gen: AtomicU64,
+    supervisor_started: std::sync::atomic::AtomicBool,
 }
 
 impl BrowserClient {
     pub fn new(ws_url: &str) -> Self {
         Self {
             ws_url: ws_url.to_string(),
-            sender: None,
+            sender: Arc::new(RwLock::new(None)),
             requests: Arc::new(RwLock::new(HashMap::new())),
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+            connection_state: watch::channel(ConnectionState::Connecting).0,
             id_gen: AtomicU64::new(1),
+            supervisor_started: std::sync::atomic::AtomicBool::new(false),
         }
     }
 
-    async fn lazy_init(&mut self) -> Result<()> {
-        if self.sender.is_some() {
-            return Ok(());
+    /// Subscribes to `BrowserEvent`s pushed by the browser bridge. Each subscriber gets its own
+    /// lagging window (see `EVENT_CHANNEL_CAPACITY`); events sent before `subscribe` is called
+    /// are never delivered to it.
+    pub fn subscribe(&self) -> BroadcastStream<BrowserEvent> {
+        BroadcastStream::new(self.events.subscribe())
+    }
+
+    /// Observes `ConnectionState` transitions as the supervised connection is established, lost,
+    /// and re-established. Unlike `subscribe`, the first polled value is always the current
+    /// state, since `watch` (unlike `broadcast`) retains it.
+    pub fn subscribe_connection_state(&self) -> WatchStream<ConnectionState> {
+        WatchStream::new(self.connection_state.subscribe())
+    }
+
+    /// Blocks until an event matching `predicate` is observed for `tab`, or `timeout` elapses.
+    /// Useful for waiting on a specific page-lifecycle event instead of relying on the
+    /// `wait_until_loaded` flag baked into `OpenTab`/`Navigate`.
+    pub async fn wait_for_event<F>(
+        &self,
+        tab: i32,
+        predicate: F,
+        timeout: Duration,
+    ) -> Result<BrowserEvent>
+    where
+        F: Fn(&BrowserEvent) -> bool,
+    {
+        let mut events = self.subscribe();
+        tokio::time::timeout(timeout, async {
+            while let Some(event) = events.next().await {
+                if let Ok(event) = event
+                    && event.tab() == tab
+                    && predicate(&event)
+                {
+                    return Some(event);
+                }
+            }
+            None
+        })
+        .await
+        .map_err(|_| anyhow::anyhow!("Timed out waiting for a browser event on tab {tab}"))?
+        .ok_or_else(|| anyhow::anyhow!("Browser event stream closed while waiting on tab {tab}"))
+    }
+
+    /// Resolves once `tab` has had no in-flight request (tracked via `NetworkRequestStarted`/
+    /// `NetworkRequestFinished`) for `quiet_period`, or errors out after `timeout`. The quiet
+    /// timer restarts on every request, so a page that keeps firing requests never goes idle
+    /// until `timeout` catches it.
+    pub async fn wait_for_network_idle(
+        &self,
+        tab: i32,
+        quiet_period: Duration,
+        timeout: Duration,
+    ) -> Result<()> {
+        let mut events = self.subscribe();
+        let mut in_flight: i64 = 0;
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            select! {
+                _ = tokio::time::sleep(quiet_period), if in_flight <= 0 => {
+                    return Ok(());
+                }
+                _ = tokio::time::sleep_until(deadline) => {
+                    return Err(anyhow::anyhow!("Timed out waiting for network idle on tab {tab}"));
+                }
+                event = events.next() => {
+                    match event {
+                        Some(Ok(event)) if event.tab() == tab => match event {
+                            BrowserEvent::NetworkRequestStarted { .. } => in_flight += 1,
+                            BrowserEvent::NetworkRequestFinished { .. } => {
+                                in_flight = (in_flight - 1).max(0);
+                            }
+                            _ => {}
+                        },
+                        Some(_) => {}
+                        None => {
+                            return Err(anyhow::anyhow!(
+                                "Browser event stream closed while waiting for network idle on tab {tab}"
+                            ));
+                        }
+                    }
+                }
+            }
         }
+    }
 
-        tracing::debug!("Connecting to browser websocket: {}", self.ws_url);
-        let (stream, _) = tokio_tungstenite::connect_async(self.ws_url.clone()).await?;
-        let (ws_tx, mut ws_rx) = stream.split();
-        self.sender = Some(RwLock::new(ws_tx));
-        {
-            let requests = Arc::clone(&self.requests);
-            tokio::spawn(async move {
-                while let Some(result) = ws_rx.next().await {
-                    //tracing::trace!("Received message: {:?}", result);
-                    match result {
-                        Ok(message) => {
-                            if let Err(e) = Self::handle_ws_message(&requests, message).await {
-                                tracing::error!("{}", e);
+    /// Resolves once `tab` has fired its load event (`TabLoaded`/`NavigationCompleted`) and the
+    /// network has been idle for `quiet_period`, capped by `timeout`. Used in place of a fixed
+    /// `sleep` as a proxy for "the page is ready to scrape".
+    pub async fn wait_for_ready(
+        &self,
+        tab: i32,
+        quiet_period: Duration,
+        timeout: Duration,
+    ) -> Result<()> {
+        let load_fired = self.wait_for_event(
+            tab,
+            |event| {
+                matches!(
+                    event,
+                    BrowserEvent::TabLoaded { .. } | BrowserEvent::NavigationCompleted { .. }
+                )
+            },
+            timeout,
+        );
+        let network_idle = self.wait_for_network_idle(tab, quiet_period, timeout);
+        tokio::try_join!(load_fired, network_idle)?;
+        Ok(())
+    }
+
+    /// Collects `Network.responseReceived` events for `tab` over `duration`, keeping only those
+    /// whose URL contains `url_filter` (when given) and truncating each body to `max_body_bytes`
+    /// so a chatty page can't blow up the model's context window.
+    pub async fn capture_responses(
+        &self,
+        tab: i32,
+        url_filter: Option<&str>,
+        max_body_bytes: usize,
+        duration: Duration,
+    ) -> Result<Vec<CapturedResponse>> {
+        let mut events = self.subscribe();
+        let mut captured = Vec::new();
+        let deadline = tokio::time::Instant::now() + duration;
+        loop {
+            select! {
+                _ = tokio::time::sleep_until(deadline) => {
+                    break;
+                }
+                event = events.next() => {
+                    match event {
+                        Some(Ok(BrowserEvent::NetworkResponseReceived {
+                            tab: event_tab,
+                            url,
+                            status,
+                            content_type,
+                            body,
+                            ..
+                        })) if event_tab == tab => {
+                            if url_filter.is_none_or(|filter| url.contains(filter)) {
+                                captured.push(CapturedResponse {
+                                    url,
+                                    status,
+                                    content_type,
+                                    body: body.map(|b| crate::utils::safe_truncated(&b, max_body_bytes)),
+                                });
                             }
                         }
-                        Err(e) => {
-                            tracing::error!("{}", e);
+                        Some(_) => {}
+                        None => break,
+                    }
+                }
+            }
+        }
+        Ok(captured)
+    }
+
+    /// Ensures the supervised connection loop is running and waits for it to report
+    /// `Connected` at least once (whether that's the initial connect or a reconnect already
+    /// in flight), so callers never write to a dead sink.
+    async fn lazy_init(&self) -> Result<()> {
+        if !self.supervisor_started.swap(true, std::sync::atomic::Ordering::SeqCst) {
+            tokio::spawn(Self::run_connection_loop(
+                self.ws_url.clone(),
+                Arc::clone(&self.sender),
+                Arc::clone(&self.requests),
+                self.events.clone(),
+                self.connection_state.clone(),
+            ));
+        }
+
+        let mut state = self.connection_state.subscribe();
+        loop {
+            if *state.borrow() == ConnectionState::Connected {
+                return Ok(());
+            }
+            state.changed().await.map_err(|_| {
+                anyhow::anyhow!("Browser connection supervisor stopped unexpectedly")
+            })?;
+        }
+    }
+
+    /// Owns the websocket for the client's whole lifetime: connects, serves requests/events
+    /// until the connection drops, then reconnects with exponential backoff and jitter,
+    /// replaying any requests still waiting in `requests` instead of failing them.
+    async fn run_connection_loop(
+        ws_url: String,
+        sender: SharedSink,
+        requests: RequestsMap,
+        events: broadcast::Sender<BrowserEvent>,
+        state: watch::Sender<ConnectionState>,
+    ) {
+        let mut backoff = RECONNECT_BASE_BACKOFF;
+        loop {
+            tracing::debug!("Connecting to browser websocket: {}", ws_url);
+            match tokio_tungstenite::connect_async(&ws_url).await {
+                Ok((stream, _)) => {
+                    backoff = RECONNECT_BASE_BACKOFF;
+                    let (ws_tx, mut ws_rx) = stream.split();
+                    *sender.write().await = Some(ws_tx);
+                    let _ = state.send(ConnectionState::Connected);
+                    Self::resend_pending_requests(&sender, &requests).await;
+
+                    while let Some(result) = ws_rx.next().await {
+                        match result {
+                            Ok(Message::Ping(payload)) => {
+                                if let Some(sink) = sender.write().await.as_mut()
+                                    && let Err(e) = sink.send(Message::Pong(payload)).await
+                                {
+                                    tracing::error!("Failed to answer browser ws ping: {}", e);
+                                    break;
+                                }
+                            }
+                            Ok(message) => {
+                                if let Err(e) =
+                                    Self::handle_ws_message(&requests, &events, message).await
+                                {
+                                    tracing::error!("{}", e);
+                                }
+                            }
+                            Err(e) => {
+                                tracing::error!("Browser websocket error: {}", e);
+                                break;
+                            }
                         }
                     }
+
+                    tracing::warn!("Browser websocket connection lost, reconnecting");
+                    *sender.write().await = None;
+                }
+                Err(e) => {
+                    tracing::error!("Failed to connect to browser websocket: {}", e);
                 }
-            });
+            }
+
+            let _ = state.send(ConnectionState::Reconnecting);
+            let jitter = Duration::from_millis(rand::random::<u64>() % 250);
+            tokio::time::sleep(backoff + jitter).await;
+            backoff = (backoff * 2).min(RECONNECT_MAX_BACKOFF);
         }
-        Ok(())
     }
 
-    async fn handle_ws_message(requests: &RequestsMap, message: Message) -> Result<()> {
+    /// Re-sends every request still awaiting a response after a (re)connect. Requests that
+    /// arrive while this runs are safe: they're inserted into `requests` before ever touching
+    /// the sink (see `send_request`), so they'll either be covered here or sent fresh afterwards.
+    async fn resend_pending_requests(sender: &SharedSink, requests: &RequestsMap) {
+        let pending = requests.read().await;
+        if pending.is_empty() {
+            return;
+        }
+        tracing::debug!("Replaying {} pending browser request(s)", pending.len());
+        let mut sink = sender.write().await;
+        let Some(sink) = sink.as_mut() else {
+            return;
+        };
+        for pending in pending.values() {
+            if let Err(e) = sink.send(Message::text(pending.text.clone())).await {
+                tracing::error!("Failed to replay pending browser request: {}", e);
+                break;
+            }
+        }
+    }
+
+    async fn handle_ws_message(
+        requests: &RequestsMap,
+        events: &broadcast::Sender<BrowserEvent>,
+        message: Message,
+    ) -> Result<()> {
         match message {
             Message::Text(text) => {
-                let response: WsResponse = serde_json::from_str(&text)?;
-                if let Some(tx) = requests.write().await.remove(&response.id) {
-                    let _ = tx.send(response);
-                } else {
-                    tracing::warn!("No request found for id: {}", response.id);
+                if let Ok(response) = serde_json::from_str::<WsResponse>(&text) {
+                    if let Some(id) = response.id.clone() {
+                        if let Some(pending) = requests.write().await.remove(&id) {
+                            let _ = pending.tx.send(response);
+                        } else {
+                            tracing::warn!("No request found for id: {}", id);
+                        }
+                        return Ok(());
+                    }
+                }
+                match serde_json::from_str::<BrowserEvent>(&text) {
+                    Ok(event) => {
+                        // No receivers yet (or all lagging) is not an error, just a dropped event.
+                        let _ = events.send(event);
+                    }
+                    Err(e) => tracing::warn!("Unrecognized browser message '{}': {}", text, e),
                 }
             }
             Message::Binary(_) => tracing::warn!("Binary message received"),
@@ -230,23 +629,28 @@ impl BrowserClient {
             params,
         };
         tracing::trace!("request: {}", serde_json::to_string_pretty(&request)?);
-
-        self.sender
-            .as_ref()
-            .unwrap()
-            .write()
-            .await
-            .send(Message::text(serde_json::to_string(&request)?))
-            .await?;
+        let text = serde_json::to_string(&request)?;
 
         let (tx, rx) = oneshot::channel();
+        self.requests.write().await.insert(
+            request_id.clone(),
+            PendingRequest {
+                text: text.clone(),
+                tx,
+            },
+        );
 
-        let mut requests = self.requests.write().await;
-        requests.insert(request_id.clone(), tx);
-        drop(requests);
+        if let Some(sink) = self.sender.write().await.as_mut()
+            && let Err(e) = sink.send(Message::text(text)).await
+        {
+            // Leave the request in `self.requests`: the reconnect loop will replay it once the
+            // connection the error just broke is re-established, instead of failing it here.
+            tracing::warn!("Failed to send browser request, awaiting reconnect: {}", e);
+        }
 
         select! {
             _ = tokio::time::sleep(REQUEST_TIMEOUT) => {
+                self.requests.write().await.remove(&request_id);
                 tracing::warn!("Request timed out");
                 Err(anyhow::anyhow!("Request timed out, no response received, request_id: {}", request_id))
             }
@@ -361,6 +765,114 @@ impl BrowserClient {
         .await?;
         Ok(())
     }
+
+    /// Runs `expression` in the page and returns its result, mirroring CDP's `Runtime.evaluate`.
+    pub async fn eval_json(&mut self, tab: i32, expression: &str) -> Result<serde_json::Value> {
+        self.send_request::<serde_json::Value>(WsRequestParams::Evaluate {
+            tab,
+            expression: expression.to_string(),
+        })
+        .await
+    }
+
+    /// Like `eval_json`, but returns the result as a string (unwrapping a JSON string result
+    /// instead of quoting it, so `eval_string(tab, "document.title")` yields the title verbatim).
+    pub async fn eval_string(&mut self, tab: i32, expression: &str) -> Result<String> {
+        match self.eval_json(tab, expression).await? {
+            serde_json::Value::String(s) => Ok(s),
+            other => Ok(other.to_string()),
+        }
+    }
+
+    /// Polls `document.querySelector(css) != null` every `WAIT_FOR_SELECTOR_POLL_INTERVAL` until
+    /// it's true or `timeout` elapses.
+    pub async fn wait_for_selector(&mut self, tab: i32, css: &str, timeout: Duration) -> Result<()> {
+        let expression = format!(
+            "document.querySelector({}) != null",
+            serde_json::to_string(css)?
+        );
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            if self.eval_json(tab, &expression).await?.as_bool() == Some(true) {
+                return Ok(());
+            }
+            if tokio::time::Instant::now() >= deadline {
+                anyhow::bail!("Timed out waiting for selector '{css}' on tab {tab}");
+            }
+            tokio::time::sleep(WAIT_FOR_SELECTOR_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Clicks the first element matching `css`, targeting it by selector instead of the integer
+    /// `element_id` produced by `GetClickableElements`.
+    pub async fn click_selector(&mut self, tab: i32, css: &str) -> Result<()> {
+        let expression = format!(
+            "(() => {{ const el = document.querySelector({}); if (!el) throw new Error('selector not found'); el.click(); return true; }})()",
+            serde_json::to_string(css)?
+        );
+        self.eval_json(tab, &expression).await?;
+        Ok(())
+    }
+
+    /// Focuses the first element matching `css` and types `text` into it character by character
+    /// via the existing `type_char` key-event plumbing.
+    pub async fn type_into(&mut self, tab: i32, css: &str, text: &str) -> Result<()> {
+        let expression = format!(
+            "(() => {{ const el = document.querySelector({}); if (!el) throw new Error('selector not found'); el.focus(); return true; }})()",
+            serde_json::to_string(css)?
+        );
+        self.eval_json(tab, &expression).await?;
+        for c in text.chars() {
+            self.type_char(tab, c).await?;
+        }
+        Ok(())
+    }
+
+    /// Seeds `tab`'s cookie jar, e.g. to inject a Huly auth cookie obtained elsewhere before
+    /// navigating to a page that requires a logged-in session.
+    pub async fn set_cookies(&mut self, tab: i32, cookies: Vec<Cookie>) -> Result<()> {
+        self.send_request::<EmptySuccessResult>(WsRequestParams::SetCookies { tab, cookies })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_cookies(&mut self, tab: i32) -> Result<Vec<Cookie>> {
+        let resp = self
+            .send_request::<CookiesResult>(WsRequestParams::GetCookies { tab })
+            .await?;
+        Ok(resp.cookies)
+    }
+
+    pub async fn set_extra_headers(
+        &mut self,
+        tab: i32,
+        headers: HashMap<String, String>,
+    ) -> Result<()> {
+        self.send_request::<EmptySuccessResult>(WsRequestParams::SetExtraHeaders { tab, headers })
+            .await?;
+        Ok(())
+    }
+
+    /// Controls the tab's viewport and device scale factor, so `take_screenshot` can capture at
+    /// a controlled DPR instead of the hardcoded 1920x1080 `open_url` opens tabs at.
+    pub async fn set_viewport(
+        &mut self,
+        tab: i32,
+        width: u32,
+        height: u32,
+        device_scale_factor: f64,
+        mobile: bool,
+    ) -> Result<()> {
+        self.send_request::<EmptySuccessResult>(WsRequestParams::SetViewport {
+            tab,
+            width,
+            height,
+            device_scale_factor,
+            mobile,
+        })
+        .await?;
+        Ok(())
+    }
 }
 
 pub struct BrowserClientSingleTab {
@@ -388,6 +900,49 @@ impl BrowserClientSingleTab {
         Ok(())
     }
 
+    /// See `BrowserClient::wait_for_ready`.
+    pub async fn wait_for_ready(&self, quiet_period: Duration, timeout: Duration) -> Result<()> {
+        self.browser_client
+            .wait_for_ready(self.tab_id, quiet_period, timeout)
+            .await
+    }
+
+    /// See `BrowserClient::eval_json`.
+    pub async fn eval_json(&mut self, expression: &str) -> Result<serde_json::Value> {
+        self.browser_client.eval_json(self.tab_id, expression).await
+    }
+
+    /// See `BrowserClient::eval_string`.
+    pub async fn eval_string(&mut self, expression: &str) -> Result<String> {
+        self.browser_client
+            .eval_string(self.tab_id, expression)
+            .await
+    }
+
+    /// See `BrowserClient::click_selector`.
+    pub async fn click_selector(&mut self, css: &str) -> Result<()> {
+        self.browser_client.click_selector(self.tab_id, css).await
+    }
+
+    /// See `BrowserClient::type_into`.
+    pub async fn type_into(&mut self, css: &str, text: &str) -> Result<()> {
+        self.browser_client
+            .type_into(self.tab_id, css, text)
+            .await
+    }
+
+    /// See `BrowserClient::capture_responses`.
+    pub async fn capture_responses(
+        &self,
+        url_filter: Option<&str>,
+        max_body_bytes: usize,
+        duration: Duration,
+    ) -> Result<Vec<CapturedResponse>> {
+        self.browser_client
+            .capture_responses(self.tab_id, url_filter, max_body_bytes, duration)
+            .await
+    }
+
     pub async fn get_clickable_elements(&mut self) -> Result<Vec<ClickableElement>> {
         self.browser_client
             .get_clickable_elements(self.tab_id)
@@ -426,6 +981,32 @@ impl BrowserClientSingleTab {
     pub async fn type_char(&mut self, c: char) -> Result<()> {
         self.browser_client.type_char(self.tab_id, c).await
     }
+
+    pub async fn set_cookies(&mut self, cookies: Vec<Cookie>) -> Result<()> {
+        self.browser_client.set_cookies(self.tab_id, cookies).await
+    }
+
+    pub async fn get_cookies(&mut self) -> Result<Vec<Cookie>> {
+        self.browser_client.get_cookies(self.tab_id).await
+    }
+
+    pub async fn set_extra_headers(&mut self, headers: HashMap<String, String>) -> Result<()> {
+        self.browser_client
+            .set_extra_headers(self.tab_id, headers)
+            .await
+    }
+
+    pub async fn set_viewport(
+        &mut self,
+        width: u32,
+        height: u32,
+        device_scale_factor: f64,
+        mobile: bool,
+    ) -> Result<()> {
+        self.browser_client
+            .set_viewport(self.tab_id, width, height, device_scale_factor, mobile)
+            .await
+    }
 }
 
 #[cfg(test)]
@@ -17,6 +17,17 @@ use crate::{
 
 mod browser_client;
 
+/// How long the network must be idle before `OpenPageTool` considers a page settled. See
+/// `browser_client::BrowserClient::wait_for_ready`.
+const PAGE_NETWORK_IDLE_QUIET_PERIOD: Duration = Duration::from_millis(500);
+/// Overall cap on `wait_for_ready`, in case the page never settles (e.g. a long-polling
+/// connection keeps the network busy forever).
+const PAGE_READY_TIMEOUT: Duration = Duration::from_secs(10);
+/// Default window `CaptureResponsesTool` listens for network responses over.
+const DEFAULT_CAPTURE_DURATION: Duration = Duration::from_secs(5);
+/// Default cap on how many bytes of a single response body `CaptureResponsesTool` keeps.
+const DEFAULT_CAPTURE_MAX_BODY_BYTES: usize = 4096;
+
 type BrowserClientRef = Arc<RwLock<browser_client::BrowserClientSingleTab>>;
 pub struct BrowserToolSet {
     browser_client: Option<BrowserClientRef>,
@@ -104,6 +115,21 @@ impl ToolSet for BrowserToolSet {
                 Box::new(TypeTextTool {
                     client: browser_client.clone(),
                 }),
+                Box::new(QuerySelectorTool {
+                    client: browser_client.clone(),
+                }),
+                Box::new(ClickSelectorTool {
+                    client: browser_client.clone(),
+                }),
+                Box::new(TypeIntoSelectorTool {
+                    client: browser_client.clone(),
+                }),
+                Box::new(GetPageTextTool {
+                    client: browser_client.clone(),
+                }),
+                Box::new(CaptureResponsesTool {
+                    client: browser_client.clone(),
+                }),
             ]
         } else {
             tracing::warn!("Browser is not configured");
@@ -138,7 +164,15 @@ impl ToolImpl for OpenPageTool {
     async fn call(&mut self, arguments: serde_json::Value) -> Result<Vec<ToolResultContent>> {
         let args = serde_json::from_value::<OpenUrlToolArgs>(arguments)?;
         self.client.write().await.open_url(&args.url).await?;
-        tokio::time::sleep(Duration::from_secs(3)).await;
+        if let Err(e) = self
+            .client
+            .read()
+            .await
+            .wait_for_ready(PAGE_NETWORK_IDLE_QUIET_PERIOD, PAGE_READY_TIMEOUT)
+            .await
+        {
+            tracing::warn!("Page readiness wait failed, scraping anyway: {}", e);
+        }
         let elements = self.client.write().await.get_clickable_elements().await?;
         let elements = elements
             .iter()
@@ -306,3 +340,165 @@ impl ToolImpl for TypeTextTool {
         ))])
     }
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct QuerySelectorToolArgs {
+    selector: String,
+    /// Treats `selector` as an XPath expression instead of a CSS selector.
+    #[serde(default)]
+    xpath: bool,
+}
+
+struct QuerySelectorTool {
+    client: BrowserClientRef,
+}
+
+#[async_trait]
+impl ToolImpl for QuerySelectorTool {
+    fn name(&self) -> &str {
+        "browser-query-selector"
+    }
+
+    async fn call(&mut self, arguments: serde_json::Value) -> Result<Vec<ToolResultContent>> {
+        let args = serde_json::from_value::<QuerySelectorToolArgs>(arguments)?;
+        let selector = serde_json::to_string(&args.selector)?;
+        let nodes_expr = if args.xpath {
+            format!(
+                "(() => {{ const r = document.evaluate({selector}, document, null, XPathResult.ORDERED_NODE_SNAPSHOT_TYPE, null); const out = []; for (let i = 0; i < r.snapshotLength; i++) out.push(r.snapshotItem(i)); return out; }})()"
+            )
+        } else {
+            format!("Array.from(document.querySelectorAll({selector}))")
+        };
+        let expression = format!(
+            "{nodes_expr}.map(el => ({{tag: el.tagName ? el.tagName.toLowerCase() : '', text: (el.textContent || '').trim(), attributes: el.attributes ? Object.fromEntries(Array.from(el.attributes).map(a => [a.name, a.value])) : {{}}}}))"
+        );
+        let result = self.client.write().await.eval_json(&expression).await?;
+        Ok(vec![ToolResultContent::text(serde_json::to_string_pretty(
+            &result,
+        )?)])
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ClickSelectorToolArgs {
+    selector: String,
+}
+
+struct ClickSelectorTool {
+    client: BrowserClientRef,
+}
+
+#[async_trait]
+impl ToolImpl for ClickSelectorTool {
+    fn name(&self) -> &str {
+        "browser-click-selector"
+    }
+
+    async fn call(&mut self, arguments: serde_json::Value) -> Result<Vec<ToolResultContent>> {
+        let args = serde_json::from_value::<ClickSelectorToolArgs>(arguments)?;
+        self.client
+            .write()
+            .await
+            .click_selector(&args.selector)
+            .await?;
+        Ok(vec![ToolResultContent::text(format!(
+            "Clicked element matching selector '{}'",
+            args.selector
+        ))])
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TypeIntoSelectorToolArgs {
+    selector: String,
+    text: String,
+}
+
+struct TypeIntoSelectorTool {
+    client: BrowserClientRef,
+}
+
+#[async_trait]
+impl ToolImpl for TypeIntoSelectorTool {
+    fn name(&self) -> &str {
+        "browser-type-into-selector"
+    }
+
+    async fn call(&mut self, arguments: serde_json::Value) -> Result<Vec<ToolResultContent>> {
+        let args = serde_json::from_value::<TypeIntoSelectorToolArgs>(arguments)?;
+        self.client
+            .write()
+            .await
+            .type_into(&args.selector, &args.text)
+            .await?;
+        Ok(vec![ToolResultContent::text(format!(
+            "Text '{}' typed into element matching selector '{}'",
+            args.text, args.selector
+        ))])
+    }
+}
+
+struct GetPageTextTool {
+    client: BrowserClientRef,
+}
+
+#[async_trait]
+impl ToolImpl for GetPageTextTool {
+    fn name(&self) -> &str {
+        "browser-get-page-text"
+    }
+
+    async fn call(&mut self, _arguments: serde_json::Value) -> Result<Vec<ToolResultContent>> {
+        let text = self
+            .client
+            .write()
+            .await
+            .eval_string("document.body.innerText")
+            .await?;
+        Ok(vec![ToolResultContent::text(text)])
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CaptureResponsesToolArgs {
+    /// Only keep responses whose URL contains this substring.
+    #[serde(default)]
+    url_filter: Option<String>,
+    /// How long to listen for responses, in seconds.
+    #[serde(default)]
+    duration_secs: Option<u64>,
+    /// Cap on how many bytes of each response body to keep.
+    #[serde(default)]
+    max_body_bytes: Option<usize>,
+}
+
+struct CaptureResponsesTool {
+    client: BrowserClientRef,
+}
+
+#[async_trait]
+impl ToolImpl for CaptureResponsesTool {
+    fn name(&self) -> &str {
+        "browser-capture-responses"
+    }
+
+    async fn call(&mut self, arguments: serde_json::Value) -> Result<Vec<ToolResultContent>> {
+        let args = serde_json::from_value::<CaptureResponsesToolArgs>(arguments)?;
+        let duration = args
+            .duration_secs
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_CAPTURE_DURATION);
+        let max_body_bytes = args
+            .max_body_bytes
+            .unwrap_or(DEFAULT_CAPTURE_MAX_BODY_BYTES);
+        let captured = self
+            .client
+            .read()
+            .await
+            .capture_responses(args.url_filter.as_deref(), max_body_bytes, duration)
+            .await?;
+        Ok(vec![ToolResultContent::text(serde_json::to_string_pretty(
+            &captured,
+        )?)])
+    }
+}
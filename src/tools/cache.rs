@@ -0,0 +1,84 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! Content-addressed cache for idempotent tool results, so a model that re-requests the same
+//! `web_fetch`/`web_search` call within a multi-step loop reuses the prior response instead of
+//! hitting the network again. Opt-in per tool via `ToolImpl::is_cacheable`.
+
+use std::{collections::HashMap, time::Duration};
+
+use serde_json::Value;
+use tokio::{sync::RwLock, time::Instant};
+
+use crate::types::ToolResultContent;
+
+struct CacheEntry {
+    result: Vec<ToolResultContent>,
+    inserted_at: Instant,
+}
+
+/// Keyed by `(tool_name, blake3 hash of canonicalized arguments)`, so argument objects that are
+/// logically identical but differently ordered (e.g. across providers/serializers) still hit.
+pub struct ToolResultCache {
+    entries: RwLock<HashMap<(String, String), CacheEntry>>,
+    ttl: Duration,
+}
+
+impl ToolResultCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: RwLock::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    /// Returns the cached result for `(tool_name, arguments)` if present and not yet past `ttl`.
+    pub async fn get(&self, tool_name: &str, arguments: &Value) -> Option<Vec<ToolResultContent>> {
+        let key = cache_key(tool_name, arguments);
+        let entries = self.entries.read().await;
+        entries.get(&key).and_then(|entry| entry.result_if_fresh(self.ttl))
+    }
+
+    pub async fn insert(&self, tool_name: &str, arguments: &Value, result: Vec<ToolResultContent>) {
+        let key = cache_key(tool_name, arguments);
+        self.entries.write().await.insert(
+            key,
+            CacheEntry {
+                result,
+                inserted_at: Instant::now(),
+            },
+        );
+    }
+}
+
+impl CacheEntry {
+    fn result_if_fresh(&self, ttl: Duration) -> Option<Vec<ToolResultContent>> {
+        (self.inserted_at.elapsed() < ttl).then(|| self.result.clone())
+    }
+}
+
+fn cache_key(tool_name: &str, arguments: &Value) -> (String, String) {
+    let canonical = canonicalize(arguments).to_string();
+    (tool_name.to_string(), blake3::hash(canonical.as_bytes()).to_hex().to_string())
+}
+
+/// Recursively sorts object keys so two argument values that differ only in key order hash
+/// identically, regardless of whether `serde_json`'s `Map` preserves insertion order elsewhere in
+/// the dependency graph.
+fn canonicalize(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut sorted: std::collections::BTreeMap<&String, Value> = std::collections::BTreeMap::new();
+            for (key, value) in map {
+                sorted.insert(key, canonicalize(value));
+            }
+            Value::Object(
+                sorted
+                    .into_iter()
+                    .map(|(key, value)| (key.clone(), value))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonicalize).collect()),
+        other => other.clone(),
+    }
+}
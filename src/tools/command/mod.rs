@@ -23,6 +23,11 @@ use crate::{
 pub mod process_registry;
 
 const COMMAND_TIMEOUT: u64 = 300; // 30 secs
+/// Output size at which a still-running command is treated as a long-lived server/daemon rather
+/// than a short one-shot: `ExecuteCommandTool` returns early with the live `command_id` instead of
+/// busy-polling for the rest of `COMMAND_TIMEOUT`, and the model follows up with
+/// `cmd_get_result`/`since_offset`.
+const COMMAND_OUTPUT_STREAM_THRESHOLD: usize = 4096;
 
 pub struct CommandsToolSet;
 
@@ -88,6 +93,11 @@ struct ExecuteCommandToolArgs {
 #[derive(Debug, Clone, Deserialize)]
 pub struct GetCommandResultToolArgs {
     pub command_id: usize,
+    /// Byte offset into the command's output buffer to resume from, as reported by a previous
+    /// `cmd_exec`/`cmd_get_result` call. Lets chatty long-running commands be polled for only the
+    /// output produced since the last read instead of resending the whole buffer every time.
+    #[serde(default)]
+    pub since_offset: usize,
 }
 
 pub struct GetCommandResultTool {
@@ -111,6 +121,10 @@ impl ToolImpl for ExecuteCommandTool {
         &self.description
     }
 
+    // Note: `ToolImpl::call` isn't passed the task's `CancellationToken` (see
+    // `agent::utils::dispatch_one_tool_call`, which already races the whole call future against
+    // one), so this loop can't itself `select!` on cancellation or call `stop_process` when
+    // dropped early — only the output-size early-return below is addressable from in here.
     async fn call(&mut self, args: serde_json::Value) -> Result<Vec<ToolResultContent>> {
         let args = serde_json::from_value::<ExecuteCommandToolArgs>(args)?;
         tracing::info!("Execute command '{}'", args.command);
@@ -132,6 +146,12 @@ impl ToolImpl for ExecuteCommandTool {
                     ))]);
                 }
                 command_output = output.to_string();
+                if command_output.len() >= COMMAND_OUTPUT_STREAM_THRESHOLD {
+                    let offset = command_output.len();
+                    return Ok(vec![ToolResultContent::text(format!(
+                        "Command ID: {command_id}\nCommand is still running (output exceeds {COMMAND_OUTPUT_STREAM_THRESHOLD} bytes, likely a long-lived process).\nUse cmd_get_result with since_offset={offset} to fetch new output.\nOutput so far:\n{command_output}"
+                    ))]);
+                }
             } else {
                 anyhow::bail!("Command '{}' not found", args.command);
             }
@@ -158,16 +178,18 @@ impl ToolImpl for GetCommandResultTool {
             .await
             .get_process(args.command_id)
         {
+            let new_output = output.get(args.since_offset..).unwrap_or(&output);
+            let next_offset = output.len();
             Ok(vec![ToolResultContent::text(
                 if let Some(exit_status) = exit_status {
                     format!(
-                        "Command ID: {}\nExit Status: Exited({})\nOutput:\n{}",
-                        args.command_id, exit_status, output
+                        "Command ID: {}\nExit Status: Exited({})\nNext Offset: {}\nOutput:\n{}",
+                        args.command_id, exit_status, next_offset, new_output
                     )
                 } else {
                     format!(
-                        "Command ID: {}\nCommand Still Running\nOutput:\n{}",
-                        args.command_id, output
+                        "Command ID: {}\nCommand Still Running\nNext Offset: {}\nOutput:\n{}",
+                        args.command_id, next_offset, new_output
                     )
                 },
             )])
@@ -5,24 +5,40 @@ use std::{
     fs,
     io::Cursor,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use anyhow::Result;
 use async_trait::async_trait;
+use base64::Engine;
 use grep_printer::StandardBuilder;
 use grep_regex::RegexMatcher;
 use grep_searcher::{BinaryDetection, SearcherBuilder};
 use serde::Deserialize;
+use uuid::Uuid;
 
 use crate::{
     config::Config,
     context::AgentContext,
     state::AgentState,
-    tools::{ToolImpl, ToolSet},
-    types::ToolResultContent,
+    tools::{ToolImpl, ToolKind, ToolSet},
+    types::{ImageMediaType, ToolResultContent, VideoMediaType},
     utils::{normalize_path, workspace_to_string},
 };
 
+/// `fs_read` truncates a file's content to this many bytes when the caller doesn't pass
+/// `max_bytes`, so a huge log or dataset can't blow out the model's context window.
+const DEFAULT_MAX_READ_BYTES: usize = 256 * 1024;
+/// `fs_run` kills the interpreter after this many seconds when the caller doesn't pass
+/// `timeout_secs`.
+const DEFAULT_RUN_CODE_TIMEOUT_SECS: u64 = 30;
+/// Directory (relative to the workspace) `fs_run` points the interpreter's output-file
+/// environment variable at. Image files dropped here are read back as `ToolResultContent::image`.
+const RUN_CODE_OUTPUT_DIR_ENV: &str = "FS_RUN_OUTPUT_DIR";
+/// `fs_search` stops printing once this many matches have been found when the caller doesn't
+/// pass `max_results`, so a broad regex over a large repo can't blow out the model's context.
+const DEFAULT_MAX_SEARCH_RESULTS: usize = 200;
+
 pub struct FilesToolSet;
 
 impl ToolSet for FilesToolSet {
@@ -44,7 +60,7 @@ impl ToolSet for FilesToolSet {
         .into_iter()
         .map(|v| (v["function"]["name"].as_str().unwrap().to_string(), v))
         .collect::<HashMap<String, serde_json::Value>>();
-        vec![
+        let mut tools: Vec<Box<dyn ToolImpl>> = vec![
             Box::new(ReadFileTool {
                 workspace: config.workspace.clone(),
                 description: descriptions.remove("fs_read").unwrap(),
@@ -65,7 +81,19 @@ impl ToolSet for FilesToolSet {
                 workspace: config.workspace.clone(),
                 description: descriptions.remove("fs_search").unwrap(),
             }),
-        ]
+        ];
+
+        if let Some(code_execution) = &config.code_execution {
+            tools.push(Box::new(RunCodeTool {
+                workspace: config.workspace.clone(),
+                allowed_languages: code_execution.allowed_languages.clone(),
+                description: descriptions.remove("fs_run").unwrap(),
+            }));
+        } else {
+            tracing::debug!("Code execution is not configured, fs_run is disabled");
+        }
+
+        tools
     }
 
     fn get_system_prompt(&self, _config: &Config) -> String {
@@ -89,6 +117,9 @@ struct ReadFileTool {
 #[derive(Deserialize)]
 struct ReadFileToolArgs {
     path: String,
+    /// Caps how many bytes are read. Defaults to `DEFAULT_MAX_READ_BYTES` so a huge file gets
+    /// truncated instead of blowing up the model's context.
+    max_bytes: Option<usize>,
 }
 
 #[async_trait]
@@ -100,11 +131,52 @@ impl ToolImpl for ReadFileTool {
     async fn call(&mut self, args: serde_json::Value) -> Result<Vec<ToolResultContent>> {
         let args = serde_json::from_value::<ReadFileToolArgs>(args)?;
         let path = normalize_path(&self.workspace, &args.path);
+        let max_bytes = args.max_bytes.unwrap_or(DEFAULT_MAX_READ_BYTES);
         tracing::info!("Reading file {}", path);
-        Ok(vec![ToolResultContent::text(fs::read_to_string(path)?)])
+
+        let ext = Path::new(&path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
+        let mime_type = mime_guess::from_ext(ext).first_or_octet_stream();
+
+        if let Some(media_type) = ImageMediaType::from_mime_type(mime_type.essence_str()) {
+            let mut content = fs::read(&path)?;
+            content.truncate(max_bytes);
+            let data = base64::engine::general_purpose::STANDARD.encode(content);
+            return Ok(vec![ToolResultContent::image(data, Some(media_type))]);
+        }
+
+        if let Some(media_type) = VideoMediaType::from_mime_type(mime_type.essence_str()) {
+            let mut content = fs::read(&path)?;
+            content.truncate(max_bytes);
+            let data = base64::engine::general_purpose::STANDARD.encode(content);
+            return Ok(vec![ToolResultContent::video(data, Some(media_type))]);
+        }
+
+        let bytes = fs::read(&path)?;
+        match String::from_utf8(bytes) {
+            Ok(mut content) => {
+                if content.len() > max_bytes {
+                    content.truncate(floor_char_boundary(&content, max_bytes));
+                    content.push_str("\n... (truncated)");
+                }
+                Ok(vec![ToolResultContent::text(content)])
+            }
+            Err(err) => Ok(vec![ToolResultContent::text(format!(
+                "Binary file, {} bytes",
+                err.into_bytes().len()
+            ))]),
+        }
     }
 }
 
+/// The largest byte offset `<= max` that lands on a UTF-8 character boundary in `s`, so
+/// truncating there never panics or splits a multi-byte character.
+fn floor_char_boundary(s: &str, max: usize) -> usize {
+    (0..=max).rev().find(|&i| s.is_char_boundary(i)).unwrap_or(0)
+}
+
 #[derive(Deserialize)]
 struct WriteToFileToolArgs {
     pub path: String,
@@ -207,13 +279,19 @@ impl ToolImpl for ReplaceInFileTool {
         let replace_diffs = parse_replace_diff(&args.diff)?;
         let original_content = fs::read_to_string(path.clone())?;
         let mut modified_content = original_content.clone();
+        let mut strategies = Vec::new();
         for replace_diff in replace_diffs {
             let search = &replace_diff.search;
             let replace = &replace_diff.replace;
-            let start = original_content.find(search);
-            if let Some(start) = start {
+            if let Some(start) = original_content.find(search) {
                 let end = start + search.len();
                 modified_content.replace_range(start..end, replace);
+                strategies.push("exact match");
+            } else if let Some((start, end, reindented_replace)) =
+                find_normalized_match(&original_content, search, replace)
+            {
+                modified_content.replace_range(start..end, &reindented_replace);
+                strategies.push("whitespace-tolerant fallback match");
             } else {
                 anyhow::bail!(format!("Search string not found: {}", search));
             }
@@ -221,11 +299,110 @@ impl ToolImpl for ReplaceInFileTool {
         let diff = create_patch(&original_content, &modified_content);
         fs::write(path, modified_content)?;
         Ok(vec![ToolResultContent::text(format!(
-            "The user made the following updates to your content:\n\n{diff}"
+            "The user made the following updates to your content (matched via {}):\n\n{diff}",
+            strategies.join(", ")
         ))])
     }
 }
 
+/// The leading run of whitespace characters in `line`.
+fn leading_whitespace(line: &str) -> &str {
+    let trimmed = line.trim_start();
+    &line[..line.len() - trimmed.len()]
+}
+
+/// Each line of `s` as `(start_byte, end_byte, line)`, where the byte range excludes the
+/// line's trailing `\n` (if any).
+fn line_spans(s: &str) -> Vec<(usize, usize, &str)> {
+    let mut spans = Vec::new();
+    let mut start = 0;
+    for line in s.split_inclusive('\n') {
+        let stripped = line.strip_suffix('\n').unwrap_or(line);
+        spans.push((start, start + stripped.len(), stripped));
+        start += line.len();
+    }
+    spans
+}
+
+/// Re-indents `replace` for a match whose indentation differs from the SEARCH block's: strips
+/// each non-blank line's `search_indent` prefix (if present) and substitutes `file_indent`,
+/// preserving the matched region's original indentation style (tabs vs. spaces, depth) instead
+/// of inserting the SEARCH block's indentation verbatim.
+fn reindent_replace(replace: &str, search_indent: &str, file_indent: &str) -> String {
+    if search_indent == file_indent {
+        return replace.to_string();
+    }
+    replace
+        .split_inclusive('\n')
+        .map(|line| {
+            let (content, newline) = match line.strip_suffix('\n') {
+                Some(content) => (content, "\n"),
+                None => (line, ""),
+            };
+            if content.trim().is_empty() {
+                format!("{content}{newline}")
+            } else if let Some(rest) = content.strip_prefix(search_indent) {
+                format!("{file_indent}{rest}{newline}")
+            } else {
+                format!("{content}{newline}")
+            }
+        })
+        .collect()
+}
+
+/// Fallback for `ReplaceInFileTool` when an exact `search` match isn't found: compares `content`
+/// and `search` line-by-line after trimming each line (tolerating trailing whitespace and
+/// leading-indentation differences), and if the normalized line sequence is found, returns the
+/// byte range of the match in `content` plus `replace` re-indented to match it.
+fn find_normalized_match(content: &str, search: &str, replace: &str) -> Option<(usize, usize, String)> {
+    let search_lines: Vec<&str> = search.lines().collect();
+    if search_lines.is_empty() {
+        return None;
+    }
+    let normalized_search: Vec<&str> = search_lines.iter().map(|line| line.trim()).collect();
+    let file_spans = line_spans(content);
+    if file_spans.len() < normalized_search.len() {
+        return None;
+    }
+
+    for window_start in 0..=(file_spans.len() - normalized_search.len()) {
+        let window = &file_spans[window_start..window_start + normalized_search.len()];
+        let is_match = window
+            .iter()
+            .zip(normalized_search.iter())
+            .all(|((_, _, line), norm)| line.trim() == *norm);
+        if !is_match {
+            continue;
+        }
+
+        let match_start = window[0].0;
+        let mut match_end = window[window.len() - 1].1;
+        // `search` always ends with a newline (see `parse_replace_diff`), so consume the matched
+        // region's trailing newline too rather than leaving a stray blank line behind.
+        if content[match_end..].starts_with('\n') {
+            match_end += 1;
+        }
+
+        let search_indent = search_lines
+            .iter()
+            .find(|line| !line.trim().is_empty())
+            .map(|line| leading_whitespace(line))
+            .unwrap_or("");
+        let file_indent = window
+            .iter()
+            .find(|(_, _, line)| !line.trim().is_empty())
+            .map(|(_, _, line)| leading_whitespace(line))
+            .unwrap_or("");
+
+        return Some((
+            match_start,
+            match_end,
+            reindent_replace(replace, search_indent, file_indent),
+        ));
+    }
+    None
+}
+
 #[derive(Debug, Default, PartialEq, Eq)]
 struct ReplaceDiffBlock {
     pub search: String,
@@ -269,6 +446,16 @@ struct SearchFilesTool {
 struct SearchFilesToolArgs {
     pub path: String,
     pub regex: String,
+    /// Only search files matching one of these globs (e.g. `["*.rs"]`). Empty means no filter.
+    #[serde(default)]
+    pub include_globs: Vec<String>,
+    /// Skip files matching any of these globs (e.g. `["*.lock", "target/**"]`).
+    #[serde(default)]
+    pub exclude_globs: Vec<String>,
+    /// Lines of context to print before and after each match.
+    pub context_lines: Option<usize>,
+    /// Stop once this many matches have been printed across all files.
+    pub max_results: Option<usize>,
 }
 
 #[async_trait]
@@ -282,25 +469,56 @@ impl ToolImpl for SearchFilesTool {
         let path = normalize_path(&self.workspace, &args.path);
         let matcher = RegexMatcher::new_line_matcher(&args.regex)?;
         tracing::info!("Search for path '{}' and regex {}", path, args.regex);
+        let context_lines = args.context_lines.unwrap_or(0);
+        let max_results = args.max_results.unwrap_or(DEFAULT_MAX_SEARCH_RESULTS);
         let mut searcher = SearcherBuilder::new()
             .binary_detection(BinaryDetection::quit(b'\x00'))
+            .before_context(context_lines)
+            .after_context(context_lines)
             .build();
 
+        let mut overrides = ignore::overrides::OverrideBuilder::new(&path);
+        for glob in &args.include_globs {
+            overrides.add(glob)?;
+        }
+        for glob in &args.exclude_globs {
+            overrides.add(&format!("!{glob}"))?;
+        }
+        let overrides = overrides.build()?;
+
         let mut buffer = Vec::new();
         let writer = Cursor::new(&mut buffer);
-        let mut printer = StandardBuilder::new().build_no_color(writer);
+        let mut printer = StandardBuilder::new().stats(true).build_no_color(writer);
 
-        for entry in ignore::Walk::new(path).filter_map(|e| e.ok()) {
+        let mut truncated = false;
+        for entry in ignore::WalkBuilder::new(&path)
+            .overrides(overrides)
+            .build()
+            .filter_map(|e| e.ok())
+        {
             if !entry.file_type().is_some_and(|t| t.is_file()) {
                 continue;
             }
+            if printer
+                .stats()
+                .is_some_and(|stats| stats.matches() as usize >= max_results)
+            {
+                truncated = true;
+                break;
+            }
             let _ = searcher.search_path(
                 &matcher,
                 entry.path(),
                 printer.sink_with_path(&matcher, entry.path()),
             );
         }
-        let res = String::from_utf8(buffer).unwrap();
+        let mut res = String::from_utf8(buffer).unwrap();
+        if truncated {
+            let matches = printer.stats().map(|stats| stats.matches()).unwrap_or(0);
+            res.push_str(&format!(
+                "\n… {matches} matches truncated (max_results={max_results})\n"
+            ));
+        }
         Ok(vec![ToolResultContent::text(if res.is_empty() {
             "No results found".to_string()
         } else {
@@ -308,3 +526,119 @@ impl ToolImpl for SearchFilesTool {
         })])
     }
 }
+
+struct RunCodeTool {
+    workspace: PathBuf,
+    allowed_languages: Vec<String>,
+    description: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct RunCodeToolArgs {
+    language: String,
+    code: String,
+    timeout_secs: Option<u64>,
+}
+
+/// Maps a `language` argument to its interpreter binary and the file extension its snippet
+/// should be saved under.
+fn interpreter_for_language(language: &str) -> Option<(&'static str, &'static str)> {
+    match language.to_ascii_lowercase().as_str() {
+        "python" | "python3" => Some(("python3", "py")),
+        "javascript" | "js" | "node" | "nodejs" => Some(("node", "js")),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl ToolImpl for RunCodeTool {
+    fn desciption(&self) -> &serde_json::Value {
+        &self.description
+    }
+
+    /// `fs_run` spawns the interpreter as a plain, unsandboxed child process (no container,
+    /// namespaces, seccomp, or resource limits) with the agent's own filesystem/network access —
+    /// `Execute` is the only thing standing between a model-chosen snippet and running unattended.
+    fn kind(&self) -> ToolKind {
+        ToolKind::Execute
+    }
+
+    async fn call(&mut self, args: serde_json::Value) -> Result<Vec<ToolResultContent>> {
+        let args = serde_json::from_value::<RunCodeToolArgs>(args)?;
+
+        if !self
+            .allowed_languages
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(&args.language))
+        {
+            anyhow::bail!(
+                "Language '{}' is not in the configured allow-list",
+                args.language
+            );
+        }
+        let Some((interpreter, ext)) = interpreter_for_language(&args.language) else {
+            anyhow::bail!("Unsupported language '{}'", args.language);
+        };
+
+        let run_id = Uuid::new_v4();
+        let script_path = self.workspace.join(format!(".fs_run_{run_id}.{ext}"));
+        let output_dir = self.workspace.join(".fs_run_output").join(run_id.to_string());
+        fs::create_dir_all(&output_dir)?;
+        fs::write(&script_path, &args.code)?;
+
+        let timeout =
+            Duration::from_secs(args.timeout_secs.unwrap_or(DEFAULT_RUN_CODE_TIMEOUT_SECS));
+        tracing::info!(%interpreter, %run_id, "Running {} snippet", args.language);
+
+        let mut command = tokio::process::Command::new(interpreter);
+        command
+            .arg(&script_path)
+            .current_dir(&self.workspace)
+            .env(RUN_CODE_OUTPUT_DIR_ENV, &output_dir)
+            .kill_on_drop(true);
+
+        let result = tokio::time::timeout(timeout, command.output()).await;
+        let _ = fs::remove_file(&script_path);
+
+        let mut contents = match result {
+            Ok(Ok(output)) => {
+                let mut text = format!("Exit status: {}\n", output.status);
+                if !output.stdout.is_empty() {
+                    text.push_str(&format!(
+                        "stdout:\n{}\n",
+                        String::from_utf8_lossy(&output.stdout)
+                    ));
+                }
+                if !output.stderr.is_empty() {
+                    text.push_str(&format!(
+                        "stderr:\n{}\n",
+                        String::from_utf8_lossy(&output.stderr)
+                    ));
+                }
+                vec![ToolResultContent::text(text)]
+            }
+            Ok(Err(err)) => vec![ToolResultContent::text(format!(
+                "Failed to run {interpreter}: {err}"
+            ))],
+            Err(_) => vec![ToolResultContent::text(format!(
+                "Timed out after {} seconds",
+                timeout.as_secs()
+            ))],
+        };
+
+        for entry in fs::read_dir(&output_dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+            let mime_type = mime_guess::from_ext(ext).first_or_octet_stream();
+            if let Some(media_type) = ImageMediaType::from_mime_type(mime_type.essence_str())
+                && let Ok(bytes) = fs::read(&path)
+            {
+                let data = base64::engine::general_purpose::STANDARD.encode(bytes);
+                contents.push(ToolResultContent::image(data, Some(media_type)));
+            }
+        }
+        let _ = fs::remove_dir_all(&output_dir);
+
+        Ok(contents)
+    }
+}
@@ -1,16 +1,20 @@
 // Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
 
-use std::{collections::HashMap, path::PathBuf};
+use std::{collections::HashMap, path::PathBuf, sync::Mutex};
 
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
 use base64::Engine;
-use hulyrs::services::transactor::{
-    TransactorClient,
-    backend::http::HttpBackend,
-    comm::{
-        BlobData, BlobPatchEventBuilder, BlobPatchOperation, CreateMessageEventBuilder, Envelope,
-        MessageRequestType, MessageType,
+use hulyrs::services::{
+    event::Class,
+    transactor::{
+        TransactorClient,
+        backend::http::HttpBackend,
+        comm::{
+            BlobData, BlobPatchEventBuilder, BlobPatchOperation, CreateMessageEventBuilder,
+            Envelope, MessageRequestType, MessageType,
+        },
+        document::{DocumentClient, FindOptionsBuilder},
     },
 };
 use reqwest::header::{self, HeaderMap, HeaderValue};
@@ -23,16 +27,26 @@ use uuid::Uuid;
 use crate::{
     config::Config,
     context::AgentContext,
-    huly::{self, blob::BlobClient},
+    huly::{
+        self,
+        blob::BlobClient,
+        types::{Person, SocialIdentity},
+    },
     state::AgentState,
-    tools::{ToolImpl, ToolSet, files::normalize_path},
+    tools::{ToolImpl, ToolKind, ToolSet, files::normalize_path},
     types::{ContentFormat, Image, ImageMediaType, Text, ToolResultContent},
 };
 
+/// A registered presenter backend (see `config::ToolBackendConfig::Presenter`) together with the
+/// tool names it ended up claiming once merged against `tools.json`.
+struct PresenterBackend {
+    client: HulyAiPresenterClient,
+    tool_names: Vec<String>,
+}
+
 pub struct HulyToolSet {
-    presenter: Option<HulyAiPresenterClient>,
+    backends: Vec<PresenterBackend>,
     tools: Vec<serde_json::Value>,
-    presenter_tools: Vec<String>,
 }
 
 impl ToolSet for HulyToolSet {
@@ -46,6 +60,7 @@ impl ToolSet for HulyToolSet {
             Box::new(SendMessageTool {
                 social_id: context.social_id.clone(),
                 tx_client: context.tx_client.clone(),
+                mention_cache: Mutex::new(HashMap::new()),
             }),
             Box::new(AddMessageReactionTool {
                 social_id: context.social_id.clone(),
@@ -56,17 +71,21 @@ impl ToolSet for HulyToolSet {
                 social_id: context.social_id.clone(),
                 tx_client: context.tx_client.clone(),
                 blob_client: context.blob_client.clone(),
+                max_download_bytes: config.huly.max_attachment_download_bytes,
+                http_client: None,
             }),
-            Box::new(SendMessageTool {
-                social_id: context.social_id.clone(),
+            Box::new(GetMessageHistoryTool {
+                tx_client: context.tx_client.clone(),
+            }),
+            Box::new(ResolveContactTool {
                 tx_client: context.tx_client.clone(),
             }),
         ];
-        if let Some(presenter) = self.presenter.as_ref() {
-            for tool in &self.presenter_tools {
+        for backend in &self.backends {
+            for tool_name in &backend.tool_names {
                 tools.push(Box::new(HulyPresenterTool {
-                    client: presenter.clone(),
-                    method: tool.clone(),
+                    client: backend.client.clone(),
+                    method: tool_name.clone(),
                 }));
             }
         }
@@ -82,45 +101,58 @@ impl ToolSet for HulyToolSet {
     }
 }
 
-pub async fn create_huly_tool_set(config: &Config, context: &AgentContext) -> Result<HulyToolSet> {
-    let (presenter, params) = if let Some(url) = &config.huly.presenter_url {
-        let presenter = create_presenter_client(url.clone(), context.token.clone()).await?;
-        let params = presenter.get_params_schema().await;
-        (Some(presenter), params)
-    } else {
-        (None, Ok(HashMap::new()))
-    };
+pub async fn create_huly_tool_set(
+    config: &Config,
+    _context: &AgentContext,
+) -> Result<HulyToolSet> {
     let mut tools: Vec<serde_json::Value> =
         serde_json::from_str(include_str!("tools.json")).unwrap();
-    let mut presenter_tools = Vec::new();
-    if let Ok(params) = params {
+    let mut claimed_by: HashMap<String, String> = HashMap::new();
+    let mut backends = Vec::new();
+
+    for backend_config in &config.huly.tool_backends {
+        let crate::config::ToolBackendConfig::Presenter {
+            base_url,
+            auth_token,
+            prefix,
+        } = backend_config;
+
+        let client = create_presenter_client(base_url.clone(), auth_token.clone()).await?;
+        let params = client.get_params_schema().await?;
+        let method_prefix = format!("huly_{prefix}_");
+        let mut tool_names = Vec::new();
+
         for tool in &mut tools {
             let tool_obj = tool.as_object_mut().unwrap();
-            let tool_name = tool_obj
-                .get("function")
-                .unwrap()
-                .get("name")
-                .unwrap()
-                .as_str()
-                .unwrap();
-            let Some(params) = params.get(tool_name.trim_start_matches("huly_")) else {
+            let tool_name = tool_obj["function"]["name"].as_str().unwrap().to_string();
+            let Some(method) = tool_name.strip_prefix(&method_prefix) else {
+                continue;
+            };
+            let Some(schema) = params.get(method) else {
                 continue;
             };
-            presenter_tools.push(tool_name.to_string());
-            tool_obj.insert("parameters".to_string(), params.clone());
+            if let Some(other_prefix) = claimed_by.insert(tool_name.clone(), prefix.clone()) {
+                return Err(anyhow!(
+                    "Tool '{tool_name}' is advertised by both backend '{other_prefix}' and backend '{prefix}'"
+                ));
+            }
+            tool_obj.insert("parameters".to_string(), schema.clone());
+            tool_names.push(tool_name);
         }
+
+        backends.push(PresenterBackend { client, tool_names });
     }
 
-    Ok(HulyToolSet {
-        presenter,
-        tools,
-        presenter_tools,
-    })
+    Ok(HulyToolSet { backends, tools })
 }
 
 struct SendMessageTool {
     social_id: String,
     tx_client: TransactorClient<HttpBackend>,
+    /// Resolved mentions, keyed by the raw `@token` text, so a message that mentions the same
+    /// person more than once (or across several `huly_send_message` calls in the same task) only
+    /// looks them up once. Lives as long as the tool instance, i.e. one agent task.
+    mention_cache: Mutex<HashMap<String, Option<ResolvedPerson>>>,
 }
 
 #[derive(Deserialize)]
@@ -129,6 +161,149 @@ struct SendMessageToolArgs {
     content: String,
 }
 
+struct ResolveContactTool {
+    tx_client: TransactorClient<HttpBackend>,
+}
+
+#[derive(Deserialize)]
+struct ResolveContactToolArgs {
+    /// A person's name, email, or other linked social id. Matched by substring against the
+    /// name and exactly against linked social ids.
+    query: String,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct ResolvedPerson {
+    person_id: String,
+    name: String,
+    avatar: Option<String>,
+    social_ids: Vec<String>,
+}
+
+/// Looks `needle` up as a bare person id first, then as a name substring, then as a linked
+/// `SocialIdentity` value (email, username, ...), returning every match with its linked social
+/// ids. Stops at the first strategy that finds anything.
+async fn resolve_contacts(
+    tx_client: &TransactorClient<HttpBackend>,
+    needle: &str,
+) -> Result<Vec<ResolvedPerson>> {
+    let options = FindOptionsBuilder::default().build();
+
+    if let Some(person) = tx_client
+        .find_one::<_, Value>(Person::CLASS, serde_json::json!({ "_id": needle }), &options)
+        .await?
+    {
+        return Ok(vec![resolved_person(tx_client, person).await?]);
+    }
+
+    let by_name = tx_client
+        .find::<Value, _>(
+            Person::CLASS,
+            serde_json::json!({ "name": { "$like": format!("%{needle}%") } }),
+            &options,
+        )
+        .await?;
+    if !by_name.is_empty() {
+        let mut resolved = Vec::with_capacity(by_name.len());
+        for person in by_name {
+            resolved.push(resolved_person(tx_client, person).await?);
+        }
+        return Ok(resolved);
+    }
+
+    let identities = tx_client
+        .find::<Value, _>(
+            SocialIdentity::CLASS,
+            serde_json::json!({ "value": needle }),
+            &options,
+        )
+        .await?;
+    let mut resolved = Vec::new();
+    for identity in identities {
+        let Some(person_id) = identity["attachedTo"].as_str() else {
+            continue;
+        };
+        if let Some(person) = tx_client
+            .find_one::<_, Value>(Person::CLASS, serde_json::json!({ "_id": person_id }), &options)
+            .await?
+        {
+            resolved.push(resolved_person(tx_client, person).await?);
+        }
+    }
+    Ok(resolved)
+}
+
+async fn resolved_person(
+    tx_client: &TransactorClient<HttpBackend>,
+    person: Value,
+) -> Result<ResolvedPerson> {
+    let person_id = person["_id"].as_str().unwrap_or_default().to_string();
+    let name = person["name"].as_str().unwrap_or_default().to_string();
+    let avatar = person["avatar"].as_str().map(|s| s.to_string());
+
+    let options = FindOptionsBuilder::default().build();
+    let social_ids = tx_client
+        .find::<Value, _>(
+            SocialIdentity::CLASS,
+            serde_json::json!({ "attachedTo": &person_id }),
+            &options,
+        )
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|identity| identity["value"].as_str().map(|s| s.to_string()))
+        .collect();
+
+    Ok(ResolvedPerson {
+        person_id,
+        name,
+        avatar,
+        social_ids,
+    })
+}
+
+/// Rewrites `@name` and `@<person-id>` tokens in `content` into Huly's mention markup, resolving
+/// each token at most once per `cache`. A token that doesn't resolve to exactly one person is
+/// left untouched, so plain `@`-mentions of non-persons (or typos) aren't mangled.
+async fn expand_mentions(
+    tx_client: &TransactorClient<HttpBackend>,
+    cache: &Mutex<HashMap<String, Option<ResolvedPerson>>>,
+    content: &str,
+) -> String {
+    let mention_pattern = regex::Regex::new(r"@([A-Za-z0-9_.\-]+)").unwrap();
+    let mut result = String::with_capacity(content.len());
+    let mut last_end = 0;
+    for m in mention_pattern.find_iter(content) {
+        let token = m.as_str();
+        let needle = &token[1..];
+        let resolved = {
+            let cached = cache.lock().unwrap().get(token).cloned();
+            match cached {
+                Some(resolved) => resolved,
+                None => {
+                    let resolved = match resolve_contacts(tx_client, needle).await {
+                        Ok(mut matches) if matches.len() == 1 => Some(matches.remove(0)),
+                        _ => None,
+                    };
+                    cache.lock().unwrap().insert(token.to_string(), resolved.clone());
+                    resolved
+                }
+            }
+        };
+        result.push_str(&content[last_end..m.start()]);
+        match resolved {
+            Some(person) => result.push_str(&format!(
+                "<span class=\"reference\" data-type=\"reference\" data-id=\"{}\" data-label=\"{}\">@{}</span>",
+                person.person_id, person.name, person.name
+            )),
+            None => result.push_str(token),
+        }
+        last_end = m.end();
+    }
+    result.push_str(&content[last_end..]);
+    result
+}
+
 struct AddMessageReactionTool {
     social_id: String,
     tx_client: TransactorClient<HttpBackend>,
@@ -146,6 +321,8 @@ struct AddMessageAttachementTool {
     social_id: String,
     tx_client: TransactorClient<HttpBackend>,
     blob_client: BlobClient,
+    max_download_bytes: u64,
+    http_client: Option<reqwest::Client>,
 }
 
 #[derive(Deserialize)]
@@ -156,12 +333,55 @@ struct AddMessageAttachementToolArgs {
     attachement_data: String,
 }
 
+struct GetMessageHistoryTool {
+    tx_client: TransactorClient<HttpBackend>,
+}
+
+#[derive(Deserialize)]
+struct GetMessageHistoryToolArgs {
+    channel: String,
+    /// Page backwards from (exclusive of) this message id, towards the start of history.
+    #[serde(default)]
+    before: Option<String>,
+    /// Page forwards from (exclusive of) this message id, towards the end of history.
+    #[serde(default)]
+    after: Option<String>,
+    #[serde(default = "default_message_history_limit")]
+    limit: u32,
+}
+
+fn default_message_history_limit() -> u32 {
+    50
+}
+
+/// One page of channel history. Ordered oldest -> newest. `cursor` is the id of the oldest
+/// message in the page (feed it back as `before` to page further back); `None` once
+/// `reached_start` is `true`, so pagination has an unambiguous stopping condition.
+#[derive(serde::Serialize)]
+struct MessageHistoryPage {
+    messages: Vec<MessageHistoryEntry>,
+    cursor: Option<String>,
+    reached_start: bool,
+}
+
+#[derive(serde::Serialize)]
+struct MessageHistoryEntry {
+    message_id: String,
+    created: String,
+    author: Option<String>,
+    content: String,
+}
+
 #[async_trait]
 impl ToolImpl for SendMessageTool {
     fn name(&self) -> &str {
         "huly_send_message"
     }
 
+    fn kind(&self) -> ToolKind {
+        ToolKind::Execute
+    }
+
     async fn call(&mut self, args: serde_json::Value) -> Result<Vec<ToolResultContent>> {
         let args = serde_json::from_value::<SendMessageToolArgs>(args)?;
         tracing::debug!(
@@ -170,12 +390,13 @@ impl ToolImpl for SendMessageTool {
             "Send message to channel"
         );
         let card_id = args.channel;
+        let content = expand_mentions(&self.tx_client, &self.mention_cache, &args.content).await;
 
         let create_event = CreateMessageEventBuilder::default()
             .message_type(MessageType::Message)
             .card_id(card_id)
             .card_type("chat:masterTag:Channel")
-            .content(args.content)
+            .content(content)
             .social_id(&self.social_id)
             .build()
             .unwrap();
@@ -196,6 +417,10 @@ impl ToolImpl for AddMessageReactionTool {
         "huly_add_message_reaction"
     }
 
+    fn kind(&self) -> ToolKind {
+        ToolKind::Execute
+    }
+
     async fn call(&mut self, args: serde_json::Value) -> Result<Vec<ToolResultContent>> {
         let args = serde_json::from_value::<AddMessageReactionToolArgs>(args)?;
         tracing::debug!(
@@ -225,6 +450,10 @@ impl ToolImpl for AddMessageAttachementTool {
         "add_message_attachement"
     }
 
+    fn kind(&self) -> ToolKind {
+        ToolKind::Execute
+    }
+
     async fn call(&mut self, args: serde_json::Value) -> Result<Vec<ToolResultContent>> {
         let args = serde_json::from_value::<AddMessageAttachementToolArgs>(args)?;
         tracing::debug!(
@@ -240,6 +469,47 @@ impl ToolImpl for AddMessageAttachementTool {
             let mime_type = data[0][5..].split(';').collect::<Vec<&str>>()[0];
             let content = base64::engine::general_purpose::STANDARD.decode(data[1])?;
             (mime_type.to_string(), content)
+        } else if args.attachement_data.starts_with("http://")
+            || args.attachement_data.starts_with("https://")
+        {
+            let client = self.http_client.get_or_insert_with(reqwest::Client::new);
+            let response = client.get(&args.attachement_data).send().await?;
+
+            if !response.status().is_success() {
+                return Ok(vec![ToolResultContent::text(format!(
+                    "Failed to download attachement from {}: HTTP {}",
+                    args.attachement_data,
+                    response.status()
+                ))]);
+            }
+            if let Some(content_length) = response.content_length() {
+                if content_length > self.max_download_bytes {
+                    return Ok(vec![ToolResultContent::text(format!(
+                        "Attachement at {} is {content_length} bytes, which exceeds the {}-byte limit",
+                        args.attachement_data, self.max_download_bytes
+                    ))]);
+                }
+            }
+            let mime_type = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.split(';').next().unwrap_or(v).trim().to_string())
+                .unwrap_or_else(|| {
+                    mime_guess::from_path(&args.attachement_data)
+                        .first_or_text_plain()
+                        .to_string()
+                });
+            let content = response.bytes().await?;
+            if content.len() as u64 > self.max_download_bytes {
+                return Ok(vec![ToolResultContent::text(format!(
+                    "Attachement at {} is {} bytes, which exceeds the {}-byte limit",
+                    args.attachement_data,
+                    content.len(),
+                    self.max_download_bytes
+                ))]);
+            }
+            (mime_type, content.to_vec())
         } else {
             let path = normalize_path(&self.workspace, &args.attachement_data);
             let mut file = File::open(path).await?;
@@ -253,7 +523,8 @@ impl ToolImpl for AddMessageAttachementTool {
 
         let size = content.len() as u32;
         let blob_id = Uuid::new_v4().to_string();
-        self.blob_client
+        let blob_id = self
+            .blob_client
             .upload_file(&blob_id, &mime_type, content)
             .await?;
 
@@ -283,6 +554,92 @@ impl ToolImpl for AddMessageAttachementTool {
     }
 }
 
+#[async_trait]
+impl ToolImpl for GetMessageHistoryTool {
+    fn name(&self) -> &str {
+        "huly_get_message_history"
+    }
+
+    async fn call(&mut self, args: serde_json::Value) -> Result<Vec<ToolResultContent>> {
+        let args = serde_json::from_value::<GetMessageHistoryToolArgs>(args)?;
+        tracing::debug!(
+            channel = args.channel,
+            before = ?args.before,
+            after = ?args.after,
+            limit = args.limit,
+            "Get message history"
+        );
+
+        // One extra row over the page size tells "more history behind this page" apart from
+        // "this page happens to exactly fill the limit" without a second round-trip.
+        let page_size = args.limit.max(1);
+        let mut query = serde_json::json!({ "cardId": args.channel });
+        if let Some(before) = &args.before {
+            query["id"] = serde_json::json!({ "$lt": before });
+        }
+        if let Some(after) = &args.after {
+            query["id"] = serde_json::json!({ "$gt": after });
+        }
+
+        let options = FindOptionsBuilder::default()
+            .sort("id", false)
+            .limit(page_size as i64 + 1)
+            .build();
+
+        let mut messages = self
+            .tx_client
+            .find::<serde_json::Value, _>("chat:class:ChatMessage", query, &options)
+            .await?;
+
+        let reached_start = (messages.len() as u32) <= page_size;
+        messages.truncate(page_size as usize);
+        // Results come back newest-first (for the "is there more?" lookahead above); flip them
+        // to the oldest->newest order the agent reads a transcript in.
+        messages.reverse();
+
+        let cursor = messages
+            .first()
+            .and_then(|m| m["id"].as_str())
+            .map(|s| s.to_string());
+
+        let entries = messages
+            .into_iter()
+            .map(|m| MessageHistoryEntry {
+                message_id: m["id"].as_str().unwrap_or_default().to_string(),
+                created: m["created"].as_str().unwrap_or_default().to_string(),
+                author: m["createdBy"].as_str().map(|s| s.to_string()),
+                content: m["content"].as_str().unwrap_or_default().to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let page = MessageHistoryPage {
+            messages: entries,
+            cursor: if reached_start { None } else { cursor },
+            reached_start,
+        };
+
+        Ok(vec![ToolResultContent::text(serde_json::to_string(
+            &page,
+        )?)])
+    }
+}
+
+#[async_trait]
+impl ToolImpl for ResolveContactTool {
+    fn name(&self) -> &str {
+        "huly_find_person"
+    }
+
+    async fn call(&mut self, args: serde_json::Value) -> Result<Vec<ToolResultContent>> {
+        let args = serde_json::from_value::<ResolveContactToolArgs>(args)?;
+        tracing::debug!(query = args.query, "Resolve contact");
+        let persons = resolve_contacts(&self.tx_client, &args.query).await?;
+        Ok(vec![ToolResultContent::text(serde_json::to_string(
+            &persons,
+        )?)])
+    }
+}
+
 #[derive(Debug, Clone)]
 struct HulyAiPresenterClient {
     client: reqwest::Client,
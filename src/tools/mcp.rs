@@ -4,11 +4,13 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use async_trait::async_trait;
+use futures::StreamExt;
 use mcp_core::{client::Client, transport::Transport};
+use tokio::sync::mpsc;
 
 use crate::{
-    tools::ToolImpl,
-    types::{ImageMediaType, ToolResultContent},
+    tools::{ToolImpl, ToolProgress},
+    types::{AudioMediaType, ImageMediaType, ToolResultContent},
 };
 
 pub struct McpTool<T: Transport> {
@@ -20,6 +22,47 @@ impl<T: Transport> McpTool<T> {
     pub fn new(client: Arc<Client<T>>, desciption: serde_json::Value) -> Self {
         Self { client, desciption }
     }
+
+    fn map_response_content(res: mcp_core::types::CallToolResponse) -> Vec<ToolResultContent> {
+        res.content
+            .iter()
+            .filter_map(|c| match c {
+                mcp_core::types::ToolResponseContent::Text(text_content) => {
+                    Some(ToolResultContent::text(text_content.text.clone()))
+                }
+                mcp_core::types::ToolResponseContent::Image(image) => {
+                    Some(ToolResultContent::image(
+                        image.data.clone(),
+                        ImageMediaType::from_mime_type(&image.mime_type),
+                    ))
+                }
+                mcp_core::types::ToolResponseContent::Audio(audio) => {
+                    Some(ToolResultContent::audio(
+                        audio.data.clone(),
+                        AudioMediaType::from_mime_type(&audio.mime_type),
+                    ))
+                }
+                mcp_core::types::ToolResponseContent::Resource(resource) => {
+                    Some(match &resource.resource {
+                        mcp_core::types::ResourceContents::Text { uri, mime_type, text } => {
+                            ToolResultContent::resource(
+                                uri.clone(),
+                                mime_type.clone(),
+                                text.clone(),
+                            )
+                        }
+                        mcp_core::types::ResourceContents::Blob { uri, mime_type, .. } => {
+                            ToolResultContent::resource(
+                                uri.clone(),
+                                mime_type.clone(),
+                                format!("[binary resource at {uri}, not inlined]"),
+                            )
+                        }
+                    })
+                }
+            })
+            .collect::<Vec<_>>()
+    }
 }
 
 #[async_trait]
@@ -46,23 +89,48 @@ impl<T: Transport> ToolImpl for McpTool<T> {
             )
             .await?;
         tracing::trace!(result = ?res, "mcp_tool_result");
-        let res = res
-            .content
-            .iter()
-            .filter_map(|c| match c {
-                mcp_core::types::ToolResponseContent::Text(text_content) => {
-                    Some(ToolResultContent::text(text_content.text.clone()))
-                }
-                mcp_core::types::ToolResponseContent::Image(image) => {
-                    Some(ToolResultContent::image(
-                        image.data.clone(),
-                        ImageMediaType::from_mime_type(&image.mime_type),
-                    ))
+        Ok(Self::map_response_content(res))
+    }
+
+    /// Forwards the MCP server's `notifications/progress` messages for this request as
+    /// `ToolProgress` updates while the call is in flight.
+    async fn call_with_progress(
+        &mut self,
+        arguments: serde_json::Value,
+        progress: mpsc::Sender<ToolProgress>,
+    ) -> Result<Vec<ToolResultContent>> {
+        tracing::trace!(
+            tool = self.name(),
+            args = arguments.to_string(),
+            "mcp_tool call"
+        );
+        let progress_token = uuid::Uuid::new_v4().to_string();
+        let mut notifications = self.client.subscribe_progress(progress_token.clone());
+        let forward_progress = tokio::spawn(async move {
+            while let Some(notification) = notifications.next().await {
+                let text = notification
+                    .message
+                    .clone()
+                    .unwrap_or_else(|| format!("{:.0}%", notification.progress));
+                if progress.send(ToolProgress { text }).await.is_err() {
+                    break;
                 }
-                mcp_core::types::ToolResponseContent::Audio(_) => None,
-                mcp_core::types::ToolResponseContent::Resource(_) => None,
-            })
-            .collect::<Vec<_>>();
-        Ok(res)
+            }
+        });
+        let res = self
+            .client
+            .call_tool_with_progress(
+                self.name(),
+                if arguments.is_null() {
+                    None
+                } else {
+                    Some(arguments)
+                },
+                progress_token,
+            )
+            .await;
+        forward_progress.abort();
+        tracing::trace!(result = ?res, "mcp_tool_result");
+        Ok(Self::map_response_content(res?))
     }
 }
@@ -7,6 +7,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     config::Config,
     context::AgentContext,
+    knowledge_graph::{self, Entity, KnowledgeGraph, Observation, Relation, SearchMode},
     state::AgentState,
     tools::{ToolImpl, ToolSet},
 };
@@ -33,13 +34,6 @@ macro_rules! create_mem_tool {
     };
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Observation {
-    #[serde(rename = "entityName")]
-    pub entity_name: String,
-    pub observations: Vec<String>,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct AddObservationsResult {
     #[serde(rename = "entityName")]
@@ -48,29 +42,8 @@ struct AddObservationsResult {
     added_observations: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub struct Entity {
-    #[serde(skip)]
-    pub id: i64,
-    pub name: String,
-    #[serde(rename = "entityType")]
-    pub entity_type: String,
-    pub observations: Vec<String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Relation {
-    pub from: String,
-    pub to: String,
-    #[serde(rename = "relationType")]
-    pub relation_type: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct KnowledgeGraph {
-    pub entities: Vec<Entity>,
-    pub relations: Vec<Relation>,
-}
+/// How many entities `search_nodes`'s `semantic`/`hybrid` modes fetch before returning.
+const SEARCH_NODES_SEMANTIC_TOP_K: usize = 10;
 
 pub struct MemoryToolSet;
 
@@ -126,13 +99,143 @@ async fn call_memory_tool(
         }
         "search_nodes" => {
             let query = args["query"].as_str().unwrap();
-            let result = state.mem_search_nodes(Some(query)).await?;
+            let mode: SearchMode = args
+                .get("mode")
+                .cloned()
+                .map(serde_json::from_value)
+                .transpose()?
+                .unwrap_or_default();
+            let result = match mode {
+                SearchMode::Keyword => state.mem_search_nodes(Some(query)).await?,
+                SearchMode::Semantic => KnowledgeGraph {
+                    entities: state
+                        .mem_search_nodes_semantic(query, SEARCH_NODES_SEMANTIC_TOP_K)
+                        .await?,
+                    relations: vec![],
+                },
+                SearchMode::Hybrid => {
+                    let keyword = state.mem_search_nodes(Some(query)).await?;
+                    let semantic = state
+                        .mem_search_nodes_semantic(query, SEARCH_NODES_SEMANTIC_TOP_K)
+                        .await?;
+                    KnowledgeGraph {
+                        entities: knowledge_graph::reciprocal_rank_fusion(
+                            keyword.entities,
+                            semantic,
+                        ),
+                        relations: keyword.relations,
+                    }
+                }
+            };
             Ok(serde_json::to_string(&result).unwrap())
         }
         "open_nodes" => {
             let names: Vec<String> = serde_json::from_value(args["names"].clone())?;
-            let entities: Vec<Entity> = state.mem_list_entities(&names).await?;
-            Ok(serde_json::to_string(&entities).unwrap())
+            let depth = args["depth"].as_u64().unwrap_or(1) as u32;
+            let relation_types: Option<Vec<String>> = args
+                .get("relation_types")
+                .cloned()
+                .map(serde_json::from_value)
+                .transpose()?;
+            let result = state
+                .mem_expand_nodes(&names, depth, relation_types.as_deref())
+                .await?;
+            Ok(serde_json::to_string(&result).unwrap())
+        }
+        "export_graph" => {
+            let graph = state.mem_search_nodes(None).await?;
+            let mut lines = Vec::with_capacity(graph.entities.len() + graph.relations.len());
+            for entity in &graph.entities {
+                lines.push(serde_json::to_string(&serde_json::json!({
+                    "type": "entity",
+                    "name": entity.name,
+                    "entityType": entity.entity_type,
+                    "observations": entity.observations,
+                }))?);
+            }
+            for relation in &graph.relations {
+                lines.push(serde_json::to_string(&serde_json::json!({
+                    "type": "relation",
+                    "from": relation.from,
+                    "to": relation.to,
+                    "relationType": relation.relation_type,
+                }))?);
+            }
+            Ok(lines.join("\n"))
+        }
+        "import_graph" => {
+            let data = args["data"].as_str().unwrap_or_default();
+            let replace = args["mode"].as_str() == Some("replace");
+
+            let mut entities = Vec::new();
+            let mut relations = Vec::new();
+            for line in data.lines() {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let value: serde_json::Value = serde_json::from_str(line)?;
+                match value["type"].as_str() {
+                    Some("entity") => entities.push(Entity {
+                        id: 0,
+                        name: value["name"]
+                            .as_str()
+                            .ok_or_else(|| anyhow::anyhow!("entity line missing \"name\""))?
+                            .to_string(),
+                        entity_type: value["entityType"].as_str().unwrap_or_default().to_string(),
+                        observations: serde_json::from_value(
+                            value["observations"].clone(),
+                        )
+                        .unwrap_or_default(),
+                        score: None,
+                    }),
+                    Some("relation") => relations.push(Relation {
+                        from: value["from"]
+                            .as_str()
+                            .ok_or_else(|| anyhow::anyhow!("relation line missing \"from\""))?
+                            .to_string(),
+                        to: value["to"]
+                            .as_str()
+                            .ok_or_else(|| anyhow::anyhow!("relation line missing \"to\""))?
+                            .to_string(),
+                        relation_type: value["relationType"].as_str().unwrap_or_default().to_string(),
+                    }),
+                    other => {
+                        return Err(anyhow::anyhow!(
+                            "unknown JSONL line type: {:?}",
+                            other
+                        ));
+                    }
+                }
+            }
+
+            if replace {
+                let existing = state.mem_search_nodes(None).await?;
+                let names: Vec<String> =
+                    existing.entities.into_iter().map(|entity| entity.name).collect();
+                if !names.is_empty() {
+                    state.mem_delete_entities(&names).await?;
+                }
+            }
+
+            let created = state.mem_add_entities(&mut entities.clone()).await?;
+            let observations = entities
+                .into_iter()
+                .filter(|entity| !entity.observations.is_empty())
+                .map(|entity| Observation {
+                    entity_name: entity.name,
+                    observations: entity.observations,
+                })
+                .collect();
+            state.mem_add_observations(observations).await?;
+            let mut relations = relations;
+            let created_relations = state.mem_add_relations(&mut relations).await?;
+
+            Ok(format!(
+                "Imported {} entities and {} relations",
+                created.len(),
+                created_relations.len()
+            ))
         }
         _ => Err(anyhow::anyhow!("Unknown tool: {}", toolname)),
     }
@@ -147,6 +250,8 @@ create_mem_tool!(MemoryDeleteRelations, delete_relations);
 create_mem_tool!(MemoryReadGraph, read_graph);
 create_mem_tool!(MemorySearchNodes, search_nodes);
 create_mem_tool!(MemoryOpenNodes, open_nodes);
+create_mem_tool!(MemoryExportGraph, export_graph);
+create_mem_tool!(MemoryImportGraph, import_graph);
 
 impl ToolSet for MemoryToolSet {
     fn get_tools<'a>(
@@ -182,6 +287,12 @@ impl ToolSet for MemoryToolSet {
             Box::new(MemoryReadGraphTool {
                 state: state.clone(),
             }),
+            Box::new(MemoryExportGraphTool {
+                state: state.clone(),
+            }),
+            Box::new(MemoryImportGraphTool {
+                state: state.clone(),
+            }),
         ]
     }
 
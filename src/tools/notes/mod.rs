@@ -1,6 +1,7 @@
 // Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -9,6 +10,8 @@ use serde::Deserialize;
 use crate::{
     config::Config,
     context::AgentContext,
+    knowledge_graph::{Entity, Observation},
+    note_classifier::{NoOpNoteClassifier, NoteClassifier},
     state::AgentState,
     tools::{ToolImpl, ToolSet},
     types::ToolResultContent,
@@ -23,9 +26,9 @@ impl ToolSet for NotesToolSet {
 
     async fn get_tools<'a>(
         &self,
-        _config: &'a Config,
+        config: &'a Config,
         _context: &'a AgentContext,
-        _state: &'a AgentState,
+        state: &'a AgentState,
     ) -> Vec<Box<dyn ToolImpl>> {
         let mut descriptions =
             serde_json::from_str::<Vec<serde_json::Value>>(include_str!("tools.json"))
@@ -33,13 +36,25 @@ impl ToolSet for NotesToolSet {
                 .into_iter()
                 .map(|v| (v["function"]["name"].as_str().unwrap().to_string(), v))
                 .collect::<HashMap<String, serde_json::Value>>();
+        let classifier: Option<Arc<dyn NoteClassifier>> = config
+            .notes
+            .classify
+            .then(|| Arc::new(NoOpNoteClassifier) as Arc<dyn NoteClassifier>);
         vec![
             Box::new(AddNoteTool {
                 description: descriptions.remove("notes_add").unwrap(),
+                classifier,
             }),
             Box::new(DeleteNotesTool {
                 description: descriptions.remove("notes_delete").unwrap(),
             }),
+            Box::new(NotesSearchTool {
+                description: descriptions.remove("notes_search").unwrap(),
+            }),
+            Box::new(NotesPromoteTool {
+                description: descriptions.remove("notes_promote").unwrap(),
+                state: state.clone(),
+            }),
         ]
     }
 
@@ -54,11 +69,16 @@ impl ToolSet for NotesToolSet {
 
 pub struct AddNoteTool {
     description: serde_json::Value,
+    /// Set only when `Config::notes.classify` is enabled; `None` keeps notes exactly as the
+    /// caller wrote them, with no derived tags or entity mentions.
+    classifier: Option<Arc<dyn NoteClassifier>>,
 }
 
 #[derive(Deserialize)]
 pub struct AddNoteToolArgs {
     pub content: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 pub struct DeleteNotesTool {
@@ -70,6 +90,42 @@ pub struct DeleteNotesToolArgs {
     pub ids: String,
 }
 
+pub struct NotesSearchTool {
+    description: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+pub struct NotesSearchToolArgs {
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub query: Option<String>,
+    #[serde(default = "default_notes_search_limit")]
+    pub limit: i64,
+    #[serde(default)]
+    pub offset: i64,
+}
+
+fn default_notes_search_limit() -> i64 {
+    20
+}
+
+pub struct NotesPromoteTool {
+    description: serde_json::Value,
+    state: AgentState,
+}
+
+#[derive(Deserialize)]
+pub struct NotesPromoteToolArgs {
+    pub id: i64,
+    /// Entities to promote the note into. Defaults to the note's classifier-extracted
+    /// `mentions` when omitted.
+    #[serde(default)]
+    pub entity_names: Option<Vec<String>>,
+    #[serde(default)]
+    pub entity_type: Option<String>,
+}
+
 #[async_trait]
 impl ToolImpl for AddNoteTool {
     fn desciption(&self) -> &serde_json::Value {
@@ -82,7 +138,21 @@ impl ToolImpl for AddNoteTool {
         arguments: serde_json::Value,
     ) -> Result<Vec<ToolResultContent>> {
         let args = serde_json::from_value::<AddNoteToolArgs>(arguments)?;
-        let id = context.db_client.add_note(&args.content).await?;
+        let mut tags = args.tags;
+        let mut mentions = Vec::new();
+        if let Some(classifier) = &self.classifier {
+            let classified = classifier.classify(&args.content).await;
+            for tag in classified.tags {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+            mentions = classified.entity_mentions;
+        }
+        let id = context
+            .db_client
+            .add_note(&args.content, &tags, &mentions)
+            .await?;
         Ok(vec![ToolResultContent::text(format!(
             "Note added with id {}",
             id
@@ -116,3 +186,99 @@ impl ToolImpl for DeleteNotesTool {
         )])
     }
 }
+
+#[async_trait]
+impl ToolImpl for NotesSearchTool {
+    fn desciption(&self) -> &serde_json::Value {
+        &self.description
+    }
+
+    async fn call(
+        &mut self,
+        context: &AgentContext,
+        arguments: serde_json::Value,
+    ) -> Result<Vec<ToolResultContent>> {
+        let args = serde_json::from_value::<NotesSearchToolArgs>(arguments)?;
+        let notes = context
+            .db_client
+            .notes_search(args.tag.as_deref(), args.query.as_deref(), args.limit, args.offset)
+            .await?;
+        let text = if notes.is_empty() {
+            "No notes found".to_string()
+        } else {
+            notes
+                .into_iter()
+                .map(|note| {
+                    if note.tags.is_empty() {
+                        format!("## id: {}\n{}", note.id, note.content)
+                    } else {
+                        format!("## id: {} [{}]\n{}", note.id, note.tags.join(", "), note.content)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        };
+        Ok(vec![ToolResultContent::text(text)])
+    }
+}
+
+#[async_trait]
+impl ToolImpl for NotesPromoteTool {
+    fn desciption(&self) -> &serde_json::Value {
+        &self.description
+    }
+
+    async fn call(
+        &mut self,
+        context: &AgentContext,
+        arguments: serde_json::Value,
+    ) -> Result<Vec<ToolResultContent>> {
+        let args = serde_json::from_value::<NotesPromoteToolArgs>(arguments)?;
+        let Some(note) = context
+            .db_client
+            .notes()
+            .await?
+            .into_iter()
+            .find(|note| note.id == args.id)
+        else {
+            anyhow::bail!("Note {} not found", args.id);
+        };
+        let names = args.entity_names.unwrap_or(note.mentions);
+        if names.is_empty() {
+            anyhow::bail!(
+                "Note {} has no entity mentions to promote; pass entity_names explicitly",
+                args.id
+            );
+        }
+        let entity_type = args.entity_type.unwrap_or_else(|| "note".to_string());
+
+        let mut entities = names
+            .iter()
+            .map(|name| Entity {
+                id: 0,
+                name: name.clone(),
+                entity_type: entity_type.clone(),
+                observations: vec![],
+                score: None,
+            })
+            .collect::<Vec<_>>();
+        self.state.mem_add_entities(&mut entities).await?;
+
+        let observations = names
+            .iter()
+            .map(|name| Observation {
+                entity_name: name.clone(),
+                observations: vec![note.content.clone()],
+            })
+            .collect::<Vec<_>>();
+        self.state.mem_add_observations(observations).await?;
+
+        Ok(vec![ToolResultContent::text(format!(
+            "Promoted note {} into {} entit{}: {}",
+            args.id,
+            names.len(),
+            if names.len() == 1 { "y" } else { "ies" },
+            names.join(", ")
+        ))])
+    }
+}
@@ -14,6 +14,8 @@ use crate::{
     types::ToolResultContent,
 };
 
+mod schedule;
+
 pub struct TaskToolSet;
 
 impl ToolSet for TaskToolSet {
@@ -83,9 +85,17 @@ impl ToolImpl for AddScheduledTaskTool {
         arguments: serde_json::Value,
     ) -> Result<Vec<ToolResultContent>> {
         let args = serde_json::from_value::<AddScheduledTaskToolArgs>(arguments)?;
+        let resolved_schedule = match schedule::resolve_schedule(
+            &args.schedule,
+            chrono::Utc::now(),
+            context.account_info.time_zone,
+        ) {
+            Ok(resolved) => resolved,
+            Err(e) => return Ok(vec![ToolResultContent::text(e.to_string())]),
+        };
         context
             .db_client
-            .add_scheduled_task(&args.content, &args.schedule)
+            .add_scheduled_task(&args.content, &resolved_schedule)
             .await?;
         Ok(vec![ToolResultContent::text(
             "Task added to the scheduler".to_string(),
@@ -0,0 +1,182 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! Resolves `AddScheduledTaskToolArgs.schedule` into a string `config::JobSchedule::new` accepts:
+//! cron expressions and iCalendar RRULEs pass straight through, while a small natural-language
+//! grammar ("in 5 minutes", "every 2 hours", "tomorrow at 8am", "every Monday") is translated
+//! into the equivalent cron/RRULE form. Times-of-day are resolved against the caller's timezone
+//! before being normalized back to UTC, so "8am" means 8am for the account, not for the server.
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+
+use crate::config::JobSchedule;
+
+/// Shown to the model when `schedule` can't be parsed, so it knows what to retry with.
+pub const SCHEDULE_FORMAT_HELP: &str = "Could not understand the schedule. Use a cron expression \
+(e.g. \"0 9 * * 1-5\"), an iCalendar RRULE (e.g. \"FREQ=WEEKLY;BYDAY=MO,WE,FR\"), or a phrase \
+like \"in 5 minutes\", \"every 2 hours\", \"tomorrow at 8am\", or \"every Monday\".";
+
+/// Resolves `schedule` (cron, RRULE, or natural language evaluated against `tz`/`now`) into a
+/// string `config::JobSchedule::new` accepts.
+pub fn resolve_schedule(schedule: &str, now: DateTime<Utc>, tz: Tz) -> Result<String> {
+    let trimmed = schedule.trim();
+    // Already machine syntax: pass through unchanged and let `JobSchedule` be the single source
+    // of truth for what's valid there.
+    if JobSchedule::new(trimmed).is_ok() {
+        return Ok(trimmed.to_string());
+    }
+    parse_natural_language(trimmed, now, tz).ok_or_else(|| anyhow!(SCHEDULE_FORMAT_HELP))
+}
+
+fn parse_natural_language(input: &str, now: DateTime<Utc>, tz: Tz) -> Option<String> {
+    let lower = input.trim().to_ascii_lowercase();
+    parse_in_offset(&lower, now)
+        .or_else(|| parse_every_interval(&lower))
+        .or_else(|| parse_every_weekday(&lower))
+        .or_else(|| parse_day_at_time(&lower, now, tz))
+}
+
+/// "in 5 minutes", "in 2 hours", "in 3 days" -> a one-shot cron pinned to that exact instant.
+fn parse_in_offset(input: &str, now: DateTime<Utc>) -> Option<String> {
+    let rest = input.strip_prefix("in ")?;
+    let (amount, unit) = split_amount_unit(rest)?;
+    let delta = unit_duration(unit, amount)?;
+    Some(one_shot_cron(now + delta))
+}
+
+/// "every 2 hours", "every 5 minutes", "every day", "every 3 weeks" -> a recurring cron/RRULE.
+fn parse_every_interval(input: &str) -> Option<String> {
+    let rest = input.strip_prefix("every ")?;
+    let (amount, unit) = split_amount_unit(rest).or_else(|| Some((1, rest)))?;
+    match unit {
+        "second" | "seconds" => Some(format!("*/{amount} * * * * *")),
+        "minute" | "minutes" => Some(format!("0 */{amount} * * * *")),
+        "hour" | "hours" => Some(format!("0 0 */{amount} * * *")),
+        "day" | "days" => Some(format!("0 0 0 */{amount} * *")),
+        "week" | "weeks" => Some(format!("FREQ=WEEKLY;INTERVAL={amount}")),
+        _ => None,
+    }
+}
+
+/// "every Monday", "every Monday at 8am" -> a weekly cron on that weekday.
+fn parse_every_weekday(input: &str) -> Option<String> {
+    let rest = input.strip_prefix("every ")?;
+    let (day_part, at_part) = match rest.split_once(" at ") {
+        Some((day, at)) => (day, Some(at)),
+        None => (rest, None),
+    };
+    let dow = weekday_abbrev(day_part.trim())?;
+    let (hour, minute) = at_part
+        .and_then(parse_time_of_day)
+        .map(|t| (t.hour(), t.minute()))
+        .unwrap_or((0, 0));
+    Some(format!("0 {minute} {hour} * * {dow}"))
+}
+
+/// "tomorrow at 8am", "today at 6pm", "at noon" -> a one-shot cron pinned to that exact instant,
+/// resolved against `tz` and bumped to the next day if the time has already passed today.
+fn parse_day_at_time(input: &str, now: DateTime<Utc>, tz: Tz) -> Option<String> {
+    let (day_part, time_part) = if let Some(rest) = input.strip_prefix("tomorrow at ") {
+        (Some("tomorrow"), rest)
+    } else if let Some(rest) = input.strip_prefix("today at ") {
+        (Some("today"), rest)
+    } else {
+        (None, input.strip_prefix("at ")?)
+    };
+    let time = parse_time_of_day(time_part)?;
+
+    let local_now = now.with_timezone(&tz);
+    let mut local_date = local_now.date_naive();
+    if day_part == Some("tomorrow") {
+        local_date = local_date.succ_opt()?;
+    } else if day_part.is_none() && local_now.time() >= time {
+        // A bare "at 8am" requested after 8am today means tomorrow, not "in the past".
+        local_date = local_date.succ_opt()?;
+    }
+
+    let local_dt = local_date.and_time(time).and_local_timezone(tz).single()?;
+    Some(one_shot_cron(local_dt.with_timezone(&Utc)))
+}
+
+/// Splits `"5 minutes"` into `(5, "minutes")`, or `"hours"` into `(1, "hours")` when no count is
+/// given.
+fn split_amount_unit(s: &str) -> Option<(u32, &str)> {
+    let s = s.trim();
+    match s.split_once(char::is_whitespace) {
+        Some((amount, unit)) => Some((amount.trim().parse().ok()?, unit.trim())),
+        None => Some((1, s)),
+    }
+}
+
+fn unit_duration(unit: &str, amount: u32) -> Option<Duration> {
+    Some(match unit {
+        "second" | "seconds" => Duration::seconds(amount as i64),
+        "minute" | "minutes" => Duration::minutes(amount as i64),
+        "hour" | "hours" => Duration::hours(amount as i64),
+        "day" | "days" => Duration::days(amount as i64),
+        "week" | "weeks" => Duration::weeks(amount as i64),
+        _ => return None,
+    })
+}
+
+fn weekday_abbrev(s: &str) -> Option<&'static str> {
+    Some(match s {
+        "monday" => "Mon",
+        "tuesday" => "Tue",
+        "wednesday" => "Wed",
+        "thursday" => "Thu",
+        "friday" => "Fri",
+        "saturday" => "Sat",
+        "sunday" => "Sun",
+        _ => return None,
+    })
+}
+
+/// Parses `"8am"`, `"8:30pm"`, `"20:00"`, `"noon"`, `"midnight"`.
+fn parse_time_of_day(s: &str) -> Option<NaiveTime> {
+    let s = s.trim();
+    match s {
+        "noon" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return NaiveTime::from_hms_opt(0, 0, 0),
+        _ => {}
+    }
+
+    let (digits, meridiem) = if let Some(stripped) = s.strip_suffix("am") {
+        (stripped.trim(), Some(false))
+    } else if let Some(stripped) = s.strip_suffix("pm") {
+        (stripped.trim(), Some(true))
+    } else {
+        (s, None)
+    };
+
+    let (hour_str, minute_str) = digits.split_once(':').unwrap_or((digits, "0"));
+    let mut hour: u32 = hour_str.trim().parse().ok()?;
+    let minute: u32 = minute_str.trim().parse().ok()?;
+
+    if let Some(is_pm) = meridiem {
+        if hour == 12 {
+            hour = 0;
+        }
+        if is_pm {
+            hour += 12;
+        }
+    }
+
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// A fully-specified 7-field cron expression (`sec min hour day month dow year`) that matches
+/// exactly `at` and nothing else, used to represent a one-shot schedule with `config::JobSchedule`
+/// (which otherwise only understands recurring cron/RRULE syntax).
+fn one_shot_cron(at: DateTime<Utc>) -> String {
+    format!(
+        "{} {} {} {} {} * {}",
+        at.second(),
+        at.minute(),
+        at.hour(),
+        at.day(),
+        at.month(),
+        at.year()
+    )
+}
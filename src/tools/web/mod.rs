@@ -1,22 +1,33 @@
 // Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
 
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::Duration,
+};
 
 use anyhow::Result;
 use async_trait::async_trait;
+use futures::future::join_all;
 use itertools::Itertools;
-use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use regex::Regex;
+use reqwest::Url;
 use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
 
 use crate::{
-    config::{Config, WebSearchProvider},
+    config::Config,
     context::AgentContext,
     state::AgentState,
-    tools::{ToolImpl, ToolSet},
+    tools::{
+        ToolImpl, ToolSet,
+        web::search::{SearchProvider, create_search_providers, fuse_with_rrf},
+    },
     types::{ImageMediaType, ToolResultContent},
     utils::safe_truncated,
 };
 
+mod search;
+
 pub struct WebToolSet;
 
 impl ToolSet for WebToolSet {
@@ -42,10 +53,14 @@ impl ToolSet for WebToolSet {
                 description: descriptions.remove("web_fetch").unwrap(),
             }),
             Box::new(WebSearchTool {
-                client: None,
-                config: config.web_search.clone(),
+                providers: create_search_providers(&config.web_search),
                 description: descriptions.remove("web_search").unwrap(),
             }),
+            Box::new(WebCrawlTool {
+                client: None,
+                description: descriptions.remove("web_crawl").unwrap(),
+                last_fetch_by_host: HashMap::new(),
+            }),
         ]
     }
 
@@ -120,6 +135,10 @@ impl ToolImpl for WebFetchTool {
         &self.description
     }
 
+    fn is_cacheable(&self) -> bool {
+        true
+    }
+
     async fn call(&mut self, arguments: serde_json::Value) -> Result<Vec<ToolResultContent>> {
         let args = serde_json::from_value::<WebFetchToolArgs>(arguments)?;
         let client = self.client.get_or_insert_with(reqwest::Client::new);
@@ -167,79 +186,306 @@ pub struct WebSearchToolArgs {
     pub offset: u16,
 }
 
-#[derive(Debug, Clone, Deserialize)]
-pub struct BraveWebResultItem {
-    pub title: String,
+pub struct WebSearchTool {
+    providers: Vec<Box<dyn SearchProvider>>,
+    description: serde_json::Value,
+}
+
+#[async_trait]
+impl ToolImpl for WebSearchTool {
+    fn desciption(&self) -> &serde_json::Value {
+        &self.description
+    }
+
+    fn is_cacheable(&self) -> bool {
+        true
+    }
+
+    async fn call(&mut self, arguments: serde_json::Value) -> Result<Vec<ToolResultContent>> {
+        let args = serde_json::from_value::<WebSearchToolArgs>(arguments)?;
+        let count = if args.count == 0 { 10 } else { args.count };
+
+        let provider_hits: Vec<Vec<search::SearchHit>> = join_all(
+            self.providers
+                .iter()
+                .map(|provider| provider.search(&args.query, count, args.offset)),
+        )
+        .await
+        .into_iter()
+        .filter_map(|result| match result {
+            Ok(hits) => Some(hits),
+            Err(e) => {
+                tracing::warn!(error = %e, "web_search: provider failed, skipping");
+                None
+            }
+        })
+        .collect();
+
+        let converter = htmd::HtmlToMarkdownBuilder::new().build();
+        let result = fuse_with_rrf(provider_hits, count)
+            .into_iter()
+            .map(|hit| {
+                format!(
+                    "Title: {}\nDescription: {}\nURL: {}",
+                    hit.title,
+                    converter.convert(&hit.description).unwrap_or(hit.description),
+                    hit.url
+                )
+            })
+            .join("\n\n");
+        Ok(vec![ToolResultContent::text(result)])
+    }
+}
+
+const DEFAULT_CRAWL_MAX_DEPTH: usize = 2;
+const DEFAULT_CRAWL_MAX_PAGES: usize = 20;
+const DEFAULT_CRAWL_POLITENESS_DELAY_MS: u64 = 500;
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebCrawlToolArgs {
     pub url: String,
-    pub description: String,
+    #[serde(default)]
+    pub max_depth: usize,
+    #[serde(default)]
+    pub max_pages: usize,
+    #[serde(default = "default_true")]
+    pub same_domain_only: bool,
+    #[serde(default)]
+    pub politeness_delay_ms: u64,
 }
-#[derive(Debug, Clone, Deserialize)]
-pub struct BraveWebResult {
-    pub results: Vec<BraveWebResultItem>,
+
+/// `Disallow` paths under the blanket `User-agent: *` robots.txt group for one host, fetched
+/// once per host and reused for every URL on it.
+struct RobotsRules {
+    disallowed: Vec<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
-pub struct BraveResult {
-    pub web: BraveWebResult,
+impl RobotsRules {
+    fn allows(&self, path: &str) -> bool {
+        !self
+            .disallowed
+            .iter()
+            .any(|prefix| path.starts_with(prefix.as_str()))
+    }
 }
 
-pub struct WebSearchTool {
-    config: WebSearchProvider,
-    client: Option<reqwest::Client>,
+/// Extracts `Disallow` paths under the blanket `User-agent: *` group. Ignores every other
+/// directive (`Allow`, `Crawl-delay`, named user-agent groups) — enough to keep the crawler off
+/// obviously-forbidden paths without a full robots.txt grammar.
+fn parse_robots_disallow(body: &str) -> Vec<String> {
+    let mut disallowed = Vec::new();
+    let mut in_wildcard_group = false;
+    for line in body.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let Some((directive, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match directive.trim().to_ascii_lowercase().as_str() {
+            "user-agent" => in_wildcard_group = value == "*",
+            "disallow" if in_wildcard_group && !value.is_empty() => {
+                disallowed.push(value.to_string());
+            }
+            _ => {}
+        }
+    }
+    disallowed
+}
+
+/// Outbound `<a href>` links in `html`, resolved against `base`. Extracted before the markdown
+/// conversion, which drops href attributes entirely.
+fn extract_links(html: &str, base: &Url) -> Vec<Url> {
+    static HREF_RE: std::sync::LazyLock<Regex> =
+        std::sync::LazyLock::new(|| Regex::new(r#"(?i)<a\s+[^>]*href\s*=\s*["']([^"']+)["']"#).unwrap());
+    HREF_RE
+        .captures_iter(html)
+        .filter_map(|cap| base.join(&cap[1]).ok())
+        .collect()
+}
+
+/// The page's `<title>`, if any, used as the per-page heading in the aggregated crawl result.
+fn extract_title(html: &str) -> Option<String> {
+    static TITLE_RE: std::sync::LazyLock<Regex> =
+        std::sync::LazyLock::new(|| Regex::new(r#"(?is)<title[^>]*>(.*?)</title>"#).unwrap());
+    TITLE_RE.captures(html).map(|cap| cap[1].trim().to_string())
+}
+
+/// Normalizes `url` for the visited set: strips the fragment, since `#section` anchors are the
+/// same page for crawling purposes.
+fn normalized(url: &Url) -> String {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    url.to_string()
+}
+
+struct CrawledPage {
+    url: Url,
+    title: String,
+    markdown: String,
+}
+
+pub struct WebCrawlTool {
     description: serde_json::Value,
+    client: Option<reqwest::Client>,
+    /// Last fetch time per host, so `politeness_wait` can space out requests even though the
+    /// frontier interleaves hosts when `same_domain_only` is off.
+    last_fetch_by_host: HashMap<String, Instant>,
+}
+
+impl WebCrawlTool {
+    async fn politeness_wait(&mut self, host: &str, delay: Duration) {
+        if let Some(last) = self.last_fetch_by_host.get(host) {
+            let elapsed = last.elapsed();
+            if elapsed < delay {
+                tokio::time::sleep(delay - elapsed).await;
+            }
+        }
+        self.last_fetch_by_host
+            .insert(host.to_string(), Instant::now());
+    }
+
+    async fn fetch_robots(client: &reqwest::Client, page_url: &Url) -> RobotsRules {
+        let mut robots_url = page_url.clone();
+        robots_url.set_path("/robots.txt");
+        robots_url.set_query(None);
+        let disallowed = match client
+            .get(robots_url)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+        {
+            Ok(response) if response.status().is_success() => response
+                .text()
+                .await
+                .map(|body| parse_robots_disallow(&body))
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        RobotsRules { disallowed }
+    }
 }
 
 #[async_trait]
-impl ToolImpl for WebSearchTool {
+impl ToolImpl for WebCrawlTool {
     fn desciption(&self) -> &serde_json::Value {
         &self.description
     }
 
     async fn call(&mut self, arguments: serde_json::Value) -> Result<Vec<ToolResultContent>> {
-        let args = serde_json::from_value::<WebSearchToolArgs>(arguments)?;
-        let client = self.client.get_or_insert_with(reqwest::Client::new);
-        match &self.config {
-            WebSearchProvider::Brave(search_config) => {
-                let url = format!(
-                    "https://api.search.brave.com/res/v1/web/search?q={}&count={}&offset={}",
-                    utf8_percent_encode(&args.query, NON_ALPHANUMERIC),
-                    if args.count == 0 { 10 } else { args.count },
-                    args.offset
-                );
-                tracing::debug!("Perform Brave web search '{}'", url);
-                let response = client
-                    .get(url)
-                    .header("Accept", "application/json")
-                    .header("X-Subscription-Token", &search_config.api_key)
-                    .send()
-                    .await?;
-                if response.status() != 200 {
-                    anyhow::bail!(
-                        "Unexpected status code: {}: {}",
-                        response.status(),
-                        response.text().await.unwrap()
-                    );
+        let args = serde_json::from_value::<WebCrawlToolArgs>(arguments)?;
+        let seed = Url::parse(&args.url)?;
+        let max_depth = if args.max_depth == 0 {
+            DEFAULT_CRAWL_MAX_DEPTH
+        } else {
+            args.max_depth
+        };
+        let max_pages = if args.max_pages == 0 {
+            DEFAULT_CRAWL_MAX_PAGES
+        } else {
+            args.max_pages
+        };
+        let politeness_delay = Duration::from_millis(if args.politeness_delay_ms == 0 {
+            DEFAULT_CRAWL_POLITENESS_DELAY_MS
+        } else {
+            args.politeness_delay_ms
+        });
+        let seed_host = seed.host_str().map(|h| h.to_string());
+        let client = self.client.get_or_insert_with(reqwest::Client::new).clone();
+
+        let mut frontier: VecDeque<(Url, usize)> = VecDeque::from([(seed.clone(), 0)]);
+        let mut visited: HashSet<String> = HashSet::from([normalized(&seed)]);
+        let mut robots_by_host: HashMap<String, RobotsRules> = HashMap::new();
+        let mut pages: Vec<CrawledPage> = Vec::new();
+
+        while let Some((url, depth)) = frontier.pop_front() {
+            if pages.len() >= max_pages {
+                break;
+            }
+            let Some(host) = url.host_str().map(|h| h.to_string()) else {
+                continue;
+            };
+
+            if !robots_by_host.contains_key(&host) {
+                let rules = Self::fetch_robots(&client, &url).await;
+                robots_by_host.insert(host.clone(), rules);
+            }
+            if !robots_by_host[&host].allows(url.path()) {
+                continue;
+            }
+
+            self.politeness_wait(&host, politeness_delay).await;
+
+            let response = match client
+                .get(url.clone())
+                .timeout(Duration::from_secs(30))
+                .send()
+                .await
+            {
+                Ok(response) => response,
+                Err(e) => {
+                    tracing::debug!(%url, error = %e, "web_crawl: fetch failed, skipping");
+                    continue;
+                }
+            };
+            let content_type = response
+                .headers()
+                .get("content-type")
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("text/html")
+                .to_string();
+            if !content_type.starts_with("text/html") {
+                continue;
+            }
+            let Ok(html) = response.text().await else {
+                continue;
+            };
+
+            if depth < max_depth {
+                for link in extract_links(&html, &url) {
+                    if !matches!(link.scheme(), "http" | "https") {
+                        continue;
+                    }
+                    if args.same_domain_only && link.host_str().map(|h| h.to_string()) != seed_host
+                    {
+                        continue;
+                    }
+                    if visited.insert(normalized(&link)) {
+                        frontier.push_back((link, depth + 1));
+                    }
                 }
-                let body = response.text().await?;
-                let json: BraveResult = serde_json::from_str(&body)?;
-                let converter = htmd::HtmlToMarkdownBuilder::new().build();
-                let result = json
-                    .web
-                    .results
-                    .into_iter()
-                    .map(|item| {
-                        format!(
-                            "Title: {}\nDescription: {}\nURL: {}",
-                            item.title,
-                            converter
-                                .convert(&item.description)
-                                .unwrap_or(item.description),
-                            item.url
-                        )
-                    })
-                    .join("\n\n");
-                Ok(vec![ToolResultContent::text(result)])
             }
+
+            let title = extract_title(&html).unwrap_or_else(|| url.to_string());
+            let converter = htmd::HtmlToMarkdownBuilder::new()
+                .skip_tags(vec![
+                    "head", "script", "style", "nav", "footer", "header", "link",
+                ])
+                .build();
+            let markdown = converter.convert(&html).unwrap_or_default();
+            pages.push(CrawledPage {
+                url,
+                title,
+                markdown,
+            });
         }
+
+        let per_page_budget = MAX_LENGTH / pages.len().max(1);
+        let result = pages
+            .into_iter()
+            .map(|page| {
+                format!(
+                    "# {}\nURL: {}\n\n{}",
+                    page.title,
+                    page.url,
+                    safe_truncated(&page.markdown, per_page_budget)
+                )
+            })
+            .join("\n\n---\n\n");
+
+        Ok(vec![ToolResultContent::text(result)])
     }
 }
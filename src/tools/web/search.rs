@@ -0,0 +1,171 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! Pluggable web search backends for `WebSearchTool`, fused with Reciprocal Rank Fusion so
+//! `config.web_search` can hold more than one provider: each is queried concurrently, and a
+//! slow/erroring/rate-limited provider degrades gracefully instead of failing the whole search.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use itertools::Itertools;
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use serde::Deserialize;
+
+use crate::config::{WebSearchBraveConfig, WebSearchProviderConfig};
+
+/// One ranked hit from a `SearchProvider`, before RRF fusion merges several providers' lists into
+/// one.
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub title: String,
+    pub url: String,
+    pub description: String,
+}
+
+#[async_trait]
+pub trait SearchProvider: Send + Sync {
+    async fn search(&self, query: &str, count: u16, offset: u16) -> Result<Vec<SearchHit>>;
+}
+
+/// Builds one `SearchProvider` per configured backend, in `config.web_search` order.
+pub fn create_search_providers(configs: &[WebSearchProviderConfig]) -> Vec<Box<dyn SearchProvider>> {
+    configs
+        .iter()
+        .map(|config| -> Box<dyn SearchProvider> {
+            match config {
+                WebSearchProviderConfig::Brave(brave_config) => Box::new(BraveSearchProvider {
+                    client: reqwest::Client::new(),
+                    config: brave_config.clone(),
+                }),
+            }
+        })
+        .collect()
+}
+
+struct BraveSearchProvider {
+    client: reqwest::Client,
+    config: WebSearchBraveConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct BraveWebResultItem {
+    title: String,
+    url: String,
+    description: String,
+}
+#[derive(Debug, Clone, Deserialize)]
+struct BraveWebResult {
+    results: Vec<BraveWebResultItem>,
+}
+#[derive(Debug, Clone, Deserialize)]
+struct BraveResult {
+    web: BraveWebResult,
+}
+
+#[async_trait]
+impl SearchProvider for BraveSearchProvider {
+    async fn search(&self, query: &str, count: u16, offset: u16) -> Result<Vec<SearchHit>> {
+        let url = format!(
+            "https://api.search.brave.com/res/v1/web/search?q={}&count={}&offset={}",
+            utf8_percent_encode(query, NON_ALPHANUMERIC),
+            count,
+            offset
+        );
+        tracing::debug!("Perform Brave web search '{}'", url);
+        let response = self
+            .client
+            .get(url)
+            .header("Accept", "application/json")
+            .header("X-Subscription-Token", &self.config.api_key)
+            .send()
+            .await?;
+        if response.status() != 200 {
+            anyhow::bail!(
+                "Unexpected status code: {}: {}",
+                response.status(),
+                response.text().await.unwrap()
+            );
+        }
+        let body = response.text().await?;
+        let json: BraveResult = serde_json::from_str(&body)?;
+        Ok(json
+            .web
+            .results
+            .into_iter()
+            .map(|item| SearchHit {
+                title: item.title,
+                url: item.url,
+                description: item.description,
+            })
+            .collect())
+    }
+}
+
+/// Reciprocal Rank Fusion constant: keeps a document that ranks moderately well across several
+/// providers competitive with a document that ranks #1 in just one, instead of letting a single
+/// provider's top pick always dominate.
+const RRF_K: f64 = 60.0;
+
+const TRACKING_QUERY_PARAMS: &[&str] = &[
+    "utm_source",
+    "utm_medium",
+    "utm_campaign",
+    "utm_term",
+    "utm_content",
+    "gclid",
+    "fbclid",
+    "ref",
+];
+
+/// Normalizes a hit URL for fusion dedup: lowercase host, no trailing slash, common tracking query
+/// params stripped, so the same page returned by two providers (possibly with different UTM tags)
+/// fuses into one document instead of counting twice.
+fn normalize_url_for_fusion(url: &str) -> String {
+    let Ok(parsed) = reqwest::Url::parse(url) else {
+        return url.trim_end_matches('/').to_string();
+    };
+    let host = parsed.host_str().unwrap_or("").to_ascii_lowercase();
+    let port = parsed.port().map(|p| format!(":{p}")).unwrap_or_default();
+    let path = parsed.path().trim_end_matches('/');
+    let query = parsed
+        .query_pairs()
+        .filter(|(key, _)| !TRACKING_QUERY_PARAMS.contains(&key.as_ref()))
+        .map(|(key, value)| format!("{key}={value}"))
+        .join("&");
+
+    let mut key = format!("{}://{host}{port}{path}", parsed.scheme());
+    if !query.is_empty() {
+        key.push('?');
+        key.push_str(&query);
+    }
+    key
+}
+
+/// Fuses several providers' ranked hit lists into one: each document's score is the sum, across
+/// the provider lists it appears in, of `1 / (RRF_K + rank)` (zero-based rank) — absent from a
+/// list contributes nothing. Keeps the first title/description seen for each document and returns
+/// the top `count` by descending fused score.
+pub fn fuse_with_rrf(provider_hits: Vec<Vec<SearchHit>>, count: u16) -> Vec<SearchHit> {
+    let mut scores: HashMap<String, f64> = HashMap::new();
+    let mut first_seen: HashMap<String, SearchHit> = HashMap::new();
+    let mut order: Vec<String> = Vec::new();
+
+    for hits in provider_hits {
+        for (rank, hit) in hits.into_iter().enumerate() {
+            let key = normalize_url_for_fusion(&hit.url);
+            *scores.entry(key.clone()).or_insert(0.0) += 1.0 / (RRF_K + rank as f64);
+            if !first_seen.contains_key(&key) {
+                order.push(key.clone());
+                first_seen.insert(key, hit);
+            }
+        }
+    }
+
+    order.sort_by(|a, b| scores[b].partial_cmp(&scores[a]).unwrap());
+    order
+        .into_iter()
+        .take(count.max(1) as usize)
+        .filter_map(|key| first_seen.remove(&key))
+        .collect()
+}
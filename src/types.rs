@@ -1,5 +1,8 @@
 // Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
 
+use std::str::FromStr;
+
+use serde::de::{Deserializer, IntoDeserializer};
 use serde::{Deserialize, Serialize};
 
 pub mod streaming;
@@ -58,14 +61,16 @@ pub enum UserContent {
     Image(Image),
     Audio(Audio),
     Document(Document),
+    Video(Video),
 }
 
-/// Describes responses from a provider which is either text or a tool call.
+/// Describes responses from a provider which is either text, a tool call, or reasoning.
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(untagged)]
 pub enum AssistantContent {
     Text(Text),
     ToolCall(ToolCall),
+    Reasoning(Reasoning),
 }
 
 impl AssistantContent {
@@ -79,6 +84,19 @@ impl AssistantContent {
             function: ToolFunction { name, arguments },
         })
     }
+
+    pub fn reasoning(reasoning: String) -> Self {
+        AssistantContent::Reasoning(Reasoning { reasoning })
+    }
+}
+
+/// A provider's reasoning/thinking trace for a response, kept distinct from `Text` (a different
+/// field name so `AssistantContent`'s `#[serde(untagged)]` deserialization isn't ambiguous between
+/// the two) so callers can surface it differently, e.g. a live "thinking" indicator instead of the
+/// final message.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Reasoning {
+    pub reasoning: String,
 }
 
 /// Tool result content containing information about a tool call and it's resulting content.
@@ -88,11 +106,15 @@ pub struct ToolResult {
     pub content: Vec<ToolResultContent>,
 }
 
-/// Describes the content of a tool result, which can be text or an image.
+/// Describes the content of a tool result, which can be text, an image, a video, audio, or a
+/// reference to an external/embedded resource.
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub enum ToolResultContent {
     Text(Text),
     Image(Image),
+    Video(Video),
+    Audio(Audio),
+    Resource(ResourceReference),
 }
 
 impl ToolResultContent {
@@ -115,6 +137,42 @@ impl ToolResultContent {
             detail: None,
         })
     }
+    pub fn video(data: String, media_type: Option<VideoMediaType>) -> Self {
+        ToolResultContent::Video(Video {
+            data,
+            format: None,
+            media_type,
+        })
+    }
+    pub fn audio(data: String, media_type: Option<AudioMediaType>) -> Self {
+        ToolResultContent::Audio(Audio {
+            data,
+            format: None,
+            media_type,
+        })
+    }
+    /// A resource a tool produced, inlined when its contents are small text/blob data, or as a
+    /// bare URI plus a short descriptor when only a reference is available (e.g. a linked
+    /// resource too large to inline).
+    pub fn resource(uri: String, mime_type: Option<String>, description: String) -> Self {
+        ToolResultContent::Resource(ResourceReference {
+            uri,
+            mime_type,
+            description,
+        })
+    }
+}
+
+/// A reference to a resource returned by a tool (see `mcp_core::types::ToolResponseContent::Resource`).
+/// Resources aren't first-class content for any provider we talk to, so they're always flattened to
+/// `description` text before being sent upstream (see `providers::openrouter::tool_content_to_json`);
+/// `uri`/`mime_type` are kept for callers that want to resolve the resource themselves.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ResourceReference {
+    pub uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    pub description: String,
 }
 /// Describes a tool call with an id and function to call, generally produced by a provider.
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
@@ -172,6 +230,16 @@ pub struct Document {
     pub media_type: Option<DocumentMediaType>,
 }
 
+/// Video content containing video data and metadata about it.
+#[derive(Default, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Video {
+    pub data: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<ContentFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<VideoMediaType>,
+}
+
 /// Describes the format of the content, which can be base64 or string.
 #[derive(Default, Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -187,11 +255,12 @@ pub enum MediaType {
     Image(ImageMediaType),
     Audio(AudioMediaType),
     Document(DocumentMediaType),
+    Video(VideoMediaType),
 }
 
 /// Describes the image media type of the content. Not every provider supports every media type.
 /// Convertible to and from MIME type strings.
-#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 #[allow(clippy::upper_case_acronyms)]
 pub enum ImageMediaType {
@@ -202,10 +271,47 @@ pub enum ImageMediaType {
     HEIC,
     HEIF,
     SVG,
+    /// Any MIME/format string without a named variant above (e.g. `image/avif`), preserved
+    /// verbatim instead of failing deserialization — see the hand-rolled `Deserialize` impl below.
+    #[serde(skip_deserializing)]
+    Other(String),
+}
+
+/// Mirrors `ImageMediaType`'s named variants so we can derive the usual string-matching
+/// `Deserialize` without it rejecting the `Other` arm, then fall back to `Other` by hand below.
+#[derive(Deserialize)]
+#[serde(remote = "ImageMediaType", rename_all = "lowercase")]
+#[allow(clippy::upper_case_acronyms)]
+enum ImageMediaTypeRemote {
+    JPEG,
+    PNG,
+    GIF,
+    WEBP,
+    HEIC,
+    HEIF,
+    SVG,
+}
+
+impl FromStr for ImageMediaType {
+    type Err = serde::de::value::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ImageMediaTypeRemote::deserialize(s.into_deserializer())
+    }
+}
+
+impl<'de> Deserialize<'de> for ImageMediaType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Ok(Self::from_str(&s).unwrap_or_else(|_| Self::Other(s)))
+    }
 }
 
 impl ImageMediaType {
-    pub fn to_mime_type(&self) -> &'static str {
+    pub fn to_mime_type(&self) -> &str {
         match self {
             ImageMediaType::JPEG => "image/jpeg",
             ImageMediaType::PNG => "image/png",
@@ -214,6 +320,7 @@ impl ImageMediaType {
             ImageMediaType::HEIC => "image/heic",
             ImageMediaType::HEIF => "image/heif",
             ImageMediaType::SVG => "image/svg+xml",
+            ImageMediaType::Other(mime_type) => mime_type,
         }
     }
 
@@ -226,6 +333,9 @@ impl ImageMediaType {
             ImageMediaType::HEIC => "heic",
             ImageMediaType::HEIF => "heif",
             ImageMediaType::SVG => "svg",
+            ImageMediaType::Other(mime_type) => {
+                mime_type.rsplit('/').next().unwrap_or(mime_type)
+            }
         }
     }
 
@@ -246,7 +356,7 @@ impl ImageMediaType {
 /// Describes the document media type of the content. Not every provider supports every media type.
 /// Includes also programming languages as document types for providers who support code running.
 /// Convertible to and from MIME type strings.
-#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 #[allow(clippy::upper_case_acronyms)]
 pub enum DocumentMediaType {
@@ -260,11 +370,50 @@ pub enum DocumentMediaType {
     XML,
     Javascript,
     Python,
+    /// Any document kind without a named variant above, preserved verbatim instead of failing
+    /// deserialization — see the hand-rolled `Deserialize` impl below.
+    #[serde(skip_deserializing)]
+    Other(String),
+}
+
+/// Mirrors `DocumentMediaType`'s named variants, see `ImageMediaTypeRemote`.
+#[derive(Deserialize)]
+#[serde(remote = "DocumentMediaType", rename_all = "lowercase")]
+#[allow(clippy::upper_case_acronyms)]
+enum DocumentMediaTypeRemote {
+    PDF,
+    TXT,
+    RTF,
+    HTML,
+    CSS,
+    MARKDOWN,
+    CSV,
+    XML,
+    Javascript,
+    Python,
+}
+
+impl FromStr for DocumentMediaType {
+    type Err = serde::de::value::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DocumentMediaTypeRemote::deserialize(s.into_deserializer())
+    }
+}
+
+impl<'de> Deserialize<'de> for DocumentMediaType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Ok(Self::from_str(&s).unwrap_or_else(|_| Self::Other(s)))
+    }
 }
 
 /// Describes the audio media type of the content. Not every provider supports every media type.
 /// Convertible to and from MIME type strings.
-#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 #[allow(clippy::upper_case_acronyms)]
 pub enum AudioMediaType {
@@ -274,6 +423,165 @@ pub enum AudioMediaType {
     AAC,
     OGG,
     FLAC,
+    /// Any audio format without a named variant above, preserved verbatim instead of failing
+    /// deserialization — see the hand-rolled `Deserialize` impl below.
+    #[serde(skip_deserializing)]
+    Other(String),
+}
+
+/// Mirrors `AudioMediaType`'s named variants, see `ImageMediaTypeRemote`.
+#[derive(Deserialize)]
+#[serde(remote = "AudioMediaType", rename_all = "lowercase")]
+#[allow(clippy::upper_case_acronyms)]
+enum AudioMediaTypeRemote {
+    WAV,
+    MP3,
+    AIFF,
+    AAC,
+    OGG,
+    FLAC,
+}
+
+impl FromStr for AudioMediaType {
+    type Err = serde::de::value::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AudioMediaTypeRemote::deserialize(s.into_deserializer())
+    }
+}
+
+impl<'de> Deserialize<'de> for AudioMediaType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Ok(Self::from_str(&s).unwrap_or_else(|_| Self::Other(s)))
+    }
+}
+
+impl AudioMediaType {
+    pub fn to_mime_type(&self) -> &str {
+        match self {
+            AudioMediaType::WAV => "audio/wav",
+            AudioMediaType::MP3 => "audio/mpeg",
+            AudioMediaType::AIFF => "audio/aiff",
+            AudioMediaType::AAC => "audio/aac",
+            AudioMediaType::OGG => "audio/ogg",
+            AudioMediaType::FLAC => "audio/flac",
+            AudioMediaType::Other(mime_type) => mime_type,
+        }
+    }
+
+    pub fn to_file_ext(&self) -> &str {
+        match self {
+            AudioMediaType::WAV => "wav",
+            AudioMediaType::MP3 => "mp3",
+            AudioMediaType::AIFF => "aiff",
+            AudioMediaType::AAC => "aac",
+            AudioMediaType::OGG => "ogg",
+            AudioMediaType::FLAC => "flac",
+            AudioMediaType::Other(mime_type) => {
+                mime_type.rsplit('/').next().unwrap_or(mime_type)
+            }
+        }
+    }
+
+    pub fn from_mime_type(mime_type: &str) -> Option<Self> {
+        match mime_type {
+            "audio/wav" | "audio/x-wav" => Some(AudioMediaType::WAV),
+            "audio/mpeg" | "audio/mp3" => Some(AudioMediaType::MP3),
+            "audio/aiff" => Some(AudioMediaType::AIFF),
+            "audio/aac" => Some(AudioMediaType::AAC),
+            "audio/ogg" => Some(AudioMediaType::OGG),
+            "audio/flac" => Some(AudioMediaType::FLAC),
+            _ => None,
+        }
+    }
+}
+
+/// Describes the video media type of the content. Not every provider supports every media type.
+/// Convertible to and from MIME type strings.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+#[allow(clippy::upper_case_acronyms)]
+pub enum VideoMediaType {
+    MP4,
+    WEBM,
+    MOV,
+    MKV,
+    AVI,
+    /// Any video format without a named variant above, preserved verbatim instead of failing
+    /// deserialization — see the hand-rolled `Deserialize` impl below.
+    #[serde(skip_deserializing)]
+    Other(String),
+}
+
+/// Mirrors `VideoMediaType`'s named variants, see `ImageMediaTypeRemote`.
+#[derive(Deserialize)]
+#[serde(remote = "VideoMediaType", rename_all = "lowercase")]
+#[allow(clippy::upper_case_acronyms)]
+enum VideoMediaTypeRemote {
+    MP4,
+    WEBM,
+    MOV,
+    MKV,
+    AVI,
+}
+
+impl FromStr for VideoMediaType {
+    type Err = serde::de::value::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        VideoMediaTypeRemote::deserialize(s.into_deserializer())
+    }
+}
+
+impl<'de> Deserialize<'de> for VideoMediaType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Ok(Self::from_str(&s).unwrap_or_else(|_| Self::Other(s)))
+    }
+}
+
+impl VideoMediaType {
+    pub fn to_mime_type(&self) -> &str {
+        match self {
+            VideoMediaType::MP4 => "video/mp4",
+            VideoMediaType::WEBM => "video/webm",
+            VideoMediaType::MOV => "video/quicktime",
+            VideoMediaType::MKV => "video/x-matroska",
+            VideoMediaType::AVI => "video/x-msvideo",
+            VideoMediaType::Other(mime_type) => mime_type,
+        }
+    }
+
+    pub fn to_file_ext(&self) -> &str {
+        match self {
+            VideoMediaType::MP4 => "mp4",
+            VideoMediaType::WEBM => "webm",
+            VideoMediaType::MOV => "mov",
+            VideoMediaType::MKV => "mkv",
+            VideoMediaType::AVI => "avi",
+            VideoMediaType::Other(mime_type) => {
+                mime_type.rsplit('/').next().unwrap_or(mime_type)
+            }
+        }
+    }
+
+    pub fn from_mime_type(mime_type: &str) -> Option<Self> {
+        match mime_type {
+            "video/mp4" => Some(VideoMediaType::MP4),
+            "video/webm" => Some(VideoMediaType::WEBM),
+            "video/quicktime" => Some(VideoMediaType::MOV),
+            "video/x-matroska" => Some(VideoMediaType::MKV),
+            "video/x-msvideo" => Some(VideoMediaType::AVI),
+            _ => None,
+        }
+    }
 }
 
 /// Describes the detail of the image content, which can be low, high, or auto (open-ai specific).
@@ -7,7 +7,10 @@ use anyhow::Result;
 use futures::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 
-use crate::types::{AssistantContent, ToolCall, ToolFunction};
+use crate::{
+    error::AgentError,
+    types::{AssistantContent, ToolCall, ToolFunction},
+};
 
 #[derive(Debug, Clone)]
 pub enum RawStreamingChoice {
@@ -21,6 +24,20 @@ pub enum RawStreamingChoice {
         arguments: serde_json::Value,
     },
 
+    /// A reasoning/thinking chunk, kept separate from `Message` so it doesn't pollute the final
+    /// assistant text.
+    Reasoning(String),
+
+    /// A raw fragment of a tool call's arguments as it arrives, before the call is known
+    /// complete. Purely informational: the consolidated `ToolCall` (fully-parsed arguments) is
+    /// still emitted once the call finishes, so existing consumers don't need to change.
+    ToolCallDelta {
+        index: usize,
+        id: Option<String>,
+        name: Option<String>,
+        arguments_chunk: String,
+    },
+
     FinalResponse(ResponseUsage),
 }
 
@@ -29,9 +46,18 @@ pub struct ResponseUsage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
+    /// Portion of `prompt_tokens` served from the provider's prompt cache, if it reports one
+    /// (e.g. OpenRouter's `prompt_tokens_details.cached_tokens`). `0` when unknown.
+    #[serde(default)]
+    pub cached_tokens: u32,
+    /// Dollar cost the provider billed for this request (e.g. OpenRouter's `usage.cost`, present
+    /// when the request set `"usage": {"include": true}`). `0.0` when the provider doesn't report
+    /// it.
+    #[serde(default)]
+    pub cost: f64,
 }
 
-pub type StreamingResult = Pin<Box<dyn Stream<Item = Result<RawStreamingChoice>> + Send>>;
+pub type StreamingResult = Pin<Box<dyn Stream<Item = Result<RawStreamingChoice, AgentError>> + Send>>;
 
 /// The response from a streaming completion request;
 /// message and response are populated at the end of the
@@ -39,6 +65,7 @@ pub type StreamingResult = Pin<Box<dyn Stream<Item = Result<RawStreamingChoice>>
 pub struct StreamingCompletionResponse {
     inner: StreamingResult,
     text: String,
+    reasoning: String,
     tool_calls: Vec<ToolCall>,
     /// The final aggregated message from the stream
     /// contains all text and tool calls generated
@@ -46,6 +73,20 @@ pub struct StreamingCompletionResponse {
     /// The final response from the stream, may be `None`
     /// if the provider didn't yield it during the stream
     pub response: Option<ResponseUsage>,
+    /// The most recent tool-call argument fragment seen so far, for streaming-aware consumers
+    /// that want to render a tool call forming in real time. Updated in place as the stream is
+    /// polled; unrelated to `choice`/`response`, which are only populated once the stream ends.
+    pub last_tool_call_delta: Option<ToolCallDelta>,
+}
+
+/// A raw tool-call argument fragment, mirroring `RawStreamingChoice::ToolCallDelta`. See
+/// `StreamingCompletionResponse::last_tool_call_delta`.
+#[derive(Debug, Clone)]
+pub struct ToolCallDelta {
+    pub index: usize,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub arguments_chunk: String,
 }
 
 impl StreamingCompletionResponse {
@@ -53,15 +94,17 @@ impl StreamingCompletionResponse {
         Self {
             inner,
             text: "".to_string(),
+            reasoning: "".to_string(),
             tool_calls: vec![],
             choice: vec![],
             response: None,
+            last_tool_call_delta: None,
         }
     }
 }
 
 impl Stream for StreamingCompletionResponse {
-    type Item = Result<AssistantContent>;
+    type Item = Result<AssistantContent, AgentError>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let stream = self.get_mut();
@@ -82,6 +125,10 @@ impl Stream for StreamingCompletionResponse {
                     choice.insert(0, AssistantContent::text(stream.text.clone()));
                 }
 
+                if !stream.reasoning.is_empty() {
+                    choice.insert(0, AssistantContent::reasoning(stream.reasoning.clone()));
+                }
+
                 stream.choice = choice;
 
                 Poll::Ready(None)
@@ -94,6 +141,12 @@ impl Stream for StreamingCompletionResponse {
                     stream.text = format!("{}{}", stream.text, text.clone());
                     Poll::Ready(Some(Ok(AssistantContent::text(text))))
                 }
+                RawStreamingChoice::Reasoning(text) => {
+                    // Forward reasoning tokens to the outer stream separately from `text` so
+                    // they can be surfaced differently (e.g. a "thinking" indicator).
+                    stream.reasoning = format!("{}{}", stream.reasoning, text.clone());
+                    Poll::Ready(Some(Ok(AssistantContent::reasoning(text))))
+                }
                 RawStreamingChoice::ToolCall {
                     id,
                     name,
@@ -110,6 +163,23 @@ impl Stream for StreamingCompletionResponse {
                     });
                     Poll::Ready(Some(Ok(AssistantContent::tool_call(id, name, arguments))))
                 }
+                RawStreamingChoice::ToolCallDelta {
+                    index,
+                    id,
+                    name,
+                    arguments_chunk,
+                } => {
+                    // Not part of the final `AssistantContent` history; just record it for
+                    // `last_tool_call_delta` and move on to the next real item.
+                    stream.last_tool_call_delta = Some(ToolCallDelta {
+                        index,
+                        id,
+                        name,
+                        arguments_chunk,
+                    });
+
+                    stream.poll_next_unpin(cx)
+                }
                 RawStreamingChoice::FinalResponse(response) => {
                     // Set the final response field and return the next item in the stream
                     stream.response = Some(response);
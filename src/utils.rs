@@ -14,6 +14,21 @@ use serde_json::json;
 
 use crate::huly::types::CommunicationDirect;
 
+/// Renders a `chrono::Duration` as the coarsest human-friendly unit ("3m", "2h", "1d"), for
+/// staleness/"as of N ago" labels shown to the model rather than a raw number of seconds.
+pub fn format_duration_short(duration: chrono::Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    if seconds < 60 {
+        format!("{seconds}s")
+    } else if seconds < 3600 {
+        format!("{}m", seconds / 60)
+    } else if seconds < 86400 {
+        format!("{}h", seconds / 3600)
+    } else {
+        format!("{}d", seconds / 86400)
+    }
+}
+
 pub fn safe_truncated(s: &str, len: usize) -> String {
     let mut new_len = usize::min(len, s.len());
     let mut s = s.to_string();
@@ -0,0 +1,250 @@
+// Copyright © 2025 Huly Labs. Use of this source code is governed by the MIT license.
+
+//! Background-worker registry with live state introspection and pause/cancel control. Replaces
+//! ad hoc `tokio::spawn` loops (e.g. the old `incoming_tasks_processor`) with a single place
+//! that knows what the agent's concurrent background processes are doing — surfaced to the
+//! model itself via the `${WORKERS}` context placeholder, and to operators via `WorkerManager`'s
+//! control channel.
+
+use std::{collections::HashMap, panic::AssertUnwindSafe, sync::Arc};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::FutureExt;
+use tokio::sync::{Mutex, RwLock, mpsc};
+
+use crate::database::DbClient;
+
+/// What a single `Worker::step()` call accomplished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkerState {
+    /// Did useful work; the manager calls `step()` again right away.
+    Busy,
+    /// Found nothing to do; the manager backs off briefly before the next `step()`.
+    Idle,
+    /// Will never do useful work again; the manager retires it.
+    Done,
+}
+
+/// One background process `WorkerManager` drives. Implementors own their own state (channel
+/// receivers, cursors, ...) and advance it one unit of work per `step()` call.
+#[async_trait]
+pub trait Worker: Send {
+    fn name(&self) -> &str;
+    async fn step(&mut self) -> Result<WorkerState>;
+    /// Called when `step()` returns `Err`, before the manager records `last_error` and retries.
+    /// Default just logs; override for e.g. a circuit breaker.
+    fn on_error(&mut self, err: &anyhow::Error) {
+        tracing::error!(worker = self.name(), ?err, "worker step failed");
+    }
+}
+
+/// Runtime state of a registered worker, as reported by `WorkerManager::list`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkerRunState {
+    Active,
+    Idle,
+    Paused,
+    Dead,
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkerStatus {
+    pub id: String,
+    pub name: String,
+    pub state: WorkerRunState,
+    pub last_error: Option<String>,
+    pub items_processed: u64,
+}
+
+/// Control-channel commands accepted by a running worker's driver loop.
+#[derive(Debug, Clone, Copy)]
+pub enum WorkerCommand {
+    Start,
+    Pause,
+    Resume,
+    Cancel,
+}
+
+struct WorkerEntry {
+    status: Arc<RwLock<WorkerStatus>>,
+    commands: mpsc::UnboundedSender<WorkerCommand>,
+}
+
+/// Central registry of background workers (memory maintenance, task execution, scheduler, ...),
+/// each driven in its own task and controllable via `Start`/`Pause`/`Resume`/`Cancel`.
+pub struct WorkerManager {
+    workers: Mutex<HashMap<String, WorkerEntry>>,
+    db_client: DbClient,
+}
+
+impl WorkerManager {
+    /// `db_client` is used to persist `Pause`/`Resume` commands, so a pause set by an operator
+    /// survives a restart. Seed each `spawn` call's `paused` flag from `db_client.paused_worker_ids`.
+    pub fn new(db_client: DbClient) -> Self {
+        Self {
+            workers: Mutex::default(),
+            db_client,
+        }
+    }
+
+    /// Registers `worker` under `id` and starts driving it in its own task. `paused` seeds its
+    /// initial state — callers typically source this from `DbClient::paused_worker_ids` so a
+    /// worker paused before a restart stays paused after it.
+    pub async fn spawn(&self, id: impl Into<String>, mut worker: Box<dyn Worker>, paused: bool) {
+        let id = id.into();
+        let (tx, mut rx) = mpsc::unbounded_channel::<WorkerCommand>();
+        let status = Arc::new(RwLock::new(WorkerStatus {
+            id: id.clone(),
+            name: worker.name().to_string(),
+            state: if paused {
+                WorkerRunState::Paused
+            } else {
+                WorkerRunState::Idle
+            },
+            last_error: None,
+            items_processed: 0,
+        }));
+
+        self.workers.lock().await.insert(
+            id.clone(),
+            WorkerEntry {
+                status: status.clone(),
+                commands: tx,
+            },
+        );
+
+        tokio::spawn(async move {
+            let mut paused = paused;
+            loop {
+                if paused {
+                    match rx.recv().await {
+                        Some(WorkerCommand::Resume) | Some(WorkerCommand::Start) => {
+                            paused = false;
+                            status.write().await.state = WorkerRunState::Idle;
+                        }
+                        Some(WorkerCommand::Cancel) | None => {
+                            status.write().await.state = WorkerRunState::Dead;
+                            return;
+                        }
+                        Some(WorkerCommand::Pause) => {}
+                    }
+                    continue;
+                }
+
+                tokio::select! {
+                    command = rx.recv() => {
+                        match command {
+                            Some(WorkerCommand::Pause) => {
+                                paused = true;
+                                status.write().await.state = WorkerRunState::Paused;
+                            }
+                            Some(WorkerCommand::Cancel) | None => {
+                                status.write().await.state = WorkerRunState::Dead;
+                                return;
+                            }
+                            Some(WorkerCommand::Start) | Some(WorkerCommand::Resume) => {}
+                        }
+                    }
+                    // Caught rather than let it take down the whole driver task: a panicking
+                    // `step()` (e.g. the scheduler hitting a bad `db_client` call) is otherwise
+                    // indistinguishable from the process crashing, since nothing supervises a
+                    // bare `tokio::spawn` from outside. Treating it as an ordinary `Err` reuses
+                    // the existing retry/backoff path below instead of needing a second one.
+                    result = AssertUnwindSafe(worker.step()).catch_unwind() => {
+                        let result = result.unwrap_or_else(|panic| {
+                            let message = panic
+                                .downcast_ref::<&str>()
+                                .map(|s| s.to_string())
+                                .or_else(|| panic.downcast_ref::<String>().cloned())
+                                .unwrap_or_else(|| "worker step panicked".to_string());
+                            Err(anyhow::anyhow!(message))
+                        });
+                        match result {
+                            Ok(WorkerState::Busy) => {
+                                let mut status = status.write().await;
+                                status.state = WorkerRunState::Active;
+                                status.items_processed += 1;
+                            }
+                            Ok(WorkerState::Idle) => {
+                                status.write().await.state = WorkerRunState::Idle;
+                                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                            }
+                            Ok(WorkerState::Done) => {
+                                status.write().await.state = WorkerRunState::Dead;
+                                return;
+                            }
+                            Err(err) => {
+                                worker.on_error(&err);
+                                let mut status = status.write().await;
+                                status.state = WorkerRunState::Idle;
+                                status.last_error = Some(err.to_string());
+                            }
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    pub async fn send(&self, id: &str, command: WorkerCommand) -> Result<()> {
+        {
+            let workers = self.workers.lock().await;
+            let entry = workers
+                .get(id)
+                .ok_or_else(|| anyhow::anyhow!("Unknown worker: {}", id))?;
+            entry
+                .commands
+                .send(command)
+                .map_err(|_| anyhow::anyhow!("Worker {} is no longer running", id))?;
+        }
+        match command {
+            WorkerCommand::Pause => self.db_client.set_worker_paused(id, true).await?,
+            WorkerCommand::Resume | WorkerCommand::Start => {
+                self.db_client.set_worker_paused(id, false).await?
+            }
+            WorkerCommand::Cancel => {}
+        }
+        Ok(())
+    }
+
+    pub async fn list(&self) -> Vec<WorkerStatus> {
+        let workers = self.workers.lock().await;
+        let mut statuses = Vec::with_capacity(workers.len());
+        for entry in workers.values() {
+            statuses.push(entry.status.read().await.clone());
+        }
+        statuses
+    }
+
+    /// `true` once `id`'s driver loop has retired (`Worker::step` returned `Done`, or the
+    /// worker was cancelled) — the same signal a caller used to get from a raw `JoinHandle`.
+    pub async fn is_dead(&self, id: &str) -> bool {
+        let workers = self.workers.lock().await;
+        match workers.get(id) {
+            Some(entry) => entry.status.read().await.state == WorkerRunState::Dead,
+            None => true,
+        }
+    }
+
+    /// Renders `list()` as the markdown table backing the `${WORKERS}` context placeholder.
+    pub async fn render_markdown(&self) -> String {
+        let statuses = self.list().await;
+        if statuses.is_empty() {
+            return "No background workers registered".to_string();
+        }
+        let mut table = String::from("| id | name | state | items processed | last error |\n");
+        table.push_str("| --- | --- | --- | --- | --- |\n");
+        for status in statuses {
+            table.push_str(&format!(
+                "| {} | {} | {:?} | {} | {} |\n",
+                status.id,
+                status.name,
+                status.state,
+                status.items_processed,
+                status.last_error.as_deref().unwrap_or("-")
+            ));
+        }
+        table
+    }
+}